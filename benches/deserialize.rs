@@ -0,0 +1,192 @@
+#[macro_use]
+extern crate bencher;
+extern crate gob;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_schema;
+#[macro_use]
+extern crate serde_schema_derive;
+
+use bencher::Bencher;
+use gob::de::DecodeOptions;
+use gob::{Decoder, StreamDeserializer, StreamSerializer};
+
+const MESSAGES: usize = 1_000_000;
+
+// a stream of 20 byte messages, each a 16 byte string
+fn small_messages() -> Vec<u8> {
+    let message = [
+        19, 12, 0, 16, b'0', b'1', b'2', b'3', b'4', b'5', b'6', b'7', b'8', b'9', b'a', b'b',
+        b'c', b'd', b'e', b'f',
+    ];
+    message
+        .iter()
+        .cloned()
+        .cycle()
+        .take(20 * MESSAGES)
+        .collect()
+}
+
+fn stream_small_messages(bench: &mut Bencher) {
+    let input = small_messages();
+    bench.iter(|| {
+        let mut stream = StreamDeserializer::new(&input[..]);
+        let mut count = 0;
+        while stream.deserialize::<&str>().unwrap().is_some() {
+            count += 1;
+        }
+        assert_eq!(count, MESSAGES);
+    });
+    bench.bytes = input.len() as u64;
+}
+
+fn decoder_small_messages(bench: &mut Bencher) {
+    let input = small_messages();
+    bench.iter(|| {
+        let mut decoder = Decoder::new();
+        let mut count = 0;
+        // chunks that never line up with message boundaries
+        for chunk in input.chunks(7) {
+            decoder.feed(chunk);
+            while decoder.next_value::<&str>().unwrap().is_some() {
+                count += 1;
+            }
+        }
+        assert_eq!(count, MESSAGES);
+    });
+    bench.bytes = input.len() as u64;
+}
+
+macro_rules! wide_struct {
+    ($($field:ident)*) => {
+        #[derive(Serialize, Deserialize, SchemaSerialize)]
+        struct Wide {
+            $($field: u64,)*
+        }
+
+        impl Wide {
+            fn filled(n: u64) -> Wide {
+                Wide { $($field: n,)* }
+            }
+        }
+    };
+}
+
+// 64 fields, so that matching field names dominates decoding
+wide_struct!(
+    f00 f01 f02 f03 f04 f05 f06 f07 f08 f09 f10 f11 f12 f13 f14 f15 f16 f17 f18 f19 f20 f21 f22 f23 f24 f25 f26 f27 f28 f29 f30 f31 f32 f33 f34 f35 f36 f37 f38 f39 f40 f41 f42 f43 f44 f45 f46 f47 f48 f49 f50 f51 f52 f53 f54 f55 f56 f57 f58 f59 f60 f61 f62 f63
+);
+
+fn decode_wide_structs(bench: &mut Bencher, options: DecodeOptions) {
+    const VALUES: usize = 10_000;
+    let mut stream = StreamSerializer::new_with_bytes();
+    for i in 0..VALUES {
+        stream.serialize(&Wide::filled(i as u64 + 1)).unwrap();
+    }
+    let input = stream.into_inner().into_inner();
+    bench.iter(|| {
+        let mut stream = StreamDeserializer::new(&input[..]);
+        stream.set_options(options);
+        let mut count = 0;
+        while stream.deserialize::<Wide>().unwrap().is_some() {
+            count += 1;
+        }
+        assert_eq!(count, VALUES);
+    });
+    bench.bytes = input.len() as u64;
+}
+
+fn wide_struct(bench: &mut Bencher) {
+    decode_wide_structs(bench, DecodeOptions::new().field_indices(false));
+}
+
+fn wide_struct_field_indices(bench: &mut Bencher) {
+    decode_wide_structs(bench, DecodeOptions::new());
+}
+
+#[derive(Serialize, SchemaSerialize)]
+struct Series {
+    id: u64,
+    samples: Vec<i64>,
+}
+
+#[derive(Deserialize)]
+struct SeriesId {
+    id: u64,
+}
+
+// the samples are not decoded, only skipped over
+fn skip_numeric_slice(bench: &mut Bencher) {
+    const VALUES: usize = 1_000;
+    let mut stream = StreamSerializer::new_with_bytes();
+    for i in 0..VALUES {
+        let samples = (0..1_000).map(|n| n * 1_000_003 - i as i64).collect();
+        stream
+            .serialize(&Series {
+                id: i as u64 + 1,
+                samples,
+            })
+            .unwrap();
+    }
+    let input = stream.into_inner().into_inner();
+    bench.iter(|| {
+        let mut stream = StreamDeserializer::new(&input[..]);
+        let mut count = 0;
+        while stream.deserialize::<SeriesId>().unwrap().is_some() {
+            count += 1;
+        }
+        assert_eq!(count, VALUES);
+    });
+    bench.bytes = input.len() as u64;
+}
+
+#[derive(Deserialize)]
+struct SeriesSamples {
+    samples: Vec<i64>,
+}
+
+fn decode_numeric_slices(bench: &mut Bencher, sample: fn(i64, i64) -> i64) {
+    const VALUES: usize = 1_000;
+    let mut stream = StreamSerializer::new_with_bytes();
+    for i in 0..VALUES {
+        let samples = (0..1_000).map(|n| sample(i as i64, n)).collect();
+        stream
+            .serialize(&Series {
+                id: i as u64 + 1,
+                samples,
+            })
+            .unwrap();
+    }
+    let input = stream.into_inner().into_inner();
+    bench.iter(|| {
+        let mut stream = StreamDeserializer::new(&input[..]);
+        let mut count = 0;
+        while let Some(series) = stream.deserialize::<SeriesSamples>().unwrap() {
+            count += series.samples.len();
+        }
+        assert_eq!(count, VALUES * 1_000);
+    });
+    bench.bytes = input.len() as u64;
+}
+
+// samples of up to eight bytes each, decoded a batch at a time
+fn numeric_slice(bench: &mut Bencher) {
+    decode_numeric_slices(bench, |i, n| n * 1_000_003 - i);
+}
+
+// samples of a byte each, decoded eight at a time within a batch
+fn numeric_slice_small(bench: &mut Bencher) {
+    decode_numeric_slices(bench, |i, n| (n + i) % 64);
+}
+
+benchmark_group!(
+    benches,
+    stream_small_messages,
+    decoder_small_messages,
+    wide_struct,
+    wide_struct_field_indices,
+    skip_numeric_slice,
+    numeric_slice,
+    numeric_slice_small
+);
+benchmark_main!(benches);
@@ -102,5 +102,57 @@ fn output_write_vec(bench: &mut Bencher) {
     assert_eq!(stream.get_ref().get_ref().len(), 43);
 }
 
-benchmark_group!(benches, output_buffer, output_write_vec);
+#[derive(Serialize, SchemaSerialize)]
+struct Wide {
+    a: u64,
+    b: i64,
+    c: f64,
+    d: bool,
+    e: &'static str,
+    f: u64,
+    g: i64,
+    h: f64,
+    i: bool,
+    j: &'static str,
+    k: u64,
+    l: i64,
+    m: f64,
+    n: bool,
+    o: &'static str,
+    p: u64,
+}
+
+fn wide_struct(bench: &mut Bencher) {
+    let wide = Wide {
+        a: 1,
+        b: -2,
+        c: 3.5,
+        d: true,
+        e: "five",
+        f: 0,
+        g: 7,
+        h: 0.0,
+        i: false,
+        j: "ten",
+        k: 11,
+        l: 0,
+        m: 13.25,
+        n: true,
+        o: "",
+        p: 16,
+    };
+
+    let mut stream = StreamSerializer::new_with_write(Vec::new());
+    let type_id = Wide::schema_register(stream.schema_mut()).unwrap();
+    stream.serialize_with_type_id(type_id, &wide).unwrap();
+
+    bench.iter(|| {
+        stream.get_mut().get_mut().truncate(0);
+        for _ in 0..100 {
+            stream.serialize_with_type_id(type_id, &wide).unwrap();
+        }
+    });
+}
+
+benchmark_group!(benches, output_buffer, output_write_vec, wide_struct);
 benchmark_main!(benches);
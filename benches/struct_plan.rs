@@ -0,0 +1,110 @@
+//! Serializing wide structs, for comparing the field plans resolved once
+//! per struct type with looking each field up in the schema.
+//!
+//! The benchmarks only use the public API, so they also build on the tree
+//! from before the plans. To compare, save a baseline there, then measure
+//! against it on this tree:
+//!
+//! ```text
+//! cargo bench --bench struct_plan -- --save-baseline lookup
+//! cargo bench --bench struct_plan -- --baseline lookup
+//! ```
+
+#[macro_use]
+extern crate criterion;
+extern crate gob;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_schema;
+#[macro_use]
+extern crate serde_schema_derive;
+
+use criterion::{Criterion, Throughput};
+use gob::StreamSerializer;
+use serde::Serialize;
+use serde_schema::SchemaSerialize;
+
+const VALUES: u64 = 1_000;
+
+#[derive(Serialize, SchemaSerialize)]
+struct Wide {
+    a: u64,
+    b: i64,
+    c: f64,
+    d: bool,
+    e: &'static str,
+    f: u64,
+    g: i64,
+    h: f64,
+    i: bool,
+    j: &'static str,
+    k: u64,
+    l: i64,
+    m: f64,
+    n: bool,
+    o: &'static str,
+    p: u64,
+}
+
+#[derive(Serialize, SchemaSerialize)]
+struct Nested {
+    id: u64,
+    left: Wide,
+    right: Wide,
+}
+
+fn wide() -> Wide {
+    Wide {
+        a: 1,
+        b: -2,
+        c: 3.5,
+        d: true,
+        e: "five",
+        f: 0,
+        g: 7,
+        h: 0.0,
+        i: false,
+        j: "ten",
+        k: 11,
+        l: 0,
+        m: 13.25,
+        n: true,
+        o: "",
+        p: 16,
+    }
+}
+
+fn serialize_many<T: Serialize + SchemaSerialize>(c: &mut Criterion, name: &str, value: &T) {
+    let mut stream = StreamSerializer::new_with_write(Vec::new());
+    let type_id = T::schema_register(stream.schema_mut()).unwrap();
+    stream.serialize_with_type_id(type_id, value).unwrap();
+
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Elements(VALUES));
+    group.bench_function("serialize", |b| {
+        b.iter(|| {
+            stream.get_mut().get_mut().truncate(0);
+            for _ in 0..VALUES {
+                stream.serialize_with_type_id(type_id, value).unwrap();
+            }
+        })
+    });
+    group.finish();
+}
+
+fn wide_struct(c: &mut Criterion) {
+    serialize_many(c, "wide_struct", &wide());
+}
+
+fn nested_wide_structs(c: &mut Criterion) {
+    let nested = Nested {
+        id: 1,
+        left: wide(),
+        right: wide(),
+    };
+    serialize_many(c, "nested_wide_structs", &nested);
+}
+
+criterion_group!(benches, wide_struct, nested_wide_structs);
+criterion_main!(benches);
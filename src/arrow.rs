@@ -0,0 +1,434 @@
+//! Arrow record batches
+//!
+//! With the `arrow` feature, a stream of values of one Go struct type can be
+//! read into Arrow `RecordBatch`es, e.g. to query gob dumps of Go services
+//! with DataFusion or Polars, without a Rust struct for the rows:
+//!
+//! ```ignore
+//! let mut batches = gob::arrow::RecordBatches::new(file).batch_size(4096);
+//! while let Some(batch) = batches.next() {
+//!     let batch = batch?;
+//!     // ...
+//! }
+//! ```
+//!
+//! The schema follows the wire type: each struct field becomes a column,
+//! slices and arrays become lists, maps become maps and nested structs
+//! become structs. Since gob leaves out fields holding their zero value,
+//! none of the columns are nullable and such fields hold the zero value
+//! again. Interface values, complex numbers, structs without fields and
+//! recursive types have no Arrow equivalent and are rejected.
+
+use std::io::Read;
+use std::sync::Arc;
+
+use arrow_array::{
+    ArrayRef, BinaryArray, BooleanArray, Float64Array, Int64Array, ListArray, MapArray,
+    RecordBatch, StringArray, StructArray, UInt64Array,
+};
+use arrow_buffer::{OffsetBuffer, ScalarBuffer};
+use arrow_schema::{ArrowError, DataType, Field, Fields, Schema, SchemaRef};
+
+use de::{StreamDeserializer, TypeInfo};
+use error::Error;
+use internal::types::{TypeId, Types, WireType};
+use value::Value;
+
+use crate::{de, error, internal, value};
+
+const DEFAULT_BATCH_SIZE: usize = 1024;
+
+/// Reads the values of a stream into record batches of up to a given
+/// number of rows.
+///
+/// All values have to be of the same struct type. A batch ends early when
+/// a value of another type follows, which is then reported as an error.
+pub struct RecordBatches<R> {
+    stream: StreamDeserializer<R>,
+    batch_size: usize,
+    // the struct type of the rows, its fields and the schema derived from it
+    plan: Option<(TypeId, Vec<(String, Column)>, SchemaRef)>,
+}
+
+impl<R: Read> RecordBatches<R> {
+    pub fn new(read: R) -> Self {
+        RecordBatches::from_stream(StreamDeserializer::new(read))
+    }
+
+    /// Reads the remaining values of `stream`, e.g. one created with type
+    /// definitions or options of its own.
+    pub fn from_stream(stream: StreamDeserializer<R>) -> Self {
+        RecordBatches {
+            stream,
+            batch_size: DEFAULT_BATCH_SIZE,
+            plan: None,
+        }
+    }
+
+    /// Sets the number of rows per batch, 1024 by default.
+    pub fn batch_size(mut self, rows: usize) -> Self {
+        self.batch_size = rows.max(1);
+        self
+    }
+
+    /// The schema of the batches, derived from the type of the first value.
+    /// Returns `None` if the stream holds no values.
+    pub fn schema(&mut self) -> Result<Option<SchemaRef>, Error> {
+        if self.plan.is_none() {
+            let type_id = match self.stream.peek_type()? {
+                Some(info) => info.id(),
+                None => return Ok(None),
+            };
+            self.plan(type_id)?;
+        }
+        Ok(self.plan.as_ref().map(|(_, _, schema)| schema.clone()))
+    }
+
+    pub fn into_inner(self) -> StreamDeserializer<R> {
+        self.stream
+    }
+
+    // derives the columns from the first type, and checks later types
+    // against it
+    fn plan(&mut self, type_id: TypeId) -> Result<(), Error> {
+        if let Some((planned, _, _)) = self.plan {
+            if planned == type_id {
+                return Ok(());
+            }
+            let defs = self.stream.type_defs();
+            return Err(Error::deserialize(format!(
+                "stream mixes values of {} and {}",
+                type_name(&defs.types, planned),
+                type_name(&defs.types, type_id)
+            )));
+        }
+        let defs = self.stream.type_defs();
+        let fields = match column(&defs.types, type_id, &mut Vec::new())? {
+            Column::Struct(fields) => fields,
+            _ => {
+                return Err(Error::deserialize(format!(
+                    "{} is not a struct, only structs can be read as rows",
+                    type_name(&defs.types, type_id)
+                )))
+            }
+        };
+        let schema = Schema::new(struct_fields(&fields));
+        self.plan = Some((type_id, fields, Arc::new(schema)));
+        Ok(())
+    }
+
+    fn next_batch(&mut self) -> Result<Option<RecordBatch>, Error> {
+        let mut rows = Vec::with_capacity(self.batch_size);
+        while rows.len() < self.batch_size {
+            let type_id = match self.stream.peek_type()? {
+                Some(info) => info.id(),
+                None => break,
+            };
+            if let Err(err) = self.plan(type_id) {
+                if rows.is_empty() {
+                    return Err(err);
+                }
+                break;
+            }
+            match self.stream.deserialize::<Value>()? {
+                Some(row) => rows.push(row),
+                None => break,
+            }
+        }
+        let (fields, schema) = match self.plan {
+            Some((_, ref fields, ref schema)) if !rows.is_empty() => (fields, schema),
+            _ => return Ok(None),
+        };
+        let rows = rows.iter().map(Some).collect::<Vec<_>>();
+        let columns = fields
+            .iter()
+            .map(|(name, column)| build(column, &field_values(name, &rows)?))
+            .collect::<Result<Vec<_>, _>>()?;
+        let batch = RecordBatch::try_new(schema.clone(), columns).map_err(arrow_error)?;
+        Ok(Some(batch))
+    }
+}
+
+impl<R: Read> Iterator for RecordBatches<R> {
+    type Item = Result<RecordBatch, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_batch().transpose()
+    }
+}
+
+// the Arrow counterpart of a wire type
+enum Column {
+    Bool,
+    Int,
+    Uint,
+    Float,
+    String,
+    Bytes,
+    List(Box<Column>),
+    Map(Box<Column>, Box<Column>),
+    Struct(Vec<(String, Column)>),
+}
+
+impl Column {
+    fn data_type(&self) -> DataType {
+        match self {
+            Column::Bool => DataType::Boolean,
+            Column::Int => DataType::Int64,
+            Column::Uint => DataType::UInt64,
+            Column::Float => DataType::Float64,
+            Column::String => DataType::Utf8,
+            Column::Bytes => DataType::Binary,
+            Column::List(elem) => DataType::List(Arc::new(list_item(elem))),
+            Column::Map(key, elem) => DataType::Map(Arc::new(map_entries(key, elem)), false),
+            Column::Struct(fields) => DataType::Struct(struct_fields(fields)),
+        }
+    }
+}
+
+fn list_item(elem: &Column) -> Field {
+    Field::new("item", elem.data_type(), false)
+}
+
+fn map_entries(key: &Column, elem: &Column) -> Field {
+    Field::new("entries", DataType::Struct(entry_fields(key, elem)), false)
+}
+
+fn entry_fields(key: &Column, elem: &Column) -> Fields {
+    Fields::from(vec![
+        Field::new("key", key.data_type(), false),
+        Field::new("value", elem.data_type(), false),
+    ])
+}
+
+fn struct_fields(fields: &[(String, Column)]) -> Fields {
+    fields
+        .iter()
+        .map(|(name, column)| Field::new(name, column.data_type(), false))
+        .collect()
+}
+
+fn type_name(defs: &Types, id: TypeId) -> String {
+    match TypeInfo::lookup(id, defs) {
+        Some(info) => info.name().to_owned(),
+        None => format!("type id {}", id.0),
+    }
+}
+
+fn column(defs: &Types, id: TypeId, visiting: &mut Vec<TypeId>) -> Result<Column, Error> {
+    match id {
+        TypeId::BOOL => return Ok(Column::Bool),
+        TypeId::INT => return Ok(Column::Int),
+        TypeId::UINT => return Ok(Column::Uint),
+        TypeId::FLOAT => return Ok(Column::Float),
+        TypeId::STRING => return Ok(Column::String),
+        TypeId::BYTES => return Ok(Column::Bytes),
+        _ => {}
+    }
+    if visiting.contains(&id) {
+        return Err(Error::deserialize(format!(
+            "{} is recursive, which Arrow has no type for",
+            type_name(defs, id)
+        )));
+    }
+    visiting.push(id);
+    let column = match defs.lookup(id) {
+        Some(WireType::Slice(slice)) => Column::List(Box::new(column(defs, slice.elem, visiting)?)),
+        Some(WireType::Array(array)) => Column::List(Box::new(column(defs, array.elem, visiting)?)),
+        Some(WireType::Map(map)) => Column::Map(
+            Box::new(column(defs, map.key, visiting)?),
+            Box::new(column(defs, map.elem, visiting)?),
+        ),
+        Some(WireType::Struct(struct_type)) if !struct_type.fields.is_empty() => {
+            let mut fields = Vec::with_capacity(struct_type.fields.len());
+            for field in struct_type.fields.iter() {
+                fields.push((field.name.to_string(), column(defs, field.id, visiting)?));
+            }
+            Column::Struct(fields)
+        }
+        // types encoding themselves are kept as their encoding
+        Some(WireType::GobEncoder(_)) => Column::Bytes,
+        // structs without fields, interface values and complex numbers
+        _ => {
+            return Err(Error::deserialize(format!(
+                "{} has no Arrow equivalent",
+                type_name(defs, id)
+            )))
+        }
+    };
+    visiting.pop();
+    Ok(column)
+}
+
+fn arrow_error(err: ArrowError) -> Error {
+    Error::deserialize(format!("building record batch: {}", err))
+}
+
+fn mismatch(expected: &str, value: &Value) -> Error {
+    Error::deserialize(format!(
+        "expected {} in column, found {:?}",
+        expected, value
+    ))
+}
+
+// the values of field `name` of each of the struct values `rows`; fields
+// left out hold their zero value, like absent values do
+fn field_values<'a>(
+    name: &str,
+    rows: &[Option<&'a Value>],
+) -> Result<Vec<Option<&'a Value>>, Error> {
+    rows.iter()
+        .map(|row| match *row {
+            None | Some(Value::Nil) => Ok(None),
+            Some(Value::Map(entries)) => Ok(entries
+                .iter()
+                .find(|(key, _)| matches!(key, Value::String(key) if key == name))
+                .map(|(_, value)| value)),
+            Some(other) => Err(mismatch("struct", other)),
+        })
+        .collect()
+}
+
+fn scalars<'a, T, F>(
+    values: &[Option<&'a Value>],
+    zero: T,
+    expected: &str,
+    f: F,
+) -> Result<Vec<T>, Error>
+where
+    T: Copy,
+    F: Fn(&'a Value) -> Option<T>,
+{
+    values
+        .iter()
+        .map(|value| match *value {
+            None | Some(Value::Nil) => Ok(zero),
+            Some(value) => f(value).ok_or_else(|| mismatch(expected, value)),
+        })
+        .collect()
+}
+
+fn as_bool(value: &Value) -> Option<bool> {
+    match *value {
+        Value::Bool(v) => Some(v),
+        _ => None,
+    }
+}
+
+fn as_int(value: &Value) -> Option<i64> {
+    match *value {
+        Value::Int(v) => Some(v),
+        _ => None,
+    }
+}
+
+fn as_uint(value: &Value) -> Option<u64> {
+    match *value {
+        Value::Uint(v) => Some(v),
+        _ => None,
+    }
+}
+
+fn as_float(value: &Value) -> Option<f64> {
+    match *value {
+        Value::Float(v) => Some(v),
+        _ => None,
+    }
+}
+
+fn as_str(value: &Value) -> Option<&str> {
+    match *value {
+        Value::String(ref v) => Some(v),
+        _ => None,
+    }
+}
+
+fn as_bytes(value: &Value) -> Option<&[u8]> {
+    match *value {
+        Value::Bytes(ref v) => Some(v),
+        _ => None,
+    }
+}
+
+// the offset at which the items of the next list or map start
+fn offset(items: usize) -> Result<i32, Error> {
+    if items > i32::MAX as usize {
+        return Err(Error::deserialize("too many items for a single batch"));
+    }
+    Ok(items as i32)
+}
+
+fn build(column: &Column, values: &[Option<&Value>]) -> Result<ArrayRef, Error> {
+    let array: ArrayRef = match column {
+        Column::Bool => Arc::new(BooleanArray::from(scalars(values, false, "bool", as_bool)?)),
+        Column::Int => Arc::new(Int64Array::from(scalars(values, 0, "int", as_int)?)),
+        Column::Uint => Arc::new(UInt64Array::from(scalars(values, 0, "uint", as_uint)?)),
+        Column::Float => Arc::new(Float64Array::from(scalars(values, 0.0, "float", as_float)?)),
+        Column::String => Arc::new(StringArray::from(scalars(values, "", "string", as_str)?)),
+        Column::Bytes => Arc::new(BinaryArray::from(scalars(
+            values,
+            &[][..],
+            "bytes",
+            as_bytes,
+        )?)),
+        Column::List(elem) => {
+            let mut ends = vec![0];
+            let mut items = Vec::new();
+            for value in values {
+                match *value {
+                    None | Some(Value::Nil) => {}
+                    Some(Value::Seq(seq)) => items.extend(seq.iter().map(Some)),
+                    Some(other) => return Err(mismatch("list", other)),
+                }
+                ends.push(offset(items.len())?);
+            }
+            let list = ListArray::try_new(
+                Arc::new(list_item(elem)),
+                OffsetBuffer::new(ScalarBuffer::from(ends)),
+                build(elem, &items)?,
+                None,
+            );
+            Arc::new(list.map_err(arrow_error)?)
+        }
+        Column::Map(key, elem) => {
+            let mut ends = vec![0];
+            let mut keys = Vec::new();
+            let mut elems = Vec::new();
+            for value in values {
+                match *value {
+                    None | Some(Value::Nil) => {}
+                    Some(Value::Map(entries)) => {
+                        for (k, v) in entries {
+                            keys.push(Some(k));
+                            elems.push(Some(v));
+                        }
+                    }
+                    Some(other) => return Err(mismatch("map", other)),
+                }
+                ends.push(offset(keys.len())?);
+            }
+            let entries = StructArray::try_new(
+                entry_fields(key, elem),
+                vec![build(key, &keys)?, build(elem, &elems)?],
+                None,
+            );
+            let map = MapArray::try_new(
+                Arc::new(map_entries(key, elem)),
+                OffsetBuffer::new(ScalarBuffer::from(ends)),
+                entries.map_err(arrow_error)?,
+                None,
+                false,
+            );
+            Arc::new(map.map_err(arrow_error)?)
+        }
+        Column::Struct(fields) => {
+            let arrays = fields
+                .iter()
+                .map(|(name, column)| build(column, &field_values(name, values)?))
+                .collect::<Result<Vec<_>, _>>()?;
+            let array = StructArray::try_new(struct_fields(fields), arrays, None);
+            Arc::new(array.map_err(arrow_error)?)
+        }
+    };
+    Ok(array)
+}
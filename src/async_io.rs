@@ -0,0 +1,271 @@
+//! `Stream` and `Sink` adapters over asynchronous I/O
+//!
+//! Both are built on the sans-io `de::Decoder` and `ser::Encoder`, so they
+//! work with any executor.
+
+use std::future::Future;
+use std::io;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures::io::{AsyncRead, AsyncWrite};
+use futures::{ready, Sink, Stream};
+use futures_timer::Delay;
+use serde::de::DeserializeOwned;
+use serde_schema::SchemaSerialize;
+
+use de::{Decoder, TypeDefs};
+use error::Error;
+use ser::Encoder;
+
+use crate::{de, error, ser};
+
+const READ_SIZE: usize = 4096;
+
+/// Reads values of type `T` off an `AsyncRead`.
+///
+/// The stream ends cleanly at the end of the input, unless it stops in the
+/// middle of a message.
+///
+/// Reading is cancellation-safe: input is fed to the decoder as soon as it
+/// is read, so dropping a pending `next()`, e.g. when it loses a `select!`,
+/// loses nothing, and the next call picks up where it left off.
+pub struct ValueStream<R, T> {
+    reader: ValueReader<R>,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<R, T> ValueStream<R, T> {
+    pub fn new(reader: R) -> Self {
+        ValueStream::with_decoder(reader, Decoder::new())
+    }
+
+    /// Create a new value stream that knows about all types in `defs` up
+    /// front.
+    pub fn with_type_defs(reader: R, defs: TypeDefs) -> Self {
+        ValueStream::with_decoder(reader, Decoder::with_type_defs(defs))
+    }
+
+    /// Create a new value stream decoding with `decoder`, e.g. one with
+    /// non-default options.
+    ///
+    /// With a buffer limit set on the decoder, the stream never reads more
+    /// than the limit ahead, and fails on sections too large for it instead
+    /// of buffering them.
+    pub fn with_decoder(reader: R, decoder: Decoder) -> Self {
+        ValueStream {
+            reader: ValueReader::new(reader, decoder),
+            marker: PhantomData,
+        }
+    }
+
+    /// Fails waiting for a value with an `ErrorKind::TimedOut` error once
+    /// it took longer than `timeout`, e.g. for a peer that went away
+    /// without closing the connection. `None`, the default, waits forever.
+    ///
+    /// The time is taken from the first poll for a value to the value
+    /// being decoded. The stream can be polled again after timing out,
+    /// with nothing read so far lost.
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.reader.timeout = timeout;
+        self.reader.timer = None;
+    }
+
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.reader.timeout
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.reader.reader
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader.reader
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader.reader
+    }
+}
+
+impl<R, T> Stream for ValueStream<R, T>
+where
+    R: AsyncRead + Unpin,
+    T: DeserializeOwned,
+{
+    type Item = Result<T, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        self.get_mut().reader.poll_next(cx)
+    }
+}
+
+// feeds a decoder from an `AsyncRead`, shared with the async rpc codecs
+pub(crate) struct ValueReader<R> {
+    pub(crate) reader: R,
+    decoder: Decoder,
+    buf: Box<[u8]>,
+    done: bool,
+    timeout: Option<Duration>,
+    // started by the first poll for a value, and dropped once it is decoded
+    timer: Option<Delay>,
+}
+
+impl<R> ValueReader<R> {
+    pub(crate) fn new(reader: R, decoder: Decoder) -> Self {
+        ValueReader {
+            reader,
+            decoder,
+            buf: vec![0; READ_SIZE].into_boxed_slice(),
+            done: false,
+            timeout: None,
+            timer: None,
+        }
+    }
+
+    pub(crate) fn poll_next<T>(&mut self, cx: &mut Context) -> Poll<Option<Result<T, Error>>>
+    where
+        R: AsyncRead + Unpin,
+        T: DeserializeOwned,
+    {
+        loop {
+            if self.done {
+                return Poll::Ready(None);
+            }
+            match self.decoder.next_value() {
+                Ok(Some(value)) => {
+                    self.timer = None;
+                    return Poll::Ready(Some(Ok(value)));
+                }
+                Ok(None) => {}
+                Err(err) => return Poll::Ready(Some(Err(err))),
+            }
+
+            // the bytes read are fed right away, so that nothing is lost if
+            // the caller stops polling
+            let room = match self.decoder.room() {
+                Ok(room) => room.min(self.buf.len()),
+                Err(err) => return Poll::Ready(Some(Err(err.into()))),
+            };
+            let buf = &mut self.buf[..room];
+            let len = match Pin::new(&mut self.reader).poll_read(cx, buf) {
+                Poll::Ready(Ok(len)) => len,
+                Poll::Ready(Err(err)) => return Poll::Ready(Some(Err(err.into()))),
+                Poll::Pending => return self.poll_timeout(cx),
+            };
+            if len == 0 {
+                self.done = true;
+                if self.decoder.buffered() > 0 {
+                    let err = io::Error::from(io::ErrorKind::UnexpectedEof);
+                    return Poll::Ready(Some(Err(err.into())));
+                }
+            }
+            self.decoder.feed(&self.buf[..len]);
+        }
+    }
+
+    // fails the value being waited for once the timeout expired
+    fn poll_timeout<T>(&mut self, cx: &mut Context) -> Poll<Option<Result<T, Error>>> {
+        let timeout = match self.timeout {
+            Some(timeout) => timeout,
+            None => return Poll::Pending,
+        };
+        let timer = self.timer.get_or_insert_with(|| Delay::new(timeout));
+        ready!(Pin::new(timer).poll(cx));
+        self.timer = None;
+        let err = io::Error::new(
+            io::ErrorKind::TimedOut,
+            format!("no value read within {:?}", timeout),
+        );
+        Poll::Ready(Some(Err(err.into())))
+    }
+}
+
+/// Writes values of type `T` to an `AsyncWrite`.
+///
+/// Each value is encoded as soon as it is sent into the sink, and written
+/// out on the next call to `poll_ready`, `poll_flush` or `poll_close`.
+pub struct ValueSink<W, T> {
+    writer: W,
+    encoder: Encoder,
+    pending: Bytes,
+    marker: PhantomData<fn(T)>,
+}
+
+impl<W, T> ValueSink<W, T> {
+    pub fn new(writer: W) -> Self {
+        ValueSink::with_encoder(writer, Encoder::new())
+    }
+
+    /// Create a new value sink encoding with `encoder`, e.g. one with
+    /// non-default options.
+    pub fn with_encoder(writer: W, encoder: Encoder) -> Self {
+        ValueSink {
+            writer,
+            encoder,
+            pending: Bytes::new(),
+            marker: PhantomData,
+        }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    // writes out the last encoded value
+    fn poll_write_pending(&mut self, cx: &mut Context) -> Poll<Result<(), Error>>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        while !self.pending.is_empty() {
+            let len = ready!(Pin::new(&mut self.writer).poll_write(cx, &self.pending))?;
+            if len == 0 {
+                return Poll::Ready(Err(io::Error::from(io::ErrorKind::WriteZero).into()));
+            }
+            self.pending.advance(len);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W, T> Sink<T> for ValueSink<W, T>
+where
+    W: AsyncWrite + Unpin,
+    T: SchemaSerialize,
+{
+    type Error = Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Error>> {
+        self.get_mut().poll_write_pending(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: T) -> Result<(), Error> {
+        let this = self.get_mut();
+        debug_assert!(this.pending.is_empty());
+        this.pending = this.encoder.encode(&item)?;
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Error>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_pending(cx))?;
+        Poll::Ready(ready!(Pin::new(&mut this.writer).poll_flush(cx)).map_err(Error::from))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Error>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_pending(cx))?;
+        Poll::Ready(ready!(Pin::new(&mut this.writer).poll_close(cx)).map_err(Error::from))
+    }
+}
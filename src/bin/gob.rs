@@ -0,0 +1,5 @@
+extern crate gob;
+
+fn main() {
+    ::std::process::exit(gob::cli::main());
+}
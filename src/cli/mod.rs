@@ -0,0 +1,204 @@
+//! Command line interface
+//!
+//! Backs the `gob` binary, which is only built with the `cli` feature:
+//!
+//! ```text
+//! gob types [FILE]
+//! gob debug [FILE]
+//! gob dump [--format json|pretty|debug] [FILE]
+//! gob encode --schema SCHEMA [FILE]
+//! ```
+//!
+//! Input is read from `FILE`, or from stdin if it is missing or `-`.
+
+use std::env;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+
+use serde_json;
+
+use de::StreamDeserializer;
+use error::Error;
+use schema::json::{SchemaExpr, Typed};
+use ser::StreamSerializer;
+
+use crate::{de, debug, error, schema, ser, transcode};
+
+mod tree;
+mod types;
+
+const USAGE: &str = "\
+usage: gob types [FILE]
+       gob debug [FILE]
+       gob dump [--format json|pretty|debug] [FILE]
+       gob encode --schema SCHEMA [FILE]";
+
+enum CliError {
+    Usage(String),
+    Schema(String),
+    Io(io::Error),
+    Gob(Error),
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CliError::Usage(ref msg) => write!(f, "{}\n\n{}", msg, USAGE),
+            CliError::Schema(ref msg) => write!(f, "invalid schema: {}", msg),
+            CliError::Io(ref err) => err.fmt(f),
+            CliError::Gob(ref err) => err.fmt(f),
+            CliError::Json(ref err) => err.fmt(f),
+        }
+    }
+}
+
+impl From<io::Error> for CliError {
+    fn from(err: io::Error) -> CliError {
+        CliError::Io(err)
+    }
+}
+
+impl From<Error> for CliError {
+    fn from(err: Error) -> CliError {
+        CliError::Gob(err)
+    }
+}
+
+impl From<serde_json::Error> for CliError {
+    fn from(err: serde_json::Error) -> CliError {
+        CliError::Json(err)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Format {
+    Json,
+    Pretty,
+    Debug,
+}
+
+/// Runs the command line tool and returns the process exit code.
+pub fn main() -> i32 {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let stdout = io::stdout();
+    match run(&args, &mut stdout.lock()) {
+        Ok(()) => 0,
+        Err(CliError::Io(ref err)) if err.kind() == io::ErrorKind::BrokenPipe => 0,
+        Err(err) => {
+            eprintln!("gob: {}", err);
+            match err {
+                CliError::Usage(_) => 2,
+                _ => 1,
+            }
+        }
+    }
+}
+
+fn run<W: Write>(args: &[String], out: &mut W) -> Result<(), CliError> {
+    let (command, rest) = match args.split_first() {
+        Some((command, rest)) => (command.as_str(), rest),
+        None => return Err(CliError::Usage("missing command".to_owned())),
+    };
+
+    let mut format = Format::Json;
+    let mut schema = None;
+    let mut input = None;
+
+    let mut rest = rest.iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--format" if command == "dump" => {
+                format = match rest.next().map(String::as_str) {
+                    Some("json") => Format::Json,
+                    Some("pretty") => Format::Pretty,
+                    Some("debug") => Format::Debug,
+                    _ => return Err(CliError::Usage("invalid --format".to_owned())),
+                }
+            }
+            "--schema" if command == "encode" => match rest.next() {
+                Some(path) => schema = Some(path.clone()),
+                None => return Err(CliError::Usage("missing schema file".to_owned())),
+            },
+            "-h" | "--help" => {
+                writeln!(out, "{}", USAGE)?;
+                return Ok(());
+            }
+            _ if input.is_none() && (arg == "-" || !arg.starts_with('-')) => {
+                input = Some(arg.clone())
+            }
+            _ => return Err(CliError::Usage(format!("unexpected argument {:?}", arg))),
+        }
+    }
+
+    let input = open(input.as_deref())?;
+    match command {
+        "types" => run_types(input, out),
+        "debug" => Ok(debug::dump(input, out)?),
+        "dump" => run_dump(input, format, out),
+        "encode" => match schema {
+            Some(path) => run_encode(input, &path, out),
+            None => Err(CliError::Usage("encode requires --schema".to_owned())),
+        },
+        _ => Err(CliError::Usage(format!("unknown command {:?}", command))),
+    }
+}
+
+fn open(path: Option<&str>) -> Result<Box<dyn Read>, CliError> {
+    match path {
+        None | Some("-") => Ok(Box::new(io::stdin())),
+        Some(path) => Ok(Box::new(BufReader::new(File::open(path)?))),
+    }
+}
+
+fn run_types<W: Write>(input: Box<dyn Read>, out: &mut W) -> Result<(), CliError> {
+    let mut stream = StreamDeserializer::new(input);
+    while stream.deserializer()?.is_some() {}
+    types::write_types(stream.types(), out)?;
+    Ok(())
+}
+
+fn run_dump<W: Write>(input: Box<dyn Read>, format: Format, out: &mut W) -> Result<(), CliError> {
+    let mut stream = StreamDeserializer::new(input);
+    let mut first = true;
+    while let Some(de) = stream.deserializer()? {
+        match format {
+            Format::Json => {
+                transcode::transcode(de, &mut serde_json::Serializer::new(&mut *out))?;
+                writeln!(out)?;
+            }
+            Format::Pretty => {
+                transcode::transcode(de, &mut serde_json::Serializer::pretty(&mut *out))?;
+                writeln!(out)?;
+            }
+            Format::Debug => {
+                if !first {
+                    writeln!(out)?;
+                }
+                tree::write_tree(de, out)?;
+            }
+        }
+        first = false;
+    }
+    Ok(())
+}
+
+fn run_encode<W: Write>(input: Box<dyn Read>, schema: &str, out: &mut W) -> Result<(), CliError> {
+    let schema: serde_json::Value = serde_json::from_reader(BufReader::new(File::open(schema)?))?;
+    let expr = SchemaExpr::parse(&schema).map_err(CliError::Schema)?;
+
+    let mut stream = StreamSerializer::new_with_write(out);
+    let type_id = expr.register(stream.schema_mut())?;
+    for value in serde_json::Deserializer::from_reader(input).into_iter::<serde_json::Value>() {
+        let value = value?;
+        stream.serialize_with_type_id(
+            type_id,
+            &Typed {
+                expr: &expr,
+                value: &value,
+            },
+        )?;
+    }
+    Ok(())
+}
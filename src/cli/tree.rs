@@ -0,0 +1,177 @@
+use std::fmt::{self, Display};
+use std::io::Write;
+
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde_json;
+
+use crate::transcode::Transcoder;
+
+/// Prints a single value as an indented tree, one leaf per line.
+pub(crate) fn write_tree<'de, D, W>(deserializer: D, out: &mut W) -> Result<(), D::Error>
+where
+    D: Deserializer<'de>,
+    W: Write,
+{
+    deserializer.deserialize_any(TreeVisitor {
+        out,
+        depth: 0,
+        top: true,
+    })
+}
+
+struct TreeVisitor<'a, W: 'a> {
+    out: &'a mut W,
+    depth: usize,
+    top: bool,
+}
+
+impl<'a, W: Write> TreeVisitor<'a, W> {
+    fn leaf<E: de::Error, T: Display>(self, value: T) -> Result<(), E> {
+        let sep = if self.top { "" } else { " " };
+        writeln!(self.out, "{}{}", sep, value).map_err(E::custom)
+    }
+
+    fn child_depth(&self) -> usize {
+        if self.top {
+            0
+        } else {
+            self.depth + 1
+        }
+    }
+
+    fn open<E: de::Error>(&mut self) -> Result<(), E> {
+        if self.top {
+            Ok(())
+        } else {
+            writeln!(self.out).map_err(E::custom)
+        }
+    }
+
+    fn indent<E: de::Error>(out: &mut W, depth: usize) -> Result<(), E> {
+        write!(out, "{:1$}", "", depth * 2).map_err(E::custom)
+    }
+}
+
+impl<'de, 'a, W: Write> Visitor<'de> for TreeVisitor<'a, W> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any value")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<(), E> {
+        self.leaf(v)
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<(), E> {
+        self.leaf(v)
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<(), E> {
+        self.leaf(v)
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<(), E> {
+        self.leaf(v)
+    }
+
+    fn visit_char<E: de::Error>(self, v: char) -> Result<(), E> {
+        self.leaf(format_args!("{:?}", v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<(), E> {
+        self.leaf(format_args!("{:?}", v))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<(), E> {
+        let hex = v.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+        self.leaf(format_args!("<{} bytes> {}", v.len(), hex))
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<(), E> {
+        self.leaf("nil")
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<(), E> {
+        self.leaf("()")
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_seq<V: SeqAccess<'de>>(mut self, mut access: V) -> Result<(), V::Error> {
+        // gob sequences always announce their length up front
+        let len = access.size_hint().unwrap_or(0);
+        if len == 0 {
+            return self.leaf("[]");
+        }
+        self.open()?;
+        let depth = self.child_depth();
+        for _ in 0..len {
+            Self::indent(self.out, depth)?;
+            write!(self.out, "-").map_err(de::Error::custom)?;
+            access.next_element_seed(TreeSeed {
+                out: &mut *self.out,
+                depth,
+            })?;
+        }
+        Ok(())
+    }
+
+    fn visit_map<V: MapAccess<'de>>(mut self, mut access: V) -> Result<(), V::Error> {
+        let depth = self.child_depth();
+        let mut empty = true;
+        while let Some(key) = access.next_key_seed(KeySeed)? {
+            if empty {
+                self.open()?;
+                empty = false;
+            }
+            Self::indent(self.out, depth)?;
+            write!(self.out, "{}:", key).map_err(de::Error::custom)?;
+            access.next_value_seed(TreeSeed {
+                out: &mut *self.out,
+                depth,
+            })?;
+        }
+        if empty {
+            return self.leaf("{}");
+        }
+        Ok(())
+    }
+}
+
+struct TreeSeed<'a, W: 'a> {
+    out: &'a mut W,
+    depth: usize,
+}
+
+impl<'de, 'a, W: Write> DeserializeSeed<'de> for TreeSeed<'a, W> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(TreeVisitor {
+            out: self.out,
+            depth: self.depth,
+            top: false,
+        })
+    }
+}
+
+struct KeySeed;
+
+impl<'de> DeserializeSeed<'de> for KeySeed {
+    type Value = String;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<String, D::Error> {
+        match serde_json::to_value(Transcoder::new(deserializer)) {
+            Ok(serde_json::Value::String(s)) => Ok(s),
+            Ok(value) => Ok(value.to_string()),
+            Err(err) => Err(de::Error::custom(err)),
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use std::io::{self, Write};
+
+use internal::types::{lookup_builtin2, TypeId, Types, WireType};
+
+use crate::internal;
+
+/// Prints every type definition in `defs` as Go-like declarations.
+pub(crate) fn write_types<W: Write>(defs: &Types, out: &mut W) -> io::Result<()> {
+    for (i, def) in defs.iter().enumerate() {
+        if i > 0 {
+            writeln!(out)?;
+        }
+        let common = def.common();
+        let name = if common.name.is_empty() {
+            "_"
+        } else {
+            &*common.name
+        };
+        writeln!(out, "// id {}", common.id.0)?;
+        match *def {
+            WireType::Struct(ref struct_type) => {
+                writeln!(out, "type {} struct {{", name)?;
+                for field in struct_type.fields.iter() {
+                    writeln!(out, "\t{} {}", field.name, type_name(defs, field.id))?;
+                }
+                writeln!(out, "}}")?;
+            }
+            _ => writeln!(out, "type {} {}", name, underlying(defs, def))?,
+        }
+    }
+    Ok(())
+}
+
+fn type_name(defs: &Types, id: TypeId) -> String {
+    match id {
+        TypeId::BOOL => "bool".to_owned(),
+        TypeId::INT => "int".to_owned(),
+        TypeId::UINT => "uint".to_owned(),
+        TypeId::FLOAT => "float64".to_owned(),
+        TypeId::BYTES => "[]byte".to_owned(),
+        TypeId::STRING => "string".to_owned(),
+        TypeId::COMPLEX => "complex128".to_owned(),
+        TypeId::INTERFACE => "interface{}".to_owned(),
+        _ => match lookup_builtin2(id).or_else(|| defs.lookup(id)) {
+            Some(def) if !def.common().name.is_empty() => def.common().name.to_string(),
+            Some(def) => underlying(defs, def),
+            None => format!("<unknown type {}>", id.0),
+        },
+    }
+}
+
+fn underlying(defs: &Types, def: &WireType) -> String {
+    match *def {
+        WireType::Array(ref array_type) => {
+            format!("[{}]{}", array_type.len, type_name(defs, array_type.elem))
+        }
+        WireType::Slice(ref slice_type) => format!("[]{}", type_name(defs, slice_type.elem)),
+        WireType::Map(ref map_type) => format!(
+            "map[{}]{}",
+            type_name(defs, map_type.key),
+            type_name(defs, map_type.elem)
+        ),
+        WireType::Struct(ref struct_type) => {
+            let fields = struct_type
+                .fields
+                .iter()
+                .map(|field| format!("{} {}", field.name, type_name(defs, field.id)))
+                .collect::<Vec<_>>();
+            format!("struct {{ {} }}", fields.join("; "))
+        }
+        WireType::GobEncoder(_) => "[]byte".to_owned(),
+    }
+}
@@ -0,0 +1,247 @@
+//! The gob wire primitives on `core` and `alloc` alone
+//!
+//! These are the encodings every value in a gob stream is made of, and the
+//! framing of the sections carrying them. The rest of the crate reads and
+//! writes through them; with the default `std` feature turned off, they are
+//! all that is built, for targets without `std` that frame and unframe
+//! sections themselves:
+//!
+//! ```
+//! use gob::codec::{decode_section, decode_uint, encode_section, encode_uint};
+//!
+//! // a uint on its own: the zero delta of a singleton value, then the uint
+//! let mut payload = Vec::new();
+//! encode_uint(&mut payload, 0);
+//! encode_uint(&mut payload, 300);
+//!
+//! let mut section = Vec::new();
+//! encode_section(&mut section, 3, &payload);
+//!
+//! let (decoded, len) = decode_section(&section).unwrap();
+//! assert_eq!(len, section.len());
+//! assert_eq!(decoded.type_id, 3);
+//! assert_eq!(decode_uint(&decoded.payload[1..]).unwrap(), (300, 3));
+//! ```
+
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Sections of this many bytes or more are rejected, like Go does. Go's
+/// limit depends on the platform's word size; this one matches it.
+pub const MAX_SECTION_LEN: u64 = (1 << 30) << ((usize::MAX as u64) >> 62);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The input ended early. Holds how many more bytes are needed, if the
+    /// bytes read so far tell.
+    Incomplete(Option<usize>),
+    Invalid(&'static str),
+    /// A section of this length, at least `MAX_SECTION_LEN`.
+    TooLarge(u64),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeError::Incomplete(_) => f.write_str("unexpected end of input"),
+            DecodeError::Invalid(reason) => f.write_str(reason),
+            DecodeError::TooLarge(len) => write!(f, "section of {} bytes is too large", len),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecodeError {}
+
+/// A section, as split off the input by `decode_section`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Section<'a> {
+    pub type_id: i64,
+    pub payload: &'a [u8],
+}
+
+pub fn encode_uint(out: &mut Vec<u8>, n: u64) {
+    let mut scratch = [0; 9];
+    out.extend_from_slice(uint_bytes(&mut scratch, n));
+}
+
+pub fn encode_int(out: &mut Vec<u8>, n: i64) {
+    encode_uint(out, int_bits(n));
+}
+
+pub fn encode_float(out: &mut Vec<u8>, n: f64) {
+    encode_uint(out, n.to_bits().swap_bytes());
+}
+
+pub fn encode_bool(out: &mut Vec<u8>, b: bool) {
+    encode_uint(out, b as u64);
+}
+
+/// Encodes `bytes` with its length in front, as byte slices and strings are.
+pub fn encode_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    encode_uint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+/// Frames `payload` as a section carrying a value or definition of the
+/// type `type_id`.
+pub fn encode_section(out: &mut Vec<u8>, type_id: i64, payload: &[u8]) {
+    let mut scratch = [0; 9];
+    let id = uint_bytes(&mut scratch, int_bits(type_id));
+    encode_uint(out, (id.len() + payload.len()) as u64);
+    out.extend_from_slice(id);
+    out.extend_from_slice(payload);
+}
+
+/// Decodes the uint at the start of `bytes`, returning it along with the
+/// number of bytes it took up.
+#[inline]
+pub fn decode_uint(bytes: &[u8]) -> Result<(u64, usize), DecodeError> {
+    let len = uint_len(bytes)?;
+    if len == 1 {
+        return Ok((bytes[0] as u64, 1));
+    }
+    let mut be = [0; 8];
+    be[9 - len..].copy_from_slice(&bytes[1..len]);
+    Ok((u64::from_be_bytes(be), len))
+}
+
+#[inline]
+pub fn decode_int(bytes: &[u8]) -> Result<(i64, usize), DecodeError> {
+    let (bits, len) = decode_uint(bytes)?;
+    Ok((int_from_bits(bits), len))
+}
+
+#[inline]
+pub fn decode_float(bytes: &[u8]) -> Result<(f64, usize), DecodeError> {
+    let (bits, len) = decode_uint(bytes)?;
+    Ok((float_from_bits(bits), len))
+}
+
+#[inline]
+pub fn decode_bool(bytes: &[u8]) -> Result<(bool, usize), DecodeError> {
+    let (bits, len) = decode_uint(bytes)?;
+    Ok((bool_from_bits(bits)?, len))
+}
+
+/// Decodes a byte slice or string, returning its contents along with the
+/// number of bytes it took up, length included.
+pub fn decode_bytes(bytes: &[u8]) -> Result<(&[u8], usize), DecodeError> {
+    let (len, offset) = decode_uint(bytes)?;
+    if ((bytes.len() - offset) as u64) < len {
+        return Err(DecodeError::Invalid("length exceeds message"));
+    }
+    let end = offset + len as usize;
+    Ok((&bytes[offset..end], end))
+}
+
+/// Splits off the section at the start of `bytes`, returning it along with
+/// where it ends. Fails with `Incomplete` unless all of it is there.
+pub fn decode_section(bytes: &[u8]) -> Result<(Section<'_>, usize), DecodeError> {
+    let (type_id, payload_offset, end) = section_bounds(bytes)?;
+    if bytes.len() < end {
+        return Err(DecodeError::Incomplete(Some(end - bytes.len())));
+    }
+    let section = Section {
+        type_id,
+        payload: &bytes[payload_offset..end],
+    };
+    Ok((section, end))
+}
+
+/// Parses the length and type id of the section at the start of `bytes`,
+/// however much of its payload follows, returning the type id, where the
+/// payload starts and where the section ends.
+pub(crate) fn section_bounds(bytes: &[u8]) -> Result<(i64, usize, usize), DecodeError> {
+    //
+    //  <---> message offset
+    //        <--------------------> message length
+    // [ len | type id | payload... ]
+    //  <-------------> payload offset
+    //                  <----------> payload length
+    //
+    let (msg_length, msg_offset) = decode_uint(bytes)?;
+    if msg_length >= MAX_SECTION_LEN {
+        return Err(DecodeError::TooLarge(msg_length));
+    }
+    let msg_length = msg_length as usize;
+    let (type_id, id_len) = match decode_int(&bytes[msg_offset..]) {
+        Err(DecodeError::Incomplete(_)) if bytes.len() >= msg_offset + msg_length => {
+            return Err(DecodeError::Invalid("type id exceeds message length"));
+        }
+        result => result?,
+    };
+    if id_len > msg_length {
+        return Err(DecodeError::Invalid("type id exceeds message length"));
+    }
+    Ok((type_id, msg_offset + id_len, msg_offset + msg_length))
+}
+
+// Length of the uint at the start of `bytes`, checking the bounds once
+// rather than for every byte.
+#[inline]
+pub(crate) fn uint_len(bytes: &[u8]) -> Result<usize, DecodeError> {
+    match bytes.first() {
+        None => Err(DecodeError::Incomplete(None)),
+        Some(&u7) if u7 < 128 => Ok(1),
+        Some(&len_byte) => {
+            let len = (!len_byte).wrapping_add(1) as usize;
+            if len > 8 {
+                Err(DecodeError::Invalid("invalid uint length"))
+            } else if bytes.len() <= len {
+                Err(DecodeError::Incomplete(Some(len + 1 - bytes.len())))
+            } else {
+                Ok(len + 1)
+            }
+        }
+    }
+}
+
+/// Encodes `n` into `scratch`, returning the part of it that was used.
+#[inline]
+pub(crate) fn uint_bytes(scratch: &mut [u8; 9], n: u64) -> &[u8] {
+    if n < 128 {
+        scratch[0] = n as u8;
+        return &scratch[..1];
+    }
+    let nbytes = 8 - (n.leading_zeros() / 8) as usize;
+    scratch[0] = !(nbytes as u8 - 1);
+    scratch[1..=nbytes].copy_from_slice(&n.to_be_bytes()[8 - nbytes..]);
+    &scratch[..=nbytes]
+}
+
+// The ints, floats and bools a decoded uint stands for, for uints decoded
+// in batches.
+
+#[inline]
+pub(crate) fn int_from_bits(bits: u64) -> i64 {
+    let sint = (bits >> 1) as i64;
+    if bits & 1 == 0 {
+        sint
+    } else {
+        !sint
+    }
+}
+
+#[inline]
+pub(crate) fn float_from_bits(bits: u64) -> f64 {
+    f64::from_bits(bits.swap_bytes())
+}
+
+#[inline]
+pub(crate) fn bool_from_bits(bits: u64) -> Result<bool, DecodeError> {
+    match bits {
+        0 => Ok(false),
+        1 => Ok(true),
+        _ => Err(DecodeError::Invalid("integer overflow")),
+    }
+}
+
+#[inline]
+fn int_bits(n: i64) -> u64 {
+    if n < 0 {
+        (!(n as u64) << 1) | 1
+    } else {
+        (n as u64) << 1
+    }
+}
@@ -0,0 +1,242 @@
+//! Adapters for common Go conventions
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{self, Deserialize, Deserializer, IgnoredAny, MapAccess, Visitor};
+use serde::ser::{self, Serialize, SerializeStruct, Serializer};
+use serde_schema::types::{Type, TypeId};
+use serde_schema::{Schema, SchemaSerialize};
+
+use value::Value;
+
+use crate::value;
+
+#[cfg(feature = "bignum")]
+pub mod bignum;
+#[cfg(feature = "uuid")]
+pub mod uuid;
+
+/// A `Result` on the wire as a Go reply with a separate error, i.e. as
+///
+/// ```go
+/// type GoResult struct {
+///     Value T
+///     Error string
+/// }
+/// ```
+///
+/// Since gob leaves out zero values, an empty error is no error at all, and
+/// a successful reply without a value decodes to `T::default()`.
+///
+/// Besides strings, the error may also arrive as an interface value, as
+/// sent by Go code using a registered error type; anything other than a
+/// string is then rendered as text.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GoResult<T>(pub Result<T, String>);
+
+impl<T> GoResult<T> {
+    pub fn into_result(self) -> Result<T, String> {
+        self.0
+    }
+}
+
+impl<T> From<Result<T, String>> for GoResult<T> {
+    fn from(result: Result<T, String>) -> GoResult<T> {
+        GoResult(result)
+    }
+}
+
+impl<T: Serialize> Serialize for GoResult<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("GoResult", 2)?;
+        match self.0 {
+            Ok(ref value) => {
+                s.serialize_field("Value", value)?;
+                s.skip_field("Error")?;
+            }
+            Err(ref error) => {
+                s.skip_field("Value")?;
+                s.serialize_field("Error", error)?;
+            }
+        }
+        s.end()
+    }
+}
+
+impl<T: SchemaSerialize> SchemaSerialize for GoResult<T> {
+    fn schema_register<S: Schema>(schema: &mut S) -> Result<S::TypeId, S::Error> {
+        let value_type = T::schema_register(schema)?;
+        let error_type = String::schema_register(schema)?;
+        schema.register_type(
+            Type::build()
+                .struct_type("GoResult", 2)
+                .field("Value", value_type)
+                .field("Error", error_type)
+                .end(),
+        )
+    }
+}
+
+impl<'de, T> Deserialize<'de> for GoResult<T>
+where
+    T: Deserialize<'de> + Default,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_struct(
+            "GoResult",
+            &["Value", "Error"],
+            GoResultVisitor(PhantomData),
+        )
+    }
+}
+
+struct GoResultVisitor<T>(PhantomData<T>);
+
+impl<'de, T> Visitor<'de> for GoResultVisitor<T>
+where
+    T: Deserialize<'de> + Default,
+{
+    type Value = GoResult<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a struct with a value and an error")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<GoResult<T>, A::Error> {
+        let mut value = None;
+        let mut error = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "Value" => value = Some(map.next_value()?),
+                "Error" => {
+                    error = match map.next_value()? {
+                        Value::Nil => None,
+                        Value::String(ref s) if s.is_empty() => None,
+                        Value::String(s) => Some(s),
+                        other => Some(other.to_string()),
+                    }
+                }
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        match error {
+            Some(error) => Ok(GoResult(Err(error))),
+            None => Ok(GoResult(Ok(value.unwrap_or_default()))),
+        }
+    }
+}
+
+/// Which of Go's interfaces a type encoding itself implements.
+///
+/// Values are sent as byte slices either way, but a Go program only
+/// decodes them into a type implementing the matching decoding interface.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExternalEncoding {
+    /// `GobEncoder` and `GobDecoder`.
+    #[default]
+    Gob,
+    /// `encoding.BinaryMarshaler` and `encoding.BinaryUnmarshaler`.
+    Binary,
+    /// `encoding.TextMarshaler` and `encoding.TextUnmarshaler`.
+    Text,
+}
+
+/// A type encoding itself into bytes, like one implementing Go's
+/// `GobEncoder`.
+///
+/// Wrapped in `Gob`, its values are sent the way Go sends those of a
+/// `GobEncoder`: as an opaque byte slice of a named type, so that Go code
+/// decodes them with its `GobDecoder`.
+pub trait GobEncode {
+    /// The name of the type on the wire.
+    const GOB_NAME: &'static str;
+
+    /// The Go interface the encoding stands in for.
+    const ENCODING: ExternalEncoding = ExternalEncoding::Gob;
+
+    type Error: fmt::Display;
+
+    fn gob_encode(&self) -> Result<Vec<u8>, Self::Error>;
+}
+
+/// A type decoding itself from bytes, like one implementing Go's
+/// `GobDecoder`.
+///
+/// Wrapped in `Gob`, it decodes values sent by a Go `GobEncoder`, or from
+/// a `BinaryMarshaler` or `TextMarshaler`, which are sent the same way.
+pub trait GobDecode: Sized {
+    type Error: fmt::Display;
+
+    fn gob_decode(bytes: &[u8]) -> Result<Self, Self::Error>;
+}
+
+/// A value sent in its own encoding rather than through serde, see
+/// `GobEncode` and `GobDecode`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Gob<T>(pub T);
+
+impl<T> Gob<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: GobEncode> Serialize for Gob<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let bytes = self.0.gob_encode().map_err(ser::Error::custom)?;
+        serializer.serialize_newtype_struct(T::GOB_NAME, &GobBytes(&bytes))
+    }
+}
+
+impl<T: GobEncode> SchemaSerialize for Gob<T> {
+    fn schema_register<S: Schema>(schema: &mut S) -> Result<S::TypeId, S::Error> {
+        // a tuple struct starting with `()` marks a type encoding itself
+        let encoding = match T::ENCODING {
+            ExternalEncoding::Gob => S::TypeId::UNIT,
+            ExternalEncoding::Binary => S::TypeId::BYTES,
+            ExternalEncoding::Text => S::TypeId::STR,
+        };
+        schema.register_type(
+            Type::build()
+                .tuple_struct_type(T::GOB_NAME, 2)
+                .element(S::TypeId::UNIT)
+                .element(encoding)
+                .end(),
+        )
+    }
+}
+
+impl<'de, T: GobDecode> Deserialize<'de> for Gob<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_newtype_struct("Gob", GobVisitor(PhantomData))
+    }
+}
+
+struct GobBytes<'a>(&'a [u8]);
+
+impl<'a> Serialize for GobBytes<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+struct GobVisitor<T>(PhantomData<T>);
+
+impl<'de, T: GobDecode> Visitor<'de> for GobVisitor<T> {
+    type Value = Gob<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an encoded value")
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Gob<T>, E> {
+        T::gob_decode(v).map(Gob).map_err(E::custom)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(self, d: D) -> Result<Gob<T>, D::Error> {
+        d.deserialize_bytes(self)
+    }
+}
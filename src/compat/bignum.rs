@@ -0,0 +1,242 @@
+//! Go's `math/big` numbers, which encode themselves.
+//!
+//! `Gob<BigInt>` and `Gob<BigRational>` send and receive `big.Int` and
+//! `big.Rat` values, with payloads identical to the ones Go produces.
+//! `big.Float` values decode into a `BigRational` as well, or into a
+//! `BigFloat` to send them back unchanged.
+
+use num_bigint::{BigInt, BigUint, Sign};
+use num_rational::BigRational;
+use num_traits::{One, Zero};
+
+use super::{GobDecode, GobEncode};
+
+const INT_VERSION: u8 = 1;
+const RAT_VERSION: u8 = 1;
+const FLOAT_VERSION: u8 = 1;
+
+impl GobEncode for BigInt {
+    const GOB_NAME: &'static str = "Int";
+
+    type Error = String;
+
+    fn gob_encode(&self) -> Result<Vec<u8>, String> {
+        let mut buf = vec![INT_VERSION << 1 | (self.sign() == Sign::Minus) as u8];
+        buf.extend_from_slice(&magnitude_bytes(self.magnitude()));
+        Ok(buf)
+    }
+}
+
+impl GobDecode for BigInt {
+    type Error = String;
+
+    fn gob_decode(buf: &[u8]) -> Result<BigInt, String> {
+        // a nil pointer
+        if buf.is_empty() {
+            return Ok(BigInt::zero());
+        }
+        if buf[0] >> 1 != INT_VERSION {
+            return Err(format!(
+                "Int.GobDecode: encoding version {} not supported",
+                buf[0] >> 1
+            ));
+        }
+        Ok(signed(buf[0] & 1 != 0, BigUint::from_bytes_be(&buf[1..])))
+    }
+}
+
+impl GobEncode for BigRational {
+    const GOB_NAME: &'static str = "Rat";
+
+    type Error = String;
+
+    fn gob_encode(&self) -> Result<Vec<u8>, String> {
+        let numer = magnitude_bytes(self.numer().magnitude());
+        let denom = magnitude_bytes(self.denom().magnitude());
+        if numer.len() > u32::MAX as usize {
+            return Err("Rat.GobEncode: numerator too large".to_owned());
+        }
+        let neg = (self.numer().sign() == Sign::Minus) != (self.denom().sign() == Sign::Minus);
+        let mut buf = vec![RAT_VERSION << 1 | neg as u8];
+        buf.extend_from_slice(&(numer.len() as u32).to_be_bytes());
+        buf.extend_from_slice(&numer);
+        buf.extend_from_slice(&denom);
+        Ok(buf)
+    }
+}
+
+/// Takes `big.Float` values besides `big.Rat` ones, failing on infinities.
+impl GobDecode for BigRational {
+    type Error = String;
+
+    fn gob_decode(buf: &[u8]) -> Result<BigRational, String> {
+        // a nil pointer
+        if buf.is_empty() {
+            return Ok(BigRational::zero());
+        }
+        // unlike the others, the version of floats takes the whole byte
+        if buf[0] == FLOAT_VERSION {
+            let float = BigFloat::gob_decode(buf)?;
+            return float
+                .to_rational()
+                .ok_or_else(|| "cannot represent an infinite Float".to_owned());
+        }
+        if buf.len() < 5 {
+            return Err("Rat.GobDecode: buffer too small".to_owned());
+        }
+        if buf[0] >> 1 != RAT_VERSION {
+            return Err(format!(
+                "Rat.GobDecode: encoding version {} not supported",
+                buf[0] >> 1
+            ));
+        }
+        let len = u32::from_be_bytes([buf[1], buf[2], buf[3], buf[4]]) as usize;
+        let numer = buf
+            .get(5..5 + len)
+            .ok_or_else(|| "Rat.GobDecode: buffer too small".to_owned())?;
+        let numer = signed(buf[0] & 1 != 0, BigUint::from_bytes_be(numer));
+        // an empty denominator stands for one
+        let denom = match &buf[5 + len..] {
+            [] => BigInt::one(),
+            denom => BigInt::from(BigUint::from_bytes_be(denom)),
+        };
+        if denom.is_zero() {
+            return Err("Rat.GobDecode: zero denominator".to_owned());
+        }
+        Ok(BigRational::new(numer, denom))
+    }
+}
+
+/// A Go `big.Float`, as it was encoded.
+///
+/// Besides the value, a `big.Float` carries its precision, rounding mode
+/// and accuracy, which are kept so that it is encoded again unchanged.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BigFloat {
+    /// The precision of the mantissa in bits.
+    pub prec: u32,
+    /// The rounding mode, as Go's `big.RoundingMode`.
+    pub mode: u8,
+    /// The accuracy of the last operation, as Go's `big.Accuracy`: -1, 0
+    /// or 1.
+    pub acc: i8,
+    pub neg: bool,
+    pub form: FloatForm,
+}
+
+/// Whether a `BigFloat` is zero, finite or infinite.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FloatForm {
+    Zero,
+    /// The value `0.mant * 2^exp`, with the mantissa in big-endian bytes.
+    Finite {
+        exp: i32,
+        mant: Vec<u8>,
+    },
+    Inf,
+}
+
+impl BigFloat {
+    /// Returns the exact value of the float, or `None` if it is infinite.
+    pub fn to_rational(&self) -> Option<BigRational> {
+        let (exp, mant) = match self.form {
+            FloatForm::Zero => return Some(BigRational::zero()),
+            FloatForm::Finite { exp, ref mant } => (exp, mant),
+            FloatForm::Inf => return None,
+        };
+        // the mantissa is a fraction of the length it was sent with
+        let shift = exp as i64 - 8 * mant.len() as i64;
+        let mant = signed(self.neg, BigUint::from_bytes_be(mant));
+        let value = if shift >= 0 {
+            BigRational::from_integer(mant << shift as usize)
+        } else {
+            BigRational::new(mant, BigInt::one() << (-shift) as usize)
+        };
+        Some(value)
+    }
+}
+
+impl GobEncode for BigFloat {
+    const GOB_NAME: &'static str = "Float";
+
+    type Error = String;
+
+    fn gob_encode(&self) -> Result<Vec<u8>, String> {
+        let form = match self.form {
+            FloatForm::Zero => 0,
+            FloatForm::Finite { .. } => 1,
+            FloatForm::Inf => 2,
+        };
+        let mut buf = vec![
+            FLOAT_VERSION,
+            (self.mode & 7) << 5 | ((self.acc + 1) as u8 & 3) << 3 | form << 1 | self.neg as u8,
+        ];
+        buf.extend_from_slice(&self.prec.to_be_bytes());
+        if let FloatForm::Finite { exp, ref mant } = self.form {
+            buf.extend_from_slice(&exp.to_be_bytes());
+            buf.extend_from_slice(mant);
+        }
+        Ok(buf)
+    }
+}
+
+impl GobDecode for BigFloat {
+    type Error = String;
+
+    fn gob_decode(buf: &[u8]) -> Result<BigFloat, String> {
+        // a nil pointer
+        if buf.is_empty() {
+            return Ok(BigFloat {
+                prec: 0,
+                mode: 0,
+                acc: 0,
+                neg: false,
+                form: FloatForm::Zero,
+            });
+        }
+        if buf.len() < 6 {
+            return Err("Float.GobDecode: buffer too small".to_owned());
+        }
+        if buf[0] != FLOAT_VERSION {
+            return Err(format!(
+                "Float.GobDecode: encoding version {} not supported",
+                buf[0]
+            ));
+        }
+        let form = match (buf[1] >> 1) & 3 {
+            0 => FloatForm::Zero,
+            1 => {
+                if buf.len() < 10 {
+                    return Err("Float.GobDecode: buffer too small".to_owned());
+                }
+                FloatForm::Finite {
+                    exp: i32::from_be_bytes([buf[6], buf[7], buf[8], buf[9]]),
+                    mant: buf[10..].to_vec(),
+                }
+            }
+            2 => FloatForm::Inf,
+            form => return Err(format!("Float.GobDecode: invalid form {}", form)),
+        };
+        Ok(BigFloat {
+            prec: u32::from_be_bytes([buf[2], buf[3], buf[4], buf[5]]),
+            mode: (buf[1] >> 5) & 7,
+            acc: ((buf[1] >> 3) & 3) as i8 - 1,
+            neg: buf[1] & 1 != 0,
+            form,
+        })
+    }
+}
+
+// Go leaves out leading zeros, down to no bytes at all for zero
+fn magnitude_bytes(magnitude: &BigUint) -> Vec<u8> {
+    if magnitude.is_zero() {
+        Vec::new()
+    } else {
+        magnitude.to_bytes_be()
+    }
+}
+
+fn signed(neg: bool, magnitude: BigUint) -> BigInt {
+    let sign = if neg { Sign::Minus } else { Sign::Plus };
+    BigInt::from_biguint(sign, magnitude)
+}
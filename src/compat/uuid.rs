@@ -0,0 +1,120 @@
+//! `Uuid`s in the ways Go programs commonly send them.
+
+use std::fmt;
+
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeTuple, Serializer};
+use serde_schema::types::{Type, TypeId};
+use serde_schema::{Schema, SchemaSerialize};
+use uuid::Uuid;
+
+use super::{ExternalEncoding, GobDecode, GobEncode};
+
+/// As `github.com/google/uuid.UUID` encodes itself, i.e. `Gob<Uuid>` sends
+/// and receives values of that type.
+impl GobEncode for Uuid {
+    const GOB_NAME: &'static str = "UUID";
+    const ENCODING: ExternalEncoding = ExternalEncoding::Binary;
+
+    type Error = uuid::Error;
+
+    fn gob_encode(&self) -> Result<Vec<u8>, uuid::Error> {
+        Ok(self.as_bytes().to_vec())
+    }
+}
+
+/// Takes the 16 bytes of a `BinaryMarshaler` as well as the text of a
+/// `TextMarshaler`.
+impl GobDecode for Uuid {
+    type Error = uuid::Error;
+
+    fn gob_decode(bytes: &[u8]) -> Result<Uuid, uuid::Error> {
+        match bytes.len() {
+            16 => Uuid::from_slice(bytes),
+            _ => Uuid::try_parse_ascii(bytes),
+        }
+    }
+}
+
+/// A `Uuid` sent as a Go `[16]byte`.
+///
+/// Decodes from any of the representations in this module.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UuidBytes(pub Uuid);
+
+impl Serialize for UuidBytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(16)?;
+        for byte in self.0.as_bytes() {
+            tuple.serialize_element(byte)?;
+        }
+        tuple.end()
+    }
+}
+
+impl SchemaSerialize for UuidBytes {
+    fn schema_register<S: Schema>(schema: &mut S) -> Result<S::TypeId, S::Error> {
+        schema.register_type(Type::build().seq_type(Some(16), S::TypeId::U8))
+    }
+}
+
+impl<'de> Deserialize<'de> for UuidBytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(UuidVisitor).map(UuidBytes)
+    }
+}
+
+/// A `Uuid` sent as a Go `string` in its hyphenated form.
+///
+/// Decodes from any of the representations in this module.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UuidString(pub Uuid);
+
+impl Serialize for UuidString {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0.hyphenated().encode_lower(&mut Uuid::encode_buffer()))
+    }
+}
+
+impl SchemaSerialize for UuidString {
+    fn schema_register<S: Schema>(schema: &mut S) -> Result<S::TypeId, S::Error> {
+        String::schema_register(schema)
+    }
+}
+
+impl<'de> Deserialize<'de> for UuidString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(UuidVisitor).map(UuidString)
+    }
+}
+
+struct UuidVisitor;
+
+impl<'de> Visitor<'de> for UuidVisitor {
+    type Value = Uuid;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a UUID as 16 bytes or a string")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Uuid, E> {
+        Uuid::try_parse(v).map_err(E::custom)
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Uuid, E> {
+        Uuid::gob_decode(v).map_err(E::custom)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Uuid, A::Error> {
+        let mut bytes = [0; 16];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(i, &self))?;
+        }
+        if seq.next_element::<u8>()?.is_some() {
+            return Err(de::Error::invalid_length(17, &self));
+        }
+        Ok(Uuid::from_bytes(bytes))
+    }
+}
@@ -0,0 +1,92 @@
+//! Compressed streams
+//!
+//! Go services often wrap their `gob.Encoder` in a gzip or snappy writer.
+//! With the `gzip` and `snappy` features, stream serializers and
+//! deserializers can be layered over such streams directly:
+//!
+//! ```ignore
+//! let mut stream = gob::StreamSerializer::new_gzip(file);
+//! stream.serialize(&value)?;
+//! let file = stream.finish()?;
+//!
+//! let mut stream = gob::StreamDeserializer::new_gzip(File::open(path)?);
+//! while let Some(value) = stream.deserialize::<Value>()? {
+//!     // ...
+//! }
+//! ```
+//!
+//! Deserializers read decompressed input in chunks into their own buffer,
+//! and the decompressors buffer the compressed input, so the reader needs no
+//! `BufReader` of its own. Compressors buffer their output and are only
+//! complete once `finish` was called.
+
+use std::io::{Read, Write};
+
+#[cfg(feature = "gzip")]
+pub use flate2::Compression;
+#[cfg(feature = "gzip")]
+use flate2::{read::MultiGzDecoder, write::GzEncoder};
+#[cfg(feature = "snappy")]
+use snap::{read::FrameDecoder, write::FrameEncoder};
+
+use de::StreamDeserializer;
+use error::Error;
+use ser::{OutputWrite, StreamSerializer};
+
+use crate::{de, error, ser};
+
+#[cfg(feature = "gzip")]
+impl<R: Read> StreamDeserializer<MultiGzDecoder<R>> {
+    /// Create a new stream deserializer reading gzip compressed input, like
+    /// Go's `gob.NewDecoder(gzip.NewReader(r))`.
+    ///
+    /// Concatenated gzip members are read as one stream, as Go does.
+    pub fn new_gzip(read: R) -> Self {
+        StreamDeserializer::new(MultiGzDecoder::new(read))
+    }
+}
+
+#[cfg(feature = "gzip")]
+impl<W: Write> StreamSerializer<OutputWrite<GzEncoder<W>>> {
+    /// Create a new stream serializer writing gzip compressed output, like
+    /// Go's `gob.NewEncoder(gzip.NewWriter(w))`.
+    pub fn new_gzip(w: W) -> Self {
+        StreamSerializer::new_gzip_with_level(w, Compression::default())
+    }
+
+    pub fn new_gzip_with_level(w: W, level: Compression) -> Self {
+        StreamSerializer::new(GzEncoder::new(w, level))
+    }
+
+    /// Writes out the rest of the compressed stream and unwraps the
+    /// underlying writer.
+    pub fn finish(self) -> Result<W, Error> {
+        Ok(self.into_writer().finish()?)
+    }
+}
+
+#[cfg(feature = "snappy")]
+impl<R: Read> StreamDeserializer<FrameDecoder<R>> {
+    /// Create a new stream deserializer reading input compressed in the
+    /// snappy framing format, like Go's `snappy.NewReader`.
+    pub fn new_snappy(read: R) -> Self {
+        StreamDeserializer::new(FrameDecoder::new(read))
+    }
+}
+
+#[cfg(feature = "snappy")]
+impl<W: Write> StreamSerializer<OutputWrite<FrameEncoder<W>>> {
+    /// Create a new stream serializer writing output compressed in the
+    /// snappy framing format, like Go's `snappy.NewBufferedWriter`.
+    pub fn new_snappy(w: W) -> Self {
+        StreamSerializer::new(FrameEncoder::new(w))
+    }
+
+    /// Writes out the rest of the compressed stream and unwraps the
+    /// underlying writer.
+    pub fn finish(self) -> Result<W, Error> {
+        self.into_writer()
+            .into_inner()
+            .map_err(|err| err.into_error().into())
+    }
+}
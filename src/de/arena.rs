@@ -0,0 +1,216 @@
+use std::fmt;
+use std::str;
+
+use bumpalo::collections::Vec as BumpVec;
+use bumpalo::Bump;
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Unexpected, Visitor};
+
+use value::Value;
+
+use crate::value;
+
+/// Decodes a string into an arena instead of its own heap allocation.
+///
+/// Meant for the `DeserializeSeed` of records whose string fields live
+/// only as long as the arena, e.g. while aggregating a batch of records
+/// which is then dropped as a whole.
+#[derive(Clone, Copy)]
+pub struct ArenaStr<'b>(pub &'b Bump);
+
+impl<'de, 'b> DeserializeSeed<'de> for ArenaStr<'b> {
+    type Value = &'b str;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<&'b str, D::Error> {
+        deserializer.deserialize_str(self)
+    }
+}
+
+impl<'de, 'b> Visitor<'de> for ArenaStr<'b> {
+    type Value = &'b str;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a string")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<&'b str, E> {
+        Ok(self.0.alloc_str(v))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<&'b str, E> {
+        match str::from_utf8(v) {
+            Ok(v) => Ok(self.0.alloc_str(v)),
+            Err(_) => Err(E::invalid_value(Unexpected::Bytes(v), &self)),
+        }
+    }
+}
+
+/// Decodes a byte slice into an arena, see `ArenaStr`.
+#[derive(Clone, Copy)]
+pub struct ArenaBytes<'b>(pub &'b Bump);
+
+impl<'de, 'b> DeserializeSeed<'de> for ArenaBytes<'b> {
+    type Value = &'b [u8];
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<&'b [u8], D::Error> {
+        deserializer.deserialize_bytes(self)
+    }
+}
+
+impl<'de, 'b> Visitor<'de> for ArenaBytes<'b> {
+    type Value = &'b [u8];
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a byte slice")
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<&'b [u8], E> {
+        Ok(self.0.alloc_slice_copy(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<&'b [u8], E> {
+        Ok(self.0.alloc_slice_copy(v.as_bytes()))
+    }
+}
+
+/// A decoded value living in an arena, the counterpart of `Value`.
+///
+/// Decoded through `InArena`, a value takes no heap allocations of its
+/// own: its strings, byte slices, sequences and maps are all allocated in
+/// the arena, and freed along with it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArenaValue<'b> {
+    Nil,
+    Bool(bool),
+    Int(i64),
+    Uint(u64),
+    Float(f64),
+    Bytes(&'b [u8]),
+    String(&'b str),
+    Seq(&'b [ArenaValue<'b>]),
+    Map(&'b [(ArenaValue<'b>, ArenaValue<'b>)]),
+}
+
+impl<'b> ArenaValue<'b> {
+    /// Copies the value out of the arena.
+    pub fn to_value(&self) -> Value {
+        match *self {
+            ArenaValue::Nil => Value::Nil,
+            ArenaValue::Bool(v) => Value::Bool(v),
+            ArenaValue::Int(v) => Value::Int(v),
+            ArenaValue::Uint(v) => Value::Uint(v),
+            ArenaValue::Float(v) => Value::Float(v),
+            ArenaValue::Bytes(v) => Value::Bytes(v.to_owned()),
+            ArenaValue::String(v) => Value::String(v.to_owned()),
+            ArenaValue::Seq(items) => Value::Seq(items.iter().map(ArenaValue::to_value).collect()),
+            ArenaValue::Map(entries) => Value::Map(
+                entries
+                    .iter()
+                    .map(|(key, value)| (key.to_value(), value.to_value()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Decodes any value into an `ArenaValue` allocated in the given arena:
+///
+/// ```
+/// # extern crate bumpalo;
+/// # extern crate gob;
+/// # use gob::de::InArena;
+/// # use gob::{StreamDeserializer, StreamSerializer};
+/// # fn main() {
+/// # let mut stream = StreamSerializer::new(Vec::new());
+/// # stream.serialize(&"hello").unwrap();
+/// # let input = stream.into_writer();
+/// let arena = bumpalo::Bump::new();
+/// let mut stream = StreamDeserializer::new(&input[..]);
+/// while let Some(value) = stream.deserialize_seed(InArena(&arena)).unwrap() {
+///     println!("{:?}", value);
+/// }
+/// # }
+/// ```
+#[derive(Clone, Copy)]
+pub struct InArena<'b>(pub &'b Bump);
+
+impl<'de, 'b> DeserializeSeed<'de> for InArena<'b> {
+    type Value = ArenaValue<'b>;
+
+    fn deserialize<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<ArenaValue<'b>, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de, 'b> Visitor<'de> for InArena<'b> {
+    type Value = ArenaValue<'b>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any gob value")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<ArenaValue<'b>, E> {
+        Ok(ArenaValue::Bool(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<ArenaValue<'b>, E> {
+        Ok(ArenaValue::Int(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<ArenaValue<'b>, E> {
+        Ok(ArenaValue::Uint(v))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<ArenaValue<'b>, E> {
+        Ok(ArenaValue::Float(v))
+    }
+
+    fn visit_char<E: de::Error>(self, v: char) -> Result<ArenaValue<'b>, E> {
+        Ok(ArenaValue::Int(v as i64))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<ArenaValue<'b>, E> {
+        Ok(ArenaValue::String(self.0.alloc_str(v)))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<ArenaValue<'b>, E> {
+        Ok(ArenaValue::Bytes(self.0.alloc_slice_copy(v)))
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<ArenaValue<'b>, E> {
+        Ok(ArenaValue::Nil)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<ArenaValue<'b>, E> {
+        Ok(ArenaValue::Nil)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<ArenaValue<'b>, D::Error> {
+        self.deserialize(deserializer)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<ArenaValue<'b>, D::Error> {
+        self.deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut access: A) -> Result<ArenaValue<'b>, A::Error> {
+        let mut items = BumpVec::with_capacity_in(access.size_hint().unwrap_or(0), self.0);
+        while let Some(item) = access.next_element_seed(self)? {
+            items.push(item);
+        }
+        Ok(ArenaValue::Seq(items.into_bump_slice()))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<ArenaValue<'b>, A::Error> {
+        let mut entries = BumpVec::with_capacity_in(access.size_hint().unwrap_or(0), self.0);
+        while let Some(entry) = access.next_entry_seed(self, self)? {
+            entries.push(entry);
+        }
+        Ok(ArenaValue::Map(entries.into_bump_slice()))
+    }
+}
@@ -0,0 +1,245 @@
+use std::cmp;
+use std::io::{self, Cursor, Read};
+
+use bytes::Buf;
+use serde::de::DeserializeOwned;
+
+use error::Error;
+use internal::de::skip_field_value;
+use internal::gob::Message;
+use internal::types::{TypeId, WireType};
+use internal::utils::Bow;
+
+use crate::{error, internal};
+
+use super::{Deserializer, StreamDeserializer};
+
+/// A byte slice or string field that is read straight off the underlying
+/// reader, rather than buffered along with the rest of its value.
+///
+/// Returned by `StreamDeserializer::deserialize_with_blob`. Whatever is not
+/// read of it is skipped once the stream is used again.
+pub struct Blob<'a, R> {
+    stream: &'a mut StreamDeserializer<R>,
+    len: u64,
+}
+
+impl<'a, R> Blob<'a, R> {
+    /// Length of the whole field, in bytes.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of bytes not read yet.
+    pub fn remaining(&self) -> u64 {
+        self.stream.blob_rest.saturating_sub(1)
+    }
+}
+
+impl<'a, R: Read> Read for Blob<'a, R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let stream = &mut *self.stream;
+        if stream.blob_rest <= 1 {
+            stream.finish_blob()?;
+            return Ok(0);
+        }
+        let want = cmp::min(out.len() as u64, stream.blob_rest - 1) as usize;
+        let n = if stream.buffer.len() > 0 {
+            let n = cmp::min(want, stream.buffer.len());
+            out[..n].copy_from_slice(&stream.buffer.bytes()[..n]);
+            stream.buffer.advance(n);
+            n
+        } else {
+            stream.stream.read(&mut out[..want])?
+        };
+        if n == 0 && want > 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        stream.blob_rest -= n as u64;
+        if stream.blob_rest == 1 {
+            stream.finish_blob()?;
+        }
+        Ok(n)
+    }
+}
+
+impl<R: Read> StreamDeserializer<R> {
+    /// Reads the next value, a struct whose last field is too large to be
+    /// buffered, like a file sent along with a header describing it.
+    ///
+    /// The field named `field` must hold a byte slice or a string. All other
+    /// fields are decoded into `T`, while the contents of `field` are left to
+    /// be read from the returned `Blob`:
+    ///
+    /// ```ignore
+    /// while let Some((header, mut blob)) = stream.deserialize_with_blob::<Header>("Data")? {
+    ///     let mut file = File::create(&header.name)?;
+    ///     io::copy(&mut blob, &mut file)?;
+    /// }
+    /// ```
+    ///
+    /// Go sends struct fields in the order they are declared, so this only
+    /// works for values where no field after `field` is set. If `field` is
+    /// not set at all, the returned `Blob` is empty.
+    pub fn deserialize_with_blob<T>(
+        &mut self,
+        field: &str,
+    ) -> Result<Option<(T, Blob<'_, R>)>, Error>
+    where
+        T: DeserializeOwned,
+    {
+        let start = match self.peeked.take() {
+            // already read as a whole, and counted in the progress
+            Some(header) => (header.type_id, header.payload_range),
+            None => match self.next_value_start()? {
+                Some(start) => {
+                    if let Some(ref mut progress) = self.progress {
+                        progress.message(start.end, start.type_id);
+                    }
+                    (start.type_id, start.payload_offset..start.end)
+                }
+                None => return Ok(None),
+            },
+        };
+        let (type_id, section) = start;
+        let def = match self.defs.lookup(TypeId(type_id)) {
+            Some(WireType::Struct(def)) => def,
+            _ => {
+                return Err(Error::deserialize(format!(
+                    "type id {} is not a struct",
+                    type_id
+                )))
+            }
+        };
+
+        // walks the fields up to `field`, reading more input as needed
+        let mut pos = section.start;
+        let mut field_no = -1i64;
+        let blob = loop {
+            let available = cmp::min(self.buffer.len(), section.end);
+            let mut msg = Message::new(Cursor::new(&self.buffer.bytes()[pos..available]));
+            let step = msg.read_uint().map_err(Error::from).and_then(|delta| {
+                if delta == 0 {
+                    return Ok(None);
+                }
+                let field_no = field_no + delta as i64;
+                let def_field = def.fields.get(field_no as usize).ok_or_else(|| {
+                    Error::deserialize(format!(
+                        "field number overflow ({}) on type {:?}",
+                        field_no, def
+                    ))
+                })?;
+                if def_field.name == field {
+                    if def_field.id != TypeId::BYTES && def_field.id != TypeId::STRING {
+                        return Err(Error::deserialize(format!(
+                            "field {} is neither a byte slice nor a string",
+                            field
+                        )));
+                    }
+                    let len = msg.read_uint()?;
+                    return Ok(Some((field_no, Some(len))));
+                }
+                skip_field_value(def_field.id, &self.defs, &mut msg)?;
+                Ok(Some((field_no, None)))
+            });
+            let read = msg.get_ref().position() as usize;
+            match step {
+                Ok(None) => break None,
+                Ok(Some((_, Some(len)))) => break Some((pos, pos + read, len)),
+                Ok(Some((no, None))) => {
+                    field_no = no;
+                    pos += read;
+                }
+                // most likely a field that is not all there yet
+                Err(_) if available < section.end => {
+                    if self.buffer.read_from(&mut self.stream)? == 0 {
+                        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+                    }
+                }
+                Err(err) => return Err(err),
+            }
+        };
+
+        // the value without `field`, with the struct ending in its place
+        let mut rest = Vec::new();
+        let len = match blob {
+            Some((field_start, blob_start, len)) => {
+                if blob_start as u64 + len + 1 != section.end as u64 {
+                    return Err(Error::deserialize(format!(
+                        "field {} is not the last field sent",
+                        field
+                    )));
+                }
+                rest.extend_from_slice(&self.buffer.bytes()[section.start..field_start]);
+                rest.push(0);
+                self.buffer.advance(blob_start);
+                self.blob_rest = len + 1;
+                len
+            }
+            None => {
+                while self.buffer.len() < section.end {
+                    if self.buffer.read_from(&mut self.stream)? == 0 {
+                        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+                    }
+                }
+                rest.extend_from_slice(&self.buffer.bytes()[section.clone()]);
+                self.buffer.advance(section.end);
+                0
+            }
+        };
+
+        let value = T::deserialize(Deserializer {
+            defs: Bow::Borrowed(&mut self.defs),
+            msg: Message::new(Cursor::new(&rest)),
+            type_id: Some(TypeId(type_id)),
+            end: Some(rest.len()),
+            opts: self.opts,
+            projection: None,
+            skipped: None,
+        })?;
+        Ok(Some((value, Blob { stream: self, len })))
+    }
+
+    // reads the byte ending the struct a blob was the last field of
+    fn finish_blob(&mut self) -> io::Result<()> {
+        if self.blob_rest == 0 {
+            return Ok(());
+        }
+        if self.buffer.len() == 0 && self.buffer.read_from(&mut self.stream)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let end = self.buffer.bytes()[0];
+        self.buffer.advance(1);
+        self.blob_rest = 0;
+        if end != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "struct continues after blob",
+            ));
+        }
+        Ok(())
+    }
+
+    // skips whatever was not read of the last blob handed out
+    pub(super) fn discard_blob(&mut self) -> Result<(), Error> {
+        while self.blob_rest > 0 {
+            if self.buffer.len() == 0 {
+                let mut reader = (&mut self.stream).take(self.blob_rest);
+                let skipped = io::copy(&mut reader, &mut io::sink())?;
+                if skipped < self.blob_rest {
+                    return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+                }
+                self.blob_rest = 0;
+            } else {
+                let n = cmp::min(self.buffer.len() as u64, self.blob_rest) as usize;
+                self.buffer.advance(n);
+                self.blob_rest -= n as u64;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,212 @@
+use internal::utils::Bow;
+use mode::Mode;
+use schema::OptionEncoding;
+
+use super::{
+    DecodeHooks, DecodeOptions, Deserializer, EnumDecoding, NumericCoercion, PointerNames,
+    SliceDeserializers, SliceStreamDeserializer, StreamDeserializer, TypeDefs,
+};
+use crate::{internal, mode, schema};
+
+/// Collects the options of a deserializer in one place, then creates any
+/// number of deserializers configured alike.
+///
+/// ```
+/// # use gob::{de::{Builder, NumericCoercion}, Mode};
+/// # let input: &[u8] = &[];
+/// let builder = Builder::new()
+///     .mode(Mode::Strict)
+///     .numeric_coercion(NumericCoercion::Saturating)
+///     .read_chunk_size(64 * 1024);
+/// let mut stream = builder.stream(input);
+/// # let _ = stream.deserialize::<u64>();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Builder {
+    opts: DecodeOptions,
+    defs: Option<TypeDefs>,
+    hooks: DecodeHooks,
+    buffer_capacity: usize,
+    chunk_size: Option<usize>,
+    resync: bool,
+}
+
+impl Builder {
+    pub fn new() -> Builder {
+        Builder::default()
+    }
+
+    /// Sets all options according to `mode`, replacing any set before.
+    pub fn mode(self, mode: Mode) -> Self {
+        self.options(mode.into())
+    }
+
+    /// Sets all options at once, replacing any set before.
+    pub fn options(mut self, options: DecodeOptions) -> Self {
+        self.opts = options;
+        self
+    }
+
+    /// See `DecodeOptions::reject_trailing_data`.
+    pub fn reject_trailing_data(mut self, reject: bool) -> Self {
+        self.opts = self.opts.reject_trailing_data(reject);
+        self
+    }
+
+    /// See `DecodeOptions::reject_redefinitions`.
+    pub fn reject_redefinitions(mut self, reject: bool) -> Self {
+        self.opts = self.opts.reject_redefinitions(reject);
+        self
+    }
+
+    /// See `DecodeOptions::lossy_utf8`.
+    pub fn lossy_utf8(mut self, lossy: bool) -> Self {
+        self.opts = self.opts.lossy_utf8(lossy);
+        self
+    }
+
+    /// See `DecodeOptions::string_bytes_interchange`.
+    pub fn string_bytes_interchange(mut self, interchange: bool) -> Self {
+        self.opts = self.opts.string_bytes_interchange(interchange);
+        self
+    }
+
+    /// See `DecodeOptions::bytes_as_seq`.
+    pub fn bytes_as_seq(mut self, as_seq: bool) -> Self {
+        self.opts = self.opts.bytes_as_seq(as_seq);
+        self
+    }
+
+    /// See `DecodeOptions::flatten_embedded`.
+    pub fn flatten_embedded(mut self, flatten: bool) -> Self {
+        self.opts = self.opts.flatten_embedded(flatten);
+        self
+    }
+
+    /// See `DecodeOptions::field_indices`.
+    pub fn field_indices(mut self, indices: bool) -> Self {
+        self.opts = self.opts.field_indices(indices);
+        self
+    }
+
+    /// See `DecodeOptions::pointer_names`.
+    pub fn pointer_names(mut self, names: PointerNames) -> Self {
+        self.opts = self.opts.pointer_names(names);
+        self
+    }
+
+    /// See `DecodeOptions::option_encoding`.
+    pub fn option_encoding(mut self, options: OptionEncoding) -> Self {
+        self.opts = self.opts.option_encoding(options);
+        self
+    }
+
+    /// See `DecodeOptions::enum_decoding`.
+    pub fn enum_decoding(mut self, enums: EnumDecoding) -> Self {
+        self.opts = self.opts.enum_decoding(enums);
+        self
+    }
+
+    /// See `DecodeOptions::numeric_coercion`.
+    pub fn numeric_coercion(mut self, coercion: NumericCoercion) -> Self {
+        self.opts = self.opts.numeric_coercion(coercion);
+        self
+    }
+
+    /// See `DecodeOptions::exact_f32`.
+    pub fn exact_f32(mut self, exact: bool) -> Self {
+        self.opts = self.opts.exact_f32(exact);
+        self
+    }
+
+    /// See `DecodeOptions::non_finite_as_none`.
+    pub fn non_finite_as_none(mut self, as_none: bool) -> Self {
+        self.opts = self.opts.non_finite_as_none(as_none);
+        self
+    }
+
+    /// Starts every deserializer with the types in `defs` defined.
+    pub fn type_defs(mut self, defs: TypeDefs) -> Self {
+        self.defs = Some(defs);
+        self
+    }
+
+    /// Decodes values of the types in `hooks` through their hooks. Only
+    /// applies to deserializers created by `stream` and `slice`.
+    pub fn decode_hooks(mut self, hooks: DecodeHooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Reserves `capacity` bytes for buffering input up front. Only applies
+    /// to stream deserializers.
+    pub fn buffer_capacity(mut self, capacity: usize) -> Self {
+        self.buffer_capacity = capacity;
+        self
+    }
+
+    /// See `StreamDeserializer::set_read_chunk_size`. Only applies to
+    /// stream deserializers.
+    pub fn read_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = Some(chunk_size);
+        self
+    }
+
+    /// See `StreamDeserializer::set_resync`. Only applies to stream
+    /// deserializers.
+    pub fn resync(mut self, resync: bool) -> Self {
+        self.resync = resync;
+        self
+    }
+
+    /// Returns the options collected so far.
+    pub fn decode_options(&self) -> DecodeOptions {
+        self.opts
+    }
+
+    /// Creates a deserializer for the stream of values read from `read`.
+    pub fn stream<R>(&self, read: R) -> StreamDeserializer<R> {
+        let mut de =
+            StreamDeserializer::with_buffer(read, Vec::with_capacity(self.buffer_capacity));
+        if let Some(ref defs) = self.defs {
+            de.defs = defs.types.clone();
+        }
+        if let Some(chunk_size) = self.chunk_size {
+            de.set_read_chunk_size(chunk_size);
+        }
+        de.set_resync(self.resync);
+        de.set_options(self.opts);
+        de.set_decode_hooks(self.hooks.clone());
+        de
+    }
+
+    /// Creates a deserializer for the stream of values in `source`.
+    pub fn slice_stream<B: AsRef<[u8]>>(&self, source: B) -> SliceStreamDeserializer<B> {
+        let mut de = match self.defs {
+            Some(ref defs) => SliceStreamDeserializer::with_type_defs(source, defs.clone()),
+            None => SliceStreamDeserializer::new(source),
+        };
+        de.set_options(self.opts);
+        de
+    }
+
+    /// Creates a deserializer for the first value in `input`, like
+    /// `Deserializer::from_slice`.
+    pub fn slice<'de>(&self, input: &'de [u8]) -> Deserializer<'de> {
+        let mut de = Deserializer::from_slice(input).with_options(self.opts);
+        if let Some(ref defs) = self.defs {
+            de.defs = Bow::Owned(defs.types.clone());
+        }
+        de.with_decode_hooks(self.hooks.clone())
+    }
+
+    /// Creates an iterator over a deserializer for each value in `input`,
+    /// like `Deserializer::from_slice_many`.
+    pub fn slice_many<'de>(&self, input: &'de [u8]) -> SliceDeserializers<'de> {
+        let de = match self.defs {
+            Some(ref defs) => Deserializer::from_slice_many_with_type_defs(input, defs.clone()),
+            None => Deserializer::from_slice_many(input),
+        };
+        de.with_options(self.opts)
+    }
+}
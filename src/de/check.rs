@@ -0,0 +1,239 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use serde_schema::types::Type;
+
+use de::{DecodeOptions, NumericCoercion, TypeInfo};
+use internal::types::{TypeId, Types, WireType};
+use schema::{external_encoding, Schema};
+use ser::go_type_name;
+
+use crate::{de, internal, schema, ser};
+
+/// How a type sent on the wire fails to match the type it is decoded into,
+/// as found by `StreamDeserializer::check_type`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CheckReport {
+    mismatches: Vec<Mismatch>,
+}
+
+impl CheckReport {
+    /// Whether values of the wire type decode into the target type.
+    pub fn is_ok(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+
+    pub fn mismatches(&self) -> &[Mismatch] {
+        &self.mismatches
+    }
+}
+
+impl fmt::Display for CheckReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, mismatch) in self.mismatches.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{}", mismatch)?;
+        }
+        Ok(())
+    }
+}
+
+/// A single place where the wire type and the target type disagree.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mismatch {
+    path: String,
+    kind: MismatchKind,
+}
+
+impl Mismatch {
+    /// Where the mismatch is, as `Type` or `Type.field`, named after the
+    /// target type.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn kind(&self) -> &MismatchKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.kind)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MismatchKind {
+    /// The wire type does not decode into the target type; both are given
+    /// as Go type names.
+    Type { wire: String, target: String },
+    /// An array was sent with a different length than the target has.
+    Len { wire: i64, target: usize },
+    /// None of the fields sent are fields of the target struct, which gob
+    /// refuses to decode.
+    NoFieldsInCommon,
+}
+
+impl fmt::Display for MismatchKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MismatchKind::Type { wire, target } => {
+                write!(f, "{} sent, {} expected", wire, target)
+            }
+            MismatchKind::Len { wire, target } => {
+                write!(f, "array of {} sent, {} expected", wire, target)
+            }
+            MismatchKind::NoFieldsInCommon => f.write_str("no fields in common"),
+        }
+    }
+}
+
+// compares the wire type `wire` to the type `target` registered in `schema`,
+// reporting every mismatch instead of stopping at the first one
+pub(crate) fn check(
+    defs: &Types,
+    opts: &DecodeOptions,
+    wire: TypeId,
+    schema: &Schema,
+    target: TypeId,
+) -> CheckReport {
+    let mut checker = Checker {
+        defs,
+        opts,
+        schema,
+        visited: HashSet::new(),
+        mismatches: Vec::new(),
+    };
+    let path = go_type_name(schema, target);
+    checker.check(path, wire, target);
+    CheckReport {
+        mismatches: checker.mismatches,
+    }
+}
+
+struct Checker<'a> {
+    defs: &'a Types,
+    opts: &'a DecodeOptions,
+    schema: &'a Schema,
+    // pairs already being compared, so that recursive types terminate
+    visited: HashSet<(TypeId, TypeId)>,
+    mismatches: Vec<Mismatch>,
+}
+
+impl<'a> Checker<'a> {
+    fn push(&mut self, path: String, kind: MismatchKind) {
+        self.mismatches.push(Mismatch { path, kind });
+    }
+
+    fn type_mismatch(&mut self, path: String, wire: TypeId, target: TypeId) {
+        let kind = MismatchKind::Type {
+            wire: match TypeInfo::lookup(wire, self.defs) {
+                Some(info) => info.name().to_owned(),
+                None => format!("type id {}", wire.0),
+            },
+            target: go_type_name(self.schema, target),
+        };
+        self.push(path, kind);
+    }
+
+    fn check(&mut self, path: String, wire: TypeId, target: TypeId) {
+        // interface values carry their concrete type along, and are checked
+        // as they are decoded
+        if wire == TypeId::INTERFACE || !self.visited.insert((wire, target)) {
+            return;
+        }
+        let target_ty = match self.schema.lookup(target) {
+            Some(target_ty) => target_ty,
+            None => {
+                if !self.builtin_matches(wire, target) {
+                    self.type_mismatch(path, wire, target);
+                }
+                return;
+            }
+        };
+        if external_encoding(&target_ty).is_some() {
+            // types encoding themselves decode from whatever they encode to
+            return;
+        }
+        let wire_ty = self.defs.lookup(wire);
+        match (&*target_ty, wire_ty) {
+            (Type::NewtypeStruct(newtype), _) => self.check(path, wire, *newtype.inner_type()),
+            (Type::Option(option), _) => self.check(path, wire, *option.inner_type()),
+            (Type::Seq(seq), Some(WireType::Slice(slice))) => {
+                self.check(format!("{}[]", path), slice.elem, *seq.element_type())
+            }
+            (Type::Seq(seq), Some(WireType::Array(array))) => {
+                if let Some(len) = seq.len() {
+                    if array.len != len as i64 {
+                        let kind = MismatchKind::Len {
+                            wire: array.len,
+                            target: len,
+                        };
+                        self.push(path.clone(), kind);
+                    }
+                }
+                self.check(format!("{}[]", path), array.elem, *seq.element_type())
+            }
+            (Type::Seq(seq), None) if wire == TypeId::BYTES => {
+                if *seq.element_type() != TypeId::UINT {
+                    self.type_mismatch(path, wire, target);
+                }
+            }
+            (Type::Map(map), Some(WireType::Map(map_type))) => {
+                self.check(format!("{}[key]", path), map_type.key, *map.key_type());
+                self.check(format!("{}[]", path), map_type.elem, *map.value_type());
+            }
+            (Type::Struct(target_struct), Some(WireType::Struct(wire_struct))) => {
+                let mut common = false;
+                for wire_field in wire_struct.fields.iter() {
+                    let target_field = target_struct
+                        .fields()
+                        .iter()
+                        .find(|field| field.name() == wire_field.name);
+                    if let Some(target_field) = target_field {
+                        common = true;
+                        let field_path = format!("{}.{}", path, wire_field.name);
+                        self.check(field_path, wire_field.id, *target_field.field_type());
+                    }
+                }
+                if !common && !wire_struct.fields.is_empty() && !target_struct.fields().is_empty() {
+                    self.push(path, MismatchKind::NoFieldsInCommon);
+                }
+            }
+            // maps decode into structs field by field
+            (Type::Struct(_), Some(WireType::Map(_))) => {}
+            (Type::Tuple(_), Some(WireType::Array(_)))
+            | (Type::Tuple(_), Some(WireType::Slice(_)))
+            | (Type::TupleStruct(_), Some(WireType::Array(_)))
+            | (Type::TupleStruct(_), Some(WireType::Slice(_))) => {}
+            // enums decode from strings, structs and interface values alike,
+            // according to `EnumDecoding`
+            (Type::Enum(_), _) | (Type::UnitStruct(_), _) => {}
+            _ => self.type_mismatch(path, wire, target),
+        }
+    }
+
+    // whether values of `wire` decode into the builtin type `target`
+    fn builtin_matches(&self, wire: TypeId, target: TypeId) -> bool {
+        let is_number = |id: TypeId| matches!(id, TypeId::INT | TypeId::UINT | TypeId::FLOAT);
+        let is_text = |id: TypeId| matches!(id, TypeId::STRING | TypeId::BYTES);
+        let coerced = self.opts.numbers != NumericCoercion::Strict;
+        if wire == target
+            || (coerced && is_number(wire) && is_number(target))
+            || (self.opts.string_bytes && is_text(wire) && is_text(target))
+        {
+            return true;
+        }
+        match (self.defs.lookup(wire), target) {
+            // Go's `[]rune`
+            (Some(WireType::Slice(slice)), TypeId::STRING) => slice.elem == TypeId::INT,
+            (Some(WireType::Slice(slice)), TypeId::BYTES) => slice.elem == TypeId::UINT,
+            (Some(WireType::Array(array)), TypeId::BYTES) => array.elem == TypeId::UINT,
+            (Some(WireType::GobEncoder(_)), TypeId::BYTES) => true,
+            _ => false,
+        }
+    }
+}
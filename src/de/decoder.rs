@@ -0,0 +1,247 @@
+use std::io::{self, Cursor};
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use bytes::Buf;
+use serde::de::{Deserialize, DeserializeSeed};
+
+use error::Error;
+use internal::gob::{parse_section, Message, MessageReadError};
+use internal::types::{TypeId, Types};
+use internal::utils::{Bow, Buffer};
+use mode::Mode;
+
+use crate::{error, internal, mode};
+
+use super::{define, resolve, BufferStats, DecodeOptions, Deserializer, SchemaRegistry, TypeDefs};
+
+/// A push-based decoder that leaves all I/O to the caller.
+///
+/// Bytes are handed over with `feed` as they arrive, in chunks of any size,
+/// and values are taken out with `next_value` once they are complete:
+///
+/// ```no_run
+/// # fn recv() -> Vec<u8> { vec![] }
+/// let mut decoder = gob::de::Decoder::new();
+/// loop {
+///     decoder.feed(&recv());
+///     while let Some(n) = decoder.next_value::<i64>().unwrap() {
+///         println!("{}", n);
+///     }
+/// }
+/// ```
+pub struct Decoder {
+    defs: Types,
+    buffer: Buffer,
+    prev_len: usize,
+    limit: Option<usize>,
+    opts: DecodeOptions,
+}
+
+impl Decoder {
+    pub fn new() -> Decoder {
+        Decoder::from_buffer(Buffer::new())
+    }
+
+    fn from_buffer(buffer: Buffer) -> Decoder {
+        Decoder {
+            defs: Types::new(),
+            buffer,
+            prev_len: 0,
+            limit: None,
+            opts: DecodeOptions::default(),
+        }
+    }
+
+    /// Create a new decoder that knows about all types in `defs` up front.
+    pub fn with_type_defs(defs: TypeDefs) -> Decoder {
+        Decoder {
+            defs: defs.types,
+            ..Decoder::new()
+        }
+    }
+
+    /// Create a new decoder that buffers input in the allocation of
+    /// `buffer`, e.g. one given up by `into_buffer`.
+    pub fn with_buffer(buffer: Vec<u8>) -> Decoder {
+        Decoder::from_buffer(Buffer::from_vec(buffer))
+    }
+
+    /// Takes a snapshot of all types defined so far.
+    pub fn type_defs(&self) -> TypeDefs {
+        TypeDefs {
+            types: self.defs.clone(),
+        }
+    }
+
+    /// Reports on the memory used for buffering input.
+    pub fn buffer_stats(&self) -> BufferStats {
+        self.buffer.stats()
+    }
+
+    /// Gives up the input buffer for reuse by `with_buffer`, discarding any
+    /// input that was fed but not decoded.
+    pub fn into_buffer(self) -> Vec<u8> {
+        self.buffer.into_vec()
+    }
+
+    /// Sets which input is accepted from now on.
+    pub fn set_options(&mut self, options: DecodeOptions) {
+        self.opts = options;
+    }
+
+    /// Sets all options according to `mode`.
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.set_options(mode.into());
+    }
+
+    /// Looks up types not defined on the stream in `registry`, see
+    /// `StreamDeserializer::set_schema_registry`.
+    pub fn set_schema_registry<S>(&mut self, registry: S)
+    where
+        S: SchemaRegistry + 'static,
+    {
+        self.defs.set_registry(Arc::new(registry));
+    }
+
+    /// Caps how many bytes may be fed ahead of decoding, or lifts the cap
+    /// with `None`, the default.
+    ///
+    /// The cap applies to `try_feed`, and must be large enough for the
+    /// largest section on the stream.
+    pub fn set_buffer_limit(&mut self, limit: Option<usize>) {
+        self.limit = limit;
+    }
+
+    pub fn buffer_limit(&self) -> Option<usize> {
+        self.limit
+    }
+
+    /// Appends `bytes` to the input, regardless of the buffer limit.
+    pub fn feed(&mut self, bytes: &[u8]) {
+        self.consume();
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Appends as much of `bytes` as the buffer limit allows, and returns
+    /// how many bytes were taken.
+    ///
+    /// Once the limit is reached, the values fed so far have to be decoded
+    /// before more input is taken: until then, `try_feed` fails with an
+    /// error of kind `WouldBlock`. If a section does not fit within the
+    /// limit at all, it fails with `InvalidData` instead.
+    pub fn try_feed(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.consume();
+        let len = bytes.len().min(self.room()?);
+        self.buffer.extend_from_slice(&bytes[..len]);
+        Ok(len)
+    }
+
+    // how many more bytes the buffer limit allows to be fed
+    pub(crate) fn room(&self) -> io::Result<usize> {
+        let limit = match self.limit {
+            Some(limit) => limit,
+            None => return Ok(usize::MAX),
+        };
+        let buffered = self.buffered();
+        if buffered < limit {
+            Ok(limit - buffered)
+        } else if self.bytes_needed() == Some(0) {
+            Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                "input buffer is full, decode values before feeding more",
+            ))
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "section does not fit within the buffer limit of {} bytes",
+                    limit
+                ),
+            ))
+        }
+    }
+
+    /// Number of bytes fed but not decoded yet.
+    ///
+    /// Anything left over once the input has ended is a truncated message.
+    pub fn buffered(&self) -> usize {
+        self.buffer.len() - self.prev_len
+    }
+
+    /// Number of bytes missing from the next section, so that the next
+    /// `feed` can be sized to complete it.
+    ///
+    /// Returns `Some(0)` if the next section was fed completely, and `None`
+    /// if its length has not been fed yet or it is malformed.
+    pub fn bytes_needed(&self) -> Option<usize> {
+        match parse_section(&self.buffer.bytes()[self.prev_len..]) {
+            Ok(_) => Some(0),
+            Err(MessageReadError::Incomplete(hint)) => hint,
+            Err(_) => None,
+        }
+    }
+
+    /// Decodes the next value, processing any type definitions before it.
+    ///
+    /// Returns `Ok(None)` when the next value has not been fed completely
+    /// yet; call `feed` and try again.
+    pub fn next_value<'de, T>(&'de mut self) -> Result<Option<T>, Error>
+    where
+        T: Deserialize<'de>,
+    {
+        self.next_value_seed(PhantomData)
+    }
+
+    /// Like `next_value`, but driven by a `DeserializeSeed`.
+    pub fn next_value_seed<'de, S>(&'de mut self, seed: S) -> Result<Option<S::Value>, Error>
+    where
+        S: DeserializeSeed<'de>,
+    {
+        self.consume();
+        loop {
+            let header = match parse_section(self.buffer.bytes()) {
+                Ok(header) => header,
+                Err(MessageReadError::Incomplete(_)) => return Ok(None),
+                Err(err) => return Err(err.into()),
+            };
+
+            if header.type_id >= 0 {
+                resolve(&mut self.defs, TypeId(header.type_id))?;
+                self.prev_len = header.payload_range.end;
+                let slice = &self.buffer.bytes()[header.payload_range];
+                let deserializer = Deserializer {
+                    defs: Bow::Borrowed(&mut self.defs),
+                    msg: Message::new(Cursor::new(slice)),
+                    type_id: Some(TypeId(header.type_id)),
+                    end: Some(slice.len()),
+                    opts: self.opts,
+                    projection: None,
+                    skipped: None,
+                };
+                return seed.deserialize(deserializer).map(Some);
+            }
+
+            {
+                let slice = &self.buffer.bytes()[header.payload_range.clone()];
+                let mut msg = Message::new(Cursor::new(slice));
+                define(&mut self.defs, &self.opts, header.type_id, &mut msg)?;
+            }
+            self.buffer.advance(header.payload_range.end);
+        }
+    }
+
+    // drops the value handed out by the last call to `next_value`
+    fn consume(&mut self) {
+        if self.prev_len > 0 {
+            self.buffer.advance(self.prev_len);
+            self.prev_len = 0;
+        }
+    }
+}
+
+impl Default for Decoder {
+    fn default() -> Decoder {
+        Decoder::new()
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use std::io::Read;
+
+use error::Error;
+
+use crate::error;
+
+use super::{Deserializer, StreamDeserializer, TypeInfo};
+
+type Handler<'a, T> = Box<dyn for<'de> FnMut(Deserializer<'de>) -> Result<T, Error> + 'a>;
+type Fallback<'a, T> =
+    Box<dyn for<'de> FnMut(&TypeInfo, Deserializer<'de>) -> Result<T, Error> + 'a>;
+
+/// Routes the values of a stream to handlers registered by type name.
+///
+/// ```ignore
+/// let mut dispatcher = Dispatcher::new()
+///     .on("main.OrderCreated", |de| OrderCreated::deserialize(de).map(Event::Created))
+///     .on("main.OrderShipped", |de| OrderShipped::deserialize(de).map(Event::Shipped));
+/// while let Some(event) = dispatcher.dispatch(&mut stream)? {
+///     // ...
+/// }
+/// ```
+///
+/// Go only sends the bare name of a type along with its definition, so the
+/// package qualifier and any leading `*` are dropped from the names handlers
+/// are registered under.
+pub struct Dispatcher<'a, T> {
+    handlers: HashMap<String, Handler<'a, T>>,
+    fallback: Option<Fallback<'a, T>>,
+}
+
+impl<'a, T> Dispatcher<'a, T> {
+    pub fn new() -> Self {
+        Dispatcher {
+            handlers: HashMap::new(),
+            fallback: None,
+        }
+    }
+
+    /// Registers the handler for values of the type named `name`, replacing
+    /// any handler registered under the same name before.
+    pub fn on<F>(mut self, name: &str, handler: F) -> Self
+    where
+        F: for<'de> FnMut(Deserializer<'de>) -> Result<T, Error> + 'a,
+    {
+        self.handlers
+            .insert(bare_name(name).to_owned(), Box::new(handler));
+        self
+    }
+
+    /// Registers the handler for values of all types without a handler of
+    /// their own.
+    pub fn otherwise<F>(mut self, handler: F) -> Self
+    where
+        F: for<'de> FnMut(&TypeInfo, Deserializer<'de>) -> Result<T, Error> + 'a,
+    {
+        self.fallback = Some(Box::new(handler));
+        self
+    }
+
+    /// Reads the next value off `stream` and passes it to its handler,
+    /// returning `None` at the end of the stream.
+    ///
+    /// Values that no handler is registered for are an error, unless there
+    /// is a fallback. Either way the value is consumed, so dispatching can
+    /// carry on with the one after it.
+    pub fn dispatch<R: Read>(
+        &mut self,
+        stream: &mut StreamDeserializer<R>,
+    ) -> Result<Option<T>, Error> {
+        let info = match stream.peek_type()? {
+            Some(info) => info,
+            None => return Ok(None),
+        };
+        let de = match stream.deserializer()? {
+            Some(de) => de,
+            None => return Ok(None),
+        };
+        if let Some(handler) = self.handlers.get_mut(bare_name(info.name())) {
+            return handler(de).map(Some);
+        }
+        match self.fallback {
+            Some(ref mut fallback) => fallback(&info, de).map(Some),
+            None => Err(Error::deserialize(format!(
+                "no handler for type {:?}",
+                info.name()
+            ))),
+        }
+    }
+}
+
+impl<'a, T> Default for Dispatcher<'a, T> {
+    fn default() -> Self {
+        Dispatcher::new()
+    }
+}
+
+// `*main.T` -> `T`, leaving composite names like `[]main.T` alone
+fn bare_name(name: &str) -> &str {
+    let name = name.trim_start_matches('*');
+    match name.rfind('.') {
+        Some(pos)
+            if name[..pos]
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '_' || c == '.' || c == '/') =>
+        {
+            &name[pos + 1..]
+        }
+        _ => name,
+    }
+}
@@ -0,0 +1,70 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+use error::Error;
+use value::Value;
+
+use crate::{error, value};
+
+pub(crate) type Hook = dyn Fn(Value) -> Result<Value, Error> + Send + Sync;
+
+/// Replaces how values of particular Go types are decoded.
+///
+/// A hook is registered for a Go type name, and gets each value of that
+/// type as a `Value`. Whatever it returns is decoded in its place, into
+/// whichever Rust type the value was meant for:
+///
+/// ```
+/// # use gob::{de::DecodeHooks, Value};
+/// // legacy `Money` structs carry cents as a string
+/// let hooks = DecodeHooks::new().on("Money", |value| match value {
+///     Value::Map(mut fields) => {
+///         for field in &mut fields {
+///             if let Value::String(ref cents) = field.1 {
+///                 field.1 = Value::Int(cents.parse().unwrap_or(0));
+///             }
+///         }
+///         Ok(Value::Map(fields))
+///     }
+///     value => Ok(value),
+/// });
+/// # let _ = hooks;
+/// ```
+///
+/// Only structs, maps, slices, arrays and types encoding themselves have
+/// names on the wire; values of other types can't be hooked.
+#[derive(Clone, Default)]
+pub struct DecodeHooks {
+    hooks: BTreeMap<String, Arc<Hook>>,
+}
+
+impl DecodeHooks {
+    pub fn new() -> DecodeHooks {
+        DecodeHooks::default()
+    }
+
+    /// Registers `hook` for values of the Go type named `type_name`,
+    /// replacing any registered before.
+    pub fn on<F>(mut self, type_name: &str, hook: F) -> Self
+    where
+        F: Fn(Value) -> Result<Value, Error> + Send + Sync + 'static,
+    {
+        self.hooks.insert(type_name.to_owned(), Arc::new(hook));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    pub(crate) fn get(&self, type_name: &str) -> Option<&Hook> {
+        self.hooks.get(type_name).map(|hook| &**hook)
+    }
+}
+
+impl fmt::Debug for DecodeHooks {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.hooks.keys()).finish()
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::sync::Arc;
+
+use serde::Deserialize;
+
+use error::Error;
+
+use super::Deserializer;
+use crate::error;
+
+/// A struct field left out of a value decoded by
+/// `Deserializer::deserialize_lossy`, because its value failed to decode.
+#[derive(Debug)]
+pub struct FieldError {
+    path: String,
+    error: Error,
+}
+
+impl FieldError {
+    /// The dotted path to the field, e.g. `Items.0.Price`.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn error(&self) -> &Error {
+        &self.error
+    }
+
+    pub fn into_error(self) -> Error {
+        self.error
+    }
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.error)
+    }
+}
+
+impl<'de> Deserializer<'de> {
+    /// Decodes a value, leaving out struct fields whose values fail to
+    /// decode instead of failing as a whole.
+    ///
+    /// Fields left out look as if they were not sent at all, so `Option`s
+    /// become `None` and fields marked `#[serde(default)]` get their
+    /// default. A struct missing a field that has neither is left out of
+    /// the struct holding it in turn. Only if the value itself fails to
+    /// decode is an error returned; otherwise the value comes with the
+    /// fields that were left out, innermost first.
+    ///
+    /// Every field that fails costs decoding the value once more, as serde
+    /// offers no way to carry on with a struct once one of its fields has
+    /// failed; that suits salvaging archived data rather than hot paths.
+    /// The fields left out so far are only known to this decode. Types
+    /// defined in the input are not added to the definitions this
+    /// deserializer was given.
+    pub fn deserialize_lossy<T>(self) -> Result<(T, Vec<FieldError>), Error>
+    where
+        T: Deserialize<'de>,
+    {
+        let mut skipped = Arc::new(BTreeSet::new());
+        let mut errors = Vec::new();
+        loop {
+            let mut de = self.fork();
+            de.skipped = Some(skipped.clone());
+            let error = match T::deserialize(de) {
+                Ok(value) => return Ok((value, errors)),
+                Err(error) => error,
+            };
+            // the fork is gone along with its reference to the set, so
+            // this adds to the set rather than copying it
+            let path = match error.field() {
+                Some((start, path)) if Arc::make_mut(&mut skipped).insert(start) => path.to_owned(),
+                _ => return Err(error),
+            };
+            errors.push(FieldError { path, error });
+        }
+    }
+}
@@ -1,51 +1,573 @@
 //! Deserialization
 
-use std::io::{Cursor, Read};
+use std::any;
+use std::collections::{BTreeSet, HashMap};
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::time::Instant;
 
 use bytes::Buf;
-use serde::de::{IgnoredAny, Visitor};
+use serde::de::{DeserializeSeed, IgnoredAny, Visitor};
 use serde::{self, Deserialize};
+use serde_schema::SchemaSerialize;
 
-use error::Error;
-use internal::gob::{Message, Stream};
+use error::{Error, ErrorKind};
+use internal::gob::{
+    parse_section, Message, MessageReadError, SectionHeader, SectionStart, Stream,
+};
 use internal::types::{TypeId, Types, WireType};
+pub use internal::utils::BufferStats;
 use internal::utils::{Bow, Buffer};
 
 use internal::de::FieldValueDeserializer;
 use internal::de::ValueDeserializer;
 
-use crate::{error, internal};
+use mode::Mode;
+pub use schema::OptionEncoding;
+use schema::Schema;
+use session::Session;
+
+use crate::{error, internal, mode, schema, session};
+
+mod builder;
+pub use self::builder::Builder;
+
+mod decoder;
+pub use self::decoder::Decoder;
+
+mod options;
+pub use self::options::{DecodeOptions, EnumDecoding, NumericCoercion, PointerNames};
+pub(crate) use self::options::{DecodeScope, DEFAULT_OPTIONS, DEFAULT_SCOPE};
+
+mod progress;
+pub use self::progress::Progress;
+use self::progress::ProgressHook;
+
+mod blob;
+pub use self::blob::Blob;
+
+mod hooks;
+pub use self::hooks::DecodeHooks;
+pub(crate) use self::hooks::Hook;
+
+mod stats;
+use self::stats::Observation;
+pub use self::stats::{DecodeObserver, DecodeStats};
+
+mod dispatch;
+pub use self::dispatch::Dispatcher;
+
+mod slice_stream;
+pub use self::slice_stream::{SliceDeserializers, SliceStreamDeserializer};
+
+mod type_info;
+pub use self::type_info::{Structure, TypeInfo};
+
+mod check;
+pub use self::check::{CheckReport, Mismatch, MismatchKind};
+
+mod lossy;
+pub use self::lossy::FieldError;
+
+mod state;
+pub use self::state::StreamState;
+
+mod registry;
+pub use self::registry::SchemaRegistry;
+pub(crate) use self::registry::{resolve, resolved};
+
+#[cfg(feature = "arena")]
+mod arena;
+#[cfg(feature = "arena")]
+pub use self::arena::{ArenaBytes, ArenaStr, ArenaValue, InArena};
+
+/// A snapshot of the type definitions read off a stream.
+///
+/// Payloads that depend on definitions sent elsewhere (e.g. once at the start
+/// of a session) can be decoded by handing a snapshot to
+/// `StreamDeserializer::with_type_defs` or
+/// `Deserializer::from_slice_with_type_defs`.
+///
+/// Snapshots are cheap to clone and can be shared across threads. Clones
+/// share their definitions until one of them learns about a new type, at
+/// which point it gets a copy of its own.
+#[derive(Clone, Debug, Default)]
+pub struct TypeDefs {
+    pub(crate) types: Types,
+}
+
+impl TypeDefs {
+    pub fn new() -> TypeDefs {
+        TypeDefs::default()
+    }
+
+    /// Looks up the id of a type defined under `name`.
+    pub fn type_id(&self, name: &str) -> Option<TypeId> {
+        self.types
+            .iter()
+            .map(|def| def.common())
+            .find(|common| common.name == name)
+            .map(|common| common.id)
+    }
+
+    /// The definition of the type `id`, unless it is one of Go's
+    /// predeclared types or was not defined.
+    pub fn lookup(&self, id: TypeId) -> Option<&WireType> {
+        if id.is_builtin() {
+            return None;
+        }
+        self.types.lookup(id)
+    }
+
+    /// All definitions, in the order of their ids.
+    pub fn iter(&self) -> impl Iterator<Item = &WireType> {
+        self.types.iter()
+    }
+
+    /// Reads the type definitions in `bytes`, e.g. a blob written by
+    /// `StreamSerializer::export_type_defs`.
+    ///
+    /// `bytes` must hold nothing but definition sections.
+    pub fn from_bytes(bytes: &[u8]) -> Result<TypeDefs, Error> {
+        let mut defs = TypeDefs::new();
+        defs.load(bytes)?;
+        Ok(defs)
+    }
+
+    /// Adds the type definitions in `bytes` to the ones known already.
+    pub fn load(&mut self, mut bytes: &[u8]) -> Result<(), Error> {
+        while !bytes.is_empty() {
+            let header = match parse_section(bytes) {
+                Ok(header) => header,
+                Err(MessageReadError::Incomplete(_)) => {
+                    return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+                }
+                Err(err) => return Err(err.into()),
+            };
+            if header.type_id >= 0 {
+                return Err(Error::deserialize(format!(
+                    "expected a type definition, found a value of type {}",
+                    header.type_id
+                )));
+            }
+            let mut msg = Message::new(Cursor::new(&bytes[header.payload_range.clone()]));
+            define(&mut self.types, &DEFAULT_OPTIONS, header.type_id, &mut msg)?;
+            bytes = &bytes[header.payload_range.end..];
+        }
+        Ok(())
+    }
+}
+
+/// A value section read by `StreamDeserializer::next_raw`, along with the
+/// types defined so far.
+pub(crate) struct RawValue<'a> {
+    pub(crate) type_id: TypeId,
+    pub(crate) payload: &'a [u8],
+    pub(crate) defs: &'a Types,
+}
 
 pub struct StreamDeserializer<R> {
     defs: Types,
     stream: Stream<R>,
     buffer: Buffer,
     prev_len: usize,
+    // a value section that was read by `peek_type`, but not handed out yet
+    peeked: Option<SectionHeader>,
+    // bytes left of a blob handed out by `deserialize_with_blob`, along
+    // with the byte ending the struct it is the last field of
+    blob_rest: u64,
+    progress: Option<ProgressHook>,
+    opts: DecodeOptions,
+    resync: bool,
+    skipped: u64,
+    observation: Observation,
+    // reports of `check_type`, by wire type and target type
+    checked: HashMap<(TypeId, any::TypeId), CheckReport>,
 }
 
 impl<R> StreamDeserializer<R> {
     pub fn new(read: R) -> Self {
+        StreamDeserializer::from_parts(read, Buffer::new())
+    }
+
+    fn from_parts(read: R, buffer: Buffer) -> Self {
         StreamDeserializer {
             defs: Types::new(),
             stream: Stream::new(read),
-            buffer: Buffer::new(),
+            buffer,
             prev_len: 0,
+            peeked: None,
+            blob_rest: 0,
+            progress: None,
+            opts: DecodeOptions::default(),
+            resync: false,
+            skipped: 0,
+            observation: Observation::default(),
+            checked: HashMap::new(),
+        }
+    }
+
+    /// Create a new stream deserializer that knows about all types in `defs`
+    /// up front.
+    pub fn with_type_defs(read: R, defs: TypeDefs) -> Self {
+        StreamDeserializer {
+            defs: defs.types,
+            ..StreamDeserializer::new(read)
+        }
+    }
+
+    /// Create a new stream deserializer that buffers input in the
+    /// allocation of `buffer`, e.g. one given up by `into_buffer`.
+    ///
+    /// Once the buffer is large enough for the largest message on the
+    /// stream, decoding does not allocate for buffering anymore.
+    pub fn with_buffer(read: R, buffer: Vec<u8>) -> Self {
+        StreamDeserializer::from_parts(read, Buffer::from_vec(buffer))
+    }
+
+    /// Takes a snapshot of all types defined on the stream so far.
+    pub fn type_defs(&self) -> TypeDefs {
+        TypeDefs {
+            types: self.defs.clone(),
         }
     }
 
+    /// Reports on the memory used for buffering input.
+    pub fn buffer_stats(&self) -> BufferStats {
+        self.buffer.stats()
+    }
+
+    /// Gives up the input buffer for reuse by `with_buffer`, discarding any
+    /// input that was read but not decoded.
+    pub fn into_buffer(self) -> Vec<u8> {
+        self.buffer.into_vec()
+    }
+
+    /// Sets the least number of bytes asked of the reader at once.
+    ///
+    /// Each read fills all of the free space in the input buffer, so a
+    /// larger chunk size means fewer, larger reads on streams of many small
+    /// messages. Defaults to 4096 bytes.
+    pub fn set_read_chunk_size(&mut self, chunk_size: usize) {
+        self.buffer.set_chunk_size(chunk_size);
+    }
+
+    /// Keeps the stream going past corrupt sections.
+    ///
+    /// Sections carry their own length, so one that fails to parse can be
+    /// stepped over as a whole: the error is still returned, but the next
+    /// call goes on with the section after it instead of failing again.
+    /// This covers type definitions that fail to decode, values of types
+    /// that were never defined and sections with a corrupt type id. A
+    /// corrupt section length cannot be recovered from. Values that fail to
+    /// decode are consumed either way.
+    ///
+    /// Values of a type whose definition was dropped fail to decode, too.
+    pub fn set_resync(&mut self, resync: bool) {
+        self.resync = resync;
+    }
+
+    /// Number of corrupt sections stepped over since `set_resync` was
+    /// enabled.
+    pub fn skipped_sections(&self) -> u64 {
+        self.skipped
+    }
+
+    /// Sets which input is accepted from now on.
+    pub fn set_options(&mut self, options: DecodeOptions) {
+        self.opts = options;
+    }
+
+    /// Sets all options according to `mode`.
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.set_options(mode.into());
+    }
+
+    /// Decodes values of the types in `hooks` through their hooks from now
+    /// on, replacing any set before. Snapshots taken by `type_defs` keep
+    /// the hooks.
+    pub fn set_decode_hooks(&mut self, hooks: DecodeHooks) {
+        self.defs.set_hooks(hooks);
+    }
+
+    /// Registers a callback that is invoked with the current `Progress`
+    /// every `every_messages` messages or every `every_bytes` bytes,
+    /// whichever comes first, and once more at the end of the stream.
+    ///
+    /// Passing zero for either interval disables that trigger. The callback
+    /// has to be `Send`, so that the deserializer can still be moved to
+    /// another thread.
+    pub fn on_progress<F>(&mut self, every_messages: u64, every_bytes: u64, callback: F)
+    where
+        F: FnMut(&Progress) + Send + 'static,
+    {
+        self.progress = Some(ProgressHook::new(
+            every_messages,
+            every_bytes,
+            Box::new(callback),
+        ));
+    }
+
+    /// Returns the counters kept so far.
+    pub fn stats(&self) -> DecodeStats {
+        self.observation.stats.clone()
+    }
+
+    /// Registers an observer that is told about each type definition read
+    /// and each value decoded, replacing any registered before. Like
+    /// progress callbacks, observers have to be `Send`.
+    pub fn set_observer<O>(&mut self, observer: O)
+    where
+        O: DecodeObserver + Send + 'static,
+    {
+        self.observation.observer = Some(Box::new(observer));
+    }
+
+    /// Looks up types not defined on the stream in `registry`, replacing
+    /// any registry set before. Snapshots taken by `type_defs` keep the
+    /// registry.
+    pub fn set_schema_registry<S>(&mut self, registry: S)
+    where
+        S: SchemaRegistry + 'static,
+    {
+        self.defs.set_registry(Arc::new(registry));
+    }
+
     pub fn deserialize<'de, T>(&'de mut self) -> Result<Option<T>, Error>
     where
         R: Read,
         T: Deserialize<'de>,
     {
-        if let Some(deserializer) = self.deserializer()? {
-            Ok(Some(T::deserialize(deserializer)?))
-        } else {
-            Ok(None)
-        }
+        self.deserialize_seed(PhantomData)
+    }
+
+    /// Like `deserialize`, but driven by a `DeserializeSeed`, so that state
+    /// can be carried from one value to the next, e.g. by passing a
+    /// `&mut` reference to a seed that implements `DeserializeSeed` for it.
+    pub fn deserialize_seed<'de, S>(&'de mut self, seed: S) -> Result<Option<S::Value>, Error>
+    where
+        R: Read,
+        S: DeserializeSeed<'de>,
+    {
+        let started = Instant::now();
+        let header = match self.observed_header(started)? {
+            Some(header) => header,
+            None => return Ok(None),
+        };
+        // the value may borrow the buffer for as long as `self`, so the
+        // observation is borrowed apart from it
+        let StreamDeserializer {
+            ref mut defs,
+            ref buffer,
+            ref mut observation,
+            opts,
+            ..
+        } = *self;
+        let deserializer = Deserializer::from_section(&header, buffer, defs, opts);
+        let result = seed.deserialize(deserializer);
+        let (type_id, len) = (TypeId(header.type_id), header.payload_range.end);
+        observation.outcome(&result, type_id, len, started.elapsed());
+        result.map(Some)
     }
 
+    /// Reads the next value, handing out a `Deserializer` for it. The value
+    /// counts as decoded once handed out.
     pub fn deserializer<'de>(&'de mut self) -> Result<Option<Deserializer<'de>>, Error>
+    where
+        R: Read,
+    {
+        let started = Instant::now();
+        let header = match self.observed_header(started)? {
+            Some(header) => header,
+            None => return Ok(None),
+        };
+        let type_id = TypeId(header.type_id);
+        self.observation
+            .value(type_id, header.payload_range.end, started.elapsed());
+        Ok(Some(Deserializer::from_section(
+            &header,
+            &self.buffer,
+            &mut self.defs,
+            self.opts,
+        )))
+    }
+
+    /// Like `deserialize`, but leaves out struct fields that fail to decode,
+    /// see `Deserializer::deserialize_lossy`.
+    pub fn deserialize_lossy<'de, T>(&'de mut self) -> Result<Option<(T, Vec<FieldError>)>, Error>
+    where
+        R: Read,
+        T: Deserialize<'de>,
+    {
+        let started = Instant::now();
+        let header = match self.observed_header(started)? {
+            Some(header) => header,
+            None => return Ok(None),
+        };
+        let StreamDeserializer {
+            ref mut defs,
+            ref buffer,
+            ref mut observation,
+            opts,
+            ..
+        } = *self;
+        let result = Deserializer::from_section(&header, buffer, defs, opts).deserialize_lossy();
+        let (type_id, len) = (TypeId(header.type_id), header.payload_range.end);
+        observation.outcome(&result, type_id, len, started.elapsed());
+        result.map(Some)
+    }
+
+    // `next_header` for handing out a value, which records the error if
+    // reading it fails; how handing it out goes is left to the caller
+    fn observed_header(&mut self, started: Instant) -> Result<Option<SectionHeader>, Error>
+    where
+        R: Read,
+    {
+        self.next_header().map_err(|err| {
+            self.observation.error(&err, started.elapsed());
+            err
+        })
+    }
+
+    // reads up to the next value section, or takes the one peeked at, and
+    // marks it to be dropped from the buffer on the next read
+    fn next_header(&mut self) -> Result<Option<SectionHeader>, Error>
+    where
+        R: Read,
+    {
+        let header = match self.peeked.take() {
+            Some(header) => header,
+            None => match self.next_value()? {
+                Some(header) => header,
+                None => return Ok(None),
+            },
+        };
+        self.prev_len = header.payload_range.end;
+        Ok(Some(header))
+    }
+
+    // reads the next value section without decoding it
+    pub(crate) fn next_raw(&mut self) -> Result<Option<RawValue<'_>>, Error>
+    where
+        R: Read,
+    {
+        let header = match self.peeked.take() {
+            Some(header) => header,
+            None => match self.next_value()? {
+                Some(header) => header,
+                None => return Ok(None),
+            },
+        };
+        self.prev_len = header.payload_range.end;
+        let payload = &self.buffer.bytes()[header.payload_range];
+        Ok(Some(RawValue {
+            type_id: TypeId(header.type_id),
+            payload,
+            defs: &self.defs,
+        }))
+    }
+
+    /// Describes the type of the next value, without consuming it.
+    ///
+    /// Any type definitions sent ahead of the value are read, so that the
+    /// returned description is complete. Returns `None` at the end of the
+    /// stream.
+    pub fn peek_type(&mut self) -> Result<Option<TypeInfo>, Error>
+    where
+        R: Read,
+    {
+        if self.peeked.is_none() {
+            self.peeked = self.next_value()?;
+        }
+        let type_id = match self.peeked {
+            Some(ref header) => TypeId(header.type_id),
+            None => return Ok(None),
+        };
+        match TypeInfo::lookup(type_id, &self.defs) {
+            Some(info) => Ok(Some(info)),
+            None => {
+                if self.resync {
+                    if let Some(header) = self.peeked.take() {
+                        self.prev_len = header.payload_range.end;
+                        self.skipped += 1;
+                    }
+                }
+                Err(Error::deserialize(format!("unknown type id {}", type_id.0)))
+            }
+        }
+    }
+
+    /// Checks whether the next value can be decoded into `T`, without
+    /// consuming it.
+    ///
+    /// Go's decoder compiles each incoming wire type against its destination
+    /// before decoding, and fails up front if they don't match. This does
+    /// the same, comparing the wire type with the schema of `T` field by
+    /// field, and reports every mismatch found rather than just the first.
+    /// Reports are kept per pair of types, so checking many values of the
+    /// same type is cheap. Returns `None` at the end of the stream.
+    pub fn check_type<T>(&mut self) -> Result<Option<CheckReport>, Error>
+    where
+        R: Read,
+        T: SchemaSerialize + 'static,
+    {
+        let wire = match self.peek_type()? {
+            Some(info) => info.id(),
+            None => return Ok(None),
+        };
+        let key = (wire, any::TypeId::of::<T>());
+        if let Some(report) = self.checked.get(&key) {
+            return Ok(Some(report.clone()));
+        }
+        let mut schema = Schema::new();
+        schema.set_option_encoding(self.opts.options);
+        let target = T::schema_register(&mut schema)?;
+        let report = check::check(&self.defs, &self.opts, wire, &schema, target);
+        self.checked.insert(key, report.clone());
+        Ok(Some(report))
+    }
+
+    /// Like `deserialize`, but fails without consuming the next value if
+    /// `check_type` finds that it does not match `T`, so that it can be
+    /// decoded into another type instead.
+    pub fn deserialize_checked<'de, T>(&'de mut self) -> Result<Option<T>, Error>
+    where
+        R: Read,
+        T: Deserialize<'de> + SchemaSerialize + 'static,
+    {
+        match self.check_type::<T>()? {
+            Some(ref report) if !report.is_ok() => {
+                Err(Error::deserialize(format!("type mismatch: {}", report)))
+            }
+            Some(_) => self.deserialize(),
+            None => Ok(None),
+        }
+    }
+
+    // reads up to the next value section, defining all types on the way
+    fn next_value(&mut self) -> Result<Option<SectionHeader>, Error>
+    where
+        R: Read,
+    {
+        if self.next_value_start()?.is_none() {
+            return Ok(None);
+        }
+        let header = match self.stream.read_section(&mut self.buffer)? {
+            Some(header) => header,
+            None => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+        };
+        if let Some(ref mut progress) = self.progress {
+            progress.message(header.payload_range.end, header.type_id);
+        }
+        self.observation
+            .section(header.payload_range.end, header.type_id);
+        Ok(Some(header))
+    }
+
+    // reads up to the type id of the next value section, without waiting for
+    // its payload
+    fn next_value_start(&mut self) -> Result<Option<SectionStart>, Error>
     where
         R: Read,
     {
@@ -53,37 +575,140 @@ impl<R> StreamDeserializer<R> {
             self.buffer.advance(self.prev_len);
             self.prev_len = 0;
         }
+        self.discard_blob()?;
         loop {
+            let start = match self.stream.read_section_start(&mut self.buffer) {
+                Ok(Some(start)) => start,
+                Err(err) => return Err(self.step_over_corrupt(err)),
+                Ok(None) => {
+                    if let Some(ref mut progress) = self.progress {
+                        progress.finish();
+                    }
+                    return Ok(None);
+                }
+            };
+            if start.type_id >= 0 {
+                resolve(&mut self.defs, TypeId(start.type_id))?;
+                return Ok(Some(start));
+            }
+
             let header = match self.stream.read_section(&mut self.buffer)? {
                 Some(header) => header,
-                None => return Ok(None),
+                None => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
             };
-
-            if header.type_id >= 0 {
-                let slice = &self.buffer.bytes()[header.payload_range.clone()];
-                let msg = Message::new(Cursor::new(slice));
-                self.prev_len = header.payload_range.end;
-                return Ok(Some(Deserializer {
-                    defs: Bow::Borrowed(&mut self.defs),
-                    msg: msg,
-                    type_id: Some(TypeId(header.type_id)),
-                }));
+            if let Some(ref mut progress) = self.progress {
+                progress.message(header.payload_range.end, header.type_id);
             }
-
-            let wire_type = {
+            self.observation
+                .section(header.payload_range.end, header.type_id);
+            let defined = {
                 let slice = &self.buffer.bytes()[header.payload_range.clone()];
                 let mut msg = Message::new(Cursor::new(slice));
-                let de = FieldValueDeserializer::new(TypeId::WIRE_TYPE, &self.defs, &mut msg);
-                WireType::deserialize(de)
-            }?;
-
-            if -header.type_id != wire_type.common().id.0 {
-                return Err(Error::deserialize("type id mismatch"));
+                define(&mut self.defs, &self.opts, header.type_id, &mut msg)
+            };
+            if defined.is_ok() || self.resync {
+                self.buffer.advance(header.payload_range.end);
             }
+            // a type may be defined anew, changing how checks come out
+            self.checked.clear();
+            if let Err(err) = defined {
+                if self.resync {
+                    self.skipped += 1;
+                }
+                return Err(err);
+            }
+        }
+    }
+
+    // in resync mode, drops the section `err` was found in if its length
+    // can be read, so that decoding goes on with the next one
+    fn step_over_corrupt(&mut self, err: Error) -> Error
+    where
+        R: Read,
+    {
+        if !self.resync || err.kind() != ErrorKind::Deserialize {
+            return err;
+        }
+        if let Ok(Some(end)) = self.stream.read_section_end(&mut self.buffer) {
+            self.buffer.advance(end);
+            self.skipped += 1;
+        }
+        err
+    }
+
+    /// Skips over the next `n` values without decoding them, returning how
+    /// many were skipped. This is less than `n` only if the stream ended.
+    ///
+    /// Type definitions in between are still read, so values after the
+    /// skipped ones decode as usual.
+    pub fn skip(&mut self, n: usize) -> Result<usize, Error>
+    where
+        R: Read,
+    {
+        for skipped in 0..n {
+            let started = Instant::now();
+            let header = match self.observed_header(started)? {
+                Some(header) => header,
+                None => return Ok(skipped),
+            };
+            let type_id = TypeId(header.type_id);
+            self.observation
+                .value(type_id, header.payload_range.end, started.elapsed());
+        }
+        Ok(n)
+    }
+
+    /// Takes a checkpoint to `resume` from later, e.g. after a crash.
+    ///
+    /// The checkpoint is taken right after the last value handed out, so
+    /// that a value peeked at is read again on resuming. Fails while a
+    /// blob is still being read.
+    pub fn save_state(&self) -> Result<StreamState, Error> {
+        if self.blob_rest > 0 {
+            return Err(Error::deserialize(
+                "cannot save the state while a blob is being read",
+            ));
+        }
+        let buffered = (self.buffer.len() - self.prev_len) as u64;
+        Ok(StreamState {
+            defs: self.defs.clone(),
+            offset: self.stream.position() - buffered,
+        })
+    }
 
-            self.defs.insert(wire_type);
-            self.buffer.advance(header.payload_range.end);
+    /// Create a new stream deserializer that continues from a checkpoint
+    /// taken by `save_state` on the same input.
+    ///
+    /// `read` is positioned at the checkpoint's offset, and the types
+    /// defined before it are known up front.
+    pub fn resume(mut read: R, state: StreamState) -> Result<Self, Error>
+    where
+        R: Read + Seek,
+    {
+        read.seek(SeekFrom::Start(state.offset))?;
+        let mut resumed = StreamDeserializer::new(read);
+        resumed.defs = state.defs;
+        resumed.stream = Stream::at(resumed.stream.into_inner(), state.offset);
+        Ok(resumed)
+    }
+
+    /// Reads the rest of the stream into a `Session`, carrying over all
+    /// types defined so far.
+    pub fn into_session(mut self) -> Result<Session, Error>
+    where
+        R: Read,
+    {
+        if self.prev_len > 0 {
+            self.buffer.advance(self.prev_len);
+            self.prev_len = 0;
         }
+        self.discard_blob()?;
+        Session::read_rest(&mut self.stream, &mut self.buffer, self.defs)
+    }
+
+    #[cfg(feature = "cli")]
+    pub(crate) fn types(&self) -> &Types {
+        &self.defs
     }
 
     pub fn get_ref(&self) -> &R {
@@ -99,10 +724,56 @@ impl<R> StreamDeserializer<R> {
     }
 }
 
+/// Deserializes a single value from `input`, collecting the paths of all
+/// parts of the message that `T` does not have a place for.
+///
+/// Paths are dotted, with sequence elements and map entries identified by
+/// their index and key respectively (e.g. `Items.0.B`).
+pub fn deserialize_reporting_ignored<'de, T>(input: &'de [u8]) -> Result<(T, Vec<String>), Error>
+where
+    T: Deserialize<'de>,
+{
+    let mut ignored = Vec::new();
+    let value = serde_ignored::deserialize(Deserializer::from_slice(input), |path| {
+        ignored.push(path.to_string())
+    })?;
+    Ok((value, ignored))
+}
+
+// reads the definition of type `-type_id` off `msg` and adds it to `defs`
+pub(crate) fn define(
+    defs: &mut Types,
+    opts: &DecodeOptions,
+    type_id: i64,
+    msg: &mut Message<Cursor<&[u8]>>,
+) -> Result<(), Error> {
+    let wire_type = {
+        let de = FieldValueDeserializer::new(TypeId::WIRE_TYPE, defs, DEFAULT_SCOPE, msg);
+        WireType::deserialize(de)?
+    };
+
+    if -type_id != wire_type.common().id.0 {
+        return Err(Error::deserialize("type id mismatch"));
+    }
+    if opts.reject_redefinitions && defs.lookup(wire_type.common().id).is_some() {
+        return Err(Error::deserialize(format!("type {} redefined", -type_id)));
+    }
+
+    defs.insert(wire_type);
+    Ok(())
+}
+
 pub struct Deserializer<'de> {
     defs: Bow<'de, Types>,
     msg: Message<Cursor<&'de [u8]>>,
     type_id: Option<TypeId>,
+    // where the current value's message ends
+    end: Option<usize>,
+    opts: DecodeOptions,
+    projection: Option<&'de [&'de str]>,
+    // where the values of struct fields to leave out start, see
+    // `deserialize_lossy`
+    skipped: Option<Arc<BTreeSet<u64>>>,
 }
 
 impl<'de> Deserializer<'de> {
@@ -111,40 +782,183 @@ impl<'de> Deserializer<'de> {
             defs: Bow::Owned(Types::new()),
             msg: Message::new(Cursor::new(input)),
             type_id: None,
+            end: None,
+            opts: DecodeOptions::default(),
+            projection: None,
+            skipped: None,
         }
     }
 
+    /// Like `from_slice`, but with the types in `defs` already known.
+    ///
+    /// Any type definitions found in `input` are added to `defs`.
+    pub fn from_slice_with_type_defs(
+        input: &'de [u8],
+        defs: &'de mut TypeDefs,
+    ) -> Deserializer<'de> {
+        Deserializer {
+            defs: Bow::Borrowed(&mut defs.types),
+            msg: Message::new(Cursor::new(input)),
+            type_id: None,
+            end: None,
+            opts: DecodeOptions::default(),
+            projection: None,
+            skipped: None,
+        }
+    }
+
+    /// Decodes all values in `input`, which holds many sections like a
+    /// captured stream, without the machinery of a `StreamDeserializer`.
+    ///
+    /// See `SliceDeserializers`.
+    pub fn from_slice_many(input: &'de [u8]) -> SliceDeserializers<'de> {
+        SliceDeserializers::new(input, Types::new())
+    }
+
+    /// Like `from_slice_many`, but with the types in `defs` already known.
+    pub fn from_slice_many_with_type_defs(
+        input: &'de [u8],
+        defs: TypeDefs,
+    ) -> SliceDeserializers<'de> {
+        SliceDeserializers::new(input, defs.types)
+    }
+
+    // a deserializer for the value in the section of `buffer` described by
+    // `header`
+    fn from_section(
+        header: &SectionHeader,
+        buffer: &'de Buffer,
+        defs: &'de mut Types,
+        opts: DecodeOptions,
+    ) -> Deserializer<'de> {
+        let slice = &buffer.bytes()[header.payload_range.clone()];
+        Deserializer {
+            defs: Bow::Borrowed(defs),
+            msg: Message::new(Cursor::new(slice)),
+            type_id: Some(TypeId(header.type_id)),
+            end: Some(slice.len()),
+            opts,
+            projection: None,
+            skipped: None,
+        }
+    }
+
+    /// Sets which input is accepted.
+    pub fn with_options(mut self, options: DecodeOptions) -> Self {
+        self.opts = options;
+        self
+    }
+
+    /// Sets all options according to `mode`.
+    pub fn with_mode(self, mode: Mode) -> Self {
+        self.with_options(mode.into())
+    }
+
+    /// Decodes values of the types in `hooks` through their hooks.
+    pub fn with_decode_hooks(mut self, hooks: DecodeHooks) -> Self {
+        self.defs.set_hooks(hooks);
+        self
+    }
+
+    /// Looks up types not defined in the input in `registry`, see
+    /// `StreamDeserializer::set_schema_registry`.
+    pub fn with_schema_registry<S>(mut self, registry: S) -> Self
+    where
+        S: SchemaRegistry + 'static,
+    {
+        self.defs.set_registry(Arc::new(registry));
+        self
+    }
+
+    /// Creates a second deserializer over the same input, positioned at the
+    /// same value.
+    ///
+    /// This allows decoding a value into several types without reading it
+    /// again. Types defined later in the input are only added to the
+    /// definitions of the deserializer that reads them.
+    pub fn fork(&self) -> Deserializer<'de> {
+        Deserializer {
+            defs: Bow::Owned((*self.defs).clone()),
+            msg: Message::new(self.msg.get_ref().clone()),
+            type_id: self.type_id,
+            end: self.end,
+            opts: self.opts,
+            projection: self.projection,
+            skipped: self.skipped.clone(),
+        }
+    }
+
+    /// Decodes only the named fields of a struct value, skipping over all
+    /// others without looking at their contents.
+    ///
+    /// To the type being deserialized the skipped fields look as if they
+    /// were not sent at all. Nested structs are decoded in full, and values
+    /// that are not structs are not affected.
+    pub fn project(mut self, fields: &'de [&'de str]) -> Self {
+        self.projection = Some(fields);
+        self
+    }
+
     fn value_deserializer<'t>(&'t mut self) -> Result<ValueDeserializer<'t, 'de>, Error> {
         if let Some(type_id) = self.type_id {
-            return Ok(ValueDeserializer::new(type_id, &self.defs, &mut self.msg));
+            let scope = DecodeScope::new(&self.opts, self.skipped.as_deref());
+            return Ok(
+                ValueDeserializer::new(type_id, &self.defs, scope, &mut self.msg)
+                    .with_projection(self.projection),
+            );
         }
 
         loop {
-            let _len = self.msg.read_bytes_len()?;
+            let len = match self.msg.read_section_len() {
+                Err(MessageReadError::Incomplete(hint)) => return Err(Error::need_more_data(hint)),
+                result => result?,
+            };
+            let start = self.msg.get_ref().position() as usize;
             let type_id = self.msg.read_int()?;
 
             if type_id >= 0 {
+                self.end = Some(start + len);
+                resolve(&mut self.defs, TypeId(type_id))?;
+                let scope = DecodeScope::new(&self.opts, self.skipped.as_deref());
                 return Ok(ValueDeserializer::new(
                     TypeId(type_id),
                     &self.defs,
+                    scope,
                     &mut self.msg,
-                ));
+                )
+                .with_projection(self.projection));
             }
 
-            let wire_type = {
-                let de = FieldValueDeserializer::new(TypeId::WIRE_TYPE, &self.defs, &mut self.msg);
-                WireType::deserialize(de)
-            }?;
+            define(&mut self.defs, &self.opts, type_id, &mut self.msg)?;
+        }
+    }
 
-            if -type_id != wire_type.common().id.0 {
-                return Err(serde::de::Error::custom(format!("type id mismatch")));
+    // checks that the value just decoded took up its whole message
+    fn finish<T>(&self, value: T) -> Result<T, Error> {
+        if let Some(end) = self.end {
+            let pos = self.msg.get_ref().position() as usize;
+            if self.opts.reject_trailing_data && pos < end {
+                return Err(Error::deserialize(format!(
+                    "{} bytes of extra data at end of message",
+                    end - pos
+                )));
             }
-
-            self.defs.insert(wire_type);
         }
+        Ok(value)
     }
 }
 
+macro_rules! value {
+    ($($fname:ident)*) => {
+        $(
+            fn $fname<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, Self::Error> {
+                let value = self.value_deserializer()?.$fname(visitor)?;
+                self.finish(value)
+            }
+        )*
+    };
+}
+
 impl<'de> serde::Deserializer<'de> for Deserializer<'de> {
     type Error = Error;
 
@@ -152,7 +966,8 @@ impl<'de> serde::Deserializer<'de> for Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        self.value_deserializer()?.deserialize_any(visitor)
+        let value = self.value_deserializer()?.deserialize_any(visitor)?;
+        self.finish(value)
     }
 
     fn deserialize_enum<V>(
@@ -164,8 +979,18 @@ impl<'de> serde::Deserializer<'de> for Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        self.value_deserializer()?
-            .deserialize_enum(name, variants, visitor)
+        let value = self
+            .value_deserializer()?
+            .deserialize_enum(name, variants, visitor)?;
+        self.finish(value)
+    }
+
+    fn deserialize_tuple<V>(mut self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let value = self.value_deserializer()?.deserialize_tuple(len, visitor)?;
+        self.finish(value)
     }
 
     fn deserialize_struct<V>(
@@ -177,23 +1002,10 @@ impl<'de> serde::Deserializer<'de> for Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        self.value_deserializer()?
-            .deserialize_struct(name, fields, visitor)
-    }
-
-    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-    where
-        V: Visitor<'de>,
-    {
-        let int = i64::deserialize(self)?;
-        if let Some(c) = ::std::char::from_u32(int as u32) {
-            visitor.visit_char(c)
-        } else {
-            Err(serde::de::Error::custom(format!(
-                "invalid char code {}",
-                int
-            )))
-        }
+        let value = self
+            .value_deserializer()?
+            .deserialize_struct(name, fields, visitor)?;
+        self.finish(value)
     }
 
     #[inline]
@@ -205,9 +1017,16 @@ impl<'de> serde::Deserializer<'de> for Deserializer<'de> {
         visitor.visit_unit()
     }
 
+    value! {
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64 deserialize_i128
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_u128
+        deserialize_f32 deserialize_f64
+        deserialize_str deserialize_string deserialize_bytes deserialize_byte_buf
+        deserialize_seq deserialize_option deserialize_char
+    }
+
     forward_to_deserialize_any! {
-        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 str string bytes
-        byte_buf option unit_struct newtype_struct seq tuple
+        bool unit_struct newtype_struct
         tuple_struct map identifier ignored_any
     }
 }
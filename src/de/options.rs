@@ -0,0 +1,296 @@
+use std::collections::BTreeSet;
+use std::ops::Deref;
+
+use mode::Mode;
+use schema::OptionEncoding;
+
+use crate::{mode, schema};
+
+/// Controls which input is accepted when decoding.
+///
+/// The defaults match `Mode::Compatible`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecodeOptions {
+    pub(crate) reject_trailing_data: bool,
+    pub(crate) reject_redefinitions: bool,
+    pub(crate) lossy_utf8: bool,
+    pub(crate) pointer_names: PointerNames,
+    pub(crate) numbers: NumericCoercion,
+    pub(crate) exact_f32: bool,
+    pub(crate) non_finite_as_none: bool,
+    pub(crate) string_bytes: bool,
+    pub(crate) bytes_as_seq: bool,
+    pub(crate) flatten_embedded: bool,
+    pub(crate) field_indices: bool,
+    pub(crate) options: OptionEncoding,
+    pub(crate) enums: EnumDecoding,
+}
+
+/// How the concrete type name sent with an interface value is matched
+/// against enum variant names.
+///
+/// Go names a concrete type `main.T` or `*main.T`, depending on whether it
+/// was registered as a value or as a pointer; the value itself is encoded
+/// the same way in both cases.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PointerNames {
+    /// Names only match if they are identical.
+    Exact,
+    /// A leading `*` is ignored on both sides, so that `*main.T` and
+    /// `main.T` match each other.
+    #[default]
+    Either,
+}
+
+impl PointerNames {
+    pub(crate) fn matches(self, wire_name: &str, variant: &str) -> bool {
+        match self {
+            PointerNames::Exact => wire_name == variant,
+            PointerNames::Either => {
+                wire_name.trim_start_matches('*') == variant.trim_start_matches('*')
+            }
+        }
+    }
+}
+
+/// How structs select the variant of an enum they are decoded into.
+///
+/// This applies to enums decoded with `deserialize_enum`, which is what
+/// serde derives by default. Enums marked `#[serde(tag = "...")]` or
+/// `#[serde(untagged)]` are decoded by serde itself from the fields of the
+/// struct, and work with any of these.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EnumDecoding {
+    /// A struct with a single field named after the variant, holding its
+    /// value. This is how enums are serialized.
+    #[default]
+    External,
+    /// A struct with a field of the given name selecting the variant, either
+    /// by name if it is a string, or by index if it is an integer. All other
+    /// fields make up the variant. Like Go, a missing integer selects the
+    /// first variant.
+    Internal(&'static str),
+    /// A struct with a field of the given name selecting the variant like
+    /// for `Internal`, followed by a field named after the variant holding
+    /// its value, if it has one. This is how `EnumEncoding::Adjacent`
+    /// writes enums, with `"Tag"` as the tag.
+    Adjacent(&'static str),
+    /// A struct whose Go type name is the name of the variant, compared
+    /// according to `PointerNames`. The whole struct makes up the variant.
+    TypeName,
+}
+
+/// How numbers are converted to the Rust type they are decoded into.
+///
+/// Go sends every signed integer as an `int64`, every unsigned one as a
+/// `uint64` and every float as a `float64`, whatever their declared size.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NumericCoercion {
+    /// Like Go, only converts between integers of the same signedness, and
+    /// only if the value fits. Finite floats too large for `f32` fail as
+    /// well. Anything else fails, naming the field the number was found in.
+    #[default]
+    Strict,
+    /// Converts between all numeric types, clamping values that don't fit
+    /// to the closest one that does.
+    Saturating,
+    /// Converts between all numeric types like Rust's `as`, so integers that
+    /// don't fit wrap around.
+    Lossy,
+}
+
+// used for type definitions, which are not affected by any options
+pub(crate) static DEFAULT_OPTIONS: DecodeOptions = DecodeOptions::new();
+
+pub(crate) static DEFAULT_SCOPE: DecodeScope<'static> = DecodeScope {
+    opts: &DEFAULT_OPTIONS,
+    skipped: None,
+};
+
+/// The options of a single decode, along with the struct fields it leaves
+/// out. Those only apply to the one value, so unlike the definitions they
+/// are not kept in `Types`.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct DecodeScope<'a> {
+    opts: &'a DecodeOptions,
+    // where the values of the fields left out start in the message
+    skipped: Option<&'a BTreeSet<u64>>,
+}
+
+impl<'a> DecodeScope<'a> {
+    pub(crate) fn new(
+        opts: &'a DecodeOptions,
+        skipped: Option<&'a BTreeSet<u64>>,
+    ) -> DecodeScope<'a> {
+        DecodeScope { opts, skipped }
+    }
+
+    pub(crate) fn is_skipped(&self, start: u64) -> bool {
+        match self.skipped {
+            Some(skipped) => skipped.contains(&start),
+            None => false,
+        }
+    }
+}
+
+impl Deref for DecodeScope<'_> {
+    type Target = DecodeOptions;
+
+    fn deref(&self) -> &DecodeOptions {
+        self.opts
+    }
+}
+
+impl DecodeOptions {
+    pub const fn new() -> DecodeOptions {
+        DecodeOptions {
+            reject_trailing_data: false,
+            reject_redefinitions: false,
+            lossy_utf8: false,
+            pointer_names: PointerNames::Either,
+            numbers: NumericCoercion::Strict,
+            exact_f32: false,
+            non_finite_as_none: false,
+            string_bytes: false,
+            bytes_as_seq: false,
+            flatten_embedded: false,
+            field_indices: true,
+            options: OptionEncoding::Pointer,
+            enums: EnumDecoding::External,
+        }
+    }
+
+    /// Fail on values that do not take up their whole message, like Go's
+    /// "extra data in buffer".
+    pub fn reject_trailing_data(mut self, reject: bool) -> Self {
+        self.reject_trailing_data = reject;
+        self
+    }
+
+    /// Fail on definitions of types that are already defined, instead of
+    /// replacing them.
+    pub fn reject_redefinitions(mut self, reject: bool) -> Self {
+        self.reject_redefinitions = reject;
+        self
+    }
+
+    /// Replace invalid UTF-8 in strings with U+FFFD instead of failing.
+    pub fn lossy_utf8(mut self, lossy: bool) -> Self {
+        self.lossy_utf8 = lossy;
+        self
+    }
+
+    /// Decode byte slices into strings and strings into byte slices,
+    /// for fields that changed between the two. Byte slices decoded into
+    /// strings must be valid UTF-8, unless `lossy_utf8` is set as well.
+    pub fn string_bytes_interchange(mut self, interchange: bool) -> Self {
+        self.string_bytes = interchange;
+        self
+    }
+
+    /// Hand byte slices to types that accept anything as sequences of
+    /// integers instead of as bytes. Some of these types, like
+    /// `serde_json::Value`, don't accept bytes at all.
+    pub fn bytes_as_seq(mut self, as_seq: bool) -> Self {
+        self.bytes_as_seq = as_seq;
+        self
+    }
+
+    /// Hand out the fields of embedded structs as if they were fields of
+    /// the struct embedding them.
+    ///
+    /// Go sends an embedded struct as a field named after its type, so a
+    /// field is taken to be embedded if its struct type has the same name
+    /// as the field itself. This cannot tell embedded structs apart from
+    /// fields like `Base Base`, which are flattened as well.
+    pub fn flatten_embedded(mut self, flatten: bool) -> Self {
+        self.flatten_embedded = flatten;
+        self
+    }
+
+    /// Hand out the fields of structs by index rather than by name, which
+    /// spares the field visitors `#[derive(Deserialize)]` generates from
+    /// matching the name of every field of every value. The index of each
+    /// field is worked out once per struct type and Rust type decoded into.
+    /// This is on by default.
+    ///
+    /// Serde lists aliases along with the fields of a type without telling
+    /// them apart, so fields are only handed out by index up to where an
+    /// alias could have shifted their positions, and by name after that.
+    /// Keys that are not identifiers, like `String`s, always get names.
+    /// Hand-written identifiers may only take names too, like those of
+    /// `Duration` and the ranges in `std`, so fields are handed out by name
+    /// regardless unless the struct type has a field by each name listed.
+    /// Turn this off for hand-written identifiers of types whose fields Go
+    /// sends all of.
+    pub fn field_indices(mut self, indices: bool) -> Self {
+        self.field_indices = indices;
+        self
+    }
+
+    /// Sets how interface values select the variant of an enum they are
+    /// decoded into, see `PointerNames`.
+    pub fn pointer_names(mut self, names: PointerNames) -> Self {
+        self.pointer_names = names;
+        self
+    }
+
+    /// Sets how `Option`s were encoded, see `OptionEncoding`. With
+    /// `OptionEncoding::Slice`, values that were not sent as a slice still
+    /// decode as `Some`, so that pointers sent by Go are understood too.
+    pub fn option_encoding(mut self, options: OptionEncoding) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Sets how structs select the variant of an enum, see `EnumDecoding`.
+    pub fn enum_decoding(mut self, enums: EnumDecoding) -> Self {
+        self.enums = enums;
+        self
+    }
+
+    /// Sets how numbers are converted to the type they are decoded into,
+    /// see `NumericCoercion`.
+    pub fn numeric_coercion(mut self, coercion: NumericCoercion) -> Self {
+        self.numbers = coercion;
+        self
+    }
+
+    /// Fail on floats decoded into `f32` that would be rounded, instead of
+    /// silently losing precision. Go sends every float as a `float64`, so
+    /// this only accepts values that were `float32`s, or happen to be
+    /// exactly representable as one.
+    pub fn exact_f32(mut self, exact: bool) -> Self {
+        self.exact_f32 = exact;
+        self
+    }
+
+    /// Decode NaNs and infinities into `Option`s as `None`, for consumers
+    /// that treat them as missing values.
+    pub fn non_finite_as_none(mut self, as_none: bool) -> Self {
+        self.non_finite_as_none = as_none;
+        self
+    }
+}
+
+impl Default for DecodeOptions {
+    fn default() -> DecodeOptions {
+        DecodeOptions::new()
+    }
+}
+
+impl From<Mode> for DecodeOptions {
+    fn from(mode: Mode) -> DecodeOptions {
+        match mode {
+            Mode::Strict => DecodeOptions::new()
+                .reject_trailing_data(true)
+                .reject_redefinitions(true)
+                .pointer_names(PointerNames::Exact),
+            Mode::Compatible => DecodeOptions::new(),
+            Mode::Lenient => DecodeOptions::new()
+                .lossy_utf8(true)
+                .string_bytes_interchange(true)
+                .numeric_coercion(NumericCoercion::Saturating),
+        }
+    }
+}
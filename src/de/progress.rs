@@ -0,0 +1,95 @@
+use schema::TypeId;
+
+use crate::schema;
+
+/// A snapshot of how far a `StreamDeserializer` has advanced.
+#[derive(Clone, Debug)]
+pub struct Progress {
+    bytes_consumed: u64,
+    messages_read: u64,
+    values_decoded: u64,
+    type_id: Option<TypeId>,
+}
+
+impl Progress {
+    /// Number of bytes read off the stream, up to the end of the last message.
+    pub fn bytes_consumed(&self) -> u64 {
+        self.bytes_consumed
+    }
+
+    /// Number of messages read, including type definitions.
+    pub fn messages_read(&self) -> u64 {
+        self.messages_read
+    }
+
+    /// Number of values handed out by the deserializer.
+    pub fn values_decoded(&self) -> u64 {
+        self.values_decoded
+    }
+
+    /// The type of the last message read, if any.
+    pub fn type_id(&self) -> Option<TypeId> {
+        self.type_id
+    }
+}
+
+pub(crate) struct ProgressHook {
+    every_messages: u64,
+    every_bytes: u64,
+    callback: Box<dyn FnMut(&Progress) + Send>,
+    progress: Progress,
+    last_messages: u64,
+    last_bytes: u64,
+}
+
+impl ProgressHook {
+    pub(crate) fn new(
+        every_messages: u64,
+        every_bytes: u64,
+        callback: Box<dyn FnMut(&Progress) + Send>,
+    ) -> Self {
+        ProgressHook {
+            every_messages,
+            every_bytes,
+            callback,
+            progress: Progress {
+                bytes_consumed: 0,
+                messages_read: 0,
+                values_decoded: 0,
+                type_id: None,
+            },
+            last_messages: 0,
+            last_bytes: 0,
+        }
+    }
+
+    pub(crate) fn message(&mut self, len: usize, type_id: i64) {
+        let progress = &mut self.progress;
+        progress.bytes_consumed += len as u64;
+        progress.messages_read += 1;
+        if type_id >= 0 {
+            progress.values_decoded += 1;
+        }
+        progress.type_id = Some(TypeId(type_id.abs()));
+
+        let due_messages = self.every_messages > 0
+            && progress.messages_read - self.last_messages >= self.every_messages;
+        let due_bytes =
+            self.every_bytes > 0 && progress.bytes_consumed - self.last_bytes >= self.every_bytes;
+        if due_messages || due_bytes {
+            self.report();
+        }
+    }
+
+    pub(crate) fn finish(&mut self) {
+        if self.progress.messages_read > self.last_messages {
+            self.report();
+        }
+    }
+
+    fn report(&mut self) {
+        self.last_messages = self.progress.messages_read;
+        self.last_bytes = self.progress.bytes_consumed;
+        (self.callback)(&self.progress);
+    }
+}
@@ -0,0 +1,122 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::Arc;
+
+use error::Error;
+use internal::types::{TypeId, Types, WireType};
+
+use crate::{error, internal};
+
+use super::TypeDefs;
+
+/// A source of type definitions not sent on the stream itself.
+///
+/// Producers sending their definitions out-of-band, e.g. to a schema
+/// registry service, leave it to consumers to find them. A deserializer
+/// with a registry set asks it for the definition of each type it has not
+/// seen defined, as the first value of that type arrives, along with the
+/// types it is made of:
+///
+/// ```
+/// # use gob::de::TypeDefs;
+/// # use gob::{StreamDeserializer, StreamSerializer};
+/// # let mut stream = StreamSerializer::new(Vec::new());
+/// # let blob = stream.export_type_defs::<Vec<bool>>().unwrap();
+/// # stream.serialize(&vec![true]).unwrap();
+/// # let input = stream.into_writer();
+/// // e.g. a table generated at build time
+/// let table = TypeDefs::from_bytes(&blob).unwrap();
+/// let mut stream = StreamDeserializer::new(&input[..]);
+/// stream.set_schema_registry(table);
+/// assert_eq!(stream.deserialize::<Vec<bool>>().unwrap(), Some(vec![true]));
+/// ```
+///
+/// Definitions fetched for the values of a stream are kept along with those
+/// read off it, so each is looked up once per deserializer. Types first met
+/// inside interface values are looked up for each such value, as nothing
+/// can be added to the definitions while a value is decoded. Closures
+/// taking a `TypeId` are registries as well, e.g. to query a service or a
+/// shared cache.
+///
+/// Registries only serve decoding: serializers keep numbering the types
+/// they send in a `Schema` of their own.
+pub trait SchemaRegistry: Send + Sync {
+    /// The definition of the type `id`, or `None` if the registry does not
+    /// know it either, in which case decoding its values fails.
+    fn lookup(&self, id: TypeId) -> Result<Option<WireType>, Error>;
+}
+
+impl SchemaRegistry for TypeDefs {
+    fn lookup(&self, id: TypeId) -> Result<Option<WireType>, Error> {
+        Ok(TypeDefs::lookup(self, id).cloned())
+    }
+}
+
+impl fmt::Debug for dyn SchemaRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("SchemaRegistry")
+    }
+}
+
+impl<S: SchemaRegistry + ?Sized> SchemaRegistry for Arc<S> {
+    fn lookup(&self, id: TypeId) -> Result<Option<WireType>, Error> {
+        (**self).lookup(id)
+    }
+}
+
+impl<F> SchemaRegistry for F
+where
+    F: Fn(TypeId) -> Result<Option<WireType>, Error> + Send + Sync,
+{
+    fn lookup(&self, id: TypeId) -> Result<Option<WireType>, Error> {
+        self(id)
+    }
+}
+
+// adds the definitions of `id` and of the types it is made of to `defs`,
+// as far as they are missing and the registry of `defs` knows them
+pub(crate) fn resolve(defs: &mut Types, id: TypeId) -> Result<(), Error> {
+    let registry = match defs.registry() {
+        Some(registry) if !id.is_builtin() && defs.lookup(id).is_none() => registry.clone(),
+        _ => return Ok(()),
+    };
+    let mut pending = vec![id];
+    while let Some(id) = pending.pop() {
+        if id.is_builtin() || defs.lookup(id).is_some() {
+            continue;
+        }
+        let def = match registry.lookup(id)? {
+            Some(def) => def,
+            None => continue,
+        };
+        if def.common().id != id {
+            return Err(Error::deserialize(format!(
+                "schema registry returned the definition of type {} for type {}",
+                def.common().id.0,
+                id.0
+            )));
+        }
+        match def {
+            WireType::Array(ref array_type) => pending.push(array_type.elem),
+            WireType::Slice(ref slice_type) => pending.push(slice_type.elem),
+            WireType::Map(ref map_type) => pending.extend(&[map_type.key, map_type.elem]),
+            WireType::Struct(ref struct_type) => {
+                pending.extend(struct_type.fields.iter().map(|field| field.id))
+            }
+            WireType::GobEncoder(_) => {}
+        }
+        defs.insert(def);
+    }
+    Ok(())
+}
+
+// like `resolve`, for definitions that cannot be added to, e.g. while a
+// value is decoded with them
+pub(crate) fn resolved(defs: &Types, id: TypeId) -> Result<Cow<'_, Types>, Error> {
+    if defs.registry().is_none() || id.is_builtin() || defs.lookup(id).is_some() {
+        return Ok(Cow::Borrowed(defs));
+    }
+    let mut defs = defs.clone();
+    resolve(&mut defs, id)?;
+    Ok(Cow::Owned(defs))
+}
@@ -0,0 +1,281 @@
+use std::io::{self, Cursor};
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use serde::de::{Deserialize, DeserializeSeed};
+
+use error::Error;
+use internal::gob::{parse_section, Message, MessageReadError};
+use internal::types::{TypeId, Types};
+use internal::utils::Bow;
+use mode::Mode;
+use shared_bytes::share_source;
+
+use crate::{error, internal, mode, shared_bytes};
+
+use super::{define, resolve, DecodeOptions, Deserializer, SchemaRegistry, TypeDefs};
+
+/// A stream deserializer over input that is in memory as a whole, like a
+/// memory-mapped file or a `bytes::Bytes`.
+///
+/// Sections are decoded right where they are, without being copied into a
+/// buffer first, and borrowed strings and byte slices point into `source`:
+///
+/// ```ignore
+/// let map = unsafe { memmap2::Mmap::map(&File::open("dump.gob")?)? };
+/// let mut stream = gob::de::SliceStreamDeserializer::new(map);
+/// while let Some(name) = stream.deserialize::<&str>()? {
+///     // ...
+/// }
+/// ```
+///
+/// Created with `from_bytes`, `shared_bytes::SharedBytes` values decoded
+/// through `deserialize` are slices of `source` rather than copies.
+pub struct SliceStreamDeserializer<B> {
+    source: B,
+    shared: Option<Bytes>,
+    defs: Types,
+    pos: usize,
+    opts: DecodeOptions,
+}
+
+impl SliceStreamDeserializer<Bytes> {
+    /// Like `new`, but byte slices decoded into `SharedBytes` share
+    /// `source`'s reference count instead of being copied.
+    pub fn from_bytes(source: Bytes) -> Self {
+        SliceStreamDeserializer {
+            shared: Some(source.clone()),
+            ..SliceStreamDeserializer::new(source)
+        }
+    }
+}
+
+impl<B: AsRef<[u8]>> SliceStreamDeserializer<B> {
+    pub fn new(source: B) -> Self {
+        SliceStreamDeserializer {
+            source,
+            shared: None,
+            defs: Types::new(),
+            pos: 0,
+            opts: DecodeOptions::default(),
+        }
+    }
+
+    /// Create a new stream deserializer that knows about all types in `defs`
+    /// up front.
+    pub fn with_type_defs(source: B, defs: TypeDefs) -> Self {
+        SliceStreamDeserializer {
+            defs: defs.types,
+            ..SliceStreamDeserializer::new(source)
+        }
+    }
+
+    /// Takes a snapshot of all types defined on the stream so far.
+    pub fn type_defs(&self) -> TypeDefs {
+        TypeDefs {
+            types: self.defs.clone(),
+        }
+    }
+
+    /// Sets which input is accepted from now on.
+    pub fn set_options(&mut self, options: DecodeOptions) {
+        self.opts = options;
+    }
+
+    /// Sets all options according to `mode`.
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.set_options(mode.into());
+    }
+
+    /// Looks up types not defined on the stream in `registry`, see
+    /// `StreamDeserializer::set_schema_registry`.
+    pub fn set_schema_registry<S>(&mut self, registry: S)
+    where
+        S: SchemaRegistry + 'static,
+    {
+        self.defs.set_registry(Arc::new(registry));
+    }
+
+    pub fn deserialize<'de, T>(&'de mut self) -> Result<Option<T>, Error>
+    where
+        T: Deserialize<'de>,
+    {
+        self.deserialize_seed(PhantomData)
+    }
+
+    /// Like `deserialize`, but driven by a `DeserializeSeed`.
+    pub fn deserialize_seed<'de, S>(&'de mut self, seed: S) -> Result<Option<S::Value>, Error>
+    where
+        S: DeserializeSeed<'de>,
+    {
+        let _source = share_source(self.shared.clone());
+        if let Some(deserializer) = self.deserializer()? {
+            Ok(Some(seed.deserialize(deserializer)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn deserializer<'de>(&'de mut self) -> Result<Option<Deserializer<'de>>, Error> {
+        let bytes = self.source.as_ref();
+        loop {
+            let rest = &bytes[self.pos..];
+            if rest.is_empty() {
+                return Ok(None);
+            }
+            let header = match parse_section(rest) {
+                Ok(header) => header,
+                Err(MessageReadError::Incomplete(_)) => {
+                    return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+                }
+                Err(err) => return Err(err.into()),
+            };
+            let payload = &rest[header.payload_range.clone()];
+            self.pos += header.payload_range.end;
+
+            if header.type_id >= 0 {
+                resolve(&mut self.defs, TypeId(header.type_id))?;
+                return Ok(Some(Deserializer {
+                    defs: Bow::Borrowed(&mut self.defs),
+                    msg: Message::new(Cursor::new(payload)),
+                    type_id: Some(TypeId(header.type_id)),
+                    end: Some(payload.len()),
+                    opts: self.opts,
+                    projection: None,
+                    skipped: None,
+                }));
+            }
+
+            let mut msg = Message::new(Cursor::new(payload));
+            define(&mut self.defs, &self.opts, header.type_id, &mut msg)?;
+        }
+    }
+
+    /// Number of bytes of `source` consumed so far.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    pub fn get_ref(&self) -> &B {
+        &self.source
+    }
+
+    pub fn into_inner(self) -> B {
+        self.source
+    }
+}
+
+/// An iterator over the values in a slice holding many concatenated
+/// sections, created by `Deserializer::from_slice_many`.
+///
+/// Type definitions are collected on the way, and each value is handed out
+/// as a `Deserializer` of its own. Values borrow from the slice rather than
+/// from the iterator, so they can be kept while iterating on:
+///
+/// ```
+/// # extern crate gob;
+/// # extern crate serde;
+/// use serde::Deserialize;
+///
+/// let mut stream = gob::StreamSerializer::new_with_write(Vec::new());
+/// stream.serialize(&"foo").unwrap();
+/// stream.serialize(&"bar").unwrap();
+/// let buffer = stream.into_writer();
+///
+/// let names = gob::Deserializer::from_slice_many(&buffer)
+///     .map(|de| <&str>::deserialize(de?))
+///     .collect::<Result<Vec<_>, _>>()
+///     .unwrap();
+/// assert_eq!(names, ["foo", "bar"]);
+/// ```
+///
+/// Iteration ends after the first error.
+pub struct SliceDeserializers<'de> {
+    input: &'de [u8],
+    defs: Types,
+    pos: usize,
+    opts: DecodeOptions,
+    failed: bool,
+}
+
+impl<'de> SliceDeserializers<'de> {
+    pub(crate) fn new(input: &'de [u8], defs: Types) -> Self {
+        SliceDeserializers {
+            input,
+            defs,
+            pos: 0,
+            opts: DecodeOptions::default(),
+            failed: false,
+        }
+    }
+
+    /// Sets which input is accepted by the deserializers handed out.
+    pub fn with_options(mut self, options: DecodeOptions) -> Self {
+        self.opts = options;
+        self
+    }
+
+    /// Sets all options according to `mode`.
+    pub fn with_mode(self, mode: Mode) -> Self {
+        self.with_options(mode.into())
+    }
+
+    /// Takes a snapshot of all types defined so far.
+    pub fn type_defs(&self) -> TypeDefs {
+        TypeDefs {
+            types: self.defs.clone(),
+        }
+    }
+
+    /// Number of bytes of the input consumed so far.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    fn next_value(&mut self) -> Result<Option<Deserializer<'de>>, Error> {
+        loop {
+            let rest = &self.input[self.pos..];
+            if rest.is_empty() {
+                return Ok(None);
+            }
+            let header = match parse_section(rest) {
+                Ok(header) => header,
+                Err(MessageReadError::Incomplete(_)) => {
+                    return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+                }
+                Err(err) => return Err(err.into()),
+            };
+            let payload = &rest[header.payload_range.clone()];
+            self.pos += header.payload_range.end;
+
+            if header.type_id >= 0 {
+                return Ok(Some(Deserializer {
+                    defs: Bow::Owned(self.defs.clone()),
+                    msg: Message::new(Cursor::new(payload)),
+                    type_id: Some(TypeId(header.type_id)),
+                    end: Some(payload.len()),
+                    opts: self.opts,
+                    projection: None,
+                    skipped: None,
+                }));
+            }
+
+            let mut msg = Message::new(Cursor::new(payload));
+            define(&mut self.defs, &self.opts, header.type_id, &mut msg)?;
+        }
+    }
+}
+
+impl<'de> Iterator for SliceDeserializers<'de> {
+    type Item = Result<Deserializer<'de>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let result = self.next_value();
+        self.failed = result.is_err();
+        result.transpose()
+    }
+}
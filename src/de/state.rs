@@ -0,0 +1,94 @@
+use std::io::Cursor;
+
+use error::Error;
+use internal::gob::Message;
+use internal::ser::serialize_wire_types::serialize_wire_type;
+use internal::types::{TypeId, Types};
+
+use crate::{error, internal};
+
+use super::{StreamDeserializer, TypeDefs};
+
+/// A checkpoint of a `StreamDeserializer`, taken by `save_state`.
+///
+/// A state holds all type definitions read so far, and the offset into
+/// the input up to which values were handed out. Restoring it with
+/// `StreamDeserializer::resume` carries on from the offset, without
+/// reading the definitions before it again:
+///
+/// ```
+/// # use gob::de::StreamState;
+/// # use gob::{StreamDeserializer, StreamSerializer};
+/// # use std::io::Cursor;
+/// # let mut stream = StreamSerializer::new(Vec::new());
+/// # for n in 0..3u64 { stream.serialize(&n).unwrap(); }
+/// # let input = stream.into_writer();
+/// let mut stream = StreamDeserializer::new(Cursor::new(&input));
+/// assert_eq!(stream.deserialize::<u64>().unwrap(), Some(0));
+/// let checkpoint = stream.save_state().unwrap().to_bytes().unwrap();
+///
+/// // e.g. after a restart
+/// let state = StreamState::from_bytes(&checkpoint).unwrap();
+/// let mut stream = StreamDeserializer::resume(Cursor::new(&input), state).unwrap();
+/// assert_eq!(stream.deserialize::<u64>().unwrap(), Some(1));
+/// ```
+#[derive(Clone, Debug)]
+pub struct StreamState {
+    pub(crate) defs: Types,
+    pub(crate) offset: u64,
+}
+
+impl StreamState {
+    /// The offset into the input at which decoding resumes.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// The type definitions read up to the offset.
+    pub fn type_defs(&self) -> TypeDefs {
+        TypeDefs {
+            types: self.defs.clone(),
+        }
+    }
+
+    /// Encodes the state, e.g. to write it to a checkpoint file.
+    ///
+    /// The encoding is a gob stream of its own: the type definitions,
+    /// followed by the offset as an unsigned integer.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        let mut bytes = Vec::new();
+        for wire_type in self.defs.iter() {
+            push_section(&mut bytes, &serialize_wire_type(wire_type)?);
+        }
+        let mut value = Message::new(Vec::new());
+        value.write_int(TypeId::UINT.0);
+        value.write_uint(0);
+        value.write_uint(self.offset);
+        push_section(&mut bytes, &value.into_inner());
+        Ok(bytes)
+    }
+
+    /// Decodes a state encoded by `to_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<StreamState, Error> {
+        let mut stream = StreamDeserializer::new(Cursor::new(bytes));
+        let offset = match stream.deserialize::<u64>()? {
+            Some(offset) => offset,
+            None => return Err(Error::deserialize("stream state without an offset")),
+        };
+        if stream.deserialize::<u64>()?.is_some() {
+            return Err(Error::deserialize("trailing data after stream state"));
+        }
+        Ok(StreamState {
+            defs: stream.defs,
+            offset,
+        })
+    }
+}
+
+// appends `payload` as a section, prefixed by its length
+fn push_section(bytes: &mut Vec<u8>, payload: &[u8]) {
+    let mut len = Message::new(Vec::new());
+    len.write_uint(payload.len() as u64);
+    bytes.extend_from_slice(&len.into_inner());
+    bytes.extend_from_slice(payload);
+}
@@ -0,0 +1,123 @@
+use std::time::Duration;
+
+use error::Error;
+use schema::TypeId;
+
+use crate::{error, schema};
+
+/// Counters kept by a `StreamDeserializer`, e.g. to export as ingest
+/// metrics.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DecodeStats {
+    bytes_consumed: u64,
+    sections_read: u64,
+    type_definitions: u64,
+    values_decoded: u64,
+    errors: u64,
+    decode_time: Duration,
+}
+
+impl DecodeStats {
+    /// Number of bytes of all sections read, including their headers.
+    pub fn bytes_consumed(&self) -> u64 {
+        self.bytes_consumed
+    }
+
+    /// Number of sections read, type definitions and values alike.
+    pub fn sections_read(&self) -> u64 {
+        self.sections_read
+    }
+
+    /// Number of type definitions read.
+    pub fn type_definitions(&self) -> u64 {
+        self.type_definitions
+    }
+
+    /// Number of values successfully decoded, counting those handed out by
+    /// `deserializer` and skipped by `skip` as well.
+    pub fn values_decoded(&self) -> u64 {
+        self.values_decoded
+    }
+
+    /// Number of errors returned while reading or decoding a value.
+    pub fn errors(&self) -> u64 {
+        self.errors
+    }
+
+    /// Time spent reading and decoding values. For values handed out by
+    /// `deserializer`, only reading them counts.
+    pub fn decode_time(&self) -> Duration {
+        self.decode_time
+    }
+}
+
+/// Gets told about what a `StreamDeserializer` reads, as it happens.
+///
+/// All methods do nothing by default.
+pub trait DecodeObserver {
+    /// A section of `len` bytes defining type `id` was read.
+    fn on_type_definition(&mut self, id: TypeId, len: usize) {
+        let _ = (id, len);
+    }
+
+    /// A value of type `id`, read from a section of `len` bytes, was decoded
+    /// in `elapsed`, or handed out by `deserializer` or skipped by `skip`.
+    fn on_value(&mut self, id: TypeId, len: usize, elapsed: Duration) {
+        let _ = (id, len, elapsed);
+    }
+
+    /// Reading or decoding a value failed with `err`.
+    fn on_error(&mut self, err: &Error) {
+        let _ = err;
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Observation {
+    pub(crate) stats: DecodeStats,
+    pub(crate) observer: Option<Box<dyn DecodeObserver + Send>>,
+}
+
+impl Observation {
+    pub(crate) fn section(&mut self, len: usize, type_id: i64) {
+        self.stats.bytes_consumed += len as u64;
+        self.stats.sections_read += 1;
+        if type_id < 0 {
+            self.stats.type_definitions += 1;
+            if let Some(ref mut observer) = self.observer {
+                observer.on_type_definition(TypeId(-type_id), len);
+            }
+        }
+    }
+
+    pub(crate) fn value(&mut self, type_id: TypeId, len: usize, elapsed: Duration) {
+        self.stats.values_decoded += 1;
+        self.stats.decode_time += elapsed;
+        if let Some(ref mut observer) = self.observer {
+            observer.on_value(type_id, len, elapsed);
+        }
+    }
+
+    // records how reading the value of type `type_id`, from a section of
+    // `len` bytes, came out
+    pub(crate) fn outcome<T>(
+        &mut self,
+        result: &Result<T, Error>,
+        type_id: TypeId,
+        len: usize,
+        elapsed: Duration,
+    ) {
+        match result {
+            Ok(_) => self.value(type_id, len, elapsed),
+            Err(err) => self.error(err, elapsed),
+        }
+    }
+
+    pub(crate) fn error(&mut self, err: &Error, elapsed: Duration) {
+        self.stats.errors += 1;
+        self.stats.decode_time += elapsed;
+        if let Some(ref mut observer) = self.observer {
+            observer.on_error(err);
+        }
+    }
+}
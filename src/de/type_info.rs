@@ -0,0 +1,129 @@
+use std::borrow::Cow;
+
+use internal::types::{
+    ArrayType, CommonType, FieldType, GobEncoderType, MapType, SliceType, StructType, TypeId,
+    Types, WireType,
+};
+
+use crate::internal;
+
+/// Describes a type as it was sent on the wire.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TypeInfo {
+    id: TypeId,
+    name: String,
+    structure: Structure,
+}
+
+/// The layout of a type, referring to other types by id.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Structure {
+    /// One of Go's predeclared types, or `interface{}`.
+    Builtin,
+    Array {
+        len: i64,
+        elem: TypeId,
+    },
+    Slice {
+        elem: TypeId,
+    },
+    Map {
+        key: TypeId,
+        elem: TypeId,
+    },
+    Struct {
+        fields: Vec<(String, TypeId)>,
+    },
+    /// A type encoding itself, like Go's `GobEncoder`, whose values are
+    /// sent as byte slices.
+    GobEncoder,
+}
+
+impl TypeInfo {
+    pub(crate) fn lookup(id: TypeId, defs: &Types) -> Option<TypeInfo> {
+        let builtin = match id {
+            TypeId::BOOL => Some("bool"),
+            TypeId::INT => Some("int"),
+            TypeId::UINT => Some("uint"),
+            TypeId::FLOAT => Some("float64"),
+            TypeId::BYTES => Some("[]byte"),
+            TypeId::STRING => Some("string"),
+            TypeId::COMPLEX => Some("complex128"),
+            TypeId::INTERFACE => Some("interface"),
+            _ => None,
+        };
+        if let Some(name) = builtin {
+            return Some(TypeInfo {
+                id,
+                name: name.to_owned(),
+                structure: Structure::Builtin,
+            });
+        }
+
+        let def = defs.lookup(id)?;
+        let structure = match *def {
+            WireType::Array(ref array_type) => Structure::Array {
+                len: array_type.len,
+                elem: array_type.elem,
+            },
+            WireType::Slice(ref slice_type) => Structure::Slice {
+                elem: slice_type.elem,
+            },
+            WireType::Map(ref map_type) => Structure::Map {
+                key: map_type.key,
+                elem: map_type.elem,
+            },
+            WireType::Struct(ref struct_type) => Structure::Struct {
+                fields: struct_type
+                    .fields
+                    .iter()
+                    .map(|field| (field.name.to_string(), field.id))
+                    .collect(),
+            },
+            WireType::GobEncoder(_) => Structure::GobEncoder,
+        };
+        Some(TypeInfo {
+            id,
+            name: def.common().name.to_string(),
+            structure,
+        })
+    }
+
+    // the definition describing the type on the wire, unless it is builtin
+    pub(crate) fn wire_type(&self) -> Option<WireType> {
+        let common = CommonType {
+            name: Cow::Owned(self.name.clone()),
+            id: self.id,
+        };
+        Some(match self.structure {
+            Structure::Builtin => return None,
+            Structure::Array { len, elem } => WireType::Array(ArrayType { common, elem, len }),
+            Structure::Slice { elem } => WireType::Slice(SliceType { common, elem }),
+            Structure::Map { key, elem } => WireType::Map(MapType { common, key, elem }),
+            Structure::Struct { ref fields } => WireType::Struct(StructType {
+                common,
+                fields: fields
+                    .iter()
+                    .map(|(name, id)| FieldType {
+                        name: Cow::Owned(name.clone()),
+                        id: *id,
+                    })
+                    .collect(),
+            }),
+            Structure::GobEncoder => WireType::GobEncoder(GobEncoderType { common }),
+        })
+    }
+
+    pub fn id(&self) -> TypeId {
+        self.id
+    }
+
+    /// The name the type was defined under.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn structure(&self) -> &Structure {
+        &self.structure
+    }
+}
@@ -0,0 +1,152 @@
+//! Wire format debugging
+//!
+//! `dump` walks a gob stream section by section and prints an annotated
+//! hex dump of every message:
+//!
+//! ```text
+//! section 1 at 0x00000020: 8 bytes, value of type 65
+//! 00000020  07                         message length 7
+//! 00000021  ff 82                      type id 65
+//! 00000023  01                         field +1 X (#0)
+//! 00000024  2c                           int 22
+//! 00000025  01                         field +1 Y (#1)
+//! 00000026  42                           int 33
+//! 00000027  00                         end of Point
+//! ```
+//!
+//! Type definitions are decoded as they are encountered, so values that
+//! follow them are broken down field by field. Bytes left over at the end
+//! of a message are reported explicitly, since that is what Go's decoder
+//! complains about as "extra data in buffer".
+//!
+//! `validate` performs the same walk without printing anything, and
+//! summarizes the stream in a `StreamReport`.
+
+use std::io::{Cursor, Read, Write};
+
+use bytes::Buf;
+use serde::Deserialize;
+
+use de::DEFAULT_SCOPE;
+use error::Error;
+use internal::de::FieldValueDeserializer;
+use internal::gob::{Message, Stream};
+use internal::types::{TypeId, Types, WireType};
+use internal::utils::Buffer;
+
+use crate::{de, error, internal};
+
+mod validate;
+mod walk;
+
+pub use self::validate::{validate, StreamReport, ValidationError};
+use self::walk::{Sink, Walker};
+
+const HEX_COLUMNS: usize = 8;
+
+/// Prints an annotated dump of the gob stream in `reader` to `writer`.
+///
+/// Stops at the first malformed section, after printing as much of it as
+/// could be decoded, and returns the error.
+pub fn dump<R: Read, W: Write>(reader: R, writer: W) -> Result<(), Error> {
+    let mut sink = DumpSink(writer);
+    let mut stream = Stream::new(reader);
+    let mut buffer = Buffer::new();
+    let mut defs = Types::new();
+    let mut offset = 0;
+    let mut section_no = 0;
+
+    loop {
+        let header = match stream.read_section(&mut buffer)? {
+            Some(header) => header,
+            None => return Ok(()),
+        };
+        let section = &buffer.bytes()[..header.payload_range.end];
+
+        if header.type_id < 0 {
+            writeln!(
+                sink.0,
+                "section {} at 0x{:08x}: {} bytes, definition of type {}",
+                section_no,
+                offset,
+                section.len(),
+                -header.type_id
+            )?;
+        } else {
+            writeln!(
+                sink.0,
+                "section {} at 0x{:08x}: {} bytes, value of type {}",
+                section_no,
+                offset,
+                section.len(),
+                header.type_id
+            )?;
+        }
+
+        {
+            let mut walker = Walker::new(section, offset, &defs, &mut sink);
+            match walker.section() {
+                Ok(0) => {}
+                Ok(extra) => walker.rest(format!(
+                    "!! {} bytes of extra data at end of message",
+                    extra
+                ))?,
+                Err(err) => {
+                    walker.rest(format!("!! {}", err))?;
+                    return Err(err);
+                }
+            }
+        }
+
+        if header.type_id < 0 {
+            let payload = &section[header.payload_range.clone()];
+            defs.insert(read_definition(-header.type_id, payload, &defs)?);
+        }
+
+        writeln!(sink.0)?;
+        offset += section.len();
+        section_no += 1;
+        buffer.advance(section.len());
+    }
+}
+
+fn read_definition(type_id: i64, payload: &[u8], defs: &Types) -> Result<WireType, Error> {
+    let mut msg = Message::new(Cursor::new(payload));
+    let de = FieldValueDeserializer::new(TypeId::WIRE_TYPE, defs, DEFAULT_SCOPE, &mut msg);
+    let wire_type = WireType::deserialize(de)?;
+    if type_id != wire_type.common().id.0 {
+        return Err(Error::deserialize("type id mismatch"));
+    }
+    Ok(wire_type)
+}
+
+struct DumpSink<W>(W);
+
+impl<W: Write> Sink for DumpSink<W> {
+    // prints the raw bytes of an item followed by its annotation;
+    // long runs of bytes are elided
+    fn item<F>(&mut self, offset: usize, bytes: &[u8], depth: usize, label: F) -> Result<(), Error>
+    where
+        F: FnOnce() -> String,
+    {
+        let mut hex = bytes
+            .iter()
+            .take(HEX_COLUMNS)
+            .map(|b| format!("{:02x}", b))
+            .collect::<Vec<_>>()
+            .join(" ");
+        if bytes.len() > HEX_COLUMNS {
+            hex.push_str(" ..");
+        }
+        writeln!(
+            self.0,
+            "{:08x}  {:<26} {:indent$}{}",
+            offset,
+            hex,
+            "",
+            label(),
+            indent = depth * 2
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,205 @@
+use std::collections::BTreeSet;
+use std::io::{self, Read};
+
+use bytes::Buf;
+
+use error::{Error, ErrorKind};
+use internal::gob::Stream;
+use internal::types::{TypeId, Types, WireType};
+use internal::utils::Buffer;
+
+use crate::{error, internal};
+
+use super::read_definition;
+use super::walk::{Sink, Walker};
+
+/// The first problem found by `validate`.
+#[derive(Clone, Debug)]
+pub struct ValidationError {
+    offset: u64,
+    section: u64,
+    message: String,
+}
+
+impl ValidationError {
+    /// Offset into the stream at which decoding stopped.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Index of the section the error occurred in.
+    pub fn section(&self) -> u64 {
+        self.section
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+/// Summary of a stream walked by `validate`.
+#[derive(Clone, Debug, Default)]
+pub struct StreamReport {
+    sections: u64,
+    definitions: u64,
+    values: u64,
+    bytes: u64,
+    types: Vec<(TypeId, String)>,
+    error: Option<ValidationError>,
+}
+
+impl StreamReport {
+    /// Number of well-formed sections, type definitions and values alike.
+    pub fn sections(&self) -> u64 {
+        self.sections
+    }
+
+    /// Number of well-formed type definition sections.
+    pub fn definitions(&self) -> u64 {
+        self.definitions
+    }
+
+    /// Number of well-formed value sections.
+    pub fn values(&self) -> u64 {
+        self.values
+    }
+
+    /// Number of bytes taken up by well-formed sections.
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    /// Ids and names of all types defined in the stream, in order.
+    pub fn types(&self) -> &[(TypeId, String)] {
+        &self.types
+    }
+
+    /// The first error in the stream, if any.
+    pub fn error(&self) -> Option<&ValidationError> {
+        self.error.as_ref()
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.error.is_none()
+    }
+
+    fn fail<S: Into<String>>(mut self, offset: usize, message: S) -> StreamReport {
+        self.error = Some(ValidationError {
+            offset: offset as u64,
+            section: self.sections,
+            message: message.into(),
+        });
+        self
+    }
+}
+
+struct Silent;
+
+impl Sink for Silent {
+    #[inline]
+    fn item<F>(&mut self, _: usize, _: &[u8], _: usize, _: F) -> Result<(), Error>
+    where
+        F: FnOnce() -> String,
+    {
+        Ok(())
+    }
+}
+
+/// Walks a whole gob stream without decoding values, checking that it is
+/// well-formed.
+///
+/// This checks that all integers are encoded correctly, that every message
+/// is consumed exactly by its payload, that the types of values (and all
+/// types they refer to) are defined before the values are sent, and that
+/// struct fields are in range.
+///
+/// Problems with the stream itself end up in the report, which stops at
+/// the first one. An error is only returned if reading fails.
+pub fn validate<R: Read>(reader: R) -> Result<StreamReport, Error> {
+    let mut report = StreamReport::default();
+    let mut stream = Stream::new(reader);
+    let mut buffer = Buffer::new();
+    let mut defs = Types::new();
+    let mut offset = 0;
+
+    loop {
+        let header = match stream.read_section(&mut buffer) {
+            Ok(Some(header)) => header,
+            Ok(None) => return Ok(report),
+            Err(err) => match err.kind() {
+                ErrorKind::Io(io::ErrorKind::UnexpectedEof) => {
+                    return Ok(report.fail(offset, "stream ends in the middle of a message"))
+                }
+                ErrorKind::Io(_) => return Err(err),
+                _ => return Ok(report.fail(offset, err.to_string())),
+            },
+        };
+        let section = &buffer.bytes()[..header.payload_range.end];
+
+        if header.type_id >= 0 {
+            if let Err(message) = check_defined(TypeId(header.type_id), &defs) {
+                return Ok(report.fail(offset, message));
+            }
+        } else if header.type_id == i64::MIN || defs.lookup(TypeId(-header.type_id)).is_some() {
+            return Ok(report.fail(offset, format!("type {} redefined", -header.type_id)));
+        }
+
+        {
+            let mut sink = Silent;
+            let mut walker = Walker::new(section, offset, &defs, &mut sink);
+            match walker.section() {
+                Ok(0) => {}
+                Ok(extra) => {
+                    let message = format!("{} bytes of extra data at end of message", extra);
+                    return Ok(report.fail(walker.offset(), message));
+                }
+                Err(err) => return Ok(report.fail(walker.offset(), err.to_string())),
+            }
+        }
+
+        if header.type_id < 0 {
+            let payload = &section[header.payload_range.clone()];
+            match read_definition(-header.type_id, payload, &defs) {
+                Ok(wire_type) => {
+                    let common = wire_type.common();
+                    report.types.push((common.id, common.name.to_string()));
+                    defs.insert(wire_type);
+                }
+                Err(err) => return Ok(report.fail(offset, err.to_string())),
+            }
+            report.definitions += 1;
+        } else {
+            report.values += 1;
+        }
+
+        report.sections += 1;
+        report.bytes += section.len() as u64;
+        offset += section.len();
+        buffer.advance(section.len());
+    }
+}
+
+// checks that `id` and every type reachable from it is defined
+fn check_defined(id: TypeId, defs: &Types) -> Result<(), String> {
+    let mut seen = BTreeSet::new();
+    let mut pending = vec![id];
+    while let Some(id) = pending.pop() {
+        if id.0 >= TypeId::BOOL.0 && id.0 <= TypeId::INTERFACE.0 || !seen.insert(id) {
+            continue;
+        }
+        match defs.lookup(id) {
+            Some(WireType::Array(array_type)) => pending.push(array_type.elem),
+            Some(WireType::Slice(slice_type)) => pending.push(slice_type.elem),
+            Some(WireType::Map(map_type)) => {
+                pending.push(map_type.key);
+                pending.push(map_type.elem);
+            }
+            Some(WireType::Struct(struct_type)) => {
+                pending.extend(struct_type.fields.iter().map(|field| field.id))
+            }
+            Some(WireType::GobEncoder(_)) => {}
+            None => return Err(format!("type {} used before it is defined", id.0)),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,261 @@
+use std::io::Cursor;
+
+use bytes::Buf;
+
+use error::Error;
+use internal::gob::{Message, MessageReadError};
+use internal::types::{TypeId, Types, WireType};
+
+use crate::{error, internal};
+
+/// Receives every item the walker decodes.
+pub(crate) trait Sink {
+    /// Called with the stream offset and raw bytes of an item; `label`
+    /// describes the item and should only be called if needed.
+    fn item<F>(&mut self, offset: usize, bytes: &[u8], depth: usize, label: F) -> Result<(), Error>
+    where
+        F: FnOnce() -> String;
+}
+
+/// Walks the payload of a single section, guided by the type definitions
+/// seen so far, without materializing any values.
+pub(crate) struct Walker<'a, S: 'a> {
+    section: &'a [u8],
+    base: usize,
+    defs: &'a Types,
+    msg: Message<Cursor<&'a [u8]>>,
+    sink: &'a mut S,
+}
+
+impl<'a, S: Sink> Walker<'a, S> {
+    pub(crate) fn new(section: &'a [u8], base: usize, defs: &'a Types, sink: &'a mut S) -> Self {
+        Walker {
+            section,
+            base,
+            defs,
+            msg: Message::new(Cursor::new(section)),
+            sink,
+        }
+    }
+
+    /// Walks a whole section, returning the number of bytes left over
+    /// at the end of the message.
+    pub(crate) fn section(&mut self) -> Result<usize, Error> {
+        self.read(
+            0,
+            |msg| msg.read_uint(),
+            |len| format!("message length {}", len),
+        )?;
+        let type_id = self.read(0, |msg| msg.read_int(), |id| format!("type id {}", id))?;
+
+        if type_id < 0 {
+            self.field_value(TypeId::WIRE_TYPE, 0)?;
+        } else {
+            self.value(TypeId(type_id), 0)?;
+        }
+
+        Ok(self.msg.get_ref().remaining())
+    }
+
+    // a top-level (or interface) value: structs are encoded directly,
+    // everything else is preceded by a zero field delta
+    fn value(&mut self, type_id: TypeId, depth: usize) -> Result<(), Error> {
+        if let Some(WireType::Struct(_)) = self.defs.lookup(type_id) {
+            return self.field_value(type_id, depth);
+        }
+        let delta = self.read(depth, |msg| msg.read_uint(), |_| "singleton".to_owned())?;
+        if delta != 0 {
+            return Err(Error::deserialize("neither a singleton nor a struct value"));
+        }
+        self.field_value(type_id, depth)
+    }
+
+    fn field_value(&mut self, type_id: TypeId, depth: usize) -> Result<(), Error> {
+        match type_id {
+            TypeId::BOOL => {
+                self.read(depth, |msg| msg.read_bool(), |v| format!("bool {}", v))?;
+            }
+            TypeId::INT => {
+                self.read(depth, |msg| msg.read_int(), |v| format!("int {}", v))?;
+            }
+            TypeId::UINT => {
+                self.read(depth, |msg| msg.read_uint(), |v| format!("uint {}", v))?;
+            }
+            TypeId::FLOAT => {
+                self.read(depth, |msg| msg.read_float(), |v| format!("float {:?}", v))?;
+            }
+            TypeId::BYTES => {
+                self.bytes(depth, |bytes| format!("{} bytes", bytes.len()))?;
+            }
+            TypeId::STRING => {
+                self.bytes(depth, |bytes| {
+                    format!("string {:?}", String::from_utf8_lossy(bytes))
+                })?;
+            }
+            TypeId::COMPLEX => {
+                self.read(depth, |msg| msg.read_float(), |v| format!("real {:?}", v))?;
+                self.read(depth, |msg| msg.read_float(), |v| format!("imag {:?}", v))?;
+            }
+            TypeId::INTERFACE => self.interface(depth)?,
+            _ => match self.defs.lookup(type_id) {
+                Some(WireType::Struct(struct_type)) => {
+                    let mut field_no = -1i64;
+                    loop {
+                        let delta = self.read(
+                            depth,
+                            |msg| msg.read_uint(),
+                            |delta| {
+                                if *delta == 0 {
+                                    return format!("end of {}", struct_type.common.name);
+                                }
+                                match struct_type.fields.get((field_no + *delta as i64) as usize) {
+                                    Some(field) => format!(
+                                        "field +{} {} (#{})",
+                                        delta,
+                                        field.name,
+                                        field_no + *delta as i64
+                                    ),
+                                    None => format!("field +{} out of range", delta),
+                                }
+                            },
+                        )?;
+                        if delta == 0 {
+                            break;
+                        }
+                        field_no += delta as i64;
+                        let field = struct_type.fields.get(field_no as usize).ok_or_else(|| {
+                            Error::deserialize(format!(
+                                "field number overflow ({}) on type {}",
+                                field_no, struct_type.common.name
+                            ))
+                        })?;
+                        self.field_value(field.id, depth + 1)?;
+                    }
+                }
+                Some(WireType::Slice(slice_type)) => {
+                    let len = self.read_len(depth, "elements")?;
+                    for _ in 0..len {
+                        self.field_value(slice_type.elem, depth + 1)?;
+                    }
+                }
+                Some(WireType::Array(array_type)) => {
+                    let len = self.read_len(depth, "elements")?;
+                    for _ in 0..len {
+                        self.field_value(array_type.elem, depth + 1)?;
+                    }
+                }
+                Some(WireType::Map(map_type)) => {
+                    let len = self.read_len(depth, "entries")?;
+                    for _ in 0..len {
+                        self.field_value(map_type.key, depth + 1)?;
+                        self.field_value(map_type.elem, depth + 1)?;
+                    }
+                }
+                Some(WireType::GobEncoder(gob_encoder_type)) => {
+                    self.bytes(depth, |bytes| {
+                        format!(
+                            "{} encoded bytes of {}",
+                            bytes.len(),
+                            gob_encoder_type.common.name
+                        )
+                    })?;
+                }
+                None => return Err(Error::deserialize(format!("unknown type id {}", type_id.0))),
+            },
+        }
+        Ok(())
+    }
+
+    fn interface(&mut self, depth: usize) -> Result<(), Error> {
+        let name = self.bytes(depth, |name| {
+            if name.is_empty() {
+                "nil interface".to_owned()
+            } else {
+                format!("interface name {:?}", String::from_utf8_lossy(name))
+            }
+        })?;
+        if name.is_empty() {
+            return Ok(());
+        }
+        let type_id = self.read(
+            depth,
+            |msg| msg.read_int(),
+            |id| format!("concrete type id {}", id),
+        )?;
+        if type_id < 0 {
+            return Err(Error::deserialize(
+                "type definitions inside interface values are not supported",
+            ));
+        }
+        self.read(
+            depth,
+            |msg| msg.read_bytes_len(),
+            |len| format!("value length {}", len),
+        )?;
+        self.value(TypeId(type_id), depth + 1)
+    }
+
+    fn bytes<F>(&mut self, depth: usize, label: F) -> Result<&'a [u8], Error>
+    where
+        F: FnOnce(&[u8]) -> String,
+    {
+        let section = self.section;
+        let start = self.position();
+        let len = self.msg.read_bytes_len()?;
+        let end = self.position() + len;
+        self.msg.get_mut().advance(len);
+        let bytes = &section[end - len..end];
+        self.line(start, depth, || label(bytes))?;
+        Ok(bytes)
+    }
+
+    fn read<T, R, F>(&mut self, depth: usize, read: R, label: F) -> Result<T, Error>
+    where
+        R: FnOnce(&mut Message<Cursor<&'a [u8]>>) -> Result<T, MessageReadError>,
+        F: FnOnce(&T) -> String,
+    {
+        let start = self.position();
+        let value = read(&mut self.msg)?;
+        self.line(start, depth, || label(&value))?;
+        Ok(value)
+    }
+
+    // reads a sequence length, which can never exceed the number of
+    // bytes left since every element takes up at least one byte
+    fn read_len(&mut self, depth: usize, unit: &str) -> Result<u64, Error> {
+        let len = self.read(
+            depth,
+            |msg| msg.read_uint(),
+            |len| format!("{} {}", len, unit),
+        )?;
+        if len > self.msg.get_ref().remaining() as u64 {
+            return Err(MessageReadError::Incomplete(None).into());
+        }
+        Ok(len)
+    }
+
+    /// Consumes the rest of the section, passing it to the sink.
+    pub(crate) fn rest(&mut self, label: String) -> Result<(), Error> {
+        let start = self.position();
+        let len = self.section.len();
+        self.msg.get_mut().set_position(len as u64);
+        self.line(start, 0, || label)
+    }
+
+    /// Offset of the next unread byte within the stream.
+    pub(crate) fn offset(&self) -> usize {
+        self.base + self.position()
+    }
+
+    fn position(&self) -> usize {
+        self.msg.get_ref().position() as usize
+    }
+
+    fn line<F>(&mut self, start: usize, depth: usize, label: F) -> Result<(), Error>
+    where
+        F: FnOnce() -> String,
+    {
+        let bytes = &self.section[start..self.position()];
+        self.sink.item(self.base + start, bytes, depth, label)
+    }
+}
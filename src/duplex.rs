@@ -0,0 +1,261 @@
+//! Full-duplex connections
+//!
+//! When both ends of a connection send and receive gob streams, each
+//! direction is a stream of its own, with its own type definitions. A
+//! `Duplex` splits a socket into a writing and a reading half, which can be
+//! moved to different threads and used at the same time:
+//!
+//! ```no_run
+//! # use gob::duplex::Duplex;
+//! # use std::net::TcpStream;
+//! # use std::thread;
+//! let socket = TcpStream::connect("127.0.0.1:1234").unwrap();
+//! let (mut writer, mut reader) = Duplex::new(socket).split();
+//! let sending = thread::spawn(move || writer.serialize(&"ping"));
+//! let reply = reader.deserialize::<String>().unwrap();
+//! # let _ = (sending, reply);
+//! ```
+//!
+//! The definitions the reading half learns are published to a
+//! `TypeRegistry`. Protocols sending their definitions once per session
+//! rather than once per connection can hand the registry of one
+//! connection to the next, which then decodes values of the types defined
+//! on the first.
+
+use std::io::{self, Read, Write};
+use std::sync::{Arc, PoisonError, RwLock};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_schema::SchemaSerialize;
+
+use de::{SchemaRegistry, StreamDeserializer, TypeDefs};
+use error::Error;
+use internal::types::{TypeId, Types, WireType};
+use ser::{OutputWrite, StreamSerializer};
+
+use crate::{de, error, internal, ser};
+
+/// The type definitions received on a connection, shared between the
+/// halves of a `Duplex` and whoever else holds a clone.
+///
+/// Connections sharing a registry add the definitions they receive to it;
+/// a definition conflicting with one published before fails the value it
+/// arrived with. As a `SchemaRegistry`, it lends the definitions to other
+/// streams.
+#[derive(Clone, Debug, Default)]
+pub struct TypeRegistry {
+    types: Arc<RwLock<Types>>,
+}
+
+impl TypeRegistry {
+    pub fn new() -> TypeRegistry {
+        TypeRegistry::default()
+    }
+
+    /// Creates a registry knowing the definitions in `defs` already.
+    pub fn with_type_defs(defs: TypeDefs) -> TypeRegistry {
+        TypeRegistry {
+            types: Arc::new(RwLock::new(defs.types)),
+        }
+    }
+
+    /// A snapshot of the definitions received so far.
+    pub fn type_defs(&self) -> TypeDefs {
+        let types = self.types.read().unwrap_or_else(PoisonError::into_inner);
+        TypeDefs {
+            types: types.clone(),
+        }
+    }
+
+    // adds the definitions in `types` that are not in `seen`, the ones it
+    // was published with before, to the registry, failing on any that
+    // conflict with one published before, e.g. by another connection
+    // sharing the registry
+    fn publish(&self, types: &Types, seen: &Types) -> Result<(), Error> {
+        let received: Vec<&WireType> = types
+            .iter()
+            .filter(|def| seen.lookup(def.common().id) != Some(*def))
+            .collect();
+        if received.is_empty() {
+            return Ok(());
+        }
+        let mut published = self.types.write().unwrap_or_else(PoisonError::into_inner);
+        if published.iter().next().is_none() {
+            *published = types.definitions();
+            return Ok(());
+        }
+        let mut missing = Vec::new();
+        for def in received {
+            match published.lookup(def.common().id) {
+                Some(known) if known != def => {
+                    return Err(Error::deserialize(format!(
+                        "type {} received as {} conflicts with its published definition",
+                        def.common().id.0,
+                        def.common().name
+                    )));
+                }
+                Some(_) => {}
+                None => missing.push(def.clone()),
+            }
+        }
+        for def in missing {
+            published.insert(def);
+        }
+        Ok(())
+    }
+}
+
+impl SchemaRegistry for TypeRegistry {
+    fn lookup(&self, id: TypeId) -> Result<Option<WireType>, Error> {
+        if id.is_builtin() {
+            return Ok(None);
+        }
+        let types = self.types.read().unwrap_or_else(PoisonError::into_inner);
+        Ok(types.lookup(id).cloned())
+    }
+}
+
+/// A socket split into a `DuplexWriter` and a `DuplexReader`.
+///
+/// Sockets are shared between the halves, which read and write through
+/// `&S`, as e.g. `TcpStream` and `UnixStream` allow.
+pub struct Duplex<S> {
+    socket: S,
+    registry: TypeRegistry,
+}
+
+impl<S> Duplex<S>
+where
+    for<'a> &'a S: Read + Write,
+{
+    pub fn new(socket: S) -> Self {
+        Duplex::with_registry(socket, TypeRegistry::new())
+    }
+
+    /// Creates a duplex whose reading half starts out with the definitions
+    /// in `registry`, and adds those it receives to it.
+    pub fn with_registry(socket: S, registry: TypeRegistry) -> Self {
+        Duplex { socket, registry }
+    }
+
+    pub fn registry(&self) -> &TypeRegistry {
+        &self.registry
+    }
+
+    /// Splits the socket into its writing and reading half.
+    pub fn split(self) -> (DuplexWriter<S>, DuplexReader<S>) {
+        let socket = Arc::new(self.socket);
+        let writer = DuplexWriter {
+            stream: StreamSerializer::new(Half(socket.clone())),
+            registry: self.registry.clone(),
+        };
+        let defs = self.registry.type_defs();
+        let reader = DuplexReader {
+            published: defs.types.clone(),
+            stream: StreamDeserializer::with_type_defs(Half(socket), defs),
+            registry: self.registry,
+        };
+        (writer, reader)
+    }
+}
+
+/// The socket of a `Duplex`, as seen by one of its halves.
+pub struct Half<S>(Arc<S>);
+
+impl<S> Read for Half<S>
+where
+    for<'a> &'a S: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        (&*self.0).read(buf)
+    }
+}
+
+impl<S> Write for Half<S>
+where
+    for<'a> &'a S: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (&*self.0).write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        (&*self.0).flush()
+    }
+}
+
+/// The writing half of a `Duplex`.
+pub struct DuplexWriter<S> {
+    stream: StreamSerializer<OutputWrite<Half<S>>>,
+    registry: TypeRegistry,
+}
+
+impl<S> DuplexWriter<S>
+where
+    for<'a> &'a S: Write,
+{
+    /// Serialize a value onto the connection, see
+    /// `StreamSerializer::serialize`.
+    pub fn serialize<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: SchemaSerialize + Serialize,
+    {
+        self.stream.serialize(value)
+    }
+
+    /// The stream written to, e.g. to set encodings or pin type ids.
+    pub fn get_mut(&mut self) -> &mut StreamSerializer<OutputWrite<Half<S>>> {
+        &mut self.stream
+    }
+
+    /// The definitions received by the reading half so far.
+    pub fn registry(&self) -> &TypeRegistry {
+        &self.registry
+    }
+
+    pub fn socket(&self) -> &S {
+        &self.stream.get_ref().get_ref().0
+    }
+}
+
+/// The reading half of a `Duplex`.
+pub struct DuplexReader<S> {
+    stream: StreamDeserializer<Half<S>>,
+    registry: TypeRegistry,
+    // the definitions of the stream as of when they were last published
+    published: Types,
+}
+
+impl<S> DuplexReader<S>
+where
+    for<'a> &'a S: Read,
+{
+    /// Deserialize the next value off the connection, see
+    /// `StreamDeserializer::deserialize`. Definitions read along the way
+    /// are published to the registry once a value decodes, failing if they
+    /// conflict with those published before.
+    pub fn deserialize<T>(&mut self) -> Result<Option<T>, Error>
+    where
+        T: DeserializeOwned,
+    {
+        let value = self.stream.deserialize()?;
+        let types = self.stream.type_defs().types;
+        // definitions are only ever added to a copy of the map, as
+        // `published` shares it, so an unchanged map means none were read
+        if !types.same_definitions(&self.published) {
+            self.registry.publish(&types, &self.published)?;
+            self.published = types;
+        }
+        Ok(value)
+    }
+
+    /// The definitions received so far.
+    pub fn registry(&self) -> &TypeRegistry {
+        &self.registry
+    }
+
+    pub fn socket(&self) -> &S {
+        &self.stream.get_ref().0
+    }
+}
@@ -6,18 +6,32 @@ pub enum ErrorKind {
     Io(io::ErrorKind),
     Serialize,
     Deserialize,
+    /// A section that is too large for Go to decode, whether it was read
+    /// or about to be written.
+    MessageTooLarge,
+    /// The input ended within a section. `hint` is how many more bytes
+    /// are needed, if the lengths read so far tell. Values running past
+    /// the end of their section are `Deserialize` errors instead.
+    NeedMoreData {
+        hint: Option<usize>,
+    },
 }
 
 #[derive(Debug)]
 pub struct Error {
     kind: ErrorKind,
     inner: ErrorInner,
+    // where the value of the innermost struct field the error happened in
+    // starts, and the path to that field
+    field: Option<(u64, String)>,
 }
 
 #[derive(Debug)]
 enum ErrorInner {
     Io(io::Error),
     Other(String),
+    // a number that could not be converted, along with where it was found
+    Numeric { path: String, msg: String },
 }
 
 impl Error {
@@ -25,6 +39,78 @@ impl Error {
         Error {
             kind: ErrorKind::Deserialize,
             inner: ErrorInner::Other(message.into()),
+            field: None,
+        }
+    }
+
+    pub(crate) fn serialize<S: Into<String>>(message: S) -> Error {
+        Error {
+            kind: ErrorKind::Serialize,
+            inner: ErrorInner::Other(message.into()),
+            field: None,
+        }
+    }
+
+    pub(crate) fn message_too_large(len: u64) -> Error {
+        Error {
+            kind: ErrorKind::MessageTooLarge,
+            inner: ErrorInner::Other(format!("{} bytes", len)),
+            field: None,
+        }
+    }
+
+    pub(crate) fn need_more_data(hint: Option<usize>) -> Error {
+        Error {
+            kind: ErrorKind::NeedMoreData { hint },
+            inner: ErrorInner::Other("message incomplete".into()),
+            field: None,
+        }
+    }
+
+    pub(crate) fn numeric<S: Into<String>>(message: S) -> Error {
+        Error {
+            kind: ErrorKind::Deserialize,
+            inner: ErrorInner::Numeric {
+                path: String::new(),
+                msg: message.into(),
+            },
+            field: None,
+        }
+    }
+
+    /// Records that the error happened within `field` of a struct, or at
+    /// index `field` of a sequence.
+    pub(crate) fn within(mut self, field: &str) -> Error {
+        if let ErrorInner::Numeric { ref mut path, .. } = self.inner {
+            prepend(path, field);
+        }
+        if let Some((_, ref mut path)) = self.field {
+            prepend(path, field);
+        }
+        self
+    }
+
+    /// Records that the error happened in the value of a struct field
+    /// starting at `start`, unless it is known to be in a field within.
+    pub(crate) fn in_field_at(mut self, start: u64) -> Error {
+        if self.field.is_none() {
+            self.field = Some((start, String::new()));
+        }
+        self
+    }
+
+    /// Where the value of the struct field the error happened in starts,
+    /// and the path to the field.
+    pub(crate) fn field(&self) -> Option<(u64, &str)> {
+        self.field.as_ref().map(|(start, path)| (*start, &**path))
+    }
+
+    /// The dotted path to the value the error is about, e.g. `Items.0.B`,
+    /// for errors that know it.
+    pub fn path(&self) -> Option<&str> {
+        match self.inner {
+            ErrorInner::Numeric { ref path, .. } if !path.is_empty() => Some(path),
+            _ => None,
         }
     }
 
@@ -33,13 +119,27 @@ impl Error {
     }
 }
 
+fn prepend(path: &mut String, field: &str) {
+    if path.is_empty() {
+        *path = field.to_owned();
+    } else {
+        *path = format!("{}.{}", field, path);
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self.inner {
             ErrorInner::Io(ref err) => write!(f, "i/o error: {}", err),
-            ErrorInner::Other(ref msg) => {
-                write!(f, "{}: {}", self, msg)
+            ErrorInner::Numeric { ref path, ref msg } if !path.is_empty() => {
+                write!(f, "deserialize error: {}: {}", path, msg)
             }
+            ErrorInner::Numeric { ref msg, .. } => write!(f, "deserialize error: {}", msg),
+            ErrorInner::Other(ref msg) => match self.kind {
+                ErrorKind::Serialize => write!(f, "serialize error: {}", msg),
+                ErrorKind::MessageTooLarge => write!(f, "message too large: {}", msg),
+                _ => write!(f, "deserialize error: {}", msg),
+            },
         }
     }
 }
@@ -50,6 +150,8 @@ impl ::std::error::Error for Error {
             ErrorKind::Io(_) => "i/o error",
             ErrorKind::Serialize => "serialize error",
             ErrorKind::Deserialize => "deserialize error",
+            ErrorKind::MessageTooLarge => "message too large",
+            ErrorKind::NeedMoreData { .. } => "need more data",
         }
     }
 }
@@ -59,6 +161,7 @@ impl ::serde::de::Error for Error {
         Error {
             kind: ErrorKind::Deserialize,
             inner: ErrorInner::Other(msg.to_string()),
+            field: None,
         }
     }
 }
@@ -68,6 +171,7 @@ impl ::serde::ser::Error for Error {
         Error {
             kind: ErrorKind::Serialize,
             inner: ErrorInner::Other(msg.to_string()),
+            field: None,
         }
     }
 }
@@ -77,6 +181,7 @@ impl From<io::Error> for Error {
         Error {
             kind: ErrorKind::Io(err.kind()),
             inner: ErrorInner::Io(err),
+            field: None,
         }
     }
 }
@@ -0,0 +1,192 @@
+//! Exporting streams as tables
+//!
+//! For one-off extraction jobs, the values of a stream of one flat Go
+//! struct type can be written out as CSV, or with the `parquet` feature as
+//! Parquet, one row per value:
+//!
+//! ```ignore
+//! let stream = gob::StreamDeserializer::new(File::open("dump.gob")?);
+//! let rows = gob::export::write_csv(stream, File::create("dump.csv")?)?;
+//! ```
+//!
+//! Fields left out by the encoder are written as their zero value, like Go
+//! decodes them.
+
+use std::io::{Read, Write};
+
+use de::{StreamDeserializer, TypeInfo};
+use error::Error;
+use internal::types::{TypeId, Types, WireType};
+use value::Value;
+
+use crate::{de, error, internal, value};
+
+/// Writes the remaining values of `stream` as CSV to `out`, with a header
+/// row naming the fields, and returns the number of rows written.
+///
+/// Values must all be of the same struct type, with fields of builtin
+/// types only: bools, numbers, strings and byte slices, the latter written
+/// in hex. Fields are quoted as needed, and rows end in `\n`. Nothing is
+/// written for a stream without values.
+pub fn write_csv<R: Read, W: Write>(
+    mut stream: StreamDeserializer<R>,
+    mut out: W,
+) -> Result<u64, Error> {
+    let mut columns: Option<(TypeId, Vec<(String, TypeId)>)> = None;
+    let mut rows = 0;
+    let mut line = String::new();
+    loop {
+        let type_id = match stream.peek_type()? {
+            Some(info) => info.id(),
+            None => break,
+        };
+        let defs = stream.type_defs();
+        let fields = match columns {
+            Some((planned, ref fields)) if planned == type_id => fields,
+            Some((planned, _)) => {
+                return Err(Error::deserialize(format!(
+                    "stream mixes values of {} and {}",
+                    type_name(&defs.types, planned),
+                    type_name(&defs.types, type_id)
+                )))
+            }
+            None => {
+                let fields = flat_fields(&defs.types, type_id)?;
+                line.clear();
+                for (i, (name, _)) in fields.iter().enumerate() {
+                    push_cell(&mut line, i, name);
+                }
+                line.push('\n');
+                out.write_all(line.as_bytes())?;
+                &columns.insert((type_id, fields)).1
+            }
+        };
+        let row = match stream.deserialize::<Value>()? {
+            Some(row) => row,
+            None => break,
+        };
+        line.clear();
+        for (i, (name, id)) in fields.iter().enumerate() {
+            let value = match row {
+                Value::Map(ref entries) => entries
+                    .iter()
+                    .find(|(key, _)| matches!(key, Value::String(key) if key == name))
+                    .map(|(_, value)| value),
+                _ => None,
+            };
+            push_cell(&mut line, i, &cell(*id, value)?);
+        }
+        line.push('\n');
+        out.write_all(line.as_bytes())?;
+        rows += 1;
+    }
+    out.flush()?;
+    Ok(rows)
+}
+
+/// Writes the remaining values of `stream` as a Parquet file to `out`, and
+/// returns the number of rows written.
+///
+/// Columns are typed after the fields, which may be of any type
+/// `arrow::RecordBatches` supports, as it builds the row groups. Nothing
+/// is written for a stream without values.
+#[cfg(feature = "parquet")]
+pub fn write_parquet<R: Read, W: Write + Send>(
+    stream: StreamDeserializer<R>,
+    out: W,
+) -> Result<u64, Error> {
+    use crate::arrow::RecordBatches;
+    use parquet::arrow::ArrowWriter;
+
+    let parquet_error =
+        |err: parquet::errors::ParquetError| Error::serialize(format!("writing parquet: {}", err));
+
+    let mut batches = RecordBatches::from_stream(stream);
+    let schema = match batches.schema()? {
+        Some(schema) => schema,
+        None => return Ok(0),
+    };
+    let mut writer = ArrowWriter::try_new(out, schema, None).map_err(parquet_error)?;
+    let mut rows = 0;
+    for batch in batches {
+        let batch = batch?;
+        rows += batch.num_rows() as u64;
+        writer.write(&batch).map_err(parquet_error)?;
+    }
+    writer.close().map_err(parquet_error)?;
+    Ok(rows)
+}
+
+fn type_name(defs: &Types, id: TypeId) -> String {
+    match TypeInfo::lookup(id, defs) {
+        Some(info) => info.name().to_owned(),
+        None => format!("type id {}", id.0),
+    }
+}
+
+// the names and types of the fields of a struct holding builtin values only
+fn flat_fields(defs: &Types, id: TypeId) -> Result<Vec<(String, TypeId)>, Error> {
+    let struct_type = match defs.lookup(id) {
+        Some(WireType::Struct(struct_type)) => struct_type,
+        _ => {
+            return Err(Error::deserialize(format!(
+                "{} is not a struct, only structs can be exported as rows",
+                type_name(defs, id)
+            )))
+        }
+    };
+    struct_type
+        .fields
+        .iter()
+        .map(|field| match field.id {
+            TypeId::BOOL
+            | TypeId::INT
+            | TypeId::UINT
+            | TypeId::FLOAT
+            | TypeId::STRING
+            | TypeId::BYTES => Ok((field.name.to_string(), field.id)),
+            _ => Err(Error::deserialize(format!(
+                "field {} of {} is a {}, which does not fit into a single column",
+                field.name,
+                type_name(defs, id),
+                type_name(defs, field.id)
+            ))),
+        })
+        .collect()
+}
+
+// the text of a field of type `id`, or of its zero value if left out
+fn cell(id: TypeId, value: Option<&Value>) -> Result<String, Error> {
+    let text = match (id, value) {
+        (TypeId::BOOL, None) => "false".to_owned(),
+        (TypeId::STRING, None) | (TypeId::BYTES, None) => String::new(),
+        (_, None) => "0".to_owned(),
+        (_, Some(Value::Bool(v))) => v.to_string(),
+        (_, Some(Value::Int(v))) => v.to_string(),
+        (_, Some(Value::Uint(v))) => v.to_string(),
+        (_, Some(Value::Float(v))) => v.to_string(),
+        (_, Some(Value::String(v))) => v.clone(),
+        (_, Some(Value::Bytes(v))) => v.iter().map(|b| format!("{:02x}", b)).collect(),
+        (_, Some(other)) => {
+            return Err(Error::deserialize(format!(
+                "{:?} does not fit into a single column",
+                other
+            )))
+        }
+    };
+    Ok(text)
+}
+
+// appends a cell to a row, quoting it if it holds separators or quotes
+fn push_cell(line: &mut String, i: usize, text: &str) {
+    if i > 0 {
+        line.push(',');
+    }
+    if text.contains(|c| matches!(c, ',' | '"' | '\n' | '\r')) {
+        line.push('"');
+        line.push_str(&text.replace('"', "\"\""));
+        line.push('"');
+    } else {
+        line.push_str(text);
+    }
+}
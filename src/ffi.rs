@@ -0,0 +1,321 @@
+//! C interface for embedding the decoder in other languages
+//!
+//! Built with the `ffi` feature. A decoder is fed bytes as they arrive and
+//! hands out complete values either as JSON or as a sequence of events
+//! passed to a callback:
+//!
+//! ```text
+//! GobDecoder *dec = gob_decoder_new();
+//! gob_decoder_feed(dec, buf, len);
+//! const char *json; size_t json_len;
+//! while (gob_decoder_next_json(dec, &json, &json_len) == GOB_VALUE) {
+//!     /* json is valid until the next call on dec */
+//! }
+//! gob_decoder_free(dec);
+//! ```
+//!
+//! Functions returning `c_int` return `GOB_VALUE` when a value was decoded,
+//! `GOB_NEED_INPUT` when more bytes have to be fed first, and `GOB_ERROR`
+//! on failure, after which `gob_decoder_last_error` describes the error.
+
+use std::ffi::CString;
+use std::fmt;
+use std::os::raw::{c_char, c_int, c_void};
+use std::ptr;
+use std::slice;
+
+use serde::de::{DeserializeSeed, MapAccess, SeqAccess, Visitor};
+use serde_json;
+
+use de::Decoder;
+use error::Error;
+use transcode::Transcoder;
+
+use crate::{de, error, transcode};
+
+pub const GOB_VALUE: c_int = 1;
+pub const GOB_NEED_INPUT: c_int = 0;
+pub const GOB_ERROR: c_int = -1;
+
+/// A decoder, along with the output of its last call.
+pub struct GobDecoder {
+    decoder: Decoder,
+    json: Vec<u8>,
+    error: Option<CString>,
+}
+
+impl GobDecoder {
+    fn result(&mut self, result: Result<Option<()>, Error>) -> c_int {
+        match result {
+            Ok(Some(())) => GOB_VALUE,
+            Ok(None) => GOB_NEED_INPUT,
+            Err(err) => {
+                let msg = err.to_string().replace('\0', " ");
+                self.error = Some(CString::new(msg).expect("NUL bytes were replaced"));
+                GOB_ERROR
+            }
+        }
+    }
+}
+
+/// Creates a decoder, to be freed with `gob_decoder_free`.
+#[no_mangle]
+pub extern "C" fn gob_decoder_new() -> *mut GobDecoder {
+    Box::into_raw(Box::new(GobDecoder {
+        decoder: Decoder::new(),
+        json: Vec::new(),
+        error: None,
+    }))
+}
+
+/// Frees a decoder.
+///
+/// # Safety
+///
+/// `dec` must be null or have been returned by `gob_decoder_new`, and must
+/// not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn gob_decoder_free(dec: *mut GobDecoder) {
+    if !dec.is_null() {
+        drop(Box::from_raw(dec));
+    }
+}
+
+/// Appends `len` bytes at `data` to the input of `dec`.
+///
+/// # Safety
+///
+/// `dec` must be a live decoder, and `data` must point to `len` readable
+/// bytes (or be null if `len` is zero).
+#[no_mangle]
+pub unsafe extern "C" fn gob_decoder_feed(dec: *mut GobDecoder, data: *const u8, len: usize) {
+    if len > 0 {
+        (*dec).decoder.feed(slice::from_raw_parts(data, len));
+    }
+}
+
+/// Decodes the next value as JSON into `*json` and `*json_len`. The JSON
+/// is not NUL-terminated, and stays valid until the next call on `dec`.
+///
+/// # Safety
+///
+/// `dec` must be a live decoder, and `json` and `json_len` must be valid
+/// for writes.
+#[no_mangle]
+pub unsafe extern "C" fn gob_decoder_next_json(
+    dec: *mut GobDecoder,
+    json: *mut *const c_char,
+    json_len: *mut usize,
+) -> c_int {
+    let dec = &mut *dec;
+    let mut out = std::mem::take(&mut dec.json);
+    out.clear();
+    let result = dec.decoder.next_value_seed(JsonSeed(&mut out));
+    let code = dec.result(result);
+    dec.json = out;
+    if code == GOB_VALUE {
+        *json = dec.json.as_ptr() as *const c_char;
+        *json_len = dec.json.len();
+    }
+    code
+}
+
+/// The kind of a `GobEvent`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GobEventKind {
+    /// `boolean` holds the value.
+    Bool,
+    /// `int` holds the value.
+    Int,
+    /// `uint` holds the value.
+    Uint,
+    /// `float` holds the value.
+    Float,
+    /// `data` and `len` hold the bytes.
+    Bytes,
+    /// `data` and `len` hold the UTF-8 bytes of the string.
+    String,
+    /// A nil interface value.
+    Nil,
+    /// A slice or array of `len` elements follows, then `End`.
+    StartSeq,
+    /// A map or struct follows as alternating keys and values, then `End`.
+    /// Struct fields are keyed by their names as strings. `len` holds the
+    /// number of entries if known.
+    StartMap,
+    /// Ends the innermost sequence or map.
+    End,
+}
+
+/// A single step through a value, passed to a `GobVisitFn`. `data` is only
+/// valid during the call.
+#[repr(C)]
+pub struct GobEvent {
+    pub kind: GobEventKind,
+    pub boolean: bool,
+    pub int: i64,
+    pub uint: u64,
+    pub float: f64,
+    pub data: *const u8,
+    pub len: usize,
+}
+
+/// Called with each event of a value, along with the `ctx` passed to
+/// `gob_decoder_next_visit`. Returning non-zero aborts decoding with an
+/// error.
+pub type GobVisitFn = extern "C" fn(ctx: *mut c_void, event: *const GobEvent) -> c_int;
+
+/// Decodes the next value by passing its events to `visit`.
+///
+/// # Safety
+///
+/// `dec` must be a live decoder, and `visit` must be safe to call with
+/// `ctx`.
+#[no_mangle]
+pub unsafe extern "C" fn gob_decoder_next_visit(
+    dec: *mut GobDecoder,
+    visit: GobVisitFn,
+    ctx: *mut c_void,
+) -> c_int {
+    let dec = &mut *dec;
+    let result = dec.decoder.next_value_seed(Events { visit, ctx });
+    dec.result(result)
+}
+
+/// Describes the error of the last call that returned `GOB_ERROR`, as a
+/// NUL-terminated string valid until the next error, or null if there was
+/// none.
+///
+/// # Safety
+///
+/// `dec` must be a live decoder.
+#[no_mangle]
+pub unsafe extern "C" fn gob_decoder_last_error(dec: *const GobDecoder) -> *const c_char {
+    match (*dec).error {
+        Some(ref msg) => msg.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+struct JsonSeed<'a>(&'a mut Vec<u8>);
+
+impl<'de, 'a> DeserializeSeed<'de> for JsonSeed<'a> {
+    type Value = ();
+
+    fn deserialize<D: serde::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        serde_json::to_writer(&mut *self.0, &Transcoder::new(deserializer))
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Events {
+    visit: GobVisitFn,
+    ctx: *mut c_void,
+}
+
+impl Events {
+    fn emit<E: serde::de::Error>(
+        &self,
+        kind: GobEventKind,
+        fill: impl FnOnce(&mut GobEvent),
+    ) -> Result<(), E> {
+        let mut event = GobEvent {
+            kind,
+            boolean: false,
+            int: 0,
+            uint: 0,
+            float: 0.0,
+            data: ptr::null(),
+            len: 0,
+        };
+        fill(&mut event);
+        match (self.visit)(self.ctx, &event) {
+            0 => Ok(()),
+            code => Err(E::custom(format!("visitor aborted with {}", code))),
+        }
+    }
+
+    fn bytes<E: serde::de::Error>(&self, kind: GobEventKind, bytes: &[u8]) -> Result<(), E> {
+        self.emit(kind, |event| {
+            event.data = bytes.as_ptr();
+            event.len = bytes.len();
+        })
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for Events {
+    type Value = ();
+
+    fn deserialize<D: serde::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Events {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a gob value")
+    }
+
+    fn visit_bool<E: serde::de::Error>(self, v: bool) -> Result<(), E> {
+        self.emit(GobEventKind::Bool, |event| event.boolean = v)
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<(), E> {
+        self.emit(GobEventKind::Int, |event| event.int = v)
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<(), E> {
+        self.emit(GobEventKind::Uint, |event| event.uint = v)
+    }
+
+    fn visit_f64<E: serde::de::Error>(self, v: f64) -> Result<(), E> {
+        self.emit(GobEventKind::Float, |event| event.float = v)
+    }
+
+    fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<(), E> {
+        self.bytes(GobEventKind::String, v.as_bytes())
+    }
+
+    fn visit_bytes<E: serde::de::Error>(self, v: &[u8]) -> Result<(), E> {
+        self.bytes(GobEventKind::Bytes, v)
+    }
+
+    fn visit_none<E: serde::de::Error>(self) -> Result<(), E> {
+        self.emit(GobEventKind::Nil, |_| {})
+    }
+
+    fn visit_unit<E: serde::de::Error>(self) -> Result<(), E> {
+        self.emit(GobEventKind::Nil, |_| {})
+    }
+
+    fn visit_some<D: serde::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_newtype_struct<D: serde::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let len = seq.size_hint().unwrap_or(0);
+        self.emit(GobEventKind::StartSeq, |event| event.len = len)?;
+        while seq.next_element_seed(self)?.is_some() {}
+        self.emit(GobEventKind::End, |_| {})
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let len = map.size_hint().unwrap_or(0);
+        self.emit(GobEventKind::StartMap, |event| event.len = len)?;
+        while map.next_key_seed(self)?.is_some() {
+            map.next_value_seed(self)?;
+        }
+        self.emit(GobEventKind::End, |_| {})
+    }
+}
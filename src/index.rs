@@ -0,0 +1,200 @@
+//! Random access to gob files
+//!
+//! An `Index` scans a seekable source once, noting where each value starts
+//! and decoding all type definitions on the way. After that, values can be
+//! decoded in any order by seeking straight to them:
+//!
+//! ```ignore
+//! let mut index = gob::index::Index::build(File::open("dump.gob")?)?;
+//! let last: Record = index.get(index.len() - 1)?;
+//! let page: Vec<Record> = index.range(100..200)?;
+//! ```
+//!
+//! Only the positions of values are kept in memory, not the values
+//! themselves, so this also works for files much larger than memory.
+
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::sync::Arc;
+
+use bytes::Buf;
+use serde::de::{Deserialize, DeserializeOwned};
+
+use de::{SchemaRegistry, DEFAULT_OPTIONS, DEFAULT_SCOPE};
+use error::Error;
+use internal::de::ValueDeserializer;
+use internal::gob::{Message, Stream};
+use internal::types::Types;
+use internal::utils::Buffer;
+use schema::TypeId;
+
+use crate::{de, error, internal, schema};
+
+struct Entry {
+    // offsets of the value's payload, relative to where the scan started
+    payload: Range<u64>,
+    type_id: TypeId,
+    // the definitions in effect at the value, as streams may redefine ids,
+    // e.g. when several were concatenated
+    defs: Types,
+}
+
+/// The positions of all values in a gob stream.
+pub struct Index<R> {
+    source: R,
+    // where the stream starts in `source`
+    base: u64,
+    entries: Vec<Entry>,
+    defs: Types,
+    buffer: Vec<u8>,
+}
+
+impl<R: Read + Seek> Index<R> {
+    /// Scans `source` from its current position to the end.
+    pub fn build(mut source: R) -> Result<Index<R>, Error> {
+        let base = source.seek(SeekFrom::Current(0))?;
+        let mut entries = Vec::new();
+        let mut defs = Types::new();
+
+        {
+            let mut stream = Stream::new(&mut source);
+            let mut buffer = Buffer::new();
+            let mut offset = 0;
+            while let Some(header) = stream.read_section(&mut buffer)? {
+                let end = header.payload_range.end;
+                if header.type_id < 0 {
+                    let payload = &buffer.bytes()[header.payload_range.clone()];
+                    let mut msg = Message::new(Cursor::new(payload));
+                    de::define(&mut defs, &DEFAULT_OPTIONS, header.type_id, &mut msg)?;
+                } else {
+                    entries.push(Entry {
+                        payload: offset + header.payload_range.start as u64..offset + end as u64,
+                        type_id: TypeId(header.type_id),
+                        defs: defs.clone(),
+                    });
+                }
+                buffer.advance(end);
+                offset += end as u64;
+            }
+        }
+
+        Ok(Index {
+            source,
+            base,
+            entries,
+            defs,
+            buffer: Vec::new(),
+        })
+    }
+
+    /// Decodes the value at `index`.
+    pub fn get<'de, T>(&'de mut self, index: usize) -> Result<T, Error>
+    where
+        T: Deserialize<'de>,
+    {
+        let entry = self.entry(index)?;
+        let (type_id, payload, defs) = (entry.type_id, entry.payload.clone(), entry.defs.clone());
+        self.read(payload)?;
+        decode(type_id, &defs, &self.buffer)
+    }
+
+    /// Decodes the values in `range`, reading their sections in one go.
+    pub fn range<T>(&mut self, range: Range<usize>) -> Result<Vec<T>, Error>
+    where
+        T: DeserializeOwned,
+    {
+        if range.start >= range.end {
+            return Ok(Vec::new());
+        }
+        let start = self.entry(range.start)?.payload.start;
+        let end = self.entry(range.end - 1)?.payload.end;
+        self.read(start..end)?;
+
+        self.entries[range]
+            .iter()
+            .map(|entry| {
+                let payload = &self.buffer
+                    [(entry.payload.start - start) as usize..(entry.payload.end - start) as usize];
+                decode(entry.type_id, &entry.defs, payload)
+            })
+            .collect()
+    }
+
+    /// Looks up the types of values not defined in the stream in
+    /// `registry`, see `StreamDeserializer::set_schema_registry`.
+    ///
+    /// The definitions of all values indexed are looked up right away.
+    pub fn set_schema_registry<S>(&mut self, registry: S) -> Result<(), Error>
+    where
+        S: SchemaRegistry + 'static,
+    {
+        let registry: Arc<dyn SchemaRegistry> = Arc::new(registry);
+        self.defs.set_registry(registry.clone());
+        // values sharing definitions keep sharing them once resolved
+        let mut scanned = Types::new();
+        let mut resolved = Types::new();
+        for entry in &mut self.entries {
+            if !entry.defs.same_definitions(&scanned) {
+                scanned = entry.defs.clone();
+                resolved = entry.defs.clone();
+                resolved.set_registry(registry.clone());
+            }
+            de::resolve(&mut resolved, entry.type_id)?;
+            entry.defs = resolved.clone();
+        }
+        Ok(())
+    }
+
+    // reads the bytes at `range` into `self.buffer`
+    fn read(&mut self, range: Range<u64>) -> Result<(), Error> {
+        self.source.seek(SeekFrom::Start(self.base + range.start))?;
+        self.buffer.resize((range.end - range.start) as usize, 0);
+        self.source.read_exact(&mut self.buffer)?;
+        Ok(())
+    }
+}
+
+impl<R> Index<R> {
+    /// Number of values in the stream.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The type of the value at `index`.
+    pub fn value_type(&self, index: usize) -> Option<TypeId> {
+        self.entries.get(index).map(|entry| entry.type_id)
+    }
+
+    /// The name a type was last defined under, if it was defined in the
+    /// stream.
+    pub fn type_name(&self, id: TypeId) -> Option<&str> {
+        self.defs.lookup(id).map(|def| &*def.common().name)
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.source
+    }
+
+    pub fn into_inner(self) -> R {
+        self.source
+    }
+
+    fn entry(&self, index: usize) -> Result<&Entry, Error> {
+        self.entries
+            .get(index)
+            .ok_or_else(|| Error::deserialize(format!("no value at index {}", index)))
+    }
+}
+
+fn decode<'de, T>(type_id: TypeId, defs: &Types, payload: &'de [u8]) -> Result<T, Error>
+where
+    T: Deserialize<'de>,
+{
+    let mut msg = Message::new(Cursor::new(payload));
+    let de = ValueDeserializer::new(type_id, defs, DEFAULT_SCOPE, &mut msg);
+    T::deserialize(de)
+}
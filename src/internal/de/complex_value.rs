@@ -5,25 +5,25 @@ use serde::de::{DeserializeSeed, Deserializer, IntoDeserializer, SeqAccess, Visi
 use crate::{error, internal::gob::Message};
 use error::Error;
 
-struct ComplexSeqAccess<'t, 'de>
-where
-    'de: 't,
-{
+struct ComplexSeqAccess {
     remaining_count: u64,
-    msg: &'t mut Message<Cursor<&'de [u8]>>,
+    // real and imaginary parts, as they were on the wire
+    parts: [u64; 2],
 }
 
-impl<'t, 'de> ComplexSeqAccess<'t, 'de> {
+impl ComplexSeqAccess {
     #[inline]
-    fn new(msg: &'t mut Message<Cursor<&'de [u8]>>) -> ComplexSeqAccess<'t, 'de> {
-        ComplexSeqAccess {
+    fn new(msg: &mut Message<Cursor<&[u8]>>) -> Result<ComplexSeqAccess, Error> {
+        let mut parts = [0; 2];
+        msg.read_uints(&mut parts)?;
+        Ok(ComplexSeqAccess {
             remaining_count: 2,
-            msg,
-        }
+            parts,
+        })
     }
 }
 
-impl<'t, 'de> SeqAccess<'de> for ComplexSeqAccess<'t, 'de> {
+impl<'de> SeqAccess<'de> for ComplexSeqAccess {
     type Error = Error;
 
     fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
@@ -33,8 +33,9 @@ impl<'t, 'de> SeqAccess<'de> for ComplexSeqAccess<'t, 'de> {
         if self.remaining_count == 0 {
             return Ok(None);
         }
+        let bits = self.parts[2 - self.remaining_count as usize];
         self.remaining_count -= 1;
-        let float = self.msg.read_float()?;
+        let float = f64::from_bits(bits.swap_bytes());
         seed.deserialize(float.into_deserializer()).map(Some)
     }
 
@@ -67,7 +68,7 @@ impl<'t, 'de> Deserializer<'de> for ComplexValueDeserializer<'t, 'de> {
     where
         V: Visitor<'de>,
     {
-        visitor.visit_seq(ComplexSeqAccess::new(self.msg))
+        visitor.visit_seq(ComplexSeqAccess::new(self.msg)?)
     }
 
     forward_to_deserialize_any! {
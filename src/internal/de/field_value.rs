@@ -1,18 +1,25 @@
 use std::io::Cursor;
 
 use bytes::Buf;
+use serde::de::value::SeqDeserializer;
 use serde::de::{IgnoredAny, Visitor};
 use serde::{self, Deserialize};
 
+use de::DecodeScope;
 use error::Error;
-use internal::gob::Message;
+use internal::gob::{Message, MessageReadError};
 use internal::types::{TypeId, Types, WireType};
+use schema::OptionEncoding;
+use value::Value;
 
-use crate::{error, internal};
+use crate::{codec, de, error, internal, schema, value};
 
 use super::complex_value::ComplexValueDeserializer;
+use super::interface_value::InterfaceValueDeserializer;
 use super::map_value::MapValueDeserializer;
+use super::number::{Coerce, Number};
 use super::seq_value::SeqValueDeserializer;
+use super::skip::skip_field_value;
 use super::struct_value::StructValueDeserializer;
 
 pub(crate) struct FieldValueDeserializer<'t, 'de>
@@ -21,16 +28,72 @@ where
 {
     type_id: TypeId,
     defs: &'t Types,
+    opts: DecodeScope<'t>,
     msg: &'t mut Message<Cursor<&'de [u8]>>,
+    map_key: bool,
+    // set once the hook for the type of the value has run
+    hooked: bool,
+    // the uint a bool or number was sent as, when it was read along with
+    // the rest of a slice or array
+    read_ahead: Option<u64>,
 }
 
 impl<'t, 'de> FieldValueDeserializer<'t, 'de> {
     pub fn new(
         type_id: TypeId,
         defs: &'t Types,
+        opts: DecodeScope<'t>,
         msg: &'t mut Message<Cursor<&'de [u8]>>,
     ) -> FieldValueDeserializer<'t, 'de> {
-        FieldValueDeserializer { type_id, defs, msg }
+        FieldValueDeserializer {
+            type_id,
+            defs,
+            opts,
+            msg,
+            map_key: false,
+            hooked: false,
+            read_ahead: None,
+        }
+    }
+
+    // the bool or number is `bits`, already read off the message
+    pub fn read_ahead(mut self, bits: u64) -> Self {
+        self.read_ahead = Some(bits);
+        self
+    }
+
+    // map keys that are bools or numbers also decode as strings, like
+    // `serde_json` does the other way around, so that maps of any keys
+    // decode into `serde_json::Value`
+    pub fn map_key(mut self) -> Self {
+        self.map_key = true;
+        self
+    }
+
+    // runs the hook registered for the type of the value, if any, with the
+    // value decoded as a `Value`
+    fn hook(&mut self) -> Result<Option<Value>, Error> {
+        if self.hooked {
+            return Ok(None);
+        }
+        let hook = match self.defs.hook(self.type_id) {
+            Some(hook) => hook,
+            None => return Ok(None),
+        };
+        let mut de = FieldValueDeserializer::new(self.type_id, self.defs, self.opts, self.msg);
+        de.hooked = true;
+        de.read_ahead = self.read_ahead.take();
+        let value = Value::deserialize(de)?;
+        hook(value).map(Some)
+    }
+
+    fn visit_byte_slice<V: Visitor<'de>>(&mut self, visitor: V) -> Result<V::Value, Error> {
+        let bytes = self.deserialize_byte_slice()?;
+        if self.opts.bytes_as_seq {
+            visitor.visit_seq(SeqDeserializer::<_, Error>::new(bytes.iter().cloned()))
+        } else {
+            visitor.visit_borrowed_bytes(bytes)
+        }
     }
 
     fn deserialize_byte_slice(&mut self) -> Result<&'de [u8], Error> {
@@ -41,12 +104,79 @@ impl<'t, 'de> FieldValueDeserializer<'t, 'de> {
         Ok(bytes)
     }
 
-    fn deserialize_str_slice(&mut self) -> Result<&'de str, Error> {
+    // whether the value is of type `sent`, and may be decoded as the other
+    // one of strings and byte slices
+    fn is_interchangeable(&self, sent: TypeId) -> bool {
+        self.opts.string_bytes && self.type_id == sent
+    }
+
+    fn read_uint(&mut self) -> Result<u64, Error> {
+        match self.read_ahead.take() {
+            Some(bits) => Ok(bits),
+            None => Ok(self.msg.read_uint()?),
+        }
+    }
+
+    fn read_int(&mut self) -> Result<i64, Error> {
+        self.read_uint().map(codec::int_from_bits)
+    }
+
+    fn read_float(&mut self) -> Result<f64, Error> {
+        self.read_uint().map(codec::float_from_bits)
+    }
+
+    fn read_bool(&mut self) -> Result<bool, Error> {
+        let bits = self.read_uint()?;
+        Ok(codec::bool_from_bits(bits).map_err(MessageReadError::from)?)
+    }
+
+    fn read_number(&mut self) -> Result<Option<Number>, Error> {
+        Ok(Some(match self.type_id {
+            TypeId::INT => Number::Int(self.read_int()?),
+            TypeId::UINT => Number::Uint(self.read_uint()?),
+            TypeId::FLOAT => Number::Float(self.read_float()?),
+            _ => return Ok(None),
+        }))
+    }
+
+    fn visit_str<V: Visitor<'de>>(&mut self, visitor: V) -> Result<V::Value, Error> {
         let bytes = self.deserialize_byte_slice()?;
-        ::std::str::from_utf8(bytes).map_err(|err| serde::de::Error::custom(err))
+        match ::std::str::from_utf8(bytes) {
+            Ok(s) => visitor.visit_borrowed_str(s),
+            Err(_) if self.opts.lossy_utf8 => {
+                visitor.visit_string(String::from_utf8_lossy(bytes).into_owned())
+            }
+            Err(err) => Err(serde::de::Error::custom(err)),
+        }
     }
 }
 
+fn char_from_rune(rune: i64) -> Result<char, Error> {
+    if rune < 0 || rune > u32::MAX as i64 {
+        return Err(serde::de::Error::custom(format!("invalid rune {}", rune)));
+    }
+    ::std::char::from_u32(rune as u32)
+        .ok_or_else(|| serde::de::Error::custom(format!("invalid rune {}", rune)))
+}
+
+// converts whatever number was sent according to the coercion option
+macro_rules! number {
+    ($fname:tt, $tname:tt, $visit:tt) => {
+        fn $fname<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, Self::Error> {
+            match self.read_number()? {
+                Some(n) => match <$tname as Coerce>::coerce(n, &self.opts) {
+                    Ok(value) => visitor.$visit(value),
+                    Err(msg) => Err(Error::numeric(msg)),
+                },
+                None => Err(serde::de::Error::custom(format!(
+                    "expected {}",
+                    stringify!($tname)
+                ))),
+            }
+        }
+    };
+}
+
 macro_rules! primitive {
     ($fname:tt, $tname:tt, $visit:tt, $id:tt, $parse:expr) => {
         fn $fname<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
@@ -69,19 +199,29 @@ impl<'t, 'de> serde::Deserializer<'de> for FieldValueDeserializer<'t, 'de> {
     where
         V: Visitor<'de>,
     {
+        if let Some(value) = self.hook()? {
+            return value.deserialize_any(visitor);
+        }
         match self.type_id {
-            TypeId::BOOL => visitor.visit_bool(self.msg.read_bool()?),
-            TypeId::INT => visitor.visit_i64(self.msg.read_int()?),
-            TypeId::UINT => visitor.visit_u64(self.msg.read_uint()?),
-            TypeId::FLOAT => visitor.visit_f64(self.msg.read_float()?),
-            TypeId::BYTES => visitor.visit_borrowed_bytes(self.deserialize_byte_slice()?),
-            TypeId::STRING => visitor.visit_borrowed_str(self.deserialize_str_slice()?),
+            TypeId::BOOL => visitor.visit_bool(self.read_bool()?),
+            TypeId::INT => visitor.visit_i64(self.read_int()?),
+            TypeId::UINT => visitor.visit_u64(self.read_uint()?),
+            TypeId::FLOAT => visitor.visit_f64(self.read_float()?),
+            TypeId::BYTES => self.visit_byte_slice(visitor),
+            TypeId::STRING => self.visit_str(visitor),
             TypeId::COMPLEX => ComplexValueDeserializer::new(self.msg).deserialize_any(visitor),
+            TypeId::INTERFACE => InterfaceValueDeserializer::new(self.defs, self.opts, self.msg)
+                .deserialize_any(visitor),
             _ => {
                 if let Some(wire_type) = self.defs.lookup(self.type_id) {
                     match wire_type {
                         &WireType::Struct(ref struct_type) => {
-                            let de = StructValueDeserializer::new(struct_type, self.defs, self.msg);
+                            let de = StructValueDeserializer::new(
+                                struct_type,
+                                self.defs,
+                                self.opts,
+                                self.msg,
+                            );
                             de.deserialize_any(visitor)
                         }
                         &WireType::Slice(ref slice_type) => {
@@ -89,6 +229,7 @@ impl<'t, 'de> serde::Deserializer<'de> for FieldValueDeserializer<'t, 'de> {
                                 None,
                                 slice_type.elem,
                                 self.defs,
+                                self.opts,
                                 self.msg,
                             );
                             de.deserialize_any(visitor)
@@ -98,14 +239,17 @@ impl<'t, 'de> serde::Deserializer<'de> for FieldValueDeserializer<'t, 'de> {
                                 Some(array_type.len as usize),
                                 array_type.elem,
                                 self.defs,
+                                self.opts,
                                 self.msg,
                             );
                             de.deserialize_any(visitor)
                         }
                         &WireType::Map(ref map_type) => {
-                            let de = MapValueDeserializer::new(map_type, self.defs, self.msg);
+                            let de =
+                                MapValueDeserializer::new(map_type, self.defs, self.opts, self.msg);
                             de.deserialize_any(visitor)
                         }
+                        &WireType::GobEncoder(_) => self.visit_byte_slice(visitor),
                     }
                 } else {
                     Err(serde::de::Error::custom(format!(
@@ -117,48 +261,49 @@ impl<'t, 'de> serde::Deserializer<'de> for FieldValueDeserializer<'t, 'de> {
         }
     }
 
-    primitive!(deserialize_bool, bool, visit_bool, BOOL, |d: Self| d
-        .msg
+    primitive!(deserialize_bool, bool, visit_bool, BOOL, |mut d: Self| d
         .read_bool());
 
-    primitive!(deserialize_i8, i8, visit_i8, INT, |d: Self| d
-        .msg
-        .read_int());
-    primitive!(deserialize_i16, i16, visit_i16, INT, |d: Self| d
-        .msg
-        .read_int());
-    primitive!(deserialize_i32, i32, visit_i32, INT, |d: Self| d
-        .msg
-        .read_int());
-    primitive!(deserialize_i64, i64, visit_i64, INT, |d: Self| d
-        .msg
-        .read_int());
-
-    primitive!(deserialize_u8, u8, visit_u8, UINT, |d: Self| d
-        .msg
-        .read_uint());
-    primitive!(deserialize_u16, u16, visit_u16, UINT, |d: Self| d
-        .msg
-        .read_uint());
-    primitive!(deserialize_u32, u32, visit_u32, UINT, |d: Self| d
-        .msg
-        .read_uint());
-    primitive!(deserialize_u64, u64, visit_u64, UINT, |d: Self| d
-        .msg
-        .read_uint());
-
-    primitive!(deserialize_f32, f32, visit_f32, FLOAT, |d: Self| d
-        .msg
-        .read_float());
-    primitive!(deserialize_f64, f64, visit_f64, FLOAT, |d: Self| d
-        .msg
-        .read_float());
+    number!(deserialize_i8, i8, visit_i8);
+    number!(deserialize_i16, i16, visit_i16);
+    number!(deserialize_i32, i32, visit_i32);
+    number!(deserialize_i64, i64, visit_i64);
+    number!(deserialize_i128, i128, visit_i128);
+
+    number!(deserialize_u8, u8, visit_u8);
+    number!(deserialize_u16, u16, visit_u16);
+    number!(deserialize_u32, u32, visit_u32);
+    number!(deserialize_u64, u64, visit_u64);
+    number!(deserialize_u128, u128, visit_u128);
 
+    number!(deserialize_f32, f32, visit_f32);
+    number!(deserialize_f64, f64, visit_f64);
+
+    // Go's `[]rune` decodes into strings as well
     fn deserialize_str<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, Self::Error> {
-        if self.type_id == TypeId::STRING {
-            visitor.visit_borrowed_str(self.deserialize_str_slice()?)
-        } else {
-            Err(serde::de::Error::custom("expected str"))
+        if self.type_id == TypeId::STRING || self.is_interchangeable(TypeId::BYTES) {
+            return self.visit_str(visitor);
+        }
+        if let Some(value) = self.hook()? {
+            return value.deserialize_str(visitor);
+        }
+        match self.defs.lookup(self.type_id) {
+            Some(WireType::Slice(slice_type)) if slice_type.elem == TypeId::INT => {
+                let len = self.msg.read_uint()?;
+                let mut s = String::new();
+                for _ in 0..len {
+                    s.push(char_from_rune(self.msg.read_int()?)?);
+                }
+                visitor.visit_string(s)
+            }
+            _ if self.map_key => match self.type_id {
+                TypeId::BOOL => visitor.visit_string(self.read_bool()?.to_string()),
+                TypeId::INT => visitor.visit_string(self.read_int()?.to_string()),
+                TypeId::UINT => visitor.visit_string(self.read_uint()?.to_string()),
+                TypeId::FLOAT => visitor.visit_string(self.read_float()?.to_string()),
+                _ => Err(serde::de::Error::custom("expected str")),
+            },
+            _ => Err(serde::de::Error::custom("expected str")),
         }
     }
 
@@ -166,11 +311,27 @@ impl<'t, 'de> serde::Deserializer<'de> for FieldValueDeserializer<'t, 'de> {
         self.deserialize_str(visitor)
     }
 
+    // byte slices are borrowed from the input, and values of types encoding
+    // themselves are byte slices as well; slices and arrays of uints, as
+    // sent for a `Vec<u8>`, are collected byte by byte
     fn deserialize_bytes<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, Self::Error> {
-        if self.type_id == TypeId::BYTES {
-            visitor.visit_borrowed_bytes(self.deserialize_byte_slice()?)
-        } else {
-            Err(serde::de::Error::custom("expected bytes"))
+        if self.type_id == TypeId::BYTES || self.is_interchangeable(TypeId::STRING) {
+            return visitor.visit_borrowed_bytes(self.deserialize_byte_slice()?);
+        }
+        if let Some(value) = self.hook()? {
+            return value.deserialize_bytes(visitor);
+        }
+        match self.defs.lookup(self.type_id) {
+            Some(WireType::GobEncoder(_)) => {
+                visitor.visit_borrowed_bytes(self.deserialize_byte_slice()?)
+            }
+            Some(WireType::Slice(slice_type)) if slice_type.elem == TypeId::UINT => {
+                visitor.visit_byte_buf(Vec::<u8>::deserialize(self)?)
+            }
+            Some(WireType::Array(array_type)) if array_type.elem == TypeId::UINT => {
+                visitor.visit_byte_buf(Vec::<u8>::deserialize(self)?)
+            }
+            _ => Err(serde::de::Error::custom("expected bytes")),
         }
     }
 
@@ -178,24 +339,106 @@ impl<'t, 'de> serde::Deserializer<'de> for FieldValueDeserializer<'t, 'de> {
         self.deserialize_bytes(visitor)
     }
 
+    // byte slices also decode into sequences of bytes, like `Vec<u8>`
+    fn deserialize_seq<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, Self::Error> {
+        if self.type_id == TypeId::BYTES || self.is_interchangeable(TypeId::STRING) {
+            let bytes = self.deserialize_byte_slice()?;
+            let mut seq = SeqDeserializer::<_, Error>::new(bytes.iter().cloned());
+            let value = visitor.visit_seq(&mut seq)?;
+            seq.end()?;
+            Ok(value)
+        } else if let Some(value) = self.hook()? {
+            value.deserialize_seq(visitor)
+        } else {
+            self.deserialize_any(visitor)
+        }
+    }
+
+    // fixed-size arrays only take arrays, slices and byte slices of their
+    // exact length, so that no elements are left over
+    fn deserialize_tuple<V: Visitor<'de>>(
+        mut self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        if self.type_id == TypeId::BYTES {
+            let bytes = self.deserialize_byte_slice()?;
+            if bytes.len() != len {
+                return Err(serde::de::Error::custom(format!(
+                    "array len mismatch (expected {}, got {})",
+                    len,
+                    bytes.len()
+                )));
+            }
+            return visitor.visit_seq(SeqDeserializer::<_, Error>::new(bytes.iter().cloned()));
+        }
+        if let Some(value) = self.hook()? {
+            return value.deserialize_tuple(len, visitor);
+        }
+        let elem = match self.defs.lookup(self.type_id) {
+            Some(WireType::Array(array_type)) => {
+                if array_type.len != len as i64 {
+                    return Err(serde::de::Error::custom(format!(
+                        "array len mismatch (expected {}, got {})",
+                        len, array_type.len
+                    )));
+                }
+                array_type.elem
+            }
+            Some(WireType::Slice(slice_type)) => slice_type.elem,
+            _ => return self.deserialize_any(visitor),
+        };
+        SeqValueDeserializer::new(Some(len), elem, self.defs, self.opts, self.msg)
+            .deserialize_any(visitor)
+    }
+
+    // a value that was sent is always `Some`, missing fields are left to
+    // serde, which makes them `None`
+    fn deserialize_option<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, Self::Error> {
+        if let Some(value) = self.hook()? {
+            return value.deserialize_option(visitor);
+        }
+        if self.opts.options == OptionEncoding::Slice {
+            if let Some(WireType::Slice(slice_type)) = self.defs.lookup(self.type_id) {
+                return match self.msg.read_uint()? {
+                    0 => visitor.visit_none(),
+                    1 => visitor.visit_some(FieldValueDeserializer::new(
+                        slice_type.elem,
+                        self.defs,
+                        self.opts,
+                        self.msg,
+                    )),
+                    len => Err(serde::de::Error::custom(format!(
+                        "expected an option, got a slice of {} elements",
+                        len
+                    ))),
+                };
+            }
+        }
+        if self.opts.non_finite_as_none && self.type_id == TypeId::FLOAT {
+            let pos = self.msg.get_ref().position();
+            let read_ahead = self.read_ahead;
+            if !self.read_float()?.is_finite() {
+                return visitor.visit_none();
+            }
+            self.read_ahead = read_ahead;
+            self.msg.get_mut().set_position(pos);
+        }
+        visitor.visit_some(self)
+    }
+
+    // chars are sent like Go's runes, as ints
     fn deserialize_char<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        let int = i64::deserialize(self)?;
-        if let Some(c) = ::std::char::from_u32(int as u32) {
-            visitor.visit_char(c)
-        } else {
-            Err(serde::de::Error::custom(format!(
-                "invalid char code {}",
-                int
-            )))
-        }
+        let rune = i64::deserialize(self)?;
+        visitor.visit_char(char_from_rune(rune)?)
     }
 
     #[inline]
     fn deserialize_enum<V>(
-        self,
+        mut self,
         name: &'static str,
         variants: &'static [&'static str],
         visitor: V,
@@ -203,8 +446,15 @@ impl<'t, 'de> serde::Deserializer<'de> for FieldValueDeserializer<'t, 'de> {
     where
         V: Visitor<'de>,
     {
+        if self.type_id == TypeId::INTERFACE {
+            let de = InterfaceValueDeserializer::new(self.defs, self.opts, self.msg);
+            return de.deserialize_enum(name, variants, visitor);
+        }
+        if let Some(value) = self.hook()? {
+            return value.deserialize_enum(name, variants, visitor);
+        }
         if let Some(&WireType::Struct(ref struct_type)) = self.defs.lookup(self.type_id) {
-            let de = StructValueDeserializer::new(struct_type, self.defs, self.msg);
+            let de = StructValueDeserializer::new(struct_type, self.defs, self.opts, self.msg);
             de.deserialize_enum(name, variants, visitor)
         } else {
             Err(serde::de::Error::custom("not an enum type"))
@@ -213,7 +463,7 @@ impl<'t, 'de> serde::Deserializer<'de> for FieldValueDeserializer<'t, 'de> {
 
     #[inline]
     fn deserialize_struct<V>(
-        self,
+        mut self,
         name: &'static str,
         fields: &'static [&'static str],
         visitor: V,
@@ -221,8 +471,15 @@ impl<'t, 'de> serde::Deserializer<'de> for FieldValueDeserializer<'t, 'de> {
     where
         V: Visitor<'de>,
     {
+        if self.type_id == TypeId::INTERFACE {
+            let de = InterfaceValueDeserializer::new(self.defs, self.opts, self.msg);
+            return de.deserialize_struct(name, fields, visitor);
+        }
+        if let Some(value) = self.hook()? {
+            return value.deserialize_struct(name, fields, visitor);
+        }
         if let Some(&WireType::Struct(ref struct_type)) = self.defs.lookup(self.type_id) {
-            let de = StructValueDeserializer::new(struct_type, self.defs, self.msg);
+            let de = StructValueDeserializer::new(struct_type, self.defs, self.opts, self.msg);
             de.deserialize_struct(name, fields, visitor)
         } else {
             Err(serde::de::Error::custom("not a struct type"))
@@ -238,8 +495,19 @@ impl<'t, 'de> serde::Deserializer<'de> for FieldValueDeserializer<'t, 'de> {
         visitor.visit_unit()
     }
 
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        if self.read_ahead.is_some() {
+            return visitor.visit_unit();
+        }
+        skip_field_value(self.type_id, self.defs, self.msg)?;
+        visitor.visit_unit()
+    }
+
     forward_to_deserialize_any! {
-        option unit_struct newtype_struct seq tuple
-        tuple_struct map identifier ignored_any
+        unit_struct newtype_struct
+        tuple_struct map identifier
     }
 }
@@ -0,0 +1,249 @@
+use std::borrow::Cow;
+use std::io::Cursor;
+
+use bytes::Buf;
+use serde::de::{
+    DeserializeSeed, Deserializer, EnumAccess, IgnoredAny, IntoDeserializer, VariantAccess, Visitor,
+};
+
+use de::{resolved, DecodeScope};
+use error::Error;
+use internal::gob::Message;
+use internal::types::{TypeId, Types};
+
+use crate::{de, error, internal};
+
+use super::value::ValueDeserializer;
+
+struct ConcreteValue<'t, 'de> {
+    name: &'de [u8],
+    type_id: TypeId,
+    msg: Message<Cursor<&'de [u8]>>,
+    // along with what the schema registry knows of the type, if it was
+    // not defined on the stream
+    defs: Cow<'t, Types>,
+}
+
+pub(crate) struct InterfaceValueDeserializer<'t, 'de>
+where
+    'de: 't,
+{
+    defs: &'t Types,
+    opts: DecodeScope<'t>,
+    msg: &'t mut Message<Cursor<&'de [u8]>>,
+}
+
+impl<'t, 'de> InterfaceValueDeserializer<'t, 'de> {
+    #[inline]
+    pub(crate) fn new(
+        defs: &'t Types,
+        opts: DecodeScope<'t>,
+        msg: &'t mut Message<Cursor<&'de [u8]>>,
+    ) -> InterfaceValueDeserializer<'t, 'de> {
+        InterfaceValueDeserializer { defs, opts, msg }
+    }
+
+    fn read_concrete_value(&mut self) -> Result<Option<ConcreteValue<'t, 'de>>, Error> {
+        //
+        // [ name len | name... | type id | value len | value... ]
+        //
+        // A name of length zero denotes a nil interface value,
+        // in which case nothing else follows.
+        //
+        let name_len = self.msg.read_bytes_len()?;
+        if name_len == 0 {
+            return Ok(None);
+        }
+        let name_pos = self.msg.get_ref().position() as usize;
+        self.msg.get_mut().advance(name_len);
+        let name = &self.msg.get_ref().get_ref()[name_pos..name_pos + name_len];
+
+        let type_id = self.msg.read_int()?;
+        if type_id < 0 {
+            return Err(serde::de::Error::custom(
+                "type definitions inside interface values are not supported",
+            ));
+        }
+
+        let len = self.msg.read_bytes_len()?;
+        let pos = self.msg.get_ref().position() as usize;
+        self.msg.get_mut().advance(len);
+        let bytes = &self.msg.get_ref().get_ref()[pos..pos + len];
+
+        Ok(Some(ConcreteValue {
+            name,
+            type_id: TypeId(type_id),
+            msg: Message::new(Cursor::new(bytes)),
+            defs: resolved(self.defs, TypeId(type_id))?,
+        }))
+    }
+}
+
+impl<'t, 'de> Deserializer<'de> for InterfaceValueDeserializer<'t, 'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(mut self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.read_concrete_value()? {
+            Some(mut concrete) => ValueDeserializer::new(
+                concrete.type_id,
+                &concrete.defs,
+                self.opts,
+                &mut concrete.msg,
+            )
+            .deserialize_any(visitor),
+            None => visitor.visit_none(),
+        }
+    }
+
+    fn deserialize_option<V>(mut self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.read_concrete_value()? {
+            Some(mut concrete) => visitor.visit_some(ValueDeserializer::new(
+                concrete.type_id,
+                &concrete.defs,
+                self.opts,
+                &mut concrete.msg,
+            )),
+            None => visitor.visit_none(),
+        }
+    }
+
+    fn deserialize_enum<V>(
+        mut self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.read_concrete_value()? {
+            Some(mut concrete) => {
+                // a variant named after the concrete type takes its value as
+                // is, otherwise the value itself has to look like an enum
+                let variant = ::std::str::from_utf8(concrete.name)
+                    .ok()
+                    .and_then(|wire_name| {
+                        variants
+                            .iter()
+                            .find(|variant| self.opts.pointer_names.matches(wire_name, variant))
+                    });
+                match variant {
+                    Some(variant) => visitor.visit_enum(ConcreteVariant {
+                        variant,
+                        concrete,
+                        opts: self.opts,
+                    }),
+                    None => ValueDeserializer::new(
+                        concrete.type_id,
+                        &concrete.defs,
+                        self.opts,
+                        &mut concrete.msg,
+                    )
+                    .deserialize_enum(name, variants, visitor),
+                }
+            }
+            None => Err(serde::de::Error::custom("nil interface value")),
+        }
+    }
+
+    fn deserialize_struct<V>(
+        mut self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.read_concrete_value()? {
+            Some(mut concrete) => ValueDeserializer::new(
+                concrete.type_id,
+                &concrete.defs,
+                self.opts,
+                &mut concrete.msg,
+            )
+            .deserialize_struct(name, fields, visitor),
+            None => Err(serde::de::Error::custom("nil interface value")),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes
+        byte_buf unit unit_struct newtype_struct seq tuple
+        tuple_struct map identifier ignored_any
+    }
+}
+
+struct ConcreteVariant<'t, 'de> {
+    variant: &'static str,
+    concrete: ConcreteValue<'t, 'de>,
+    opts: DecodeScope<'t>,
+}
+
+impl<'t, 'de> ConcreteVariant<'t, 'de> {
+    fn value_deserializer<'a>(&'a mut self) -> ValueDeserializer<'a, 'de> {
+        ValueDeserializer::new(
+            self.concrete.type_id,
+            &self.concrete.defs,
+            self.opts,
+            &mut self.concrete.msg,
+        )
+    }
+}
+
+impl<'t, 'de> EnumAccess<'de> for ConcreteVariant<'t, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let de = <&str as IntoDeserializer<'_, Error>>::into_deserializer(self.variant);
+        let val = seed.deserialize(de)?;
+        Ok((val, self))
+    }
+}
+
+impl<'t, 'de> VariantAccess<'de> for ConcreteVariant<'t, 'de> {
+    type Error = Error;
+
+    fn unit_variant(mut self) -> Result<(), Error> {
+        self.value_deserializer()
+            .deserialize_ignored_any(IgnoredAny)?;
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(mut self, seed: T) -> Result<T::Value, Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        seed.deserialize(self.value_deserializer())
+    }
+
+    fn tuple_variant<V>(mut self, _len: usize, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.value_deserializer().deserialize_seq(visitor)
+    }
+
+    fn struct_variant<V>(
+        mut self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        let variant = self.variant;
+        self.value_deserializer()
+            .deserialize_struct(variant, fields, visitor)
+    }
+}
@@ -2,9 +2,10 @@ use std::io::Cursor;
 
 use serde::de::{DeserializeSeed, Deserializer, MapAccess, Visitor};
 
-use crate::{error, internal};
+use crate::{de, error, internal};
 
 use super::FieldValueDeserializer;
+use de::DecodeScope;
 use error::Error;
 use internal::gob::Message;
 use internal::types::{MapType, Types};
@@ -15,6 +16,7 @@ where
 {
     def: &'t MapType,
     defs: &'t Types,
+    opts: DecodeScope<'t>,
     remaining_count: u64,
     msg: &'t mut Message<Cursor<&'de [u8]>>,
 }
@@ -23,6 +25,7 @@ impl<'t, 'de> MapMapAccess<'t, 'de> {
     fn new(
         def: &'t MapType,
         defs: &'t Types,
+        opts: DecodeScope<'t>,
         msg: &'t mut Message<Cursor<&'de [u8]>>,
     ) -> Result<MapMapAccess<'t, 'de>, Error> {
         let remaining_count = msg.read_uint()?;
@@ -30,6 +33,7 @@ impl<'t, 'de> MapMapAccess<'t, 'de> {
         Ok(MapMapAccess {
             def,
             defs,
+            opts,
             remaining_count,
             msg,
         })
@@ -47,7 +51,8 @@ impl<'f, 'de> MapAccess<'de> for MapMapAccess<'f, 'de> {
             return Ok(None);
         }
         self.remaining_count -= 1;
-        let de = FieldValueDeserializer::new(self.def.key, self.defs, &mut self.msg);
+        let de = FieldValueDeserializer::new(self.def.key, self.defs, self.opts, &mut self.msg)
+            .map_key();
         seed.deserialize(de).map(Some)
     }
 
@@ -55,7 +60,7 @@ impl<'f, 'de> MapAccess<'de> for MapMapAccess<'f, 'de> {
     where
         V: DeserializeSeed<'de>,
     {
-        let de = FieldValueDeserializer::new(self.def.elem, self.defs, &mut self.msg);
+        let de = FieldValueDeserializer::new(self.def.elem, self.defs, self.opts, &mut self.msg);
         seed.deserialize(de)
     }
 
@@ -70,6 +75,7 @@ where
 {
     def: &'t MapType,
     defs: &'t Types,
+    opts: DecodeScope<'t>,
     msg: &'t mut Message<Cursor<&'de [u8]>>,
 }
 
@@ -78,9 +84,15 @@ impl<'t, 'de> MapValueDeserializer<'t, 'de> {
     pub(crate) fn new(
         def: &'t MapType,
         defs: &'t Types,
+        opts: DecodeScope<'t>,
         msg: &'t mut Message<Cursor<&'de [u8]>>,
     ) -> MapValueDeserializer<'t, 'de> {
-        MapValueDeserializer { def, defs, msg }
+        MapValueDeserializer {
+            def,
+            defs,
+            opts,
+            msg,
+        }
     }
 }
 
@@ -92,7 +104,7 @@ impl<'t, 'de> Deserializer<'de> for MapValueDeserializer<'t, 'de> {
     where
         V: Visitor<'de>,
     {
-        visitor.visit_map(MapMapAccess::new(self.def, self.defs, self.msg)?)
+        visitor.visit_map(MapMapAccess::new(self.def, self.defs, self.opts, self.msg)?)
     }
 
     forward_to_deserialize_any! {
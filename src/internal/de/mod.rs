@@ -1,9 +1,13 @@
 mod complex_value;
 mod field_value;
+mod interface_value;
 mod map_value;
+mod number;
 mod seq_value;
+mod skip;
 mod struct_value;
 mod value;
 
 pub(crate) use self::field_value::FieldValueDeserializer;
+pub(crate) use self::skip::skip_field_value;
 pub(crate) use self::value::ValueDeserializer;
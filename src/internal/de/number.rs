@@ -0,0 +1,93 @@
+use de::{DecodeOptions, NumericCoercion};
+
+use crate::de;
+
+/// A number as it was sent, before conversion.
+#[derive(Clone, Copy)]
+pub(crate) enum Number {
+    Int(i64),
+    Uint(u64),
+    Float(f64),
+}
+
+pub(crate) trait Coerce: Sized {
+    fn coerce(n: Number, opts: &DecodeOptions) -> Result<Self, String>;
+}
+
+fn overflow<N: ::std::fmt::Display>(n: N, to: &str) -> String {
+    format!("integer overflow: {} does not fit into {}", n, to)
+}
+
+macro_rules! coerce_integer {
+    ($($t:ident: $signed:expr),*) => {
+        $(
+            impl Coerce for $t {
+                fn coerce(n: Number, opts: &DecodeOptions) -> Result<$t, String> {
+                    let signed = $signed;
+                    match (n, opts.numbers) {
+                        (Number::Int(v), NumericCoercion::Strict) if signed => {
+                            $t::try_from(v).map_err(|_| overflow(v, stringify!($t)))
+                        }
+                        (Number::Uint(v), NumericCoercion::Strict) if !signed => {
+                            $t::try_from(v).map_err(|_| overflow(v, stringify!($t)))
+                        }
+                        (_, NumericCoercion::Strict) => {
+                            Err(format!("expected {}", stringify!($t)))
+                        }
+                        (Number::Int(v), NumericCoercion::Saturating) => Ok($t::try_from(v)
+                            .unwrap_or(if v < 0 { $t::MIN } else { $t::MAX })),
+                        (Number::Uint(v), NumericCoercion::Saturating) => {
+                            Ok($t::try_from(v).unwrap_or($t::MAX))
+                        }
+                        (Number::Int(v), NumericCoercion::Lossy) => Ok(v as $t),
+                        (Number::Uint(v), NumericCoercion::Lossy) => Ok(v as $t),
+                        // saturates, and maps NaN to zero
+                        (Number::Float(v), _) => Ok(v as $t),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+coerce_integer!(
+    i8: true, i16: true, i32: true, i64: true, i128: true,
+    u8: false, u16: false, u32: false, u64: false, u128: false
+);
+
+impl Coerce for f64 {
+    fn coerce(n: Number, opts: &DecodeOptions) -> Result<f64, String> {
+        match (n, opts.numbers) {
+            (Number::Float(v), _) => Ok(v),
+            (_, NumericCoercion::Strict) => Err("expected f64".to_owned()),
+            (Number::Int(v), _) => Ok(v as f64),
+            (Number::Uint(v), _) => Ok(v as f64),
+        }
+    }
+}
+
+impl Coerce for f32 {
+    fn coerce(n: Number, opts: &DecodeOptions) -> Result<f32, String> {
+        let v = f64::coerce(n, opts)?;
+        let narrowed = v as f32;
+        // like Go, infinities pass but finite values must stay finite
+        if narrowed.is_infinite() && v.is_finite() {
+            match opts.numbers {
+                NumericCoercion::Strict => {
+                    return Err(format!("float overflow: {} does not fit into f32", v));
+                }
+                NumericCoercion::Saturating => {
+                    return Ok(if v < 0.0 { f32::MIN } else { f32::MAX });
+                }
+                NumericCoercion::Lossy => {}
+            }
+        }
+        if opts.exact_f32 && !v.is_nan() && f64::from(narrowed) != v {
+            return Err(format!(
+                "precision loss: {} does not fit into f32 exactly",
+                v
+            ));
+        }
+        Ok(narrowed)
+    }
+}
@@ -2,6 +2,7 @@ use std::io::Cursor;
 
 use serde::de::{self, DeserializeSeed, Deserializer, Visitor};
 
+use crate::de as other_de;
 use crate::{error, internal};
 
 use super::FieldValueDeserializer;
@@ -9,6 +10,7 @@ use error::Error;
 use internal::gob::Message;
 use internal::types::TypeId;
 use internal::types::Types;
+use other_de::DecodeScope;
 
 struct SeqAccess<'t, 'de>
 where
@@ -16,15 +18,26 @@ where
 {
     element: TypeId,
     defs: &'t Types,
+    opts: DecodeScope<'t>,
     remaining_count: u64,
+    index: u64,
     msg: &'t mut Message<Cursor<&'de [u8]>>,
+    // bools and numbers are read ahead in batches, handed out from
+    // `ahead[ahead_pos..ahead_len]`
+    batched: bool,
+    ahead: [u64; BATCH_LEN],
+    ahead_pos: usize,
+    ahead_len: usize,
 }
 
+const BATCH_LEN: usize = 32;
+
 impl<'t, 'de> SeqAccess<'t, 'de> {
     fn new(
         len: Option<usize>,
         element: TypeId,
         defs: &'t Types,
+        opts: DecodeScope<'t>,
         msg: &'t mut Message<Cursor<&'de [u8]>>,
     ) -> Result<SeqAccess<'t, 'de>, Error> {
         let remaining_count = msg.read_uint()?;
@@ -38,13 +51,39 @@ impl<'t, 'de> SeqAccess<'t, 'de> {
             }
         }
 
+        let batched = match element {
+            TypeId::BOOL | TypeId::INT | TypeId::UINT | TypeId::FLOAT => {
+                defs.hook(element).is_none()
+            }
+            _ => false,
+        };
+
         Ok(SeqAccess {
             element,
             defs,
+            opts,
             remaining_count,
+            index: 0,
             msg,
+            batched,
+            ahead: [0; BATCH_LEN],
+            ahead_pos: 0,
+            ahead_len: 0,
         })
     }
+
+    fn next_ahead(&mut self) -> Result<u64, Error> {
+        if self.ahead_pos == self.ahead_len {
+            // the element being read is no longer counted as remaining
+            let len = (self.remaining_count + 1).min(BATCH_LEN as u64) as usize;
+            self.msg.read_uints(&mut self.ahead[..len])?;
+            self.ahead_pos = 0;
+            self.ahead_len = len;
+        }
+        let bits = self.ahead[self.ahead_pos];
+        self.ahead_pos += 1;
+        Ok(bits)
+    }
 }
 
 impl<'f, 'de> de::SeqAccess<'de> for SeqAccess<'f, 'de> {
@@ -58,8 +97,23 @@ impl<'f, 'de> de::SeqAccess<'de> for SeqAccess<'f, 'de> {
             return Ok(None);
         }
         self.remaining_count -= 1;
-        let de = FieldValueDeserializer::new(self.element, self.defs, &mut self.msg);
-        seed.deserialize(de).map(Some)
+        let index = self.index;
+        self.index += 1;
+        let bits = if self.batched {
+            Some(
+                self.next_ahead()
+                    .map_err(|err| err.within(&index.to_string()))?,
+            )
+        } else {
+            None
+        };
+        let mut de = FieldValueDeserializer::new(self.element, self.defs, self.opts, &mut self.msg);
+        if let Some(bits) = bits {
+            de = de.read_ahead(bits);
+        }
+        seed.deserialize(de)
+            .map(Some)
+            .map_err(|err| err.within(&index.to_string()))
     }
 
     fn size_hint(&self) -> Option<usize> {
@@ -74,6 +128,7 @@ where
     len: Option<usize>,
     element: TypeId,
     defs: &'t Types,
+    opts: DecodeScope<'t>,
     msg: &'t mut Message<Cursor<&'de [u8]>>,
 }
 
@@ -83,12 +138,14 @@ impl<'t, 'de> SeqValueDeserializer<'t, 'de> {
         len: Option<usize>,
         element: TypeId,
         defs: &'t Types,
+        opts: DecodeScope<'t>,
         msg: &'t mut Message<Cursor<&'de [u8]>>,
     ) -> SeqValueDeserializer<'t, 'de> {
         SeqValueDeserializer {
             len,
             element,
             defs,
+            opts,
             msg,
         }
     }
@@ -102,7 +159,13 @@ impl<'t, 'de> Deserializer<'de> for SeqValueDeserializer<'t, 'de> {
     where
         V: Visitor<'de>,
     {
-        visitor.visit_seq(SeqAccess::new(self.len, self.element, self.defs, self.msg)?)
+        visitor.visit_seq(SeqAccess::new(
+            self.len,
+            self.element,
+            self.defs,
+            self.opts,
+            self.msg,
+        )?)
     }
 
     forward_to_deserialize_any! {
@@ -0,0 +1,127 @@
+use std::io::Cursor;
+
+use bytes::Buf;
+
+use error::Error;
+use internal::gob::Message;
+use internal::types::{TypeId, Types, WireType};
+
+use crate::{error, internal};
+
+/// Advances `msg` past a top-level value of type `type_id`
+/// without decoding it.
+pub(crate) fn skip_value(
+    type_id: TypeId,
+    defs: &Types,
+    msg: &mut Message<Cursor<&[u8]>>,
+) -> Result<(), Error> {
+    if let Some(WireType::Struct(_)) = defs.lookup(type_id) {
+        return skip_field_value(type_id, defs, msg);
+    }
+    if msg.read_uint()? != 0 {
+        return Err(serde::de::Error::custom(
+            "neither a singleton nor a struct value",
+        ));
+    }
+    skip_field_value(type_id, defs, msg)
+}
+
+/// Advances `msg` past a value of type `type_id`, only looking at lengths
+/// and at the structure of composite types.
+pub(crate) fn skip_field_value(
+    type_id: TypeId,
+    defs: &Types,
+    msg: &mut Message<Cursor<&[u8]>>,
+) -> Result<(), Error> {
+    match type_id {
+        TypeId::BOOL | TypeId::INT | TypeId::UINT | TypeId::FLOAT => {
+            msg.read_uint()?;
+        }
+        TypeId::BYTES | TypeId::STRING => skip_bytes(msg)?,
+        TypeId::COMPLEX => msg.skip_uints(2)?,
+        TypeId::INTERFACE => {
+            let name_len = msg.read_bytes_len()?;
+            if name_len > 0 {
+                msg.get_mut().advance(name_len);
+                msg.read_int()?;
+                skip_bytes(msg)?;
+            }
+        }
+        _ => match defs.lookup(type_id) {
+            Some(WireType::Array(array_type)) => {
+                let len = msg.read_uint()?;
+                if len != array_type.len as u64 {
+                    return Err(serde::de::Error::custom(format!(
+                        "sequence len mismatch (expected {}, got {})",
+                        array_type.len, len
+                    )));
+                }
+                skip_elements(array_type.elem, len, defs, msg)?;
+            }
+            Some(WireType::Slice(slice_type)) => {
+                let len = msg.read_uint()?;
+                skip_elements(slice_type.elem, len, defs, msg)?;
+            }
+            Some(WireType::GobEncoder(_)) => skip_bytes(msg)?,
+            Some(WireType::Map(map_type)) => {
+                for _ in 0..msg.read_uint()? {
+                    skip_field_value(map_type.key, defs, msg)?;
+                    skip_field_value(map_type.elem, defs, msg)?;
+                }
+            }
+            Some(WireType::Struct(struct_type)) => {
+                let mut field_no = -1i64;
+                loop {
+                    let delta = msg.read_uint()?;
+                    if delta == 0 {
+                        break;
+                    }
+                    field_no += delta as i64;
+                    let field = match struct_type.fields.get(field_no as usize) {
+                        Some(field) => field,
+                        None => {
+                            return Err(serde::de::Error::custom(format!(
+                                "field number overflow ({}) on type {:?}",
+                                field_no, struct_type
+                            )))
+                        }
+                    };
+                    skip_field_value(field.id, defs, msg)?;
+                }
+            }
+            None => {
+                return Err(serde::de::Error::custom(format!(
+                    "unknown type id {:?}",
+                    type_id
+                )))
+            }
+        },
+    }
+    Ok(())
+}
+
+fn skip_bytes(msg: &mut Message<Cursor<&[u8]>>) -> Result<(), Error> {
+    let len = msg.read_bytes_len()?;
+    msg.get_mut().advance(len);
+    Ok(())
+}
+
+// Runs of numbers are skipped in one go, without going through their types
+// one element at a time.
+fn skip_elements(
+    elem: TypeId,
+    len: u64,
+    defs: &Types,
+    msg: &mut Message<Cursor<&[u8]>>,
+) -> Result<(), Error> {
+    match elem {
+        TypeId::BOOL | TypeId::INT | TypeId::UINT | TypeId::FLOAT => msg.skip_uints(len)?,
+        TypeId::COMPLEX => msg.skip_uints(len.saturating_mul(2))?,
+        _ => {
+            for _ in 0..len {
+                skip_field_value(elem, defs, msg)?;
+            }
+        }
+    }
+    Ok(())
+}
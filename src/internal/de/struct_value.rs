@@ -1,16 +1,20 @@
 use std::io::Cursor;
+use std::marker::PhantomData;
 
 use serde;
-use serde::de::{DeserializeSeed, Deserializer, IntoDeserializer, Visitor};
+use serde::de::{DeserializeSeed, Deserializer, IgnoredAny, IntoDeserializer, Visitor};
 use serde::de::{EnumAccess, MapAccess, VariantAccess};
+use serde::Deserialize;
 
-use crate::{error, internal};
+use crate::{de, error, internal};
 
+use super::skip::skip_field_value;
 use super::FieldValueDeserializer;
+use de::{DecodeScope, EnumDecoding};
 use error::Error;
 use internal::{
     gob::Message,
-    types::{FieldType, StructType, TypeId, Types},
+    types::{FieldIndices, FieldType, StructType, TypeId, Types, WireType},
 };
 
 struct StructAccess<'t, 'de>
@@ -19,8 +23,17 @@ where
 {
     def: &'t StructType,
     defs: &'t Types,
+    opts: DecodeScope<'t>,
     field_no: i64,
     field_id: TypeId,
+    field_name: &'t str,
+    projection: Option<&'t [&'t str]>,
+    hidden: Option<&'t str>,
+    flatten: bool,
+    // the structs and field numbers embedded structs were entered from
+    outer: Vec<(&'t StructType, i64)>,
+    // the indices to hand out for the fields of `def`, rather than names
+    indices: Option<FieldIndices>,
     msg: &'t mut Message<Cursor<&'de [u8]>>,
 }
 
@@ -28,17 +41,50 @@ impl<'t, 'de> StructAccess<'t, 'de> {
     fn new(
         def: &'t StructType,
         defs: &'t Types,
+        opts: DecodeScope<'t>,
         msg: &'t mut Message<Cursor<&'de [u8]>>,
     ) -> StructAccess<'t, 'de> {
         StructAccess {
             def,
             defs,
+            opts,
             field_no: -1,
             field_id: TypeId(0),
+            field_name: "",
+            projection: None,
+            hidden: None,
+            flatten: opts.flatten_embedded,
+            outer: Vec::new(),
+            indices: None,
             msg,
         }
     }
 
+    // the type of the field if it is an embedded struct to be flattened,
+    // which Go names after its type
+    fn embedded(&self, field: &FieldType) -> Option<&'t StructType> {
+        if !self.flatten {
+            return None;
+        }
+        match self.defs.lookup(field.id) {
+            Some(WireType::Struct(struct_type)) if struct_type.common.name == field.name => {
+                Some(struct_type)
+            }
+            _ => None,
+        }
+    }
+
+    // whether the field is to be skipped rather than handed out
+    fn projected_out(&self, field: &FieldType) -> bool {
+        if self.hidden == Some(&*field.name) {
+            return true;
+        }
+        match self.projection {
+            Some(fields) => !fields.iter().any(|name| *name == field.name),
+            None => false,
+        }
+    }
+
     fn current_field(&self) -> Result<&'t FieldType, Error> {
         let field_no = self.field_no as usize;
         self.def.fields.get(field_no).ok_or_else(|| {
@@ -57,16 +103,49 @@ impl<'t, 'de> MapAccess<'de> for StructAccess<'t, 'de> {
     where
         K: DeserializeSeed<'de>,
     {
-        let field_delta = self.msg.read_uint()?;
+        let field = loop {
+            let field_delta = self.msg.read_uint()?;
 
-        if field_delta == 0 {
-            return Ok(None);
-        }
+            if field_delta == 0 {
+                match self.outer.pop() {
+                    Some((def, field_no)) => {
+                        self.def = def;
+                        self.field_no = field_no;
+                        continue;
+                    }
+                    None => return Ok(None),
+                }
+            }
 
-        self.field_no += field_delta as i64;
-        let field = self.current_field()?;
+            self.field_no += field_delta as i64;
+            let field = self.current_field()?;
+            if self.projected_out(field) || self.opts.is_skipped(self.msg.get_ref().position()) {
+                skip_field_value(field.id, self.defs, self.msg)?;
+                continue;
+            }
+            if let Some(embedded) = self.embedded(field) {
+                self.outer.push((self.def, self.field_no));
+                self.def = embedded;
+                self.field_no = -1;
+                continue;
+            }
+            break field;
+        };
         self.field_id = field.id;
+        self.field_name = &field.name;
 
+        let index = match self.indices {
+            // fields of embedded structs have no index in the outer one
+            Some(ref indices) if self.outer.is_empty() => indices[self.field_no as usize],
+            _ => None,
+        };
+        if let Some(index) = index {
+            let de = FieldKey {
+                index,
+                name: &field.name,
+            };
+            return seed.deserialize(de).map(Some);
+        }
         let de = <&str as IntoDeserializer<'_, Error>>::into_deserializer(&field.name);
         let value = seed.deserialize(de)?;
         Ok(Some(value))
@@ -76,8 +155,10 @@ impl<'t, 'de> MapAccess<'de> for StructAccess<'t, 'de> {
     where
         V: DeserializeSeed<'de>,
     {
-        let de = FieldValueDeserializer::new(self.field_id, self.defs, &mut self.msg);
+        let start = self.msg.get_ref().position();
+        let de = FieldValueDeserializer::new(self.field_id, self.defs, self.opts, &mut self.msg);
         seed.deserialize(de)
+            .map_err(|err| err.in_field_at(start).within(self.field_name))
     }
 }
 
@@ -89,6 +170,9 @@ impl<'t, 'de> EnumAccess<'de> for StructAccess<'t, 'de> {
     where
         V: DeserializeSeed<'de>,
     {
+        // the field holding the variant is named after the variant, which
+        // may well be the name of its type
+        self.flatten = false;
         if let Some(val) = self.next_key_seed(seed)? {
             Ok((val, self))
         } else {
@@ -110,7 +194,7 @@ impl<'t, 'de> VariantAccess<'de> for StructAccess<'t, 'de> {
     {
         let field = self.current_field()?;
         let val = {
-            let de = FieldValueDeserializer::new(field.id, self.defs, &mut self.msg);
+            let de = FieldValueDeserializer::new(field.id, self.defs, self.opts, &mut self.msg);
             seed.deserialize(de)?
         };
         let field_delta = self.msg.read_uint()?;
@@ -129,7 +213,7 @@ impl<'t, 'de> VariantAccess<'de> for StructAccess<'t, 'de> {
     {
         let field = self.current_field()?;
         let val = {
-            let de = FieldValueDeserializer::new(field.id, self.defs, &mut self.msg);
+            let de = FieldValueDeserializer::new(field.id, self.defs, self.opts, &mut self.msg);
             de.deserialize_seq(visitor)?
         };
         let field_delta = self.msg.read_uint()?;
@@ -152,7 +236,7 @@ impl<'t, 'de> VariantAccess<'de> for StructAccess<'t, 'de> {
     {
         let field = self.current_field()?;
         let val = {
-            let de = FieldValueDeserializer::new(field.id, self.defs, &mut self.msg);
+            let de = FieldValueDeserializer::new(field.id, self.defs, self.opts, &mut self.msg);
             de.deserialize_seq(visitor)?
         };
         let field_delta = self.msg.read_uint()?;
@@ -172,6 +256,9 @@ where
 {
     def: &'t StructType,
     defs: &'t Types,
+    opts: DecodeScope<'t>,
+    projection: Option<&'t [&'t str]>,
+    hidden: Option<&'t str>,
     msg: &'t mut Message<Cursor<&'de [u8]>>,
 }
 
@@ -180,9 +267,310 @@ impl<'t, 'de> StructValueDeserializer<'t, 'de> {
     pub(crate) fn new(
         def: &'t StructType,
         defs: &'t Types,
+        opts: DecodeScope<'t>,
         msg: &'t mut Message<Cursor<&'de [u8]>>,
     ) -> StructValueDeserializer<'t, 'de> {
-        StructValueDeserializer { def, defs, msg }
+        StructValueDeserializer {
+            def,
+            defs,
+            opts,
+            projection: None,
+            hidden: None,
+            msg,
+        }
+    }
+
+    /// Only hands out the fields named in `projection`, skipping all others.
+    pub(crate) fn with_projection(mut self, projection: Option<&'t [&'t str]>) -> Self {
+        self.projection = projection;
+        self
+    }
+
+    fn access(self) -> StructAccess<'t, 'de> {
+        let mut access = StructAccess::new(self.def, self.defs, self.opts, self.msg);
+        access.projection = self.projection;
+        access.hidden = self.hidden;
+        access
+    }
+
+    // finds the value of the field `tag` without consuming any input
+    fn read_tag(&mut self, tag: &str) -> Result<Option<Tag>, Error> {
+        let start = self.msg.get_ref().position();
+        let result = self.scan_tag(tag);
+        self.msg.get_mut().set_position(start);
+        result
+    }
+
+    fn scan_tag(&mut self, tag: &str) -> Result<Option<Tag>, Error> {
+        let mut field_no = -1;
+        loop {
+            let field_delta = self.msg.read_uint()?;
+            if field_delta == 0 {
+                return Ok(None);
+            }
+            field_no += field_delta as i64;
+            let field = match self.def.fields.get(field_no as usize) {
+                Some(field) => field,
+                None => {
+                    return Err(serde::de::Error::custom(format!(
+                        "field number overflow ({}) on type {:?}",
+                        field_no, self.def
+                    )))
+                }
+            };
+            if field.name != tag {
+                skip_field_value(field.id, self.defs, self.msg)?;
+                continue;
+            }
+            let de = FieldValueDeserializer::new(field.id, self.defs, self.opts, self.msg);
+            return match field.id {
+                TypeId::STRING => String::deserialize(de).map(|name| Some(Tag::Name(name))),
+                TypeId::INT | TypeId::UINT => {
+                    u64::deserialize(de).map(|index| Some(Tag::Index(index)))
+                }
+                _ => Err(serde::de::Error::custom(format!(
+                    "enum tag {} is neither a string nor an integer",
+                    tag
+                ))),
+            };
+        }
+    }
+
+    // the variant of `variants` selected by the struct, according to
+    // `EnumDecoding`
+    fn select_variant(
+        &mut self,
+        tag: &str,
+        variants: &'static [&'static str],
+    ) -> Result<String, Error> {
+        let index = match self.read_tag(tag)? {
+            Some(Tag::Name(name)) => return Ok(name),
+            Some(Tag::Index(index)) => index,
+            None => match self.def.fields.iter().find(|field| field.name == tag) {
+                Some(field) if field.id == TypeId::INT || field.id == TypeId::UINT => 0,
+                _ => {
+                    return Err(serde::de::Error::custom(format!(
+                        "missing enum tag {}",
+                        tag
+                    )))
+                }
+            },
+        };
+        match variants.get(index as usize) {
+            Some(variant) => Ok((*variant).to_owned()),
+            None => Err(serde::de::Error::custom(format!(
+                "enum tag {} out of range ({} variants)",
+                index,
+                variants.len()
+            ))),
+        }
+    }
+}
+
+enum Tag {
+    Name(String),
+    Index(u64),
+}
+
+// The position in `fields` of each field of `def`, as far as it is sure
+// to be the index of the field. Serde's generated field visitors take the
+// index of a field as well as its name, but list the aliases of each field
+// among `fields`, which shifts the positions of all fields after them.
+// Aliases are not marked as such, and a struct type may well have a field
+// as well as one of its aliases. But serde lists a field's names in
+// ascending order, so there is no alias before the first two names that
+// are: positions up to there are indices, and later fields are handed out
+// by name. So are all of them unless `def` has a field by each of the
+// names, as hand-written identifiers may only take names.
+fn index_fields(def: &StructType, fields: &[&str]) -> Option<FieldIndices> {
+    if fields.is_empty()
+        || !fields
+            .iter()
+            .all(|name| def.fields.iter().any(|field| field.name == *name))
+    {
+        return None;
+    }
+    let sure = 1 + fields
+        .windows(2)
+        .take_while(|pair| pair[0] > pair[1])
+        .count();
+    let indices: Vec<Option<u64>> = def
+        .fields
+        .iter()
+        .map(|field| {
+            fields[..sure]
+                .iter()
+                .position(|name| *name == field.name)
+                .map(|pos| pos as u64)
+        })
+        .collect();
+    Some(indices.into())
+}
+
+// hands out a field by its index to field visitors, which take it as an
+// identifier, and by its name to keys of any other type, like `String`
+struct FieldKey<'t> {
+    index: u64,
+    name: &'t str,
+}
+
+impl<'t, 'de> Deserializer<'de> for FieldKey<'t> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_str(self.name)
+    }
+
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_u64(self.index)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum ignored_any
+    }
+}
+
+// hands out the variant selected by a tag or type name, with the rest of
+// the struct as its value
+struct SelectedVariant<'t, 'de>
+where
+    'de: 't,
+{
+    variant: String,
+    value: StructValueDeserializer<'t, 'de>,
+}
+
+impl<'t, 'de> EnumAccess<'de> for SelectedVariant<'t, 'de> {
+    type Error = Error;
+    type Variant = StructValueDeserializer<'t, 'de>;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let de = <String as IntoDeserializer<'_, Error>>::into_deserializer(self.variant);
+        Ok((seed.deserialize(de)?, self.value))
+    }
+}
+
+// hands out the variant selected by a tag, with the field following it as
+// its value
+struct AdjacentVariant<'t, 'de>
+where
+    'de: 't,
+{
+    variant: String,
+    present: bool,
+    access: StructAccess<'t, 'de>,
+}
+
+impl<'t, 'de> AdjacentVariant<'t, 'de> {
+    fn value(self) -> Result<StructAccess<'t, 'de>, Error> {
+        if self.present {
+            Ok(self.access)
+        } else {
+            Err(serde::de::Error::custom(format!(
+                "missing value of variant {}",
+                self.variant
+            )))
+        }
+    }
+}
+
+impl<'t, 'de> EnumAccess<'de> for AdjacentVariant<'t, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let de = <&str as IntoDeserializer<'_, Error>>::into_deserializer(&self.variant);
+        let variant = seed.deserialize(de)?;
+        Ok((variant, self))
+    }
+}
+
+impl<'t, 'de> VariantAccess<'de> for AdjacentVariant<'t, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        if self.present {
+            Err(serde::de::Error::custom(format!(
+                "unit variant {} has a value",
+                self.variant
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        self.value()?.newtype_variant_seed(seed)
+    }
+
+    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.value()?.tuple_variant(len, visitor)
+    }
+
+    fn struct_variant<V>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.value()?.struct_variant(fields, visitor)
+    }
+}
+
+impl<'t, 'de> VariantAccess<'de> for StructValueDeserializer<'t, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        self.deserialize_ignored_any(IgnoredAny).map(|_| ())
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V>(self, _len: usize, _visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        Err(serde::de::Error::custom(
+            "tuple variants can't be selected by a tag",
+        ))
+    }
+
+    fn struct_variant<V>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_struct("", fields, visitor)
     }
 }
 
@@ -194,25 +582,93 @@ impl<'t, 'de> Deserializer<'de> for StructValueDeserializer<'t, 'de> {
     where
         V: Visitor<'de>,
     {
-        visitor.visit_map(StructAccess::new(self.def, self.defs, self.msg))
+        visitor.visit_map(self.access())
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let indices = if self.opts.field_indices {
+            let def = self.def;
+            self.defs
+                .field_indices(def.common.id, fields, || index_fields(def, fields))
+        } else {
+            None
+        };
+        let mut access = self.access();
+        access.indices = indices;
+        visitor.visit_map(access)
     }
 
     #[inline]
     fn deserialize_enum<V>(
-        self,
+        mut self,
         _: &'static str,
-        _: &'static [&'static str],
+        variants: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_enum(StructAccess::new(self.def, self.defs, self.msg))
+        let variant = match self.opts.enums {
+            EnumDecoding::External => return visitor.visit_enum(self.access()),
+            EnumDecoding::Internal(tag) => {
+                let variant = self.select_variant(tag, variants)?;
+                self.hidden = Some(tag);
+                variant
+            }
+            EnumDecoding::Adjacent(tag) => {
+                let variant = self.select_variant(tag, variants)?;
+                self.hidden = Some(tag);
+                let mut access = self.access();
+                access.flatten = false;
+                let present = match access.next_key_seed(PhantomData::<String>)? {
+                    Some(name) if name == variant => true,
+                    Some(name) => {
+                        return Err(serde::de::Error::custom(format!(
+                            "expected the field of variant {}, got {}",
+                            variant, name
+                        )))
+                    }
+                    None => false,
+                };
+                return visitor.visit_enum(AdjacentVariant {
+                    variant,
+                    present,
+                    access,
+                });
+            }
+            EnumDecoding::TypeName => {
+                let name = &self.def.common.name;
+                match variants
+                    .iter()
+                    .find(|variant| self.opts.pointer_names.matches(name, variant))
+                {
+                    Some(variant) => (*variant).to_owned(),
+                    None => {
+                        return Err(serde::de::Error::custom(format!(
+                            "no variant matches type {}",
+                            name
+                        )))
+                    }
+                }
+            }
+        };
+        visitor.visit_enum(SelectedVariant {
+            variant,
+            value: self,
+        })
     }
 
     forward_to_deserialize_any! {
         bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes
         byte_buf option unit unit_struct newtype_struct seq tuple
-        tuple_struct map struct identifier ignored_any
+        tuple_struct map identifier ignored_any
     }
 }
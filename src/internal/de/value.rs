@@ -2,14 +2,18 @@ use std::io::Cursor;
 
 use serde;
 use serde::de::{Deserializer, IgnoredAny, Visitor};
+use serde::Deserialize;
 
+use de::DecodeScope;
 use error::Error;
 use internal::gob::Message;
 use internal::types::{TypeId, Types, WireType};
+use value::Value;
 
-use crate::{error, internal};
+use crate::{de, error, internal, value};
 
 use super::field_value::FieldValueDeserializer;
+use super::skip::skip_value;
 use super::struct_value::StructValueDeserializer;
 
 pub(crate) struct ValueDeserializer<'t, 'de>
@@ -18,6 +22,8 @@ where
 {
     type_id: TypeId,
     defs: &'t Types,
+    opts: DecodeScope<'t>,
+    projection: Option<&'t [&'t str]>,
     msg: &'t mut Message<Cursor<&'de [u8]>>,
 }
 
@@ -25,12 +31,66 @@ impl<'t, 'de> ValueDeserializer<'t, 'de> {
     pub fn new(
         type_id: TypeId,
         defs: &'t Types,
+        opts: DecodeScope<'t>,
         msg: &'t mut Message<Cursor<&'de [u8]>>,
     ) -> ValueDeserializer<'t, 'de> {
-        ValueDeserializer { type_id, defs, msg }
+        ValueDeserializer {
+            type_id,
+            defs,
+            opts,
+            projection: None,
+            msg,
+        }
+    }
+
+    /// Only decodes the named fields if the value is a struct, see
+    /// `Deserializer::project`.
+    pub fn with_projection(mut self, projection: Option<&'t [&'t str]>) -> Self {
+        self.projection = projection;
+        self
+    }
+
+    // runs the hook registered for the type of a struct value, which is
+    // decoded without going through `FieldValueDeserializer` otherwise
+    fn hook(&mut self) -> Result<Option<Value>, Error> {
+        match self.defs.lookup(self.type_id) {
+            Some(&WireType::Struct(_)) if self.defs.hook(self.type_id).is_some() => {
+                let de = FieldValueDeserializer::new(self.type_id, self.defs, self.opts, self.msg);
+                Value::deserialize(de).map(Some)
+            }
+            _ => Ok(None),
+        }
     }
 }
 
+// numbers, strings and byte slices go through `FieldValueDeserializer`, so
+// that they are converted the same way whether they are sent on their own
+// or as part of a value
+macro_rules! singleton {
+    ($($fname:ident)*) => {
+        $(
+            fn $fname<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, Self::Error> {
+                if let Some(value) = self.hook()? {
+                    return value.$fname(visitor);
+                }
+                // sent without the prefix, as usual for structs
+                if let Some(&WireType::Struct(ref struct_type)) = self.defs.lookup(self.type_id) {
+                    return StructValueDeserializer::new(struct_type, self.defs, self.opts, self.msg)
+                        .with_projection(self.projection)
+                        .$fname(visitor);
+                }
+                if self.msg.read_uint()? != 0 {
+                    return Err(serde::de::Error::custom(
+                        "neither a singleton nor a struct value",
+                    ));
+                }
+                FieldValueDeserializer::new(self.type_id, self.defs, self.opts, self.msg)
+                    .$fname(visitor)
+            }
+        )*
+    };
+}
+
 impl<'t, 'de> Deserializer<'de> for ValueDeserializer<'t, 'de> {
     type Error = Error;
 
@@ -38,8 +98,13 @@ impl<'t, 'de> Deserializer<'de> for ValueDeserializer<'t, 'de> {
     where
         V: Visitor<'de>,
     {
+        if let Some(value) = self.hook()? {
+            return value.deserialize_any(visitor);
+        }
         if let Some(&WireType::Struct(ref struct_type)) = self.defs.lookup(self.type_id) {
-            let de = StructValueDeserializer::new(struct_type, &self.defs, &mut self.msg);
+            let de =
+                StructValueDeserializer::new(struct_type, &self.defs, self.opts, &mut self.msg)
+                    .with_projection(self.projection);
             return de.deserialize_any(visitor);
         }
 
@@ -49,7 +114,7 @@ impl<'t, 'de> Deserializer<'de> for ValueDeserializer<'t, 'de> {
             )));
         }
 
-        let de = FieldValueDeserializer::new(self.type_id, &self.defs, &mut self.msg);
+        let de = FieldValueDeserializer::new(self.type_id, &self.defs, self.opts, &mut self.msg);
         return de.deserialize_any(visitor);
     }
 
@@ -62,8 +127,12 @@ impl<'t, 'de> Deserializer<'de> for ValueDeserializer<'t, 'de> {
     where
         V: Visitor<'de>,
     {
+        if let Some(value) = self.hook()? {
+            return value.deserialize_enum(name, variants, visitor);
+        }
         if let Some(&WireType::Struct(ref struct_type)) = self.defs.lookup(self.type_id) {
-            let de = StructValueDeserializer::new(struct_type, &self.defs, &mut self.msg);
+            let de =
+                StructValueDeserializer::new(struct_type, &self.defs, self.opts, &mut self.msg);
             return de.deserialize_enum(name, variants, visitor);
         }
 
@@ -73,7 +142,7 @@ impl<'t, 'de> Deserializer<'de> for ValueDeserializer<'t, 'de> {
             )));
         }
 
-        let de = FieldValueDeserializer::new(self.type_id, &self.defs, &mut self.msg);
+        let de = FieldValueDeserializer::new(self.type_id, &self.defs, self.opts, &mut self.msg);
         return de.deserialize_enum(name, variants, visitor);
     }
 
@@ -86,8 +155,13 @@ impl<'t, 'de> Deserializer<'de> for ValueDeserializer<'t, 'de> {
     where
         V: Visitor<'de>,
     {
+        if let Some(value) = self.hook()? {
+            return value.deserialize_struct(name, fields, visitor);
+        }
         if let Some(&WireType::Struct(ref struct_type)) = self.defs.lookup(self.type_id) {
-            let de = StructValueDeserializer::new(struct_type, &self.defs, &mut self.msg);
+            let de =
+                StructValueDeserializer::new(struct_type, &self.defs, self.opts, &mut self.msg)
+                    .with_projection(self.projection);
             return de.deserialize_struct(name, fields, visitor);
         }
 
@@ -97,10 +171,53 @@ impl<'t, 'de> Deserializer<'de> for ValueDeserializer<'t, 'de> {
             )));
         }
 
-        let de = FieldValueDeserializer::new(self.type_id, &self.defs, &mut self.msg);
+        let de = FieldValueDeserializer::new(self.type_id, &self.defs, self.opts, &mut self.msg);
         return de.deserialize_struct(name, fields, visitor);
     }
 
+    fn deserialize_tuple<V>(mut self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        if let Some(value) = self.hook()? {
+            return value.deserialize_tuple(len, visitor);
+        }
+        if let Some(&WireType::Struct(ref struct_type)) = self.defs.lookup(self.type_id) {
+            let de = StructValueDeserializer::new(struct_type, self.defs, self.opts, self.msg)
+                .with_projection(self.projection);
+            return de.deserialize_tuple(len, visitor);
+        }
+        if self.msg.read_uint()? != 0 {
+            return Err(serde::de::Error::custom(
+                "neither a singleton nor a struct value",
+            ));
+        }
+
+        FieldValueDeserializer::new(self.type_id, self.defs, self.opts, self.msg)
+            .deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_option<V>(mut self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        if let Some(value) = self.hook()? {
+            return value.deserialize_option(visitor);
+        }
+        if let Some(&WireType::Struct(_)) = self.defs.lookup(self.type_id) {
+            return visitor.visit_some(self);
+        }
+
+        if self.msg.read_uint()? != 0 {
+            return Err(serde::de::Error::custom(
+                "neither a singleton nor a struct value",
+            ));
+        }
+
+        FieldValueDeserializer::new(self.type_id, self.defs, self.opts, self.msg)
+            .deserialize_option(visitor)
+    }
+
     #[inline]
     fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
@@ -110,9 +227,24 @@ impl<'t, 'de> Deserializer<'de> for ValueDeserializer<'t, 'de> {
         visitor.visit_unit()
     }
 
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        skip_value(self.type_id, self.defs, self.msg)?;
+        visitor.visit_unit()
+    }
+
+    singleton! {
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64 deserialize_i128
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_u128
+        deserialize_f32 deserialize_f64
+        deserialize_str deserialize_string deserialize_bytes deserialize_byte_buf
+        deserialize_seq deserialize_char
+    }
+
     forward_to_deserialize_any! {
-        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes
-        byte_buf option unit_struct newtype_struct seq tuple
-        tuple_struct map identifier ignored_any
+        bool unit_struct newtype_struct
+        tuple_struct map identifier
     }
 }
@@ -1,26 +1,44 @@
-use std::io::{self, Cursor, Read};
+use std::io::{self, Read};
 use std::ops::Range;
 
-use byteorder::{BigEndian, ByteOrder};
 use bytes::{Buf, BufMut};
 
+use codec::DecodeError;
 use error::Error;
 use internal::utils::Buffer;
 
+use crate::codec;
 use crate::error;
 use crate::internal;
 
+pub(crate) use codec::MAX_SECTION_LEN;
+
 #[derive(Debug)]
 pub(crate) enum MessageReadError {
-    Incomplete,
+    // how many more bytes are needed, if the input tells; only sections
+    // can be completed by reading on, so this is a parse error unless
+    // section-level parsing asks for it
+    Incomplete(Option<usize>),
     Parse(String),
+    TooLarge(u64),
+}
+
+impl From<DecodeError> for MessageReadError {
+    fn from(err: DecodeError) -> MessageReadError {
+        match err {
+            DecodeError::Incomplete(missing) => MessageReadError::Incomplete(missing),
+            DecodeError::Invalid(reason) => MessageReadError::Parse(reason.into()),
+            DecodeError::TooLarge(len) => MessageReadError::TooLarge(len),
+        }
+    }
 }
 
 impl From<MessageReadError> for Error {
     fn from(err: MessageReadError) -> Error {
         match err {
-            MessageReadError::Incomplete => Error::deserialize("message incomplete"),
+            MessageReadError::Incomplete(_) => Error::deserialize("unexpected end of message"),
             MessageReadError::Parse(reason) => Error::deserialize(reason),
+            MessageReadError::TooLarge(len) => Error::message_too_large(len),
         }
     }
 }
@@ -50,52 +68,100 @@ impl<B> Message<B> {
 impl<B: LinearBuf> Message<B> {
     #[inline]
     pub fn read_uint(&mut self) -> Result<u64, MessageReadError> {
-        if self.buf.remaining() < 1 {
-            return Err(MessageReadError::Incomplete);
+        let (n, len) = decode_uint(self.buf.remaining_bytes())?;
+        self.buf.advance(len);
+        Ok(n)
+    }
+
+    /// Reads `out.len()` uints in one go, advancing past them only once
+    /// all of them are there. Runs of uints under 128, as small numbers and
+    /// bools are sent, are taken eight at a time.
+    pub fn read_uints(&mut self, out: &mut [u64]) -> Result<(), MessageReadError> {
+        let bytes = self.buf.remaining_bytes();
+        let mut pos = 0;
+        let mut chunks = out.chunks_exact_mut(8);
+        for chunk in &mut chunks {
+            if let Some(run) = single_byte_run(&bytes[pos..]) {
+                for (n, &byte) in chunk.iter_mut().zip(run) {
+                    *n = byte as u64;
+                }
+                pos += 8;
+                continue;
+            }
+            for n in chunk.iter_mut() {
+                let (value, len) = decode_uint(&bytes[pos..])?;
+                *n = value;
+                pos += len;
+            }
+        }
+        for n in chunks.into_remainder() {
+            let (value, len) = decode_uint(&bytes[pos..])?;
+            *n = value;
+            pos += len;
         }
-        let u7_or_len = self.buf.get_u8();
-        if u7_or_len < 128 {
-            return Ok(u7_or_len as u64);
+        self.buf.advance(pos);
+        Ok(())
+    }
+
+    /// Advances past `count` uints without decoding them.
+    pub fn skip_uints(&mut self, count: u64) -> Result<(), MessageReadError> {
+        let bytes = self.buf.remaining_bytes();
+        let mut pos = 0;
+        let mut left = count;
+        while left >= 8 {
+            if single_byte_run(&bytes[pos..]).is_some() {
+                pos += 8;
+                left -= 8;
+            } else {
+                pos += codec::uint_len(&bytes[pos..])?;
+                left -= 1;
+            }
         }
-        let len = !u7_or_len + 1;
-        if self.buf.remaining() < len as usize {
-            return Err(MessageReadError::Incomplete);
+        for _ in 0..left {
+            pos += codec::uint_len(&bytes[pos..])?;
         }
-        Ok(self.buf.fast_get_uint_be(len as usize))
+        self.buf.advance(pos);
+        Ok(())
     }
 
     #[inline]
     pub fn read_int(&mut self) -> Result<i64, MessageReadError> {
-        let bits = self.read_uint()?;
-        let sign = bits & 1;
-        let sint = (bits >> 1) as i64;
-        if sign == 0 {
-            Ok(sint)
-        } else {
-            Ok(!sint)
-        }
+        let (n, len) = codec::decode_int(self.buf.remaining_bytes())?;
+        self.buf.advance(len);
+        Ok(n)
     }
 
     #[inline]
     pub fn read_float(&mut self) -> Result<f64, MessageReadError> {
-        let bits = self.read_uint()?;
-        Ok(f64::from_bits(bits.swap_bytes()))
+        let (n, len) = codec::decode_float(self.buf.remaining_bytes())?;
+        self.buf.advance(len);
+        Ok(n)
     }
 
     #[inline]
     pub fn read_bool(&mut self) -> Result<bool, MessageReadError> {
-        match self.read_uint()? {
-            0 => Ok(false),
-            1 => Ok(true),
-            _ => Err(MessageReadError::Parse("integer overflow".into())),
-        }
+        let (b, len) = codec::decode_bool(self.buf.remaining_bytes())?;
+        self.buf.advance(len);
+        Ok(b)
     }
 
     #[inline]
     pub fn read_bytes_len(&mut self) -> Result<usize, MessageReadError> {
         let len = self.read_uint()?;
         if (self.buf.remaining() as u64) < len {
-            return Err(MessageReadError::Incomplete);
+            return Err(MessageReadError::Parse("length exceeds message".into()));
+        }
+        Ok(len as usize)
+    }
+
+    /// Reads the length of the section starting at the current position,
+    /// failing with `Incomplete` unless all of it is there.
+    pub fn read_section_len(&mut self) -> Result<usize, MessageReadError> {
+        let len = self.read_uint()?;
+        let remaining = self.buf.remaining() as u64;
+        if remaining < len {
+            let missing = (len - remaining) as usize;
+            return Err(MessageReadError::Incomplete(Some(missing)));
         }
         Ok(len as usize)
     }
@@ -107,9 +173,8 @@ impl<B: BufMut> Message<B> {
         if n < 128 {
             self.buf.put_u8(n as u8);
         } else {
-            let nbytes = 8 - (n.leading_zeros() / 8) as u8;
-            self.buf.put_u8(!(nbytes - 1));
-            self.buf.put_uint_be(n, nbytes as usize);
+            let mut scratch = [0; 9];
+            self.buf.put_slice(codec::uint_bytes(&mut scratch, n));
         }
     }
 
@@ -145,13 +210,32 @@ impl<B: BufMut> Message<B> {
     }
 }
 
+/// Decodes the uint at the start of `bytes`, returning it along with the
+/// number of bytes it took up.
+#[inline]
+pub(crate) fn decode_uint(bytes: &[u8]) -> Result<(u64, usize), MessageReadError> {
+    Ok(codec::decode_uint(bytes)?)
+}
+
 pub(crate) struct Stream<Io> {
     inner: Io,
+    // where in the input the next read starts
+    position: u64,
 }
 
 impl<Io> Stream<Io> {
     pub fn new(inner: Io) -> Stream<Io> {
-        Stream { inner }
+        Stream::at(inner, 0)
+    }
+
+    /// Reads from `inner`, which was advanced to `position` already.
+    pub fn at(inner: Io, position: u64) -> Stream<Io> {
+        Stream { inner, position }
+    }
+
+    /// Number of bytes read so far, starting from where the stream started.
+    pub fn position(&self) -> u64 {
+        self.position
     }
 
     pub fn get_ref(&self) -> &Io {
@@ -167,59 +251,108 @@ impl<Io> Stream<Io> {
     }
 }
 
+impl<Io: Read> Read for Stream<Io> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
 #[derive(Clone)]
 pub(crate) struct SectionHeader {
     pub(crate) type_id: i64,
     pub(crate) payload_range: Range<usize>,
 }
 
-impl<Io: Read> Stream<Io> {
-    fn parse_section(&mut self, bytes: &[u8]) -> Result<SectionHeader, MessageReadError> {
-        let mut msg = Message::new(Cursor::new(bytes));
-        //
-        //  <---> message offset
-        //        <--------------------> message length
-        // [ len | type id | payload... ]
-        //  <-------------> payload offset
-        //                  <----------> payload length
-        //
-        let msg_length = msg.read_uint()? as usize;
-        let msg_offset = msg.get_ref().position() as usize;
-        if bytes.len() < msg_offset + msg_length {
-            return Err(MessageReadError::Incomplete);
-        }
-        let type_id = msg.read_int()?;
-        let payload_offset = msg.get_ref().position() as usize;
-        let payload_length = msg_length as usize - (payload_offset - msg_offset);
-        Ok(SectionHeader {
-            type_id,
-            payload_range: Range {
-                start: payload_offset,
-                end: payload_offset + payload_length,
-            },
-        })
+/// Where a section starts and ends, known before all of its payload is in.
+#[derive(Clone)]
+pub(crate) struct SectionStart {
+    pub(crate) type_id: i64,
+    pub(crate) payload_offset: usize,
+    pub(crate) end: usize,
+}
+
+/// Parses the length and type id of the section at the start of `bytes`,
+/// however much of its payload follows.
+pub(crate) fn parse_section_start(bytes: &[u8]) -> Result<SectionStart, MessageReadError> {
+    let (type_id, payload_offset, end) = codec::section_bounds(bytes)?;
+    Ok(SectionStart {
+        type_id,
+        payload_offset,
+        end,
+    })
+}
+
+/// Parses only the length of the section at the start of `bytes`, failing
+/// with `Incomplete` unless the whole section is there.
+fn parse_section_end(bytes: &[u8]) -> Result<usize, MessageReadError> {
+    let (msg_length, msg_offset) = codec::decode_uint(bytes)?;
+    if msg_length >= MAX_SECTION_LEN {
+        return Err(MessageReadError::TooLarge(msg_length));
+    }
+    let end = msg_offset + msg_length as usize;
+    if bytes.len() < end {
+        return Err(MessageReadError::Incomplete(Some(end - bytes.len())));
     }
+    Ok(end)
+}
 
+/// Parses the header of the section at the start of `bytes`, failing with
+/// `Incomplete` unless the whole section is there.
+pub(crate) fn parse_section(bytes: &[u8]) -> Result<SectionHeader, MessageReadError> {
+    let start = parse_section_start(bytes)?;
+    if bytes.len() < start.end {
+        return Err(MessageReadError::Incomplete(Some(start.end - bytes.len())));
+    }
+    Ok(SectionHeader {
+        type_id: start.type_id,
+        payload_range: Range {
+            start: start.payload_offset,
+            end: start.end,
+        },
+    })
+}
+
+impl<Io: Read> Stream<Io> {
     pub fn read_section(&mut self, buf: &mut Buffer) -> Result<Option<SectionHeader>, Error> {
+        self.read_until(buf, parse_section)
+    }
+
+    /// Like `read_section`, but only reads up to the section's type id.
+    pub fn read_section_start(&mut self, buf: &mut Buffer) -> Result<Option<SectionStart>, Error> {
+        self.read_until(buf, parse_section_start)
+    }
+
+    /// Reads all of the section at the start of `buf` by its length alone,
+    /// returning where it ends.
+    pub fn read_section_end(&mut self, buf: &mut Buffer) -> Result<Option<usize>, Error> {
+        self.read_until(buf, parse_section_end)
+    }
+
+    fn read_until<T, F>(&mut self, buf: &mut Buffer, parse: F) -> Result<Option<T>, Error>
+    where
+        F: Fn(&[u8]) -> Result<T, MessageReadError>,
+    {
         if buf.len() == 0 {
-            let n = buf.read_from(&mut self.inner)?;
+            let n = buf.read_from(self)?;
             if n == 0 {
                 return Ok(None);
             }
         }
         loop {
-            match self.parse_section(buf.bytes()) {
-                Ok(header) => {
-                    return Ok(Some(header));
+            match parse(buf.bytes()) {
+                Ok(parsed) => {
+                    return Ok(Some(parsed));
                 }
-                Err(MessageReadError::Incomplete) => {
-                    let n = buf.read_from(&mut self.inner)?;
+                Err(MessageReadError::Incomplete(_)) => {
+                    let n = buf.read_from(self)?;
                     if n == 0 {
                         return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
                     }
                 }
-                Err(MessageReadError::Parse(reason)) => {
-                    return Err(Error::deserialize(reason));
+                Err(err) => {
+                    return Err(err.into());
                 }
             }
         }
@@ -230,15 +363,6 @@ impl<Io: Read> Stream<Io> {
 /// to _all_ remaining bytes.
 pub trait LinearBuf: Buf {
     fn remaining_bytes(&self) -> &[u8];
-
-    fn fast_get_uint_be(&mut self, nbytes: usize) -> u64 {
-        let ret = {
-            let src = &self.remaining_bytes()[..nbytes];
-            BigEndian::read_uint(src, nbytes)
-        };
-        self.advance(nbytes);
-        ret
-    }
 }
 
 impl<T: AsRef<[u8]>> LinearBuf for io::Cursor<T> {
@@ -246,3 +370,81 @@ impl<T: AsRef<[u8]>> LinearBuf for io::Cursor<T> {
         &self.get_ref().as_ref()[self.position() as usize..]
     }
 }
+
+// The first eight bytes of `bytes`, if each of them is a uint on its own,
+// told by their top bits all being clear.
+#[inline]
+fn single_byte_run(bytes: &[u8]) -> Option<&[u8]> {
+    let run = bytes.get(..8)?;
+    let mut word = [0; 8];
+    word.copy_from_slice(run);
+    if u64::from_le_bytes(word) & 0x8080_8080_8080_8080 == 0 {
+        Some(run)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::{Message, MessageReadError};
+
+    #[test]
+    fn batch_of_uints() {
+        let mut msg = Message::new(Vec::new());
+        for &n in &[0, 127, 128, 1 << 40, u64::MAX] {
+            msg.write_uint(n);
+        }
+        let bytes = msg.into_inner();
+
+        let mut msg = Message::new(Cursor::new(&bytes[..]));
+        let mut out = [0; 5];
+        msg.read_uints(&mut out).unwrap();
+        assert_eq!(out, [0, 127, 128, 1 << 40, u64::MAX]);
+        assert_eq!(msg.get_ref().position() as usize, bytes.len());
+
+        let mut msg = Message::new(Cursor::new(&bytes[..]));
+        msg.skip_uints(4).unwrap();
+        assert_eq!(msg.read_uint().unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn incomplete_batch_does_not_advance() {
+        let mut msg = Message::new(Vec::new());
+        msg.write_uint(1);
+        msg.write_uint(1 << 40);
+        let mut bytes = msg.into_inner();
+        bytes.pop();
+
+        let mut msg = Message::new(Cursor::new(&bytes[..]));
+        let mut out = [0; 2];
+        match msg.read_uints(&mut out) {
+            Err(MessageReadError::Incomplete(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(msg.skip_uints(2).is_err());
+        assert_eq!(msg.get_ref().position(), 0);
+    }
+
+    #[test]
+    fn batch_of_uints_with_runs() {
+        let values: Vec<u64> = (0..20).map(|n| if n == 11 { 300 } else { n }).collect();
+        let mut msg = Message::new(Vec::new());
+        for &n in &values {
+            msg.write_uint(n);
+        }
+        let bytes = msg.into_inner();
+
+        let mut msg = Message::new(Cursor::new(&bytes[..]));
+        let mut out = [0; 20];
+        msg.read_uints(&mut out).unwrap();
+        assert_eq!(&out[..], &values[..]);
+        assert_eq!(msg.get_ref().position() as usize, bytes.len());
+
+        let mut msg = Message::new(Cursor::new(&bytes[..]));
+        msg.skip_uints(19).unwrap();
+        assert_eq!(msg.read_uint().unwrap(), 19);
+    }
+}
@@ -1,10 +1,13 @@
 use std::borrow::{Borrow, BorrowMut};
 
+use bytes::Bytes;
 use serde::ser::{self, Impossible};
 use serde::Serialize;
+use serde_schema::types::Type;
 
 use crate::internal;
 use crate::ser as other_ser;
+use crate::shared_bytes;
 
 use internal::gob::Message;
 use internal::types::TypeId;
@@ -12,7 +15,8 @@ use internal::types::TypeId;
 use error::Error;
 use other_ser::Output;
 use other_ser::OutputPart;
-use schema::Schema;
+use other_ser::{FieldOptions, FloatOptions, MapOrder};
+use schema::{external_encoding, OptionEncoding, Schema};
 
 mod serialize_struct;
 use crate::{error, schema};
@@ -27,7 +31,7 @@ pub(crate) use self::serialize_map::SerializeMapValue;
 mod serialize_variant;
 pub(crate) use self::serialize_variant::{SerializeStructVariantValue, SerializeVariantValue};
 mod serialize_empty;
-pub(crate) use self::serialize_empty::SerializeEmptyValue;
+pub(crate) use self::serialize_empty::{encode_empty_value, SerializeEmptyValue};
 pub(crate) mod serialize_wire_types;
 
 pub(crate) struct SerializationOk<S> {
@@ -38,6 +42,12 @@ pub(crate) struct SerializationOk<S> {
 pub(crate) struct SerializationCtx<S> {
     pub schema: S,
     pub value: Message<Vec<u8>>,
+    // byte slices written without copying them, each following the first
+    // `usize` bytes of `value`
+    pub shared: Vec<(usize, Bytes)>,
+    pub floats: FloatOptions,
+    pub fields: FieldOptions,
+    pub maps: MapOrder,
 }
 
 impl<S> SerializationCtx<S> {
@@ -45,6 +55,10 @@ impl<S> SerializationCtx<S> {
         SerializationCtx {
             schema,
             value: Message::new(Vec::new()),
+            shared: Vec::new(),
+            floats: FloatOptions::default(),
+            fields: FieldOptions::default(),
+            maps: MapOrder::default(),
         }
     }
 
@@ -53,36 +67,79 @@ impl<S> SerializationCtx<S> {
         S: Borrow<Schema>,
         F: FnOnce(SerializationCtx<&Schema>) -> Result<SerializationOk<&Schema>, E>,
     {
-        let (is_empty, msg) = {
+        let (is_empty, msg, shared) = {
             let buf = ::std::mem::replace(self.value.get_mut(), Vec::new());
             let msg = Message::new(buf);
             let ctx = SerializationCtx {
                 schema: self.schema.borrow(),
                 value: msg,
+                shared: ::std::mem::take(&mut self.shared),
+                floats: self.floats,
+                fields: self.fields,
+                maps: self.maps,
             };
             let ok = f(ctx)?;
-            (ok.is_empty, ok.ctx.value)
+            (ok.is_empty, ok.ctx.value, ok.ctx.shared)
         };
         self.value = msg;
+        self.shared = shared;
         Ok(is_empty)
     }
 
+    // byte slices are only shared when nobody looks at the encoded bytes
+    fn shares_bytes(&self) -> bool {
+        !self.maps.is_sorted() && !self.fields.has_hook()
+    }
+
+    /// Drops everything written after the first `len` bytes.
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.value.get_mut().truncate(len);
+        self.shared.retain(|&(at, _)| at <= len);
+    }
+
+    /// Copies the shared byte slices into the buffer.
+    pub(crate) fn unshare(&mut self) {
+        if self.shared.is_empty() {
+            return;
+        }
+        let buf = ::std::mem::take(self.value.get_mut());
+        let mut unshared = Vec::with_capacity(
+            buf.len()
+                + self
+                    .shared
+                    .iter()
+                    .map(|(_, bytes)| bytes.len())
+                    .sum::<usize>(),
+        );
+        let mut last = 0;
+        for (at, bytes) in self.shared.drain(..) {
+            unshared.extend_from_slice(&buf[last..at]);
+            unshared.extend_from_slice(&bytes);
+            last = at;
+        }
+        unshared.extend_from_slice(&buf[last..]);
+        *self.value.get_mut() = unshared;
+    }
+
     pub(crate) fn flush<O: Output>(&mut self, mut out: O) -> Result<(), Error>
     where
         S: BorrowMut<Schema>,
     {
         self.schema.borrow_mut().write_pending(&mut out)?;
         let buffer = ::std::mem::replace(self.value.get_mut(), Vec::new());
-        out.serialize_part(OutputPart::new(buffer))
+        let shared = ::std::mem::take(&mut self.shared);
+        out.serialize_part(OutputPart::try_with_shared(buffer, shared)?)
     }
 }
 
-pub(crate) struct FieldValueSerializer<S> {
+pub(crate) struct FieldValueSerializer<'z, S> {
     pub ctx: SerializationCtx<S>,
     pub type_id: TypeId,
+    // the encoding of the zero value of the type, if planned ahead
+    pub zero: Option<&'z [u8]>,
 }
 
-impl<S> FieldValueSerializer<S> {
+impl<S> FieldValueSerializer<'_, S> {
     fn check_type(&self, got: TypeId) -> Result<(), Error> {
         if self.type_id != got {
             Err(ser::Error::custom(format!(
@@ -95,7 +152,7 @@ impl<S> FieldValueSerializer<S> {
     }
 }
 
-impl<S> ser::Serializer for FieldValueSerializer<S>
+impl<S> ser::Serializer for FieldValueSerializer<'_, S>
 where
     S: Borrow<Schema>,
 {
@@ -141,6 +198,18 @@ where
         })
     }
 
+    // gob has no integers wider than 64 bits, so only values that fit
+    // into one can be sent
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
+        match i64::try_from(v) {
+            Ok(v) => self.serialize_i64(v),
+            Err(_) => Err(ser::Error::custom(format!(
+                "integer overflow: {} does not fit into a gob int",
+                v
+            ))),
+        }
+    }
+
     fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
         self.serialize_u64(v as u64)
     }
@@ -162,12 +231,24 @@ where
         })
     }
 
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok, Self::Error> {
+        match u64::try_from(v) {
+            Ok(v) => self.serialize_u64(v),
+            Err(_) => Err(ser::Error::custom(format!(
+                "integer overflow: {} does not fit into a gob uint",
+                v
+            ))),
+        }
+    }
+
+    // widening is exact, so the value decodes back into the same `f32`
     fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
-        self.serialize_f64(v as f64)
+        self.serialize_f64(f64::from(v))
     }
 
     fn serialize_f64(mut self, v: f64) -> Result<Self::Ok, Self::Error> {
         self.check_type(TypeId::FLOAT)?;
+        let v = self.ctx.floats.apply(v).map_err(Error::serialize)?;
         self.ctx.value.write_float(v);
         Ok(SerializationOk {
             ctx: self.ctx,
@@ -188,31 +269,73 @@ where
         })
     }
 
+    // buffers registered as sequences of `u8`, e.g. a `Vec<u8>` serialized
+    // through `serde_bytes`, have to be sent element by element
     fn serialize_bytes(mut self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        if self.type_id != TypeId::BYTES {
+            let is_byte_seq = match self.ctx.schema.borrow().lookup(self.type_id) {
+                Some(ty) => match *ty {
+                    Type::Seq(ref seq_type) => *seq_type.element_type() == TypeId::UINT,
+                    _ => false,
+                },
+                None => false,
+            };
+            if is_byte_seq {
+                let mut seq = self.serialize_seq(Some(v.len()))?;
+                for byte in v {
+                    ser::SerializeSeq::serialize_element(&mut seq, byte)?;
+                }
+                return ser::SerializeSeq::end(seq);
+            }
+        }
         self.check_type(TypeId::BYTES)?;
-        self.ctx.value.write_bytes(v);
+        match shared_bytes::take_outgoing(v) {
+            Some(bytes) if self.ctx.shares_bytes() && !bytes.is_empty() => {
+                self.ctx.value.write_uint(bytes.len() as u64);
+                let at = self.ctx.value.get_ref().len();
+                self.ctx.shared.push((at, bytes));
+            }
+            _ => self.ctx.value.write_bytes(v),
+        }
         Ok(SerializationOk {
             ctx: self.ctx,
             is_empty: v.len() == 0,
         })
     }
 
-    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+    fn serialize_none(mut self) -> Result<Self::Ok, Self::Error> {
+        if let Some(zero) = self.zero {
+            self.ctx.value.get_mut().extend_from_slice(zero);
+            return Ok(SerializationOk {
+                ctx: self.ctx,
+                is_empty: true,
+            });
+        }
         let value = {
             let ser = FieldValueSerializer {
                 ctx: SerializationCtx {
                     schema: self.ctx.schema.borrow(),
                     value: self.ctx.value,
+                    shared: self.ctx.shared,
+                    floats: self.ctx.floats,
+                    fields: self.ctx.fields,
+                    maps: self.ctx.maps,
                 },
                 type_id: self.type_id,
+                zero: None,
             };
             let value = SerializeEmptyValue::new(self.ctx.schema.borrow(), self.type_id);
-            value.serialize(ser)?.ctx.value
+            let ctx = value.serialize(ser)?.ctx;
+            (ctx.value, ctx.shared)
         };
         Ok(SerializationOk {
             ctx: SerializationCtx {
                 schema: self.ctx.schema,
-                value,
+                value: value.0,
+                shared: value.1,
+                floats: self.ctx.floats,
+                fields: self.ctx.fields,
+                maps: self.ctx.maps,
             },
             is_empty: true,
         })
@@ -222,7 +345,14 @@ where
     where
         T: Serialize,
     {
-        value.serialize(self)
+        match self.ctx.schema.borrow().option_encoding() {
+            OptionEncoding::Pointer => value.serialize(self),
+            OptionEncoding::Slice => {
+                let mut seq = ser::Serializer::serialize_seq(self, Some(1))?;
+                ser::SerializeSeq::serialize_element(&mut seq, value)?;
+                ser::SerializeSeq::end(seq)
+            }
+        }
     }
 
     fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
@@ -236,21 +366,35 @@ where
     fn serialize_unit_variant(
         self,
         _name: &'static str,
-        _variant_index: u32,
+        variant_index: u32,
         _variant: &'static str,
     ) -> Result<Self::Ok, Self::Error> {
-        Err(ser::Error::custom("not implemented yet"))
+        let ser = SerializeVariantValue::new(self.ctx, self.type_id, variant_index)?;
+        ser.serialize_unit()
     }
 
+    // types encoding themselves hand over their encoding as bytes, which
+    // are sent as they are
     fn serialize_newtype_struct<T: ?Sized>(
         self,
         _name: &'static str,
-        _value: &T,
+        value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
         T: Serialize,
     {
-        Err(ser::Error::custom("not implemented yet"))
+        let encodes_itself = match self.ctx.schema.borrow().lookup(self.type_id) {
+            Some(ty) => external_encoding(&ty).is_some(),
+            None => false,
+        };
+        if !encodes_itself {
+            return Err(ser::Error::custom("not implemented yet"));
+        }
+        value.serialize(FieldValueSerializer {
+            ctx: self.ctx,
+            type_id: TypeId::BYTES,
+            zero: None,
+        })
     }
 
     fn serialize_newtype_variant<T: ?Sized>(
@@ -3,9 +3,13 @@ use std::borrow::Borrow;
 use serde::{self, Serialize, Serializer};
 use serde_schema::types::Type;
 
+use error::Error;
+use schema::external_encoding;
 use ser::{Schema, TypeId};
 
-use crate::ser;
+use crate::{error, schema, ser};
+
+use super::{FieldValueSerializer, SerializationCtx};
 
 pub struct SerializeEmptyValue<S> {
     schema: S,
@@ -27,6 +31,11 @@ impl<S: Borrow<Schema>> SerializeEmptyValue<S> {
                 SerializeEmptyValue::new(self.schema.borrow(), *option_type.inner_type())
                     .serialize(ser)
             }
+            // types encoding themselves are sent as byte slices
+            _ if external_encoding(ty).is_some() => {
+                let value = SerializeEmptyValue::new(self.schema.borrow(), TypeId::BYTES);
+                ser.serialize_newtype_struct("", &value)
+            }
             &Type::NewtypeStruct(ref newtype_struct_type) => {
                 let value = SerializeEmptyValue::new(
                     self.schema.borrow(),
@@ -96,3 +105,14 @@ impl<S: Borrow<Schema>> Serialize for SerializeEmptyValue<S> {
         }
     }
 }
+
+// the encoding of the zero value of `type_id`, as written for `None`
+pub(crate) fn encode_empty_value(schema: &Schema, type_id: TypeId) -> Result<Vec<u8>, Error> {
+    let ser = FieldValueSerializer {
+        ctx: SerializationCtx::with_schema(schema),
+        type_id,
+        zero: None,
+    };
+    let ok = SerializeEmptyValue::new(schema, type_id).serialize(ser)?;
+    Ok(ok.ctx.value.into_inner())
+}
@@ -1,13 +1,17 @@
 use std::borrow::Borrow;
+use std::str;
 
+use owning_ref::OwningRef;
 use serde::ser::{self, Serialize};
-use serde_schema::types::Type;
+use serde_schema::types::{StructField, Type};
 
 use error::Error;
+use internal::gob::decode_uint;
 use internal::types::TypeId;
-use schema::Schema;
+use other_ser::{Field, MapKey};
+use schema::{Schema, SchemaType};
 
-use crate::{error, internal, schema};
+use crate::{error, internal, schema, ser as other_ser};
 
 use super::{FieldValueSerializer, SerializationCtx, SerializationOk};
 
@@ -17,6 +21,22 @@ pub(crate) struct SerializeMapValue<S> {
     len: usize,
     key: TypeId,
     value: TypeId,
+    // where each entry starts and its key ends, if entries are reordered
+    entries: Vec<(usize, usize)>,
+    // set if the map is written as a struct
+    fields: Option<StructFields>,
+}
+
+// a map written as a struct, keyed by field name, like serde writes
+// structs with `#[serde(flatten)]` fields
+struct StructFields {
+    fields: OwningRef<SchemaType, [StructField<TypeId>]>,
+    // where the struct starts
+    start: usize,
+    // the field the next value belongs to
+    field_idx: usize,
+    // the number of each field written, and where its value starts and ends
+    written: Vec<(usize, usize, usize)>,
 }
 
 impl<S: Borrow<Schema>> SerializeMapValue<S> {
@@ -26,7 +46,9 @@ impl<S: Borrow<Schema>> SerializeMapValue<S> {
         type_id: TypeId,
     ) -> Result<Self, Error> {
         let (len, key, value) = if let Some(schema_type) = ctx.schema.borrow().lookup(type_id) {
-            if let &Type::Map(ref map_type) = &*schema_type {
+            if let Type::Struct(_) = *schema_type {
+                return SerializeMapValue::new_struct(ctx, schema_type, type_id);
+            } else if let &Type::Map(ref map_type) = &*schema_type {
                 if let Some(len) = ser_len {
                     (len, *map_type.key_type(), *map_type.value_type())
                 } else {
@@ -47,8 +69,166 @@ impl<S: Borrow<Schema>> SerializeMapValue<S> {
             len,
             key,
             value,
+            entries: Vec::new(),
+            fields: None,
         })
     }
+
+    fn new_struct(
+        mut ctx: SerializationCtx<S>,
+        schema_type: SchemaType,
+        type_id: TypeId,
+    ) -> Result<Self, Error> {
+        if ctx.schema.borrow().layout(type_id).is_some() {
+            return Err(ser::Error::custom(
+                "maps cannot be written as structs embedding others",
+            ));
+        }
+        let fields = OwningRef::new(schema_type).map(|typ| match *typ {
+            Type::Struct(ref struct_type) => struct_type.fields(),
+            _ => unreachable!(),
+        });
+        // fields are reordered once all are written, which needs the
+        // positions in the buffer to stay put
+        ctx.unshare();
+        let start = ctx.value.get_ref().len();
+        Ok(SerializeMapValue {
+            needs_init: false,
+            ctx,
+            len: 0,
+            key: TypeId::STRING,
+            value: TypeId::STRING,
+            entries: Vec::new(),
+            fields: Some(StructFields {
+                fields,
+                start,
+                field_idx: 0,
+                written: Vec::new(),
+            }),
+        })
+    }
+
+    // looks up the field named by `key`, which is written to the buffer
+    // and read back, as there is no other way to get at it
+    fn struct_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Error> {
+        let start = self.ctx.value.get_ref().len();
+        self.ctx.with_borrow(|ctx| {
+            let de = FieldValueSerializer {
+                ctx,
+                type_id: TypeId::STRING,
+                zero: None,
+            };
+            key.serialize(de)
+        })?;
+        let field_idx = {
+            let encoded = &self.ctx.value.get_ref()[start..];
+            let (len, len_len) = decode_uint(encoded)?;
+            let name = str::from_utf8(&encoded[len_len..len_len + len as usize])
+                .map_err(|_| ser::Error::custom("field name is not valid UTF-8"))?;
+            let fields = &self.fields.as_ref().unwrap().fields;
+            match fields.iter().position(|field| field.name() == name) {
+                Some(field_idx) => field_idx,
+                None => {
+                    return Err(ser::Error::custom(format!("no field named {}", name)));
+                }
+            }
+        };
+        self.ctx.truncate(start);
+        self.fields.as_mut().unwrap().field_idx = field_idx;
+        Ok(())
+    }
+
+    fn struct_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        let (field_idx, type_id) = {
+            let fields = self.fields.as_ref().unwrap();
+            let field_idx = fields.field_idx;
+            (field_idx, *fields.fields[field_idx].field_type())
+        };
+        let start = self.ctx.value.get_ref().len();
+        let is_zero = self.ctx.with_borrow(|ctx| {
+            let de = FieldValueSerializer {
+                ctx,
+                type_id,
+                zero: None,
+            };
+            value.serialize(de)
+        })?;
+        self.ctx.unshare();
+        let end = self.ctx.value.get_ref().len();
+
+        let fields = self.fields.as_mut().unwrap();
+        let field = Field {
+            name: fields.fields[field_idx].name(),
+            type_id,
+            value: &self.ctx.value.get_ref()[start..],
+            is_zero,
+        };
+        if self.ctx.fields.omit(&field) {
+            self.ctx.truncate(start);
+        } else {
+            fields.written.push((field_idx, start, end));
+        }
+        Ok(())
+    }
+
+    // writes the fields in the order of their numbers, as gob requires
+    fn end_struct(&mut self) -> Result<(), Error> {
+        let mut fields = self.fields.take().unwrap();
+        fields.written.sort_by_key(|&(field_idx, _, _)| field_idx);
+        let tail = self.ctx.value.get_mut().split_off(fields.start);
+        let mut last = -1;
+        for (field_idx, start, end) in fields.written {
+            if field_idx as i64 == last {
+                return Err(ser::Error::custom(format!(
+                    "field {} written twice",
+                    fields.fields[field_idx].name()
+                )));
+            }
+            self.ctx.value.write_uint((field_idx as i64 - last) as u64);
+            self.ctx
+                .value
+                .get_mut()
+                .extend_from_slice(&tail[start - fields.start..end - fields.start]);
+            last = field_idx as i64;
+        }
+        self.ctx.value.write_uint(0);
+        Ok(())
+    }
+
+    fn sort_entries(&mut self) {
+        let start = match self.entries.first() {
+            Some(&(start, _)) => start,
+            None => return,
+        };
+        let buf = self.ctx.value.get_mut();
+        let tail = buf.split_off(start);
+        let mut entries = Vec::with_capacity(self.entries.len());
+        for (i, &(entry_start, key_end)) in self.entries.iter().enumerate() {
+            let entry_end = match self.entries.get(i + 1) {
+                Some(&(next, _)) => next,
+                None => start + tail.len(),
+            };
+            entries.push((
+                &tail[entry_start - start..key_end - start],
+                &tail[entry_start - start..entry_end - start],
+            ));
+        }
+        let (key, order) = (self.key, self.ctx.maps);
+        entries.sort_by(|a, b| {
+            let a = MapKey {
+                type_id: key,
+                value: a.0,
+            };
+            let b = MapKey {
+                type_id: key,
+                value: b.0,
+            };
+            order.compare(&a, &b)
+        });
+        for (_, entry) in entries {
+            buf.extend_from_slice(entry);
+        }
+    }
 }
 
 impl<S: Borrow<Schema>> ser::SerializeMap for SerializeMapValue<S> {
@@ -59,15 +239,26 @@ impl<S: Borrow<Schema>> ser::SerializeMap for SerializeMapValue<S> {
     where
         T: Serialize,
     {
+        if self.fields.is_some() {
+            return self.struct_key(key);
+        }
         if self.needs_init {
             self.ctx.value.write_uint(self.len as u64);
             self.needs_init = false;
         }
         let type_id = self.key;
+        let start = self.ctx.value.get_ref().len();
         self.ctx.with_borrow(|ctx| {
-            let de = FieldValueSerializer { ctx, type_id };
+            let de = FieldValueSerializer {
+                ctx,
+                type_id,
+                zero: None,
+            };
             key.serialize(de)
         })?;
+        if self.ctx.maps.is_sorted() {
+            self.entries.push((start, self.ctx.value.get_ref().len()));
+        }
         Ok(())
     }
 
@@ -75,20 +266,35 @@ impl<S: Borrow<Schema>> ser::SerializeMap for SerializeMapValue<S> {
     where
         T: Serialize,
     {
+        if self.fields.is_some() {
+            return self.struct_value(value);
+        }
         let type_id = self.value;
         self.ctx.with_borrow(|ctx| {
-            let de = FieldValueSerializer { ctx, type_id };
+            let de = FieldValueSerializer {
+                ctx,
+                type_id,
+                zero: None,
+            };
             value.serialize(de)
         })?;
         Ok(())
     }
 
     fn end(mut self) -> Result<Self::Ok, Self::Error> {
+        if self.fields.is_some() {
+            self.end_struct()?;
+            return Ok(SerializationOk {
+                ctx: self.ctx,
+                is_empty: false,
+            });
+        }
         let is_empty = self.len == 0;
 
         if is_empty {
             self.ctx.value.write_uint(0);
         }
+        self.sort_entries();
 
         Ok(SerializationOk {
             ctx: self.ctx,
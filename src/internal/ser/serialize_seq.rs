@@ -15,6 +15,7 @@ pub(crate) struct SerializeSeqValue<S> {
     needs_init: bool,
     ctx: SerializationCtx<S>,
     len: usize,
+    count: usize,
     elem: TypeId,
 }
 
@@ -26,6 +27,14 @@ impl<S: Borrow<Schema>> SerializeSeqValue<S> {
     ) -> Result<Self, Error> {
         let (len, elem) = if let Some(schema_type) = ctx.schema.borrow().lookup(type_id) {
             if let &Type::Seq(ref seq_type) = &*schema_type {
+                if let (Some(len), Some(ser_len)) = (seq_type.len(), ser_len) {
+                    if len != ser_len {
+                        return Err(ser::Error::custom(format!(
+                            "array len mismatch (expected {}, got {})",
+                            len, ser_len
+                        )));
+                    }
+                }
                 if let Some(len) = seq_type.len().or(ser_len) {
                     (len, *seq_type.element_type())
                 } else {
@@ -44,6 +53,7 @@ impl<S: Borrow<Schema>> SerializeSeqValue<S> {
             needs_init: true,
             ctx,
             len,
+            count: 0,
             elem,
         })
     }
@@ -63,13 +73,24 @@ impl<S: Borrow<Schema>> ser::SerializeSeq for SerializeSeqValue<S> {
         }
         let type_id = self.elem;
         self.ctx.with_borrow(|ctx| {
-            let de = FieldValueSerializer { ctx, type_id };
+            let de = FieldValueSerializer {
+                ctx,
+                type_id,
+                zero: None,
+            };
             value.serialize(de)
         })?;
+        self.count += 1;
         Ok(())
     }
 
     fn end(mut self) -> Result<Self::Ok, Self::Error> {
+        if self.count != self.len {
+            return Err(ser::Error::custom(format!(
+                "sequence len mismatch (expected {}, got {})",
+                self.len, self.count
+            )));
+        }
         let is_empty = self.len == 0;
 
         if is_empty {
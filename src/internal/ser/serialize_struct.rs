@@ -1,50 +1,103 @@
 use std::borrow::Borrow;
+use std::sync::Arc;
 
-use owning_ref::OwningRef;
 use serde::ser::{self, Serialize};
-use serde_schema::types::{StructField, Type};
 
 use error::Error;
 use internal::types::TypeId;
-use schema::{Schema, SchemaType};
+use other_ser::Field;
+use schema::{FieldPlan, FieldSlot, Schema};
 
-use crate::{error, internal, schema};
+use crate::{error, internal, schema, ser as other_ser};
 
 use super::{FieldValueSerializer, SerializationCtx, SerializationOk};
 
 pub(crate) struct SerializeStructValue<S> {
     ctx: SerializationCtx<S>,
-    fields: OwningRef<SchemaType, [StructField<TypeId>]>,
+    // the type and place of each field, looked up once per struct type
+    plan: Arc<[FieldPlan]>,
     current_field_idx: usize,
     last_serialized_field_idx: i64,
+    // the field number of the embedded struct being written, and the last
+    // of its fields written so far
+    embedded: Option<(usize, i64)>,
 }
 
 impl<S: Borrow<Schema>> SerializeStructValue<S> {
     pub(crate) fn new(ctx: SerializationCtx<S>, type_id: TypeId) -> Result<Self, Error> {
-        let fields;
-        if let Some(schema_type) = ctx.schema.borrow().lookup(type_id) {
-            fields = OwningRef::new(schema_type).try_map::<_, _, Error>(|typ| {
-                if let &Type::Struct(ref struct_type) = typ {
-                    Ok(struct_type.fields())
-                } else {
-                    Err(ser::Error::custom("schema mismatch, not a struct"))
-                }
-            })?;
-        } else {
-            return Err(ser::Error::custom("type not found"));
-        }
-        Ok(SerializeStructValue::from_parts(ctx, fields))
+        let plan = match ctx.schema.borrow().struct_plan(type_id) {
+            Some(plan) => plan,
+            None if ctx.schema.borrow().lookup(type_id).is_some() => {
+                return Err(ser::Error::custom("schema mismatch, not a struct"))
+            }
+            None => return Err(ser::Error::custom("type not found")),
+        };
+        Ok(SerializeStructValue::from_parts(ctx, plan))
     }
 
-    pub(crate) fn from_parts(
-        ctx: SerializationCtx<S>,
-        fields: OwningRef<SchemaType, [StructField<TypeId>]>,
-    ) -> Self {
+    pub(crate) fn from_parts(ctx: SerializationCtx<S>, plan: Arc<[FieldPlan]>) -> Self {
         SerializeStructValue {
             ctx,
-            fields,
+            plan,
             current_field_idx: 0,
             last_serialized_field_idx: -1,
+            embedded: None,
+        }
+    }
+
+    fn end_embedded(&mut self) {
+        if self.embedded.take().is_some() {
+            self.ctx.value.write_uint(0);
+        }
+    }
+
+    // writes the delta leading to the current field, entering or leaving
+    // embedded structs on the way, and returns where the delta starts
+    fn write_delta(&mut self, slot: FieldSlot) -> usize {
+        let (field_idx, last) = match slot {
+            FieldSlot::Direct(field_idx) => {
+                self.end_embedded();
+                (field_idx, self.last_serialized_field_idx)
+            }
+            FieldSlot::Embedded(outer_idx, field_idx) => match self.embedded {
+                Some((open, last)) if open == outer_idx => (field_idx, last),
+                _ => {
+                    self.end_embedded();
+                    let delta = outer_idx as i64 - self.last_serialized_field_idx;
+                    self.ctx.value.write_uint(delta as u64);
+                    self.last_serialized_field_idx = outer_idx as i64;
+                    self.embedded = Some((outer_idx, -1));
+                    (field_idx, -1)
+                }
+            },
+        };
+        let pos = self.ctx.value.get_ref().len();
+        self.ctx.value.write_uint((field_idx as i64 - last) as u64);
+        pos
+    }
+
+    // the slot of the current field, named `key`, failing if the value
+    // has more fields than the schema
+    fn slot(&self, key: &str) -> Result<FieldSlot, Error> {
+        match self.plan.get(self.current_field_idx) {
+            Some(plan) => Ok(plan.slot),
+            None => Err(ser::Error::custom(format!(
+                "schema mismatch, struct has {} fields, found field {:?} after them",
+                self.plan.len(),
+                key
+            ))),
+        }
+    }
+
+    // marks the field in `slot` as written
+    fn written(&mut self, slot: FieldSlot) {
+        match slot {
+            FieldSlot::Direct(field_idx) => self.last_serialized_field_idx = field_idx as i64,
+            FieldSlot::Embedded(_, field_idx) => {
+                if let Some((_, ref mut last)) = self.embedded {
+                    *last = field_idx as i64;
+                }
+            }
         }
     }
 }
@@ -55,27 +108,39 @@ impl<S: Borrow<Schema>> ser::SerializeStruct for SerializeStructValue<S> {
 
     fn serialize_field<T: ?Sized>(
         &mut self,
-        _key: &'static str,
+        key: &'static str,
         value: &T,
     ) -> Result<(), Self::Error>
     where
         T: Serialize,
     {
-        let pre_pos = self.ctx.value.get_ref().len();
-        let field_delta = self.current_field_idx as i64 - self.last_serialized_field_idx;
-        self.ctx.value.write_uint(field_delta as u64);
+        let slot = self.slot(key)?;
+        let pre_pos = self.write_delta(slot);
+        let value_pos = self.ctx.value.get_ref().len();
 
-        let type_id = *self.fields[self.current_field_idx].field_type();
+        // in bounds, as checked by `slot`
+        let plan = &self.plan[self.current_field_idx];
+        let type_id = plan.type_id;
         let is_empty = self.ctx.with_borrow(|ctx| {
-            let de = FieldValueSerializer { ctx, type_id };
+            let de = FieldValueSerializer {
+                ctx,
+                type_id,
+                zero: plan.zero(),
+            };
             value.serialize(de)
         })?;
 
-        if !is_empty {
-            self.last_serialized_field_idx = self.current_field_idx as i64;
+        let field = Field {
+            name: key,
+            type_id,
+            value: &self.ctx.value.get_ref()[value_pos..],
+            is_zero: is_empty,
+        };
+        if !self.ctx.fields.omit(&field) {
+            self.written(slot);
         } else {
             // reset the buffer to the previous position
-            self.ctx.value.get_mut().truncate(pre_pos);
+            self.ctx.truncate(pre_pos);
         }
 
         self.current_field_idx += 1;
@@ -83,12 +148,14 @@ impl<S: Borrow<Schema>> ser::SerializeStruct for SerializeStructValue<S> {
         Ok(())
     }
 
-    fn skip_field(&mut self, _key: &'static str) -> Result<(), Self::Error> {
+    fn skip_field(&mut self, key: &'static str) -> Result<(), Self::Error> {
+        self.slot(key)?;
         self.current_field_idx += 1;
         Ok(())
     }
 
     fn end(mut self) -> Result<Self::Ok, Self::Error> {
+        self.end_embedded();
         self.ctx.value.write_uint(0);
 
         Ok(SerializationOk {
@@ -6,7 +6,7 @@ use serde_schema::types::{EnumVariant, Type};
 
 use error::Error;
 use internal::types::TypeId;
-use schema::{Schema, SchemaType};
+use schema::{EnumEncoding, Schema, SchemaType};
 
 use crate::{error, internal, schema};
 
@@ -16,7 +16,11 @@ use super::{FieldValueSerializer, SerializationCtx, SerializationOk};
 pub(crate) struct SerializeVariantValue<S> {
     ctx: SerializationCtx<S>,
     variant: OwningRef<SchemaType, EnumVariant<TypeId>>,
+    type_id: TypeId,
     variant_idx: u32,
+    enums: EnumEncoding,
+    // the number of the struct field holding the variant
+    field_idx: u32,
 }
 
 impl<S: Borrow<Schema>> SerializeVariantValue<S> {
@@ -26,8 +30,20 @@ impl<S: Borrow<Schema>> SerializeVariantValue<S> {
         variant_idx: u32,
     ) -> Result<Self, Error> {
         let variant;
+        let enums = ctx.schema.borrow().enum_encoding_of(type_id);
+        let mut field_idx = variant_idx;
 
         if let Some(schema_type) = ctx.schema.borrow().lookup(type_id) {
+            if let (Type::Enum(enum_type), EnumEncoding::Adjacent) = (&*schema_type, enums) {
+                // the tag comes first, and unit variants have no field
+                let units = enum_type
+                    .variants()
+                    .iter()
+                    .take(variant_idx as usize)
+                    .filter(|variant| variant.as_unit_variant().is_some())
+                    .count();
+                field_idx = variant_idx + 1 - units as u32;
+            }
             variant = OwningRef::new(schema_type).try_map::<_, _, Error>(|typ| {
                 if let &Type::Enum(ref enum_type) = typ {
                     if let Some(enum_variant) = enum_type.variant(variant_idx) {
@@ -46,12 +62,27 @@ impl<S: Borrow<Schema>> SerializeVariantValue<S> {
         Ok(SerializeVariantValue {
             ctx,
             variant,
+            type_id,
             variant_idx,
+            enums,
+            field_idx,
         })
     }
 
-    fn write_header(ctx: &mut SerializationCtx<S>, idx: u32) -> Result<(), Error> {
-        ctx.value.write_uint(idx as u64 + 1);
+    fn write_tag(&mut self) {
+        if self.enums == EnumEncoding::Adjacent {
+            self.ctx.value.write_uint(1);
+            self.ctx.value.write_bytes(self.variant.name().as_bytes());
+        }
+    }
+
+    fn write_header(&mut self) -> Result<(), Error> {
+        self.write_tag();
+        let delta = match self.enums {
+            EnumEncoding::External => self.field_idx + 1,
+            EnumEncoding::Adjacent => self.field_idx,
+        };
+        self.ctx.value.write_uint(delta as u64);
         Ok(())
     }
 
@@ -67,7 +98,7 @@ impl<S: Borrow<Schema>> SerializeVariantValue<S> {
     where
         T: Serialize,
     {
-        Self::write_header(&mut self.ctx, self.variant_idx)?;
+        self.write_header()?;
 
         let type_id = if let Some(newtype_variant) = self.variant.as_newtype_variant() {
             *newtype_variant.inner_type()
@@ -80,6 +111,7 @@ impl<S: Borrow<Schema>> SerializeVariantValue<S> {
         let de = FieldValueSerializer {
             ctx: self.ctx,
             type_id,
+            zero: None,
         };
         let mut ok = value.serialize(de)?;
 
@@ -91,20 +123,31 @@ impl<S: Borrow<Schema>> SerializeVariantValue<S> {
         })
     }
 
+    pub(crate) fn serialize_unit(mut self) -> Result<SerializationOk<S>, Error> {
+        if self.enums != EnumEncoding::Adjacent {
+            return Err(ser::Error::custom(
+                "unit variants need EnumEncoding::Adjacent",
+            ));
+        }
+        self.write_tag();
+        Self::write_footer(&mut self.ctx)?;
+        Ok(SerializationOk {
+            ctx: self.ctx,
+            is_empty: false,
+        })
+    }
+
     pub(crate) fn serialize_struct(mut self) -> Result<SerializeStructVariantValue<S>, Error> {
-        Self::write_header(&mut self.ctx, self.variant_idx)?;
-        let struct_variant = self.variant.clone().try_map::<_, _, Error>(|variant| {
-            if let Some(struct_variant) = variant.as_struct_variant() {
-                Ok(struct_variant.fields())
-            } else {
-                Err(ser::Error::custom(
-                    "variant type mismatch, expected newtype variant",
-                ))
-            }
-        })?;
+        self.write_header()?;
+        let plan = self
+            .ctx
+            .schema
+            .borrow()
+            .variant_plan(self.type_id, self.variant_idx)
+            .ok_or_else(|| Error::serialize("variant type mismatch, expected struct variant"))?;
 
         Ok(SerializeStructVariantValue {
-            inner: SerializeStructValue::from_parts(self.ctx, struct_variant),
+            inner: SerializeStructValue::from_parts(self.ctx, plan),
         })
     }
 }
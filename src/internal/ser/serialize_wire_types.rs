@@ -2,22 +2,50 @@ use serde::ser::{SerializeSeq, SerializeStruct};
 use serde::{Serialize, Serializer};
 use serde_schema::types::{EnumVariant, StructField, Type};
 
+use compat::ExternalEncoding;
 use error::Error;
-use schema::{Schema, TypeId};
+use internal::types::WireType;
+use schema::{external_encoding, EnumEncoding, Schema, TypeId, ENUM_TAG};
 
-use crate::{error, schema};
+use crate::{compat, error, internal, schema};
 
 use super::{FieldValueSerializer, SerializationCtx};
 
+/// Encodes a definition that was decoded from a stream, e.g. to pass it on,
+/// as the type id and payload of its section.
+pub(crate) fn serialize_wire_type(wire_type: &WireType) -> Result<Vec<u8>, Error> {
+    let mut ctx = SerializationCtx::with_schema(Schema::new());
+    ctx.value.write_int(-wire_type.common().id.0);
+    let ser = FieldValueSerializer {
+        ctx,
+        type_id: TypeId::WIRE_TYPE,
+        zero: None,
+    };
+    let ok = match wire_type {
+        WireType::Array(inner) => ser.serialize_newtype_variant("WireType", 0, "ArrayT", inner)?,
+        WireType::Slice(inner) => ser.serialize_newtype_variant("WireType", 1, "SliceT", inner)?,
+        WireType::Struct(inner) => {
+            ser.serialize_newtype_variant("WireType", 2, "StructT", inner)?
+        }
+        WireType::Map(inner) => ser.serialize_newtype_variant("WireType", 3, "MapT", inner)?,
+        WireType::GobEncoder(inner) => {
+            ser.serialize_newtype_variant("WireType", 4, "GobEncoderT", inner)?
+        }
+    };
+    Ok(ok.ctx.value.into_inner())
+}
+
 pub(crate) struct SerializeWireTypes<'a> {
     len_pre: usize,
+    enums: EnumEncoding,
     wire_types: &'a mut Vec<Vec<u8>>,
 }
 
 impl<'a> SerializeWireTypes<'a> {
-    pub fn new(wire_types: &'a mut Vec<Vec<u8>>) -> Self {
+    pub fn new(wire_types: &'a mut Vec<Vec<u8>>, enums: EnumEncoding) -> Self {
         SerializeWireTypes {
             len_pre: wire_types.len(),
+            enums,
             wire_types,
         }
     }
@@ -34,6 +62,7 @@ impl<'a> SerializeWireTypes<'a> {
         let ser = FieldValueSerializer {
             ctx,
             type_id: TypeId::WIRE_TYPE,
+            zero: None,
         };
         let ok = match ty {
             &Type::Struct(ref struct_type) => ser.serialize_newtype_variant(
@@ -88,8 +117,25 @@ impl<'a> SerializeWireTypes<'a> {
                     id,
                     name: enum_type.name(),
                     variants: enum_type.variants(),
+                    enums: self.enums,
                 },
             )?,
+            Type::TupleStruct(tuple_struct_type) if external_encoding(ty).is_some() => {
+                let (index, variant) = match external_encoding(ty) {
+                    Some(ExternalEncoding::Binary) => (5, "BinaryMarshalerT"),
+                    Some(ExternalEncoding::Text) => (6, "TextMarshalerT"),
+                    _ => (4, "GobEncoderT"),
+                };
+                ser.serialize_newtype_variant(
+                    "WireType",
+                    index,
+                    variant,
+                    &SerializeGobEncoderType {
+                        id,
+                        name: tuple_struct_type.name(),
+                    },
+                )?
+            }
             _ => {
                 return Err(::serde::de::Error::custom("unsupported type"));
             }
@@ -112,6 +158,7 @@ impl<'a> SerializeWireTypes<'a> {
                         let ser = FieldValueSerializer {
                             ctx,
                             type_id: TypeId::WIRE_TYPE,
+                            zero: None,
                         };
                         ser.serialize_newtype_variant(
                             "WireType",
@@ -137,6 +184,7 @@ struct SerializeEnumStructType<'a> {
     id: TypeId,
     name: &'a str,
     variants: &'a [EnumVariant<TypeId>],
+    enums: EnumEncoding,
 }
 
 impl<'a> Serialize for SerializeEnumStructType<'a> {
@@ -154,6 +202,7 @@ impl<'a> Serialize for SerializeEnumStructType<'a> {
             &SerializeEnumStructFields {
                 id: self.id,
                 variants: self.variants,
+                enums: self.enums,
             },
         )?;
         s.end()
@@ -163,14 +212,31 @@ impl<'a> Serialize for SerializeEnumStructType<'a> {
 struct SerializeEnumStructFields<'a> {
     id: TypeId,
     variants: &'a [EnumVariant<TypeId>],
+    enums: EnumEncoding,
 }
 
 impl<'a> Serialize for SerializeEnumStructFields<'a> {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        let mut s = serializer.serialize_seq(Some(self.variants.len()))?;
+        let mut len = self.variants.len();
+        if self.enums == EnumEncoding::Adjacent {
+            let units = self
+                .variants
+                .iter()
+                .filter(|variant| variant.as_unit_variant().is_some())
+                .count();
+            len = len + 1 - units;
+        }
+        let mut s = serializer.serialize_seq(Some(len))?;
+        if self.enums == EnumEncoding::Adjacent {
+            s.serialize_element(&SerializeStructField {
+                name: ENUM_TAG,
+                id: TypeId::STRING,
+            })?;
+        }
         let mut next_id = self.id.next();
         for variant in self.variants {
             match variant {
+                &EnumVariant::Unit(_) if self.enums == EnumEncoding::Adjacent => {}
                 &EnumVariant::Newtype(ref newtype_variant) => {
                     s.serialize_element(&SerializeStructField {
                         name: newtype_variant.name(),
@@ -314,6 +380,25 @@ impl<'a> Serialize for SerializeStructField<'a> {
     }
 }
 
+struct SerializeGobEncoderType<'a> {
+    id: TypeId,
+    name: &'a str,
+}
+
+impl<'a> Serialize for SerializeGobEncoderType<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("GobEncoderType", 1)?;
+        s.serialize_field(
+            "common",
+            &SerializeCommonType {
+                id: self.id,
+                name: self.name,
+            },
+        )?;
+        s.end()
+    }
+}
+
 struct SerializeCommonType<'a> {
     id: TypeId,
     name: &'a str,
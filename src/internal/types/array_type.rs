@@ -6,11 +6,34 @@ use super::{CommonType, FieldType, StructType, TypeId, WireType};
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct ArrayType {
-    pub common: CommonType,
+    pub(crate) common: CommonType,
     #[serde(rename = "Elem")]
-    pub elem: TypeId,
+    pub(crate) elem: TypeId,
     #[serde(rename = "Len", default)]
-    pub len: i64,
+    pub(crate) len: i64,
+}
+
+impl ArrayType {
+    pub fn new(common: CommonType, elem: TypeId, len: i64) -> ArrayType {
+        ArrayType { common, elem, len }
+    }
+
+    pub fn common(&self) -> &CommonType {
+        &self.common
+    }
+
+    pub fn elem(&self) -> TypeId {
+        self.elem
+    }
+
+    /// The number of elements of the array type.
+    pub fn len(&self) -> i64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
 }
 
 lazy_static! {
@@ -7,9 +7,27 @@ use super::{FieldType, StructType, TypeId, WireType};
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct CommonType {
     #[serde(rename = "Name", default)]
-    pub name: Cow<'static, str>,
+    pub(crate) name: Cow<'static, str>,
     #[serde(rename = "Id")]
-    pub id: TypeId,
+    pub(crate) id: TypeId,
+}
+
+impl CommonType {
+    pub fn new<S: Into<Cow<'static, str>>>(name: S, id: TypeId) -> CommonType {
+        CommonType {
+            name: name.into(),
+            id,
+        }
+    }
+
+    /// The name of the type, empty for unnamed types like `[]int`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn id(&self) -> TypeId {
+        self.id
+    }
 }
 
 lazy_static! {
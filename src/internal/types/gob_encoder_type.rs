@@ -0,0 +1,44 @@
+use std::borrow::Cow;
+
+use serde_schema::types::Type;
+
+use super::{CommonType, FieldType, StructType, TypeId, WireType};
+
+// the type of values encoded by Go's `GobEncoder`, `BinaryMarshaler` or
+// `TextMarshaler`, which are sent as byte slices
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct GobEncoderType {
+    pub(crate) common: CommonType,
+}
+
+impl GobEncoderType {
+    pub fn new(common: CommonType) -> GobEncoderType {
+        GobEncoderType { common }
+    }
+
+    pub fn common(&self) -> &CommonType {
+        &self.common
+    }
+}
+
+lazy_static! {
+    pub static ref GOB_ENCODER_TYPE_DEF: Type<TypeId> = {
+        Type::build()
+            .struct_type("gobEncoderType", 1)
+            .field("common", TypeId::COMMON_TYPE)
+            .end()
+    };
+}
+
+pub static GOB_ENCODER_TYPE_DEF_2: WireType = {
+    WireType::Struct(StructType {
+        common: CommonType {
+            name: Cow::Borrowed("gobEncoderType"),
+            id: TypeId::GOB_ENCODER_TYPE,
+        },
+        fields: Cow::Borrowed(&[FieldType {
+            name: Cow::Borrowed("common"),
+            id: TypeId::COMMON_TYPE,
+        }]),
+    })
+};
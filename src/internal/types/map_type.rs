@@ -6,11 +6,29 @@ use super::{CommonType, FieldType, StructType, TypeId, WireType};
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct MapType {
-    pub common: CommonType,
+    pub(crate) common: CommonType,
     #[serde(rename = "Key")]
-    pub key: TypeId,
+    pub(crate) key: TypeId,
     #[serde(rename = "Elem")]
-    pub elem: TypeId,
+    pub(crate) elem: TypeId,
+}
+
+impl MapType {
+    pub fn new(common: CommonType, key: TypeId, elem: TypeId) -> MapType {
+        MapType { common, key, elem }
+    }
+
+    pub fn common(&self) -> &CommonType {
+        &self.common
+    }
+
+    pub fn key(&self) -> TypeId {
+        self.key
+    }
+
+    pub fn elem(&self) -> TypeId {
+        self.elem
+    }
 }
 
 lazy_static! {
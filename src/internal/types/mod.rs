@@ -1,34 +1,53 @@
 use std::collections::BTreeMap;
+use std::sync::{Arc, PoisonError, RwLock};
 
 use serde_schema::types::Type;
 
 mod wire_type;
-use crate::schema;
+use crate::{de, schema};
 
-pub(crate) use self::wire_type::WireType;
+pub use self::wire_type::WireType;
 
 mod common_type;
-pub(crate) use self::common_type::CommonType;
+pub use self::common_type::CommonType;
 
 mod array_type;
-pub(crate) use self::array_type::ArrayType;
+pub use self::array_type::ArrayType;
 
 mod slice_type;
-pub(crate) use self::slice_type::SliceType;
+pub use self::slice_type::SliceType;
 
 mod struct_type;
-pub(crate) use self::struct_type::{FieldType, StructType};
+pub use self::struct_type::{FieldType, StructType};
 
 mod map_type;
-pub(crate) use self::map_type::MapType;
+pub use self::map_type::MapType;
+
+mod gob_encoder_type;
+pub use self::gob_encoder_type::GobEncoderType;
 
 pub use schema::TypeId;
 
-#[derive(Debug)]
+use de::{DecodeHooks, Hook, SchemaRegistry};
+
+// Shared between clones until one of them is modified, so that
+// deserializers started from the same snapshot don't each hold a copy.
+#[derive(Clone, Debug, Default)]
 pub struct Types {
-    map: BTreeMap<TypeId, WireType>,
+    map: Arc<BTreeMap<TypeId, WireType>>,
+    // kept along with the definitions, as these reach every deserializer
+    hooks: Option<Arc<DecodeHooks>>,
+    // the field indices of struct types, per struct type and address of
+    // the field list of the Rust type decoded into it
+    indices: Arc<RwLock<BTreeMap<(TypeId, usize), Option<FieldIndices>>>>,
+    // where definitions not sent on the stream are looked up, kept along
+    // with them like the hooks
+    registry: Option<Arc<dyn SchemaRegistry>>,
 }
 
+// the position of each field of a struct type in the fields of a Rust type
+pub(crate) type FieldIndices = Arc<[Option<u64>]>;
+
 pub(crate) fn lookup_builtin(id: TypeId) -> Option<&'static Type<TypeId>> {
     match id {
         TypeId::ARRAY_TYPE => Some(&self::array_type::ARRAY_TYPE_DEF),
@@ -39,6 +58,7 @@ pub(crate) fn lookup_builtin(id: TypeId) -> Option<&'static Type<TypeId>> {
         TypeId::STRUCT_TYPE => Some(&self::struct_type::STRUCT_TYPE_DEF),
         TypeId::WIRE_TYPE => Some(&self::wire_type::WIRE_TYPE_DEF),
         TypeId::COMMON_TYPE => Some(&self::common_type::COMMON_TYPE_DEF),
+        TypeId::GOB_ENCODER_TYPE => Some(&self::gob_encoder_type::GOB_ENCODER_TYPE_DEF),
         _ => None,
     }
 }
@@ -53,6 +73,7 @@ pub(crate) fn lookup_builtin2(id: TypeId) -> Option<&'static WireType> {
         TypeId::STRUCT_TYPE => Some(&self::struct_type::STRUCT_TYPE_DEF_2),
         TypeId::WIRE_TYPE => Some(&self::wire_type::WIRE_TYPE_DEF_2),
         TypeId::COMMON_TYPE => Some(&self::common_type::COMMON_TYPE_DEF_2),
+        TypeId::GOB_ENCODER_TYPE => Some(&self::gob_encoder_type::GOB_ENCODER_TYPE_DEF_2),
         _ => None,
     }
 }
@@ -60,15 +81,88 @@ pub(crate) fn lookup_builtin2(id: TypeId) -> Option<&'static WireType> {
 impl Types {
     pub fn new() -> Types {
         Types {
-            map: BTreeMap::new(),
+            map: Arc::new(BTreeMap::new()),
+            hooks: None,
+            indices: Arc::default(),
+            registry: None,
         }
     }
 
     pub(crate) fn insert(&mut self, def: WireType) {
-        self.map.insert(def.common().id, def);
+        Arc::make_mut(&mut self.map).insert(def.common().id, def);
+        // which may have been built for an earlier definition of the id
+        self.indices = Arc::default();
     }
 
     pub(crate) fn lookup(&self, id: TypeId) -> Option<&WireType> {
         lookup_builtin2(id).or_else(|| self.map.get(&id))
     }
+
+    pub(crate) fn set_hooks(&mut self, hooks: DecodeHooks) {
+        self.hooks = if hooks.is_empty() {
+            None
+        } else {
+            Some(Arc::new(hooks))
+        };
+    }
+
+    // the hook registered for the type `id`, if it is a named one
+    pub(crate) fn hook(&self, id: TypeId) -> Option<&Hook> {
+        let hooks = self.hooks.as_ref()?;
+        hooks.get(&self.lookup(id)?.common().name)
+    }
+
+    pub(crate) fn set_registry(&mut self, registry: Arc<dyn SchemaRegistry>) {
+        self.registry = Some(registry);
+    }
+
+    pub(crate) fn registry(&self) -> Option<&Arc<dyn SchemaRegistry>> {
+        self.registry.as_ref()
+    }
+
+    // the field indices of the struct type `id` in `fields`, built by
+    // `build` on first use
+    pub(crate) fn field_indices<F>(
+        &self,
+        id: TypeId,
+        fields: &'static [&'static str],
+        build: F,
+    ) -> Option<FieldIndices>
+    where
+        F: FnOnce() -> Option<FieldIndices>,
+    {
+        let key = (id, fields.as_ptr() as usize);
+        let cached = self
+            .indices
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&key)
+            .cloned();
+        if let Some(indices) = cached {
+            return indices;
+        }
+        let indices = build();
+        self.indices
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(key, indices.clone());
+        indices
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &WireType> {
+        self.map.values()
+    }
+
+    // the definitions alone, without the hooks and registry kept along
+    pub(crate) fn definitions(&self) -> Types {
+        Types {
+            map: self.map.clone(),
+            ..Types::new()
+        }
+    }
+
+    // whether neither was given definitions since one was cloned off the other
+    pub(crate) fn same_definitions(&self, other: &Types) -> bool {
+        Arc::ptr_eq(&self.map, &other.map)
+    }
 }
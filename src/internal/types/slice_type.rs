@@ -6,9 +6,23 @@ use super::{CommonType, FieldType, StructType, TypeId, WireType};
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct SliceType {
-    pub common: CommonType,
+    pub(crate) common: CommonType,
     #[serde(rename = "Elem")]
-    pub elem: TypeId,
+    pub(crate) elem: TypeId,
+}
+
+impl SliceType {
+    pub fn new(common: CommonType, elem: TypeId) -> SliceType {
+        SliceType { common, elem }
+    }
+
+    pub fn common(&self) -> &CommonType {
+        &self.common
+    }
+
+    pub fn elem(&self) -> TypeId {
+        self.elem
+    }
 }
 
 lazy_static! {
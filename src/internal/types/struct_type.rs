@@ -6,10 +6,45 @@ use super::{CommonType, SliceType, TypeId, WireType};
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct StructType {
-    pub common: CommonType,
+    pub(crate) common: CommonType,
     // the fields of the struct
     #[serde(rename = "Fields", default)]
-    pub fields: Cow<'static, [FieldType]>,
+    pub(crate) fields: Cow<'static, [FieldType]>,
+}
+
+impl StructType {
+    /// Creates a struct type without fields, to be added with `field`:
+    ///
+    /// ```
+    /// # use gob::types::{CommonType, StructType, TypeId};
+    /// let id = TypeId::new(65).unwrap();
+    /// let point = StructType::new(CommonType::new("Point", id))
+    ///     .field("X", TypeId::INT)
+    ///     .field("Y", TypeId::INT);
+    /// assert_eq!(point.fields()[1].name(), "Y");
+    /// ```
+    pub fn new(common: CommonType) -> StructType {
+        StructType {
+            common,
+            fields: Cow::Borrowed(&[]),
+        }
+    }
+
+    /// Adds a field of type `id`, after those added so far.
+    pub fn field<S: Into<Cow<'static, str>>>(mut self, name: S, id: TypeId) -> StructType {
+        self.fields.to_mut().push(FieldType::new(name, id));
+        self
+    }
+
+    pub fn common(&self) -> &CommonType {
+        &self.common
+    }
+
+    /// The fields in the order they are sent, which is the order of their
+    /// field numbers.
+    pub fn fields(&self) -> &[FieldType] {
+        &self.fields
+    }
 }
 
 lazy_static! {
@@ -45,10 +80,27 @@ pub static STRUCT_TYPE_DEF_2: WireType = {
 pub struct FieldType {
     // the name of the field
     #[serde(rename = "Name")]
-    pub name: Cow<'static, str>,
+    pub(crate) name: Cow<'static, str>,
     // the type id of the field, which must be already defined
     #[serde(rename = "Id")]
-    pub id: TypeId,
+    pub(crate) id: TypeId,
+}
+
+impl FieldType {
+    pub fn new<S: Into<Cow<'static, str>>>(name: S, id: TypeId) -> FieldType {
+        FieldType {
+            name: name.into(),
+            id,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn id(&self) -> TypeId {
+        self.id
+    }
 }
 
 lazy_static! {
@@ -2,7 +2,9 @@ use std::borrow::Cow;
 
 use serde_schema::types::Type;
 
-use super::{ArrayType, CommonType, FieldType, MapType, SliceType, StructType, TypeId};
+use super::{
+    ArrayType, CommonType, FieldType, GobEncoderType, MapType, SliceType, StructType, TypeId,
+};
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize)]
 pub enum WireType {
@@ -14,16 +16,25 @@ pub enum WireType {
     Struct(StructType),
     #[serde(rename = "MapT")]
     Map(MapType),
+    #[serde(
+        rename = "GobEncoderT",
+        alias = "BinaryMarshalerT",
+        alias = "TextMarshalerT"
+    )]
+    GobEncoder(GobEncoderType),
 }
 
 lazy_static! {
     pub static ref WIRE_TYPE_DEF: Type<TypeId> = {
         Type::build()
-            .enum_type("WireType", 4)
+            .enum_type("WireType", 7)
             .newtype_variant("ArrayT", TypeId::ARRAY_TYPE)
             .newtype_variant("SliceT", TypeId::SLICE_TYPE)
             .newtype_variant("StructT", TypeId::STRUCT_TYPE)
             .newtype_variant("MapT", TypeId::MAP_TYPE)
+            .newtype_variant("GobEncoderT", TypeId::GOB_ENCODER_TYPE)
+            .newtype_variant("BinaryMarshalerT", TypeId::GOB_ENCODER_TYPE)
+            .newtype_variant("TextMarshalerT", TypeId::GOB_ENCODER_TYPE)
             .end()
     };
 }
@@ -51,17 +62,31 @@ pub static WIRE_TYPE_DEF_2: WireType = {
                 name: Cow::Borrowed("MapT"),
                 id: TypeId::MAP_TYPE,
             },
+            FieldType {
+                name: Cow::Borrowed("GobEncoderT"),
+                id: TypeId::GOB_ENCODER_TYPE,
+            },
+            FieldType {
+                name: Cow::Borrowed("BinaryMarshalerT"),
+                id: TypeId::GOB_ENCODER_TYPE,
+            },
+            FieldType {
+                name: Cow::Borrowed("TextMarshalerT"),
+                id: TypeId::GOB_ENCODER_TYPE,
+            },
         ]),
     })
 };
 
 impl WireType {
+    /// The name and id shared by all kinds of definitions.
     pub fn common(&self) -> &CommonType {
         match self {
             &WireType::Array(ref inner) => &inner.common,
             &WireType::Slice(ref inner) => &inner.common,
             &WireType::Struct(ref inner) => &inner.common,
             &WireType::Map(ref inner) => &inner.common,
+            WireType::GobEncoder(inner) => &inner.common,
         }
     }
 }
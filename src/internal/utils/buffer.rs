@@ -3,55 +3,139 @@ use std::io::{Read, Result as IoResult};
 use bytes::Buf;
 use safemem::copy_over;
 
+pub const DEFAULT_CHUNK_SIZE: usize = 4096;
+
+// `bytes` is kept initialized up to its length, so that reads can go
+// straight into the part past `end` without zeroing it every time.
 pub struct Buffer {
     bytes: Vec<u8>,
     offset: usize,
+    end: usize,
+    chunk_size: usize,
+    stats: BufferStats,
+}
+
+/// How much memory a stream deserializer has set aside for buffering
+/// input, and how often it had to grow.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BufferStats {
+    capacity: usize,
+    reallocations: u64,
+}
+
+impl BufferStats {
+    /// Current size of the buffer's allocation, in bytes.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Number of times the buffer was reallocated to make room for a
+    /// message larger than what it could hold.
+    pub fn reallocations(&self) -> u64 {
+        self.reallocations
+    }
 }
 
 impl Buffer {
     pub fn new() -> Buffer {
+        Buffer::from_vec(Vec::with_capacity(DEFAULT_CHUNK_SIZE))
+    }
+
+    /// Reuses the allocation of `bytes`, discarding its contents.
+    pub fn from_vec(mut bytes: Vec<u8>) -> Buffer {
+        bytes.clear();
         Buffer {
-            bytes: Vec::with_capacity(4096),
+            stats: BufferStats {
+                capacity: bytes.capacity(),
+                reallocations: 0,
+            },
+            bytes,
             offset: 0,
+            end: 0,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+        }
+    }
+
+    /// Gives up the allocation for reuse, discarding anything unread.
+    pub fn into_vec(mut self) -> Vec<u8> {
+        self.bytes.clear();
+        self.bytes
+    }
+
+    pub fn stats(&self) -> BufferStats {
+        self.stats
+    }
+
+    /// Sets the least number of bytes each call to `read_from` asks for.
+    pub fn set_chunk_size(&mut self, chunk_size: usize) {
+        self.chunk_size = chunk_size.max(1);
+    }
+
+    fn track_growth(&mut self) {
+        let capacity = self.bytes.capacity();
+        if capacity != self.stats.capacity {
+            self.stats.capacity = capacity;
+            self.stats.reallocations += 1;
         }
     }
 
     pub fn len(&self) -> usize {
-        self.bytes.len() - self.offset
+        self.end - self.offset
     }
 
     pub fn as_slice(&self) -> &[u8] {
-        &self.bytes.as_slice()[self.offset..]
+        &self.bytes.as_slice()[self.offset..self.end]
     }
 
+    // Moves the remaining bytes to the front, but only once at least as
+    // many bytes have been consumed as remain. Each byte is then moved at
+    // most once for every byte consumed before it, which keeps many small
+    // reads and advances linear instead of shifting the whole buffer
+    // every time.
     fn make_space(&mut self) {
         let off = self.offset;
-        if off > 0 {
-            let len = self.bytes.len();
-            debug_assert!(len >= off);
+        let len = self.end;
+        debug_assert!(len >= off);
+        if off > 0 && off >= len - off {
             if len > off {
                 // copy remaining bytes to the beginning of the buffer
                 copy_over(self.bytes.as_mut_slice(), off, 0, len - off);
             }
-            self.bytes.truncate(len - off);
+            self.end = len - off;
             self.offset = 0;
         }
     }
 
+    // makes sure there are at least `n` initialized bytes past `end`
+    fn reserve(&mut self, n: usize) {
+        if self.bytes.len() - self.end < n {
+            // use all of the allocation, whether it's new or not
+            self.bytes.reserve(self.end + n - self.bytes.len());
+            let capacity = self.bytes.capacity();
+            self.bytes.resize(capacity, 0);
+            self.track_growth();
+        }
+    }
+
+    pub fn extend_from_slice(&mut self, bytes: &[u8]) {
+        self.make_space();
+        self.reserve(bytes.len());
+        self.bytes[self.end..self.end + bytes.len()].copy_from_slice(bytes);
+        self.end += bytes.len();
+    }
+
+    /// Reads into all of the free space in the buffer, after making room
+    /// for at least one chunk.
+    ///
+    /// The free space is a single slice, so a vectored read could not take
+    /// in more at once. It is only zeroed when the buffer grows, which
+    /// leaves `read_buf` (not stable yet) nothing to save either.
     pub fn read_from<R: Read>(&mut self, r: &mut R) -> IoResult<usize> {
         self.make_space();
-        let pre_len = self.bytes.len();
-        self.bytes.resize(pre_len + 4096, 0);
-        match r.read(&mut self.bytes.as_mut_slice()[pre_len..]) {
-            Ok(len) => {
-                self.bytes.truncate(pre_len + len);
-                Ok(len)
-            }
-            Err(err) => {
-                self.bytes.truncate(pre_len);
-                Err(err)
-            }
-        }
+        self.reserve(self.chunk_size);
+        let len = r.read(&mut self.bytes.as_mut_slice()[self.end..])?;
+        self.end += len;
+        Ok(len)
     }
 }
 
@@ -75,3 +159,57 @@ impl Buf for Buffer {
         self.offset += cnt;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bytes::Buf;
+
+    use super::Buffer;
+
+    #[test]
+    fn small_messages_stay_compact() {
+        let message = (0..20).collect::<Vec<u8>>();
+        let mut buffer = Buffer::new();
+        let mut pos = 0;
+        for _ in 0..100_000 {
+            // misaligned chunks, so there is mostly a partial message left
+            let chunk = &message[pos..(pos + 7).min(20)];
+            pos = (pos + chunk.len()) % 20;
+            buffer.extend_from_slice(chunk);
+            while buffer.len() >= 20 {
+                assert_eq!(&buffer.bytes()[..20], &message[..]);
+                buffer.advance(20);
+            }
+        }
+        assert!(buffer.bytes.capacity() <= 4096);
+        assert_eq!(buffer.stats().reallocations(), 0);
+    }
+
+    #[test]
+    fn reused_allocation() {
+        let mut buffer = Buffer::from_vec(vec![0; 10_000]);
+        assert_eq!(buffer.stats().capacity(), 10_000);
+        assert_eq!(buffer.len(), 0);
+
+        // one read fills the whole allocation
+        let mut input = &[1u8; 16_384][..];
+        assert_eq!(buffer.read_from(&mut input).unwrap(), 10_000);
+        assert_eq!(buffer.stats().reallocations(), 0);
+
+        buffer.read_from(&mut input).unwrap();
+        assert_eq!(buffer.len(), 16_384);
+        assert_eq!(buffer.stats().reallocations(), 1);
+        assert!(buffer.into_vec().capacity() >= 16_384);
+    }
+
+    #[test]
+    fn chunk_size() {
+        let mut buffer = Buffer::new();
+        buffer.set_chunk_size(65_536);
+        let mut input = &[1u8; 100_000][..];
+        assert_eq!(buffer.read_from(&mut input).unwrap(), 65_536);
+        buffer.advance(65_536);
+        assert_eq!(buffer.read_from(&mut input).unwrap(), 34_464);
+        assert_eq!(buffer.stats().reallocations(), 1);
+    }
+}
@@ -2,7 +2,7 @@ mod bow;
 pub use self::bow::Bow;
 
 mod buffer;
-pub use self::buffer::Buffer;
+pub use self::buffer::{Buffer, BufferStats};
 
 mod bufvec;
 pub use self::bufvec::BufVec;
@@ -1,4 +1,7 @@
 #![deny(warnings)]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
 
 #[cfg(test)]
 extern crate partial_io;
@@ -6,28 +9,136 @@ extern crate partial_io;
 #[macro_use]
 extern crate quickcheck;
 
+#[cfg(feature = "arrow")]
+extern crate arrow_array;
+#[cfg(feature = "arrow")]
+extern crate arrow_buffer;
+#[cfg(feature = "arrow")]
+extern crate arrow_schema;
+#[cfg(feature = "arena")]
+extern crate bumpalo;
+#[cfg(feature = "std")]
 extern crate byteorder;
+#[cfg(feature = "std")]
 extern crate bytes;
+#[cfg(feature = "erased")]
+extern crate erased_serde;
+#[cfg(feature = "gzip")]
+extern crate flate2;
+#[cfg(feature = "futures")]
+extern crate futures;
+#[cfg(feature = "futures")]
+extern crate futures_timer;
+#[cfg(feature = "std")]
 extern crate iovec;
+#[cfg(feature = "std")]
 #[macro_use]
 extern crate lazy_static;
+#[cfg(feature = "std")]
 extern crate owning_ref;
+#[cfg(feature = "parquet")]
+extern crate parquet;
+#[cfg(feature = "testing")]
+extern crate proptest;
+#[cfg(feature = "testing")]
+extern crate rand;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "std")]
 extern crate safemem;
+#[cfg(feature = "std")]
 #[macro_use]
 extern crate serde;
+#[cfg(feature = "std")]
 #[macro_use]
 extern crate serde_derive;
+#[cfg(feature = "std")]
+extern crate serde_ignored;
+#[cfg(feature = "json")]
+extern crate serde_json;
+#[cfg(feature = "std")]
 extern crate serde_schema;
+#[cfg(feature = "snappy")]
+extern crate snap;
+#[cfg(feature = "wasm")]
+extern crate wasm_bindgen;
 
+#[cfg(feature = "std")]
 mod internal;
-mod schema;
 
+pub mod codec;
+#[cfg(feature = "std")]
 pub mod error;
 
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(feature = "futures")]
+pub mod async_io;
+#[cfg(feature = "std")]
+pub mod compat;
+#[cfg(any(feature = "gzip", feature = "snappy"))]
+pub mod compress;
+#[cfg(feature = "std")]
 pub mod de;
+#[cfg(feature = "std")]
+pub mod debug;
+#[cfg(feature = "std")]
+pub mod duplex;
+#[cfg(feature = "std")]
+pub mod export;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "std")]
+pub mod index;
+#[cfg(feature = "std")]
+pub mod message;
+#[cfg(feature = "std")]
+pub mod mode;
+#[cfg(feature = "std")]
+pub mod reader;
+#[cfg(feature = "std")]
+pub mod relay;
+#[cfg(feature = "std")]
+pub mod rpc;
+#[cfg(feature = "std")]
+pub mod schema;
+#[cfg(feature = "std")]
 pub mod ser;
+#[cfg(feature = "std")]
+pub mod session;
+#[cfg(feature = "std")]
+pub mod shared_bytes;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "std")]
+pub mod transcode;
+#[cfg(feature = "std")]
+pub mod types;
+#[cfg(feature = "std")]
+pub mod value;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "std")]
+pub mod wire;
+#[cfg(feature = "std")]
+pub mod writer;
+
+#[cfg(feature = "cli")]
+#[doc(hidden)]
+pub mod cli;
 
+#[cfg(feature = "std")]
 pub use error::Error;
+#[cfg(feature = "std")]
+pub use mode::Mode;
 
-pub use de::{Deserializer, StreamDeserializer};
+#[cfg(feature = "std")]
+pub use de::{Decoder, Deserializer, StreamDeserializer};
+#[cfg(feature = "std")]
 pub use ser::StreamSerializer;
+#[cfg(feature = "std")]
+pub use session::Session;
+#[cfg(feature = "std")]
+pub use shared_bytes::SharedBytes;
+#[cfg(feature = "std")]
+pub use value::Value;
@@ -0,0 +1,70 @@
+//! Standalone messages for datagram transports
+//!
+//! Transports like NATS, Kafka or UDP deliver each message on its own, so
+//! a message cannot rely on type definitions sent in an earlier one, which
+//! may have been lost, reordered or consumed by another subscriber.
+//! `encode_message` bundles the definitions a value needs with the value
+//! itself, the way Go does when a fresh `gob.Encoder` encodes a single
+//! value:
+//!
+//! ```
+//! # extern crate gob;
+//! let bytes = gob::message::encode_message(&vec![1i64, 2, 3]).unwrap();
+//! let decoded: Vec<i64> = gob::message::decode_message(&bytes).unwrap();
+//! assert_eq!(decoded, [1, 2, 3]);
+//! ```
+//!
+//! When the peer is known to have the definitions already, e.g. from an
+//! earlier message or from `StreamSerializer::export_type_defs`,
+//! `encode_message_without_types` leaves them out. Type ids are assigned
+//! the same way for every message of a type, so such messages decode with
+//! `decode_message_with_type_defs`.
+
+use serde::Deserialize;
+use serde_schema::SchemaSerialize;
+
+use de::{Deserializer, TypeDefs};
+use error::Error;
+use ser::StreamSerializer;
+
+use crate::{de, error, ser};
+
+/// Encodes `value` into a message of its own, type definitions included.
+pub fn encode_message<T: SchemaSerialize>(value: &T) -> Result<Vec<u8>, Error> {
+    let mut stream = StreamSerializer::new_with_write(Vec::new());
+    stream.serialize(value)?;
+    Ok(stream.into_writer())
+}
+
+/// Encodes `value` into a message without the type definitions it needs.
+pub fn encode_message_without_types<T: SchemaSerialize>(value: &T) -> Result<Vec<u8>, Error> {
+    let mut stream = StreamSerializer::new_with_write(Vec::new());
+    stream.export_type_defs::<T>()?;
+    stream.serialize(value)?;
+    Ok(stream.into_writer())
+}
+
+/// Decodes a message holding a single value, along with any type
+/// definitions it needs.
+///
+/// Fails if anything follows the value.
+pub fn decode_message<'de, T: Deserialize<'de>>(bytes: &'de [u8]) -> Result<T, Error> {
+    decode_message_with_type_defs(bytes, &TypeDefs::new())
+}
+
+/// Like `decode_message`, but with the types in `defs` already known, for
+/// messages encoded by `encode_message_without_types`.
+pub fn decode_message_with_type_defs<'de, T>(bytes: &'de [u8], defs: &TypeDefs) -> Result<T, Error>
+where
+    T: Deserialize<'de>,
+{
+    let mut values = Deserializer::from_slice_many_with_type_defs(bytes, defs.clone());
+    let value = match values.next() {
+        Some(de) => T::deserialize(de?)?,
+        None => return Err(Error::deserialize("message holds no value")),
+    };
+    if values.position() != bytes.len() {
+        return Err(Error::deserialize("message holds more than one value"));
+    }
+    Ok(value)
+}
@@ -0,0 +1,26 @@
+//! Strictness presets
+
+/// How strictly input is checked, bundling the individual options.
+///
+/// - `Strict` rejects anything Go's own decoder would reject, such as
+///   messages with bytes left over after their value and types that are
+///   defined twice. Interface values only select enum variants named
+///   exactly like their concrete type, pointer prefix included.
+/// - `Compatible` accepts everything Go produces, and is the default.
+/// - `Lenient` also accepts input that can only be decoded with some loss,
+///   like strings that are not valid UTF-8 and numbers that don't fit the
+///   type they are decoded into, which are clamped. Strings and byte slices
+///   decode into each other.
+///
+/// On the write side, see `ser::Builder::mode`, `Strict` writes values in
+/// their canonical encoding, while the other two write like Go does.
+///
+/// A mode only sets a starting point; individual options can still be
+/// changed afterwards.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Mode {
+    Strict,
+    #[default]
+    Compatible,
+    Lenient,
+}
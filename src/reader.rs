@@ -0,0 +1,435 @@
+//! Pull-based event reader
+//!
+//! `EventReader` breaks a gob stream down into structural events without
+//! going through serde, for tools that look at the shape of the data
+//! rather than decode it into Rust types:
+//!
+//! ```ignore
+//! let mut reader = gob::reader::EventReader::new(File::open("dump.gob")?);
+//! let mut fields = 0;
+//! while let Some(event) = reader.next_event()? {
+//!     if let Event::Field(_) = event {
+//!         fields += 1;
+//!     }
+//! }
+//! ```
+//!
+//! Every value on the stream starts with `Event::Value`, followed by the
+//! events of the value itself. Structs, sequences, maps and interface
+//! values are opened by a `Start*` event and closed by `Event::End`; each
+//! struct field is announced by `Event::Field` ahead of its value. Type
+//! definitions are decoded as they are encountered and reported as
+//! `Event::TypeDef`.
+
+use std::io::{Cursor, Read};
+use std::ops::Range;
+
+use bytes::Buf;
+
+use de::{TypeDefs, TypeInfo, DEFAULT_OPTIONS};
+use error::Error;
+use internal::gob::{Message, MessageReadError, SectionHeader, Stream};
+use internal::types::{TypeId, Types, WireType};
+use internal::utils::Buffer;
+
+use crate::{de, error, internal};
+
+/// A structural event of a gob stream.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event<'a> {
+    /// A type was defined.
+    TypeDef(TypeInfo),
+    /// A value of the given type follows.
+    Value(TypeId),
+    Bool(bool),
+    Int(i64),
+    Uint(u64),
+    Float(f64),
+    Complex(f64, f64),
+    /// A byte slice, or the payload of a type that encodes itself.
+    Bytes(&'a [u8]),
+    /// A string, which Go does not require to be valid UTF-8.
+    String(&'a [u8]),
+    /// A nil interface value.
+    Nil,
+    /// A struct of the given type, whose fields follow until `End`.
+    StartStruct(TypeId),
+    /// The field of the enclosing struct whose value follows.
+    Field(&'a str),
+    /// A slice or array of the given number of elements.
+    StartSeq(u64),
+    /// A map of the given number of entries, each key followed by its value.
+    StartMap(u64),
+    /// An interface value of a concrete type registered under the given
+    /// name, whose value follows.
+    StartInterface(&'a str, TypeId),
+    /// Closes the innermost struct, sequence, map or interface value.
+    End,
+}
+
+enum Frame {
+    Struct {
+        type_id: TypeId,
+        field_no: i64,
+    },
+    Seq {
+        elem: TypeId,
+        remaining: u64,
+    },
+    Map {
+        key: TypeId,
+        elem: TypeId,
+        remaining: u64,
+        key_next: bool,
+    },
+    Interface {
+        done: bool,
+    },
+}
+
+// an event whose borrowed parts are still ranges of the input or indices
+// into the type definitions
+enum Raw {
+    Event(Event<'static>),
+    Bytes(Range<usize>),
+    String(Range<usize>),
+    Field(TypeId, usize),
+    StartInterface(Range<usize>, TypeId),
+}
+
+/// Reads a gob stream event by event.
+pub struct EventReader<R> {
+    stream: Stream<R>,
+    buffer: Buffer,
+    defs: Types,
+    // the section being read, and the position within it
+    section: Option<SectionHeader>,
+    pos: usize,
+    frames: Vec<Frame>,
+    // the type of the value to be read next
+    pending: Option<TypeId>,
+}
+
+impl<R> EventReader<R> {
+    pub fn new(read: R) -> Self {
+        EventReader {
+            stream: Stream::new(read),
+            buffer: Buffer::new(),
+            defs: Types::new(),
+            section: None,
+            pos: 0,
+            frames: Vec::new(),
+            pending: None,
+        }
+    }
+
+    /// Create a new event reader that knows about all types in `defs`
+    /// up front.
+    pub fn with_type_defs(read: R, defs: TypeDefs) -> Self {
+        EventReader {
+            defs: defs.types,
+            ..EventReader::new(read)
+        }
+    }
+
+    /// Takes a snapshot of all types defined on the stream so far.
+    pub fn type_defs(&self) -> TypeDefs {
+        TypeDefs {
+            types: self.defs.clone(),
+        }
+    }
+
+    /// Describes a type defined on the stream so far.
+    pub fn type_info(&self, id: TypeId) -> Option<TypeInfo> {
+        TypeInfo::lookup(id, &self.defs)
+    }
+
+    /// Number of enclosing structs, sequences, maps and interface values.
+    pub fn depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn get_ref(&self) -> &R {
+        self.stream.get_ref()
+    }
+
+    pub fn into_inner(self) -> R {
+        self.stream.into_inner()
+    }
+}
+
+impl<R: Read> EventReader<R> {
+    /// Reads the next event, or returns `None` at the end of the stream.
+    ///
+    /// The stream may only end between values; running out of input in
+    /// the middle of one is an error.
+    pub fn next_event(&mut self) -> Result<Option<Event<'_>>, Error> {
+        let raw = match self.next_raw()? {
+            Some(raw) => raw,
+            None => return Ok(None),
+        };
+        let payload = match self.section {
+            Some(ref header) => &self.buffer.bytes()[..header.payload_range.end],
+            None => &[][..],
+        };
+        Ok(Some(match raw {
+            Raw::Event(event) => event,
+            Raw::Bytes(range) => Event::Bytes(&payload[range]),
+            Raw::String(range) => Event::String(&payload[range]),
+            Raw::Field(type_id, field_no) => match self.defs.lookup(type_id) {
+                Some(WireType::Struct(struct_type)) => {
+                    Event::Field(&struct_type.fields[field_no].name)
+                }
+                _ => unreachable!(),
+            },
+            Raw::StartInterface(name, type_id) => {
+                let name = ::std::str::from_utf8(&payload[name])
+                    .map_err(|_| Error::deserialize("interface name is not valid UTF-8"))?;
+                Event::StartInterface(name, type_id)
+            }
+        }))
+    }
+
+    /// Skips the rest of the innermost struct, sequence, map or interface
+    /// value, including its `End` event, or the rest of the current value
+    /// at the top level.
+    pub fn skip(&mut self) -> Result<(), Error> {
+        let depth = self.frames.len();
+        if depth == 0 {
+            while self.pending.is_some() || !self.frames.is_empty() {
+                self.next_raw()?;
+            }
+            return Ok(());
+        }
+        while self.frames.len() >= depth {
+            self.next_raw()?;
+        }
+        Ok(())
+    }
+
+    fn next_raw(&mut self) -> Result<Option<Raw>, Error> {
+        loop {
+            if let Some(type_id) = self.pending.take() {
+                return self.field_value(type_id).map(Some);
+            }
+            match self.frames.last_mut() {
+                Some(&mut Frame::Struct { type_id, field_no }) => {
+                    let delta = self.read(|msg| msg.read_uint())?;
+                    if delta == 0 {
+                        self.frames.pop();
+                        return Ok(Some(Raw::Event(Event::End)));
+                    }
+                    let field_no = field_no + delta as i64;
+                    if let Some(Frame::Struct { field_no: last, .. }) = self.frames.last_mut() {
+                        *last = field_no;
+                    }
+                    let field = match self.defs.lookup(type_id) {
+                        Some(WireType::Struct(struct_type)) => {
+                            struct_type.fields.get(field_no as usize)
+                        }
+                        _ => None,
+                    };
+                    let field = field.ok_or_else(|| {
+                        Error::deserialize(format!(
+                            "field number overflow ({}) on type {}",
+                            field_no, type_id.0
+                        ))
+                    })?;
+                    self.pending = Some(field.id);
+                    return Ok(Some(Raw::Field(type_id, field_no as usize)));
+                }
+                Some(Frame::Seq { elem, remaining }) => {
+                    if *remaining == 0 {
+                        self.frames.pop();
+                        return Ok(Some(Raw::Event(Event::End)));
+                    }
+                    *remaining -= 1;
+                    self.pending = Some(*elem);
+                }
+                Some(Frame::Map {
+                    key,
+                    elem,
+                    remaining,
+                    key_next,
+                }) => {
+                    if *key_next {
+                        if *remaining == 0 {
+                            self.frames.pop();
+                            return Ok(Some(Raw::Event(Event::End)));
+                        }
+                        *remaining -= 1;
+                        self.pending = Some(*key);
+                    } else {
+                        self.pending = Some(*elem);
+                    }
+                    *key_next = !*key_next;
+                }
+                Some(Frame::Interface { done }) => {
+                    if *done {
+                        self.frames.pop();
+                        return Ok(Some(Raw::Event(Event::End)));
+                    }
+                    *done = true;
+                }
+                None => return self.next_section(),
+            }
+        }
+    }
+
+    // moves on to the next section, reading any type definition in it
+    fn next_section(&mut self) -> Result<Option<Raw>, Error> {
+        if let Some(header) = self.section.take() {
+            self.buffer.advance(header.payload_range.end);
+        }
+        let header = match self.stream.read_section(&mut self.buffer)? {
+            Some(header) => header,
+            None => return Ok(None),
+        };
+        self.pos = header.payload_range.start;
+        let type_id = header.type_id;
+        let payload = &self.buffer.bytes()[header.payload_range.clone()];
+        self.section = Some(header);
+
+        if type_id < 0 {
+            let mut msg = Message::new(Cursor::new(payload));
+            de::define(&mut self.defs, &DEFAULT_OPTIONS, type_id, &mut msg)?;
+            let info = TypeInfo::lookup(TypeId(-type_id), &self.defs)
+                .ok_or_else(|| Error::deserialize("type definition not found"))?;
+            // nothing else is read from a definition section
+            self.pos += payload.len();
+            return Ok(Some(Raw::Event(Event::TypeDef(info))));
+        }
+
+        self.value(TypeId(type_id))?;
+        Ok(Some(Raw::Event(Event::Value(TypeId(type_id)))))
+    }
+
+    // prepares for a top-level (or interface) value: structs are encoded
+    // directly, everything else is preceded by a zero field delta
+    fn value(&mut self, type_id: TypeId) -> Result<(), Error> {
+        if let Some(WireType::Struct(_)) = self.defs.lookup(type_id) {
+            self.pending = Some(type_id);
+            return Ok(());
+        }
+        if self.read(|msg| msg.read_uint())? != 0 {
+            return Err(Error::deserialize("neither a singleton nor a struct value"));
+        }
+        self.pending = Some(type_id);
+        Ok(())
+    }
+
+    fn field_value(&mut self, type_id: TypeId) -> Result<Raw, Error> {
+        let event = match type_id {
+            TypeId::BOOL => Event::Bool(self.read(|msg| msg.read_bool())?),
+            TypeId::INT => Event::Int(self.read(|msg| msg.read_int())?),
+            TypeId::UINT => Event::Uint(self.read(|msg| msg.read_uint())?),
+            TypeId::FLOAT => Event::Float(self.read(|msg| msg.read_float())?),
+            TypeId::COMPLEX => {
+                let re = self.read(|msg| msg.read_float())?;
+                Event::Complex(re, self.read(|msg| msg.read_float())?)
+            }
+            TypeId::BYTES => return Ok(Raw::Bytes(self.bytes()?)),
+            TypeId::STRING => return Ok(Raw::String(self.bytes()?)),
+            TypeId::INTERFACE => {
+                let name = self.bytes()?;
+                if name.is_empty() {
+                    return Ok(Raw::Event(Event::Nil));
+                }
+                let concrete = self.read(|msg| msg.read_int())?;
+                if concrete < 0 {
+                    return Err(Error::deserialize(
+                        "type definitions inside interface values are not supported",
+                    ));
+                }
+                self.read(|msg| msg.read_bytes_len())?;
+                self.value(TypeId(concrete))?;
+                self.frames.push(Frame::Interface { done: false });
+                return Ok(Raw::StartInterface(name, TypeId(concrete)));
+            }
+            _ => match self.defs.lookup(type_id) {
+                Some(WireType::Struct(_)) => {
+                    self.frames.push(Frame::Struct {
+                        type_id,
+                        field_no: -1,
+                    });
+                    Event::StartStruct(type_id)
+                }
+                Some(WireType::Slice(slice_type)) => {
+                    let elem = slice_type.elem;
+                    let len = self.read_len()?;
+                    self.frames.push(Frame::Seq {
+                        elem,
+                        remaining: len,
+                    });
+                    Event::StartSeq(len)
+                }
+                Some(WireType::Array(array_type)) => {
+                    let elem = array_type.elem;
+                    let len = self.read_len()?;
+                    self.frames.push(Frame::Seq {
+                        elem,
+                        remaining: len,
+                    });
+                    Event::StartSeq(len)
+                }
+                Some(WireType::Map(map_type)) => {
+                    let (key, elem) = (map_type.key, map_type.elem);
+                    let len = self.read_len()?;
+                    self.frames.push(Frame::Map {
+                        key,
+                        elem,
+                        remaining: len,
+                        key_next: true,
+                    });
+                    Event::StartMap(len)
+                }
+                Some(WireType::GobEncoder(_)) => return Ok(Raw::Bytes(self.bytes()?)),
+                None => return Err(Error::deserialize(format!("unknown type id {}", type_id.0))),
+            },
+        };
+        Ok(Raw::Event(event))
+    }
+
+    fn read<T, F>(&mut self, f: F) -> Result<T, Error>
+    where
+        F: FnOnce(&mut Message<Cursor<&[u8]>>) -> Result<T, MessageReadError>,
+    {
+        read(&self.buffer, &self.section, &mut self.pos, f)
+    }
+
+    // reads a sequence length, which can never exceed the number of
+    // bytes left since every element takes up at least one byte
+    fn read_len(&mut self) -> Result<u64, Error> {
+        let len = self.read(|msg| msg.read_uint())?;
+        let end = self.section.as_ref().map_or(0, |h| h.payload_range.end);
+        if len > (end - self.pos) as u64 {
+            return Err(MessageReadError::Incomplete(None).into());
+        }
+        Ok(len)
+    }
+
+    fn bytes(&mut self) -> Result<Range<usize>, Error> {
+        let len = self.read(|msg| msg.read_bytes_len())?;
+        let start = self.pos;
+        self.pos += len;
+        Ok(start..self.pos)
+    }
+}
+
+// reads from the current section at `pos`, advancing it
+fn read<T, F>(
+    buffer: &Buffer,
+    section: &Option<SectionHeader>,
+    pos: &mut usize,
+    f: F,
+) -> Result<T, Error>
+where
+    F: FnOnce(&mut Message<Cursor<&[u8]>>) -> Result<T, MessageReadError>,
+{
+    let end = section.as_ref().map_or(0, |h| h.payload_range.end);
+    let mut msg = Message::new(Cursor::new(&buffer.bytes()[..end]));
+    msg.get_mut().set_position(*pos as u64);
+    let value = f(&mut msg)?;
+    *pos = msg.get_ref().position() as usize;
+    Ok(value)
+}
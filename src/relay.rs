@@ -0,0 +1,497 @@
+//! Relaying values between streams
+//!
+//! A `Relay` copies values from an upstream `StreamDeserializer` onto a
+//! downstream `StreamSerializer` without decoding them, e.g. to merge
+//! several Go connections into one:
+//!
+//! ```ignore
+//! let mut downstream = StreamSerializer::new(TcpStream::connect(addr)?);
+//! let mut relays: Vec<_> = upstreams
+//!     .into_iter()
+//!     .map(|conn| Relay::new(StreamDeserializer::new(conn)))
+//!     .collect();
+//! for relay in &mut relays {
+//!     relay.forward(&mut downstream)?;
+//! }
+//! ```
+//!
+//! Each upstream numbers its types on its own, so types are registered
+//! with the downstream schema as they are first needed, and values are
+//! sent under the downstream ids. Identical types from different upstreams
+//! (or registered by the serializer itself) share one id. Payloads are
+//! passed on byte for byte, unless they hold interface values, whose
+//! concrete type ids are translated as well.
+//!
+//! A `FieldFilter` drops, renames or redacts struct fields on the way,
+//! rewriting the type definitions sent downstream to match:
+//!
+//! ```ignore
+//! let filter = FieldFilter::new()
+//!     .drop("User", "Password")
+//!     .rename("User", "Mail", "Email")
+//!     .redact("*", "Token");
+//! let mut relay = Relay::with_filter(StreamDeserializer::new(conn), filter);
+//! ```
+//!
+//! Only values of types that are affected by the filter are taken apart
+//! and put back together; all others are still passed on as they are.
+//!
+//! Recursive types cannot be relayed, since the schema needs to know all
+//! types a type refers to before it can be registered.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{Cursor, Read};
+
+use bytes::Buf;
+use serde_schema::types::{Type, TypeId as _};
+
+use de::{RawValue, StreamDeserializer};
+use error::Error;
+use internal::de::skip_field_value;
+use internal::gob::{Message, MessageReadError};
+use internal::types::{FieldType, Types, WireType};
+use schema::{Schema, TypeId};
+use ser::{Output, StreamSerializer};
+use value::intern;
+
+use crate::{de, error, internal, schema, ser, value};
+
+/// What happens to a struct field on its way downstream.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FieldAction {
+    /// The field is removed from the type along with its values.
+    Drop,
+    /// The field is sent under another name.
+    Rename(String),
+    /// The field is kept, but its values are replaced by the zero value,
+    /// which gob leaves out.
+    Redact,
+}
+
+#[derive(Clone, Debug)]
+struct Rule {
+    type_name: String,
+    field: String,
+    action: FieldAction,
+}
+
+/// The struct fields to change while relaying, by type and field name.
+///
+/// A type name of `"*"` matches all struct types. Where several rules
+/// match a field, the first one added applies.
+#[derive(Clone, Debug, Default)]
+pub struct FieldFilter {
+    rules: Vec<Rule>,
+}
+
+impl FieldFilter {
+    pub fn new() -> FieldFilter {
+        FieldFilter::default()
+    }
+
+    pub fn drop(self, type_name: &str, field: &str) -> Self {
+        self.rule(type_name, field, FieldAction::Drop)
+    }
+
+    pub fn rename(self, type_name: &str, field: &str, to: &str) -> Self {
+        self.rule(type_name, field, FieldAction::Rename(to.to_owned()))
+    }
+
+    pub fn redact(self, type_name: &str, field: &str) -> Self {
+        self.rule(type_name, field, FieldAction::Redact)
+    }
+
+    pub fn rule(mut self, type_name: &str, field: &str, action: FieldAction) -> Self {
+        self.rules.push(Rule {
+            type_name: type_name.to_owned(),
+            field: field.to_owned(),
+            action,
+        });
+        self
+    }
+
+    /// The action for `field` of the struct type `type_name`, if any.
+    pub fn action(&self, type_name: &str, field: &str) -> Option<&FieldAction> {
+        self.rules
+            .iter()
+            .find(|rule| {
+                (rule.type_name == "*" || rule.type_name == type_name) && rule.field == field
+            })
+            .map(|rule| &rule.action)
+    }
+
+    fn affects(&self, type_name: &str, fields: &[FieldType]) -> bool {
+        fields
+            .iter()
+            .any(|field| self.action(type_name, &field.name).is_some())
+    }
+}
+
+/// Forwards the values of one upstream.
+pub struct Relay<R> {
+    upstream: StreamDeserializer<R>,
+    filter: FieldFilter,
+    // upstream type ids to downstream ones
+    ids: HashMap<TypeId, TypeId>,
+    // for each struct type, the downstream index of each upstream field,
+    // or `None` if its values are not sent
+    layouts: HashMap<TypeId, Vec<Option<usize>>>,
+    // whether values of a type need to be rewritten, because they may
+    // hold interface values or fields changed by the filter
+    rewrites: HashMap<TypeId, bool>,
+}
+
+impl<R> Relay<R> {
+    pub fn new(upstream: StreamDeserializer<R>) -> Self {
+        Relay::with_filter(upstream, FieldFilter::new())
+    }
+
+    /// Create a relay that changes struct fields according to `filter`.
+    pub fn with_filter(upstream: StreamDeserializer<R>, filter: FieldFilter) -> Self {
+        Relay {
+            upstream,
+            filter,
+            ids: HashMap::new(),
+            layouts: HashMap::new(),
+            rewrites: HashMap::new(),
+        }
+    }
+
+    /// The downstream id of an upstream type, once values of it (or of
+    /// types referring to it) were forwarded.
+    pub fn downstream_id(&self, upstream: TypeId) -> Option<TypeId> {
+        if is_builtin(upstream) {
+            return Some(upstream);
+        }
+        self.ids.get(&upstream).cloned()
+    }
+
+    pub fn get_ref(&self) -> &StreamDeserializer<R> {
+        &self.upstream
+    }
+
+    pub fn get_mut(&mut self) -> &mut StreamDeserializer<R> {
+        &mut self.upstream
+    }
+
+    pub fn into_inner(self) -> StreamDeserializer<R> {
+        self.upstream
+    }
+}
+
+impl<R: Read> Relay<R> {
+    /// Copies the next upstream value onto `downstream`, preceded by the
+    /// definitions of any types it needs that were not sent downstream
+    /// yet. Returns `false` at the end of the upstream.
+    pub fn forward<O: Output>(
+        &mut self,
+        downstream: &mut StreamSerializer<O>,
+    ) -> Result<bool, Error> {
+        let RawValue {
+            type_id,
+            payload,
+            defs,
+        } = match self.upstream.next_raw()? {
+            Some(raw) => raw,
+            None => return Ok(false),
+        };
+
+        let mut translator = Translator {
+            defs,
+            schema: downstream.schema_mut(),
+            filter: &self.filter,
+            ids: &mut self.ids,
+            layouts: &mut self.layouts,
+            pending: HashSet::new(),
+        };
+        let id = translator.translate(type_id)?;
+
+        let mut msg = Message::new(Vec::with_capacity(payload.len() + 9));
+        msg.write_int(id.0);
+        if needs_rewrite(type_id, defs, &self.filter, &mut self.rewrites) {
+            let mut input = Message::new(Cursor::new(payload));
+            translator.value(type_id, &mut input, &mut msg)?;
+        } else {
+            msg.get_mut().extend_from_slice(payload);
+        }
+
+        downstream.write_section(msg.into_inner())?;
+        Ok(true)
+    }
+
+    /// Forwards all remaining upstream values, returning how many there
+    /// were.
+    pub fn forward_all<O: Output>(
+        &mut self,
+        downstream: &mut StreamSerializer<O>,
+    ) -> Result<u64, Error> {
+        let mut count = 0;
+        while self.forward(downstream)? {
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+fn is_builtin(id: TypeId) -> bool {
+    id.0 <= TypeId::INTERFACE.0
+}
+
+// whether values of `type_id` may hold interface values, and thus type
+// ids, or struct fields changed by `filter`
+fn needs_rewrite(
+    type_id: TypeId,
+    defs: &Types,
+    filter: &FieldFilter,
+    memo: &mut HashMap<TypeId, bool>,
+) -> bool {
+    fn walk(
+        type_id: TypeId,
+        defs: &Types,
+        filter: &FieldFilter,
+        seen: &mut HashSet<TypeId>,
+    ) -> bool {
+        if type_id == TypeId::INTERFACE {
+            return true;
+        }
+        if is_builtin(type_id) || !seen.insert(type_id) {
+            return false;
+        }
+        match defs.lookup(type_id) {
+            Some(WireType::Array(array_type)) => walk(array_type.elem, defs, filter, seen),
+            Some(WireType::Slice(slice_type)) => walk(slice_type.elem, defs, filter, seen),
+            Some(WireType::Map(map_type)) => {
+                walk(map_type.key, defs, filter, seen) || walk(map_type.elem, defs, filter, seen)
+            }
+            Some(WireType::Struct(struct_type)) => {
+                filter.affects(&struct_type.common.name, &struct_type.fields)
+                    || struct_type
+                        .fields
+                        .iter()
+                        .any(|field| walk(field.id, defs, filter, seen))
+            }
+            Some(WireType::GobEncoder(_)) | None => false,
+        }
+    }
+
+    *memo
+        .entry(type_id)
+        .or_insert_with(|| walk(type_id, defs, filter, &mut HashSet::new()))
+}
+
+struct Translator<'a> {
+    defs: &'a Types,
+    schema: &'a mut Schema,
+    filter: &'a FieldFilter,
+    ids: &'a mut HashMap<TypeId, TypeId>,
+    layouts: &'a mut HashMap<TypeId, Vec<Option<usize>>>,
+    // types being translated, to detect recursion
+    pending: HashSet<TypeId>,
+}
+
+impl<'a> Translator<'a> {
+    // registers an upstream type and the types it refers to downstream
+    fn translate(&mut self, id: TypeId) -> Result<TypeId, Error> {
+        if is_builtin(id) {
+            return Ok(id);
+        }
+        if let Some(&downstream) = self.ids.get(&id) {
+            return Ok(downstream);
+        }
+        if !self.pending.insert(id) {
+            return Err(Error::serialize(format!(
+                "recursive type {} cannot be relayed",
+                id.0
+            )));
+        }
+
+        let def = self
+            .defs
+            .lookup(id)
+            .ok_or_else(|| Error::deserialize(format!("unknown type id {}", id.0)))?;
+        let ty = match def {
+            WireType::Array(array_type) => {
+                let elem = self.translate(array_type.elem)?;
+                Type::build().seq_type(Some(array_type.len as usize), elem)
+            }
+            WireType::Slice(slice_type) => {
+                let elem = self.translate(slice_type.elem)?;
+                Type::build().seq_type(None, elem)
+            }
+            WireType::Map(map_type) => {
+                let key = self.translate(map_type.key)?;
+                let elem = self.translate(map_type.elem)?;
+                Type::build().map_type(key, elem)
+            }
+            WireType::Struct(struct_type) => {
+                let type_name = &struct_type.common.name;
+                let mut fields = Vec::with_capacity(struct_type.fields.len());
+                let mut layout = Vec::with_capacity(struct_type.fields.len());
+                for field in struct_type.fields.iter() {
+                    let (name, sent) = match self.filter.action(type_name, &field.name) {
+                        Some(FieldAction::Drop) => {
+                            layout.push(None);
+                            continue;
+                        }
+                        Some(FieldAction::Rename(name)) => (&name[..], true),
+                        Some(FieldAction::Redact) => (&field.name[..], false),
+                        None => (&field.name[..], true),
+                    };
+                    layout.push(if sent { Some(fields.len()) } else { None });
+                    fields.push((intern(name), self.translate(field.id)?));
+                }
+                self.layouts.insert(id, layout);
+
+                let mut builder = Type::build().struct_type(intern(type_name), fields.len());
+                for (name, field_id) in fields {
+                    builder = builder.field(name, field_id);
+                }
+                builder.end()
+            }
+            WireType::GobEncoder(gob_encoder_type) => Type::build()
+                .tuple_struct_type(intern(&gob_encoder_type.common.name), 2)
+                .element(TypeId::UNIT)
+                .element(TypeId::UNIT)
+                .end(),
+        };
+        let downstream = ::serde_schema::Schema::register_type(&mut *self.schema, ty)?;
+
+        self.pending.remove(&id);
+        self.ids.insert(id, downstream);
+        Ok(downstream)
+    }
+
+    // copies a top-level (or interface) value: structs are encoded
+    // directly, everything else is preceded by a zero field delta
+    fn value(
+        &mut self,
+        type_id: TypeId,
+        input: &mut Message<Cursor<&[u8]>>,
+        out: &mut Message<Vec<u8>>,
+    ) -> Result<(), Error> {
+        if let Some(WireType::Struct(_)) = self.defs.lookup(type_id) {
+            return self.field_value(type_id, input, out);
+        }
+        if copy(input, out, |msg| msg.read_uint())? != 0 {
+            return Err(Error::deserialize("neither a singleton nor a struct value"));
+        }
+        self.field_value(type_id, input, out)
+    }
+
+    // copies a value, translating the concrete types of interface values
+    fn field_value(
+        &mut self,
+        type_id: TypeId,
+        input: &mut Message<Cursor<&[u8]>>,
+        out: &mut Message<Vec<u8>>,
+    ) -> Result<(), Error> {
+        let defs = self.defs;
+        match type_id {
+            TypeId::BOOL | TypeId::INT | TypeId::UINT | TypeId::FLOAT => {
+                copy(input, out, |msg| msg.read_uint())?;
+            }
+            TypeId::BYTES | TypeId::STRING => copy_bytes(input, out)?,
+            TypeId::COMPLEX => copy(input, out, |msg| msg.skip_uints(2))?,
+            TypeId::INTERFACE => {
+                let name_len = copy(input, out, |msg| msg.read_bytes_len())?;
+                if name_len == 0 {
+                    return Ok(());
+                }
+                copy(input, out, |msg| {
+                    msg.get_mut().advance(name_len);
+                    Ok(())
+                })?;
+                let concrete = TypeId(input.read_int()?);
+                if concrete.0 < 0 {
+                    return Err(Error::deserialize(
+                        "type definitions inside interface values are not supported",
+                    ));
+                }
+                out.write_int(self.translate(concrete)?.0);
+                input.read_bytes_len()?;
+                let mut inner = Message::new(Vec::new());
+                self.value(concrete, input, &mut inner)?;
+                out.write_bytes(inner.get_ref());
+            }
+            _ => match defs.lookup(type_id) {
+                Some(WireType::Array(array_type)) => {
+                    let len = copy(input, out, |msg| msg.read_uint())?;
+                    for _ in 0..len {
+                        self.field_value(array_type.elem, input, out)?;
+                    }
+                }
+                Some(WireType::Slice(slice_type)) => {
+                    let len = copy(input, out, |msg| msg.read_uint())?;
+                    for _ in 0..len {
+                        self.field_value(slice_type.elem, input, out)?;
+                    }
+                }
+                Some(WireType::Map(map_type)) => {
+                    let len = copy(input, out, |msg| msg.read_uint())?;
+                    for _ in 0..len {
+                        self.field_value(map_type.key, input, out)?;
+                        self.field_value(map_type.elem, input, out)?;
+                    }
+                }
+                Some(WireType::Struct(struct_type)) => {
+                    let mut field_no = -1i64;
+                    let mut sent_no = -1i64;
+                    loop {
+                        let delta = input.read_uint()?;
+                        if delta == 0 {
+                            out.write_uint(0);
+                            break;
+                        }
+                        field_no += delta as i64;
+                        let field = struct_type.fields.get(field_no as usize).ok_or_else(|| {
+                            Error::deserialize(format!(
+                                "field number overflow ({}) on type {}",
+                                field_no, struct_type.common.name
+                            ))
+                        })?;
+                        let index = match self.layouts.get(&type_id) {
+                            Some(layout) => layout[field_no as usize],
+                            None => Some(field_no as usize),
+                        };
+                        match index {
+                            Some(index) => {
+                                out.write_uint((index as i64 - sent_no) as u64);
+                                sent_no = index as i64;
+                                self.field_value(field.id, input, out)?;
+                            }
+                            None => skip_field_value(field.id, defs, input)?,
+                        }
+                    }
+                }
+                Some(WireType::GobEncoder(_)) => copy_bytes(input, out)?,
+                None => return Err(Error::deserialize(format!("unknown type id {}", type_id.0))),
+            },
+        }
+        Ok(())
+    }
+}
+
+// reads from `input`, copying the bytes read to `out`
+fn copy<T, F>(
+    input: &mut Message<Cursor<&[u8]>>,
+    out: &mut Message<Vec<u8>>,
+    read: F,
+) -> Result<T, Error>
+where
+    F: FnOnce(&mut Message<Cursor<&[u8]>>) -> Result<T, MessageReadError>,
+{
+    let start = input.get_ref().position() as usize;
+    let value = read(input)?;
+    let end = input.get_ref().position() as usize;
+    out.get_mut()
+        .extend_from_slice(&input.get_ref().get_ref()[start..end]);
+    Ok(value)
+}
+
+fn copy_bytes(input: &mut Message<Cursor<&[u8]>>, out: &mut Message<Vec<u8>>) -> Result<(), Error> {
+    copy(input, out, |msg| {
+        let len = msg.read_bytes_len()?;
+        msg.get_mut().advance(len);
+        Ok(())
+    })
+}
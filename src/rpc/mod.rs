@@ -0,0 +1,212 @@
+//! Go `net/rpc` compatible codecs
+//!
+//! `net/rpc` exchanges a header followed by a body for every call, in both
+//! directions, all on one gob stream per direction:
+//!
+//! ```text
+//! client -> server: Request, args, Request, args, ...
+//! server -> client: Response, reply, Response, reply, ...
+//! ```
+//!
+//! A call is named by `Service.Method`, and its sequence number is chosen
+//! by the client and echoed in the response. Failed calls carry an error
+//! string in the response header and an empty struct as the body.
+
+use std::io::{Read, Write};
+
+use serde::de::{DeserializeOwned, IgnoredAny};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use serde_schema::types::Type;
+use serde_schema::{Schema, SchemaSerialize};
+
+use de::StreamDeserializer;
+use error::Error;
+use ser::{OutputWrite, StreamSerializer};
+
+use crate::{de, error, ser};
+
+#[cfg(feature = "futures")]
+mod nonblocking;
+#[cfg(feature = "futures")]
+pub use self::nonblocking::{AsyncClientCodec, AsyncServerCodec};
+
+/// The header of a call, as sent by the client.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Request {
+    #[serde(rename = "ServiceMethod")]
+    pub service_method: String,
+    #[serde(rename = "Seq")]
+    pub seq: u64,
+}
+
+impl SchemaSerialize for Request {
+    fn schema_register<S: Schema>(schema: &mut S) -> Result<S::TypeId, S::Error> {
+        let string_type = String::schema_register(schema)?;
+        let seq_type = u64::schema_register(schema)?;
+        schema.register_type(
+            Type::build()
+                .struct_type("Request", 2)
+                .field("ServiceMethod", string_type)
+                .field("Seq", seq_type)
+                .end(),
+        )
+    }
+}
+
+/// The header of a reply, as sent by the server.
+///
+/// An empty `error` means the call succeeded.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Response {
+    #[serde(rename = "ServiceMethod")]
+    pub service_method: String,
+    #[serde(rename = "Seq")]
+    pub seq: u64,
+    #[serde(rename = "Error")]
+    pub error: String,
+}
+
+impl SchemaSerialize for Response {
+    fn schema_register<S: Schema>(schema: &mut S) -> Result<S::TypeId, S::Error> {
+        let string_type = String::schema_register(schema)?;
+        let seq_type = u64::schema_register(schema)?;
+        schema.register_type(
+            Type::build()
+                .struct_type("Response", 3)
+                .field("ServiceMethod", string_type.clone())
+                .field("Seq", seq_type)
+                .field("Error", string_type)
+                .end(),
+        )
+    }
+}
+
+// the body sent along with errors, Go's `invalidRequest`
+struct InvalidRequest;
+
+impl Serialize for InvalidRequest {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_struct("invalidRequest", 0)?.end()
+    }
+}
+
+impl SchemaSerialize for InvalidRequest {
+    fn schema_register<S: Schema>(schema: &mut S) -> Result<S::TypeId, S::Error> {
+        schema.register_type(Type::build().struct_type("invalidRequest", 0).end())
+    }
+}
+
+/// The client side of a `net/rpc` connection.
+pub struct ClientCodec<R, W> {
+    de: StreamDeserializer<R>,
+    ser: StreamSerializer<OutputWrite<W>>,
+}
+
+impl<R: Read, W: Write> ClientCodec<R, W> {
+    pub fn new(reader: R, writer: W) -> Self {
+        ClientCodec {
+            de: StreamDeserializer::new(reader),
+            ser: StreamSerializer::new(writer),
+        }
+    }
+
+    /// Sends a call, flushing the writer afterwards.
+    pub fn write_request<B>(&mut self, header: &Request, body: &B) -> Result<(), Error>
+    where
+        B: SchemaSerialize,
+    {
+        self.ser.serialize(header)?;
+        self.ser.serialize(body)?;
+        self.ser.get_mut().get_mut().flush()?;
+        Ok(())
+    }
+
+    /// Reads the header of the next reply, or `None` if the server closed
+    /// the connection.
+    pub fn read_response_header(&mut self) -> Result<Option<Response>, Error> {
+        self.de.deserialize()
+    }
+
+    /// Reads the body of the reply whose header was just read.
+    pub fn read_response_body<T: DeserializeOwned>(&mut self) -> Result<T, Error> {
+        read_body(&mut self.de)
+    }
+
+    /// Skips the body of the reply whose header was just read, as needed
+    /// for failed calls.
+    pub fn skip_response_body(&mut self) -> Result<(), Error> {
+        self.read_response_body::<IgnoredAny>().map(|_| ())
+    }
+
+    pub fn into_inner(self) -> (R, W) {
+        (self.de.into_inner(), self.ser.into_writer())
+    }
+}
+
+/// The server side of a `net/rpc` connection.
+pub struct ServerCodec<R, W> {
+    de: StreamDeserializer<R>,
+    ser: StreamSerializer<OutputWrite<W>>,
+}
+
+impl<R: Read, W: Write> ServerCodec<R, W> {
+    pub fn new(reader: R, writer: W) -> Self {
+        ServerCodec {
+            de: StreamDeserializer::new(reader),
+            ser: StreamSerializer::new(writer),
+        }
+    }
+
+    /// Reads the header of the next call, or `None` if the client closed
+    /// the connection.
+    pub fn read_request_header(&mut self) -> Result<Option<Request>, Error> {
+        self.de.deserialize()
+    }
+
+    /// Reads the arguments of the call whose header was just read.
+    pub fn read_request_body<T: DeserializeOwned>(&mut self) -> Result<T, Error> {
+        read_body(&mut self.de)
+    }
+
+    /// Skips the arguments of the call whose header was just read, e.g.
+    /// when the method is unknown.
+    pub fn skip_request_body(&mut self) -> Result<(), Error> {
+        self.read_request_body::<IgnoredAny>().map(|_| ())
+    }
+
+    /// Sends the reply to a successful call, flushing the writer afterwards.
+    pub fn write_response<B>(&mut self, header: &Response, body: &B) -> Result<(), Error>
+    where
+        B: SchemaSerialize,
+    {
+        self.ser.serialize(header)?;
+        self.ser.serialize(body)?;
+        self.ser.get_mut().get_mut().flush()?;
+        Ok(())
+    }
+
+    /// Sends the reply to a failed call, flushing the writer afterwards.
+    pub fn write_error(&mut self, request: &Request, error: &str) -> Result<(), Error> {
+        let header = error_response(request, error);
+        self.write_response(&header, &InvalidRequest)
+    }
+
+    pub fn into_inner(self) -> (R, W) {
+        (self.de.into_inner(), self.ser.into_writer())
+    }
+}
+
+fn read_body<R: Read, T: DeserializeOwned>(de: &mut StreamDeserializer<R>) -> Result<T, Error> {
+    de.deserialize()?
+        .ok_or_else(|| Error::deserialize("connection closed before body"))
+}
+
+fn error_response(request: &Request, error: &str) -> Response {
+    Response {
+        service_method: request.service_method.clone(),
+        seq: request.seq,
+        error: error.to_string(),
+    }
+}
@@ -0,0 +1,152 @@
+use futures::future::poll_fn;
+use futures::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use serde::de::{DeserializeOwned, IgnoredAny};
+use serde_schema::SchemaSerialize;
+
+use async_io::ValueReader;
+use de::Decoder;
+use error::Error;
+use ser::Encoder;
+
+use crate::{async_io, de, error, ser};
+
+use super::{error_response, InvalidRequest, Request, Response};
+
+/// The client side of a `net/rpc` connection over asynchronous I/O.
+pub struct AsyncClientCodec<R, W> {
+    reader: ValueReader<R>,
+    writer: W,
+    encoder: Encoder,
+}
+
+impl<R, W> AsyncClientCodec<R, W>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    pub fn new(reader: R, writer: W) -> Self {
+        AsyncClientCodec {
+            reader: ValueReader::new(reader, Decoder::new()),
+            writer,
+            encoder: Encoder::new(),
+        }
+    }
+
+    /// Sends a call, flushing the writer afterwards.
+    pub async fn write_request<B>(&mut self, header: &Request, body: &B) -> Result<(), Error>
+    where
+        B: SchemaSerialize,
+    {
+        write_pair(&mut self.writer, &mut self.encoder, header, body).await
+    }
+
+    /// Reads the header of the next reply, or `None` if the server closed
+    /// the connection.
+    pub async fn read_response_header(&mut self) -> Result<Option<Response>, Error> {
+        poll_fn(|cx| self.reader.poll_next(cx)).await.transpose()
+    }
+
+    /// Reads the body of the reply whose header was just read.
+    pub async fn read_response_body<T: DeserializeOwned>(&mut self) -> Result<T, Error> {
+        read_body(&mut self.reader).await
+    }
+
+    /// Skips the body of the reply whose header was just read, as needed
+    /// for failed calls.
+    pub async fn skip_response_body(&mut self) -> Result<(), Error> {
+        read_body::<_, IgnoredAny>(&mut self.reader)
+            .await
+            .map(|_| ())
+    }
+
+    pub fn into_inner(self) -> (R, W) {
+        (self.reader.reader, self.writer)
+    }
+}
+
+/// The server side of a `net/rpc` connection over asynchronous I/O.
+pub struct AsyncServerCodec<R, W> {
+    reader: ValueReader<R>,
+    writer: W,
+    encoder: Encoder,
+}
+
+impl<R, W> AsyncServerCodec<R, W>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    pub fn new(reader: R, writer: W) -> Self {
+        AsyncServerCodec {
+            reader: ValueReader::new(reader, Decoder::new()),
+            writer,
+            encoder: Encoder::new(),
+        }
+    }
+
+    /// Reads the header of the next call, or `None` if the client closed
+    /// the connection.
+    pub async fn read_request_header(&mut self) -> Result<Option<Request>, Error> {
+        poll_fn(|cx| self.reader.poll_next(cx)).await.transpose()
+    }
+
+    /// Reads the arguments of the call whose header was just read.
+    pub async fn read_request_body<T: DeserializeOwned>(&mut self) -> Result<T, Error> {
+        read_body(&mut self.reader).await
+    }
+
+    /// Skips the arguments of the call whose header was just read, e.g.
+    /// when the method is unknown.
+    pub async fn skip_request_body(&mut self) -> Result<(), Error> {
+        read_body::<_, IgnoredAny>(&mut self.reader)
+            .await
+            .map(|_| ())
+    }
+
+    /// Sends the reply to a successful call, flushing the writer afterwards.
+    pub async fn write_response<B>(&mut self, header: &Response, body: &B) -> Result<(), Error>
+    where
+        B: SchemaSerialize,
+    {
+        write_pair(&mut self.writer, &mut self.encoder, header, body).await
+    }
+
+    /// Sends the reply to a failed call, flushing the writer afterwards.
+    pub async fn write_error(&mut self, request: &Request, error: &str) -> Result<(), Error> {
+        let header = error_response(request, error);
+        self.write_response(&header, &InvalidRequest).await
+    }
+
+    pub fn into_inner(self) -> (R, W) {
+        (self.reader.reader, self.writer)
+    }
+}
+
+async fn read_body<R, T>(reader: &mut ValueReader<R>) -> Result<T, Error>
+where
+    R: AsyncRead + Unpin,
+    T: DeserializeOwned,
+{
+    poll_fn(|cx| reader.poll_next(cx))
+        .await
+        .unwrap_or_else(|| Err(Error::deserialize("connection closed before body")))
+}
+
+async fn write_pair<W, H, B>(
+    writer: &mut W,
+    encoder: &mut Encoder,
+    header: &H,
+    body: &B,
+) -> Result<(), Error>
+where
+    W: AsyncWrite + Unpin,
+    H: SchemaSerialize,
+    B: SchemaSerialize,
+{
+    let header = encoder.encode(header)?;
+    let body = encoder.encode(body)?;
+    writer.write_all(&header).await?;
+    writer.write_all(&body).await?;
+    writer.flush().await?;
+    Ok(())
+}
@@ -1,6 +1,6 @@
 //! Schema management
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::ops::Deref;
 use std::sync::Arc;
 
@@ -9,12 +9,19 @@ use serde::{Deserialize, Deserializer};
 use serde::{Serialize, Serializer};
 use serde_schema::types::Type;
 
+use crate::compat::ExternalEncoding;
+use crate::internal::ser::encode_empty_value;
 use crate::internal::ser::serialize_wire_types::SerializeWireTypes;
+use crate::value::intern;
 use error::Error;
 use other_ser::{Output, OutputPart};
 
 use crate::{error, internal, ser as other_ser};
 
+pub mod compat;
+#[cfg(feature = "json")]
+pub(crate) mod json;
+
 #[derive(Clone)]
 pub(crate) enum SchemaType {
     Builtin(&'static Type<TypeId>),
@@ -37,23 +44,417 @@ impl Deref for SchemaType {
 
 const CUSTOM_TYPE_ID_OFFSET: i64 = 65;
 
+/// How `Option`s are encoded.
+///
+/// Go has no option type, and a nil pointer is sent by omitting its field,
+/// just like a zero value. Whichever encoding is used, both ends of a stream
+/// have to agree on it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OptionEncoding {
+    /// Like a Go pointer: `Some` is sent as its value and `None` as the zero
+    /// value, so struct fields holding either `None` or `Some` of a zero
+    /// value are omitted and decode as `None`.
+    #[default]
+    Pointer,
+    /// As a slice of zero or one elements, so that `Some` of a zero value
+    /// survives a round-trip between two Rust ends. Go sees a `[]T`.
+    Slice,
+}
+
+/// How enums are encoded.
+///
+/// Either way an enum is sent as a struct with a field for each variant, of
+/// which only the one of the variant the value holds is set. Go can decode
+/// it into a struct with a pointer field for each variant.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EnumEncoding {
+    /// Only the field of the variant is sent. Unit variants are not
+    /// supported.
+    #[default]
+    External,
+    /// A string field named `Tag` comes first, holding the name of the
+    /// variant, followed by the field of the variant. Unit variants have no
+    /// field of their own and only send their tag.
+    ///
+    /// `ser::go_declarations` writes out the matching Go types.
+    Adjacent,
+}
+
+/// The name of the field holding the variant name of enums encoded with
+/// `EnumEncoding::Adjacent`.
+pub const ENUM_TAG: &str = "Tag";
+
+// types encoding themselves (see `compat::GobEncode`) are registered as
+// tuple structs of `()`, which has no representation of its own, and the
+// type of their encoding: `()` again for a `GobEncoder`, bytes for a
+// `BinaryMarshaler` and a string for a `TextMarshaler`
+pub(crate) fn external_encoding(ty: &Type<TypeId>) -> Option<ExternalEncoding> {
+    use serde_schema::types::TypeId as _;
+
+    let elements = match ty {
+        Type::TupleStruct(tuple_struct_type) => tuple_struct_type.element_types(),
+        _ => return None,
+    };
+    match *elements {
+        [TypeId::UNIT, TypeId::UNIT] => Some(ExternalEncoding::Gob),
+        [TypeId::UNIT, TypeId::BYTES] => Some(ExternalEncoding::Binary),
+        [TypeId::UNIT, TypeId::STRING] => Some(ExternalEncoding::Text),
+        _ => None,
+    }
+}
+
+fn type_name(ty: &Type<TypeId>) -> Option<&str> {
+    match ty {
+        Type::Struct(struct_type) => Some(struct_type.name()),
+        Type::Enum(enum_type) => Some(enum_type.name()),
+        Type::TupleStruct(tuple_struct_type) => Some(tuple_struct_type.name()),
+        _ => None,
+    }
+}
+
+// how many ids the definitions of `ty` take: enums define a struct for each
+// struct variant, with the ids following the one of the enum
+fn ids_needed(ty: &Type<TypeId>) -> usize {
+    match ty {
+        Type::Enum(enum_type) => {
+            1 + enum_type
+                .variants()
+                .iter()
+                .filter(|variant| variant.as_struct_variant().is_some())
+                .count()
+        }
+        _ => 1,
+    }
+}
+
+#[derive(Clone)]
 pub struct Schema {
     pending_wire_types: Vec<Vec<u8>>,
+    options: OptionEncoding,
+    enums: EnumEncoding,
+    adjacent_enums: BTreeSet<TypeId>,
     next_type_id: TypeId,
     schema_types: Vec<(TypeId, Arc<Type<TypeId>>)>,
     schema_types_reverse: BTreeMap<Arc<Type<TypeId>>, TypeId>,
+    // ids pinned by `assign_id`, by type name
+    pinned: BTreeMap<String, TypeId>,
+    // all ids handed out so far, including those of enum variant structs
+    taken: BTreeSet<TypeId>,
+    // structs to embed declared by `embed`, by the name of the struct
+    // embedding them
+    embeds: BTreeMap<String, Vec<(String, Vec<String>)>>,
+    // where the fields of structs with embedded structs are sent
+    layouts: BTreeMap<TypeId, Arc<[FieldSlot]>>,
+    // how the fields of structs and struct variants are written, by the id
+    // of the struct or enum and the number of the variant
+    plans: BTreeMap<(TypeId, Option<u32>), Arc<[FieldPlan]>>,
+}
+
+/// Where a field of a struct with embedded structs is sent: as the field
+/// of the given number, or as the field of the given number of the struct
+/// embedded as the field of the given number.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum FieldSlot {
+    Direct(usize),
+    Embedded(usize, usize),
+}
+
+/// A field of a struct as it is written, resolved once when the struct is
+/// registered instead of for each value.
+#[derive(Clone, Debug)]
+pub(crate) struct FieldPlan {
+    pub type_id: TypeId,
+    pub slot: FieldSlot,
+    // the encoding of the zero value of the field, for custom types that
+    // have one
+    zero: Option<Arc<[u8]>>,
+}
+
+impl FieldPlan {
+    /// The encoding written for a `None` field, instead of walking the
+    /// schema for the zero value of its type. `None` if the zero value is
+    /// not known ahead, e.g. for builtin types, which are cheap to write.
+    pub fn zero(&self) -> Option<&[u8]> {
+        self.zero.as_deref()
+    }
+}
+
+// plans the fields of a struct, sent directly unless `layout` says otherwise
+fn plan_fields<I>(schema: &Schema, field_types: I, layout: Option<&[FieldSlot]>) -> Arc<[FieldPlan]>
+where
+    I: Iterator<Item = TypeId>,
+{
+    field_types
+        .enumerate()
+        .map(|(idx, type_id)| FieldPlan {
+            type_id,
+            slot: layout.map_or(FieldSlot::Direct(idx), |layout| layout[idx]),
+            zero: planned_zero(schema, type_id),
+        })
+        .collect()
+}
+
+// the zero value of a custom type, if all types it is made of are known
+fn planned_zero(schema: &Schema, type_id: TypeId) -> Option<Arc<[u8]>> {
+    if type_id.is_builtin() {
+        return None;
+    }
+    encode_empty_value(schema, type_id).ok().map(Arc::from)
 }
 
 impl Schema {
     pub fn new() -> Schema {
         Schema {
             pending_wire_types: Vec::new(),
+            options: OptionEncoding::Pointer,
+            enums: EnumEncoding::External,
+            adjacent_enums: BTreeSet::new(),
             next_type_id: TypeId(CUSTOM_TYPE_ID_OFFSET),
             schema_types: Vec::new(),
             schema_types_reverse: BTreeMap::new(),
+            pinned: BTreeMap::new(),
+            taken: BTreeSet::new(),
+            embeds: BTreeMap::new(),
+            layouts: BTreeMap::new(),
+            plans: BTreeMap::new(),
+        }
+    }
+
+    /// Pins the type named `name` to type id `id`, instead of the next
+    /// free id at the time it is registered.
+    ///
+    /// Ids are otherwise assigned in the order types are first used, like
+    /// Go does, so the ids of a type differ between streams writing types
+    /// in a different order. Pinning keeps them stable, e.g. for
+    /// definitions exported out-of-band or cached by peers. Types not
+    /// pinned are assigned ids around the pinned ones. An enum with struct
+    /// variants takes the ids following its own for those.
+    ///
+    /// Fails if `id` is below 65, where Go's builtin types live, if it is
+    /// pinned or taken already, or if `name` was registered under another
+    /// id. Registering a type pinned to an id taken in the meantime, e.g.
+    /// by a variant struct of another pinned enum, fails as well.
+    pub fn assign_id(&mut self, name: &str, id: i64) -> Result<TypeId, Error> {
+        let id = TypeId(id);
+        if id.0 < CUSTOM_TYPE_ID_OFFSET {
+            return Err(Error::serialize(format!(
+                "type id {} is reserved for builtin types",
+                id.0
+            )));
+        }
+        if let Some(registered) = self.registered_id(name) {
+            if registered == id {
+                self.pinned.insert(name.to_owned(), id);
+                return Ok(id);
+            }
+            return Err(Error::serialize(format!(
+                "type {} already registered with id {}",
+                name, registered.0
+            )));
+        }
+        if let Some((other, _)) = self.pinned.iter().find(|(_, pinned)| **pinned == id) {
+            if other != name {
+                return Err(Error::serialize(format!(
+                    "type id {} already pinned to {}",
+                    id.0, other
+                )));
+            }
+        }
+        if self.taken.contains(&id) {
+            return Err(Error::serialize(format!("type id {} already taken", id.0)));
+        }
+        self.pinned.insert(name.to_owned(), id);
+        Ok(id)
+    }
+
+    /// Sends the fields named `fields` of the struct named `outer` as a
+    /// struct named `embedded`, embedded in `outer` like Go embeds structs.
+    ///
+    /// This lets a flat Rust struct stand in for a Go struct that embeds
+    /// another one; decoders get the fields back out with
+    /// `de::DecodeOptions::flatten_embedded`. The fields have to follow one
+    /// another in `outer`, and the embedded struct takes the place of the
+    /// first of them. Several structs can be embedded into the same one.
+    ///
+    /// Fails if `outer` was registered already.
+    pub fn embed(&mut self, outer: &str, embedded: &str, fields: &[&str]) -> Result<(), Error> {
+        if self.registered_id(outer).is_some() {
+            return Err(Error::serialize(format!(
+                "type {} already registered",
+                outer
+            )));
+        }
+        let fields = fields.iter().map(|field| (*field).to_owned()).collect();
+        self.embeds
+            .entry(outer.to_owned())
+            .or_default()
+            .push((embedded.to_owned(), fields));
+        Ok(())
+    }
+
+    // where each field of `ty` is sent, along with the struct type sent in
+    // its place, with the embedded structs registered
+    fn embedded_layout(
+        &mut self,
+        ty: &Type<TypeId>,
+        embeds: &[(String, Vec<String>)],
+    ) -> Result<(Type<TypeId>, Arc<[FieldSlot]>), Error> {
+        let struct_type = match ty {
+            Type::Struct(struct_type) => struct_type,
+            _ => {
+                return Err(Error::serialize(format!(
+                    "cannot embed into {}, which is not a struct",
+                    type_name(ty).unwrap_or_default()
+                )))
+            }
+        };
+        let fields = struct_type.fields();
+        let mut slots = Vec::with_capacity(fields.len());
+        let mut sent: Vec<(&'static str, TypeId)> = Vec::new();
+        let mut pos = 0;
+        while pos < fields.len() {
+            let field = &fields[pos];
+            let embed = embeds
+                .iter()
+                .find(|(_, names)| names.iter().any(|name| name == field.name()));
+            let (embedded, names) = match embed {
+                Some(embed) => embed,
+                None => {
+                    slots.push(FieldSlot::Direct(sent.len()));
+                    sent.push((intern(field.name()), *field.field_type()));
+                    pos += 1;
+                    continue;
+                }
+            };
+            let group = &fields[pos..(pos + names.len()).min(fields.len())];
+            if group.len() != names.len()
+                || !group
+                    .iter()
+                    .all(|field| names.iter().any(|name| name == field.name()))
+            {
+                return Err(Error::serialize(format!(
+                    "fields of {} embedded into {} do not follow one another",
+                    embedded,
+                    struct_type.name()
+                )));
+            }
+            let mut builder = Type::build().struct_type(intern(embedded), group.len());
+            for (idx, field) in group.iter().enumerate() {
+                slots.push(FieldSlot::Embedded(sent.len(), idx));
+                builder = builder.field(intern(field.name()), *field.field_type());
+            }
+            let id = ::serde_schema::Schema::register_type(self, builder.end())?;
+            sent.push((intern(embedded), id));
+            pos += group.len();
+        }
+
+        let mut builder = Type::build().struct_type(intern(struct_type.name()), sent.len());
+        for (name, id) in sent {
+            builder = builder.field(name, id);
+        }
+        Ok((builder.end(), slots.into()))
+    }
+
+    // where the fields of the struct `id` are sent, if it embeds structs
+    pub(crate) fn layout(&self, id: TypeId) -> Option<Arc<[FieldSlot]>> {
+        self.layouts.get(&id).cloned()
+    }
+
+    /// The fields of the struct registered as `id`, as they are written.
+    pub(crate) fn struct_plan(&self, id: TypeId) -> Option<Arc<[FieldPlan]>> {
+        self.plans.get(&(id, None)).cloned()
+    }
+
+    /// The fields of struct variant `variant_idx` of the enum registered as
+    /// `id`, as they are written.
+    pub(crate) fn variant_plan(&self, id: TypeId, variant_idx: u32) -> Option<Arc<[FieldPlan]>> {
+        self.plans.get(&(id, Some(variant_idx))).cloned()
+    }
+
+    fn insert_plans(&mut self, id: TypeId, ty: &Type<TypeId>) {
+        match ty {
+            Type::Struct(struct_type) => {
+                let field_types = struct_type.fields().iter().map(|field| *field.field_type());
+                let layout = self.layouts.get(&id).map(|layout| &**layout);
+                let plan = plan_fields(self, field_types, layout);
+                self.plans.insert((id, None), plan);
+            }
+            Type::Enum(enum_type) => {
+                for (idx, variant) in enum_type.variants().iter().enumerate() {
+                    if let Some(struct_variant) = variant.as_struct_variant() {
+                        let field_types = struct_variant
+                            .fields()
+                            .iter()
+                            .map(|field| *field.field_type());
+                        let plan = plan_fields(self, field_types, None);
+                        self.plans.insert((id, Some(idx as u32)), plan);
+                    }
+                }
+            }
+            _ => {}
         }
     }
 
+    // the id a named type was registered under
+    fn registered_id(&self, name: &str) -> Option<TypeId> {
+        self.schema_types
+            .iter()
+            .find(|(_, ty)| type_name(ty) == Some(name))
+            .map(|(id, _)| *id)
+    }
+
+    // the first id from which `count` ids are neither taken nor pinned
+    fn next_free_ids(&self, count: usize) -> TypeId {
+        let mut id = self.next_type_id;
+        loop {
+            let end = TypeId(id.0 + count as i64);
+            match (id.0..end.0)
+                .map(TypeId)
+                .find(|id| self.taken.contains(id) || self.pinned.values().any(|p| p == id))
+            {
+                Some(blocked) => id = blocked.next(),
+                None => return id,
+            }
+        }
+    }
+
+    /// Sets how `Option`s are encoded, see `OptionEncoding`.
+    ///
+    /// Only affects types registered from now on.
+    pub fn set_option_encoding(&mut self, options: OptionEncoding) {
+        self.options = options;
+    }
+
+    pub fn option_encoding(&self) -> OptionEncoding {
+        self.options
+    }
+
+    /// Sets how enums are encoded, see `EnumEncoding`.
+    ///
+    /// Only affects enums registered from now on; enums that already are
+    /// keep their encoding.
+    pub fn set_enum_encoding(&mut self, enums: EnumEncoding) {
+        self.enums = enums;
+    }
+
+    pub fn enum_encoding(&self) -> EnumEncoding {
+        self.enums
+    }
+
+    // the encoding the enum `id` was registered with
+    pub(crate) fn enum_encoding_of(&self, id: TypeId) -> EnumEncoding {
+        if self.adjacent_enums.contains(&id) {
+            EnumEncoding::Adjacent
+        } else {
+            EnumEncoding::External
+        }
+    }
+
+    // all types registered so far, in the order of their ids
+    pub(crate) fn types(&self) -> &[(TypeId, Arc<Type<TypeId>>)] {
+        &self.schema_types
+    }
+
     #[inline]
     pub(crate) fn lookup(&self, id: TypeId) -> Option<SchemaType> {
         if id.0 < CUSTOM_TYPE_ID_OFFSET {
@@ -82,10 +483,13 @@ impl ::serde_schema::Schema for Schema {
     type Error = Error;
 
     fn register_type(&mut self, ty: Type<TypeId>) -> Result<TypeId, Error> {
-        let next_id = self.next_type_id;
-
         if let Type::Option(ref option_type) = ty {
-            return Ok(*option_type.inner_type());
+            return match self.options {
+                OptionEncoding::Pointer => Ok(*option_type.inner_type()),
+                OptionEncoding::Slice => {
+                    self.register_type(Type::build().seq_type(None, *option_type.inner_type()))
+                }
+            };
         }
 
         let arc_ty = Arc::new(ty);
@@ -94,18 +498,70 @@ impl ::serde_schema::Schema for Schema {
             return Ok(*id);
         }
 
-        self.schema_types.push((next_id, arc_ty.clone()));
+        let embeds = type_name(&arc_ty).and_then(|name| self.embeds.get(name).cloned());
+        let embedded = match embeds {
+            Some(embeds) => Some(self.embedded_layout(&arc_ty, &embeds)?),
+            None => None,
+        };
+
+        let count = ids_needed(&arc_ty);
+        let pinned = type_name(&arc_ty).and_then(|name| self.pinned.get(name).copied());
+        let next_id = match pinned {
+            Some(id) => {
+                let end = id.0 + count as i64;
+                if let Some(blocked) = (id.0..end).map(TypeId).find(|id| self.taken.contains(id)) {
+                    return Err(Error::serialize(format!(
+                        "type id {} pinned to {} is already taken",
+                        blocked.0,
+                        type_name(&arc_ty).unwrap_or_default()
+                    )));
+                }
+                id
+            }
+            None => self.next_free_ids(count),
+        };
+
+        let pos = self
+            .schema_types
+            .binary_search_by(|(probe_id, _)| probe_id.cmp(&next_id))
+            .unwrap_or_else(|pos| pos);
+        self.schema_types.insert(pos, (next_id, arc_ty.clone()));
         self.schema_types_reverse.insert(arc_ty.clone(), next_id);
 
-        let delta = SerializeWireTypes::new(&mut self.pending_wire_types)
-            .serialize_wire_types(next_id, &arc_ty)?;
+        let enums = match *arc_ty {
+            Type::Enum(_) => self.enums,
+            _ => EnumEncoding::External,
+        };
+        let sent_ty = match embedded {
+            Some((ref sent_ty, ref layout)) => {
+                self.layouts.insert(next_id, layout.clone());
+                sent_ty
+            }
+            None => &*arc_ty,
+        };
+        let delta = SerializeWireTypes::new(&mut self.pending_wire_types, enums)
+            .serialize_wire_types(next_id, sent_ty)?;
+        if enums == EnumEncoding::Adjacent {
+            self.adjacent_enums.insert(next_id);
+        }
+        self.insert_plans(next_id, &arc_ty);
 
-        self.next_type_id = TypeId((self.next_type_id.0 as usize + delta) as i64);
+        let end = TypeId(next_id.0 + delta as i64);
+        self.taken.extend((next_id.0..end.0).map(TypeId));
+        if pinned.is_none() {
+            self.next_type_id = end;
+        }
 
         Ok(next_id)
     }
 }
 
+/// The id a type is sent under on a stream.
+///
+/// Ids below 65 are fixed: Go's predeclared types are found at the
+/// constants below, and the types describing definitions are reserved.
+/// All other ids are assigned by the encoder, and only mean something
+/// along with the definitions sent on the same stream.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct TypeId(pub(crate) i64);
 
@@ -117,6 +573,7 @@ impl TypeId {
     pub const BYTES: TypeId = TypeId(5);
     pub const STRING: TypeId = TypeId(6);
     pub const COMPLEX: TypeId = TypeId(7);
+    pub const INTERFACE: TypeId = TypeId(8);
     pub(crate) const WIRE_TYPE: TypeId = TypeId(16);
     pub(crate) const ARRAY_TYPE: TypeId = TypeId(17);
     pub(crate) const COMMON_TYPE: TypeId = TypeId(18);
@@ -125,6 +582,26 @@ impl TypeId {
     pub(crate) const FIELD_TYPE: TypeId = TypeId(21);
     pub(crate) const FIELD_TYPE_SLICE: TypeId = TypeId(22);
     pub(crate) const MAP_TYPE: TypeId = TypeId(23);
+    pub(crate) const GOB_ENCODER_TYPE: TypeId = TypeId(24);
+
+    /// The id `id` as found on the wire, unless it is not positive.
+    pub fn new(id: i64) -> Option<TypeId> {
+        if id > 0 {
+            Some(TypeId(id))
+        } else {
+            None
+        }
+    }
+
+    pub fn get(self) -> i64 {
+        self.0
+    }
+
+    /// Whether the id is one of the fixed ones below 65, rather than
+    /// assigned on a stream.
+    pub fn is_builtin(self) -> bool {
+        self.0 < CUSTOM_TYPE_ID_OFFSET
+    }
 
     pub(crate) fn next(&self) -> TypeId {
         TypeId(self.0 + 1)
@@ -0,0 +1,453 @@
+//! Checking schema changes for compatibility with gob peers
+//!
+//! Gob matches struct fields by name and ignores fields the receiver does
+//! not know, so adding a field never breaks a peer. Changing the type of a
+//! field, renaming it or removing a field peers still send does: the value
+//! either fails to decode or is silently dropped.
+//!
+//! ```
+//! # extern crate gob;
+//! # extern crate serde_schema;
+//! use gob::schema::{compat, Schema};
+//! use serde_schema::types::Type;
+//! use serde_schema::Schema as _;
+//!
+//! let mut old = Schema::new();
+//! old.register_type(Type::build().struct_type("Point", 2)
+//!     .field("x", gob::ser::TypeId::INT)
+//!     .field("y", gob::ser::TypeId::INT)
+//!     .end()).unwrap();
+//!
+//! let mut new = Schema::new();
+//! new.register_type(Type::build().struct_type("Point", 2)
+//!     .field("x", gob::ser::TypeId::INT)
+//!     .field("y", gob::ser::TypeId::FLOAT)
+//!     .end()).unwrap();
+//!
+//! let report = compat::check(&old, &new);
+//! assert!(!report.is_compatible());
+//! assert_eq!(report.breaking().next().unwrap().path(), "Point.y");
+//! ```
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+use serde_schema::types::{EnumVariant, StructField, Type};
+
+use super::{external_encoding, type_name, Schema, TypeId};
+use crate::ser::go_type_name;
+
+/// The changes found by `check`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CompatReport {
+    issues: Vec<Issue>,
+}
+
+impl CompatReport {
+    /// Whether none of the changes breaks a peer.
+    pub fn is_compatible(&self) -> bool {
+        self.breaking().next().is_none()
+    }
+
+    /// All changes, breaking or not, in the order of the types of the new
+    /// schema.
+    pub fn issues(&self) -> &[Issue] {
+        &self.issues
+    }
+
+    /// The changes that break a peer.
+    pub fn breaking(&self) -> impl Iterator<Item = &Issue> {
+        self.issues.iter().filter(|issue| issue.is_breaking())
+    }
+}
+
+impl fmt::Display for CompatReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for issue in &self.issues {
+            writeln!(f, "{}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+/// A single change between two schemas.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Issue {
+    path: String,
+    kind: IssueKind,
+}
+
+impl Issue {
+    /// The type, field or variant changed, as `Type` or `Type.field`.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn kind(&self) -> &IssueKind {
+        &self.kind
+    }
+
+    pub fn is_breaking(&self) -> bool {
+        self.kind.is_breaking()
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = if self.is_breaking() {
+            "breaking"
+        } else {
+            "compatible"
+        };
+        write!(f, "{}: {}: {}", severity, self.path, self.kind)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IssueKind {
+    /// The field or variant holds a type the old one does not decode into;
+    /// both are given as Go type names.
+    TypeChanged { old: String, new: String },
+    /// The field took the place of a field of a compatible type under
+    /// another name, which peers would drop.
+    FieldRenamed { from: String, to: String },
+    /// The field is gone, so what peers send in it is dropped.
+    FieldRemoved,
+    /// A new field, which peers not knowing it ignore.
+    FieldAdded,
+    /// None of the fields of the struct are left, which gob refuses to
+    /// decode.
+    NoFieldsInCommon,
+    /// The variant is gone, so peers sending it are decoded as no variant.
+    VariantRemoved,
+    /// A new variant, which peers not knowing it decode as no variant.
+    VariantAdded,
+    /// The enum switched between `EnumEncoding`s.
+    EnumEncodingChanged,
+    /// No type of that name is registered in the new schema anymore.
+    TypeRemoved,
+}
+
+impl IssueKind {
+    pub fn is_breaking(&self) -> bool {
+        !matches!(self, IssueKind::FieldAdded | IssueKind::TypeRemoved)
+    }
+}
+
+impl fmt::Display for IssueKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IssueKind::TypeChanged { old, new } => {
+                write!(f, "type changed from {} to {}", old, new)
+            }
+            IssueKind::FieldRenamed { from, to } => {
+                write!(f, "field {} renamed to {}", from, to)
+            }
+            IssueKind::FieldRemoved => f.write_str("field removed"),
+            IssueKind::FieldAdded => f.write_str("field added"),
+            IssueKind::NoFieldsInCommon => f.write_str("no fields in common"),
+            IssueKind::VariantRemoved => f.write_str("variant removed"),
+            IssueKind::VariantAdded => f.write_str("variant added"),
+            IssueKind::EnumEncodingChanged => f.write_str("enum encoding changed"),
+            IssueKind::TypeRemoved => f.write_str("type removed"),
+        }
+    }
+}
+
+/// Compares the named types (structs, enums and tuple structs) `old` and
+/// `new` have in common, matched by name, and reports how values sent by
+/// one are received by a peer built against the other.
+///
+/// Types only in `new` are not reported; types only in `old` are, but as a
+/// compatible change, since they may just have moved out of the schema.
+pub fn check(old: &Schema, new: &Schema) -> CompatReport {
+    let mut checker = Checker {
+        old,
+        new,
+        issues: Vec::new(),
+    };
+
+    let old_names = named_types(old);
+    let new_names = named_types(new);
+    for (name, &new_id) in &new_names {
+        if let Some(&old_id) = old_names.get(name) {
+            checker.check_named(name, old_id, new_id);
+        }
+    }
+    for name in old_names.keys() {
+        if !new_names.contains_key(name) {
+            checker.push(name.to_string(), IssueKind::TypeRemoved);
+        }
+    }
+
+    CompatReport {
+        issues: checker.issues,
+    }
+}
+
+fn named_types(schema: &Schema) -> BTreeMap<&str, TypeId> {
+    let mut names = BTreeMap::new();
+    for (id, ty) in schema.types() {
+        if let Some(name) = type_name(ty) {
+            names.entry(name).or_insert(*id);
+        }
+    }
+    names
+}
+
+struct Checker<'a> {
+    old: &'a Schema,
+    new: &'a Schema,
+    issues: Vec<Issue>,
+}
+
+impl<'a> Checker<'a> {
+    fn push(&mut self, path: String, kind: IssueKind) {
+        self.issues.push(Issue { path, kind });
+    }
+
+    fn type_changed(&mut self, path: String, old_id: TypeId, new_id: TypeId) {
+        let kind = IssueKind::TypeChanged {
+            old: go_type_name(self.old, old_id),
+            new: go_type_name(self.new, new_id),
+        };
+        self.push(path, kind);
+    }
+
+    fn check_named(&mut self, name: &str, old_id: TypeId, new_id: TypeId) {
+        let (old_ty, new_ty) = match (self.old.lookup(old_id), self.new.lookup(new_id)) {
+            (Some(old_ty), Some(new_ty)) => (old_ty, new_ty),
+            _ => return,
+        };
+        match (&*old_ty, &*new_ty) {
+            (Type::Struct(old_struct), Type::Struct(new_struct)) => {
+                self.check_fields(name, old_struct.fields(), new_struct.fields())
+            }
+            (Type::Enum(old_enum), Type::Enum(new_enum)) => {
+                if self.old.enum_encoding_of(old_id) != self.new.enum_encoding_of(new_id) {
+                    self.push(name.to_owned(), IssueKind::EnumEncodingChanged);
+                }
+                self.check_variants(name, old_enum.variants(), new_enum.variants())
+            }
+            (old_ty, new_ty) => {
+                let mut visited = HashSet::new();
+                visited.insert((old_id, new_id));
+                if !self.compatible_types((old_id, old_ty), (new_id, new_ty), &mut visited) {
+                    self.type_changed(name.to_owned(), old_id, new_id);
+                }
+            }
+        }
+    }
+
+    fn check_fields(
+        &mut self,
+        path: &str,
+        old_fields: &[StructField<TypeId>],
+        new_fields: &[StructField<TypeId>],
+    ) {
+        let mut removed = Vec::new();
+        let mut common = 0;
+        for (idx, old_field) in old_fields.iter().enumerate() {
+            match new_fields.iter().find(|f| f.name() == old_field.name()) {
+                Some(new_field) => {
+                    common += 1;
+                    let (old_id, new_id) = (*old_field.field_type(), *new_field.field_type());
+                    if !self.compatible(old_id, new_id, &mut HashSet::new()) {
+                        let field_path = format!("{}.{}", path, old_field.name());
+                        self.type_changed(field_path, old_id, new_id);
+                    }
+                }
+                None => removed.push(idx),
+            }
+        }
+
+        let mut added = Vec::new();
+        for (idx, new_field) in new_fields.iter().enumerate() {
+            if !old_fields.iter().any(|f| f.name() == new_field.name()) {
+                added.push(idx);
+            }
+        }
+
+        // a field removed where one of a compatible type was added is
+        // taken for a rename
+        for old_idx in removed {
+            let old_field = &old_fields[old_idx];
+            let renamed = added.iter().position(|&new_idx| {
+                new_idx == old_idx
+                    && self.compatible(
+                        *old_field.field_type(),
+                        *new_fields[new_idx].field_type(),
+                        &mut HashSet::new(),
+                    )
+            });
+            let field_path = format!("{}.{}", path, old_field.name());
+            match renamed {
+                Some(pos) => {
+                    let new_field = &new_fields[added.remove(pos)];
+                    let kind = IssueKind::FieldRenamed {
+                        from: old_field.name().to_owned(),
+                        to: new_field.name().to_owned(),
+                    };
+                    self.push(field_path, kind);
+                }
+                None => self.push(field_path, IssueKind::FieldRemoved),
+            }
+        }
+        for new_idx in added {
+            let field_path = format!("{}.{}", path, new_fields[new_idx].name());
+            self.push(field_path, IssueKind::FieldAdded);
+        }
+
+        if common == 0 && !old_fields.is_empty() && !new_fields.is_empty() {
+            self.push(path.to_owned(), IssueKind::NoFieldsInCommon);
+        }
+    }
+
+    fn check_variants(
+        &mut self,
+        path: &str,
+        old_variants: &[EnumVariant<TypeId>],
+        new_variants: &[EnumVariant<TypeId>],
+    ) {
+        for old_variant in old_variants {
+            let variant_path = format!("{}.{}", path, old_variant.name());
+            let new_variant = match new_variants.iter().find(|v| v.name() == old_variant.name()) {
+                Some(new_variant) => new_variant,
+                None => {
+                    self.push(variant_path, IssueKind::VariantRemoved);
+                    continue;
+                }
+            };
+            match (old_variant, new_variant) {
+                (EnumVariant::Unit(_), EnumVariant::Unit(_)) => {}
+                (EnumVariant::Newtype(old_newtype), EnumVariant::Newtype(new_newtype)) => {
+                    let (old_id, new_id) = (*old_newtype.inner_type(), *new_newtype.inner_type());
+                    if !self.compatible(old_id, new_id, &mut HashSet::new()) {
+                        self.type_changed(variant_path, old_id, new_id);
+                    }
+                }
+                (EnumVariant::Struct(old_struct), EnumVariant::Struct(new_struct)) => {
+                    self.check_fields(&variant_path, old_struct.fields(), new_struct.fields())
+                }
+                _ => {
+                    let kind = IssueKind::TypeChanged {
+                        old: variant_kind(old_variant).to_owned(),
+                        new: variant_kind(new_variant).to_owned(),
+                    };
+                    self.push(variant_path, kind);
+                }
+            }
+        }
+        for new_variant in new_variants {
+            if !old_variants.iter().any(|v| v.name() == new_variant.name()) {
+                let variant_path = format!("{}.{}", path, new_variant.name());
+                self.push(variant_path, IssueKind::VariantAdded);
+            }
+        }
+    }
+
+    // whether values of `old_id` sent by one peer decode as `new_id` by the
+    // other; pairs already being compared count as compatible, so that
+    // recursive types terminate, and so do named types of the same name,
+    // whose changes are reported under their own name
+    fn compatible(
+        &self,
+        old_id: TypeId,
+        new_id: TypeId,
+        visited: &mut HashSet<(TypeId, TypeId)>,
+    ) -> bool {
+        if old_id.0 < super::CUSTOM_TYPE_ID_OFFSET || new_id.0 < super::CUSTOM_TYPE_ID_OFFSET {
+            return old_id == new_id;
+        }
+        if !visited.insert((old_id, new_id)) {
+            return true;
+        }
+        let (old_ty, new_ty) = match (self.old.lookup(old_id), self.new.lookup(new_id)) {
+            (Some(old_ty), Some(new_ty)) => (old_ty, new_ty),
+            _ => return false,
+        };
+        if let (Some(old_name), Some(new_name)) = (type_name(&old_ty), type_name(&new_ty)) {
+            if old_name == new_name {
+                return true;
+            }
+        }
+        self.compatible_types((old_id, &old_ty), (new_id, &new_ty), visited)
+    }
+
+    fn compatible_types(
+        &self,
+        (old_id, old_ty): (TypeId, &Type<TypeId>),
+        (new_id, new_ty): (TypeId, &Type<TypeId>),
+        visited: &mut HashSet<(TypeId, TypeId)>,
+    ) -> bool {
+        match (old_ty, new_ty) {
+            (Type::Seq(old_seq), Type::Seq(new_seq)) => {
+                old_seq.len() == new_seq.len()
+                    && self.compatible(*old_seq.element_type(), *new_seq.element_type(), visited)
+            }
+            (Type::Map(old_map), Type::Map(new_map)) => {
+                self.compatible(*old_map.key_type(), *new_map.key_type(), visited)
+                    && self.compatible(*old_map.value_type(), *new_map.value_type(), visited)
+            }
+            (Type::Struct(old_struct), Type::Struct(new_struct)) => {
+                let mut common = false;
+                for old_field in old_struct.fields() {
+                    let new_field = new_struct
+                        .fields()
+                        .iter()
+                        .find(|f| f.name() == old_field.name());
+                    if let Some(new_field) = new_field {
+                        let (old_id, new_id) = (*old_field.field_type(), *new_field.field_type());
+                        if !self.compatible(old_id, new_id, visited) {
+                            return false;
+                        }
+                        common = true;
+                    }
+                }
+                common || old_struct.fields().is_empty() || new_struct.fields().is_empty()
+            }
+            (Type::Enum(_), Type::Enum(_)) => {
+                self.old.enum_encoding_of(old_id) == self.new.enum_encoding_of(new_id)
+            }
+            (Type::TupleStruct(old_tuple), Type::TupleStruct(new_tuple)) => {
+                match (external_encoding(old_ty), external_encoding(new_ty)) {
+                    (Some(old_encoding), Some(new_encoding)) => old_encoding == new_encoding,
+                    (None, None) => {
+                        old_tuple.element_types().len() == new_tuple.element_types().len()
+                            && old_tuple
+                                .element_types()
+                                .iter()
+                                .zip(new_tuple.element_types())
+                                .all(|(&o, &n)| self.compatible(o, n, visited))
+                    }
+                    _ => false,
+                }
+            }
+            (Type::Tuple(old_tuple), Type::Tuple(new_tuple)) => {
+                old_tuple.element_types().len() == new_tuple.element_types().len()
+                    && old_tuple
+                        .element_types()
+                        .iter()
+                        .zip(new_tuple.element_types())
+                        .all(|(&o, &n)| self.compatible(o, n, visited))
+            }
+            (Type::NewtypeStruct(old_newtype), Type::NewtypeStruct(new_newtype)) => self
+                .compatible(
+                    *old_newtype.inner_type(),
+                    *new_newtype.inner_type(),
+                    visited,
+                ),
+            (Type::UnitStruct(_), Type::UnitStruct(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+fn variant_kind(variant: &EnumVariant<TypeId>) -> &'static str {
+    match variant {
+        EnumVariant::Unit(_) => "unit variant",
+        EnumVariant::Newtype(_) => "newtype variant",
+        EnumVariant::Tuple(_) => "tuple variant",
+        EnumVariant::Struct(_) => "struct variant",
+    }
+}
@@ -0,0 +1,241 @@
+//! Gob types described in JSON, for encoding JSON values as gob
+//!
+//! Shared by `gob encode` and the `wasm` bindings.
+
+use serde::ser::{self, Serialize, SerializeMap, SerializeSeq, SerializeStruct, Serializer};
+use serde_json::Value as JsonValue;
+use serde_schema::types::Type;
+use serde_schema::Schema as SchemaTrait;
+
+use error::Error;
+use value::intern;
+
+use super::{Schema, TypeId};
+use crate::{error, value};
+
+/// A gob type described in JSON.
+///
+/// Builtin types are written as strings (`"bool"`, `"int"`, `"uint"`,
+/// `"float"`, `"bytes"`, `"string"`), composite types as single-key
+/// objects:
+///
+/// ```text
+/// {"slice": T}
+/// {"array": {"len": N, "elem": T}}
+/// {"map": {"key": K, "elem": V}}
+/// {"struct": {"name": "Point", "fields": [{"name": "X", "type": "int"}]}}
+/// ```
+pub(crate) enum SchemaExpr {
+    Bool,
+    Int,
+    Uint,
+    Float,
+    Bytes,
+    String,
+    Slice(Box<SchemaExpr>),
+    Array(usize, Box<SchemaExpr>),
+    Map(Box<SchemaExpr>, Box<SchemaExpr>),
+    Struct(&'static str, Vec<(&'static str, SchemaExpr)>),
+}
+
+impl SchemaExpr {
+    pub(crate) fn parse(value: &JsonValue) -> Result<SchemaExpr, String> {
+        if let Some(name) = value.as_str() {
+            return match name {
+                "bool" => Ok(SchemaExpr::Bool),
+                "int" => Ok(SchemaExpr::Int),
+                "uint" => Ok(SchemaExpr::Uint),
+                "float" => Ok(SchemaExpr::Float),
+                "bytes" => Ok(SchemaExpr::Bytes),
+                "string" => Ok(SchemaExpr::String),
+                _ => Err(format!("unknown builtin type {:?}", name)),
+            };
+        }
+
+        let obj = match value.as_object() {
+            Some(obj) if obj.len() == 1 => obj,
+            _ => return Err(format!("invalid type expression {}", value)),
+        };
+        let (kind, body) = obj.iter().next().unwrap();
+        match kind.as_str() {
+            "slice" => Ok(SchemaExpr::Slice(Box::new(SchemaExpr::parse(body)?))),
+            "array" => {
+                let len = body["len"]
+                    .as_u64()
+                    .ok_or_else(|| format!("array type without len in {}", body))?;
+                let elem = SchemaExpr::parse(&body["elem"])?;
+                Ok(SchemaExpr::Array(len as usize, Box::new(elem)))
+            }
+            "map" => {
+                let key = SchemaExpr::parse(&body["key"])?;
+                let elem = SchemaExpr::parse(&body["elem"])?;
+                Ok(SchemaExpr::Map(Box::new(key), Box::new(elem)))
+            }
+            "struct" => {
+                let name = body["name"]
+                    .as_str()
+                    .ok_or_else(|| format!("struct type without name in {}", body))?;
+                let fields = body["fields"]
+                    .as_array()
+                    .ok_or_else(|| format!("struct type without fields in {}", body))?
+                    .iter()
+                    .map(|field| {
+                        let name = field["name"]
+                            .as_str()
+                            .ok_or_else(|| format!("struct field without name in {}", field))?;
+                        Ok((intern(name), SchemaExpr::parse(&field["type"])?))
+                    })
+                    .collect::<Result<Vec<_>, String>>()?;
+                Ok(SchemaExpr::Struct(intern(name), fields))
+            }
+            _ => Err(format!("unknown type kind {:?}", kind)),
+        }
+    }
+
+    pub(crate) fn register(&self, schema: &mut Schema) -> Result<TypeId, Error> {
+        match self {
+            SchemaExpr::Bool => Ok(TypeId::BOOL),
+            SchemaExpr::Int => Ok(TypeId::INT),
+            SchemaExpr::Uint => Ok(TypeId::UINT),
+            SchemaExpr::Float => Ok(TypeId::FLOAT),
+            SchemaExpr::Bytes => Ok(TypeId::BYTES),
+            SchemaExpr::String => Ok(TypeId::STRING),
+            SchemaExpr::Slice(ref elem) => {
+                let elem_id = elem.register(schema)?;
+                schema.register_type(Type::build().seq_type(None, elem_id))
+            }
+            SchemaExpr::Array(len, ref elem) => {
+                let elem_id = elem.register(schema)?;
+                schema.register_type(Type::build().seq_type(Some(*len), elem_id))
+            }
+            SchemaExpr::Map(ref key, ref elem) => {
+                let key_id = key.register(schema)?;
+                let elem_id = elem.register(schema)?;
+                schema.register_type(Type::build().map_type(key_id, elem_id))
+            }
+            SchemaExpr::Struct(name, ref fields) => {
+                let mut builder = Type::build().struct_type(name, fields.len());
+                for &(field_name, ref field_type) in fields {
+                    builder = builder.field(field_name, field_type.register(schema)?);
+                }
+                schema.register_type(builder.end())
+            }
+        }
+    }
+}
+
+/// Serializes a JSON value according to a schema expression.
+pub(crate) struct Typed<'a> {
+    pub expr: &'a SchemaExpr,
+    pub value: &'a JsonValue,
+}
+
+impl<'a> Typed<'a> {
+    fn mismatch<E: ser::Error>(&self, expected: &str) -> E {
+        E::custom(format!("expected {}, found {}", expected, self.value))
+    }
+}
+
+impl<'a> Serialize for Typed<'a> {
+    fn serialize<S: Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        match self.expr {
+            SchemaExpr::Bool => match self.value.as_bool() {
+                Some(v) => ser.serialize_bool(v),
+                None => Err(self.mismatch("bool")),
+            },
+            SchemaExpr::Int => match self.value.as_i64() {
+                Some(v) => ser.serialize_i64(v),
+                None => Err(self.mismatch("int")),
+            },
+            SchemaExpr::Uint => match self.value.as_u64() {
+                Some(v) => ser.serialize_u64(v),
+                None => Err(self.mismatch("uint")),
+            },
+            SchemaExpr::Float => match self.value.as_f64() {
+                Some(v) => ser.serialize_f64(v),
+                None => Err(self.mismatch("float")),
+            },
+            SchemaExpr::String => match self.value.as_str() {
+                Some(v) => ser.serialize_str(v),
+                None => Err(self.mismatch("string")),
+            },
+            SchemaExpr::Bytes => match self.value {
+                JsonValue::String(ref s) => ser.serialize_bytes(s.as_bytes()),
+                JsonValue::Array(ref elems) => {
+                    let bytes = elems
+                        .iter()
+                        .map(|elem| match elem.as_u64() {
+                            Some(b) if b <= 0xff => Ok(b as u8),
+                            _ => Err(self.mismatch("bytes")),
+                        })
+                        .collect::<Result<Vec<u8>, S::Error>>()?;
+                    ser.serialize_bytes(&bytes)
+                }
+                _ => Err(self.mismatch("bytes")),
+            },
+            SchemaExpr::Slice(ref elem) | SchemaExpr::Array(_, ref elem) => {
+                let elems = match self.value.as_array() {
+                    Some(elems) => elems,
+                    None => return Err(self.mismatch("array")),
+                };
+                if let SchemaExpr::Array(len, _) = self.expr {
+                    if *len != elems.len() {
+                        return Err(self.mismatch(&format!("array of length {}", len)));
+                    }
+                }
+                let mut seq = ser.serialize_seq(Some(elems.len()))?;
+                for value in elems {
+                    seq.serialize_element(&Typed { expr: elem, value })?;
+                }
+                seq.end()
+            }
+            SchemaExpr::Map(ref key, ref elem) => {
+                let entries = match self.value.as_object() {
+                    Some(entries) => entries,
+                    None => return Err(self.mismatch("object")),
+                };
+                let mut map = ser.serialize_map(Some(entries.len()))?;
+                for (k, value) in entries {
+                    // non-string keys are spelled as JSON inside the object key
+                    let k = match **key {
+                        SchemaExpr::String | SchemaExpr::Bytes => JsonValue::String(k.clone()),
+                        _ => ::serde_json::from_str(k).map_err(ser::Error::custom)?,
+                    };
+                    map.serialize_entry(
+                        &Typed {
+                            expr: key,
+                            value: &k,
+                        },
+                        &Typed { expr: elem, value },
+                    )?;
+                }
+                map.end()
+            }
+            SchemaExpr::Struct(name, ref fields) => {
+                let obj = match self.value.as_object() {
+                    Some(obj) => obj,
+                    None => return Err(self.mismatch("object")),
+                };
+                if let Some(unknown) = obj
+                    .keys()
+                    .find(|k| !fields.iter().any(|&(name, _)| name == k.as_str()))
+                {
+                    return Err(ser::Error::custom(format!(
+                        "unknown field {:?} for struct {}",
+                        unknown, name
+                    )));
+                }
+                let mut st = ser.serialize_struct(name, fields.len())?;
+                for &(field_name, ref expr) in fields {
+                    match obj.get(field_name) {
+                        Some(value) if !value.is_null() => {
+                            st.serialize_field(field_name, &Typed { expr, value })?
+                        }
+                        _ => st.skip_field(field_name)?,
+                    }
+                }
+                st.end()
+            }
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use std::io::Write;
+
+use mode::Mode;
+use schema::{EnumEncoding, OptionEncoding};
+
+use super::{
+    FieldOptions, FloatOptions, MapOrder, OutputBuffer, OutputBytes, OutputWrite, StreamSerializer,
+};
+use crate::{mode, schema};
+
+/// Collects the options of a serializer in one place, then creates any
+/// number of serializers configured alike.
+///
+/// ```
+/// # use gob::ser::{Builder, MapOrder};
+/// let builder = Builder::new()
+///     .map_order(MapOrder::EncodedKey)
+///     .emit_zero_fields(true);
+/// let mut stream = builder.writer(Vec::new());
+/// stream.serialize(&0u64).unwrap();
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Builder {
+    options: OptionEncoding,
+    enums: EnumEncoding,
+    floats: FloatOptions,
+    fields: FieldOptions,
+    maps: MapOrder,
+}
+
+impl Builder {
+    pub fn new() -> Builder {
+        Builder::default()
+    }
+
+    /// Starts from the options of `StreamSerializer::canonical`.
+    pub fn canonical(mut self) -> Self {
+        self.floats = FloatOptions::new()
+            .normalize_negative_zero(true)
+            .canonicalize_nan(true);
+        self.fields = FieldOptions::default();
+        self.maps = MapOrder::EncodedKey;
+        self
+    }
+
+    /// Sets how floats and map entries are written according to `mode`,
+    /// replacing any set before.
+    ///
+    /// - `Strict` writes them like `canonical`: NaNs as Go's `math.NaN()`,
+    ///   `-0.0` as `0.0`, and map entries ordered by their encoded keys.
+    /// - `Compatible` and `Lenient` only differ in what is accepted on
+    ///   input, and both write them like Go does, which is the default.
+    pub fn mode(mut self, mode: Mode) -> Self {
+        match mode {
+            Mode::Strict => {
+                self.floats = FloatOptions::new()
+                    .normalize_negative_zero(true)
+                    .canonicalize_nan(true);
+                self.maps = MapOrder::EncodedKey;
+            }
+            Mode::Compatible | Mode::Lenient => {
+                self.floats = FloatOptions::default();
+                self.maps = MapOrder::default();
+            }
+        }
+        self
+    }
+
+    /// Sets how `Option`s are encoded, see `OptionEncoding`.
+    pub fn option_encoding(mut self, options: OptionEncoding) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Sets how enums are encoded, see `EnumEncoding`.
+    pub fn enum_encoding(mut self, enums: EnumEncoding) -> Self {
+        self.enums = enums;
+        self
+    }
+
+    /// Sets how floats are written.
+    pub fn float_options(mut self, options: FloatOptions) -> Self {
+        self.floats = options;
+        self
+    }
+
+    /// Sets which struct fields are written.
+    pub fn field_options(mut self, options: FieldOptions) -> Self {
+        self.fields = options;
+        self
+    }
+
+    /// See `FieldOptions::emit_zero_fields`.
+    pub fn emit_zero_fields(mut self, emit: bool) -> Self {
+        self.fields = self.fields.emit_zero_fields(emit);
+        self
+    }
+
+    /// Sets the order map entries are written in.
+    pub fn map_order(mut self, order: MapOrder) -> Self {
+        self.maps = order;
+        self
+    }
+
+    /// Creates a serializer that writes straight into `w`, like
+    /// `StreamSerializer::new`.
+    pub fn writer<W: Write>(&self, w: W) -> StreamSerializer<OutputWrite<W>> {
+        self.build(OutputWrite::new(w))
+    }
+
+    /// Creates a serializer that writes into a buffer, like
+    /// `StreamSerializer::new_with_buffer`.
+    pub fn buffer(&self) -> StreamSerializer<OutputBuffer> {
+        self.build(OutputBuffer::new())
+    }
+
+    /// Creates a serializer that writes into a `BytesMut`, like
+    /// `StreamSerializer::new_with_bytes`.
+    pub fn bytes(&self) -> StreamSerializer<OutputBytes> {
+        self.build(OutputBytes::new())
+    }
+
+    fn build<O>(&self, out: O) -> StreamSerializer<O> {
+        let mut ser = StreamSerializer::with_output(out);
+        ser.schema.set_option_encoding(self.options);
+        ser.schema.set_enum_encoding(self.enums);
+        ser.floats = self.floats;
+        ser.fields = self.fields;
+        ser.maps = self.maps;
+        ser
+    }
+}
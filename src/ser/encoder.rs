@@ -0,0 +1,80 @@
+use bytes::Bytes;
+use serde_schema::SchemaSerialize;
+
+use error::Error;
+use value::Value;
+
+use crate::{error, value};
+
+use super::{FieldOptions, FloatOptions, MapOrder, OutputBytes, Schema, StreamSerializer};
+
+/// An encoder that leaves all I/O to the caller.
+///
+/// Every call returns a frame holding the definitions of any types not
+/// encoded before, followed by the value itself. Frames have to be
+/// delivered in order, but can otherwise be sent over any transport; the
+/// receiving end can feed them to a `de::Decoder` one by one.
+pub struct Encoder {
+    stream: StreamSerializer<OutputBytes>,
+}
+
+impl Encoder {
+    pub fn new() -> Encoder {
+        Encoder {
+            stream: StreamSerializer::new_with_bytes(),
+        }
+    }
+
+    /// Encode equal values as identical frames, see
+    /// `StreamSerializer::canonical`.
+    pub fn canonical(self) -> Encoder {
+        Encoder {
+            stream: self.stream.canonical(),
+        }
+    }
+
+    pub fn schema_mut(&mut self) -> &mut Schema {
+        self.stream.schema_mut()
+    }
+
+    /// Sets how floats are written from now on.
+    pub fn set_float_options(&mut self, options: FloatOptions) {
+        self.stream.set_float_options(options);
+    }
+
+    /// Sets which struct fields are written from now on.
+    pub fn set_field_options(&mut self, options: FieldOptions) {
+        self.stream.set_field_options(options);
+    }
+
+    /// Sets the order map entries are written in from now on.
+    pub fn set_map_order(&mut self, order: MapOrder) {
+        self.stream.set_map_order(order);
+    }
+
+    /// Encodes `value` into a new frame.
+    pub fn encode<T>(&mut self, value: &T) -> Result<Bytes, Error>
+    where
+        T: SchemaSerialize,
+    {
+        self.stream.serialize(value)?;
+        Ok(self.frame())
+    }
+
+    /// Encodes a dynamically typed value into a new frame, see
+    /// `StreamSerializer::serialize_value`.
+    pub fn encode_value(&mut self, value: &Value) -> Result<Bytes, Error> {
+        self.stream.serialize_value(value)?;
+        Ok(self.frame())
+    }
+
+    fn frame(&mut self) -> Bytes {
+        self.stream.get_mut().get_mut().take().freeze()
+    }
+}
+
+impl Default for Encoder {
+    fn default() -> Encoder {
+        Encoder::new()
+    }
+}
@@ -0,0 +1,91 @@
+use std::fmt;
+
+use erased_serde;
+use serde_schema::SchemaSerialize;
+
+use error::Error;
+use schema::{Schema, TypeId};
+
+use super::{Output, StreamSerializer};
+use crate::{error, schema};
+
+/// The schema of a type, with the type itself erased.
+///
+/// Handles are created for a concrete type once, e.g. when a plugin is
+/// loaded, and can then register the type with any serializer's schema.
+#[derive(Clone, Copy)]
+pub struct SchemaHandle {
+    name: &'static str,
+    register: fn(&mut Schema) -> Result<TypeId, Error>,
+}
+
+impl SchemaHandle {
+    pub fn of<T: SchemaSerialize>() -> SchemaHandle {
+        SchemaHandle {
+            name: ::std::any::type_name::<T>(),
+            register: T::schema_register::<Schema>,
+        }
+    }
+
+    /// The Rust name of the type, for diagnostics.
+    pub fn type_name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Registers the type with `schema`, returning its id.
+    pub fn register(&self, schema: &mut Schema) -> Result<TypeId, Error> {
+        (self.register)(schema)
+    }
+}
+
+impl fmt::Debug for SchemaHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("SchemaHandle").field(&self.name).finish()
+    }
+}
+
+/// A value that can be serialized behind a trait object, along with its
+/// schema.
+///
+/// Implemented for every type that can be passed to
+/// `StreamSerializer::serialize`, so that values of many types can be
+/// collected as `Box<dyn SerializeErased>` and written with
+/// `StreamSerializer::serialize_erased`.
+pub trait SerializeErased: erased_serde::Serialize {
+    /// The schema of the type of the value.
+    fn schema_handle(&self) -> SchemaHandle;
+
+    /// The value as a plain `erased_serde::Serialize` trait object.
+    fn as_erased_serialize(&self) -> &dyn erased_serde::Serialize;
+}
+
+impl<T: SchemaSerialize> SerializeErased for T {
+    fn schema_handle(&self) -> SchemaHandle {
+        SchemaHandle::of::<T>()
+    }
+
+    fn as_erased_serialize(&self) -> &dyn erased_serde::Serialize {
+        self
+    }
+}
+
+impl<O: Output> StreamSerializer<O> {
+    /// Serialize a value behind a trait object onto the stream.
+    pub fn serialize_erased(&mut self, value: &dyn SerializeErased) -> Result<(), Error> {
+        self.serialize_with_schema(&value.schema_handle(), value.as_erased_serialize())
+    }
+
+    /// Serialize a value behind a trait object onto the stream, with its
+    /// schema given separately.
+    ///
+    /// `schema` has to be the handle of the type of `value`, or the value
+    /// is written with a type it doesn't match.
+    pub fn serialize_with_schema(
+        &mut self,
+        schema: &SchemaHandle,
+        value: &dyn erased_serde::Serialize,
+    ) -> Result<(), Error> {
+        let type_id = schema.register(&mut self.schema)?;
+        erased_serde::serialize(value, self.serializer(type_id)?)
+    }
+}
@@ -0,0 +1,114 @@
+use std::fmt::Write;
+
+use serde_schema::types::{EnumType, EnumVariant, StructField, Type};
+use serde_schema::SchemaSerialize;
+
+use error::Error;
+use schema::{EnumEncoding, Schema, TypeId, ENUM_TAG};
+
+use crate::{error, schema};
+
+/// Returns Go declarations of the types `T` is made of, for a Go program
+/// decoding values of `T`.
+///
+/// Enums are declared the way `EnumEncoding::Adjacent` encodes them: a
+/// struct with a `Tag` field naming the variant, and a pointer field for
+/// each variant that has a value. Each gets a `Value` method returning the
+/// value of the variant `Tag` names.
+pub fn go_declarations<T: SchemaSerialize>() -> Result<String, Error> {
+    let mut schema = Schema::new();
+    schema.set_enum_encoding(EnumEncoding::Adjacent);
+    T::schema_register(&mut schema)?;
+
+    let mut out = String::new();
+    for (_, ty) in schema.types() {
+        match **ty {
+            Type::Struct(ref struct_type) => {
+                write_struct(&schema, &mut out, struct_type.name(), struct_type.fields())
+            }
+            Type::Enum(ref enum_type) => write_enum(&schema, &mut out, enum_type),
+            _ => {}
+        }
+    }
+    Ok(out)
+}
+
+fn write_struct(schema: &Schema, out: &mut String, name: &str, fields: &[StructField<TypeId>]) {
+    writeln!(out, "type {} struct {{", name).unwrap();
+    for field in fields {
+        let type_name = go_type_name(schema, *field.field_type());
+        writeln!(out, "\t{} {}", field.name(), type_name).unwrap();
+    }
+    writeln!(out, "}}\n").unwrap();
+}
+
+fn write_enum(schema: &Schema, out: &mut String, enum_type: &EnumType<TypeId>) {
+    for variant in enum_type.variants() {
+        if let EnumVariant::Struct(ref struct_variant) = *variant {
+            write_struct(schema, out, struct_variant.name(), struct_variant.fields());
+        }
+    }
+
+    let name = enum_type.name();
+    writeln!(out, "type {} struct {{", name).unwrap();
+    writeln!(out, "\t{} string", ENUM_TAG).unwrap();
+    for variant in enum_type.variants() {
+        match *variant {
+            EnumVariant::Newtype(ref newtype_variant) => {
+                let type_name = go_type_name(schema, *newtype_variant.inner_type());
+                writeln!(out, "\t{} *{}", variant.name(), type_name).unwrap();
+            }
+            EnumVariant::Struct(_) => {
+                writeln!(out, "\t{} *{}", variant.name(), variant.name()).unwrap();
+            }
+            _ => {}
+        }
+    }
+    writeln!(out, "}}\n").unwrap();
+
+    writeln!(
+        out,
+        "// Value returns the value of the variant {} names, or nil if it has none.",
+        ENUM_TAG
+    )
+    .unwrap();
+    writeln!(out, "func (e *{}) Value() interface{{}} {{", name).unwrap();
+    writeln!(out, "\tswitch e.{} {{", ENUM_TAG).unwrap();
+    for variant in enum_type.variants() {
+        if variant.as_newtype_variant().is_some() || variant.as_struct_variant().is_some() {
+            writeln!(out, "\tcase {:?}:", variant.name()).unwrap();
+            writeln!(out, "\t\treturn e.{}", variant.name()).unwrap();
+        }
+    }
+    writeln!(out, "\t}}\n\treturn nil\n}}\n").unwrap();
+}
+
+pub(crate) fn go_type_name(schema: &Schema, id: TypeId) -> String {
+    match id {
+        TypeId::BOOL => "bool".to_owned(),
+        TypeId::INT => "int64".to_owned(),
+        TypeId::UINT => "uint64".to_owned(),
+        TypeId::FLOAT => "float64".to_owned(),
+        TypeId::BYTES => "[]byte".to_owned(),
+        TypeId::STRING => "string".to_owned(),
+        TypeId::COMPLEX => "complex128".to_owned(),
+        _ => match schema.lookup(id).as_deref() {
+            Some(Type::Struct(struct_type)) => struct_type.name().to_owned(),
+            Some(Type::Enum(enum_type)) => enum_type.name().to_owned(),
+            Some(Type::TupleStruct(tuple_struct_type)) => tuple_struct_type.name().to_owned(),
+            Some(Type::Seq(seq_type)) => {
+                let elem = go_type_name(schema, *seq_type.element_type());
+                match seq_type.len() {
+                    Some(len) => format!("[{}]{}", len, elem),
+                    None => format!("[]{}", elem),
+                }
+            }
+            Some(Type::Map(map_type)) => format!(
+                "map[{}]{}",
+                go_type_name(schema, *map_type.key_type()),
+                go_type_name(schema, *map_type.value_type())
+            ),
+            _ => "interface{}".to_owned(),
+        },
+    }
+}
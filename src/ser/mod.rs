@@ -1,7 +1,10 @@
 //! Serialization
 
+use std::any::TypeId as AnyTypeId;
+use std::collections::HashMap;
 use std::io::Write;
 
+use bytes::BytesMut;
 use serde::ser::{self, Impossible};
 use serde::Serialize;
 use serde_schema::SchemaSerialize;
@@ -10,12 +13,36 @@ use internal::ser::{FieldValueSerializer, SerializationCtx, SerializeVariantValu
 use internal::utils::Bow;
 
 use error::Error;
-pub use schema::{Schema, TypeId};
+pub use schema::{EnumEncoding, OptionEncoding, Schema, TypeId};
+#[cfg(feature = "json")]
+use value::FromJson;
+use value::{Shape, Value};
 
 mod output;
-use crate::{error, internal, schema};
+use crate::{error, internal, schema, value};
 
-pub use self::output::{Output, OutputBuffer, OutputPart, OutputWrite};
+pub use self::output::{
+    Output, OutputBuffer, OutputBytes, OutputBytesMut, OutputCount, OutputPart, OutputSlice,
+    OutputVec, OutputWrite,
+};
+
+mod builder;
+pub use self::builder::Builder;
+
+mod encoder;
+pub use self::encoder::Encoder;
+
+#[cfg(feature = "erased")]
+mod erased;
+#[cfg(feature = "erased")]
+pub use self::erased::{SchemaHandle, SerializeErased};
+
+mod go;
+pub use self::go::go_declarations;
+pub(crate) use self::go::go_type_name;
+
+mod options;
+pub use self::options::{Field, FieldOptions, FloatOptions, MapKey, MapOrder, NonFinite};
 
 mod serialize_struct;
 pub use self::serialize_struct::SerializeStruct;
@@ -27,6 +54,8 @@ mod serialize_map;
 pub use self::serialize_map::SerializeMap;
 mod serialize_struct_variant;
 pub use self::serialize_struct_variant::SerializeStructVariant;
+mod seq_stream;
+pub use self::seq_stream::SeqStream;
 
 /// Serializes a single value.
 pub struct Serializer<'t, O> {
@@ -38,6 +67,10 @@ pub struct Serializer<'t, O> {
 /// Serializes a stream of values.
 pub struct StreamSerializer<O> {
     schema: Schema,
+    type_ids: HashMap<AnyTypeId, TypeId>,
+    floats: FloatOptions,
+    fields: FieldOptions,
+    maps: MapOrder,
     out: O,
 }
 
@@ -45,29 +78,149 @@ impl StreamSerializer<OutputBuffer> {
     /// Create a new stream serializer that writes into a buffer.
     pub fn new_with_buffer() -> Self {
         let buffer = OutputBuffer::new();
-        StreamSerializer::new(buffer)
+        StreamSerializer::with_output(buffer)
+    }
+}
+
+impl StreamSerializer<OutputBytes> {
+    /// Create a new stream serializer that writes into a `BytesMut`.
+    pub fn new_with_bytes() -> Self {
+        StreamSerializer::with_output(OutputBytes::new())
+    }
+}
+
+impl<'a> StreamSerializer<OutputVec<'a>> {
+    /// Create a new stream serializer that appends to `buf`, so that its
+    /// allocation can be reused across streams.
+    pub fn new_with_vec(buf: &'a mut Vec<u8>) -> Self {
+        StreamSerializer::with_output(OutputVec::new(buf))
+    }
+}
+
+impl<'a> StreamSerializer<OutputBytesMut<'a>> {
+    /// Create a new stream serializer that appends to `buf`.
+    pub fn new_with_bytes_mut(buf: &'a mut BytesMut) -> Self {
+        StreamSerializer::with_output(OutputBytesMut::new(buf))
+    }
+}
+
+impl<'a> StreamSerializer<OutputSlice<'a>> {
+    /// Create a new stream serializer that writes into `buf`, failing once
+    /// a section doesn't fit into what is left of it. See `OutputSlice`.
+    pub fn new_with_slice(buf: &'a mut [u8]) -> Self {
+        StreamSerializer::with_output(OutputSlice::new(buf))
     }
 }
 
 impl<W: Write> StreamSerializer<OutputWrite<W>> {
+    /// Create a new stream serializer that writes straight into `w`,
+    /// like Go's `gob.NewEncoder`.
+    ///
+    /// Each call to `serialize` writes the definitions of any types not
+    /// seen before, followed by the value itself. Definitions are sent only
+    /// once per stream.
+    pub fn new(w: W) -> Self {
+        StreamSerializer::with_output(OutputWrite::new(w))
+    }
+
     /// Create a new stream serializer with the provided `Write` output.
     pub fn new_with_write(w: W) -> Self {
-        StreamSerializer::new(OutputWrite::new(w))
+        StreamSerializer::new(w)
+    }
+
+    /// Unwraps the underlying writer.
+    pub fn into_writer(self) -> W {
+        self.out.into_inner()
     }
 }
 
 impl<O> StreamSerializer<O> {
-    fn new(out: O) -> StreamSerializer<O> {
+    fn with_output(out: O) -> StreamSerializer<O> {
         let schema = Schema::new();
-        StreamSerializer { schema, out }
+        StreamSerializer {
+            schema,
+            type_ids: HashMap::new(),
+            floats: FloatOptions::default(),
+            fields: FieldOptions::default(),
+            maps: MapOrder::default(),
+            out,
+        }
     }
 
     pub fn schema_mut(&mut self) -> &mut Schema {
         &mut self.schema
     }
 
+    /// Sets how `Option`s are encoded from now on, see `OptionEncoding`.
+    pub fn set_option_encoding(&mut self, options: OptionEncoding) {
+        self.schema.set_option_encoding(options);
+        self.reset();
+    }
+
+    /// Sets how enums are encoded from now on, see `EnumEncoding`.
+    pub fn set_enum_encoding(&mut self, enums: EnumEncoding) {
+        self.schema.set_enum_encoding(enums);
+    }
+
+    /// Sets how floats are written from now on.
+    pub fn set_float_options(&mut self, options: FloatOptions) {
+        self.floats = options;
+    }
+
+    /// Sets which struct fields are written from now on.
+    pub fn set_field_options(&mut self, options: FieldOptions) {
+        self.fields = options;
+    }
+
+    /// Sets the order map entries are written in from now on.
+    pub fn set_map_order(&mut self, order: MapOrder) {
+        self.maps = order;
+    }
+
+    /// Write equal values as identical bytes, e.g. for content addressing
+    /// or signatures.
+    ///
+    /// Type ids are always assigned in the order types are first used,
+    /// starting at 65, with the types a type refers to registered depth
+    /// first; their definitions are sent right before the first value that
+    /// needs them. Struct fields are written in declaration order, with
+    /// zero values left out. In canonical mode, map entries are also
+    /// ordered by the encoded bytes of their keys, NaNs are written as Go's
+    /// `math.NaN()` and `-0.0` as `0.0`. Equal sequences of values written
+    /// to fresh streams then always produce the same bytes.
+    pub fn canonical(mut self) -> Self {
+        self.floats = FloatOptions::new()
+            .normalize_negative_zero(true)
+            .canonicalize_nan(true);
+        self.fields = FieldOptions::default();
+        self.maps = MapOrder::EncodedKey;
+        self
+    }
+
+    /// Returns how many bytes `serialize` would write for `value` right
+    /// now, type definitions it still has to send included, without
+    /// writing anything.
+    pub fn serialized_size<T>(&self, value: &T) -> Result<u64, Error>
+    where
+        T: SchemaSerialize,
+    {
+        let mut counting = StreamSerializer {
+            schema: self.schema.clone(),
+            type_ids: self.type_ids.clone(),
+            floats: self.floats,
+            fields: self.fields,
+            maps: self.maps,
+            out: OutputCount::new(),
+        };
+        counting.serialize(value)?;
+        Ok(counting.out.count())
+    }
+
     pub fn serializer<'a>(&'a mut self, id: TypeId) -> Result<Serializer<'a, &'a mut O>, Error> {
-        let ctx = SerializationCtx::with_schema(Bow::Borrowed(&mut self.schema));
+        let mut ctx = SerializationCtx::with_schema(Bow::Borrowed(&mut self.schema));
+        ctx.floats = self.floats;
+        ctx.fields = self.fields;
+        ctx.maps = self.maps;
         Ok(Serializer {
             type_id: id,
             ctx,
@@ -75,6 +228,66 @@ impl<O> StreamSerializer<O> {
         })
     }
 
+    /// Returns a serializer for values of type `T`.
+    ///
+    /// `T` is registered with the schema on first use only. Its type id is
+    /// cached from then on, so that serializing many values of the same
+    /// type skips the schema walk.
+    pub fn serializer_for<'a, T>(&'a mut self) -> Result<Serializer<'a, &'a mut O>, Error>
+    where
+        T: SchemaSerialize + 'static,
+    {
+        let type_id = match self.type_ids.get(&AnyTypeId::of::<T>()) {
+            Some(&type_id) => type_id,
+            None => {
+                let type_id = T::schema_register(&mut self.schema)?;
+                self.type_ids.insert(AnyTypeId::of::<T>(), type_id);
+                type_id
+            }
+        };
+        self.serializer(type_id)
+    }
+
+    /// Forgets the type ids cached by `serializer_for`.
+    ///
+    /// Types stay registered with the schema, so their definitions are not
+    /// sent again; the next `serializer_for` only walks the schema once more.
+    pub fn reset(&mut self) {
+        self.type_ids.clear();
+    }
+
+    /// Write the type definitions needed for `T` onto the stream right away,
+    /// instead of along with the first value of that type.
+    ///
+    /// Returns the id the type was registered under.
+    pub fn preflight<T>(&mut self) -> Result<TypeId, Error>
+    where
+        T: SchemaSerialize,
+        O: Output,
+    {
+        let type_id = T::schema_register(&mut self.schema)?;
+        self.schema.write_pending(&mut self.out)?;
+        Ok(type_id)
+    }
+
+    /// Registers `T` like `preflight`, but returns the type definitions it
+    /// needs as a standalone blob instead of writing them onto the stream,
+    /// e.g. to hand them to consumers out-of-band once.
+    ///
+    /// Values of `T` serialized from then on carry no definitions, and are
+    /// decoded by loading the blob with `de::TypeDefs::from_bytes` first.
+    /// Blobs exported for several types can be concatenated into one. The
+    /// blob is empty for types whose definitions were sent already.
+    pub fn export_type_defs<T>(&mut self) -> Result<Vec<u8>, Error>
+    where
+        T: SchemaSerialize,
+    {
+        T::schema_register(&mut self.schema)?;
+        let mut blob = OutputWrite::new(Vec::new());
+        self.schema.write_pending(&mut blob)?;
+        Ok(blob.into_inner())
+    }
+
     /// Serialize a value onto the stream.
     pub fn serialize<T>(&mut self, value: &T) -> Result<(), Error>
     where
@@ -85,6 +298,30 @@ impl<O> StreamSerializer<O> {
         self.serialize_with_type_id(type_id, value)
     }
 
+    /// Serialize a dynamically typed value onto the stream, with its gob
+    /// type inferred from the value itself.
+    ///
+    /// Struct types are registered under synthetic names, see
+    /// `value::synthetic_type_name`. All elements of a sequence or map must
+    /// be of the same type, and there must be at least one to infer it from.
+    pub fn serialize_value(&mut self, value: &Value) -> Result<(), Error>
+    where
+        O: Output,
+    {
+        let type_id = Shape::of(value)?.register(&mut self.schema)?;
+        self.serialize_with_type_id(type_id, value)
+    }
+
+    /// Serialize a JSON value onto the stream, with its gob type deduced
+    /// as described for `value::FromJson`.
+    #[cfg(feature = "json")]
+    pub fn serialize_json(&mut self, json: &::serde_json::Value) -> Result<(), Error>
+    where
+        O: Output,
+    {
+        self.serialize_value(&FromJson::new().convert(json)?)
+    }
+
     pub fn serialize_with_type_id<T>(&mut self, type_id: TypeId, value: &T) -> Result<(), Error>
     where
         T: Serialize,
@@ -93,6 +330,16 @@ impl<O> StreamSerializer<O> {
         value.serialize(self.serializer(type_id)?)
     }
 
+    // writes any pending type definitions, followed by a section holding
+    // an already encoded value
+    pub(crate) fn write_section(&mut self, section: Vec<u8>) -> Result<(), Error>
+    where
+        O: Output,
+    {
+        self.schema.write_pending(&mut self.out)?;
+        self.out.serialize_part(OutputPart::try_new(section)?)
+    }
+
     pub fn get_ref(&self) -> &O {
         &self.out
     }
@@ -106,6 +353,20 @@ impl<O> StreamSerializer<O> {
     }
 }
 
+/// Returns how many bytes serializing `value` onto a stream using `schema`
+/// would take, type definitions the schema still has to send included.
+///
+/// Nothing is kept, and `schema` is left as it was.
+pub fn serialized_size<T>(value: &T, schema: &Schema) -> Result<u64, Error>
+where
+    T: SchemaSerialize,
+{
+    let mut counting = StreamSerializer::with_output(OutputCount::new());
+    counting.schema = schema.clone();
+    counting.serialize(value)?;
+    Ok(counting.out.count())
+}
+
 impl<'t, O: Output> ser::Serializer for Serializer<'t, O> {
     type Ok = ();
     type Error = Error;
@@ -125,6 +386,7 @@ impl<'t, O: Output> ser::Serializer for Serializer<'t, O> {
             let ser = FieldValueSerializer {
                 ctx: self.ctx,
                 type_id: self.type_id,
+                zero: None,
             };
             ser.serialize_bool(v)?
         };
@@ -150,12 +412,27 @@ impl<'t, O: Output> ser::Serializer for Serializer<'t, O> {
             let ser = FieldValueSerializer {
                 ctx: self.ctx,
                 type_id: self.type_id,
+                zero: None,
             };
             ser.serialize_i64(v)?
         };
         ok.ctx.flush(self.out)
     }
 
+    fn serialize_i128(mut self, v: i128) -> Result<Self::Ok, Self::Error> {
+        self.ctx.value.write_int(self.type_id.0);
+        self.ctx.value.write_uint(0);
+        let mut ok = {
+            let ser = FieldValueSerializer {
+                ctx: self.ctx,
+                type_id: self.type_id,
+                zero: None,
+            };
+            ser.serialize_i128(v)?
+        };
+        ok.ctx.flush(self.out)
+    }
+
     fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
         self.serialize_u64(v as u64)
     }
@@ -175,14 +452,29 @@ impl<'t, O: Output> ser::Serializer for Serializer<'t, O> {
             let ser = FieldValueSerializer {
                 ctx: self.ctx,
                 type_id: self.type_id,
+                zero: None,
             };
             ser.serialize_u64(v)?
         };
         ok.ctx.flush(self.out)
     }
 
+    fn serialize_u128(mut self, v: u128) -> Result<Self::Ok, Self::Error> {
+        self.ctx.value.write_int(self.type_id.0);
+        self.ctx.value.write_uint(0);
+        let mut ok = {
+            let ser = FieldValueSerializer {
+                ctx: self.ctx,
+                type_id: self.type_id,
+                zero: None,
+            };
+            ser.serialize_u128(v)?
+        };
+        ok.ctx.flush(self.out)
+    }
+
     fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
-        self.serialize_f64(v as f64)
+        self.serialize_f64(f64::from(v))
     }
 
     fn serialize_f64(mut self, v: f64) -> Result<Self::Ok, Self::Error> {
@@ -192,6 +484,7 @@ impl<'t, O: Output> ser::Serializer for Serializer<'t, O> {
             let ser = FieldValueSerializer {
                 ctx: self.ctx,
                 type_id: self.type_id,
+                zero: None,
             };
             ser.serialize_f64(v)?
         };
@@ -209,6 +502,7 @@ impl<'t, O: Output> ser::Serializer for Serializer<'t, O> {
             let ser = FieldValueSerializer {
                 ctx: self.ctx,
                 type_id: self.type_id,
+                zero: None,
             };
             ser.serialize_str(v)?
         };
@@ -222,6 +516,7 @@ impl<'t, O: Output> ser::Serializer for Serializer<'t, O> {
             let ser = FieldValueSerializer {
                 ctx: self.ctx,
                 type_id: self.type_id,
+                zero: None,
             };
             ser.serialize_bytes(v)?
         };
@@ -235,6 +530,7 @@ impl<'t, O: Output> ser::Serializer for Serializer<'t, O> {
             let ser = FieldValueSerializer {
                 ctx: self.ctx,
                 type_id: self.type_id,
+                zero: None,
             };
             ser.serialize_none()?
         };
@@ -245,7 +541,14 @@ impl<'t, O: Output> ser::Serializer for Serializer<'t, O> {
     where
         T: Serialize,
     {
-        value.serialize(self)
+        match self.ctx.schema.option_encoding() {
+            OptionEncoding::Pointer => value.serialize(self),
+            OptionEncoding::Slice => {
+                let mut seq = ser::Serializer::serialize_seq(self, Some(1))?;
+                ser::SerializeSeq::serialize_element(&mut seq, value)?;
+                ser::SerializeSeq::end(seq)
+            }
+        }
     }
 
     fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
@@ -257,23 +560,42 @@ impl<'t, O: Output> ser::Serializer for Serializer<'t, O> {
     }
 
     fn serialize_unit_variant(
-        self,
-        _name: &'static str,
-        _variant_index: u32,
-        _variant: &'static str,
+        mut self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
     ) -> Result<Self::Ok, Self::Error> {
-        Err(ser::Error::custom("not implemented yet"))
+        self.ctx.value.write_int(self.type_id.0);
+        let mut ok = {
+            let ser = FieldValueSerializer {
+                ctx: self.ctx,
+                type_id: self.type_id,
+                zero: None,
+            };
+            ser.serialize_unit_variant(name, variant_index, variant)?
+        };
+        ok.ctx.flush(self.out)
     }
 
     fn serialize_newtype_struct<T: ?Sized>(
-        self,
-        _name: &'static str,
-        _value: &T,
+        mut self,
+        name: &'static str,
+        value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
         T: Serialize,
     {
-        Err(ser::Error::custom("not implemented yet"))
+        self.ctx.value.write_int(self.type_id.0);
+        self.ctx.value.write_uint(0);
+        let mut ok = {
+            let ser = FieldValueSerializer {
+                ctx: self.ctx,
+                type_id: self.type_id,
+                zero: None,
+            };
+            ser.serialize_newtype_struct(name, value)?
+        };
+        ok.ctx.flush(self.out)
     }
 
     fn serialize_newtype_variant<T: ?Sized>(
@@ -291,6 +613,7 @@ impl<'t, O: Output> ser::Serializer for Serializer<'t, O> {
             let ser = FieldValueSerializer {
                 ctx: self.ctx,
                 type_id: self.type_id,
+                zero: None,
             };
             ser.serialize_newtype_variant(name, variant_index, variant, value)?
         };
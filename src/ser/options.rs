@@ -0,0 +1,274 @@
+use std::cmp::Ordering;
+use std::str;
+
+use internal::gob::decode_uint;
+use internal::types::TypeId;
+
+use crate::internal;
+
+/// Controls how floats are written.
+///
+/// By default floats are written bit for bit, like Go does. Decoding is
+/// always bit-exact, so a stream decoded into `f64`s and encoded again with
+/// the default options reproduces the original bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FloatOptions {
+    normalize_negative_zero: bool,
+    canonicalize_nan: bool,
+    non_finite: NonFinite,
+}
+
+/// What is done with NaNs and infinities, which some Go consumers can't
+/// handle.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NonFinite {
+    /// Write them like any other float, like Go does.
+    #[default]
+    Write,
+    /// Fail to serialize them.
+    Reject,
+    /// Write them as `0.0`.
+    Zero,
+}
+
+/// The NaN returned by Go's `math.NaN()`.
+const GO_NAN_BITS: u64 = 0x7ff8_0000_0000_0001;
+
+impl FloatOptions {
+    pub fn new() -> FloatOptions {
+        FloatOptions::default()
+    }
+
+    /// Write `-0.0` as `0.0`.
+    pub fn normalize_negative_zero(mut self, normalize: bool) -> Self {
+        self.normalize_negative_zero = normalize;
+        self
+    }
+
+    /// Write every NaN as the one returned by Go's `math.NaN()`, dropping
+    /// its sign and payload.
+    pub fn canonicalize_nan(mut self, canonicalize: bool) -> Self {
+        self.canonicalize_nan = canonicalize;
+        self
+    }
+
+    /// Sets what is done with NaNs and infinities, see `NonFinite`. This
+    /// takes precedence over `canonicalize_nan`.
+    pub fn non_finite(mut self, non_finite: NonFinite) -> Self {
+        self.non_finite = non_finite;
+        self
+    }
+
+    pub(crate) fn apply(&self, v: f64) -> Result<f64, String> {
+        if !v.is_finite() {
+            match self.non_finite {
+                NonFinite::Write => {}
+                NonFinite::Reject => return Err(format!("non-finite float {}", v)),
+                NonFinite::Zero => return Ok(0.0),
+            }
+        }
+        Ok(if self.canonicalize_nan && v.is_nan() {
+            f64::from_bits(GO_NAN_BITS)
+        } else if self.normalize_negative_zero && v == 0.0 {
+            0.0
+        } else {
+            v
+        })
+    }
+}
+
+/// Controls which struct fields are written.
+///
+/// Like Go, fields holding the zero value of their type are left out by
+/// default, and decoders fill them in with the zero value again.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FieldOptions {
+    emit_zero_fields: bool,
+    omit: Option<fn(&Field) -> Option<bool>>,
+}
+
+impl FieldOptions {
+    pub fn new() -> FieldOptions {
+        FieldOptions::default()
+    }
+
+    /// Write fields holding the zero value too. A `None` written like this
+    /// decodes as `Some` of the zero value.
+    pub fn emit_zero_fields(mut self, emit: bool) -> Self {
+        self.emit_zero_fields = emit;
+        self
+    }
+
+    /// Decide for each field whether it is left out, by returning
+    /// `Some(true)` to omit it or `Some(false)` to write it. Fields the hook
+    /// returns `None` for are handled according to `emit_zero_fields`.
+    pub fn omit_with(mut self, hook: fn(&Field) -> Option<bool>) -> Self {
+        self.omit = Some(hook);
+        self
+    }
+
+    pub(crate) fn has_hook(&self) -> bool {
+        self.omit.is_some()
+    }
+
+    pub(crate) fn omit(&self, field: &Field) -> bool {
+        if let Some(omit) = self.omit.and_then(|hook| hook(field)) {
+            return omit;
+        }
+        field.is_zero && !self.emit_zero_fields
+    }
+}
+
+/// A struct field about to be written, as seen by `FieldOptions::omit_with`.
+#[derive(Debug)]
+pub struct Field<'a> {
+    pub(crate) name: &'a str,
+    pub(crate) type_id: TypeId,
+    pub(crate) value: &'a [u8],
+    pub(crate) is_zero: bool,
+}
+
+impl<'a> Field<'a> {
+    /// The name of the field.
+    pub fn name(&self) -> &str {
+        self.name
+    }
+
+    /// The gob type of the field.
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
+
+    /// Whether the field holds the zero value of its type, and would be
+    /// left out by Go.
+    pub fn is_zero(&self) -> bool {
+        self.is_zero
+    }
+
+    /// The encoded value of the field.
+    pub fn value(&self) -> &[u8] {
+        self.value
+    }
+
+    /// The value of the field if it is a float, e.g. to omit NaNs as well.
+    pub fn as_f64(&self) -> Option<f64> {
+        if self.type_id != TypeId::FLOAT {
+            return None;
+        }
+        let (bits, _) = decode_uint(self.value).ok()?;
+        Some(f64::from_bits(bits.swap_bytes()))
+    }
+}
+
+/// The order map entries are written in.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum MapOrder {
+    /// The order the map iterates in, which is random for `HashMap`s.
+    #[default]
+    Iteration,
+    /// Ordered by the encoded bytes of their keys, so that equal maps are
+    /// always written the same way.
+    EncodedKey,
+    /// Ordered by the values of their keys: strings byte by byte, numbers
+    /// and bools by value. Keys of other types are ordered by their encoded
+    /// bytes.
+    Key,
+    /// Ordered by the given comparison of their keys.
+    Custom(fn(&MapKey, &MapKey) -> Ordering),
+}
+
+impl MapOrder {
+    pub(crate) fn is_sorted(&self) -> bool {
+        !matches!(*self, MapOrder::Iteration)
+    }
+
+    pub(crate) fn compare(&self, a: &MapKey, b: &MapKey) -> Ordering {
+        match *self {
+            MapOrder::Iteration | MapOrder::EncodedKey => a.value.cmp(b.value),
+            MapOrder::Key => a.cmp_values(b),
+            MapOrder::Custom(cmp) => cmp(a, b),
+        }
+    }
+}
+
+/// A map key about to be written, as seen by `MapOrder::Custom`.
+#[derive(Debug)]
+pub struct MapKey<'a> {
+    pub(crate) type_id: TypeId,
+    pub(crate) value: &'a [u8],
+}
+
+impl<'a> MapKey<'a> {
+    /// The gob type of the key.
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
+
+    /// The encoded key.
+    pub fn value(&self) -> &[u8] {
+        self.value
+    }
+
+    fn as_uint(&self, type_id: TypeId) -> Option<u64> {
+        if self.type_id != type_id {
+            return None;
+        }
+        let (bits, _) = decode_uint(self.value).ok()?;
+        Some(bits)
+    }
+
+    /// The key if it is a bool.
+    pub fn as_bool(&self) -> Option<bool> {
+        self.as_uint(TypeId::BOOL).map(|bits| bits != 0)
+    }
+
+    /// The key if it is a signed integer.
+    pub fn as_i64(&self) -> Option<i64> {
+        let bits = self.as_uint(TypeId::INT)?;
+        if bits & 1 == 0 {
+            Some((bits >> 1) as i64)
+        } else {
+            Some(!((bits >> 1) as i64))
+        }
+    }
+
+    /// The key if it is an unsigned integer.
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_uint(TypeId::UINT)
+    }
+
+    /// The key if it is a float.
+    pub fn as_f64(&self) -> Option<f64> {
+        let bits = self.as_uint(TypeId::FLOAT)?;
+        Some(f64::from_bits(bits.swap_bytes()))
+    }
+
+    /// The key if it is a string.
+    pub fn as_str(&self) -> Option<&str> {
+        if self.type_id != TypeId::STRING {
+            return None;
+        }
+        let (len, len_len) = decode_uint(self.value).ok()?;
+        let bytes = self.value.get(len_len..len_len + len as usize)?;
+        str::from_utf8(bytes).ok()
+    }
+
+    fn cmp_values(&self, other: &MapKey) -> Ordering {
+        if let (Some(a), Some(b)) = (self.as_str(), other.as_str()) {
+            return a.cmp(b);
+        }
+        if let (Some(a), Some(b)) = (self.as_i64(), other.as_i64()) {
+            return a.cmp(&b);
+        }
+        if let (Some(a), Some(b)) = (self.as_u64(), other.as_u64()) {
+            return a.cmp(&b);
+        }
+        if let (Some(a), Some(b)) = (self.as_f64(), other.as_f64()) {
+            return a.total_cmp(&b);
+        }
+        if let (Some(a), Some(b)) = (self.as_bool(), other.as_bool()) {
+            return a.cmp(&b);
+        }
+        self.value.cmp(other.value)
+    }
+}
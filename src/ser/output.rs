@@ -1,9 +1,11 @@
 use std::io::{Cursor, Write};
+use std::iter;
+use std::ops::Range;
 
-use bytes::Buf;
+use bytes::{Buf, Bytes, BytesMut};
 use iovec::IoVec;
 
-use internal::gob::Message;
+use internal::gob::{Message, MAX_SECTION_LEN};
 use internal::utils::BufVec;
 
 use error::Error;
@@ -14,15 +16,58 @@ pub struct OutputPart {
     len_buf_len: u8,
     len_buf: [u8; 9],
     buf: Vec<u8>,
+    chunks: Vec<Chunk>,
+    len: usize,
     pos: usize,
 }
 
+// the payload of a part, as ranges of `buf` interleaved with byte slices
+// that were not copied into it
+enum Chunk {
+    Buf(Range<usize>),
+    Shared(Bytes),
+}
+
 impl OutputPart {
     pub(crate) fn new(buf: Vec<u8>) -> Self {
+        OutputPart::with_shared(buf, Vec::new())
+    }
+
+    // like `new`, but refuses sections that Go would not accept
+    pub(crate) fn try_new(buf: Vec<u8>) -> Result<Self, Error> {
+        OutputPart::try_with_shared(buf, Vec::new())
+    }
+
+    // `shared` holds byte slices to be sent right after the first `usize`
+    // bytes of `buf`, in order
+    pub(crate) fn try_with_shared(
+        buf: Vec<u8>,
+        shared: Vec<(usize, Bytes)>,
+    ) -> Result<Self, Error> {
+        let len = buf.len() + shared.iter().map(|(_, bytes)| bytes.len()).sum::<usize>();
+        if len as u64 >= MAX_SECTION_LEN {
+            return Err(Error::message_too_large(len as u64));
+        }
+        Ok(OutputPart::with_shared(buf, shared))
+    }
+
+    fn with_shared(buf: Vec<u8>, shared: Vec<(usize, Bytes)>) -> Self {
+        let mut chunks = Vec::with_capacity(shared.len() * 2 + 1);
+        let mut len = 0;
+        let mut last = 0;
+        for (at, bytes) in shared {
+            chunks.push(Chunk::Buf(last..at));
+            len += bytes.len();
+            chunks.push(Chunk::Shared(bytes));
+            last = at;
+        }
+        chunks.push(Chunk::Buf(last..buf.len()));
+        len += buf.len();
+
         let mut len_buf = [0u8; 9];
         let len_buf_len = {
             let mut len_msg = Message::new(Cursor::new(&mut len_buf));
-            len_msg.write_uint(buf.len() as u64);
+            len_msg.write_uint(len as u64);
             len_msg.get_ref().position() as u8
         };
 
@@ -30,22 +75,46 @@ impl OutputPart {
             len_buf_len,
             len_buf,
             buf,
+            chunks,
+            len: len_buf_len as usize + len,
             pos: 0,
         }
     }
+
+    fn segments(&self) -> impl Iterator<Item = &[u8]> {
+        let len = &self.len_buf[..self.len_buf_len as usize];
+        let chunks = self.chunks.iter().map(move |chunk| match *chunk {
+            Chunk::Buf(ref range) => &self.buf[range.clone()],
+            Chunk::Shared(ref bytes) => &bytes[..],
+        });
+        iter::once(len)
+            .chain(chunks)
+            .filter(|segment| !segment.is_empty())
+    }
+
+    // the segments left to write, the first one cut to the current position
+    fn remaining_segments(&self) -> impl Iterator<Item = &[u8]> {
+        let mut skip = self.pos;
+        self.segments().filter_map(move |segment| {
+            if skip >= segment.len() {
+                skip -= segment.len();
+                None
+            } else {
+                let rest = &segment[skip..];
+                skip = 0;
+                Some(rest)
+            }
+        })
+    }
 }
 
 impl Buf for OutputPart {
     fn remaining(&self) -> usize {
-        (self.len_buf_len as usize + self.buf.len()) - self.pos
+        self.len - self.pos
     }
 
     fn bytes(&self) -> &[u8] {
-        if self.pos < self.len_buf_len as usize {
-            &self.len_buf[self.pos..self.len_buf_len as usize]
-        } else {
-            &self.buf[self.pos - self.len_buf_len as usize..]
-        }
+        self.remaining_segments().next().unwrap_or(&[])
     }
 
     fn advance(&mut self, cnt: usize) {
@@ -53,15 +122,12 @@ impl Buf for OutputPart {
     }
 
     fn bytes_vec<'a>(&'a self, dst: &mut [&'a IoVec]) -> usize {
-        let mut pos = self.pos;
         let mut idx = 0;
-        if idx < dst.len() && pos < self.len_buf_len as usize {
-            dst[idx] = IoVec::from_bytes(&self.len_buf[pos..self.len_buf_len as usize]).unwrap();
-            idx += 1;
-            pos = self.len_buf_len as usize;
-        }
-        if idx < dst.len() && pos < self.len_buf_len as usize + self.buf.len() {
-            dst[idx] = IoVec::from_bytes(&self.buf[pos - self.len_buf_len as usize..]).unwrap();
+        for segment in self.remaining_segments() {
+            if idx == dst.len() {
+                break;
+            }
+            dst[idx] = IoVec::from_bytes(segment).unwrap();
             idx += 1;
         }
         idx
@@ -142,12 +208,193 @@ impl<W: Write> Output for OutputWrite<W> {
     }
 }
 
+pub struct OutputBytes(BytesMut);
+
+impl OutputBytes {
+    pub(crate) fn new() -> Self {
+        OutputBytes(BytesMut::new())
+    }
+
+    pub fn get_ref(&self) -> &BytesMut {
+        &self.0
+    }
+
+    pub fn get_mut(&mut self) -> &mut BytesMut {
+        &mut self.0
+    }
+
+    pub fn into_inner(self) -> BytesMut {
+        self.0
+    }
+}
+
+impl Output for OutputBytes {
+    fn serialize_part(&mut self, mut part: OutputPart) -> Result<(), Error> {
+        self.0.reserve(part.remaining());
+        while part.has_remaining() {
+            let len = {
+                let bytes = part.bytes();
+                self.0.extend_from_slice(bytes);
+                bytes.len()
+            };
+            part.advance(len);
+        }
+        Ok(())
+    }
+}
+
+/// Counts the bytes written instead of keeping them.
+#[derive(Debug, Default)]
+pub struct OutputCount {
+    count: u64,
+}
+
+impl OutputCount {
+    pub(crate) fn new() -> Self {
+        OutputCount::default()
+    }
+
+    /// Number of bytes written so far.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+impl Output for OutputCount {
+    fn serialize_part(&mut self, part: OutputPart) -> Result<(), Error> {
+        self.count += part.remaining() as u64;
+        Ok(())
+    }
+}
+
+// hands the bytes of `part` to `f` segment by segment, returning how many
+// there were
+fn copy_part<F: FnMut(&[u8])>(mut part: OutputPart, mut f: F) -> usize {
+    let len = part.remaining();
+    while part.has_remaining() {
+        let n = {
+            let bytes = part.bytes();
+            f(bytes);
+            bytes.len()
+        };
+        part.advance(n);
+    }
+    len
+}
+
+/// Appends to a `Vec<u8>` owned by the caller.
+pub struct OutputVec<'a> {
+    buf: &'a mut Vec<u8>,
+    written: usize,
+}
+
+impl<'a> OutputVec<'a> {
+    pub(crate) fn new(buf: &'a mut Vec<u8>) -> Self {
+        OutputVec { buf, written: 0 }
+    }
+
+    /// Number of bytes appended so far.
+    pub fn written(&self) -> usize {
+        self.written
+    }
+}
+
+impl<'a> Output for OutputVec<'a> {
+    fn serialize_part(&mut self, part: OutputPart) -> Result<(), Error> {
+        self.buf.reserve(part.remaining());
+        let buf = &mut *self.buf;
+        self.written += copy_part(part, |bytes| buf.extend_from_slice(bytes));
+        Ok(())
+    }
+}
+
+/// Appends to a `BytesMut` owned by the caller.
+pub struct OutputBytesMut<'a> {
+    buf: &'a mut BytesMut,
+    written: usize,
+}
+
+impl<'a> OutputBytesMut<'a> {
+    pub(crate) fn new(buf: &'a mut BytesMut) -> Self {
+        OutputBytesMut { buf, written: 0 }
+    }
+
+    /// Number of bytes appended so far.
+    pub fn written(&self) -> usize {
+        self.written
+    }
+}
+
+impl<'a> Output for OutputBytesMut<'a> {
+    fn serialize_part(&mut self, part: OutputPart) -> Result<(), Error> {
+        self.buf.reserve(part.remaining());
+        let buf = &mut *self.buf;
+        self.written += copy_part(part, |bytes| buf.extend_from_slice(bytes));
+        Ok(())
+    }
+}
+
+/// Writes into a fixed slice owned by the caller, without allocating.
+///
+/// Sections that don't fit into what is left of the slice fail to be
+/// written as a whole. Type definitions written before the failing section
+/// are taken to be sent, so every section after it fails as well.
+pub struct OutputSlice<'a> {
+    buf: &'a mut [u8],
+    written: usize,
+    overflowed: bool,
+}
+
+impl<'a> OutputSlice<'a> {
+    pub(crate) fn new(buf: &'a mut [u8]) -> Self {
+        OutputSlice {
+            buf,
+            written: 0,
+            overflowed: false,
+        }
+    }
+
+    /// Number of bytes written so far, from the start of the slice.
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    /// The part of the slice written so far.
+    pub fn filled(&self) -> &[u8] {
+        &self.buf[..self.written]
+    }
+}
+
+impl<'a> Output for OutputSlice<'a> {
+    fn serialize_part(&mut self, part: OutputPart) -> Result<(), Error> {
+        if self.overflowed {
+            return Err(Error::serialize("output slice overflowed before"));
+        }
+        let left = self.buf.len() - self.written;
+        if part.remaining() > left {
+            self.overflowed = true;
+            return Err(Error::serialize(format!(
+                "output slice too small ({} bytes needed, {} left)",
+                part.remaining(),
+                left
+            )));
+        }
+        let buf = &mut *self.buf;
+        let mut pos = self.written;
+        self.written += copy_part(part, |bytes| {
+            buf[pos..pos + bytes.len()].copy_from_slice(bytes);
+            pos += bytes.len();
+        });
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::io::Read;
     use std::ops::Deref;
 
-    use bytes::Buf;
+    use bytes::{Buf, Bytes};
     use iovec::IoVec;
     use partial_io::quickcheck_types::GenNoErrors;
     use partial_io::quickcheck_types::PartialWithErrors;
@@ -181,6 +428,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn part_shared_bytes() {
+        let shared = vec![(2, Bytes::from(&[9, 9][..])), (2, Bytes::from(&[8][..]))];
+        let mut part = OutputPart::try_with_shared(vec![84, 1, 2], shared).unwrap();
+        assert_eq!(part.remaining(), 7);
+        {
+            let mut vecs = vec![IoVec::from_bytes(&[0]).unwrap(); 5];
+            let n = part.bytes_vec(vecs.as_mut_slice());
+            assert_eq!(n, 5);
+            assert_eq!(vecs[1].deref(), &[84, 1]);
+            assert_eq!(vecs[2].deref(), &[9, 9]);
+        }
+        part.advance(4);
+        assert_eq!(part.bytes(), &[9]);
+        assert_eq!(part.collect::<Vec<_>>(), vec![9, 8, 2]);
+    }
+
     #[test]
     fn part_bytes_vec() {
         let mut part = OutputPart::new(vec![84, 1, 2, 3, 4, 5, 6]);
@@ -0,0 +1,158 @@
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+
+use serde::ser::{self, Serialize};
+use serde_schema::types::Type;
+use serde_schema::SchemaSerialize;
+
+use error::Error;
+use internal::gob::{Message, MAX_SECTION_LEN};
+use internal::ser::{FieldValueSerializer, SerializationCtx};
+use schema::TypeId;
+
+use crate::{error, internal, schema};
+
+use super::{OutputWrite, StreamSerializer};
+
+/// Writes a sequence onto a stream one element at a time, for sequences
+/// too large to be collected first.
+///
+/// gob puts the number of elements and the length of the whole message in
+/// front of a sequence, so elements are encoded into a spill buffer until
+/// `finish` knows both. With a temporary file as the spill buffer, memory
+/// use stays bounded however long the sequence gets:
+///
+/// ```ignore
+/// let mut seq = stream.serialize_seq_streaming::<Record, _>(tempfile::tempfile()?)?;
+/// for record in records {
+///     seq.push(&record?)?;
+/// }
+/// seq.finish()?;
+/// ```
+///
+/// On the wire, the sequence is the same as a `Vec<T>` serialized as a
+/// whole.
+pub struct SeqStream<'a, W, S, T> {
+    stream: &'a mut StreamSerializer<OutputWrite<W>>,
+    spill: S,
+    type_id: TypeId,
+    elem: TypeId,
+    count: u64,
+    // bytes written to `spill`
+    spilled: u64,
+    // set if `spill` could not be rewound after a failed write, leaving
+    // part of an element after the ones pushed
+    poisoned: bool,
+    buffer: Vec<u8>,
+    elements: PhantomData<fn(&T)>,
+}
+
+impl<W: Write> StreamSerializer<OutputWrite<W>> {
+    /// Starts writing a sequence of `T`s, using `spill` to hold the encoded
+    /// elements until the sequence is complete.
+    pub fn serialize_seq_streaming<T, S>(
+        &mut self,
+        mut spill: S,
+    ) -> Result<SeqStream<'_, W, S, T>, Error>
+    where
+        T: SchemaSerialize,
+        S: Read + Write + Seek,
+    {
+        let type_id = Vec::<T>::schema_register(&mut self.schema)?;
+        let elem = match self.schema.lookup(type_id).as_deref() {
+            Some(Type::Seq(seq_type)) => *seq_type.element_type(),
+            _ => return Err(ser::Error::custom("schema mismatch, not a sequence")),
+        };
+        spill.seek(SeekFrom::Start(0))?;
+        Ok(SeqStream {
+            stream: self,
+            spill,
+            type_id,
+            elem,
+            count: 0,
+            spilled: 0,
+            poisoned: false,
+            buffer: Vec::new(),
+            elements: PhantomData,
+        })
+    }
+}
+
+impl<'a, W: Write, S: Read + Write + Seek, T: Serialize> SeqStream<'a, W, S, T> {
+    /// Encodes `value` as the next element.
+    ///
+    /// If writing it to the spill buffer fails, the element is left out
+    /// and pushing can carry on.
+    pub fn push(&mut self, value: &T) -> Result<(), Error> {
+        self.check_poisoned()?;
+        let mut ctx = SerializationCtx::with_schema(&self.stream.schema);
+        ctx.floats = self.stream.floats;
+        ctx.fields = self.stream.fields;
+        ctx.maps = self.stream.maps;
+        ctx.value = Message::new(std::mem::take(&mut self.buffer));
+        let ser = FieldValueSerializer {
+            ctx,
+            type_id: self.elem,
+            zero: None,
+        };
+        let mut ctx = value.serialize(ser)?.ctx;
+        ctx.unshare();
+        let mut buffer = ctx.value.into_inner();
+        let result = self.spill.write_all(&buffer);
+        if result.is_ok() {
+            self.spilled += buffer.len() as u64;
+            self.count += 1;
+        } else if self.spill.seek(SeekFrom::Start(self.spilled)).is_err() {
+            self.poisoned = true;
+        }
+        buffer.clear();
+        self.buffer = buffer;
+        Ok(result?)
+    }
+
+    /// Number of elements pushed so far.
+    pub fn len(&self) -> u64 {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Writes the sequence onto the stream, handing back the spill buffer.
+    pub fn finish(mut self) -> Result<S, Error> {
+        self.check_poisoned()?;
+        let mut header = Message::new(Vec::new());
+        header.write_int(self.type_id.0);
+        header.write_uint(0);
+        header.write_uint(self.count);
+        let header = header.into_inner();
+
+        let section_len = header.len() as u64 + self.spilled;
+        if section_len >= MAX_SECTION_LEN {
+            return Err(Error::message_too_large(section_len));
+        }
+        let mut prefix = Message::new(Vec::new());
+        prefix.write_uint(section_len);
+
+        self.stream.schema.write_pending(&mut self.stream.out)?;
+        let w = self.stream.out.get_mut();
+        w.write_all(prefix.get_ref())?;
+        w.write_all(&header)?;
+        self.spill.seek(SeekFrom::Start(0))?;
+        let copied = io::copy(&mut (&mut self.spill).take(self.spilled), w)?;
+        if copied < self.spilled {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        Ok(self.spill)
+    }
+
+    fn check_poisoned(&self) -> Result<(), Error> {
+        if self.poisoned {
+            return Err(Error::serialize(
+                "spill buffer holds part of an element after a failed write",
+            ));
+        }
+        Ok(())
+    }
+}
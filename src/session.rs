@@ -0,0 +1,187 @@
+//! Captured gob streams
+
+use std::io::{self, Cursor, Read, Write};
+use std::ops::Range;
+
+use bytes::Buf;
+#[cfg(feature = "rayon")]
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+#[cfg(feature = "rayon")]
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use de::DEFAULT_SCOPE;
+use error::Error;
+use internal::de::{FieldValueDeserializer, ValueDeserializer};
+use internal::gob::{Message, Stream};
+use internal::types::{Types, WireType};
+use internal::utils::Buffer;
+use schema::TypeId;
+
+use crate::{de, error, internal, schema};
+
+struct Section {
+    // the whole section, including the length prefix
+    range: Range<usize>,
+    payload: Range<usize>,
+    type_id: i64,
+}
+
+/// A whole gob stream, read into memory and indexed by value.
+///
+/// Values can be decoded any number of times and in any order, and the
+/// stream can be written back out byte for byte.
+pub struct Session {
+    bytes: Vec<u8>,
+    sections: Vec<Section>,
+    // indices into `sections` of all value sections
+    values: Vec<usize>,
+    defs: Types,
+}
+
+impl Session {
+    /// Reads `reader` to the end, decoding all type definitions along the way.
+    pub fn capture<R: Read>(reader: R) -> Result<Session, Error> {
+        Session::read_rest(&mut Stream::new(reader), &mut Buffer::new(), Types::new())
+    }
+
+    // reads all remaining sections, starting out with what is already
+    // buffered and with the types in `defs` known
+    pub(crate) fn read_rest<R: Read>(
+        stream: &mut Stream<R>,
+        buffer: &mut Buffer,
+        defs: Types,
+    ) -> Result<Session, Error> {
+        let mut session = Session {
+            bytes: Vec::new(),
+            sections: Vec::new(),
+            values: Vec::new(),
+            defs,
+        };
+
+        while let Some(header) = stream.read_section(buffer)? {
+            let section = &buffer.bytes()[..header.payload_range.end];
+
+            if header.type_id < 0 {
+                let payload = &section[header.payload_range.clone()];
+                let mut msg = Message::new(Cursor::new(payload));
+                let de = FieldValueDeserializer::new(
+                    TypeId::WIRE_TYPE,
+                    &session.defs,
+                    DEFAULT_SCOPE,
+                    &mut msg,
+                );
+                let wire_type = WireType::deserialize(de)?;
+                if -header.type_id != wire_type.common().id.0 {
+                    return Err(Error::deserialize("type id mismatch"));
+                }
+                session.defs.insert(wire_type);
+            } else {
+                session.values.push(session.sections.len());
+            }
+
+            let start = session.bytes.len();
+            session.bytes.extend_from_slice(section);
+            session.sections.push(Section {
+                range: start..session.bytes.len(),
+                payload: start + header.payload_range.start..session.bytes.len(),
+                type_id: header.type_id,
+            });
+            let len = section.len();
+            buffer.advance(len);
+        }
+
+        Ok(session)
+    }
+
+    /// Number of values in the stream.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Number of sections in the stream, type definitions included.
+    pub fn sections(&self) -> usize {
+        self.sections.len()
+    }
+
+    /// The type of the value at `index`.
+    pub fn value_type(&self, index: usize) -> Option<TypeId> {
+        self.value(index).map(|section| TypeId(section.type_id))
+    }
+
+    /// The name a type was defined under, if it was defined in the stream.
+    pub fn type_name(&self, id: TypeId) -> Option<&str> {
+        self.defs.lookup(id).map(|def| &*def.common().name)
+    }
+
+    /// Decodes the value at `index`.
+    pub fn deserialize<'de, T>(&'de self, index: usize) -> Result<T, Error>
+    where
+        T: Deserialize<'de>,
+    {
+        let section = self
+            .value(index)
+            .ok_or_else(|| Error::deserialize(format!("no value at index {}", index)))?;
+        let mut msg = Message::new(Cursor::new(&self.bytes[section.payload.clone()]));
+        let de =
+            ValueDeserializer::new(TypeId(section.type_id), &self.defs, DEFAULT_SCOPE, &mut msg);
+        T::deserialize(de)
+    }
+
+    /// Raw bytes of the value section at `index`, including its length
+    /// prefix and type id.
+    pub fn raw_value(&self, index: usize) -> Option<&[u8]> {
+        self.value(index)
+            .map(|section| &self.bytes[section.range.clone()])
+    }
+
+    /// The captured stream, exactly as it was read.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Writes the captured stream back out.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&self.bytes)
+    }
+
+    /// Writes a stream holding only the value at `index`, preceded by all
+    /// type definitions sent before it.
+    pub fn write_value_to<W: Write>(&self, index: usize, mut writer: W) -> Result<(), Error> {
+        let position = match self.values.get(index) {
+            Some(&position) => position,
+            None => return Err(Error::deserialize(format!("no value at index {}", index))),
+        };
+        for section in &self.sections[..position] {
+            if section.type_id < 0 {
+                writer.write_all(&self.bytes[section.range.clone()])?;
+            }
+        }
+        writer.write_all(&self.bytes[self.sections[position].range.clone()])?;
+        Ok(())
+    }
+
+    /// Decodes all values in parallel on the rayon thread pool.
+    ///
+    /// The iterator is indexed, so collecting it keeps values in stream
+    /// order.
+    #[cfg(feature = "rayon")]
+    pub fn par_iter<'a, T>(&'a self) -> impl IndexedParallelIterator<Item = Result<T, Error>> + 'a
+    where
+        T: DeserializeOwned + Send + 'a,
+    {
+        (0..self.len())
+            .into_par_iter()
+            .map(move |index| self.deserialize(index))
+    }
+
+    fn value(&self, index: usize) -> Option<&Section> {
+        self.values
+            .get(index)
+            .map(|&position| &self.sections[position])
+    }
+}
@@ -0,0 +1,145 @@
+//! `bytes::Bytes` values that are neither copied on their way out nor on
+//! their way in.
+//!
+//! `SharedBytes` is sent as a byte slice. Its contents are written to the
+//! output straight from the `Bytes` rather than being buffered along with
+//! the rest of the value, and when decoding from a
+//! `SliceStreamDeserializer` created with `from_bytes`, it is a slice of
+//! the input sharing its reference count.
+//!
+//! Fields declared as plain `Bytes` get the same treatment with
+//! `#[serde(with = "gob::shared_bytes")]`, as long as no schema is derived
+//! for them.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::ops::Deref;
+
+use bytes::Bytes;
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
+use serde_schema::types::TypeId;
+use serde_schema::{Schema, SchemaSerialize};
+
+/// A `Bytes` value sent as a byte slice without being copied.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SharedBytes(pub Bytes);
+
+impl SharedBytes {
+    pub fn into_inner(self) -> Bytes {
+        self.0
+    }
+}
+
+impl Deref for SharedBytes {
+    type Target = Bytes;
+
+    fn deref(&self) -> &Bytes {
+        &self.0
+    }
+}
+
+impl From<Bytes> for SharedBytes {
+    fn from(bytes: Bytes) -> SharedBytes {
+        SharedBytes(bytes)
+    }
+}
+
+impl Serialize for SharedBytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize(&self.0, serializer)
+    }
+}
+
+impl SchemaSerialize for SharedBytes {
+    fn schema_register<S: Schema>(_: &mut S) -> Result<S::TypeId, S::Error> {
+        Ok(S::TypeId::BYTES)
+    }
+}
+
+impl<'de> Deserialize<'de> for SharedBytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize(deserializer).map(SharedBytes)
+    }
+}
+
+thread_local! {
+    // the value being serialized, for the serializer to pick up
+    static OUTGOING: RefCell<Option<Bytes>> = const { RefCell::new(None) };
+    // the input of the innermost stream deserializer decoding from `Bytes`
+    static SOURCE: RefCell<Option<Bytes>> = const { RefCell::new(None) };
+}
+
+/// Serializes `bytes` as a byte slice, see the module documentation.
+pub fn serialize<S: Serializer>(bytes: &Bytes, serializer: S) -> Result<S::Ok, S::Error> {
+    OUTGOING.with(|outgoing| *outgoing.borrow_mut() = Some(bytes.clone()));
+    let result = serializer.serialize_bytes(bytes);
+    OUTGOING.with(|outgoing| outgoing.borrow_mut().take());
+    result
+}
+
+/// Deserializes a byte slice into `Bytes`, see the module documentation.
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Bytes, D::Error> {
+    deserializer.deserialize_bytes(BytesVisitor)
+}
+
+// hands out the `Bytes` that `v` is the contents of, if it is being
+// serialized through `serialize`
+pub(crate) fn take_outgoing(v: &[u8]) -> Option<Bytes> {
+    OUTGOING.with(|outgoing| {
+        let mut outgoing = outgoing.borrow_mut();
+        match *outgoing {
+            Some(ref bytes) if bytes.as_ptr() == v.as_ptr() && bytes.len() == v.len() => {
+                outgoing.take()
+            }
+            _ => None,
+        }
+    })
+}
+
+/// Makes `source` the input that decoded byte slices are shared with, until
+/// the guard is dropped.
+pub(crate) fn share_source(source: Option<Bytes>) -> SourceGuard {
+    SourceGuard(SOURCE.with(|current| current.replace(source)))
+}
+
+pub(crate) struct SourceGuard(Option<Bytes>);
+
+impl Drop for SourceGuard {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        SOURCE.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Bytes;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a byte slice")
+    }
+
+    fn visit_borrowed_bytes<E: de::Error>(self, v: &'de [u8]) -> Result<Bytes, E> {
+        let shared = SOURCE.with(|source| match *source.borrow() {
+            Some(ref source) if contains(source, v) => Some(source.slice_ref(v)),
+            _ => None,
+        });
+        Ok(shared.unwrap_or_else(|| Bytes::from(v)))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Bytes, E> {
+        Ok(Bytes::from(v))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Bytes, E> {
+        Ok(Bytes::from(v))
+    }
+}
+
+fn contains(source: &Bytes, v: &[u8]) -> bool {
+    let start = source.as_ptr() as usize;
+    let ptr = v.as_ptr() as usize;
+    ptr >= start && ptr + v.len() <= start + source.len()
+}
@@ -0,0 +1,379 @@
+//! Random gob types and values for fuzzing, and fixtures recorded off
+//! live connections
+
+use std::borrow::Cow;
+
+use proptest::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::de::DeserializeOwned;
+use serde_schema::SchemaSerialize;
+
+use de::{StreamDeserializer, TypeDefs};
+use error::Error;
+use internal::gob::Message;
+use internal::ser::serialize_wire_types::serialize_wire_type;
+use internal::types::{
+    ArrayType, CommonType, FieldType, MapType, SliceType, StructType, TypeId, Types, WireType,
+};
+use ser::StreamSerializer;
+use value::Value;
+
+use crate::{de, error, internal, ser, value};
+
+mod fixture;
+pub use self::fixture::{Recorder, Replay};
+
+// keeps values of recursive types finite
+const MAX_DEPTH: usize = 8;
+const MAX_LEN: usize = 4;
+// keeps generated types small
+const MAX_TYPE_DEPTH: usize = 3;
+const MAX_FIELDS: usize = 4;
+
+const BUILTINS: &[TypeId] = &[
+    TypeId::BOOL,
+    TypeId::INT,
+    TypeId::UINT,
+    TypeId::FLOAT,
+    TypeId::BYTES,
+    TypeId::STRING,
+    TypeId::COMPLEX,
+    TypeId::INTERFACE,
+];
+const MAP_KEYS: &[TypeId] = &[TypeId::BOOL, TypeId::INT, TypeId::UINT, TypeId::STRING];
+// the first id Go hands out to types it defines
+const FIRST_TYPE_ID: i64 = 65;
+
+/// Generates a random, well-formed value message of type `type_id`.
+///
+/// The result is a complete value section (length prefix, type id and
+/// payload) that decodes against the definitions in `defs`. Sequences and
+/// maps are kept short, and interface values are always nil, since nothing
+/// is known about the types they may hold.
+pub fn arbitrary_value<R: Rng>(
+    defs: &TypeDefs,
+    type_id: TypeId,
+    rng: &mut R,
+) -> Result<Vec<u8>, Error> {
+    let defs = &defs.types;
+    let mut payload = Message::new(Vec::new());
+    payload.write_int(type_id.0);
+    match defs.lookup(type_id) {
+        Some(&WireType::Struct(_)) => {}
+        _ => payload.write_uint(0),
+    }
+    Generator { defs, rng }.value(type_id, &mut payload, 0)?;
+    Ok(section(payload.into_inner()))
+}
+
+// prefixes a type id and payload with their length
+fn section(payload: Vec<u8>) -> Vec<u8> {
+    let mut msg = Message::new(Vec::with_capacity(payload.len() + 9));
+    msg.write_uint(payload.len() as u64);
+    let mut section = msg.into_inner();
+    section.extend_from_slice(&payload);
+    section
+}
+
+/// A random type along with values of it, as a self-contained stream.
+#[derive(Clone, Debug)]
+pub struct Sample {
+    defs: TypeDefs,
+    type_id: TypeId,
+    definitions: Vec<u8>,
+    values: Vec<Vec<u8>>,
+}
+
+impl Sample {
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
+
+    /// The definitions of the type and all types it refers to.
+    pub fn type_defs(&self) -> &TypeDefs {
+        &self.defs
+    }
+
+    /// The sections defining the type, in the order they are sent.
+    pub fn definitions(&self) -> &[u8] {
+        &self.definitions
+    }
+
+    /// The value sections, see `arbitrary_value`.
+    pub fn values(&self) -> &[Vec<u8>] {
+        &self.values
+    }
+
+    /// The definitions followed by all values, as they would be sent.
+    pub fn stream(&self) -> Vec<u8> {
+        let mut stream = self.definitions.clone();
+        for value in &self.values {
+            stream.extend_from_slice(value);
+        }
+        stream
+    }
+}
+
+/// Generates a random type, made of slices, arrays, maps and structs of
+/// all builtin types, and `values` values of it.
+pub fn arbitrary_sample<R: Rng>(rng: &mut R, values: usize) -> Result<Sample, Error> {
+    let mut types = TypeGenerator {
+        rng: &mut *rng,
+        defs: Types::new(),
+        next_id: FIRST_TYPE_ID,
+        definitions: Vec::new(),
+    };
+    let type_id = types.type_id(0)?;
+    let defs = TypeDefs { types: types.defs };
+    let definitions = types.definitions;
+    let values = (0..values)
+        .map(|_| arbitrary_value(&defs, type_id, rng))
+        .collect::<Result<_, _>>()?;
+    Ok(Sample {
+        defs,
+        type_id,
+        definitions,
+        values,
+    })
+}
+
+/// A proptest strategy for `Sample`s with up to `max_values` values.
+///
+/// Samples are generated from a random seed, so shrinking a failing case
+/// yields another random sample rather than a smaller one.
+pub fn samples(max_values: usize) -> impl Strategy<Value = Sample> {
+    (any::<u64>(), 0..=max_values).prop_map(|(seed, values)| {
+        let mut rng = StdRng::seed_from_u64(seed);
+        arbitrary_sample(&mut rng, values).expect("generated types are valid")
+    })
+}
+
+/// Decodes all values of `sample` from its stream, failing on the first
+/// that does not decode.
+pub fn check_sample(sample: &Sample) -> Result<Vec<Value>, Error> {
+    let stream = sample.stream();
+    let mut stream = StreamDeserializer::new(&stream[..]);
+    let mut values = Vec::new();
+    while let Some(value) = stream.deserialize::<Value>()? {
+        values.push(value);
+    }
+    if values.len() != sample.values.len() {
+        return Err(Error::deserialize(format!(
+            "{} values decoded, {} generated",
+            values.len(),
+            sample.values.len()
+        )));
+    }
+    Ok(values)
+}
+
+/// Encodes `value` onto a fresh stream and decodes it again, to check in a
+/// property test that a type survives the trip:
+///
+/// ```
+/// # #[macro_use] extern crate proptest;
+/// # extern crate gob;
+/// # use std::collections::BTreeMap;
+/// # use gob::testing::round_trip;
+/// proptest! {
+///     fn scores(scores: BTreeMap<String, Vec<i64>>) {
+///         prop_assert_eq!(round_trip(&scores).unwrap(), scores);
+///     }
+/// }
+/// # fn main() { scores(); }
+/// ```
+pub fn round_trip<T>(value: &T) -> Result<T, Error>
+where
+    T: SchemaSerialize + DeserializeOwned,
+{
+    let mut stream = StreamSerializer::new(Vec::new());
+    stream.serialize(value)?;
+    let buffer = stream.into_writer();
+    let mut stream = StreamDeserializer::new(&buffer[..]);
+    match stream.deserialize()? {
+        Some(decoded) => Ok(decoded),
+        None => Err(Error::deserialize("no value encoded")),
+    }
+}
+
+struct TypeGenerator<'a, R> {
+    rng: &'a mut R,
+    defs: Types,
+    next_id: i64,
+    definitions: Vec<u8>,
+}
+
+impl<'a, R: Rng> TypeGenerator<'a, R> {
+    // picks a builtin type, or defines a new one out of others; the root
+    // type is always defined
+    fn type_id(&mut self, depth: usize) -> Result<TypeId, Error> {
+        if depth > 0 && (depth >= MAX_TYPE_DEPTH || self.rng.gen_bool(0.4)) {
+            return Ok(BUILTINS[self.rng.gen_range(0..BUILTINS.len())]);
+        }
+        // the types referred to are defined first, as Go does
+        let wire_type = match self.rng.gen_range(0..4) {
+            0 => {
+                let elem = self.type_id(depth + 1)?;
+                WireType::Slice(SliceType {
+                    common: self.common("[]"),
+                    elem,
+                })
+            }
+            1 => {
+                let elem = self.type_id(depth + 1)?;
+                WireType::Array(ArrayType {
+                    common: self.common("[N]"),
+                    elem,
+                    len: self.rng.gen_range(1..=MAX_LEN) as i64,
+                })
+            }
+            2 => {
+                let key = MAP_KEYS[self.rng.gen_range(0..MAP_KEYS.len())];
+                let elem = self.type_id(depth + 1)?;
+                WireType::Map(MapType {
+                    common: self.common("map"),
+                    key,
+                    elem,
+                })
+            }
+            _ => {
+                let len = self.rng.gen_range(1..=MAX_FIELDS);
+                let mut fields = Vec::with_capacity(len);
+                for i in 0..len {
+                    fields.push(FieldType {
+                        name: Cow::Owned(format!("F{}", i)),
+                        id: self.type_id(depth + 1)?,
+                    });
+                }
+                WireType::Struct(StructType {
+                    common: self.common("T"),
+                    fields: Cow::Owned(fields),
+                })
+            }
+        };
+        let id = wire_type.common().id;
+        self.definitions
+            .extend_from_slice(&section(serialize_wire_type(&wire_type)?));
+        self.defs.insert(wire_type);
+        Ok(id)
+    }
+
+    // takes the next id, naming the type after it
+    fn common(&mut self, prefix: &str) -> CommonType {
+        let id = TypeId(self.next_id);
+        self.next_id += 1;
+        CommonType {
+            name: Cow::Owned(format!("{}{}", prefix, id.0)),
+            id,
+        }
+    }
+}
+
+struct Generator<'a, R> {
+    defs: &'a Types,
+    rng: &'a mut R,
+}
+
+impl<'a, R: Rng> Generator<'a, R> {
+    fn value(&mut self, id: TypeId, out: &mut Message<Vec<u8>>, depth: usize) -> Result<(), Error> {
+        match id {
+            TypeId::BOOL => out.write_bool(self.rng.gen()),
+            TypeId::INT => out.write_int(self.int()),
+            TypeId::UINT => out.write_uint(self.int() as u64),
+            TypeId::FLOAT => out.write_float(self.float()),
+            TypeId::BYTES => {
+                let len = self.rng.gen_range(0..=MAX_LEN * 2);
+                let bytes = (0..len).map(|_| self.rng.gen()).collect::<Vec<u8>>();
+                out.write_bytes(&bytes);
+            }
+            TypeId::STRING => {
+                let len = self.rng.gen_range(0..=MAX_LEN * 2);
+                let string = (0..len).map(|_| self.char()).collect::<String>();
+                out.write_bytes(string.as_bytes());
+            }
+            TypeId::COMPLEX => {
+                out.write_float(self.float());
+                out.write_float(self.float());
+            }
+            TypeId::INTERFACE => out.write_uint(0),
+            _ => match self.defs.lookup(id) {
+                Some(WireType::Array(array_type)) => {
+                    out.write_uint(array_type.len as u64);
+                    for _ in 0..array_type.len {
+                        self.value(array_type.elem, out, depth + 1)?;
+                    }
+                }
+                Some(WireType::Slice(slice_type)) => {
+                    let len = self.len(depth);
+                    out.write_uint(len as u64);
+                    for _ in 0..len {
+                        self.value(slice_type.elem, out, depth + 1)?;
+                    }
+                }
+                Some(WireType::Map(map_type)) => {
+                    let len = self.len(depth);
+                    out.write_uint(len as u64);
+                    for _ in 0..len {
+                        self.value(map_type.key, out, depth + 1)?;
+                        self.value(map_type.elem, out, depth + 1)?;
+                    }
+                }
+                // any bytes will do, since only the type itself can tell
+                // which it accepts
+                Some(WireType::GobEncoder(_)) => self.value(TypeId::BYTES, out, depth)?,
+                Some(WireType::Struct(struct_type)) => {
+                    let mut last = -1;
+                    for (i, field) in struct_type.fields.iter().enumerate() {
+                        // left out fields decode as their zero value
+                        if depth >= MAX_DEPTH || self.rng.gen_bool(0.25) {
+                            continue;
+                        }
+                        out.write_uint((i as i64 - last) as u64);
+                        self.value(field.id, out, depth + 1)?;
+                        last = i as i64;
+                    }
+                    out.write_uint(0);
+                }
+                None => {
+                    return Err(Error::deserialize(format!("unknown type id {}", id.0)));
+                }
+            },
+        }
+        Ok(())
+    }
+
+    fn len(&mut self, depth: usize) -> usize {
+        if depth >= MAX_DEPTH {
+            0
+        } else {
+            self.rng.gen_range(0..=MAX_LEN)
+        }
+    }
+
+    // a mix of small numbers, which fit in a single byte, extremes and
+    // anything in between
+    fn int(&mut self) -> i64 {
+        match self.rng.gen_range(0..4) {
+            0 => self.rng.gen_range(-128..128),
+            1 => [0, i64::MIN, i64::MAX][self.rng.gen_range(0..3)],
+            _ => self.rng.gen(),
+        }
+    }
+
+    fn float(&mut self) -> f64 {
+        match self.rng.gen_range(0..4) {
+            0 => f64::from_bits(self.rng.gen()),
+            1 => self.rng.gen_range(-1000..1000) as f64,
+            _ => self.rng.gen(),
+        }
+    }
+
+    fn char(&mut self) -> char {
+        if self.rng.gen_bool(0.75) {
+            self.rng.gen_range(' '..='~')
+        } else {
+            self.rng.gen()
+        }
+    }
+}
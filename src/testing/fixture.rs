@@ -0,0 +1,194 @@
+use std::io::{self, Cursor, Read, Write};
+
+use error::Error;
+use internal::gob::Message;
+
+use crate::{error, internal};
+
+const MAGIC: &[u8] = b"gob fixture 1\n";
+
+// what each record of a fixture holds
+const DATA: u64 = 0;
+const END: u64 = 1;
+const ERROR: u64 = 2;
+
+// error kinds replayed as themselves, identified by their position; all
+// others are replayed as `Other`
+const ERROR_KINDS: &[io::ErrorKind] = &[
+    io::ErrorKind::Other,
+    io::ErrorKind::Interrupted,
+    io::ErrorKind::WouldBlock,
+    io::ErrorKind::TimedOut,
+    io::ErrorKind::UnexpectedEof,
+    io::ErrorKind::ConnectionReset,
+    io::ErrorKind::ConnectionAborted,
+    io::ErrorKind::BrokenPipe,
+];
+
+/// Records everything read from a reader into a fixture.
+///
+/// Wrapping the reader of a connection, a recorder writes each read into
+/// the fixture as it happens, type definitions and read errors included. A
+/// `Replay` plays the fixture back as a reader returning the same bytes in
+/// the same reads, so that a deserializer sees exactly what it saw on the
+/// connection:
+///
+/// ```
+/// # use gob::testing::{Recorder, Replay};
+/// # use gob::{StreamDeserializer, StreamSerializer};
+/// # let mut stream = StreamSerializer::new(Vec::new());
+/// # stream.serialize(&"hello").unwrap();
+/// # let connection = std::io::Cursor::new(stream.into_writer());
+/// let recorder = Recorder::new(connection, Vec::new()).unwrap();
+/// let mut stream = StreamDeserializer::new(recorder);
+/// let live = stream.deserialize::<String>().unwrap();
+/// let (_, fixture) = stream.into_inner().into_parts();
+///
+/// let mut replayed = StreamDeserializer::new(Replay::new(&fixture).unwrap());
+/// assert_eq!(replayed.deserialize::<String>().unwrap(), live);
+/// ```
+pub struct Recorder<R, W> {
+    inner: R,
+    fixture: W,
+}
+
+impl<R: Read, W: Write> Recorder<R, W> {
+    /// Starts recording reads from `inner` into `fixture`.
+    pub fn new(inner: R, mut fixture: W) -> io::Result<Self> {
+        fixture.write_all(MAGIC)?;
+        Ok(Recorder { inner, fixture })
+    }
+
+    fn record(&mut self, result: &io::Result<usize>, buf: &[u8]) -> io::Result<()> {
+        let mut msg = Message::new(Vec::new());
+        match *result {
+            Ok(0) => msg.write_uint(END),
+            Ok(len) => {
+                msg.write_uint(DATA);
+                msg.write_bytes(&buf[..len]);
+            }
+            Err(ref err) => {
+                let kind = ERROR_KINDS
+                    .iter()
+                    .position(|&kind| kind == err.kind())
+                    .unwrap_or(0);
+                msg.write_uint(ERROR);
+                msg.write_uint(kind as u64);
+                msg.write_bytes(err.to_string().as_bytes());
+            }
+        }
+        self.fixture.write_all(&msg.into_inner())
+    }
+}
+
+impl<R, W> Recorder<R, W> {
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Stops recording, giving back the reader and the fixture.
+    pub fn into_parts(self) -> (R, W) {
+        (self.inner, self.fixture)
+    }
+}
+
+impl<R: Read, W: Write> Read for Recorder<R, W> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let result = self.inner.read(buf);
+        self.record(&result, buf)?;
+        result
+    }
+}
+
+enum Record<'a> {
+    Data(&'a [u8]),
+    End,
+    Error(io::ErrorKind, &'a str),
+}
+
+/// Plays back a fixture written by a `Recorder`.
+///
+/// Each read returns what the recorded read returned, or as much of it as
+/// fits, the rest following with the next read. Once all reads are played
+/// back, the replay reports the end of input.
+pub struct Replay<'a> {
+    records: Vec<Record<'a>>,
+    next: usize,
+}
+
+impl<'a> Replay<'a> {
+    pub fn new(fixture: &'a [u8]) -> Result<Replay<'a>, Error> {
+        if !fixture.starts_with(MAGIC) {
+            return Err(Error::deserialize("not a gob fixture"));
+        }
+        let mut msg = Message::new(Cursor::new(&fixture[MAGIC.len()..]));
+        let mut records = Vec::new();
+        while (msg.get_ref().position() as usize) < msg.get_ref().get_ref().len() {
+            let record = match msg.read_uint()? {
+                DATA => Record::Data(read_bytes(&mut msg)?),
+                END => Record::End,
+                ERROR => {
+                    let kind = msg.read_uint()?;
+                    let kind = ERROR_KINDS.get(kind as usize).cloned();
+                    let message = ::std::str::from_utf8(read_bytes(&mut msg)?)
+                        .map_err(|_| Error::deserialize("invalid error message in fixture"))?;
+                    Record::Error(kind.unwrap_or(io::ErrorKind::Other), message)
+                }
+                other => {
+                    return Err(Error::deserialize(format!(
+                        "unknown fixture record {}",
+                        other
+                    )))
+                }
+            };
+            records.push(record);
+        }
+        Ok(Replay { records, next: 0 })
+    }
+
+    /// The bytes read off the connection, as a plain gob stream.
+    pub fn stream(&self) -> Vec<u8> {
+        let mut stream = Vec::new();
+        for record in &self.records {
+            if let Record::Data(data) = *record {
+                stream.extend_from_slice(data);
+            }
+        }
+        stream
+    }
+}
+
+fn read_bytes<'a>(msg: &mut Message<Cursor<&'a [u8]>>) -> Result<&'a [u8], Error> {
+    let len = msg.read_bytes_len()?;
+    let pos = msg.get_ref().position() as usize;
+    msg.get_mut().set_position((pos + len) as u64);
+    Ok(&msg.get_ref().get_ref()[pos..pos + len])
+}
+
+impl<'a> Read for Replay<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let record = match self.records.get_mut(self.next) {
+            Some(record) => record,
+            None => return Ok(0),
+        };
+        match *record {
+            Record::Data(ref mut data) => {
+                let len = buf.len().min(data.len());
+                buf[..len].copy_from_slice(&data[..len]);
+                *data = &data[len..];
+                if data.is_empty() {
+                    self.next += 1;
+                }
+                Ok(len)
+            }
+            Record::End => {
+                self.next += 1;
+                Ok(0)
+            }
+            Record::Error(kind, message) => {
+                self.next += 1;
+                Err(io::Error::new(kind, message))
+            }
+        }
+    }
+}
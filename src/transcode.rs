@@ -0,0 +1,184 @@
+//! Transcoding into other formats
+//!
+//! Drives a gob `Deserializer` directly into any `serde::Serializer`,
+//! without going through an intermediate Rust type:
+//!
+//! ```ignore
+//! let de = gob::Deserializer::from_slice(&bytes);
+//! let mut ser = serde_json::Serializer::new(std::io::stdout());
+//! gob::transcode::transcode(de, &mut ser)?;
+//! ```
+//!
+//! Structs are emitted as maps keyed by their field names, byte slices
+//! are passed on as bytes, and interface values are replaced by the
+//! concrete value they hold (or a none value if they are nil).
+
+use std::cell::RefCell;
+use std::fmt;
+
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{self, Serialize, SerializeMap, SerializeSeq, Serializer};
+
+use crate::de::Deserializer as GobDeserializer;
+
+/// Transcode a single gob value into the provided serializer.
+pub fn transcode<'de, S>(
+    deserializer: GobDeserializer<'de>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    Transcoder::new(deserializer).serialize(serializer)
+}
+
+/// Wraps a deserializer so that it can be serialized.
+///
+/// The wrapped value can only be serialized once, since serializing
+/// it consumes the deserializer.
+pub struct Transcoder<D>(RefCell<Option<D>>);
+
+impl<'de, D: Deserializer<'de>> Transcoder<D> {
+    pub fn new(deserializer: D) -> Transcoder<D> {
+        Transcoder(RefCell::new(Some(deserializer)))
+    }
+}
+
+impl<'de, D: Deserializer<'de>> Serialize for Transcoder<D> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0.borrow_mut().take() {
+            Some(deserializer) => deserializer
+                .deserialize_any(TranscodeVisitor(serializer))
+                .map_err(d2s),
+            None => Err(ser::Error::custom("transcoder can only be serialized once")),
+        }
+    }
+}
+
+struct TranscodeVisitor<S>(S);
+
+impl<'de, S: Serializer> Visitor<'de> for TranscodeVisitor<S> {
+    type Value = S::Ok;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any value")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<S::Ok, E> {
+        self.0.serialize_bool(v).map_err(s2d)
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<S::Ok, E> {
+        self.0.serialize_i64(v).map_err(s2d)
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<S::Ok, E> {
+        self.0.serialize_u64(v).map_err(s2d)
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<S::Ok, E> {
+        self.0.serialize_f64(v).map_err(s2d)
+    }
+
+    fn visit_char<E: de::Error>(self, v: char) -> Result<S::Ok, E> {
+        self.0.serialize_char(v).map_err(s2d)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<S::Ok, E> {
+        self.0.serialize_str(v).map_err(s2d)
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<S::Ok, E> {
+        self.0.serialize_bytes(v).map_err(s2d)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<S::Ok, E> {
+        self.0.serialize_none().map_err(s2d)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<S::Ok, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        self.0
+            .serialize_some(&Transcoder::new(deserializer))
+            .map_err(s2d)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<S::Ok, E> {
+        self.0.serialize_unit().map_err(s2d)
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<S::Ok, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        self.0
+            .serialize_newtype_struct("<newtype>", &Transcoder::new(deserializer))
+            .map_err(s2d)
+    }
+
+    fn visit_seq<V>(self, mut access: V) -> Result<S::Ok, V::Error>
+    where
+        V: SeqAccess<'de>,
+    {
+        let mut seq = self.0.serialize_seq(access.size_hint()).map_err(s2d)?;
+        while let Some(()) = access.next_element_seed(SeqSeed(&mut seq))? {}
+        seq.end().map_err(s2d)
+    }
+
+    fn visit_map<V>(self, mut access: V) -> Result<S::Ok, V::Error>
+    where
+        V: MapAccess<'de>,
+    {
+        let mut map = self.0.serialize_map(access.size_hint()).map_err(s2d)?;
+        while let Some(()) = access.next_key_seed(KeySeed(&mut map))? {
+            access.next_value_seed(ValueSeed(&mut map))?;
+        }
+        map.end().map_err(s2d)
+    }
+}
+
+struct SeqSeed<'a, S: 'a>(&'a mut S);
+
+impl<'de, 'a, S: SerializeSeq> DeserializeSeed<'de> for SeqSeed<'a, S> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        self.0
+            .serialize_element(&Transcoder::new(deserializer))
+            .map_err(s2d)
+    }
+}
+
+struct KeySeed<'a, S: 'a>(&'a mut S);
+
+impl<'de, 'a, S: SerializeMap> DeserializeSeed<'de> for KeySeed<'a, S> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        self.0
+            .serialize_key(&Transcoder::new(deserializer))
+            .map_err(s2d)
+    }
+}
+
+struct ValueSeed<'a, S: 'a>(&'a mut S);
+
+impl<'de, 'a, S: SerializeMap> DeserializeSeed<'de> for ValueSeed<'a, S> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        self.0
+            .serialize_value(&Transcoder::new(deserializer))
+            .map_err(s2d)
+    }
+}
+
+fn d2s<D: de::Error, S: ser::Error>(err: D) -> S {
+    S::custom(err.to_string())
+}
+
+fn s2d<S: ser::Error, D: de::Error>(err: S) -> D {
+    D::custom(err.to_string())
+}
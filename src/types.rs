@@ -0,0 +1,34 @@
+//! Type definitions as sent on the wire
+//!
+//! A stream sends a definition for each type it has values of, other than
+//! Go's predeclared types, before the first value of it. Each definition
+//! is a `WireType`, naming its type and referring to the types it is made
+//! of by their `TypeId`.
+//!
+//! Definitions read off a stream can be inspected through
+//! `de::TypeDefs`, e.g. to dispatch on the type of incoming values or to
+//! generate code for them:
+//!
+//! ```
+//! # use gob::types::WireType;
+//! # use gob::StreamSerializer;
+//! # use gob::StreamDeserializer;
+//! # let mut stream = StreamSerializer::new(Vec::new());
+//! # stream.serialize(&vec![1u8, 2]).unwrap();
+//! # stream.serialize(&vec![true]).unwrap();
+//! # let input = stream.into_writer();
+//! let mut stream = StreamDeserializer::new(&input[..]);
+//! while stream.deserialize::<gob::Value>().unwrap().is_some() {}
+//! for def in stream.type_defs().iter() {
+//!     if let WireType::Slice(slice) = def {
+//!         println!("{:?} is a slice of {:?}", def.common().id(), slice.elem());
+//!     }
+//! }
+//! ```
+
+pub use internal::types::{
+    ArrayType, CommonType, FieldType, GobEncoderType, MapType, SliceType, StructType, WireType,
+};
+pub use schema::TypeId;
+
+use crate::{internal, schema};
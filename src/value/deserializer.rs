@@ -0,0 +1,95 @@
+use std::iter;
+
+use serde::de::value::{MapAccessDeserializer, MapDeserializer, SeqDeserializer};
+use serde::de::{self, Deserializer, IntoDeserializer, Visitor};
+
+use error::Error;
+
+use crate::error;
+
+use super::Value;
+
+// values hand themselves out the way they were decoded, so that decoding
+// a `Value` into a type works like decoding the stream it came from
+impl<'de> Deserializer<'de> for Value {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Nil => visitor.visit_none(),
+            Value::Bool(v) => visitor.visit_bool(v),
+            Value::Int(v) => visitor.visit_i64(v),
+            Value::Uint(v) => visitor.visit_u64(v),
+            Value::Float(v) => visitor.visit_f64(v),
+            Value::Bytes(v) => visitor.visit_byte_buf(v),
+            Value::String(v) => visitor.visit_string(v),
+            Value::Seq(items) => {
+                let mut seq = SeqDeserializer::<_, Error>::new(items.into_iter());
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            Value::Map(entries) => {
+                let mut map = MapDeserializer::<_, Error>::new(entries.into_iter());
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+            Value::Struct(fields) => {
+                let mut map = MapDeserializer::<_, Error>::new(fields.into_iter());
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Nil => visitor.visit_none(),
+            value => visitor.visit_some(value),
+        }
+    }
+
+    // variants are selected by name, as a string or as the only field of a
+    // struct, like they are encoded by default
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let (variant, value) = match self {
+            Value::String(variant) => (variant, Value::Nil),
+            Value::Map(mut entries) if entries.len() == 1 => match entries.pop() {
+                Some((Value::String(variant), value)) => (variant, value),
+                _ => return Err(de::Error::custom("expected an enum")),
+            },
+            Value::Struct(mut fields) if fields.len() == 1 => fields.pop().unwrap(),
+            _ => return Err(de::Error::custom("expected an enum")),
+        };
+        let access = MapDeserializer::<_, Error>::new(iter::once((variant, value)));
+        visitor.visit_enum(MapAccessDeserializer::new(access))
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Nil => visitor.visit_unit(),
+            value => value.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit_struct newtype_struct seq tuple tuple_struct
+        map struct identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Value {
+    type Deserializer = Value;
+
+    fn into_deserializer(self) -> Value {
+        self
+    }
+}
@@ -0,0 +1,159 @@
+use serde::ser;
+use serde_json::{Map, Number, Value as JsonValue};
+
+use error::Error;
+
+use crate::error;
+
+use super::Value;
+
+/// The gob type a JSON number is encoded as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NumberKind {
+    Int,
+    Uint,
+    Float,
+}
+
+/// The gob type a JSON object is encoded as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ObjectKind {
+    /// A struct with a field for each key.
+    Struct,
+    /// A map from strings to values of a single type.
+    Map,
+}
+
+/// Converts JSON into `Value`s, deducing a gob type for each JSON value.
+///
+/// By default, objects become structs, numbers that are integers become
+/// ints (or uints, if they only fit in one) and all others floats. Arrays
+/// and objects holding both integers and floats hold floats only. Fields
+/// that are `null`, empty arrays and objects converted to empty maps are
+/// left out of structs, like Go leaves out zero values.
+///
+/// Hooks can override the deduced types. They are passed the path of the
+/// value, which joins the keys of the objects it is nested in with `.`,
+/// and appends `[]` for each array element or map value, e.g.
+/// `servers[].ports[]`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FromJson {
+    numbers: Option<NumberHook>,
+    objects: Option<ObjectHook>,
+}
+
+type NumberHook = fn(&str, &Number) -> Option<NumberKind>;
+type ObjectHook = fn(&str, &Map<String, JsonValue>) -> Option<ObjectKind>;
+
+impl FromJson {
+    pub fn new() -> FromJson {
+        FromJson::default()
+    }
+
+    /// Decide for each number which type it is encoded as, by returning
+    /// `Some` kind. Numbers the hook returns `None` for are deduced as
+    /// usual. Numbers that don't fit the kind returned fail to convert.
+    pub fn number_kind_with(mut self, hook: fn(&str, &Number) -> Option<NumberKind>) -> Self {
+        self.numbers = Some(hook);
+        self
+    }
+
+    /// Decide for each object whether it is encoded as a struct or as a
+    /// map, by returning `Some` kind. Objects the hook returns `None` for
+    /// become structs.
+    pub fn object_kind_with(
+        mut self,
+        hook: fn(&str, &Map<String, JsonValue>) -> Option<ObjectKind>,
+    ) -> Self {
+        self.objects = Some(hook);
+        self
+    }
+
+    /// Converts `json`, which can then be encoded with
+    /// `StreamSerializer::serialize_value`.
+    pub fn convert(&self, json: &JsonValue) -> Result<Value, Error> {
+        self.value("", json)
+    }
+
+    fn value(&self, path: &str, json: &JsonValue) -> Result<Value, Error> {
+        match *json {
+            JsonValue::Null => Ok(Value::Nil),
+            JsonValue::Bool(v) => Ok(Value::Bool(v)),
+            JsonValue::Number(ref n) => self.number(path, n),
+            JsonValue::String(ref s) => Ok(Value::String(s.clone())),
+            JsonValue::Array(ref items) => {
+                let path = format!("{}[]", path);
+                let items = items
+                    .iter()
+                    .map(|item| self.value(&path, item))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                Ok(Value::Seq(unify_numbers(items)))
+            }
+            JsonValue::Object(ref obj) => {
+                let kind = self.objects.and_then(|hook| hook(path, obj));
+                if kind == Some(ObjectKind::Map) {
+                    let path = format!("{}[]", path);
+                    let mut keys = Vec::with_capacity(obj.len());
+                    let mut values = Vec::with_capacity(obj.len());
+                    for (key, value) in obj {
+                        keys.push(Value::String(key.clone()));
+                        values.push(self.value(&path, value)?);
+                    }
+                    let entries = keys.into_iter().zip(unify_numbers(values));
+                    return Ok(Value::Map(entries.collect()));
+                }
+                let mut fields = Vec::with_capacity(obj.len());
+                for (key, value) in obj {
+                    let path = if path.is_empty() {
+                        key.clone()
+                    } else {
+                        format!("{}.{}", path, key)
+                    };
+                    let value = match self.value(&path, value)? {
+                        Value::Seq(ref items) if items.is_empty() => Value::Nil,
+                        Value::Map(ref entries) if entries.is_empty() => Value::Nil,
+                        value => value,
+                    };
+                    fields.push((key.clone(), value));
+                }
+                Ok(Value::Struct(fields))
+            }
+        }
+    }
+
+    fn number(&self, path: &str, n: &Number) -> Result<Value, Error> {
+        let kind = self.numbers.and_then(|hook| hook(path, n));
+        let value = match kind {
+            None if n.is_i64() => n.as_i64().map(Value::Int),
+            None if n.is_u64() => n.as_u64().map(Value::Uint),
+            None | Some(NumberKind::Float) => n.as_f64().map(Value::Float),
+            Some(NumberKind::Int) => n.as_i64().map(Value::Int),
+            Some(NumberKind::Uint) => n.as_u64().map(Value::Uint),
+        };
+        value.ok_or_else(|| {
+            let kind = kind.unwrap_or(NumberKind::Float);
+            ser::Error::custom(format!(
+                "number {} at {:?} does not fit {:?}",
+                n, path, kind
+            ))
+        })
+    }
+}
+
+// integers among floats become floats, so that they share a type
+fn unify_numbers(mut values: Vec<Value>) -> Vec<Value> {
+    let has_float = values.iter().any(|v| matches!(*v, Value::Float(_)));
+    let has_int = values
+        .iter()
+        .any(|v| matches!(*v, Value::Int(_) | Value::Uint(_)));
+    if has_float && has_int {
+        for value in &mut values {
+            match *value {
+                Value::Int(v) => *value = Value::Float(v as f64),
+                Value::Uint(v) => *value = Value::Float(v as f64),
+                _ => {}
+            }
+        }
+    }
+    values
+}
@@ -0,0 +1,381 @@
+//! Dynamically typed gob values
+
+use std::fmt::{self, Write};
+
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{self, Serialize, SerializeMap, SerializeSeq, SerializeStruct, Serializer};
+
+use error::Error;
+
+use crate::error;
+
+#[cfg(feature = "json")]
+mod json;
+#[cfg(feature = "json")]
+pub use self::json::{FromJson, NumberKind, ObjectKind};
+
+mod deserializer;
+
+mod shape;
+pub(crate) use self::shape::intern;
+pub(crate) use self::shape::Shape;
+
+/// Any value that can be decoded from a gob stream.
+///
+/// Structs decode into `Map`s keyed by field name, in wire order.
+/// Interface values decode into the value they hold, or `Nil`.
+///
+/// `Struct` is never produced by decoding, but can be used to encode
+/// struct values without a schema, see `StreamSerializer::serialize_value`.
+/// Fields that are `Nil` are left out.
+///
+/// A struct can keep the fields it doesn't declare in a
+/// `#[serde(flatten)] extra: HashMap<String, Value>` field. serde writes
+/// such structs as maps, which are written as the fields of the same name
+/// when the type they are written as is a struct, so that they can be sent
+/// back unchanged by serializing with the type id of the full struct.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Nil,
+    Bool(bool),
+    Int(i64),
+    Uint(u64),
+    Float(f64),
+    Bytes(Vec<u8>),
+    String(String),
+    Seq(Vec<Value>),
+    Map(Vec<(Value, Value)>),
+    Struct(Vec<(String, Value)>),
+}
+
+/// Returns the Go type name a `Value::Struct` is registered under when
+/// encoded without a schema.
+///
+/// The name is a hash over the names and inferred types of the non-nil
+/// fields, so that equally shaped values always share a name, across runs
+/// and platforms.
+pub fn synthetic_type_name(value: &Value) -> Result<String, Error> {
+    match *value {
+        Value::Struct(_) => Ok(Shape::of(value)?.name()),
+        _ => Err(ser::Error::custom("not a struct value")),
+    }
+}
+
+/// Controls how much of a `Value` gets rendered.
+///
+/// All limits are unset by default.
+#[derive(Clone, Debug, Default)]
+pub struct PrintOptions {
+    max_depth: Option<usize>,
+    max_items: Option<usize>,
+    max_string_len: Option<usize>,
+    hex_ints: bool,
+    hex_bytes: bool,
+}
+
+impl PrintOptions {
+    pub fn new() -> PrintOptions {
+        PrintOptions::default()
+    }
+
+    /// Collapse sequences and maps nested deeper than `depth`.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Show at most `items` elements of each sequence or map.
+    pub fn max_items(mut self, items: usize) -> Self {
+        self.max_items = Some(items);
+        self
+    }
+
+    /// Show at most `len` bytes of each string or byte slice.
+    pub fn max_string_len(mut self, len: usize) -> Self {
+        self.max_string_len = Some(len);
+        self
+    }
+
+    /// Print integers in hexadecimal.
+    pub fn hex_ints(mut self, hex: bool) -> Self {
+        self.hex_ints = hex;
+        self
+    }
+
+    /// Print byte slices as hex digits instead of escaped byte strings.
+    pub fn hex_bytes(mut self, hex: bool) -> Self {
+        self.hex_bytes = hex;
+        self
+    }
+}
+
+impl Value {
+    /// Renders the value as an indented, human readable tree.
+    pub fn render(&self, options: &PrintOptions) -> String {
+        let mut out = String::new();
+        self.write(&mut out, options, 0).unwrap();
+        out
+    }
+
+    fn write(&self, out: &mut String, opts: &PrintOptions, depth: usize) -> fmt::Result {
+        match *self {
+            Value::Nil => out.write_str("nil"),
+            Value::Bool(v) => write!(out, "{}", v),
+            Value::Int(v) if opts.hex_ints && v < 0 => write!(out, "-{:#x}", v.unsigned_abs()),
+            Value::Int(v) if opts.hex_ints => write!(out, "{:#x}", v),
+            Value::Int(v) => write!(out, "{}", v),
+            Value::Uint(v) if opts.hex_ints => write!(out, "{:#x}", v),
+            Value::Uint(v) => write!(out, "{}", v),
+            Value::Float(v) => write!(out, "{:?}", v),
+            Value::String(ref s) => {
+                let shown = truncate_str(s, opts.max_string_len);
+                write!(out, "{:?}", shown)?;
+                write_elided(out, s.len() - shown.len(), "bytes")
+            }
+            Value::Bytes(ref b) => {
+                let shown = &b[..opts.max_string_len.map_or(b.len(), |max| max.min(b.len()))];
+                if opts.hex_bytes {
+                    out.write_str("0x")?;
+                    for byte in shown {
+                        write!(out, "{:02x}", byte)?;
+                    }
+                } else {
+                    out.write_str("b\"")?;
+                    for byte in shown {
+                        for c in ::std::ascii::escape_default(*byte) {
+                            out.push(c as char);
+                        }
+                    }
+                    out.write_str("\"")?;
+                }
+                write_elided(out, b.len() - shown.len(), "bytes")
+            }
+            Value::Seq(ref items) => {
+                write_container(out, opts, depth, ('[', ']'), items.len(), |out, i| {
+                    items[i].write(out, opts, depth + 1)
+                })
+            }
+            Value::Map(ref entries) => {
+                write_container(out, opts, depth, ('{', '}'), entries.len(), |out, i| {
+                    let (ref key, ref value) = entries[i];
+                    match *key {
+                        Value::String(ref s) if is_identifier(s) => out.write_str(s)?,
+                        _ => key.write(out, opts, depth + 1)?,
+                    }
+                    out.write_str(": ")?;
+                    value.write(out, opts, depth + 1)
+                })
+            }
+            Value::Struct(ref fields) => {
+                write_container(out, opts, depth, ('{', '}'), fields.len(), |out, i| {
+                    let (ref name, ref value) = fields[i];
+                    write!(out, "{}: ", name)?;
+                    value.write(out, opts, depth + 1)
+                })
+            }
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.render(&PrintOptions::default()))
+    }
+}
+
+fn write_container<F>(
+    out: &mut String,
+    opts: &PrintOptions,
+    depth: usize,
+    (open, close): (char, char),
+    len: usize,
+    mut item: F,
+) -> fmt::Result
+where
+    F: FnMut(&mut String, usize) -> fmt::Result,
+{
+    if len == 0 {
+        return write!(out, "{}{}", open, close);
+    }
+    if opts.max_depth.is_some_and(|max| depth >= max) {
+        return write!(out, "{}... {} items{}", open, len, close);
+    }
+    let shown = opts.max_items.map_or(len, |max| max.min(len));
+    out.push(open);
+    out.push('\n');
+    for i in 0..shown {
+        indent(out, depth + 1);
+        item(out, i)?;
+        out.push('\n');
+    }
+    if shown < len {
+        indent(out, depth + 1);
+        writeln!(out, "... {} more", len - shown)?;
+    }
+    indent(out, depth);
+    out.push(close);
+    Ok(())
+}
+
+fn indent(out: &mut String, depth: usize) {
+    for _ in 0..depth {
+        out.push_str("  ");
+    }
+}
+
+fn write_elided(out: &mut String, elided: usize, unit: &str) -> fmt::Result {
+    if elided > 0 {
+        write!(out, "... ({} more {})", elided, unit)?;
+    }
+    Ok(())
+}
+
+fn truncate_str(s: &str, max: Option<usize>) -> &str {
+    match max {
+        Some(max) if max < s.len() => {
+            let mut end = max;
+            while !s.is_char_boundary(end) {
+                end -= 1;
+            }
+            &s[..end]
+        }
+        _ => s,
+    }
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '_' => chars.all(|c| c.is_alphanumeric() || c == '_'),
+        _ => false,
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Value, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any gob value")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::Int(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Value, E> {
+        Ok(Value::Uint(v))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Value, E> {
+        Ok(Value::Float(v))
+    }
+
+    fn visit_char<E: de::Error>(self, v: char) -> Result<Value, E> {
+        Ok(Value::Int(v as i64))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::String(v.to_owned()))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Value, E> {
+        Ok(Value::String(v))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Value, E> {
+        Ok(Value::Bytes(v.to_owned()))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Value, E> {
+        Ok(Value::Bytes(v))
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Nil)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Nil)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Value, D::Error> {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut access: A) -> Result<Value, A::Error> {
+        let mut items = Vec::with_capacity(access.size_hint().unwrap_or(0));
+        while let Some(item) = access.next_element()? {
+            items.push(item);
+        }
+        Ok(Value::Seq(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Value, A::Error> {
+        let mut entries = Vec::with_capacity(access.size_hint().unwrap_or(0));
+        while let Some(entry) = access.next_entry()? {
+            entries.push(entry);
+        }
+        Ok(Value::Map(entries))
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            Value::Nil => serializer.serialize_none(),
+            Value::Bool(v) => serializer.serialize_bool(v),
+            Value::Int(v) => serializer.serialize_i64(v),
+            Value::Uint(v) => serializer.serialize_u64(v),
+            Value::Float(v) => serializer.serialize_f64(v),
+            Value::Bytes(ref v) => serializer.serialize_bytes(v),
+            Value::String(ref v) => serializer.serialize_str(v),
+            Value::Seq(ref items) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            Value::Map(ref entries) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+            Value::Struct(ref fields) => {
+                let len = fields
+                    .iter()
+                    .filter(|field| !matches!(field.1, Value::Nil))
+                    .count();
+                // structs built at runtime have no static name
+                let mut state = serializer.serialize_struct("", len)?;
+                for (name, value) in fields {
+                    if !matches!(*value, Value::Nil) {
+                        state.serialize_field(intern(name), value)?;
+                    }
+                }
+                state.end()
+            }
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+
+use serde::ser;
+use serde_schema::types::Type;
+use serde_schema::Schema as SchemaTrait;
+
+use error::Error;
+use schema::{Schema, TypeId};
+
+use crate::{error, schema};
+
+use super::Value;
+
+/// The gob type inferred for a `Value`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Shape {
+    Bool,
+    Int,
+    Uint,
+    Float,
+    Bytes,
+    String,
+    Slice(Box<Shape>),
+    Map(Box<Shape>, Box<Shape>),
+    Struct(Vec<(String, Shape)>),
+}
+
+impl Shape {
+    pub(crate) fn of(value: &Value) -> Result<Shape, Error> {
+        match *value {
+            Value::Nil => Err(ser::Error::custom("cannot infer a type for nil")),
+            Value::Bool(_) => Ok(Shape::Bool),
+            Value::Int(_) => Ok(Shape::Int),
+            Value::Uint(_) => Ok(Shape::Uint),
+            Value::Float(_) => Ok(Shape::Float),
+            Value::Bytes(_) => Ok(Shape::Bytes),
+            Value::String(_) => Ok(Shape::String),
+            Value::Seq(ref items) => {
+                let elem = common(items.iter(), "sequence elements")?;
+                Ok(Shape::Slice(Box::new(elem)))
+            }
+            Value::Map(ref entries) => {
+                let key = common(entries.iter().map(|entry| &entry.0), "map keys")?;
+                let elem = common(entries.iter().map(|entry| &entry.1), "map values")?;
+                Ok(Shape::Map(Box::new(key), Box::new(elem)))
+            }
+            Value::Struct(ref fields) => fields
+                .iter()
+                .filter(|field| !matches!(field.1, Value::Nil))
+                .map(|(name, value)| Ok((name.clone(), Shape::of(value)?)))
+                .collect::<Result<_, Error>>()
+                .map(Shape::Struct),
+        }
+    }
+
+    /// Go syntax for the type, with structs written out as literals.
+    pub(crate) fn describe(&self) -> String {
+        match *self {
+            Shape::Bool => "bool".to_owned(),
+            Shape::Int => "int64".to_owned(),
+            Shape::Uint => "uint64".to_owned(),
+            Shape::Float => "float64".to_owned(),
+            Shape::Bytes => "[]byte".to_owned(),
+            Shape::String => "string".to_owned(),
+            Shape::Slice(ref elem) => format!("[]{}", elem.describe()),
+            Shape::Map(ref key, ref elem) => {
+                format!("map[{}]{}", key.describe(), elem.describe())
+            }
+            Shape::Struct(ref fields) if fields.is_empty() => "struct {}".to_owned(),
+            Shape::Struct(ref fields) => {
+                let fields = fields
+                    .iter()
+                    .map(|(name, shape)| format!("{} {}", name, shape.describe()))
+                    .collect::<Vec<_>>();
+                format!("struct {{ {} }}", fields.join("; "))
+            }
+        }
+    }
+
+    pub(crate) fn name(&self) -> String {
+        format!("T{:016x}", fnv1a(self.describe().as_bytes()))
+    }
+
+    pub(crate) fn register(&self, schema: &mut Schema) -> Result<TypeId, Error> {
+        match *self {
+            Shape::Bool => Ok(TypeId::BOOL),
+            Shape::Int => Ok(TypeId::INT),
+            Shape::Uint => Ok(TypeId::UINT),
+            Shape::Float => Ok(TypeId::FLOAT),
+            Shape::Bytes => Ok(TypeId::BYTES),
+            Shape::String => Ok(TypeId::STRING),
+            Shape::Slice(ref elem) => {
+                let elem_id = elem.register(schema)?;
+                schema.register_type(Type::build().seq_type(None, elem_id))
+            }
+            Shape::Map(ref key, ref elem) => {
+                let key_id = key.register(schema)?;
+                let elem_id = elem.register(schema)?;
+                schema.register_type(Type::build().map_type(key_id, elem_id))
+            }
+            Shape::Struct(ref fields) => {
+                let mut builder = Type::build().struct_type(intern(&self.name()), fields.len());
+                for (name, shape) in fields {
+                    builder = builder.field(intern(name), shape.register(schema)?);
+                }
+                schema.register_type(builder.end())
+            }
+        }
+    }
+}
+
+fn common<'a, I>(values: I, what: &str) -> Result<Shape, Error>
+where
+    I: Iterator<Item = &'a Value>,
+{
+    let mut shape = None;
+    for value in values {
+        let next = Shape::of(value)?;
+        match shape {
+            None => shape = Some(next),
+            Some(ref shape) if *shape == next => {}
+            Some(_) => return Err(ser::Error::custom(format!("{} differ in type", what))),
+        }
+    }
+    shape.ok_or_else(|| ser::Error::custom(format!("cannot infer a type for empty {}", what)))
+}
+
+// 64-bit FNV-1a, which is simple enough to be pinned down here so that
+// names do not change between releases or platforms.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+// Field and type names end up in `serde_schema` types and serde field
+// keys, which only hold static strings. Each distinct name is leaked once.
+pub(crate) fn intern(s: &str) -> &'static str {
+    lazy_static! {
+        static ref NAMES: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+    }
+    let mut names = NAMES.lock().unwrap();
+    if let Some(name) = names.get(s) {
+        return name;
+    }
+    let name: &'static str = Box::leak(s.to_owned().into_boxed_str());
+    names.insert(name);
+    name
+}
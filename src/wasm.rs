@@ -0,0 +1,70 @@
+//! Helpers for inspecting and producing gob payloads from JavaScript
+//!
+//! Built with the `wasm` feature, which exports them through
+//! `wasm-bindgen` as `decode_to_json(bytes)` and
+//! `encode_from_json(schema, json)`:
+//!
+//! ```text
+//! import { decode_to_json } from "gob";
+//! const values = JSON.parse(decode_to_json(new Uint8Array(buffer)));
+//! ```
+//!
+//! Schemas are described in JSON the same way as for `gob encode`, e.g.
+//! `{"struct": {"name": "Point", "fields": [{"name": "X", "type": "int"}]}}`.
+
+use serde_json;
+use wasm_bindgen::prelude::*;
+
+use de::Deserializer;
+use error::Error;
+use schema::json::{SchemaExpr, Typed};
+use ser::StreamSerializer;
+
+use crate::{de, error, schema, ser, transcode};
+
+/// Decodes all values in `bytes` into a JSON array.
+pub fn decode_to_json(bytes: &[u8]) -> Result<String, Error> {
+    let mut out = b"[".to_vec();
+    for (i, de) in Deserializer::from_slice_many(bytes).enumerate() {
+        if i > 0 {
+            out.push(b',');
+        }
+        transcode::transcode(de?, &mut serde_json::Serializer::new(&mut out))
+            .map_err(|err| Error::deserialize(err.to_string()))?;
+    }
+    out.push(b']');
+    Ok(String::from_utf8(out).expect("JSON is valid UTF-8"))
+}
+
+/// Encodes the JSON values in `json`, separated by whitespace, as values of
+/// the type `schema` describes.
+pub fn encode_from_json(schema: &str, json: &str) -> Result<Vec<u8>, Error> {
+    let schema = serde_json::from_str(schema)
+        .map_err(|err| Error::serialize(format!("invalid schema: {}", err)))?;
+    let expr = SchemaExpr::parse(&schema)
+        .map_err(|msg| Error::serialize(format!("invalid schema: {}", msg)))?;
+
+    let mut stream = StreamSerializer::new_with_write(Vec::new());
+    let type_id = expr.register(stream.schema_mut())?;
+    for value in serde_json::Deserializer::from_str(json).into_iter::<serde_json::Value>() {
+        let value = value.map_err(|err| Error::serialize(err.to_string()))?;
+        stream.serialize_with_type_id(
+            type_id,
+            &Typed {
+                expr: &expr,
+                value: &value,
+            },
+        )?;
+    }
+    Ok(stream.into_writer())
+}
+
+#[wasm_bindgen(js_name = decode_to_json)]
+pub fn decode_to_json_js(bytes: &[u8]) -> Result<String, JsError> {
+    decode_to_json(bytes).map_err(|err| JsError::new(&err.to_string()))
+}
+
+#[wasm_bindgen(js_name = encode_from_json)]
+pub fn encode_from_json_js(schema: &str, json: &str) -> Result<Vec<u8>, JsError> {
+    encode_from_json(schema, json).map_err(|err| JsError::new(&err.to_string()))
+}
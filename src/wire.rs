@@ -0,0 +1,91 @@
+//! Low-level section framing
+//!
+//! A gob stream is a sequence of sections, each made up of its length, a
+//! type id and a payload:
+//!
+//! ```text
+//! [ len | type id | payload... ]
+//! ```
+//!
+//! Values are sent under the (positive) id of their type, while type
+//! definitions are sent under the negated id they define. `SectionWriter`
+//! takes care of the framing and leaves the payload to the caller, which is
+//! useful for custom transports and for tools that pass sections on without
+//! decoding them.
+
+use std::io::Write;
+
+use internal::gob::Message;
+
+use error::Error;
+use schema::TypeId;
+use ser::{Output, OutputPart, OutputWrite};
+
+use crate::{error, internal, schema, ser};
+
+/// Writes framed sections to an `io::Write`.
+pub struct SectionWriter<W> {
+    out: OutputWrite<W>,
+}
+
+impl<W: Write> SectionWriter<W> {
+    pub fn new(w: W) -> Self {
+        SectionWriter {
+            out: OutputWrite::new(w),
+        }
+    }
+
+    /// Writes a section carrying a value of type `type_id`.
+    ///
+    /// `payload` appends the encoded value to the buffer it is passed. If it
+    /// fails, nothing is written.
+    pub fn write_value<F>(&mut self, type_id: TypeId, payload: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Vec<u8>) -> Result<(), Error>,
+    {
+        self.write_section(type_id.0, payload)
+    }
+
+    /// Writes a section carrying the definition of type `type_id`.
+    ///
+    /// `payload` appends the encoded `wireType` to the buffer it is passed.
+    /// If it fails, nothing is written.
+    pub fn write_type_definition<F>(&mut self, type_id: TypeId, payload: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Vec<u8>) -> Result<(), Error>,
+    {
+        self.write_section(-type_id.0, payload)
+    }
+
+    /// Writes a section with an already encoded payload, under the raw
+    /// (possibly negative) type id found on the wire.
+    pub fn write_raw(&mut self, type_id: i64, payload: &[u8]) -> Result<(), Error> {
+        self.write_section(type_id, |buf| {
+            buf.extend_from_slice(payload);
+            Ok(())
+        })
+    }
+
+    fn write_section<F>(&mut self, type_id: i64, payload: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Vec<u8>) -> Result<(), Error>,
+    {
+        let mut msg = Message::new(Vec::new());
+        msg.write_int(type_id);
+        payload(msg.get_mut())?;
+        self.out
+            .serialize_part(OutputPart::try_new(msg.into_inner())?)
+    }
+
+    pub fn get_ref(&self) -> &W {
+        self.out.get_ref()
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        self.out.get_mut()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out.into_inner()
+    }
+}
@@ -0,0 +1,431 @@
+//! Event writer
+//!
+//! `EventWriter` is the counterpart of `reader::EventReader`: it accepts
+//! the same events and produces a valid gob stream from them, so that
+//! transformations can be written as pipelines over events:
+//!
+//! ```ignore
+//! let mut reader = EventReader::new(input);
+//! let mut writer = EventWriter::new(output);
+//! while let Some(event) = reader.next_event()? {
+//!     match event {
+//!         Event::String(s) if s == b"secret" => writer.write_event(Event::String(b"***"))?,
+//!         event => writer.write_event(event)?,
+//!     }
+//! }
+//! ```
+//!
+//! Type definitions are taken note of, and only sent ahead of the first
+//! value that needs them. Events are checked against the types as they
+//! come in: a value must match the type it is written as, and struct
+//! fields must be written in the order they were defined in, though any
+//! of them may be left out.
+
+use std::collections::HashSet;
+use std::io::Write;
+
+use de::TypeDefs;
+use error::Error;
+use internal::gob::Message;
+use internal::ser::serialize_wire_types::serialize_wire_type;
+use internal::types::{lookup_builtin2, TypeId, Types, WireType};
+use reader::Event;
+use ser::{Output, OutputPart, OutputWrite};
+
+use crate::{de, error, internal, reader, ser};
+
+enum Frame {
+    Struct {
+        type_id: TypeId,
+        field_no: i64,
+    },
+    Seq {
+        elem: TypeId,
+        remaining: u64,
+    },
+    Map {
+        key: TypeId,
+        elem: TypeId,
+        remaining: u64,
+        key_next: bool,
+    },
+    // the value of an interface is preceded by its length, which is
+    // filled in at `start` once it is known
+    Interface {
+        start: usize,
+        done: bool,
+    },
+}
+
+/// Writes a gob stream event by event.
+pub struct EventWriter<W> {
+    out: OutputWrite<W>,
+    defs: Types,
+    sent: HashSet<TypeId>,
+    // the value being written, and its type
+    value: Message<Vec<u8>>,
+    type_id: Option<TypeId>,
+    frames: Vec<Frame>,
+    // the type of the value to be written next
+    pending: Option<TypeId>,
+}
+
+impl<W: Write> EventWriter<W> {
+    pub fn new(w: W) -> Self {
+        EventWriter {
+            out: OutputWrite::new(w),
+            defs: Types::new(),
+            sent: HashSet::new(),
+            value: Message::new(Vec::new()),
+            type_id: None,
+            frames: Vec::new(),
+            pending: None,
+        }
+    }
+
+    /// Create a new event writer that knows about all types in `defs`
+    /// up front. They are still sent ahead of the first value needing them.
+    pub fn with_type_defs(w: W, defs: TypeDefs) -> Self {
+        EventWriter {
+            defs: defs.types,
+            ..EventWriter::new(w)
+        }
+    }
+
+    /// Whether a value was started but not finished yet.
+    pub fn in_value(&self) -> bool {
+        self.type_id.is_some()
+    }
+
+    /// Writes the next event.
+    ///
+    /// A value is written to the underlying writer once it is complete.
+    /// Events that don't fit in where they are written fail without
+    /// changing anything, so that the value can be completed regardless.
+    pub fn write_event(&mut self, event: Event) -> Result<(), Error> {
+        match event {
+            Event::TypeDef(info) => {
+                let wire_type = info.wire_type().ok_or_else(|| {
+                    Error::serialize(format!("builtin type {} cannot be defined", info.id().0))
+                })?;
+                self.define(wire_type)
+            }
+            Event::Value(type_id) => {
+                if self.type_id.is_some() {
+                    return Err(Error::serialize("previous value is not complete"));
+                }
+                self.send_type(type_id)?;
+                self.value = Message::new(Vec::new());
+                self.begin(type_id);
+                self.type_id = Some(type_id);
+                Ok(())
+            }
+            Event::Field(name) => self.field(name),
+            Event::End => self.end(),
+            event => self.field_value(event),
+        }
+    }
+
+    /// Takes note of a type definition, so that values of the type can be
+    /// written. The definition is sent ahead of the first of them.
+    fn define(&mut self, wire_type: WireType) -> Result<(), Error> {
+        let id = wire_type.common().id;
+        if self.defs.lookup(id) == Some(&wire_type) {
+            return Ok(());
+        }
+        if self.sent.contains(&id) || lookup_builtin2(id).is_some() {
+            return Err(Error::serialize(format!("type {} redefined", id.0)));
+        }
+        self.defs.insert(wire_type);
+        Ok(())
+    }
+
+    // sends the definitions of `type_id` and of all types it refers to that
+    // were not sent yet, the outer types first, like Go does
+    fn send_type(&mut self, type_id: TypeId) -> Result<(), Error> {
+        if type_id.0 <= TypeId::INTERFACE.0 || self.sent.contains(&type_id) {
+            return Ok(());
+        }
+        let buf = match self.defs.lookup(type_id) {
+            Some(wire_type) => serialize_wire_type(wire_type)?,
+            None => return Err(Error::serialize(format!("unknown type id {}", type_id.0))),
+        };
+        self.out.serialize_part(OutputPart::try_new(buf)?)?;
+        self.sent.insert(type_id);
+
+        let inner = match self.defs.lookup(type_id) {
+            Some(WireType::Array(array_type)) => vec![array_type.elem],
+            Some(WireType::Slice(slice_type)) => vec![slice_type.elem],
+            Some(WireType::Map(map_type)) => vec![map_type.key, map_type.elem],
+            Some(WireType::Struct(struct_type)) => {
+                struct_type.fields.iter().map(|field| field.id).collect()
+            }
+            _ => Vec::new(),
+        };
+        for id in inner {
+            self.send_type(id)?;
+        }
+        Ok(())
+    }
+
+    // prepares for a top-level (or interface) value: structs are encoded
+    // directly, everything else is preceded by a zero field delta
+    fn begin(&mut self, type_id: TypeId) {
+        match self.defs.lookup(type_id) {
+            Some(WireType::Struct(_)) => {}
+            _ => self.value.write_uint(0),
+        }
+        self.pending = Some(type_id);
+    }
+
+    fn field(&mut self, name: &str) -> Result<(), Error> {
+        let (type_id, field_no) = match self.frames.last() {
+            Some(&Frame::Struct { type_id, field_no }) if self.pending.is_none() => {
+                (type_id, field_no)
+            }
+            _ => return Err(Error::serialize(format!("unexpected field {}", name))),
+        };
+        let fields = match self.defs.lookup(type_id) {
+            Some(WireType::Struct(struct_type)) => &struct_type.fields,
+            _ => unreachable!(),
+        };
+        let (index, field) = fields
+            .iter()
+            .enumerate()
+            .find(|(_, field)| field.name == name)
+            .ok_or_else(|| Error::serialize(format!("type {} has no field {}", type_id.0, name)))?;
+        let index = index as i64;
+        if index <= field_no {
+            return Err(Error::serialize(format!(
+                "field {} is out of order or written twice",
+                name
+            )));
+        }
+        let field_id = field.id;
+
+        self.value.write_uint((index - field_no) as u64);
+        if let Some(Frame::Struct { field_no, .. }) = self.frames.last_mut() {
+            *field_no = index;
+        }
+        self.pending = Some(field_id);
+        Ok(())
+    }
+
+    fn end(&mut self) -> Result<(), Error> {
+        let closes = self.pending.is_none()
+            && match self.frames.last() {
+                Some(Frame::Struct { .. }) => true,
+                Some(&Frame::Seq { remaining, .. }) => remaining == 0,
+                Some(&Frame::Map {
+                    remaining,
+                    key_next,
+                    ..
+                }) => remaining == 0 && key_next,
+                Some(&Frame::Interface { done, .. }) => done,
+                None => false,
+            };
+        if !closes {
+            return Err(Error::serialize("unexpected end"));
+        }
+        match self.frames.pop() {
+            Some(Frame::Struct { .. }) => self.value.write_uint(0),
+            Some(Frame::Interface { start, .. }) => {
+                let len = self.value.get_ref().len() - start;
+                let mut prefix = Message::new(Vec::new());
+                prefix.write_uint(len as u64);
+                let prefix = prefix.into_inner();
+                self.value.get_mut().splice(start..start, prefix);
+            }
+            _ => {}
+        }
+        self.value_done()
+    }
+
+    fn field_value(&mut self, event: Event) -> Result<(), Error> {
+        let type_id = match self.pending {
+            Some(type_id) => type_id,
+            None => return Err(Error::serialize(format!("unexpected {:?}", event))),
+        };
+        let def = match type_id {
+            TypeId::BOOL
+            | TypeId::INT
+            | TypeId::UINT
+            | TypeId::FLOAT
+            | TypeId::BYTES
+            | TypeId::STRING
+            | TypeId::COMPLEX
+            | TypeId::INTERFACE => None,
+            _ => Some(
+                self.defs
+                    .lookup(type_id)
+                    .ok_or_else(|| Error::serialize(format!("unknown type id {}", type_id.0)))?,
+            ),
+        };
+        let frame = match (event, def) {
+            (Event::Bool(v), None) if type_id == TypeId::BOOL => {
+                self.value.write_bool(v);
+                None
+            }
+            (Event::Int(v), None) if type_id == TypeId::INT => {
+                self.value.write_int(v);
+                None
+            }
+            (Event::Uint(v), None) if type_id == TypeId::UINT => {
+                self.value.write_uint(v);
+                None
+            }
+            (Event::Float(v), None) if type_id == TypeId::FLOAT => {
+                self.value.write_float(v);
+                None
+            }
+            (Event::Complex(re, im), None) if type_id == TypeId::COMPLEX => {
+                self.value.write_float(re);
+                self.value.write_float(im);
+                None
+            }
+            (Event::Bytes(v), None) if type_id == TypeId::BYTES => {
+                self.value.write_bytes(v);
+                None
+            }
+            (Event::Bytes(v), Some(WireType::GobEncoder(_))) => {
+                self.value.write_bytes(v);
+                None
+            }
+            (Event::String(v), None) if type_id == TypeId::STRING => {
+                self.value.write_bytes(v);
+                None
+            }
+            (Event::Nil, None) if type_id == TypeId::INTERFACE => {
+                self.value.write_uint(0);
+                None
+            }
+            (Event::StartInterface(name, concrete), None) if type_id == TypeId::INTERFACE => {
+                if name.is_empty() {
+                    return Err(Error::serialize("interface values need a name"));
+                }
+                self.send_type(concrete)?;
+                self.value.write_bytes(name.as_bytes());
+                self.value.write_int(concrete.0);
+                let start = self.value.get_ref().len();
+                self.begin(concrete);
+                self.frames.push(Frame::Interface { start, done: false });
+                return Ok(());
+            }
+            (Event::StartStruct(id), Some(WireType::Struct(_))) if id == type_id => {
+                Some(Frame::Struct {
+                    type_id,
+                    field_no: -1,
+                })
+            }
+            (Event::StartSeq(len), Some(WireType::Slice(slice_type))) => {
+                let elem = slice_type.elem;
+                self.value.write_uint(len);
+                Some(Frame::Seq {
+                    elem,
+                    remaining: len,
+                })
+            }
+            (Event::StartSeq(len), Some(WireType::Array(array_type))) => {
+                if len != array_type.len as u64 {
+                    return Err(Error::serialize(format!(
+                        "array of {} elements cannot have {}",
+                        array_type.len, len
+                    )));
+                }
+                let elem = array_type.elem;
+                self.value.write_uint(len);
+                Some(Frame::Seq {
+                    elem,
+                    remaining: len,
+                })
+            }
+            (Event::StartMap(len), Some(WireType::Map(map_type))) => {
+                let (key, elem) = (map_type.key, map_type.elem);
+                self.value.write_uint(len);
+                Some(Frame::Map {
+                    key,
+                    elem,
+                    remaining: len,
+                    key_next: true,
+                })
+            }
+            (event, _) => {
+                return Err(Error::serialize(format!(
+                    "{:?} is not a value of type {}",
+                    event, type_id.0
+                )))
+            }
+        };
+
+        self.pending = None;
+        match frame {
+            Some(frame) => {
+                self.frames.push(frame);
+                self.next_element();
+                Ok(())
+            }
+            None => self.value_done(),
+        }
+    }
+
+    // moves on after a complete value, sending it at the top level
+    fn value_done(&mut self) -> Result<(), Error> {
+        match self.frames.last_mut() {
+            Some(Frame::Seq { remaining, .. }) => *remaining -= 1,
+            Some(Frame::Map {
+                remaining,
+                key_next,
+                ..
+            }) => {
+                if *key_next {
+                    *remaining -= 1;
+                }
+                *key_next = !*key_next;
+            }
+            Some(Frame::Interface { done, .. }) => *done = true,
+            Some(Frame::Struct { .. }) => {}
+            None => {
+                let type_id = self.type_id.take().unwrap();
+                let mut msg = Message::new(Vec::new());
+                msg.write_int(type_id.0);
+                msg.get_mut().extend_from_slice(self.value.get_ref());
+                self.value.get_mut().clear();
+                return self
+                    .out
+                    .serialize_part(OutputPart::try_new(msg.into_inner())?);
+            }
+        }
+        self.next_element();
+        Ok(())
+    }
+
+    // expects the next element of a sequence or map, if there is one
+    fn next_element(&mut self) {
+        self.pending = match self.frames.last() {
+            Some(&Frame::Seq { elem, remaining }) if remaining > 0 => Some(elem),
+            Some(&Frame::Map {
+                key,
+                elem,
+                remaining,
+                key_next,
+            }) => match (key_next, remaining) {
+                (true, 0) => None,
+                (true, _) => Some(key),
+                (false, _) => Some(elem),
+            },
+            _ => None,
+        };
+    }
+
+    pub fn get_ref(&self) -> &W {
+        self.out.get_ref()
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        self.out.get_mut()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out.into_inner()
+    }
+}
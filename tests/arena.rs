@@ -0,0 +1,50 @@
+#![cfg(feature = "arena")]
+
+extern crate bumpalo;
+extern crate gob;
+
+use bumpalo::Bump;
+use gob::de::{ArenaStr, ArenaValue, InArena};
+use gob::{StreamDeserializer, StreamSerializer, Value};
+
+#[test]
+fn values_in_arena() {
+    let buffer = include_bytes!("reference/output/non_empty_values.gob");
+
+    let mut stream = StreamDeserializer::new(&buffer[..]);
+    let mut values = Vec::new();
+    while let Some(value) = stream.deserialize::<Value>().unwrap() {
+        values.push(value);
+    }
+
+    let arena = Bump::new();
+    let mut stream = StreamDeserializer::new(&buffer[..]);
+    let mut in_arena = Vec::new();
+    while let Some(value) = stream.deserialize_seed(InArena(&arena)).unwrap() {
+        in_arena.push(value);
+    }
+
+    assert!(!values.is_empty());
+    let copied = in_arena
+        .iter()
+        .map(ArenaValue::to_value)
+        .collect::<Vec<_>>();
+    assert_eq!(copied, values);
+}
+
+#[test]
+fn strings_in_arena() {
+    let mut stream = StreamSerializer::new(Vec::new());
+    stream.serialize(&"hello").unwrap();
+    stream.serialize(&"world").unwrap();
+    let buffer = stream.into_writer();
+
+    let arena = Bump::new();
+    let mut stream = StreamDeserializer::new(&buffer[..]);
+    let hello = stream.deserialize_seed(ArenaStr(&arena)).unwrap();
+    let world = stream.deserialize_seed(ArenaStr(&arena)).unwrap();
+    assert_eq!(hello, Some("hello"));
+    assert_eq!(world, Some("world"));
+    assert!(stream.deserialize_seed(ArenaStr(&arena)).unwrap().is_none());
+    assert!(arena.allocated_bytes() > 0);
+}
@@ -0,0 +1,114 @@
+#![cfg(feature = "arrow")]
+
+extern crate arrow_array;
+extern crate arrow_schema;
+extern crate gob;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_schema;
+#[macro_use]
+extern crate serde_schema_derive;
+
+use std::collections::BTreeMap;
+
+use arrow_array::{Array, Float64Array, ListArray, MapArray, StringArray, UInt64Array};
+use arrow_schema::DataType;
+use gob::arrow::RecordBatches;
+use gob::StreamSerializer;
+
+#[derive(Serialize, SchemaSerialize)]
+struct Row {
+    name: String,
+    age: u64,
+    score: f64,
+    tags: Vec<String>,
+    attrs: BTreeMap<String, i64>,
+}
+
+#[test]
+fn record_batches() {
+    let rows = vec![
+        Row {
+            name: "ada".into(),
+            age: 36,
+            score: 9.5,
+            tags: vec!["math".into(), "engines".into()],
+            attrs: vec![("papers".into(), 1)].into_iter().collect(),
+        },
+        Row {
+            name: "bob".into(),
+            age: 0,
+            score: 1.0,
+            tags: vec![],
+            attrs: BTreeMap::new(),
+        },
+        Row {
+            name: "cy".into(),
+            age: 7,
+            score: 0.0,
+            tags: vec!["x".into()],
+            attrs: vec![("a".into(), -1), ("b".into(), 2)]
+                .into_iter()
+                .collect(),
+        },
+    ];
+    let mut stream = StreamSerializer::new(Vec::new());
+    for row in &rows {
+        stream.serialize(row).unwrap();
+    }
+    stream.serialize(&"trailer").unwrap();
+    let buffer = stream.into_writer();
+
+    let mut batches = RecordBatches::new(&buffer[..]).batch_size(2);
+    let schema = batches.schema().unwrap().unwrap();
+    let names = schema
+        .fields()
+        .iter()
+        .map(|field| field.name().as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["name", "age", "score", "tags", "attrs"]);
+    assert_eq!(schema.field(1).data_type(), &DataType::UInt64);
+    assert!(matches!(schema.field(3).data_type(), DataType::List(_)));
+    assert!(matches!(schema.field(4).data_type(), DataType::Map(_, _)));
+
+    let first = batches.next().unwrap().unwrap();
+    assert_eq!(first.num_rows(), 2);
+    let names = first
+        .column(0)
+        .as_any()
+        .downcast_ref::<StringArray>()
+        .unwrap();
+    assert_eq!(names.value(0), "ada");
+    assert_eq!(names.value(1), "bob");
+    let ages = first
+        .column(1)
+        .as_any()
+        .downcast_ref::<UInt64Array>()
+        .unwrap();
+    assert_eq!(ages.values(), &[36, 0]);
+    let tags = first
+        .column(3)
+        .as_any()
+        .downcast_ref::<ListArray>()
+        .unwrap();
+    assert_eq!(tags.value(0).len(), 2);
+    assert_eq!(tags.value(1).len(), 0);
+
+    // the batch ends early, before the string
+    let second = batches.next().unwrap().unwrap();
+    assert_eq!(second.num_rows(), 1);
+    let scores = second
+        .column(2)
+        .as_any()
+        .downcast_ref::<Float64Array>()
+        .unwrap();
+    assert_eq!(scores.value(0), 0.0);
+    let attrs = second
+        .column(4)
+        .as_any()
+        .downcast_ref::<MapArray>()
+        .unwrap();
+    assert_eq!(attrs.value(0).len(), 2);
+
+    assert!(batches.next().unwrap().is_err());
+}
@@ -0,0 +1,121 @@
+#![cfg(feature = "futures")]
+
+extern crate futures;
+extern crate gob;
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures::executor::block_on;
+use futures::io::{AsyncRead, Cursor};
+use futures::{FutureExt, SinkExt, StreamExt, TryStreamExt};
+
+use gob::async_io::{ValueSink, ValueStream};
+use gob::error::ErrorKind;
+
+// hands out a byte at a time, and is pending before each
+struct Trickle {
+    input: &'static [u8],
+    ready: bool,
+}
+
+impl AsyncRead for Trickle {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        if !self.ready {
+            self.ready = true;
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        if self.input.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        self.ready = false;
+        buf[0] = self.input[0];
+        self.input = &self.input[1..];
+        Poll::Ready(Ok(1))
+    }
+}
+
+// a peer that went away without closing the connection
+struct HalfOpen;
+
+impl AsyncRead for HalfOpen {
+    fn poll_read(self: Pin<&mut Self>, _: &mut Context, _: &mut [u8]) -> Poll<io::Result<usize>> {
+        Poll::Pending
+    }
+}
+
+#[test]
+fn stream_of_values() {
+    let buffer = include_bytes!("reference/output/slice_of_bool_non_empty_twice.gob");
+    let stream = ValueStream::<_, Vec<bool>>::new(Cursor::new(&buffer[..]));
+    let values = block_on(stream.try_collect::<Vec<_>>()).unwrap();
+    assert_eq!(values, vec![vec![true, false], vec![false, true]]);
+}
+
+#[test]
+fn stream_truncated() {
+    let buffer = include_bytes!("reference/output/slice_of_bool_non_empty_twice.gob");
+    let mut stream = ValueStream::<_, Vec<bool>>::new(Cursor::new(&buffer[..25]));
+    block_on(async {
+        assert_eq!(stream.next().await.unwrap().unwrap(), vec![true, false]);
+        assert!(stream.next().await.unwrap().is_err());
+        assert!(stream.next().await.is_none());
+    });
+}
+
+#[test]
+fn sink_then_stream() {
+    let mut sink = ValueSink::new(Cursor::new(Vec::new()));
+    block_on(async {
+        sink.send(vec![true, false]).await.unwrap();
+        sink.send(vec![false, true]).await.unwrap();
+    });
+    let buffer = sink.into_inner().into_inner();
+
+    assert_eq!(
+        buffer,
+        &include_bytes!("reference/output/slice_of_bool_non_empty_twice.gob")[..]
+    );
+
+    let stream = ValueStream::<_, Vec<bool>>::new(Cursor::new(buffer));
+    let values = block_on(stream.collect::<Vec<_>>());
+    assert_eq!(values.len(), 2);
+}
+
+#[test]
+fn cancelled_next() {
+    let buffer = include_bytes!("reference/output/slice_of_bool_non_empty_twice.gob");
+    let reader = Trickle {
+        input: &buffer[..],
+        ready: false,
+    };
+    let mut stream = ValueStream::<_, Vec<bool>>::new(reader);
+
+    // every poll but the last for each value is dropped
+    let mut values = Vec::new();
+    loop {
+        match stream.next().now_or_never() {
+            Some(Some(value)) => values.push(value.unwrap()),
+            Some(None) => break,
+            None => {}
+        }
+    }
+    assert_eq!(values, vec![vec![true, false], vec![false, true]]);
+}
+
+#[test]
+fn read_timeout() {
+    let mut stream = ValueStream::<_, Vec<bool>>::new(HalfOpen);
+    stream.set_read_timeout(Some(Duration::from_millis(20)));
+    let err = block_on(stream.next()).unwrap().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Io(io::ErrorKind::TimedOut));
+    // the stream keeps waiting when polled again
+    assert!(block_on(stream.next()).unwrap().is_err());
+}
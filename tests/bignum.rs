@@ -0,0 +1,96 @@
+#![cfg(feature = "bignum")]
+
+extern crate gob;
+extern crate num_bigint;
+extern crate num_rational;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_schema;
+#[macro_use]
+extern crate serde_schema_derive;
+
+use gob::compat::bignum::{BigFloat, FloatForm};
+use gob::compat::{Gob, GobDecode, GobEncode};
+use gob::{Deserializer, StreamSerializer, Value};
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use serde::Deserialize;
+
+// as encoded by Go's `big.NewInt(-300)`, `big.NewRat(-3, 4)` and
+// `big.NewFloat(1.5)`
+const INT: &[u8] = &[3, 0x01, 0x2c];
+const RAT: &[u8] = &[3, 0, 0, 0, 1, 3, 4];
+const FLOAT: &[u8] = &[1, 0x0a, 0, 0, 0, 53, 0, 0, 0, 1, 0xc0, 0, 0, 0, 0, 0, 0, 0];
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, SchemaSerialize)]
+struct Ledger {
+    #[serde(rename = "Balance")]
+    balance: Gob<BigInt>,
+    #[serde(rename = "Rate")]
+    rate: Gob<BigRational>,
+    #[serde(rename = "Scale")]
+    scale: Gob<BigFloat>,
+}
+
+#[test]
+fn go_payloads() {
+    let int = BigInt::gob_decode(INT).unwrap();
+    assert_eq!(int, BigInt::from(-300));
+    assert_eq!(int.gob_encode().unwrap(), INT);
+    assert_eq!(BigInt::from(0).gob_encode().unwrap(), [2]);
+
+    let rat = BigRational::gob_decode(RAT).unwrap();
+    assert_eq!(rat, BigRational::new((-3).into(), 4.into()));
+    assert_eq!(rat.gob_encode().unwrap(), RAT);
+
+    let float = BigFloat::gob_decode(FLOAT).unwrap();
+    assert_eq!(float.prec, 53);
+    assert!(!float.neg);
+    assert_eq!(
+        float.to_rational(),
+        Some(BigRational::new(3.into(), 2.into()))
+    );
+    assert_eq!(float.gob_encode().unwrap(), FLOAT);
+    assert_eq!(
+        BigRational::gob_decode(FLOAT).unwrap(),
+        BigRational::new(3.into(), 2.into())
+    );
+
+    assert!(BigInt::gob_decode(&[4, 1]).is_err());
+    assert!(BigRational::gob_decode(&[2, 0, 0, 0, 9, 1]).is_err());
+}
+
+#[test]
+fn infinite_floats() {
+    let inf = BigFloat::gob_decode(&[1, 0x0c, 0, 0, 0, 53]).unwrap();
+    assert_eq!(inf.form, FloatForm::Inf);
+    assert_eq!(inf.to_rational(), None);
+    assert!(BigRational::gob_decode(&[1, 0x0c, 0, 0, 0, 53]).is_err());
+}
+
+#[test]
+fn ledger_round_trip() {
+    let ledger = Ledger {
+        balance: Gob(BigInt::from(-300)),
+        rate: Gob(BigRational::new((-3).into(), 4.into())),
+        scale: Gob(BigFloat::gob_decode(FLOAT).unwrap()),
+    };
+    let mut stream = StreamSerializer::new(Vec::new());
+    stream.serialize(&ledger).unwrap();
+    let buffer = stream.into_writer();
+    assert_eq!(
+        Ledger::deserialize(Deserializer::from_slice(&buffer)).unwrap(),
+        ledger
+    );
+
+    let value = Value::deserialize(Deserializer::from_slice(&buffer)).unwrap();
+    assert_eq!(
+        value,
+        Value::Map(vec![
+            (Value::String("Balance".into()), Value::Bytes(INT.to_vec())),
+            (Value::String("Rate".into()), Value::Bytes(RAT.to_vec())),
+            (Value::String("Scale".into()), Value::Bytes(FLOAT.to_vec())),
+        ])
+    );
+}
@@ -0,0 +1,297 @@
+extern crate bytes;
+extern crate gob;
+extern crate iovec;
+extern crate serde;
+extern crate serde_bytes;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_schema;
+#[macro_use]
+extern crate serde_schema_derive;
+
+use std::io::{Cursor, Read};
+
+use bytes::{Buf, Bytes};
+use gob::de::SliceStreamDeserializer;
+use gob::{Deserializer, SharedBytes, StreamDeserializer, StreamSerializer, Value};
+use iovec::IoVec;
+use serde::Deserialize;
+use serde_bytes::ByteBuf;
+
+#[derive(Serialize, SchemaSerialize)]
+struct Upload {
+    name: String,
+    size: u64,
+    data: ByteBuf,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct UploadHeader {
+    name: String,
+    #[serde(default)]
+    size: u64,
+}
+
+fn uploads(uploads: &[(&str, usize)]) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    {
+        let mut stream = StreamSerializer::new_with_write(&mut buffer);
+        for &(name, size) in uploads {
+            stream
+                .serialize(&Upload {
+                    name: name.to_owned(),
+                    size: size as u64,
+                    data: ByteBuf::from((0..size).map(|n| n as u8).collect::<Vec<_>>()),
+                })
+                .unwrap();
+        }
+    }
+    buffer
+}
+
+#[test]
+fn streamed_blobs() {
+    let input = uploads(&[("a", 100_000), ("b", 3), ("c", 20_000)]);
+    let mut stream = StreamDeserializer::new(Cursor::new(input));
+    stream.set_read_chunk_size(1024);
+
+    for &(name, size) in &[("a", 100_000), ("b", 3), ("c", 20_000)] {
+        let (header, mut blob) = stream
+            .deserialize_with_blob::<UploadHeader>("data")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            header,
+            UploadHeader {
+                name: name.to_owned(),
+                size: size as u64,
+            }
+        );
+        assert_eq!(blob.len(), size as u64);
+        let mut data = Vec::new();
+        blob.read_to_end(&mut data).unwrap();
+        assert_eq!(data, (0..size).map(|n| n as u8).collect::<Vec<_>>());
+    }
+    assert!(stream
+        .deserialize_with_blob::<UploadHeader>("data")
+        .unwrap()
+        .is_none());
+    assert!(stream.buffer_stats().capacity() < 100_000);
+}
+
+#[test]
+fn unread_blob_is_skipped() {
+    let input = uploads(&[("a", 50_000), ("b", 10)]);
+    let mut stream = StreamDeserializer::new(Cursor::new(input));
+    {
+        let (_, mut blob) = stream
+            .deserialize_with_blob::<UploadHeader>("data")
+            .unwrap()
+            .unwrap();
+        let mut start = [0; 10];
+        blob.read_exact(&mut start).unwrap();
+        assert_eq!(blob.remaining(), 49_990);
+    }
+    let header = stream.deserialize::<UploadHeader>().unwrap().unwrap();
+    assert_eq!(header.name, "b");
+}
+
+#[test]
+fn blob_not_sent() {
+    let input = uploads(&[("empty", 0)]);
+    let mut stream = StreamDeserializer::new(Cursor::new(input));
+    let (header, blob) = stream
+        .deserialize_with_blob::<UploadHeader>("data")
+        .unwrap()
+        .unwrap();
+    assert_eq!(header.name, "empty");
+    assert!(blob.is_empty());
+}
+
+#[test]
+fn blob_not_last() {
+    #[derive(Serialize, SchemaSerialize)]
+    struct Upload {
+        data: ByteBuf,
+        name: String,
+    }
+
+    let mut buffer = Vec::new();
+    StreamSerializer::new_with_write(&mut buffer)
+        .serialize(&Upload {
+            data: ByteBuf::from(vec![1, 2, 3]),
+            name: "a".to_owned(),
+        })
+        .unwrap();
+    let mut stream = StreamDeserializer::new(Cursor::new(buffer));
+    assert!(stream
+        .deserialize_with_blob::<UploadHeader>("data")
+        .is_err());
+}
+
+#[test]
+fn byte_buffers_are_byte_slices() {
+    #[derive(Serialize, SchemaSerialize)]
+    struct Owned {
+        data: ByteBuf,
+    }
+
+    #[derive(Serialize, SchemaSerialize)]
+    struct Borrowed<'a> {
+        data: serde_bytes::Bytes<'a>,
+    }
+
+    #[derive(Deserialize)]
+    struct Received<'a> {
+        data: &'a [u8],
+    }
+
+    let mut stream = StreamSerializer::new(Vec::new());
+    stream
+        .serialize(&Owned {
+            data: ByteBuf::from(vec![1, 2, 3]),
+        })
+        .unwrap();
+    stream
+        .serialize(&Borrowed {
+            data: serde_bytes::Bytes::new(&[1, 2, 3]),
+        })
+        .unwrap();
+    let buffer = stream.into_writer();
+
+    let mut stream = StreamDeserializer::new(&buffer[..]);
+    for _ in 0..2 {
+        let value = stream.deserialize::<Value>().unwrap().unwrap();
+        assert_eq!(
+            value,
+            Value::Map(vec![(
+                Value::String("data".into()),
+                Value::Bytes(vec![1, 2, 3])
+            )])
+        );
+    }
+
+    // the decoded slice points into the input
+    let received = Received::deserialize(Deserializer::from_slice(&buffer)).unwrap();
+    assert_eq!(received.data, [1, 2, 3]);
+    let input = buffer.as_ptr_range();
+    assert!(input.contains(&received.data.as_ptr()));
+}
+
+#[test]
+fn byte_vectors_with_serde_bytes() {
+    // the schema only sees a `Vec<u8>`, registered as a slice of uints
+    #[derive(Serialize, SchemaSerialize)]
+    struct Upload {
+        #[serde(with = "serde_bytes")]
+        data: Vec<u8>,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Received {
+        data: ByteBuf,
+    }
+
+    let mut buffer = Vec::new();
+    StreamSerializer::new_with_write(&mut buffer)
+        .serialize(&Upload {
+            data: vec![1, 2, 255],
+        })
+        .unwrap();
+    let received = Received::deserialize(Deserializer::from_slice(&buffer)).unwrap();
+    assert_eq!(received.data, ByteBuf::from(vec![1, 2, 255]));
+
+    let value = Value::deserialize(Deserializer::from_slice(&buffer)).unwrap();
+    assert_eq!(
+        value,
+        Value::Map(vec![(
+            Value::String("data".into()),
+            Value::Seq(vec![Value::Uint(1), Value::Uint(2), Value::Uint(255)])
+        )])
+    );
+}
+
+#[derive(Serialize, Deserialize, SchemaSerialize)]
+struct Forwarded {
+    name: String,
+    data: SharedBytes,
+}
+
+mod copied {
+    use serde_bytes::ByteBuf;
+
+    #[derive(Serialize, SchemaSerialize)]
+    pub struct Forwarded {
+        pub name: String,
+        pub data: ByteBuf,
+    }
+}
+
+#[test]
+fn shared_bytes_are_not_copied() {
+    let data = Bytes::from((0..10_000).map(|n| n as u8).collect::<Vec<_>>());
+    let mut stream = StreamSerializer::new_with_buffer();
+    stream
+        .serialize(&Forwarded {
+            name: "a".to_owned(),
+            data: SharedBytes(data.clone()),
+        })
+        .unwrap();
+
+    // the output refers to the data rather than holding a copy of it
+    let mut output = stream.into_inner();
+    let mut vecs = [IoVec::from_bytes(&[0]).unwrap(); 16];
+    let n = output.bytes_vec(&mut vecs);
+    assert!(vecs[..n]
+        .iter()
+        .any(|vec| vec.as_ptr() == data.as_ptr() && vec.len() == data.len()));
+
+    let mut buffer = Vec::new();
+    while output.has_remaining() {
+        let len = {
+            let bytes = output.bytes();
+            buffer.extend_from_slice(bytes);
+            bytes.len()
+        };
+        output.advance(len);
+    }
+
+    // which is sent like any other byte slice
+    let mut expected = Vec::new();
+    StreamSerializer::new_with_write(&mut expected)
+        .serialize(&copied::Forwarded {
+            name: "a".to_owned(),
+            data: ByteBuf::from(data.to_vec()),
+        })
+        .unwrap();
+    assert_eq!(buffer, expected);
+
+    // and decodes into a slice of the input
+    let input = Bytes::from(buffer);
+    let mut stream = SliceStreamDeserializer::from_bytes(input.clone());
+    let forwarded = stream.deserialize::<Forwarded>().unwrap().unwrap();
+    assert_eq!(forwarded.name, "a");
+    assert_eq!(*forwarded.data, data);
+    let start = input.as_ptr() as usize;
+    let ptr = forwarded.data.as_ptr() as usize;
+    assert!(ptr > start && ptr < start + input.len());
+}
+
+#[test]
+fn shared_bytes_elsewhere_are_copied() {
+    #[derive(Deserialize)]
+    struct Received {
+        #[serde(with = "gob::shared_bytes")]
+        data: Bytes,
+    }
+
+    let mut buffer = Vec::new();
+    StreamSerializer::new_with_write(&mut buffer)
+        .serialize(&Forwarded {
+            name: "a".to_owned(),
+            data: SharedBytes(Bytes::from(vec![7; 100])),
+        })
+        .unwrap();
+    let received = Received::deserialize(Deserializer::from_slice(&buffer)).unwrap();
+    assert_eq!(received.data, Bytes::from(vec![7; 100]));
+}
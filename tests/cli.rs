@@ -0,0 +1,75 @@
+#![cfg(feature = "cli")]
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+fn gob(args: &[&str], stdin: &[u8]) -> Vec<u8> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_gob"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    output.stdout
+}
+
+#[test]
+fn types() {
+    let output = gob(&["types", "tests/reference/output/point_struct.gob"], b"");
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "// id 65\ntype Point struct {\n\tX int\n\tY int\n}\n"
+    );
+}
+
+#[test]
+fn dump_json() {
+    let output = gob(
+        &["dump", "-"],
+        include_bytes!("reference/output/non_empty_values.gob"),
+    );
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "true\n42\n42\n42.0\n\"foo\"\n[1,2]\n[true,false]\n"
+    );
+}
+
+#[test]
+fn dump_debug() {
+    let output = gob(
+        &["dump", "--format", "debug"],
+        include_bytes!("reference/output/interface_value.gob"),
+    );
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "X: 1\nY: 2\n\nValue:\n  X: 22\n  Y: 33\n"
+    );
+}
+
+#[test]
+fn encode_point_struct() {
+    // unique per process, so that concurrent test runs don't collide
+    let schema =
+        std::env::temp_dir().join(format!("gob-cli-point-struct-{}.json", std::process::id()));
+    std::fs::write(
+        &schema,
+        r#"{"struct": {"name": "Point", "fields": [
+            {"name": "X", "type": "int"},
+            {"name": "Y", "type": "int"}
+        ]}}"#,
+    )
+    .unwrap();
+    let output = gob(
+        &["encode", "--schema", schema.to_str().unwrap()],
+        br#"{"X": 22, "Y": 33}"#,
+    );
+    std::fs::remove_file(&schema).unwrap();
+    assert_eq!(
+        output,
+        include_bytes!("reference/output/point_struct.gob").as_ref()
+    );
+}
@@ -0,0 +1,137 @@
+extern crate gob;
+extern crate serde;
+extern crate serde_bytes;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_schema;
+#[macro_use]
+extern crate serde_schema_derive;
+
+use gob::compat::{GoResult, Gob, GobDecode, GobEncode};
+use gob::{Deserializer, StreamSerializer, Value};
+use serde::Deserialize;
+
+fn round_trip(result: GoResult<i64>) -> GoResult<i64> {
+    let mut stream = StreamSerializer::new(Vec::new());
+    stream.serialize(&result).unwrap();
+    let buffer = stream.into_writer();
+    GoResult::deserialize(Deserializer::from_slice(&buffer)).unwrap()
+}
+
+#[test]
+fn go_result_ok() {
+    assert_eq!(round_trip(GoResult(Ok(42))), GoResult(Ok(42)));
+    assert_eq!(round_trip(GoResult(Ok(0))), GoResult(Ok(0)));
+}
+
+#[test]
+fn go_result_err() {
+    let result = GoResult(Err("division by zero".into()));
+    assert_eq!(round_trip(result.clone()), result);
+
+    assert_eq!(round_trip(GoResult(Err("".into()))), GoResult(Ok(0)));
+}
+
+#[test]
+fn go_result_from_reply_struct() {
+    let reply = Value::Struct(vec![
+        ("Value".into(), Value::Int(7)),
+        ("Error".into(), Value::String("busy".into())),
+        ("Retry".into(), Value::Bool(true)),
+    ]);
+
+    let mut stream = StreamSerializer::new(Vec::new());
+    stream.serialize_value(&reply).unwrap();
+    let buffer = stream.into_writer();
+
+    let decoded = GoResult::<i64>::deserialize(Deserializer::from_slice(&buffer)).unwrap();
+    assert_eq!(decoded.into_result(), Err("busy".into()));
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Point {
+    x: u8,
+    y: u8,
+}
+
+impl GobEncode for Point {
+    const GOB_NAME: &'static str = "Point";
+
+    type Error = String;
+
+    fn gob_encode(&self) -> Result<Vec<u8>, String> {
+        Ok(format!("{},{}", self.x, self.y).into_bytes())
+    }
+}
+
+impl GobDecode for Point {
+    type Error = String;
+
+    fn gob_decode(bytes: &[u8]) -> Result<Point, String> {
+        let text = std::str::from_utf8(bytes).map_err(|e| e.to_string())?;
+        let mut coords = text.split(',').map(|coord| coord.parse::<u8>());
+        match (coords.next(), coords.next(), coords.next()) {
+            (Some(Ok(x)), Some(Ok(y)), None) => Ok(Point { x, y }),
+            _ => Err(format!("invalid point {:?}", text)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, SchemaSerialize)]
+struct Marker {
+    #[serde(rename = "Label")]
+    label: String,
+    #[serde(rename = "At")]
+    at: Gob<Point>,
+}
+
+#[test]
+fn gob_encoder_round_trip() {
+    let marker = Marker {
+        label: "home".to_owned(),
+        at: Gob(Point { x: 3, y: 4 }),
+    };
+    let mut stream = StreamSerializer::new(Vec::new());
+    stream.serialize(&marker).unwrap();
+    let buffer = stream.into_writer();
+    assert_eq!(
+        Marker::deserialize(Deserializer::from_slice(&buffer)).unwrap(),
+        marker
+    );
+
+    // the point is an opaque byte slice to anyone else
+    let value = Value::deserialize(Deserializer::from_slice(&buffer)).unwrap();
+    assert_eq!(
+        value,
+        Value::Map(vec![
+            (Value::String("Label".into()), Value::String("home".into())),
+            (Value::String("At".into()), Value::Bytes(b"3,4".to_vec())),
+        ])
+    );
+}
+
+#[test]
+fn gob_encoder_wire_format() {
+    let mut stream = StreamSerializer::new(Vec::new());
+    stream.serialize(&Gob(Point { x: 1, y: 2 })).unwrap();
+    let buffer = stream.into_writer();
+    // the type is defined as `GobEncoderT`, the value sent as a byte slice
+    let mut expected = vec![17, 0xff, 0x81, 5, 1, 1, 5, b'P', b'o', b'i', b'n', b't'];
+    expected.extend_from_slice(&[1, 0xff, 0x82, 0, 0, 0]);
+    expected.extend_from_slice(&[7, 0xff, 0x82, 0, 3, b'1', b',', b'2']);
+    assert_eq!(buffer, expected);
+
+    // Go's `BinaryMarshalerT` is the next field of its wire type
+    let mut go = buffer.clone();
+    go[3] = 6;
+    let point = Gob::<Point>::deserialize(Deserializer::from_slice(&go)).unwrap();
+    assert_eq!(point, Gob(Point { x: 1, y: 2 }));
+}
+
+#[test]
+fn gob_decoder_errors() {
+    let mut stream = StreamSerializer::new(Vec::new());
+    stream.serialize(&serde_bytes::Bytes::new(b"3")).unwrap();
+    let buffer = stream.into_writer();
+    assert!(Gob::<Point>::deserialize(Deserializer::from_slice(&buffer)).is_err());
+}
@@ -0,0 +1,64 @@
+#![cfg(all(feature = "gzip", feature = "snappy"))]
+
+extern crate gob;
+
+use std::io::Cursor;
+
+use gob::compress::Compression;
+use gob::{StreamDeserializer, StreamSerializer};
+
+fn values() -> Vec<Vec<String>> {
+    (0..100).map(|i| vec![format!("value {}", i); 10]).collect()
+}
+
+#[test]
+fn gzip_round_trip() {
+    let mut stream = StreamSerializer::new_gzip(Vec::new());
+    for value in values() {
+        stream.serialize(&value).unwrap();
+    }
+    let compressed = stream.finish().unwrap();
+
+    let mut plain = StreamSerializer::new_with_write(Vec::new());
+    for value in values() {
+        plain.serialize(&value).unwrap();
+    }
+    assert!(compressed.len() < plain.into_writer().len() / 4);
+
+    let mut stream = StreamDeserializer::new_gzip(Cursor::new(compressed));
+    for value in values() {
+        assert_eq!(stream.deserialize::<Vec<String>>().unwrap(), Some(value));
+    }
+    assert_eq!(stream.deserialize::<Vec<String>>().unwrap(), None);
+}
+
+#[test]
+fn gzip_levels_and_members() {
+    let mut compressed = Vec::new();
+    for level in &[Compression::fast(), Compression::best()] {
+        let mut stream = StreamSerializer::new_gzip_with_level(Vec::new(), *level);
+        stream.serialize(&values()[0]).unwrap();
+        compressed.extend(stream.finish().unwrap());
+    }
+
+    // the second member repeats the type definition, which is fine
+    let mut stream = StreamDeserializer::new_gzip(Cursor::new(compressed));
+    assert!(stream.deserialize::<Vec<String>>().unwrap().is_some());
+    assert!(stream.deserialize::<Vec<String>>().unwrap().is_some());
+    assert!(stream.deserialize::<Vec<String>>().unwrap().is_none());
+}
+
+#[test]
+fn snappy_round_trip() {
+    let mut stream = StreamSerializer::new_snappy(Vec::new());
+    for value in values() {
+        stream.serialize(&value).unwrap();
+    }
+    let compressed = stream.finish().unwrap();
+
+    let mut stream = StreamDeserializer::new_snappy(Cursor::new(compressed));
+    for value in values() {
+        assert_eq!(stream.deserialize::<Vec<String>>().unwrap(), Some(value));
+    }
+    assert_eq!(stream.deserialize::<Vec<String>>().unwrap(), None);
+}
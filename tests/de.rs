@@ -1,3 +1,4 @@
+extern crate bytes;
 extern crate gob;
 extern crate partial_io;
 extern crate serde;
@@ -7,12 +8,15 @@ extern crate serde_derive;
 #[macro_use]
 extern crate quickcheck;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::io::Cursor;
+use std::sync::{Arc, Mutex};
 
-use gob::{error::ErrorKind, Deserializer, StreamDeserializer};
+use gob::de::{deserialize_reporting_ignored, DecodeOptions, StreamState, TypeDefs};
+use gob::{error::ErrorKind, Decoder, Deserializer, Mode, StreamDeserializer, Value};
 use partial_io::quickcheck_types::{GenWouldBlock, PartialWithErrors};
 use partial_io::PartialRead;
+use serde::de::IgnoredAny;
 use serde::Deserialize;
 use serde_bytes::{ByteBuf, Bytes};
 
@@ -194,6 +198,21 @@ fn f64_neg() {
     assert_eq!(decoded, -42f64);
 }
 
+#[test]
+fn f64_bits_preserved() {
+    let deserializer = Deserializer::from_slice(&[4, 8, 0, 255, 128]);
+    assert_eq!(
+        f64::deserialize(deserializer).unwrap().to_bits(),
+        (-0f64).to_bits()
+    );
+
+    let deserializer = Deserializer::from_slice(&[11, 8, 0, 248, 52, 18, 0, 0, 0, 0, 248, 255]);
+    assert_eq!(
+        f64::deserialize(deserializer).unwrap().to_bits(),
+        0xfff8_0000_0000_1234
+    );
+}
+
 #[test]
 fn char_ascii() {
     let deserializer = Deserializer::from_slice(&[4, 4, 0, 255, 204]);
@@ -298,6 +317,23 @@ fn vec_of_bool_from_non_empty_array() {
     assert_eq!(decoded, &[true, false]);
 }
 
+#[test]
+fn array_of_bool_from_non_empty_array() {
+    let buffer = include_bytes!("reference/output/array_of_bool_non_empty.gob");
+    let decoded = <[bool; 2]>::deserialize(Deserializer::from_slice(buffer)).unwrap();
+    assert_eq!(decoded, [true, false]);
+    assert!(<[bool; 1]>::deserialize(Deserializer::from_slice(buffer)).is_err());
+    assert!(<[bool; 3]>::deserialize(Deserializer::from_slice(buffer)).is_err());
+}
+
+#[test]
+fn array_of_bool_from_non_empty_slice() {
+    let buffer = include_bytes!("reference/output/slice_of_bool_non_empty.gob");
+    let decoded = <[bool; 2]>::deserialize(Deserializer::from_slice(buffer)).unwrap();
+    assert_eq!(decoded, [true, false]);
+    assert!(<[bool; 1]>::deserialize(Deserializer::from_slice(buffer)).is_err());
+}
+
 #[test]
 fn vec_of_bool_from_empty_slice_twice() {
     let buffer = include_bytes!("reference/output/slice_of_bool_empty_twice.gob");
@@ -377,6 +413,72 @@ fn point_struct() {
     assert_eq!(decoded.y, 33);
 }
 
+#[test]
+fn point_struct_into_any_visitor() {
+    use serde::de::{MapAccess, Visitor};
+    use serde::Deserializer as _;
+    use std::fmt;
+
+    // takes a map whatever it was asked for
+    struct Fields;
+
+    impl<'de> Visitor<'de> for Fields {
+        type Value = Vec<(String, i64)>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a struct")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut fields = Vec::new();
+            while let Some(field) = map.next_entry()? {
+                fields.push(field);
+            }
+            Ok(fields)
+        }
+    }
+
+    let buffer = include_bytes!("reference/output/point_struct.gob");
+    let expected = vec![("X".to_owned(), 22), ("Y".to_owned(), 33)];
+    let fields = Deserializer::from_slice(buffer).deserialize_seq(Fields);
+    assert_eq!(fields.unwrap(), expected);
+    let fields = Deserializer::from_slice(buffer).deserialize_tuple(2, Fields);
+    assert_eq!(fields.unwrap(), expected);
+    let fields = Deserializer::from_slice(buffer).deserialize_str(Fields);
+    assert_eq!(fields.unwrap(), expected);
+}
+
+#[test]
+fn lossy_point_struct() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Point {
+        #[serde(rename = "X")]
+        x: Option<String>,
+        #[serde(rename = "Y", default)]
+        y: bool,
+    }
+
+    let buffer = include_bytes!("reference/output/point_struct.gob");
+    assert!(Point::deserialize(Deserializer::from_slice(buffer)).is_err());
+
+    let (decoded, errors) = Deserializer::from_slice(buffer)
+        .deserialize_lossy::<Point>()
+        .unwrap();
+    assert_eq!(decoded, Point { x: None, y: false });
+    let paths: Vec<_> = errors.iter().map(|err| err.path()).collect();
+    assert_eq!(paths, ["X", "Y"]);
+
+    // without a default, the struct itself can't be salvaged
+    #[derive(Debug, Deserialize)]
+    struct Strict {
+        #[serde(rename = "X")]
+        _x: String,
+    }
+    assert!(Deserializer::from_slice(buffer)
+        .deserialize_lossy::<Strict>()
+        .is_err());
+}
+
 #[test]
 fn unit_struct() {
     #[derive(Deserialize)]
@@ -449,6 +551,454 @@ fn unit_from_any() {
     assert!(stream.deserialize::<()>().unwrap().is_none());
 }
 
+#[test]
+fn strings_and_bytes() {
+    let buffer = include_bytes!("reference/output/non_empty_values.gob");
+
+    let mut stream = StreamDeserializer::new(Cursor::new(buffer.as_ref()));
+    assert_eq!(stream.skip(4).unwrap(), 4);
+    assert!(stream.deserialize::<ByteBuf>().is_err());
+    assert!(stream.deserialize::<String>().is_err());
+
+    let mut stream = StreamDeserializer::new(Cursor::new(buffer.as_ref()));
+    stream.set_options(DecodeOptions::new().string_bytes_interchange(true));
+    assert_eq!(stream.skip(4).unwrap(), 4);
+    let bytes = stream.deserialize::<ByteBuf>().unwrap().unwrap();
+    assert_eq!(&bytes[..], b"foo");
+    let string = stream.deserialize::<String>().unwrap().unwrap();
+    assert_eq!(string, "\u{1}\u{2}");
+
+    let mut stream = StreamDeserializer::new(Cursor::new(buffer.as_ref()));
+    stream.set_mode(Mode::Lenient);
+    assert_eq!(stream.skip(4).unwrap(), 4);
+    assert_eq!(stream.deserialize::<Vec<u8>>().unwrap().unwrap(), b"foo");
+    assert_eq!(stream.deserialize::<Vec<u8>>().unwrap().unwrap(), &[1, 2]);
+}
+
+#[test]
+fn bytes_into_vec() {
+    let buffer = include_bytes!("reference/output/non_empty_values.gob");
+    let mut stream = StreamDeserializer::new(Cursor::new(buffer.as_ref()));
+    assert_eq!(stream.skip(5).unwrap(), 5);
+    assert_eq!(stream.deserialize::<Vec<u8>>().unwrap().unwrap(), &[1, 2]);
+}
+
+#[test]
+fn interface_value() {
+    #[derive(Deserialize, Debug, PartialEq, Eq)]
+    struct Point {
+        #[serde(rename = "X")]
+        x: i64,
+        #[serde(rename = "Y")]
+        y: i64,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq, Eq)]
+    struct Wrapper {
+        #[serde(rename = "Value")]
+        value: Point,
+    }
+
+    let buffer = include_bytes!("reference/output/interface_value.gob");
+    let mut stream = StreamDeserializer::new(Cursor::new(buffer.as_ref()));
+
+    let point = stream.deserialize::<Point>().unwrap().unwrap();
+    assert_eq!(point, Point { x: 1, y: 2 });
+
+    let wrapper = stream.deserialize::<Wrapper>().unwrap().unwrap();
+    assert_eq!(
+        wrapper,
+        Wrapper {
+            value: Point { x: 22, y: 33 }
+        }
+    );
+}
+
+#[test]
+fn interface_value_registered_by_pointer() {
+    #[derive(Deserialize, Debug, PartialEq, Eq)]
+    struct Point {
+        #[serde(rename = "X")]
+        x: i64,
+        #[serde(rename = "Y")]
+        y: i64,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq, Eq)]
+    struct Wrapper {
+        #[serde(rename = "Value")]
+        value: Point,
+    }
+
+    let buffer = include_bytes!("reference/output/interface_value_pointer.gob");
+    let mut stream = StreamDeserializer::new(Cursor::new(buffer.as_ref()));
+
+    let point = stream.deserialize::<Point>().unwrap().unwrap();
+    assert_eq!(point, Point { x: 1, y: 2 });
+
+    let wrapper = stream.deserialize::<Wrapper>().unwrap().unwrap();
+    assert_eq!(
+        wrapper,
+        Wrapper {
+            value: Point { x: 22, y: 33 }
+        }
+    );
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+struct ConcretePoint {
+    #[serde(rename = "X")]
+    x: i64,
+    #[serde(rename = "Y")]
+    y: i64,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+enum ByValue {
+    #[serde(rename = "main.Point")]
+    Point(ConcretePoint),
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+enum ByPointer {
+    #[serde(rename = "*main.Point")]
+    Point(ConcretePoint),
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+struct Shapes<T> {
+    #[serde(rename = "Value")]
+    value: T,
+}
+
+fn interface_enum<T>(buffer: &[u8], mode: Mode) -> Result<T, gob::Error>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    let mut stream = StreamDeserializer::new(Cursor::new(buffer));
+    stream.set_mode(mode);
+    stream.deserialize::<IgnoredAny>().unwrap();
+    stream
+        .deserialize::<Shapes<T>>()
+        .map(|shapes| shapes.unwrap().value)
+}
+
+#[test]
+fn interface_value_into_enum_by_concrete_name() {
+    let by_value = include_bytes!("reference/output/interface_value.gob");
+    let by_pointer = include_bytes!("reference/output/interface_value_pointer.gob");
+    let point = || ConcretePoint { x: 22, y: 33 };
+
+    for &buffer in &[by_value.as_ref(), by_pointer.as_ref()] {
+        assert_eq!(
+            interface_enum::<ByValue>(buffer, Mode::Compatible).unwrap(),
+            ByValue::Point(point())
+        );
+        assert_eq!(
+            interface_enum::<ByPointer>(buffer, Mode::Compatible).unwrap(),
+            ByPointer::Point(point())
+        );
+    }
+
+    assert_eq!(
+        interface_enum::<ByValue>(by_value, Mode::Strict).unwrap(),
+        ByValue::Point(point())
+    );
+    assert_eq!(
+        interface_enum::<ByPointer>(by_pointer, Mode::Strict).unwrap(),
+        ByPointer::Point(point())
+    );
+    assert!(interface_enum::<ByPointer>(by_value, Mode::Strict).is_err());
+    assert!(interface_enum::<ByValue>(by_pointer, Mode::Strict).is_err());
+}
+
+#[test]
+fn interface_value_pointer_names_option() {
+    use gob::de::{DecodeOptions, PointerNames};
+
+    let buffer = include_bytes!("reference/output/interface_value_pointer.gob");
+    let mut stream = StreamDeserializer::new(Cursor::new(buffer.as_ref()));
+    stream.set_options(DecodeOptions::new().pointer_names(PointerNames::Exact));
+    stream.deserialize::<IgnoredAny>().unwrap();
+    assert!(stream.deserialize::<Shapes<ByValue>>().is_err());
+}
+
+#[test]
+fn peek_type_dispatch() {
+    use gob::de::Structure;
+
+    #[derive(Deserialize, Debug, PartialEq, Eq)]
+    struct Point {
+        #[serde(rename = "X")]
+        x: i64,
+        #[serde(rename = "Y")]
+        y: i64,
+    }
+
+    let buffer = include_bytes!("reference/output/interface_value.gob");
+    let mut stream = StreamDeserializer::new(Cursor::new(buffer.as_ref()));
+
+    let info = stream.peek_type().unwrap().unwrap();
+    assert_eq!(info.name(), "Point");
+    match *info.structure() {
+        Structure::Struct { ref fields } => {
+            let names = fields.iter().map(|f| &*f.0).collect::<Vec<_>>();
+            assert_eq!(names, &["X", "Y"]);
+        }
+        ref other => panic!("unexpected structure {:?}", other),
+    }
+    // peeking again does not move on
+    assert_eq!(stream.peek_type().unwrap().unwrap(), info);
+    let point = stream.deserialize::<Point>().unwrap().unwrap();
+    assert_eq!(point, Point { x: 1, y: 2 });
+
+    let info = stream.peek_type().unwrap().unwrap();
+    assert_eq!(info.name(), "Wrapper");
+    assert_ne!(Some(info.id()), stream.type_defs().type_id("Point"));
+    stream.deserialize::<IgnoredAny>().unwrap().unwrap();
+
+    assert!(stream.peek_type().unwrap().is_none());
+    assert!(stream.deserialize::<IgnoredAny>().unwrap().is_none());
+}
+
+#[test]
+fn peek_type_builtin() {
+    let buffer = include_bytes!("reference/output/non_empty_values.gob");
+    let mut stream = StreamDeserializer::new(Cursor::new(buffer.as_ref()));
+
+    let info = stream.peek_type().unwrap().unwrap();
+    assert_eq!(info.name(), "bool");
+    assert_eq!(*info.structure(), gob::de::Structure::Builtin);
+    assert!(stream.deserialize::<bool>().unwrap().unwrap());
+    assert_eq!(stream.peek_type().unwrap().unwrap().name(), "uint");
+}
+
+#[test]
+fn dispatch_by_type_name() {
+    use gob::de::Dispatcher;
+
+    #[derive(Deserialize, Debug, PartialEq, Eq)]
+    struct Point {
+        #[serde(rename = "X")]
+        x: i64,
+        #[serde(rename = "Y")]
+        y: i64,
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    enum Event {
+        Point(Point),
+        Other(String),
+    }
+
+    let buffer = include_bytes!("reference/output/interface_value.gob");
+
+    let mut stream = StreamDeserializer::new(Cursor::new(buffer.as_ref()));
+    let mut dispatcher = Dispatcher::new()
+        .on("*main.Point", |de| Point::deserialize(de).map(Event::Point))
+        .otherwise(|info, de| {
+            IgnoredAny::deserialize(de)?;
+            Ok(Event::Other(info.name().to_owned()))
+        });
+    let mut events = Vec::new();
+    while let Some(event) = dispatcher.dispatch(&mut stream).unwrap() {
+        events.push(event);
+    }
+    assert_eq!(
+        events,
+        vec![
+            Event::Point(Point { x: 1, y: 2 }),
+            Event::Other("Wrapper".to_owned()),
+        ]
+    );
+
+    let mut stream = StreamDeserializer::new(Cursor::new(buffer.as_ref()));
+    let mut dispatcher = Dispatcher::new().on("Wrapper", |de| {
+        IgnoredAny::deserialize(de).map(|_| "wrapper")
+    });
+    assert!(dispatcher.dispatch(&mut stream).is_err());
+    assert_eq!(dispatcher.dispatch(&mut stream).unwrap(), Some("wrapper"));
+    assert_eq!(dispatcher.dispatch(&mut stream).unwrap(), None);
+}
+
+#[test]
+fn projected_fields() {
+    #[derive(Deserialize, Debug, PartialEq, Eq)]
+    struct Point {
+        #[serde(rename = "X", default)]
+        x: i64,
+        #[serde(rename = "Y", default)]
+        y: i64,
+    }
+
+    let buffer = include_bytes!("reference/output/point_struct.gob");
+
+    let deserializer = Deserializer::from_slice(buffer).project(&["Y"]);
+    let point = Point::deserialize(deserializer).unwrap();
+    assert_eq!(point, Point { x: 0, y: 33 });
+
+    let deserializer = Deserializer::from_slice(buffer).project(&["X", "Y"]);
+    let point = Point::deserialize(deserializer).unwrap();
+    assert_eq!(point, Point { x: 22, y: 33 });
+
+    let mut stream = StreamDeserializer::new(Cursor::new(buffer.as_ref()));
+    let deserializer = stream.deserializer().unwrap().unwrap().project(&[]);
+    let fields = HashMap::<String, i64>::deserialize(deserializer).unwrap();
+    assert!(fields.is_empty());
+    assert!(stream.deserializer().unwrap().is_none());
+}
+
+#[test]
+fn aliased_fields() {
+    // serde lists aliases among the struct's fields, so `Y` comes third in
+    // there while its visitor knows it as the second field
+    #[derive(Deserialize, Debug, PartialEq, Eq)]
+    struct Point {
+        #[serde(rename = "X", alias = "Horizontal")]
+        x: i64,
+        #[serde(rename = "Y")]
+        y: i64,
+    }
+
+    let buffer = include_bytes!("reference/output/point_struct.gob");
+    let point = Point::deserialize(Deserializer::from_slice(buffer)).unwrap();
+    assert_eq!(point, Point { x: 22, y: 33 });
+
+    // there is no `Horizontal` field on the wire, so names are handed out
+    // rather than positions in there
+    let by_name = DecodeOptions::new().field_indices(false);
+    let point = Point::deserialize(Deserializer::from_slice(buffer).with_options(by_name));
+    assert_eq!(point.unwrap(), Point { x: 22, y: 33 });
+
+    // `Y` is an alias here, but on the wire as a field of its own: it comes
+    // second in the listed fields, but `X` is the only field
+    #[derive(Deserialize, Debug)]
+    struct Abscissa {
+        #[serde(rename = "X", alias = "Y")]
+        #[allow(dead_code)]
+        x: i64,
+    }
+
+    // both are handed out as the same field, by index or not
+    let x = Abscissa::deserialize(Deserializer::from_slice(buffer).with_options(by_name));
+    assert!(x.unwrap_err().to_string().contains("duplicate field"));
+    let x = Abscissa::deserialize(Deserializer::from_slice(buffer));
+    assert!(x.unwrap_err().to_string().contains("duplicate field"));
+}
+
+#[test]
+fn field_indices() {
+    // declared the other way round than in Go
+    #[derive(Deserialize, Debug, PartialEq, Eq)]
+    struct Point {
+        #[serde(rename = "Y")]
+        y: i64,
+        #[serde(rename = "X")]
+        x: i64,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq, Eq)]
+    struct Abscissa {
+        #[serde(rename = "X")]
+        x: i64,
+    }
+
+    let buffer = include_bytes!("reference/output/point_struct.gob");
+    let point = Point::deserialize(Deserializer::from_slice(buffer));
+    assert_eq!(point.unwrap(), Point { x: 22, y: 33 });
+    // `Y` has no index, and is handed out by name to be ignored
+    let x = Abscissa::deserialize(Deserializer::from_slice(buffer));
+    assert_eq!(x.unwrap(), Abscissa { x: 22 });
+}
+
+#[test]
+fn progress_reporting() {
+    let buffer = include_bytes!("reference/output/slice_of_bool_non_empty_twice.gob");
+    let mut stream = StreamDeserializer::new(Cursor::new(buffer.as_ref()));
+
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let sink = reports.clone();
+    stream.on_progress(2, 0, move |progress| {
+        sink.lock().unwrap().push((
+            progress.bytes_consumed(),
+            progress.messages_read(),
+            progress.values_decoded(),
+        ))
+    });
+
+    while stream.deserialize::<Vec<bool>>().unwrap().is_some() {}
+
+    assert_eq!(*reports.lock().unwrap(), &[(20, 2, 1), (27, 3, 2)]);
+}
+
+#[test]
+fn decode_stats_and_observer() {
+    use gob::de::DecodeObserver;
+
+    #[derive(Default)]
+    struct Events(Arc<Mutex<Vec<String>>>);
+
+    impl DecodeObserver for Events {
+        fn on_type_definition(&mut self, id: gob::ser::TypeId, len: usize) {
+            self.0.lock().unwrap().push(format!("def {:?} {}", id, len));
+        }
+
+        fn on_value(&mut self, id: gob::ser::TypeId, len: usize, _: std::time::Duration) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("value {:?} {}", id, len));
+        }
+
+        fn on_error(&mut self, _: &gob::Error) {
+            self.0.lock().unwrap().push("error".to_owned());
+        }
+    }
+
+    let buffer = include_bytes!("reference/output/slice_of_bool_non_empty_twice.gob");
+    let mut stream = StreamDeserializer::new(Cursor::new(buffer.as_ref()));
+    let events = Events::default();
+    let log = events.0.clone();
+    stream.set_observer(events);
+
+    assert!(stream.deserialize::<Vec<bool>>().unwrap().is_some());
+    assert!(stream.deserialize::<String>().is_err());
+    assert!(stream.deserialize::<Vec<bool>>().unwrap().is_none());
+
+    assert_eq!(
+        *log.lock().unwrap(),
+        &["def TypeId(65) 13", "value TypeId(65) 7", "error"]
+    );
+    let stats = stream.stats();
+    assert_eq!(stats.bytes_consumed(), 27);
+    assert_eq!(stats.sections_read(), 3);
+    assert_eq!(stats.type_definitions(), 1);
+    assert_eq!(stats.values_decoded(), 1);
+    assert_eq!(stats.errors(), 1);
+}
+
+#[test]
+fn decode_stats_without_deserialize() {
+    let buffer = include_bytes!("reference/output/slice_of_bool_non_empty_twice.gob");
+
+    let mut stream = StreamDeserializer::new(Cursor::new(buffer.as_ref()));
+    assert_eq!(stream.skip(1).unwrap(), 1);
+    assert!(stream.deserializer().unwrap().is_some());
+    assert!(stream.deserializer().unwrap().is_none());
+    let stats = stream.stats();
+    assert_eq!(stats.sections_read(), 3);
+    assert_eq!(stats.values_decoded(), 2);
+    assert_eq!(stats.errors(), 0);
+
+    let mut stream = StreamDeserializer::new(Cursor::new(buffer.as_ref()));
+    let (value, errors) = stream.deserialize_lossy::<Vec<bool>>().unwrap().unwrap();
+    assert_eq!((value, errors.len()), (vec![true, false], 0));
+    assert!(stream.deserialize_lossy::<String>().is_err());
+    let stats = stream.stats();
+    assert_eq!(stats.values_decoded(), 1);
+    assert_eq!(stats.errors(), 1);
+}
+
 quickcheck! {
     fn non_blocking_io(seq: PartialWithErrors<GenWouldBlock>) -> bool {
         macro_rules! block {
@@ -494,3 +1044,808 @@ quickcheck! {
         block!(stream.deserialize::<()>()).unwrap().is_none()
     }
 }
+
+#[test]
+fn reporting_ignored_fields() {
+    #[derive(Deserialize, Debug, PartialEq, Eq)]
+    struct Inner {
+        #[serde(rename = "A")]
+        a: i64,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq, Eq)]
+    struct Partial {
+        #[serde(rename = "Name")]
+        name: String,
+        #[serde(rename = "Inner")]
+        inner: Inner,
+        #[serde(rename = "Items")]
+        items: Vec<Inner>,
+    }
+
+    let buffer = include_bytes!("reference/output/ignored_fields.gob");
+    let (value, ignored) = deserialize_reporting_ignored::<Partial>(buffer).unwrap();
+    assert_eq!(
+        value,
+        Partial {
+            name: "everything".to_owned(),
+            inner: Inner { a: 1 },
+            items: vec![Inner { a: 2 }, Inner { a: 3 }],
+        }
+    );
+    assert_eq!(
+        ignored,
+        &[
+            "Flag",
+            "Count",
+            "Ratio",
+            "Data",
+            "Z",
+            "Tags",
+            "Grid",
+            "Attrs",
+            "Inner.B",
+            "Items.0.B",
+            "Items.1.B",
+            "Any",
+        ]
+    );
+}
+
+#[test]
+fn reporting_ignored_nothing() {
+    let buffer = include_bytes!("reference/output/ignored_fields.gob");
+    let (_, ignored) = deserialize_reporting_ignored::<Value>(buffer).unwrap();
+    assert!(ignored.is_empty());
+}
+
+#[test]
+fn type_defs_from_preamble() {
+    let buffer = include_bytes!("reference/output/slice_of_bool_non_empty_twice.gob");
+    let (preamble, first, second) = (&buffer[..13], &buffer[13..20], &buffer[20..]);
+
+    let mut stream = StreamDeserializer::new(preamble);
+    assert!(stream.deserialize::<Vec<bool>>().unwrap().is_none());
+    let mut defs = stream.type_defs();
+
+    let deserializer = Deserializer::from_slice_with_type_defs(first, &mut defs);
+    assert_eq!(
+        Vec::<bool>::deserialize(deserializer).unwrap(),
+        &[true, false]
+    );
+
+    let mut stream = StreamDeserializer::with_type_defs(second, defs);
+    assert_eq!(
+        stream.deserialize::<Vec<bool>>().unwrap(),
+        Some(vec![false, true])
+    );
+
+    assert!(Vec::<bool>::deserialize(Deserializer::from_slice(first)).is_err());
+}
+
+#[test]
+fn thread_safety() {
+    fn assert_send<T: Send>() {}
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    assert_send_sync::<Deserializer<'static>>();
+    assert_send::<StreamDeserializer<Cursor<Vec<u8>>>>();
+    assert_send::<gob::de::SliceStreamDeserializer<Vec<u8>>>();
+    assert_send::<gob::de::SliceDeserializers<'static>>();
+    assert_send_sync::<Decoder>();
+    assert_send_sync::<gob::session::Session>();
+    assert_send::<gob::reader::EventReader<Cursor<Vec<u8>>>>();
+    assert_send_sync::<Value>();
+    assert_send_sync::<gob::Error>();
+}
+
+#[test]
+fn type_defs_shared_across_threads() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<gob::de::TypeDefs>();
+
+    let buffer = include_bytes!("reference/output/slice_of_bool_non_empty_twice.gob");
+    let mut stream = StreamDeserializer::new(&buffer[..13]);
+    assert!(stream.deserialize::<Vec<bool>>().unwrap().is_none());
+    let defs = stream.type_defs();
+
+    let workers = (0..4)
+        .map(|i| {
+            let defs = defs.clone();
+            std::thread::spawn(move || {
+                // every other worker defines its types again on its own stream
+                let input = if i % 2 == 0 {
+                    buffer[20..].to_vec()
+                } else {
+                    buffer.to_vec()
+                };
+                let mut stream = StreamDeserializer::with_type_defs(Cursor::new(input), defs);
+                stream.deserialize::<Vec<bool>>().unwrap()
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for (i, worker) in workers.into_iter().enumerate() {
+        let expected = if i % 2 == 0 {
+            vec![false, true]
+        } else {
+            vec![true, false]
+        };
+        assert_eq!(worker.join().unwrap(), Some(expected));
+    }
+}
+
+#[test]
+fn resync_after_corrupt_sections() {
+    let bool_true = [3, 2, 0, 1];
+    let mut input = bool_true.to_vec();
+    // a definition of type 65 that does not decode
+    input.extend(&[4, 0xff, 0x81, 0x07, 0x07]);
+    input.extend(&bool_true);
+    // a type id running past the end of its section
+    input.extend(&[2, 0xfa, 0x00]);
+    input.extend(&bool_true);
+    // a value of a type never defined
+    input.extend(&[3, 0xff, 0xc8, 0x00]);
+    input.extend(&bool_true);
+
+    let mut stream = StreamDeserializer::new(Cursor::new(&input[..]));
+    stream.set_resync(true);
+    assert_eq!(stream.deserialize::<bool>().unwrap(), Some(true));
+    assert!(stream.deserialize::<bool>().is_err());
+    assert_eq!(stream.deserialize::<bool>().unwrap(), Some(true));
+    assert!(stream.deserialize::<bool>().is_err());
+    assert_eq!(stream.deserialize::<bool>().unwrap(), Some(true));
+    assert!(stream.peek_type().is_err());
+    assert_eq!(stream.peek_type().unwrap().unwrap().name(), "bool");
+    assert_eq!(stream.deserialize::<bool>().unwrap(), Some(true));
+    assert_eq!(stream.deserialize::<bool>().unwrap(), None);
+    assert_eq!(stream.skipped_sections(), 3);
+
+    // without resync, the stream stays stuck on the corrupt definition
+    let mut stream = StreamDeserializer::new(Cursor::new(&input[..]));
+    assert_eq!(stream.deserialize::<bool>().unwrap(), Some(true));
+    assert!(stream.deserialize::<bool>().is_err());
+    assert!(stream.deserialize::<bool>().is_err());
+    assert_eq!(stream.skipped_sections(), 0);
+}
+
+#[test]
+fn slice_many() {
+    let buffer = include_bytes!("reference/output/non_empty_values.gob");
+    let mut values = Deserializer::from_slice_many(&buffer[..]);
+    for _ in 0..4 {
+        Value::deserialize(values.next().unwrap().unwrap()).unwrap();
+    }
+    let s = <&str>::deserialize(values.next().unwrap().unwrap()).unwrap();
+    let bytes = <&[u8]>::deserialize(values.next().unwrap().unwrap()).unwrap();
+    assert_eq!((s, bytes), ("foo", &[1, 2][..]));
+    assert!(buffer.as_ptr_range().contains(&s.as_ptr()));
+
+    let buffer = include_bytes!("reference/output/slice_of_bool_non_empty_twice.gob");
+    let decoded = Deserializer::from_slice_many(&buffer[..])
+        .map(|de| Vec::<bool>::deserialize(de?))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(decoded, [vec![true, false], vec![false, true]]);
+
+    // definitions are carried over, and iteration stops at a truncated
+    // section
+    let mut values = Deserializer::from_slice_many(&buffer[..buffer.len() - 1]);
+    assert!(values.next().unwrap().is_ok());
+    assert!(values.position() > 0);
+    assert!(values.next().unwrap().is_err());
+    assert!(values.next().is_none());
+}
+
+#[test]
+fn slice_stream_borrowed_values() {
+    use gob::de::SliceStreamDeserializer;
+
+    let buffer = include_bytes!("reference/output/non_empty_values.gob");
+    let mut stream = SliceStreamDeserializer::new(bytes::Bytes::from(&buffer[..]));
+    let source = stream.get_ref().as_ref().as_ptr_range();
+
+    for _ in 0..4 {
+        stream.deserialize::<Value>().unwrap().unwrap();
+    }
+    {
+        let s = stream.deserialize::<&str>().unwrap().unwrap();
+        assert_eq!(s, "foo");
+        assert!(source.contains(&s.as_ptr()));
+    }
+    assert_eq!(stream.deserialize::<&[u8]>().unwrap(), Some(&[1, 2][..]));
+    assert_eq!(
+        stream.deserialize::<Vec<bool>>().unwrap(),
+        Some(vec![true, false])
+    );
+    assert_eq!(stream.deserialize::<Value>().unwrap(), None);
+    assert_eq!(stream.position(), buffer.len());
+}
+
+#[test]
+fn slice_stream_truncated() {
+    use gob::de::SliceStreamDeserializer;
+
+    let buffer = include_bytes!("reference/output/slice_of_bool_non_empty_twice.gob");
+    let mut stream = SliceStreamDeserializer::new(&buffer[..buffer.len() - 1]);
+
+    assert!(stream.deserialize::<Vec<bool>>().unwrap().is_some());
+    let err = stream.deserialize::<Vec<bool>>().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Io(std::io::ErrorKind::UnexpectedEof));
+}
+
+#[test]
+fn reused_stream_buffer() {
+    let buffer = include_bytes!("reference/output/non_empty_values.gob");
+
+    let mut stream = StreamDeserializer::new(Cursor::new(buffer.as_ref()));
+    while stream.deserialize::<Value>().unwrap().is_some() {}
+    let stats = stream.buffer_stats();
+    assert_eq!(stats.reallocations(), 0);
+
+    let reused = stream.into_buffer();
+    let ptr = reused.as_ptr();
+    let mut stream = StreamDeserializer::with_buffer(Cursor::new(buffer.as_ref()), reused);
+    assert_eq!(stream.deserialize::<bool>().unwrap(), Some(true));
+    assert_eq!(stream.buffer_stats(), stats);
+    assert_eq!(stream.into_buffer().as_ptr(), ptr);
+}
+
+#[test]
+fn decoder_buffer_growth() {
+    let buffer = include_bytes!("reference/output/non_empty_values.gob");
+    let mut decoder = Decoder::with_buffer(Vec::new());
+    assert_eq!(decoder.buffer_stats().capacity(), 0);
+
+    decoder.feed(buffer);
+    assert_eq!(decoder.buffer_stats().reallocations(), 1);
+    while decoder.next_value::<Value>().unwrap().is_some() {}
+    decoder.feed(buffer);
+    assert_eq!(decoder.buffer_stats().reallocations(), 1);
+}
+
+#[test]
+fn decoder_byte_by_byte() {
+    let buffer = include_bytes!("reference/output/non_empty_values.gob");
+    let mut decoder = Decoder::new();
+    let mut values = Vec::new();
+
+    for byte in buffer.iter() {
+        decoder.feed(&[*byte]);
+        while let Some(value) = decoder.next_value::<Value>().unwrap() {
+            values.push(value);
+        }
+    }
+
+    assert_eq!(values.len(), 7);
+    assert_eq!(values[0], Value::Bool(true));
+    assert_eq!(values[4], Value::String("foo".into()));
+    assert_eq!(decoder.buffered(), 0);
+}
+
+#[test]
+fn decoder_borrowed_values() {
+    let buffer = include_bytes!("reference/output/non_empty_values.gob");
+    let mut decoder = Decoder::new();
+    decoder.feed(buffer);
+
+    for _ in 0..4 {
+        decoder.next_value::<Value>().unwrap().unwrap();
+    }
+    assert_eq!(decoder.next_value::<&str>().unwrap(), Some("foo"));
+    assert_eq!(decoder.next_value::<&[u8]>().unwrap(), Some(&[1, 2][..]));
+}
+
+// interns decoded values, returning the index of each
+#[derive(Default)]
+struct Interner(Vec<Value>);
+
+impl<'a, 'de> serde::de::DeserializeSeed<'de> for &'a mut Interner {
+    type Value = usize;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<usize, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = Value::deserialize(deserializer)?;
+        match self.0.iter().position(|v| *v == value) {
+            Some(index) => Ok(index),
+            None => {
+                self.0.push(value);
+                Ok(self.0.len() - 1)
+            }
+        }
+    }
+}
+
+#[test]
+fn stream_deserialize_seed() {
+    let buffer = include_bytes!("reference/output/slice_of_bool_non_empty_twice.gob");
+    let input = [&buffer[..], &buffer[..]].concat();
+    let mut stream = StreamDeserializer::new(Cursor::new(input));
+    let mut interner = Interner::default();
+
+    let mut indices = Vec::new();
+    while let Some(index) = stream.deserialize_seed(&mut interner).unwrap() {
+        indices.push(index);
+    }
+    assert_eq!(indices, &[0, 1, 0, 1]);
+    assert_eq!(interner.0.len(), 2);
+}
+
+#[test]
+fn stream_resume() {
+    let buffer = include_bytes!("reference/output/slice_of_bool_non_empty_twice.gob");
+    let mut stream = StreamDeserializer::new(Cursor::new(&buffer[..]));
+    assert_eq!(
+        stream.deserialize::<Vec<bool>>().unwrap(),
+        Some(vec![true, false])
+    );
+    // a peeked value is read again on resuming
+    assert!(stream.peek_type().unwrap().is_some());
+    let state = stream.save_state().unwrap();
+    assert_eq!(state.offset(), 20);
+
+    let state = StreamState::from_bytes(&state.to_bytes().unwrap()).unwrap();
+    assert_eq!(state.offset(), 20);
+    let mut resumed = StreamDeserializer::resume(Cursor::new(&buffer[..]), state).unwrap();
+    assert_eq!(
+        resumed.deserialize::<Vec<bool>>().unwrap(),
+        Some(vec![false, true])
+    );
+    assert_eq!(resumed.save_state().unwrap().offset(), buffer.len() as u64);
+    assert_eq!(resumed.deserialize::<Vec<bool>>().unwrap(), None);
+}
+
+#[test]
+fn schema_registry() {
+    // the definition of `Point` is kept apart from its value
+    let buffer = include_bytes!("reference/output/point_struct.gob");
+    let (def, value) = buffer.split_at(buffer[0] as usize + 1);
+    let table = TypeDefs::from_bytes(def).unwrap();
+    let input = [value, value].concat();
+
+    let lookups = Arc::new(Mutex::new(Vec::new()));
+    let registry = {
+        let lookups = lookups.clone();
+        move |id: gob::types::TypeId| {
+            lookups.lock().unwrap().push(id.get());
+            Ok::<_, gob::Error>(table.lookup(id).cloned())
+        }
+    };
+    let mut stream = StreamDeserializer::new(Cursor::new(&input[..]));
+    stream.set_schema_registry(registry);
+    while let Some(value) = stream.deserialize::<Value>().unwrap() {
+        assert!(matches!(value, Value::Map(ref fields) if fields.len() == 2));
+    }
+    assert_eq!(*lookups.lock().unwrap(), &[65]);
+
+    let mut decoder = Decoder::new();
+    decoder.set_schema_registry(TypeDefs::from_bytes(def).unwrap());
+    decoder.feed(value);
+    assert!(decoder.next_value::<Value>().unwrap().is_some());
+
+    let mut stream = gob::de::SliceStreamDeserializer::new(value);
+    stream.set_schema_registry(TypeDefs::from_bytes(def).unwrap());
+    assert!(stream.deserialize::<Value>().unwrap().is_some());
+
+    let table = TypeDefs::from_bytes(def).unwrap();
+    let de = Deserializer::from_slice(value).with_schema_registry(table);
+    assert!(Value::deserialize(de).is_ok());
+
+    // without a registry, the type is unknown
+    let mut stream = StreamDeserializer::new(Cursor::new(value));
+    assert!(stream.deserialize::<Value>().is_err());
+}
+
+#[test]
+fn schema_registry_interface_value() {
+    #[derive(Deserialize, Debug, PartialEq, Eq)]
+    struct Point {
+        #[serde(rename = "X")]
+        x: i64,
+        #[serde(rename = "Y")]
+        y: i64,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq, Eq)]
+    struct Wrapper {
+        #[serde(rename = "Value")]
+        value: Point,
+    }
+
+    // leaves out the definition and value of `Point`, sent ahead of the
+    // wrapper holding one
+    let buffer = include_bytes!("reference/output/interface_value.gob");
+    let point_len = buffer[0] as usize + 1;
+    let wrapper = &buffer[point_len + buffer[point_len] as usize + 1..];
+    let table = TypeDefs::from_bytes(&buffer[..point_len]).unwrap();
+
+    let mut stream = StreamDeserializer::new(Cursor::new(wrapper));
+    stream.set_schema_registry(table);
+    assert_eq!(
+        stream.deserialize::<Wrapper>().unwrap(),
+        Some(Wrapper {
+            value: Point { x: 22, y: 33 }
+        })
+    );
+
+    let mut stream = StreamDeserializer::new(Cursor::new(wrapper));
+    assert!(stream.deserialize::<Wrapper>().is_err());
+}
+
+#[test]
+fn decoder_next_value_seed() {
+    let buffer = include_bytes!("reference/output/non_empty_values.gob");
+    let mut decoder = Decoder::new();
+    let mut interner = Interner::default();
+    decoder.feed(buffer);
+    decoder.feed(buffer);
+
+    let mut indices = Vec::new();
+    while let Some(index) = decoder.next_value_seed(&mut interner).unwrap() {
+        indices.push(index);
+    }
+    assert_eq!(indices, &[0, 1, 2, 3, 4, 5, 6, 0, 1, 2, 3, 4, 5, 6]);
+}
+
+#[test]
+fn message_too_large() {
+    // a section claiming to be 2^56 bytes long
+    let input = [0xf8, 1, 0, 0, 0, 0, 0, 0, 0, 0x04, 0x00, 0x01];
+
+    let mut stream = StreamDeserializer::new(Cursor::new(&input[..]));
+    let err = stream.deserialize::<Value>().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::MessageTooLarge);
+
+    let mut decoder = Decoder::new();
+    decoder.feed(&input);
+    let err = decoder.next_value::<Value>().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::MessageTooLarge);
+}
+
+#[test]
+fn type_id_beyond_message() {
+    let input = [0x01, 0xff, 0x82];
+    let mut stream = StreamDeserializer::new(Cursor::new(&input[..]));
+    let err = stream.deserialize::<Value>().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Deserialize);
+}
+
+#[test]
+fn decoder_truncated() {
+    let buffer = include_bytes!("reference/output/point_struct.gob");
+    let mut decoder = Decoder::new();
+    decoder.feed(&buffer[..buffer.len() - 1]);
+
+    assert!(decoder.next_value::<Value>().unwrap().is_none());
+    assert!(decoder.buffered() > 0);
+
+    decoder.feed(&buffer[buffer.len() - 1..]);
+    assert!(decoder.next_value::<Value>().unwrap().is_some());
+    assert_eq!(decoder.buffered(), 0);
+}
+
+#[test]
+fn decoder_bytes_needed() {
+    let buffer = include_bytes!("reference/output/point_struct.gob");
+    let mut decoder = Decoder::new();
+    assert_eq!(decoder.bytes_needed(), None);
+
+    // the definition of `Point` comes first
+    let def_len = buffer[0] as usize + 1;
+    decoder.feed(&buffer[..3]);
+    assert_eq!(decoder.bytes_needed(), Some(def_len - 3));
+    decoder.feed(&buffer[3..]);
+    assert_eq!(decoder.bytes_needed(), Some(0));
+}
+
+#[test]
+fn decoder_buffer_limit() {
+    let buffer = include_bytes!("reference/output/non_empty_values.gob");
+    let mut decoder = Decoder::new();
+    decoder.set_buffer_limit(Some(16));
+
+    // a fast producer is held back until values are decoded
+    let mut input = &buffer[..];
+    let mut values = Vec::new();
+    while !input.is_empty() {
+        match decoder.try_feed(input) {
+            Ok(len) => input = &input[len..],
+            Err(err) => {
+                assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+                assert!(decoder.buffered() <= 16);
+                while let Some(value) = decoder.next_value::<Value>().unwrap() {
+                    values.push(value);
+                }
+            }
+        }
+    }
+    while let Some(value) = decoder.next_value::<Value>().unwrap() {
+        values.push(value);
+    }
+    assert_eq!(values.len(), 7);
+    assert_eq!(values[4], Value::String("foo".into()));
+
+    // a section larger than the limit can never be decoded
+    let mut decoder = Decoder::new();
+    decoder.set_buffer_limit(Some(4));
+    let point = include_bytes!("reference/output/point_struct.gob");
+    assert_eq!(decoder.try_feed(point).unwrap(), 4);
+    let err = decoder.try_feed(&point[4..]).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn need_more_data() {
+    // a section of 9 bytes with only 4 of them sent
+    let input = [9, 12, 0, 5, b'a'];
+    let err = String::deserialize(Deserializer::from_slice(&input)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NeedMoreData { hint: Some(5) });
+
+    // a string of 5 bytes in a section that ends after one of them, which
+    // no more input can fix
+    let input = [4, 12, 0, 5, b'a'];
+    let err = String::deserialize(Deserializer::from_slice(&input)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Deserialize);
+}
+
+#[test]
+fn strict_mode_trailing_data() {
+    let input = [4, 2, 0, 1, 9];
+
+    let deserializer = Deserializer::from_slice(&input);
+    assert_eq!(bool::deserialize(deserializer).unwrap(), true);
+
+    let deserializer = Deserializer::from_slice(&input).with_mode(Mode::Strict);
+    assert!(bool::deserialize(deserializer).is_err());
+
+    let mut decoder = Decoder::new();
+    decoder.set_mode(Mode::Strict);
+    decoder.feed(&input);
+    assert!(decoder.next_value::<bool>().is_err());
+}
+
+#[test]
+fn strict_mode_redefinition() {
+    let buffer = include_bytes!("reference/output/slice_of_bool_non_empty_twice.gob");
+    let input = [&buffer[..13], &buffer[..13], &buffer[13..20]].concat();
+
+    let mut stream = StreamDeserializer::new(Cursor::new(&input));
+    assert_eq!(
+        stream.deserialize::<Vec<bool>>().unwrap(),
+        Some(vec![true, false])
+    );
+
+    let mut stream = StreamDeserializer::new(Cursor::new(&input));
+    stream.set_mode(Mode::Strict);
+    assert!(stream.deserialize::<Vec<bool>>().is_err());
+}
+
+#[test]
+fn builder() {
+    let input = [4, 2, 0, 1, 9];
+    let builder = gob::de::Builder::new().reject_trailing_data(true);
+    assert!(bool::deserialize(builder.slice(&input)).is_err());
+    let deserializer = builder.slice_many(&input).next().unwrap().unwrap();
+    assert!(bool::deserialize(deserializer).is_err());
+    assert!(builder
+        .stream(Cursor::new(&input))
+        .deserialize::<bool>()
+        .is_err());
+    assert!(builder.slice_stream(&input).deserialize::<bool>().is_err());
+
+    let builder = builder.mode(Mode::Lenient).read_chunk_size(1);
+    let mut stream = builder.stream(Cursor::new(&input));
+    assert_eq!(stream.deserialize::<bool>().unwrap(), Some(true));
+
+    let buffer = include_bytes!("reference/output/slice_of_bool_non_empty_twice.gob");
+    let mut stream = StreamDeserializer::new(Cursor::new(&buffer[..]));
+    stream.deserialize::<Vec<bool>>().unwrap();
+    let builder = builder.type_defs(stream.type_defs());
+    let deserializer = builder.slice(&buffer[13..20]);
+    assert_eq!(
+        Vec::<bool>::deserialize(deserializer).unwrap(),
+        [true, false]
+    );
+}
+
+#[test]
+fn serde_json_value() {
+    let buffer = include_bytes!("reference/output/non_empty_values.gob");
+    let mut stream = StreamDeserializer::new(Cursor::new(buffer.as_ref()));
+    stream.set_options(DecodeOptions::new().bytes_as_seq(true));
+    let mut values = Vec::new();
+    while let Some(value) = stream.deserialize::<serde_json::Value>().unwrap() {
+        values.push(value);
+    }
+    assert_eq!(
+        serde_json::Value::Array(values),
+        serde_json::json!([true, 42, 42, 42.0, "foo", [1, 2], [true, false]])
+    );
+
+    let buffer = include_bytes!("reference/output/ignored_fields.gob");
+    let deserializer =
+        Deserializer::from_slice(buffer).with_options(DecodeOptions::new().bytes_as_seq(true));
+    let value = serde_json::Value::deserialize(deserializer).unwrap();
+    assert_eq!(value["Data"], serde_json::json!([1, 2]));
+    assert_eq!(value["Attrs"], serde_json::json!({"k": 5}));
+
+    let mut stream = gob::StreamSerializer::new(Vec::new());
+    stream
+        .serialize(&BTreeMap::from([(-3i64, 1.5f64), (7, 2.0)]))
+        .unwrap();
+    let buffer = stream.into_writer();
+    let value = serde_json::Value::deserialize(Deserializer::from_slice(&buffer)).unwrap();
+    assert_eq!(value, serde_json::json!({"-3": 1.5, "7": 2.0}));
+}
+
+#[test]
+fn lenient_mode_invalid_utf8() {
+    let input = [6, 12, 0, 3, b'a', 0xff, b'b'];
+
+    let deserializer = Deserializer::from_slice(&input);
+    assert!(String::deserialize(deserializer).is_err());
+
+    let deserializer = Deserializer::from_slice(&input).with_mode(Mode::Lenient);
+    assert_eq!(String::deserialize(deserializer).unwrap(), "a\u{fffd}b");
+}
+
+#[test]
+fn fork() {
+    #[derive(Deserialize)]
+    struct Point {
+        #[serde(rename = "X")]
+        x: i64,
+        #[serde(rename = "Y")]
+        y: i64,
+    }
+
+    #[derive(Deserialize)]
+    struct Index {
+        #[serde(rename = "X")]
+        x: i64,
+    }
+
+    let buffer = include_bytes!("reference/output/point_struct.gob");
+
+    let deserializer = Deserializer::from_slice(buffer);
+    let forked = deserializer.fork();
+    let point = Point::deserialize(deserializer).unwrap();
+    let index = Index::deserialize(forked).unwrap();
+    assert_eq!((point.x, point.y, index.x), (22, 33, 22));
+
+    let mut stream = StreamDeserializer::new(Cursor::new(buffer));
+    let deserializer = stream.deserializer().unwrap().unwrap();
+    let forked = deserializer.fork();
+    let index = Index::deserialize(forked).unwrap();
+    let point = Point::deserialize(deserializer).unwrap();
+    assert_eq!((point.x, point.y, index.x), (22, 33, 22));
+}
+
+#[test]
+fn skip_values() {
+    let buffer = include_bytes!("reference/output/non_empty_values.gob");
+    let mut stream = StreamDeserializer::new(Cursor::new(buffer.as_ref()));
+
+    assert_eq!(stream.skip(4).unwrap(), 4);
+    assert_eq!(stream.deserialize::<String>().unwrap().unwrap(), "foo");
+    assert_eq!(stream.skip(0).unwrap(), 0);
+    assert_eq!(stream.skip(5).unwrap(), 2);
+    assert!(stream.deserialize::<()>().unwrap().is_none());
+}
+
+#[test]
+fn ignored_any_values() {
+    let buffer = include_bytes!("reference/output/ignored_fields.gob");
+    let deserializer = Deserializer::from_slice(buffer).with_mode(Mode::Strict);
+    IgnoredAny::deserialize(deserializer).unwrap();
+
+    let buffer = include_bytes!("reference/output/non_empty_values.gob");
+    let mut stream = StreamDeserializer::new(Cursor::new(buffer.as_ref()));
+    stream.set_mode(Mode::Strict);
+    for _ in 0..7 {
+        stream.deserialize::<IgnoredAny>().unwrap().unwrap();
+    }
+    assert!(stream.deserialize::<IgnoredAny>().unwrap().is_none());
+}
+
+#[test]
+fn ignored_any_truncated() {
+    let buffer = include_bytes!("reference/output/ignored_fields.gob");
+    let deserializer = Deserializer::from_slice(&buffer[..buffer.len() - 4]);
+    assert!(IgnoredAny::deserialize(deserializer).is_err());
+}
+
+#[test]
+fn decode_hooks() {
+    use gob::de::DecodeHooks;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[serde(rename_all = "UPPERCASE")]
+    struct Point {
+        x: i64,
+        y: i64,
+    }
+
+    let buffer = include_bytes!("reference/output/point_struct.gob");
+
+    let hooks = DecodeHooks::new().on("Point", |value| match value {
+        Value::Map(fields) => Ok(Value::Seq(
+            fields.into_iter().map(|(_, value)| value).collect(),
+        )),
+        _ => Err(serde::de::Error::custom("expected a struct")),
+    });
+    let deserializer = Deserializer::from_slice(buffer).with_decode_hooks(hooks.clone());
+    let decoded = <(i64, i64)>::deserialize(deserializer).unwrap();
+    assert_eq!(decoded, (22, 33));
+
+    let mut stream = StreamDeserializer::new(Cursor::new(buffer.as_ref()));
+    stream.set_decode_hooks(DecodeHooks::new().on("Point", |_| {
+        Ok(Value::Struct(vec![
+            ("X".into(), Value::Int(1)),
+            ("Y".into(), Value::Int(2)),
+        ]))
+    }));
+    let decoded = stream.deserialize::<Point>().unwrap();
+    assert_eq!(decoded, Some(Point { x: 1, y: 2 }));
+
+    let deserializer = Deserializer::from_slice(buffer)
+        .with_decode_hooks(DecodeHooks::new().on("Other", |_| Ok(Value::Nil)));
+    let decoded = Point::deserialize(deserializer).unwrap();
+    assert_eq!(decoded, Point { x: 22, y: 33 });
+
+    let deserializer = Deserializer::from_slice(buffer).with_decode_hooks(
+        DecodeHooks::new().on("Point", |_| Err(serde::de::Error::custom("legacy"))),
+    );
+    assert!(Point::deserialize(deserializer).is_err());
+}
+
+#[test]
+fn non_finite_as_none() {
+    let options = DecodeOptions::new().non_finite_as_none(true);
+    let decode = |bytes: &[u8], options| {
+        Option::<f64>::deserialize(Deserializer::from_slice(bytes).with_options(options)).unwrap()
+    };
+
+    let infinity = [5, 8, 0, 254, 240, 127];
+    assert_eq!(decode(&infinity, DecodeOptions::new()), Some(f64::INFINITY));
+    assert_eq!(decode(&infinity, options), None);
+    assert_eq!(decode(&[5, 8, 0, 254, 240, 63], options), Some(1.0));
+    assert_eq!(
+        f64::deserialize(Deserializer::from_slice(&infinity).with_options(options)).unwrap(),
+        f64::INFINITY
+    );
+}
+
+#[test]
+fn long_numeric_slices() {
+    let ints: Vec<i64> = (0..100)
+        .map(|n| if n % 7 == 0 { -n * 1_000_003 } else { n })
+        .collect();
+    let mut stream = gob::StreamSerializer::new(Vec::new());
+    stream.serialize(&ints).unwrap();
+    let buffer = stream.into_writer();
+    let decoded = <Vec<i64>>::deserialize(Deserializer::from_slice(&buffer)).unwrap();
+    assert_eq!(decoded, ints);
+    let decoded = <Vec<i8>>::deserialize(Deserializer::from_slice(&buffer));
+    assert!(decoded.is_err());
+    assert!(
+        <Vec<i64>>::deserialize(Deserializer::from_slice(&buffer[..buffer.len() - 1])).is_err()
+    );
+
+    let floats: Vec<Option<f64>> = (0..40)
+        .map(|n| if n == 33 { None } else { Some(n as f64) })
+        .collect();
+    let mut stream = gob::StreamSerializer::new(Vec::new());
+    let sent: Vec<f64> = floats.iter().map(|f| f.unwrap_or(f64::NAN)).collect();
+    stream.serialize(&sent).unwrap();
+    let buffer = stream.into_writer();
+    let options = DecodeOptions::new().non_finite_as_none(true);
+    let deserializer = Deserializer::from_slice(&buffer).with_options(options);
+    assert_eq!(
+        <Vec<Option<f64>>>::deserialize(deserializer).unwrap(),
+        floats
+    );
+}
@@ -0,0 +1,108 @@
+extern crate gob;
+
+use gob::debug::{dump, validate};
+
+fn dump_to_string(input: &[u8]) -> (String, Result<(), gob::Error>) {
+    let mut output = Vec::new();
+    let result = dump(input, &mut output);
+    (String::from_utf8(output).unwrap(), result)
+}
+
+#[test]
+fn point_struct() {
+    let (output, result) = dump_to_string(include_bytes!("reference/output/point_struct.gob"));
+    result.unwrap();
+    assert!(output.starts_with("section 0 at 0x00000000: 32 bytes, definition of type 65\n"));
+    assert!(output.ends_with(
+        "\
+section 1 at 0x00000020: 8 bytes, value of type 65
+00000020  07                         message length 7
+00000021  ff 82                      type id 65
+00000023  01                         field +1 X (#0)
+00000024  2c                           int 22
+00000025  01                         field +1 Y (#1)
+00000026  42                           int 33
+00000027  00                         end of Point
+
+"
+    ));
+}
+
+#[test]
+fn extra_data() {
+    let (output, result) = dump_to_string(&[5, 4, 0, 2, 0, 7]);
+    result.unwrap();
+    assert_eq!(
+        output,
+        "\
+section 0 at 0x00000000: 6 bytes, value of type 2
+00000000  05                         message length 5
+00000001  04                         type id 2
+00000002  00                         singleton
+00000003  02                         int 1
+00000004  00 07                      !! 2 bytes of extra data at end of message
+
+"
+    );
+}
+
+#[test]
+fn unknown_type() {
+    let (output, result) = dump_to_string(&[3, 255, 130, 0]);
+    assert!(result.is_err());
+    assert!(output.ends_with(
+        "00000004                             !! deserialize error: unknown type id 65\n"
+    ));
+}
+
+#[test]
+fn validate_enum_with_struct_variants() {
+    let report =
+        validate(include_bytes!("reference/output/enum_with_struct_variants.gob").as_ref())
+            .unwrap();
+    assert!(report.is_valid());
+    assert_eq!(report.sections(), 5);
+    assert_eq!(report.definitions(), 4);
+    assert_eq!(report.values(), 1);
+    assert_eq!(report.bytes(), 139);
+    let names = report
+        .types()
+        .iter()
+        .map(|(_, name)| name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, &["Enum", "V1", "V2", "V3"]);
+}
+
+#[test]
+fn validate_extra_data() {
+    let report = validate(&[3, 4, 0, 2, 5, 4, 0, 2, 0, 7][..]).unwrap();
+    let error = report.error().unwrap();
+    assert_eq!(report.values(), 1);
+    assert_eq!(error.section(), 1);
+    assert_eq!(error.offset(), 8);
+    assert_eq!(error.message(), "2 bytes of extra data at end of message");
+}
+
+#[test]
+fn validate_undefined_type() {
+    let report = validate(&[3, 255, 130, 0][..]).unwrap();
+    let error = report.error().unwrap();
+    assert_eq!(error.offset(), 0);
+    assert_eq!(error.message(), "type 65 used before it is defined");
+}
+
+#[test]
+fn validate_invalid_uint() {
+    let report = validate(&[4, 4, 0, 0x80, 0][..]).unwrap();
+    let error = report.error().unwrap();
+    assert_eq!(error.message(), "deserialize error: invalid uint length");
+}
+
+#[test]
+fn validate_truncated() {
+    let report = validate(&[3, 4, 0, 2, 5, 4, 0][..]).unwrap();
+    let error = report.error().unwrap();
+    assert_eq!(report.sections(), 1);
+    assert_eq!(error.offset(), 4);
+    assert_eq!(error.message(), "stream ends in the middle of a message");
+}
@@ -0,0 +1,130 @@
+extern crate gob;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_schema;
+#[macro_use]
+extern crate serde_schema_derive;
+
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::thread;
+
+use gob::duplex::{Duplex, TypeRegistry};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, SchemaSerialize)]
+struct Point {
+    x: i64,
+    y: i64,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, SchemaSerialize)]
+struct Label {
+    text: String,
+}
+
+// a connected pair of sockets, the client's first
+fn socket_pair() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (server, _) = listener.accept().unwrap();
+    (client, server)
+}
+
+#[test]
+fn concurrent_halves() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    // answers every point with its sum, on a thread of its own for each half
+    let server = thread::spawn(move || {
+        let (socket, _) = listener.accept().unwrap();
+        let (mut writer, mut reader) = Duplex::new(socket).split();
+        let (sums, received) = std::sync::mpsc::channel();
+        let sending = thread::spawn(move || {
+            for sum in received {
+                writer.serialize(&sum).unwrap();
+            }
+            writer
+        });
+        while let Some(point) = reader.deserialize::<Point>().unwrap() {
+            sums.send(point.x + point.y).unwrap();
+        }
+        drop(sums);
+        let writer = sending.join().unwrap();
+        assert!(writer.registry().type_defs().type_id("Point").is_some());
+    });
+
+    let socket = TcpStream::connect(addr).unwrap();
+    let (mut writer, mut reader) = Duplex::new(socket).split();
+    let sending = thread::spawn(move || {
+        for i in 0..10 {
+            writer.serialize(&Point { x: i, y: 2 * i }).unwrap();
+        }
+        writer.socket().shutdown(std::net::Shutdown::Write).unwrap();
+    });
+    let mut sums = Vec::new();
+    while let Some(sum) = reader.deserialize::<i64>().unwrap() {
+        sums.push(sum);
+    }
+    sending.join().unwrap();
+    server.join().unwrap();
+
+    assert_eq!(sums, (0..10).map(|i| 3 * i).collect::<Vec<_>>());
+    assert!(reader.registry().type_defs().type_id("Point").is_none());
+}
+
+#[test]
+fn connections_sharing_registry() {
+    let registry = TypeRegistry::new();
+
+    let (client, server) = socket_pair();
+    let (mut writer, _) = Duplex::new(client).split();
+    writer.serialize(&Point { x: 1, y: 2 }).unwrap();
+    writer.socket().shutdown(Shutdown::Write).unwrap();
+    let (_, mut reader) = Duplex::with_registry(server, registry.clone()).split();
+    assert_eq!(
+        reader.deserialize::<Point>().unwrap(),
+        Some(Point { x: 1, y: 2 })
+    );
+
+    // the same definition of Point again, and one of Label to add
+    let label = Label {
+        text: "five".into(),
+    };
+    let (client, server) = socket_pair();
+    let (mut writer, _) = Duplex::new(client).split();
+    writer.serialize(&Point { x: 3, y: 4 }).unwrap();
+    writer.serialize(&label).unwrap();
+    writer.socket().shutdown(Shutdown::Write).unwrap();
+    let (_, mut reader) = Duplex::with_registry(server, registry.clone()).split();
+    assert_eq!(
+        reader.deserialize::<Point>().unwrap(),
+        Some(Point { x: 3, y: 4 })
+    );
+    assert_eq!(reader.deserialize::<Label>().unwrap(), Some(label));
+    let defs = registry.type_defs();
+    let point = defs.type_id("Point");
+    assert!(point.is_some());
+    assert!(defs.type_id("Label").is_some());
+
+    // a peer sending Label under the id of Point
+    let (client, server) = socket_pair();
+    let (mut writer, _) = Duplex::new(client).split();
+    writer.serialize(&Label { text: "six".into() }).unwrap();
+    writer.socket().shutdown(Shutdown::Write).unwrap();
+    let (_, mut reader) = Duplex::with_registry(server, registry.clone()).split();
+    assert!(reader.deserialize::<Label>().is_err());
+    assert_eq!(registry.type_defs().type_id("Point"), point);
+}
+
+#[test]
+fn failed_value_publishes_nothing() {
+    let registry = TypeRegistry::new();
+
+    let (client, server) = socket_pair();
+    let (mut writer, _) = Duplex::new(client).split();
+    writer.serialize(&Point { x: 5, y: 6 }).unwrap();
+    writer.socket().shutdown(Shutdown::Write).unwrap();
+    let (_, mut reader) = Duplex::with_registry(server, registry.clone()).split();
+    assert!(reader.deserialize::<String>().is_err());
+    assert!(registry.type_defs().type_id("Point").is_none());
+}
@@ -0,0 +1,268 @@
+extern crate gob;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_schema;
+#[macro_use]
+extern crate serde_schema_derive;
+
+use gob::de::{DecodeOptions, EnumDecoding};
+use gob::ser::{go_declarations, EnumEncoding};
+use gob::{Deserializer, StreamSerializer};
+use serde::Deserialize;
+use serde_schema::SchemaSerialize;
+
+fn encode<T: SchemaSerialize>(value: &T) -> Vec<u8> {
+    let mut stream = StreamSerializer::new_with_write(Vec::new());
+    stream.serialize(value).unwrap();
+    stream.into_writer()
+}
+
+fn decode<'de, T: Deserialize<'de>>(
+    buffer: &'de [u8],
+    enums: EnumDecoding,
+) -> Result<T, gob::Error> {
+    let options = DecodeOptions::new().enum_decoding(enums);
+    T::deserialize(Deserializer::from_slice(buffer).with_options(options))
+}
+
+#[derive(Serialize, SchemaSerialize)]
+struct NamedEnvelope {
+    #[serde(rename = "Seq")]
+    seq: i64,
+    #[serde(rename = "Kind")]
+    kind: String,
+    #[serde(rename = "Text")]
+    text: String,
+}
+
+#[derive(Serialize, SchemaSerialize)]
+struct IndexedEnvelope {
+    #[serde(rename = "Kind")]
+    kind: u64,
+    #[serde(rename = "Seq")]
+    seq: i64,
+    #[serde(rename = "Text")]
+    text: String,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+enum Message {
+    Ping {
+        #[serde(rename = "Seq", default)]
+        seq: i64,
+    },
+    Say {
+        #[serde(rename = "Text", default)]
+        text: String,
+    },
+    Quit,
+}
+
+#[test]
+fn internal_tag_by_name() {
+    let buffer = encode(&NamedEnvelope {
+        seq: 0,
+        kind: "Say".to_owned(),
+        text: "hi".to_owned(),
+    });
+    let decoded = decode::<Message>(&buffer, EnumDecoding::Internal("Kind")).unwrap();
+    assert_eq!(
+        decoded,
+        Message::Say {
+            text: "hi".to_owned()
+        }
+    );
+    assert!(decode::<Message>(&buffer, EnumDecoding::External).is_err());
+
+    let buffer = encode(&NamedEnvelope {
+        seq: 0,
+        kind: "Quit".to_owned(),
+        text: "bye".to_owned(),
+    });
+    let decoded = decode::<Message>(&buffer, EnumDecoding::Internal("Kind")).unwrap();
+    assert_eq!(decoded, Message::Quit);
+}
+
+#[test]
+fn internal_tag_by_index() {
+    let buffer = encode(&IndexedEnvelope {
+        kind: 1,
+        seq: 0,
+        text: "hi".to_owned(),
+    });
+    let decoded = decode::<Message>(&buffer, EnumDecoding::Internal("Kind")).unwrap();
+    assert_eq!(
+        decoded,
+        Message::Say {
+            text: "hi".to_owned()
+        }
+    );
+
+    // the zero value is not sent, and selects the first variant
+    let buffer = encode(&IndexedEnvelope {
+        kind: 0,
+        seq: 3,
+        text: String::new(),
+    });
+    let decoded = decode::<Message>(&buffer, EnumDecoding::Internal("Kind")).unwrap();
+    assert_eq!(decoded, Message::Ping { seq: 3 });
+
+    let buffer = encode(&IndexedEnvelope {
+        kind: 3,
+        seq: 0,
+        text: String::new(),
+    });
+    assert!(decode::<Message>(&buffer, EnumDecoding::Internal("Kind")).is_err());
+}
+
+#[derive(Serialize, SchemaSerialize)]
+struct Ping {
+    #[serde(rename = "Seq")]
+    seq: i64,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct PingValue {
+    #[serde(rename = "Seq")]
+    seq: i64,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+enum ByType {
+    Pong(PingValue),
+    Ping(PingValue),
+}
+
+#[test]
+fn variant_by_type_name() {
+    let buffer = encode(&Ping { seq: 7 });
+    let decoded = decode::<ByType>(&buffer, EnumDecoding::TypeName).unwrap();
+    assert_eq!(decoded, ByType::Ping(PingValue { seq: 7 }));
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(tag = "Kind")]
+enum SerdeTagged {
+    Ping {
+        #[serde(rename = "Seq")]
+        seq: i64,
+    },
+    Say {
+        #[serde(rename = "Text")]
+        text: String,
+    },
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(untagged)]
+enum Untagged {
+    Say {
+        #[serde(rename = "Text")]
+        text: String,
+    },
+    Ping {
+        #[serde(rename = "Seq")]
+        seq: i64,
+    },
+}
+
+#[test]
+fn serde_tagged_and_untagged() {
+    let buffer = encode(&NamedEnvelope {
+        seq: 5,
+        kind: "Ping".to_owned(),
+        text: String::new(),
+    });
+    let decoded = decode::<SerdeTagged>(&buffer, EnumDecoding::External).unwrap();
+    assert_eq!(decoded, SerdeTagged::Ping { seq: 5 });
+    let decoded = decode::<Untagged>(&buffer, EnumDecoding::External).unwrap();
+    assert_eq!(decoded, Untagged::Ping { seq: 5 });
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, SchemaSerialize)]
+enum Shape {
+    Empty,
+    Circle(f64),
+    Rect {
+        #[serde(rename = "W")]
+        w: u64,
+        #[serde(rename = "H")]
+        h: u64,
+    },
+}
+
+// what a Go program sees
+#[derive(Debug, PartialEq, Deserialize)]
+struct GoShape {
+    #[serde(rename = "Tag")]
+    tag: String,
+    #[serde(rename = "Circle")]
+    circle: Option<f64>,
+}
+
+#[test]
+fn adjacent_tags() {
+    let shapes = [Shape::Empty, Shape::Circle(1.5), Shape::Rect { w: 2, h: 3 }];
+    let mut stream = StreamSerializer::new_with_write(Vec::new());
+    stream.set_enum_encoding(EnumEncoding::Adjacent);
+    for shape in &shapes {
+        stream.serialize(shape).unwrap();
+    }
+    let buffer = stream.into_writer();
+
+    let options = DecodeOptions::new().enum_decoding(EnumDecoding::Adjacent("Tag"));
+    let mut decoder = gob::StreamDeserializer::new(&buffer[..]);
+    decoder.set_options(options);
+    for shape in &shapes {
+        assert_eq!(
+            decoder.deserialize::<Shape>().unwrap().as_ref(),
+            Some(shape)
+        );
+    }
+
+    let mut stream = StreamSerializer::new_with_write(Vec::new());
+    stream.set_enum_encoding(EnumEncoding::Adjacent);
+    stream.serialize(&Shape::Circle(1.5)).unwrap();
+    let buffer = stream.into_writer();
+    let decoded = GoShape::deserialize(Deserializer::from_slice(&buffer)).unwrap();
+    assert_eq!(
+        decoded,
+        GoShape {
+            tag: "Circle".to_owned(),
+            circle: Some(1.5),
+        }
+    );
+}
+
+#[test]
+fn go_helper() {
+    let declarations = go_declarations::<Shape>().unwrap();
+    assert_eq!(
+        declarations,
+        "type Rect struct {
+\tW uint64
+\tH uint64
+}
+
+type Shape struct {
+\tTag string
+\tCircle *float64
+\tRect *Rect
+}
+
+// Value returns the value of the variant Tag names, or nil if it has none.
+func (e *Shape) Value() interface{} {
+\tswitch e.Tag {
+\tcase \"Circle\":
+\t\treturn e.Circle
+\tcase \"Rect\":
+\t\treturn e.Rect
+\t}
+\treturn nil
+}
+
+"
+    );
+}
@@ -0,0 +1,70 @@
+#![cfg(feature = "erased")]
+
+extern crate erased_serde;
+extern crate gob;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_schema;
+#[macro_use]
+extern crate serde_schema_derive;
+
+use gob::ser::{SchemaHandle, SerializeErased};
+use gob::{StreamDeserializer, StreamSerializer, Value};
+
+#[derive(Serialize, SchemaSerialize)]
+struct Metric {
+    #[serde(rename = "Name")]
+    name: String,
+    #[serde(rename = "Value")]
+    value: f64,
+}
+
+#[derive(Serialize, SchemaSerialize)]
+struct Event {
+    #[serde(rename = "Kind")]
+    kind: u64,
+}
+
+#[test]
+fn serialize_trait_objects() {
+    let outputs: Vec<Box<dyn SerializeErased>> = vec![
+        Box::new(Metric {
+            name: "load".into(),
+            value: 0.5,
+        }),
+        Box::new(Event { kind: 3 }),
+        Box::new(vec![1i64, 2]),
+    ];
+
+    let mut stream = StreamSerializer::new(Vec::new());
+    for output in &outputs {
+        stream.serialize_erased(output.as_ref()).unwrap();
+    }
+
+    let handle = SchemaHandle::of::<Event>();
+    let event: Box<dyn erased_serde::Serialize> = Box::new(Event { kind: 4 });
+    stream
+        .serialize_with_schema(&handle, event.as_ref())
+        .unwrap();
+    let bytes = stream.into_writer();
+
+    let mut stream = StreamDeserializer::new(&bytes[..]);
+    let mut values = Vec::new();
+    while let Some(value) = stream.deserialize::<Value>().unwrap() {
+        values.push(value);
+    }
+    assert_eq!(values.len(), 4);
+    assert_eq!(
+        values[0],
+        Value::Map(vec![
+            (Value::String("Name".into()), Value::String("load".into())),
+            (Value::String("Value".into()), Value::Float(0.5)),
+        ])
+    );
+    assert_eq!(values[2], Value::Seq(vec![Value::Int(1), Value::Int(2)]));
+    assert_eq!(
+        values[3],
+        Value::Map(vec![(Value::String("Kind".into()), Value::Uint(4))])
+    );
+}
@@ -0,0 +1,65 @@
+extern crate gob;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_schema;
+#[macro_use]
+extern crate serde_schema_derive;
+
+use gob::export::write_csv;
+use gob::{StreamDeserializer, StreamSerializer};
+
+#[derive(Serialize, SchemaSerialize)]
+struct Row {
+    name: &'static str,
+    count: i64,
+    ratio: f64,
+    ok: bool,
+}
+
+#[derive(Serialize, SchemaSerialize)]
+struct Nested {
+    rows: Vec<Row>,
+}
+
+#[test]
+fn csv() {
+    let mut stream = StreamSerializer::new(Vec::new());
+    stream
+        .serialize(&Row {
+            name: "plain",
+            count: -3,
+            ratio: 0.5,
+            ok: true,
+        })
+        .unwrap();
+    stream
+        .serialize(&Row {
+            name: "with \"quotes\", commas",
+            count: 0,
+            ratio: 0.0,
+            ok: false,
+        })
+        .unwrap();
+    let buffer = stream.into_writer();
+
+    let mut out = Vec::new();
+    let rows = write_csv(StreamDeserializer::new(&buffer[..]), &mut out).unwrap();
+    assert_eq!(rows, 2);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "name,count,ratio,ok\n\
+         plain,-3,0.5,true\n\
+         \"with \"\"quotes\"\", commas\",0,0,false\n"
+    );
+}
+
+#[test]
+fn csv_rejects_nested_fields() {
+    let mut stream = StreamSerializer::new(Vec::new());
+    stream.serialize(&Nested { rows: vec![] }).unwrap();
+    let buffer = stream.into_writer();
+
+    let mut out = Vec::new();
+    assert!(write_csv(StreamDeserializer::new(&buffer[..]), &mut out).is_err());
+    assert!(out.is_empty());
+}
@@ -0,0 +1,109 @@
+#![cfg(feature = "ffi")]
+
+extern crate gob;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_schema;
+#[macro_use]
+extern crate serde_schema_derive;
+
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int, c_void};
+use std::{ptr, slice};
+
+use gob::ffi::*;
+use gob::StreamSerializer;
+
+#[derive(Serialize, SchemaSerialize)]
+struct Point {
+    x: i64,
+    tags: Vec<String>,
+}
+
+fn encode() -> Vec<u8> {
+    let mut stream = StreamSerializer::new_with_write(Vec::new());
+    stream
+        .serialize(&Point {
+            x: -2,
+            tags: vec!["a".to_owned()],
+        })
+        .unwrap();
+    stream.serialize(&7u64).unwrap();
+    stream.into_writer()
+}
+
+#[test]
+fn next_json() {
+    let bytes = encode();
+    unsafe {
+        let dec = gob_decoder_new();
+        let mut json: *const c_char = ptr::null();
+        let mut len = 0;
+
+        gob_decoder_feed(dec, bytes.as_ptr(), bytes.len() - 1);
+        assert_eq!(gob_decoder_next_json(dec, &mut json, &mut len), GOB_VALUE);
+        let value = slice::from_raw_parts(json as *const u8, len);
+        assert_eq!(value, br#"{"x":-2,"tags":["a"]}"#);
+        assert_eq!(
+            gob_decoder_next_json(dec, &mut json, &mut len),
+            GOB_NEED_INPUT
+        );
+
+        gob_decoder_feed(dec, bytes[bytes.len() - 1..].as_ptr(), 1);
+        assert_eq!(gob_decoder_next_json(dec, &mut json, &mut len), GOB_VALUE);
+        assert_eq!(slice::from_raw_parts(json as *const u8, len), b"7");
+        assert!(gob_decoder_last_error(dec).is_null());
+
+        gob_decoder_feed(dec, [3, 0xff, 0xc8, 0].as_ptr(), 4);
+        assert_eq!(gob_decoder_next_json(dec, &mut json, &mut len), GOB_ERROR);
+        let msg = CStr::from_ptr(gob_decoder_last_error(dec));
+        assert!(msg.to_str().unwrap().contains("100"));
+
+        gob_decoder_free(dec);
+    }
+}
+
+extern "C" fn record(ctx: *mut c_void, event: *const GobEvent) -> c_int {
+    let (log, event) = unsafe { (&mut *(ctx as *mut Vec<String>), &*event) };
+    let entry = match event.kind {
+        GobEventKind::Int => format!("int {}", event.int),
+        GobEventKind::Uint => format!("uint {}", event.uint),
+        GobEventKind::String => {
+            let bytes = unsafe { slice::from_raw_parts(event.data, event.len) };
+            format!("string {}", String::from_utf8_lossy(bytes))
+        }
+        kind => format!("{:?} {}", kind, event.len),
+    };
+    log.push(entry);
+    0
+}
+
+#[test]
+fn next_visit() {
+    let bytes = encode();
+    let mut log = Vec::<String>::new();
+    unsafe {
+        let dec = gob_decoder_new();
+        gob_decoder_feed(dec, bytes.as_ptr(), bytes.len());
+        let ctx = &mut log as *mut Vec<String> as *mut c_void;
+        assert_eq!(gob_decoder_next_visit(dec, record, ctx), GOB_VALUE);
+        assert_eq!(gob_decoder_next_visit(dec, record, ctx), GOB_VALUE);
+        assert_eq!(gob_decoder_next_visit(dec, record, ctx), GOB_NEED_INPUT);
+        gob_decoder_free(dec);
+    }
+    assert_eq!(
+        log,
+        [
+            "StartMap 0",
+            "string x",
+            "int -2",
+            "string tags",
+            "StartSeq 1",
+            "string a",
+            "End 0",
+            "End 0",
+            "uint 7",
+        ]
+    );
+}
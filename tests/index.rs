@@ -0,0 +1,88 @@
+extern crate gob;
+
+use std::collections::HashMap;
+use std::io::{Cursor, Seek, SeekFrom};
+
+use gob::de::TypeDefs;
+use gob::index::Index;
+use gob::Value;
+
+#[test]
+fn random_access() {
+    let buffer = include_bytes!("reference/output/non_empty_values.gob");
+    let mut index = Index::build(Cursor::new(buffer.as_ref())).unwrap();
+
+    assert_eq!(index.len(), 7);
+    assert_eq!(index.get::<String>(4).unwrap(), "foo");
+    assert_eq!(index.get::<u64>(1).unwrap(), 42);
+    assert_eq!(index.get::<&str>(4).unwrap(), "foo");
+    assert_eq!(index.get::<Vec<bool>>(6).unwrap(), &[true, false]);
+    assert!(index.get::<bool>(0).unwrap());
+    assert!(index.get::<bool>(7).is_err());
+}
+
+#[test]
+fn range_queries() {
+    let buffer = include_bytes!("reference/output/slice_of_bool_non_empty_twice.gob");
+    let mut index = Index::build(Cursor::new(buffer.as_ref())).unwrap();
+
+    let values = index.range::<Vec<bool>>(0..2).unwrap();
+    assert_eq!(values, vec![vec![true, false], vec![false, true]]);
+    assert!(index.range::<Value>(1..1).unwrap().is_empty());
+    assert!(index.range::<Value>(1..3).is_err());
+}
+
+#[test]
+fn starts_at_current_position() {
+    let buffer = include_bytes!("reference/output/point_struct.gob");
+    let mut input = b"header".to_vec();
+    input.extend_from_slice(buffer);
+
+    let mut cursor = Cursor::new(input);
+    cursor.seek(SeekFrom::Start(6)).unwrap();
+    let mut index = Index::build(cursor).unwrap();
+
+    assert_eq!(index.len(), 1);
+    let type_id = index.value_type(0).unwrap();
+    assert_eq!(index.type_name(type_id), Some("Point"));
+    let point = index.get::<HashMap<String, i64>>(0).unwrap();
+    assert_eq!(point["X"], 22);
+    assert_eq!(point["Y"], 33);
+}
+
+#[test]
+fn concatenated_streams() {
+    // both streams define their struct as type 65
+    let mut input = include_bytes!("reference/output/point_struct.gob").to_vec();
+    input.extend_from_slice(include_bytes!("reference/output/bool_struct.gob"));
+    let mut index = Index::build(Cursor::new(input)).unwrap();
+
+    assert_eq!(index.len(), 2);
+    assert_eq!(index.value_type(0), index.value_type(1));
+    let point = index.get::<HashMap<String, i64>>(0).unwrap();
+    assert_eq!(point["X"], 22);
+    assert_eq!(point["Y"], 33);
+    let bools = index.get::<HashMap<String, bool>>(1).unwrap();
+    assert!(bools["V"]);
+
+    let values = index.range::<Value>(0..2).unwrap();
+    assert_eq!(values[0], index.get::<Value>(0).unwrap());
+    assert_eq!(values[1], index.get::<Value>(1).unwrap());
+}
+
+#[test]
+fn schema_registry() {
+    // the definition of the struct is kept apart from its values
+    let buffer = include_bytes!("reference/output/point_struct.gob");
+    let (def, value) = buffer.split_at(buffer[0] as usize + 1);
+    let input = [value, value].concat();
+    let mut index = Index::build(Cursor::new(input)).unwrap();
+    assert!(index.get::<Value>(0).is_err());
+
+    index
+        .set_schema_registry(TypeDefs::from_bytes(def).unwrap())
+        .unwrap();
+    let point = index.get::<HashMap<String, i64>>(1).unwrap();
+    assert_eq!(point["X"], 22);
+    assert_eq!(index.range::<Value>(0..2).unwrap().len(), 2);
+}
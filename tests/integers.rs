@@ -0,0 +1,232 @@
+extern crate gob;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_schema;
+#[macro_use]
+extern crate serde_schema_derive;
+
+use std::num::{NonZeroU16, NonZeroU32, Wrapping};
+
+use gob::de::{DecodeOptions, NumericCoercion};
+use gob::ser::TypeId;
+use gob::{Deserializer, Mode, StreamSerializer};
+use serde::Deserialize;
+
+fn encode_uints(values: &[u64]) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    StreamSerializer::new_with_write(&mut buffer)
+        .serialize(&values.to_vec())
+        .unwrap();
+    buffer
+}
+
+#[test]
+fn u128_in_range() {
+    let mut buffer = Vec::new();
+    {
+        let mut stream = StreamSerializer::new_with_write(&mut buffer);
+        stream
+            .serialize_with_type_id(TypeId::UINT, &300u128)
+            .unwrap();
+        stream.serialize_with_type_id(TypeId::INT, &-2i128).unwrap();
+    }
+    assert_eq!(buffer, &[5, 6, 0, 0xfe, 0x01, 0x2c, 3, 4, 0, 3]);
+
+    let decoded = u128::deserialize(Deserializer::from_slice(&buffer[..6])).unwrap();
+    assert_eq!(decoded, 300);
+    let decoded = i128::deserialize(Deserializer::from_slice(&buffer[6..])).unwrap();
+    assert_eq!(decoded, -2);
+}
+
+#[test]
+fn u128_out_of_range() {
+    let mut stream = StreamSerializer::new_with_write(Vec::new());
+    assert!(stream
+        .serialize_with_type_id(TypeId::UINT, &(u64::MAX as u128 + 1))
+        .is_err());
+    assert!(stream
+        .serialize_with_type_id(TypeId::INT, &(i64::MIN as i128 - 1))
+        .is_err());
+    assert!(stream.into_writer().is_empty());
+}
+
+#[test]
+fn elements_out_of_range() {
+    let buffer = encode_uints(&[7, 300]);
+    let decoded = Vec::<u16>::deserialize(Deserializer::from_slice(&buffer)).unwrap();
+    assert_eq!(decoded, &[7, 300]);
+    assert!(Vec::<u8>::deserialize(Deserializer::from_slice(&buffer)).is_err());
+    let decoded = Vec::<u128>::deserialize(Deserializer::from_slice(&buffer)).unwrap();
+    assert_eq!(decoded, &[7, 300]);
+}
+
+#[test]
+fn non_zero() {
+    let mut buffer = Vec::new();
+    StreamSerializer::new_with_write(&mut buffer)
+        .serialize_with_type_id(TypeId::UINT, &NonZeroU32::new(7).unwrap())
+        .unwrap();
+    assert_eq!(buffer, &[3, 6, 0, 7]);
+    let decoded = NonZeroU32::deserialize(Deserializer::from_slice(&buffer)).unwrap();
+    assert_eq!(decoded.get(), 7);
+
+    let buffer = encode_uints(&[1, 0]);
+    assert!(Vec::<NonZeroU16>::deserialize(Deserializer::from_slice(&buffer)).is_err());
+    let buffer = encode_uints(&[1, 70_000]);
+    assert!(Vec::<NonZeroU16>::deserialize(Deserializer::from_slice(&buffer)).is_err());
+}
+
+#[test]
+fn wrapping() {
+    let mut buffer = Vec::new();
+    StreamSerializer::new_with_write(&mut buffer)
+        .serialize_with_type_id(TypeId::UINT, &Wrapping(7u8))
+        .unwrap();
+    assert_eq!(buffer, &[3, 6, 0, 7]);
+
+    let buffer = encode_uints(&[7, 255]);
+    let decoded = Vec::<Wrapping<u8>>::deserialize(Deserializer::from_slice(&buffer)).unwrap();
+    assert_eq!(decoded, &[Wrapping(7), Wrapping(255)]);
+    let buffer = encode_uints(&[256]);
+    assert!(Vec::<Wrapping<u8>>::deserialize(Deserializer::from_slice(&buffer)).is_err());
+}
+
+#[derive(Serialize, SchemaSerialize)]
+struct Sample {
+    #[serde(rename = "Count")]
+    count: i64,
+    #[serde(rename = "Values")]
+    values: Vec<i64>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct SmallSample {
+    #[serde(rename = "Count")]
+    count: u8,
+    #[serde(rename = "Values")]
+    values: Vec<i8>,
+}
+
+fn sample(count: i64, values: &[i64]) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    StreamSerializer::new_with_write(&mut buffer)
+        .serialize(&Sample {
+            count,
+            values: values.to_vec(),
+        })
+        .unwrap();
+    buffer
+}
+
+fn decode_sample(buffer: &[u8], coercion: NumericCoercion) -> Result<SmallSample, gob::Error> {
+    let options = DecodeOptions::new().numeric_coercion(coercion);
+    SmallSample::deserialize(Deserializer::from_slice(buffer).with_options(options))
+}
+
+#[test]
+fn strict_coercion() {
+    // signed integers don't go into unsigned ones, even if they fit
+    let buffer = sample(7, &[1, -1]);
+    let err = decode_sample(&buffer, NumericCoercion::Strict).unwrap_err();
+    assert_eq!(err.path(), Some("Count"));
+    assert_eq!(err.to_string(), "deserialize error: Count: expected u8");
+
+    let mut buffer = Vec::new();
+    StreamSerializer::new_with_write(&mut buffer)
+        .serialize(&vec![1i64, 200])
+        .unwrap();
+    let err = Vec::<i8>::deserialize(Deserializer::from_slice(&buffer)).unwrap_err();
+    assert_eq!(err.path(), Some("1"));
+    assert_eq!(
+        err.to_string(),
+        "deserialize error: 1: integer overflow: 200 does not fit into i8"
+    );
+}
+
+#[test]
+fn saturating_coercion() {
+    let buffer = sample(-7, &[1, -200, 200]);
+    let decoded = decode_sample(&buffer, NumericCoercion::Saturating).unwrap();
+    assert_eq!(
+        decoded,
+        SmallSample {
+            count: 0,
+            values: vec![1, -128, 127],
+        }
+    );
+
+    let options = DecodeOptions::from(Mode::Lenient);
+    let decoded = SmallSample::deserialize(Deserializer::from_slice(&buffer).with_options(options));
+    assert_eq!(decoded.unwrap().values, &[1, -128, 127]);
+}
+
+#[test]
+fn lossy_coercion() {
+    let buffer = sample(300, &[1, -200, 200]);
+    let decoded = decode_sample(&buffer, NumericCoercion::Lossy).unwrap();
+    assert_eq!(
+        decoded,
+        SmallSample {
+            count: 44,
+            values: vec![1, 56, -56],
+        }
+    );
+}
+
+#[test]
+fn coercion_between_integers_and_floats() {
+    let mut buffer = Vec::new();
+    StreamSerializer::new_with_write(&mut buffer)
+        .serialize(&2.9f64)
+        .unwrap();
+    let options = DecodeOptions::new().numeric_coercion(NumericCoercion::Saturating);
+    assert_eq!(
+        u8::deserialize(Deserializer::from_slice(&buffer).with_options(options)).unwrap(),
+        2
+    );
+    assert!(u8::deserialize(Deserializer::from_slice(&buffer)).is_err());
+    assert_eq!(
+        f32::deserialize(Deserializer::from_slice(&buffer)).unwrap(),
+        2.9
+    );
+}
+
+#[test]
+fn f32_precision() {
+    let encode = |v: f64| {
+        let mut buffer = Vec::new();
+        StreamSerializer::new_with_write(&mut buffer)
+            .serialize(&v)
+            .unwrap();
+        buffer
+    };
+    let exact = DecodeOptions::new().exact_f32(true);
+
+    let mut buffer = Vec::new();
+    StreamSerializer::new_with_write(&mut buffer)
+        .serialize(&0.1f32)
+        .unwrap();
+    assert_eq!(buffer, encode(f64::from(0.1f32)));
+    let decoded = f32::deserialize(Deserializer::from_slice(&buffer).with_options(exact));
+    assert_eq!(decoded.unwrap(), 0.1);
+
+    let buffer = encode(0.1);
+    assert_eq!(
+        f32::deserialize(Deserializer::from_slice(&buffer)).unwrap(),
+        0.1
+    );
+    assert!(f32::deserialize(Deserializer::from_slice(&buffer).with_options(exact)).is_err());
+
+    let buffer = encode(1e300);
+    assert!(f32::deserialize(Deserializer::from_slice(&buffer)).is_err());
+    let options = DecodeOptions::new().numeric_coercion(NumericCoercion::Saturating);
+    assert_eq!(
+        f32::deserialize(Deserializer::from_slice(&buffer).with_options(options)).unwrap(),
+        f32::MAX
+    );
+
+    let buffer = encode(f64::INFINITY);
+    let decoded = f32::deserialize(Deserializer::from_slice(&buffer).with_options(exact));
+    assert_eq!(decoded.unwrap(), f32::INFINITY);
+}
@@ -0,0 +1,120 @@
+#![cfg(feature = "json")]
+
+extern crate gob;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+#[macro_use]
+extern crate serde_json;
+
+use std::collections::BTreeMap;
+
+use gob::value::{FromJson, NumberKind, ObjectKind};
+use gob::{StreamDeserializer, StreamSerializer, Value};
+use serde_json::{Map, Number};
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[allow(non_snake_case)]
+struct Server {
+    Host: String,
+    Ports: Vec<i64>,
+    Weight: f64,
+    #[serde(default)]
+    Tags: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[allow(non_snake_case)]
+struct Config {
+    Name: String,
+    Servers: Vec<Server>,
+    Limits: BTreeMap<String, f64>,
+    Retries: u64,
+}
+
+fn kinds(path: &str, _: &Number) -> Option<NumberKind> {
+    match path {
+        "Retries" => Some(NumberKind::Uint),
+        "Servers[].Weight" => Some(NumberKind::Float),
+        _ => None,
+    }
+}
+
+fn maps(path: &str, _: &Map<String, serde_json::Value>) -> Option<ObjectKind> {
+    if path == "Limits" {
+        Some(ObjectKind::Map)
+    } else {
+        None
+    }
+}
+
+#[test]
+fn json_into_struct() {
+    let json = json!({
+        "Name": "edge",
+        "Servers": [
+            {"Host": "a", "Ports": [80, 443], "Weight": 1, "Tags": []},
+            {"Host": "b", "Ports": [8080], "Weight": 0.5, "Tags": null},
+        ],
+        "Limits": {"cpu": 2, "mem": 0.75},
+        "Retries": 3,
+    });
+    let value = FromJson::new()
+        .number_kind_with(kinds)
+        .object_kind_with(maps)
+        .convert(&json)
+        .unwrap();
+
+    let mut stream = StreamSerializer::new(Vec::new());
+    stream.serialize_value(&value).unwrap();
+    let bytes = stream.into_writer();
+
+    let mut stream = StreamDeserializer::new(&bytes[..]);
+    let config = stream.deserialize::<Config>().unwrap().unwrap();
+    assert_eq!(config.Name, "edge");
+    assert_eq!(config.Retries, 3);
+    assert_eq!(config.Limits["cpu"], 2.0);
+    assert_eq!(
+        config.Servers[0],
+        Server {
+            Host: "a".into(),
+            Ports: vec![80, 443],
+            Weight: 1.0,
+            Tags: vec![],
+        }
+    );
+    assert_eq!(config.Servers[1].Weight, 0.5);
+}
+
+#[test]
+fn json_number_deduction() {
+    let value = FromJson::new().convert(&json!([1, -2, 3.5])).unwrap();
+    assert_eq!(
+        value,
+        Value::Seq(vec![
+            Value::Float(1.0),
+            Value::Float(-2.0),
+            Value::Float(3.5)
+        ])
+    );
+    let value = FromJson::new().convert(&json!([u64::MAX])).unwrap();
+    assert_eq!(value, Value::Seq(vec![Value::Uint(u64::MAX)]));
+
+    let ints = |_: &str, _: &Number| Some(NumberKind::Int);
+    assert!(FromJson::new()
+        .number_kind_with(ints)
+        .convert(&json!(1.5))
+        .is_err());
+}
+
+#[test]
+fn serialize_json() {
+    let mut stream = StreamSerializer::new(Vec::new());
+    stream
+        .serialize_json(&json!({"X": 1, "Y": 2, "Tags": ["a"]}))
+        .unwrap();
+    let bytes = stream.into_writer();
+    let mut stream = StreamDeserializer::new(&bytes[..]);
+    let value = stream.deserialize::<serde_json::Value>().unwrap().unwrap();
+    assert_eq!(value, json!({"X": 1, "Y": 2, "Tags": ["a"]}));
+}
@@ -0,0 +1,60 @@
+extern crate gob;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_schema;
+#[macro_use]
+extern crate serde_schema_derive;
+
+use gob::de::TypeDefs;
+use gob::message::{
+    decode_message, decode_message_with_type_defs, encode_message, encode_message_without_types,
+};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, SchemaSerialize)]
+struct Event {
+    topic: String,
+    seq: u64,
+}
+
+fn event(seq: u64) -> Event {
+    Event {
+        topic: "orders".to_owned(),
+        seq,
+    }
+}
+
+#[test]
+fn standalone_messages() {
+    let first = encode_message(&event(1)).unwrap();
+    let second = encode_message(&event(2)).unwrap();
+    // each message carries the same definitions
+    assert_eq!(first.len(), second.len());
+    assert_eq!(decode_message::<Event>(&second).unwrap(), event(2));
+    assert_eq!(decode_message::<Event>(&first).unwrap(), event(1));
+}
+
+#[test]
+fn messages_without_types() {
+    let bare = encode_message_without_types(&event(3)).unwrap();
+    assert!(bare.len() < encode_message(&event(3)).unwrap().len());
+    assert!(decode_message::<Event>(&bare).is_err());
+
+    // definitions learned from a full message
+    let full = encode_message(&event(1)).unwrap();
+    let mut stream = gob::Deserializer::from_slice_many(&full);
+    stream.next().unwrap().unwrap();
+    let defs: TypeDefs = stream.type_defs();
+    assert_eq!(
+        decode_message_with_type_defs::<Event>(&bare, &defs).unwrap(),
+        event(3)
+    );
+}
+
+#[test]
+fn rejects_extra_values() {
+    let mut bytes = encode_message(&event(1)).unwrap();
+    bytes.extend(encode_message_without_types(&event(2)).unwrap());
+    assert!(decode_message::<Event>(&bytes).is_err());
+    assert!(decode_message::<Event>(&[]).is_err());
+}
@@ -0,0 +1,103 @@
+extern crate gob;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_schema;
+#[macro_use]
+extern crate serde_schema_derive;
+
+use gob::de::{DecodeOptions, OptionEncoding};
+use gob::{Deserializer, StreamSerializer};
+use serde::Deserialize;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, SchemaSerialize)]
+struct Pointers {
+    #[serde(rename = "A")]
+    a: Option<i64>,
+    #[serde(rename = "B")]
+    b: Option<String>,
+    #[serde(rename = "C")]
+    c: Option<i64>,
+}
+
+fn encode(value: &Pointers, options: OptionEncoding) -> Vec<u8> {
+    let mut stream = StreamSerializer::new_with_write(Vec::new());
+    stream.set_option_encoding(options);
+    stream.serialize(value).unwrap();
+    stream.into_writer()
+}
+
+fn decode(buffer: &[u8], options: OptionEncoding) -> Pointers {
+    let options = DecodeOptions::new().option_encoding(options);
+    Pointers::deserialize(Deserializer::from_slice(buffer).with_options(options)).unwrap()
+}
+
+#[test]
+fn pointer_encoding() {
+    let value = Pointers {
+        a: Some(7),
+        b: None,
+        c: Some(0),
+    };
+    let buffer = encode(&value, OptionEncoding::Pointer);
+    // zero values are omitted like nil pointers, losing `Some(0)`
+    let decoded = decode(&buffer, OptionEncoding::Pointer);
+    assert_eq!(
+        decoded,
+        Pointers {
+            a: Some(7),
+            b: None,
+            c: None,
+        }
+    );
+}
+
+#[test]
+fn slice_encoding() {
+    let value = Pointers {
+        a: Some(7),
+        b: None,
+        c: Some(0),
+    };
+    let buffer = encode(&value, OptionEncoding::Slice);
+    assert_eq!(decode(&buffer, OptionEncoding::Slice), value);
+}
+
+#[test]
+fn slice_encoding_accepts_pointers() {
+    let value = Pointers {
+        a: Some(7),
+        b: Some("foo".to_owned()),
+        c: None,
+    };
+    let buffer = encode(&value, OptionEncoding::Pointer);
+    assert_eq!(decode(&buffer, OptionEncoding::Slice), value);
+}
+
+#[test]
+fn top_level_options() {
+    for &options in &[OptionEncoding::Pointer, OptionEncoding::Slice] {
+        let mut stream = StreamSerializer::new_with_write(Vec::new());
+        stream.set_option_encoding(options);
+        stream.serialize(&Some(0u64)).unwrap();
+        let buffer = stream.into_writer();
+        let decoded = Option::<u64>::deserialize(
+            Deserializer::from_slice(&buffer)
+                .with_options(DecodeOptions::new().option_encoding(options)),
+        )
+        .unwrap();
+        assert_eq!(decoded, Some(0));
+    }
+}
+
+#[test]
+fn slice_of_two_is_not_an_option() {
+    let mut stream = StreamSerializer::new_with_write(Vec::new());
+    stream.serialize(&vec![1u64, 2]).unwrap();
+    let buffer = stream.into_writer();
+    let options = DecodeOptions::new().option_encoding(OptionEncoding::Slice);
+    assert!(
+        Option::<u64>::deserialize(Deserializer::from_slice(&buffer).with_options(options))
+            .is_err()
+    );
+}
@@ -0,0 +1,118 @@
+extern crate gob;
+
+use gob::de::Structure;
+use gob::reader::{Event, EventReader};
+use gob::StreamSerializer;
+
+#[test]
+fn structs_and_interfaces() {
+    let buffer = include_bytes!("reference/output/interface_value.gob");
+    let mut reader = EventReader::new(&buffer[..]);
+
+    match reader.next_event().unwrap() {
+        Some(Event::TypeDef(info)) => {
+            assert_eq!(info.name(), "Point");
+            assert!(match *info.structure() {
+                Structure::Struct { ref fields } => fields.len() == 2,
+                _ => false,
+            });
+        }
+        event => panic!("expected a type definition, got {:?}", event),
+    }
+    let point = reader.type_defs().type_id("Point").unwrap();
+
+    let mut events = Vec::new();
+    while let Some(event) = reader.next_event().unwrap() {
+        match event {
+            Event::TypeDef(info) => assert_eq!(info.name(), "Wrapper"),
+            event => events.push(format!("{:?}", event)),
+        }
+    }
+    let wrapper = reader.type_defs().type_id("Wrapper").unwrap();
+    assert_eq!(
+        events,
+        [
+            format!("Value({:?})", point),
+            format!("StartStruct({:?})", point),
+            "Field(\"X\")".to_owned(),
+            "Int(1)".to_owned(),
+            "Field(\"Y\")".to_owned(),
+            "Int(2)".to_owned(),
+            "End".to_owned(),
+            format!("Value({:?})", wrapper),
+            format!("StartStruct({:?})", wrapper),
+            "Field(\"Value\")".to_owned(),
+            format!("StartInterface(\"main.Point\", {:?})", point),
+            format!("StartStruct({:?})", point),
+            "Field(\"X\")".to_owned(),
+            "Int(22)".to_owned(),
+            "Field(\"Y\")".to_owned(),
+            "Int(33)".to_owned(),
+            "End".to_owned(),
+            "End".to_owned(),
+            "End".to_owned(),
+        ]
+    );
+}
+
+#[test]
+fn maps() {
+    let buffer = include_bytes!("reference/output/map_non_empty.gob");
+    let mut reader = EventReader::new(&buffer[..]);
+    let mut events = Vec::new();
+    while let Some(event) = reader.next_event().unwrap() {
+        match event {
+            Event::TypeDef(_) | Event::Value(_) => {}
+            event => events.push(format!("{:?}", event)),
+        }
+    }
+    assert_eq!(
+        events,
+        [
+            "StartMap(2)",
+            "String([98, 97, 114])",
+            "Bool(false)",
+            "String([102, 111, 111])",
+            "Bool(true)",
+            "End",
+        ]
+    );
+}
+
+#[test]
+fn skipping() {
+    let mut buffer = Vec::new();
+    {
+        let mut stream = StreamSerializer::new_with_write(&mut buffer);
+        stream.serialize(&vec![vec![1u64, 2], vec![3]]).unwrap();
+        stream.serialize(&"next").unwrap();
+    }
+    let mut reader = EventReader::new(&buffer[..]);
+    loop {
+        match reader.next_event().unwrap() {
+            Some(Event::StartSeq(2)) => break,
+            Some(_) => {}
+            None => panic!("no outer slice"),
+        }
+    }
+    assert_eq!(reader.next_event().unwrap(), Some(Event::StartSeq(2)));
+    reader.skip().unwrap();
+    assert_eq!(reader.next_event().unwrap(), Some(Event::StartSeq(1)));
+    reader.skip().unwrap();
+    assert_eq!(reader.next_event().unwrap(), Some(Event::End));
+
+    assert!(match reader.next_event().unwrap() {
+        Some(Event::Value(_)) => true,
+        _ => false,
+    });
+    reader.skip().unwrap();
+    assert_eq!(reader.next_event().unwrap(), None);
+}
+
+#[test]
+fn truncated_input() {
+    let buffer = include_bytes!("reference/output/point_struct.gob");
+    let mut reader = EventReader::new(&buffer[..buffer.len() - 1]);
+    assert!(reader.next_event().is_ok());
+    assert!(reader.next_event().is_err());
+}
@@ -0,0 +1,44 @@
+package main
+
+import (
+	"encoding/gob"
+	"os"
+)
+
+type Inner struct {
+	A int64
+	B string
+}
+
+type Everything struct {
+	Name  string
+	Flag  bool
+	Count uint64
+	Ratio float64
+	Data  []byte
+	Z     complex128
+	Tags  []string
+	Grid  [2]int64
+	Attrs map[string]int64
+	Inner Inner
+	Items []Inner
+	Any   interface{}
+}
+
+func main() {
+	var enc = gob.NewEncoder(os.Stdout)
+	enc.Encode(Everything{
+		Name:  "everything",
+		Flag:  true,
+		Count: 300,
+		Ratio: 1.5,
+		Data:  []byte{1, 2},
+		Z:     complex(1, -2),
+		Tags:  []string{"x", "y"},
+		Grid:  [2]int64{3, -4},
+		Attrs: map[string]int64{"k": 5},
+		Inner: Inner{A: 1, B: "one"},
+		Items: []Inner{{A: 2, B: "two"}, {A: 3, B: "three"}},
+		Any:   "hi",
+	})
+}
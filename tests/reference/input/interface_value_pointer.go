@@ -0,0 +1,22 @@
+package main
+
+import (
+	"encoding/gob"
+	"os"
+)
+
+type Point struct {
+	X int64
+	Y int64
+}
+
+type Wrapper struct {
+	Value interface{}
+}
+
+func main() {
+	gob.Register(&Point{})
+	var enc = gob.NewEncoder(os.Stdout)
+	enc.Encode(Point{X: 1, Y: 2})
+	enc.Encode(Wrapper{Value: &Point{X: 22, Y: 33}})
+}
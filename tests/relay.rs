@@ -0,0 +1,211 @@
+extern crate gob;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_schema;
+#[macro_use]
+extern crate serde_schema_derive;
+
+use gob::relay::{FieldFilter, Relay};
+use gob::{StreamDeserializer, StreamSerializer, Value};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, SchemaSerialize)]
+struct Point {
+    #[serde(rename = "X")]
+    x: i64,
+    #[serde(rename = "Y")]
+    y: i64,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, SchemaSerialize)]
+struct Label {
+    #[serde(rename = "Text")]
+    text: String,
+    #[serde(rename = "At")]
+    at: Point,
+}
+
+fn encode<F: FnOnce(&mut StreamSerializer<gob::ser::OutputWrite<&mut Vec<u8>>>)>(f: F) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    f(&mut StreamSerializer::new(&mut buffer));
+    buffer
+}
+
+#[test]
+fn merges_upstreams() {
+    // the upstreams number their types differently
+    let first = encode(|stream| {
+        stream.serialize(&Point { x: 1, y: 2 }).unwrap();
+        stream.serialize(&vec![3u64, 4]).unwrap();
+    });
+    let second = encode(|stream| {
+        stream
+            .serialize(&Label {
+                text: "a".to_owned(),
+                at: Point { x: 5, y: 6 },
+            })
+            .unwrap();
+        stream.serialize(&Point { x: 7, y: 8 }).unwrap();
+    });
+
+    let mut downstream = StreamSerializer::new(Vec::new());
+    let mut relays = vec![
+        Relay::new(StreamDeserializer::new(&first[..])),
+        Relay::new(StreamDeserializer::new(&second[..])),
+    ];
+    for _ in 0..2 {
+        for relay in &mut relays {
+            assert!(relay.forward(&mut downstream).unwrap());
+        }
+    }
+    for relay in &mut relays {
+        assert!(!relay.forward(&mut downstream).unwrap());
+    }
+    // values of the downstream's own types mix in
+    downstream.serialize(&Point { x: 9, y: 10 }).unwrap();
+    let output = downstream.into_writer();
+
+    let mut stream = StreamDeserializer::new(&output[..]);
+    assert_eq!(
+        stream.deserialize::<Point>().unwrap(),
+        Some(Point { x: 1, y: 2 })
+    );
+    assert_eq!(
+        stream.deserialize::<Label>().unwrap(),
+        Some(Label {
+            text: "a".to_owned(),
+            at: Point { x: 5, y: 6 },
+        })
+    );
+    assert_eq!(stream.deserialize::<Vec<u64>>().unwrap(), Some(vec![3, 4]));
+    assert_eq!(
+        stream.deserialize::<Point>().unwrap(),
+        Some(Point { x: 7, y: 8 })
+    );
+    assert_eq!(
+        stream.deserialize::<Point>().unwrap(),
+        Some(Point { x: 9, y: 10 })
+    );
+    assert_eq!(stream.deserialize::<Point>().unwrap(), None);
+
+    // `Point` was only defined once
+    let points = stream.type_defs().type_id("Point").unwrap();
+    assert_eq!(
+        relays[0].downstream_id(relays[0].get_ref().type_defs().type_id("Point").unwrap()),
+        Some(points)
+    );
+    assert_eq!(
+        relays[1].downstream_id(relays[1].get_ref().type_defs().type_id("Point").unwrap()),
+        Some(points)
+    );
+}
+
+#[test]
+fn translates_interface_values() {
+    let buffer = include_bytes!("reference/output/interface_value.gob");
+
+    // shifts the ids of the relayed types
+    let mut downstream = StreamSerializer::new(Vec::new());
+    downstream
+        .serialize(&Label {
+            text: "b".to_owned(),
+            at: Point { x: 3, y: 4 },
+        })
+        .unwrap();
+    let mut relay = Relay::new(StreamDeserializer::new(&buffer[..]));
+    assert_eq!(relay.forward_all(&mut downstream).unwrap(), 2);
+    let output = downstream.into_writer();
+
+    let mut original = StreamDeserializer::new(&buffer[..]);
+    let mut relayed = StreamDeserializer::new(&output[..]);
+    relayed.deserialize::<Label>().unwrap().unwrap();
+    for _ in 0..2 {
+        let expected = original.deserialize::<Value>().unwrap();
+        assert_eq!(relayed.deserialize::<Value>().unwrap(), expected);
+    }
+    assert_ne!(
+        relay.downstream_id(original.type_defs().type_id("Wrapper").unwrap()),
+        original.type_defs().type_id("Wrapper")
+    );
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, SchemaSerialize)]
+struct User {
+    #[serde(rename = "Name")]
+    name: String,
+    #[serde(rename = "Password")]
+    password: String,
+    #[serde(rename = "Mail")]
+    mail: String,
+    #[serde(rename = "Token")]
+    token: u64,
+    #[serde(rename = "Home")]
+    home: Point,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ArchivedUser {
+    #[serde(rename = "Name")]
+    name: String,
+    #[serde(rename = "Email")]
+    email: String,
+    #[serde(rename = "Token", default)]
+    token: u64,
+    #[serde(rename = "Home")]
+    home: Point,
+}
+
+#[test]
+fn filters_fields() {
+    let users = encode(|stream| {
+        for name in &["a", "b"] {
+            stream
+                .serialize(&User {
+                    name: name.to_string(),
+                    password: "hunter2".to_owned(),
+                    mail: format!("{}@example.com", name),
+                    token: 42,
+                    home: Point { x: 1, y: 2 },
+                })
+                .unwrap();
+        }
+        stream.serialize(&vec![Point { x: 3, y: 4 }]).unwrap();
+    });
+
+    let filter = FieldFilter::new()
+        .drop("User", "Password")
+        .rename("User", "Mail", "Email")
+        .redact("*", "Token")
+        .drop("Point", "Z");
+    let mut relay = Relay::with_filter(StreamDeserializer::new(&users[..]), filter);
+    let mut downstream = StreamSerializer::new(Vec::new());
+    assert_eq!(relay.forward_all(&mut downstream).unwrap(), 3);
+    let output = downstream.into_writer();
+    assert!(!output.windows(7).any(|window| window == b"hunter2"));
+
+    let mut stream = StreamDeserializer::new(&output[..]);
+    for name in &["a", "b"] {
+        let info = stream.peek_type().unwrap().unwrap();
+        assert!(match *info.structure() {
+            gob::de::Structure::Struct { ref fields } => {
+                fields.iter().map(|f| &f.0[..]).collect::<Vec<_>>()
+                    == ["Name", "Email", "Token", "Home"]
+            }
+            _ => false,
+        });
+        assert_eq!(
+            stream.deserialize::<ArchivedUser>().unwrap(),
+            Some(ArchivedUser {
+                name: name.to_string(),
+                email: format!("{}@example.com", name),
+                token: 0,
+                home: Point { x: 1, y: 2 },
+            })
+        );
+    }
+    assert_eq!(
+        stream.deserialize::<Vec<Point>>().unwrap(),
+        Some(vec![Point { x: 3, y: 4 }])
+    );
+}
@@ -0,0 +1,91 @@
+extern crate gob;
+
+use std::io::Cursor;
+
+use gob::rpc::{ClientCodec, Request, Response, ServerCodec};
+
+fn request(service_method: &str, seq: u64) -> Request {
+    Request {
+        service_method: service_method.into(),
+        seq,
+    }
+}
+
+#[test]
+fn call_and_reply() {
+    let mut client = ClientCodec::new(Cursor::new(Vec::new()), Vec::new());
+    client
+        .write_request(&request("Arith.Sum", 1), &vec![1i64, 2, 3])
+        .unwrap();
+    client
+        .write_request(&request("Arith.Missing", 2), &vec![4i64])
+        .unwrap();
+    let (_, calls) = client.into_inner();
+
+    let mut server = ServerCodec::new(Cursor::new(calls), Vec::new());
+    let header = server.read_request_header().unwrap().unwrap();
+    assert_eq!(header, request("Arith.Sum", 1));
+    let args = server.read_request_body::<Vec<i64>>().unwrap();
+    let reply = Response {
+        service_method: header.service_method,
+        seq: header.seq,
+        error: String::new(),
+    };
+    server
+        .write_response(&reply, &args.iter().sum::<i64>())
+        .unwrap();
+
+    let header = server.read_request_header().unwrap().unwrap();
+    assert_eq!(header, request("Arith.Missing", 2));
+    server.skip_request_body().unwrap();
+    server
+        .write_error(&header, "rpc: can't find method")
+        .unwrap();
+
+    assert!(server.read_request_header().unwrap().is_none());
+    let (_, replies) = server.into_inner();
+
+    let mut client = ClientCodec::new(Cursor::new(replies), Vec::new());
+    let header = client.read_response_header().unwrap().unwrap();
+    assert_eq!((header.seq, header.error.as_str()), (1, ""));
+    assert_eq!(client.read_response_body::<i64>().unwrap(), 6);
+
+    let header = client.read_response_header().unwrap().unwrap();
+    assert_eq!(
+        (header.seq, header.error.as_str()),
+        (2, "rpc: can't find method")
+    );
+    client.skip_response_body().unwrap();
+    assert!(client.read_response_header().unwrap().is_none());
+}
+
+#[cfg(feature = "futures")]
+#[test]
+fn async_call_and_reply() {
+    extern crate futures;
+
+    use futures::executor::block_on;
+    use gob::rpc::{AsyncClientCodec, AsyncServerCodec};
+
+    block_on(async {
+        let mut client = AsyncClientCodec::new(futures::io::empty(), Vec::new());
+        client
+            .write_request(&request("Arith.Sum", 1), &vec![1i64, 2, 3])
+            .await
+            .unwrap();
+        let (_, calls) = client.into_inner();
+
+        let mut server = AsyncServerCodec::new(&calls[..], Vec::new());
+        let header = server.read_request_header().await.unwrap().unwrap();
+        let args = server.read_request_body::<Vec<i64>>().await.unwrap();
+        server.write_error(&header, "overflow").await.unwrap();
+        assert_eq!(args, vec![1, 2, 3]);
+        assert!(server.read_request_header().await.unwrap().is_none());
+        let (_, replies) = server.into_inner();
+
+        let mut client = AsyncClientCodec::new(&replies[..], Vec::new());
+        let header = client.read_response_header().await.unwrap().unwrap();
+        assert_eq!(header.error, "overflow");
+        client.skip_response_body().await.unwrap();
+    });
+}
@@ -0,0 +1,195 @@
+extern crate gob;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_schema;
+#[macro_use]
+extern crate serde_schema_derive;
+
+use gob::schema::compat::{self, IssueKind};
+use gob::schema::Schema;
+use serde_schema::SchemaSerialize;
+
+fn schema_of<T: SchemaSerialize>() -> Schema {
+    let mut schema = Schema::new();
+    T::schema_register(&mut schema).unwrap();
+    schema
+}
+
+mod v1 {
+    #[derive(Serialize, SchemaSerialize)]
+    pub struct Order {
+        pub id: u64,
+        pub customer: String,
+        pub items: Vec<Item>,
+        pub note: String,
+    }
+
+    #[derive(Serialize, SchemaSerialize)]
+    pub struct Item {
+        pub sku: String,
+        pub count: u32,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Serialize, SchemaSerialize)]
+    pub enum Event {
+        Placed(Order),
+        Cancelled(String),
+    }
+}
+
+mod v2 {
+    #[derive(Serialize, SchemaSerialize)]
+    pub struct Order {
+        pub id: u64,
+        pub client: String,
+        pub items: Vec<Item>,
+        pub total: f64,
+    }
+
+    #[derive(Serialize, SchemaSerialize)]
+    pub struct Item {
+        pub sku: String,
+        pub count: i32,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Serialize, SchemaSerialize)]
+    pub enum Event {
+        Placed(Order),
+        Shipped(u64),
+    }
+}
+
+#[test]
+fn identical_schemas_are_compatible() {
+    let report = compat::check(&schema_of::<v1::Event>(), &schema_of::<v1::Event>());
+    assert!(report.is_compatible());
+    assert!(report.issues().is_empty());
+}
+
+#[test]
+fn added_fields_are_compatible() {
+    mod before {
+        #[derive(Serialize, SchemaSerialize)]
+        pub struct Item {
+            pub sku: String,
+        }
+    }
+
+    let report = compat::check(&schema_of::<before::Item>(), &schema_of::<v1::Item>());
+    assert!(report.is_compatible());
+    assert_eq!(report.issues().len(), 1);
+    assert_eq!(report.issues()[0].path(), "Item.count");
+    assert_eq!(*report.issues()[0].kind(), IssueKind::FieldAdded);
+}
+
+#[test]
+fn reports_breaking_changes() {
+    let report = compat::check(&schema_of::<v1::Event>(), &schema_of::<v2::Event>());
+    assert!(!report.is_compatible());
+
+    let issues = report
+        .issues()
+        .iter()
+        .map(|issue| (issue.path(), issue.kind().clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        issues,
+        vec![
+            ("Event.Cancelled", IssueKind::VariantRemoved),
+            ("Event.Shipped", IssueKind::VariantAdded),
+            (
+                "Item.count",
+                IssueKind::TypeChanged {
+                    old: "uint64".to_owned(),
+                    new: "int64".to_owned(),
+                },
+            ),
+            (
+                "Order.customer",
+                IssueKind::FieldRenamed {
+                    from: "customer".to_owned(),
+                    to: "client".to_owned(),
+                },
+            ),
+            ("Order.note", IssueKind::FieldRemoved),
+            ("Order.total", IssueKind::FieldAdded),
+        ]
+    );
+    assert!(report
+        .issues()
+        .iter()
+        .all(|issue| issue.is_breaking() || *issue.kind() == IssueKind::FieldAdded));
+}
+
+#[test]
+fn reports_structs_without_common_fields() {
+    mod before {
+        #[derive(Serialize, SchemaSerialize)]
+        pub struct Item {
+            pub name: String,
+        }
+    }
+
+    let report = compat::check(&schema_of::<before::Item>(), &schema_of::<v1::Item>());
+    assert!(report
+        .breaking()
+        .any(|issue| *issue.kind() == IssueKind::NoFieldsInCommon));
+}
+
+#[test]
+fn checked_decode() {
+    use gob::de::MismatchKind;
+    use gob::{StreamDeserializer, StreamSerializer};
+
+    #[derive(Serialize, SchemaSerialize)]
+    struct Point {
+        x: i64,
+        y: i64,
+        label: String,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize, SchemaSerialize)]
+    struct FloatPoint {
+        x: f64,
+        y: f64,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize, SchemaSerialize)]
+    struct Label {
+        label: String,
+    }
+
+    let mut stream = StreamSerializer::new(Vec::new());
+    for label in &["a", "b"] {
+        let point = Point {
+            x: 1,
+            y: 2,
+            label: label.to_string(),
+        };
+        stream.serialize(&point).unwrap();
+    }
+    let buffer = stream.into_writer();
+
+    let mut stream = StreamDeserializer::new(&buffer[..]);
+    let report = stream.check_type::<FloatPoint>().unwrap().unwrap();
+    let paths: Vec<_> = report.mismatches().iter().map(|m| m.path()).collect();
+    assert_eq!(paths, ["FloatPoint.x", "FloatPoint.y"]);
+    assert_eq!(
+        *report.mismatches()[0].kind(),
+        MismatchKind::Type {
+            wire: "int".into(),
+            target: "float64".into(),
+        }
+    );
+
+    // the value is left for another type to decode
+    assert!(stream.deserialize_checked::<FloatPoint>().is_err());
+    let decoded = stream.deserialize_checked::<Label>().unwrap();
+    assert_eq!(decoded, Some(Label { label: "a".into() }));
+    let decoded = stream.deserialize_checked::<Label>().unwrap();
+    assert_eq!(decoded, Some(Label { label: "b".into() }));
+    assert!(stream.check_type::<Label>().unwrap().is_none());
+}
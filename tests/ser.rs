@@ -1,4 +1,5 @@
 extern crate gob;
+extern crate partial_io;
 extern crate serde;
 extern crate serde_bytes;
 #[macro_use]
@@ -7,11 +8,28 @@ extern crate serde_schema;
 #[macro_use]
 extern crate serde_schema_derive;
 
-use std::collections::BTreeMap;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
 
+use gob::ser::{Encoder, Field, FieldOptions, FloatOptions, MapKey, MapOrder, NonFinite};
 use gob::StreamSerializer;
+use partial_io::{PartialOp, PartialWrite};
+use serde::{Deserialize, Serialize};
 use serde_bytes::Bytes;
 
+#[test]
+fn thread_safety() {
+    fn assert_send<T: Send>() {}
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    assert_send::<StreamSerializer<gob::ser::OutputWrite<Vec<u8>>>>();
+    assert_send::<StreamSerializer<gob::ser::OutputBuffer>>();
+    assert_send_sync::<gob::ser::Schema>();
+    assert_send_sync::<Encoder>();
+    assert_send::<gob::writer::EventWriter<Vec<u8>>>();
+}
+
 #[test]
 fn bool_true() {
     let mut buffer = Vec::new();
@@ -251,6 +269,62 @@ fn f64_neg() {
     assert_eq!(buffer, &[5, 8, 0, 254, 69, 192]);
 }
 
+#[test]
+fn f64_neg_zero() {
+    let mut stream = StreamSerializer::new(Vec::new());
+    stream.serialize(&-0f64).unwrap();
+    assert_eq!(stream.into_writer(), &[4, 8, 0, 255, 128]);
+}
+
+#[test]
+fn f64_neg_zero_normalized() {
+    let mut stream = StreamSerializer::new(Vec::new());
+    stream.set_float_options(FloatOptions::new().normalize_negative_zero(true));
+    stream.serialize(&-0f64).unwrap();
+    assert_eq!(stream.into_writer(), &[3, 8, 0, 0]);
+}
+
+#[test]
+fn f64_nan_payload() {
+    let nan = f64::from_bits(0xfff8_0000_0000_1234);
+
+    let mut stream = StreamSerializer::new(Vec::new());
+    stream.serialize(&nan).unwrap();
+    assert_eq!(
+        stream.into_writer(),
+        &[11, 8, 0, 248, 52, 18, 0, 0, 0, 0, 248, 255]
+    );
+
+    let mut stream = StreamSerializer::new(Vec::new());
+    stream.set_float_options(FloatOptions::new().canonicalize_nan(true));
+    stream.serialize(&nan).unwrap();
+    assert_eq!(
+        stream.into_writer(),
+        &[11, 8, 0, 248, 1, 0, 0, 0, 0, 0, 248, 127]
+    );
+}
+
+#[test]
+fn f64_non_finite() {
+    let encode = |v: f64, non_finite: NonFinite| {
+        let mut stream = StreamSerializer::new(Vec::new());
+        stream.set_float_options(FloatOptions::new().non_finite(non_finite));
+        stream.serialize(&v).map(|()| stream.into_writer())
+    };
+
+    assert_eq!(
+        encode(f64::INFINITY, NonFinite::Write).unwrap(),
+        &[5, 8, 0, 254, 240, 127]
+    );
+    assert!(encode(f64::NAN, NonFinite::Reject).is_err());
+    assert!(encode(f64::NEG_INFINITY, NonFinite::Reject).is_err());
+    assert_eq!(
+        encode(1.0, NonFinite::Reject).unwrap(),
+        &[5, 8, 0, 254, 240, 63]
+    );
+    assert_eq!(encode(f64::NAN, NonFinite::Zero).unwrap(), &[3, 8, 0, 0]);
+}
+
 #[test]
 fn char_ascii() {
     let mut buffer = Vec::new();
@@ -337,6 +411,118 @@ fn vec_of_bool_to_non_empty_slice() {
     );
 }
 
+#[test]
+fn streamed_seq_of_bool_to_non_empty_slice() {
+    let mut buffer = Vec::new();
+    {
+        let mut stream = StreamSerializer::new_with_write(&mut buffer);
+        let mut seq = stream
+            .serialize_seq_streaming::<bool, _>(Cursor::new(Vec::new()))
+            .unwrap();
+        seq.push(&true).unwrap();
+        seq.push(&false).unwrap();
+        assert_eq!(seq.len(), 2);
+        seq.finish().unwrap();
+    }
+    assert_eq!(
+        buffer,
+        include_bytes!("reference/output/slice_of_bool_non_empty.gob")
+    );
+}
+
+#[test]
+fn streamed_seq_of_bool_to_empty_slice() {
+    let mut buffer = Vec::new();
+    {
+        let mut stream = StreamSerializer::new_with_write(&mut buffer);
+        let seq = stream
+            .serialize_seq_streaming::<bool, _>(Cursor::new(Vec::new()))
+            .unwrap();
+        seq.finish().unwrap();
+    }
+    assert_eq!(
+        buffer,
+        include_bytes!("reference/output/slice_of_bool_empty.gob")
+    );
+}
+
+#[test]
+fn streamed_seq_of_structs() {
+    let points = (0..1000).map(|n| Point { x: n, y: -n }).collect::<Vec<_>>();
+
+    let mut expected = Vec::new();
+    StreamSerializer::new_with_write(&mut expected)
+        .serialize(&points)
+        .unwrap();
+
+    let mut buffer = Vec::new();
+    {
+        let mut stream = StreamSerializer::new_with_write(&mut buffer);
+        let mut seq = stream
+            .serialize_seq_streaming::<Point, _>(Cursor::new(Vec::new()))
+            .unwrap();
+        for point in &points {
+            seq.push(point).unwrap();
+        }
+        seq.finish().unwrap();
+    }
+    assert_eq!(buffer, expected);
+}
+
+// a spill buffer whose writes go through `PartialWrite`
+struct FlakySpill(PartialWrite<Cursor<Vec<u8>>>);
+
+impl Read for FlakySpill {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.get_mut().read(buf)
+    }
+}
+
+impl Write for FlakySpill {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl Seek for FlakySpill {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.0.get_mut().seek(pos)
+    }
+}
+
+#[test]
+fn streamed_seq_after_failed_push() {
+    let points = [Point { x: 1, y: -1 }, Point { x: 2, y: -2 }];
+
+    let mut expected = Vec::new();
+    StreamSerializer::new_with_write(&mut expected)
+        .serialize(&points)
+        .unwrap();
+
+    // the second element is cut off after its first byte
+    let ops = vec![
+        PartialOp::Unlimited,
+        PartialOp::Limited(1),
+        PartialOp::Err(io::ErrorKind::Other),
+    ];
+    let spill = FlakySpill(PartialWrite::new(Cursor::new(Vec::new()), ops));
+    let mut buffer = Vec::new();
+    {
+        let mut stream = StreamSerializer::new_with_write(&mut buffer);
+        let mut seq = stream.serialize_seq_streaming::<Point, _>(spill).unwrap();
+        seq.push(&points[0]).unwrap();
+        assert!(seq.push(&Point { x: 3, y: -3 }).is_err());
+        seq.push(&points[1]).unwrap();
+        assert_eq!(seq.len(), 2);
+        seq.finish().unwrap();
+    }
+    assert_eq!(buffer, expected);
+}
+
 #[test]
 fn vec_of_bool_to_empty_array() {
     let mut buffer = Vec::new();
@@ -454,6 +640,62 @@ fn point_struct_skip_x() {
     );
 }
 
+#[test]
+fn point_struct_preflight() {
+    let mut buffer = Vec::new();
+    {
+        let mut stream = StreamSerializer::new_with_write(&mut buffer);
+        stream.preflight::<Point>().unwrap();
+    }
+    assert_eq!(
+        buffer,
+        include_bytes!("reference/output/point_struct.gob")[..32].as_ref()
+    );
+}
+
+#[test]
+fn point_struct_preflight_then_value() {
+    let mut buffer = Vec::new();
+    {
+        let mut stream = StreamSerializer::new_with_write(&mut buffer);
+        stream.preflight::<Point>().unwrap();
+        stream.serialize(&Point { x: 22, y: 33 }).unwrap();
+    }
+    assert_eq!(
+        buffer,
+        include_bytes!("reference/output/point_struct.gob").as_ref()
+    );
+}
+
+#[test]
+fn point_struct_twice_into_writer() {
+    let mut stream = StreamSerializer::new(Vec::new());
+    stream.serialize(&Point { x: 22, y: 33 }).unwrap();
+    stream.serialize(&Point { x: 0, y: 42 }).unwrap();
+    let buffer = stream.into_writer();
+
+    let mut expected = include_bytes!("reference/output/point_struct.gob").to_vec();
+    expected.extend_from_slice(&include_bytes!("reference/output/point_struct_skip_x.gob")[32..]);
+    assert_eq!(buffer, expected);
+}
+
+#[test]
+fn point_struct_serializer_for() {
+    let mut stream = StreamSerializer::new(Vec::new());
+    Point { x: 22, y: 33 }
+        .serialize(stream.serializer_for::<Point>().unwrap())
+        .unwrap();
+    stream.reset();
+    Point { x: 0, y: 42 }
+        .serialize(stream.serializer_for::<Point>().unwrap())
+        .unwrap();
+    let buffer = stream.into_writer();
+
+    let mut expected = include_bytes!("reference/output/point_struct.gob").to_vec();
+    expected.extend_from_slice(&include_bytes!("reference/output/point_struct_skip_x.gob")[32..]);
+    assert_eq!(buffer, expected);
+}
+
 #[derive(Serialize, SchemaSerialize)]
 struct BoolStruct {
     #[serde(rename = "V")]
@@ -568,3 +810,576 @@ fn option_some_to_non_empty_values() {
         include_bytes!("reference/output/non_empty_values.gob").as_ref()
     );
 }
+
+#[test]
+fn point_struct_frames() {
+    let mut encoder = Encoder::new();
+    let first = encoder.encode(&Point { x: 22, y: 33 }).unwrap();
+    let second = encoder.encode(&Point { x: 0, y: 42 }).unwrap();
+
+    assert_eq!(
+        &first[..],
+        &include_bytes!("reference/output/point_struct.gob")[..]
+    );
+    assert_eq!(
+        &second[..],
+        &include_bytes!("reference/output/point_struct_skip_x.gob")[32..]
+    );
+}
+
+#[test]
+fn emit_zero_fields() {
+    let mut encoder = Encoder::new();
+    encoder.set_field_options(FieldOptions::new().emit_zero_fields(true));
+    let frame = encoder.encode(&Point { x: 0, y: 42 }).unwrap();
+    assert_eq!(&frame[32..], &[7, 0xff, 0x82, 1, 0, 1, 84, 0]);
+}
+
+#[test]
+fn none_fields_as_zero_values() {
+    #[derive(Serialize, SchemaSerialize)]
+    struct Shape {
+        origin: Option<Point>,
+        path: Option<Vec<Point>>,
+        tags: Option<BTreeMap<String, u64>>,
+    }
+
+    let encode = |shape: &Shape| {
+        let mut encoder = Encoder::new();
+        encoder.set_field_options(FieldOptions::new().emit_zero_fields(true));
+        encoder.encode(shape).unwrap()
+    };
+    let none = Shape {
+        origin: None,
+        path: None,
+        tags: None,
+    };
+    let zero = Shape {
+        origin: None,
+        path: Some(Vec::new()),
+        tags: Some(BTreeMap::new()),
+    };
+    assert_eq!(encode(&none), encode(&zero));
+}
+
+#[test]
+fn builder() {
+    let builder = gob::ser::Builder::new().emit_zero_fields(true);
+    let mut stream = builder.writer(Vec::new());
+    stream.serialize(&Point { x: 0, y: 42 }).unwrap();
+    let written = stream.into_writer();
+    assert_eq!(&written[32..], &[7, 0xff, 0x82, 1, 0, 1, 84, 0]);
+
+    let mut stream = builder.bytes();
+    stream.serialize(&Point { x: 0, y: 42 }).unwrap();
+    assert_eq!(&stream.get_ref().get_ref()[..], &written[..]);
+}
+
+#[test]
+fn builder_mode() {
+    let nan = f64::from_bits(0xfff8_0000_0000_1234);
+    let builder = gob::ser::Builder::new().mode(gob::Mode::Strict);
+    let mut stream = builder.writer(Vec::new());
+    stream.serialize(&nan).unwrap();
+    stream.serialize(&-0.0f64).unwrap();
+    let mut canonical = StreamSerializer::new(Vec::new()).canonical();
+    canonical.serialize(&nan).unwrap();
+    canonical.serialize(&-0.0f64).unwrap();
+    assert_eq!(stream.into_writer(), canonical.into_writer());
+
+    // writing like Go does again, payload and sign kept
+    let mut stream = builder.mode(gob::Mode::Lenient).writer(Vec::new());
+    stream.serialize(&nan).unwrap();
+    assert_eq!(
+        stream.into_writer(),
+        &[11, 8, 0, 248, 52, 18, 0, 0, 0, 0, 248, 255]
+    );
+}
+
+#[derive(Serialize, SchemaSerialize)]
+struct Reading {
+    #[serde(rename = "Value")]
+    value: f64,
+    #[serde(rename = "Count")]
+    count: u64,
+}
+
+fn omit_nan(field: &Field) -> Option<bool> {
+    match field.as_f64() {
+        Some(v) if v.is_nan() => Some(true),
+        _ if field.name() == "Count" => Some(false),
+        _ => None,
+    }
+}
+
+#[test]
+fn omit_fields_with_hook() {
+    let mut encoder = Encoder::new();
+    encoder.set_field_options(FieldOptions::new().omit_with(omit_nan));
+    let frame = encoder
+        .encode(&Reading {
+            value: ::std::f64::NAN,
+            count: 0,
+        })
+        .unwrap();
+    // only the zero count is sent
+    assert_eq!(&frame[frame.len() - 5..], &[0xff, 0x82, 2, 0, 0]);
+}
+
+#[test]
+fn canonical_maps() {
+    let encode = |map: &HashMap<String, f64>| {
+        let mut stream = StreamSerializer::new_with_write(Vec::new()).canonical();
+        stream.serialize(map).unwrap();
+        stream.into_writer()
+    };
+
+    let mut first = HashMap::new();
+    let mut second = HashMap::new();
+    for i in 0..64 {
+        first.insert(format!("key{}", i), i as f64);
+        second.insert(format!("key{}", 63 - i), (63 - i) as f64);
+    }
+    first.insert("zero".to_owned(), -0.0);
+    second.insert("zero".to_owned(), 0.0);
+
+    let buffer = encode(&first);
+    assert_eq!(buffer, encode(&second));
+    let decoded = HashMap::<String, f64>::deserialize(gob::Deserializer::from_slice(&buffer));
+    assert_eq!(decoded.unwrap(), second);
+}
+
+#[test]
+fn sorted_maps() {
+    use gob::Value;
+
+    let encode = |map: &HashMap<i64, String>, order: MapOrder| {
+        let mut stream = StreamSerializer::new_with_write(Vec::new());
+        stream.set_map_order(order);
+        stream.serialize(map).unwrap();
+        stream.into_writer()
+    };
+
+    let mut map = HashMap::new();
+    let mut sorted = BTreeMap::new();
+    for i in -32..32 {
+        map.insert(i, format!("value{}", i));
+        sorted.insert(i, format!("value{}", i));
+    }
+
+    let mut expected = StreamSerializer::new_with_write(Vec::new());
+    expected.serialize(&sorted).unwrap();
+    assert_eq!(encode(&map, MapOrder::Key), expected.into_writer());
+
+    fn descending(a: &MapKey, b: &MapKey) -> Ordering {
+        b.as_i64().cmp(&a.as_i64())
+    }
+    let buffer = encode(&map, MapOrder::Custom(descending));
+    let decoded = Value::deserialize(gob::Deserializer::from_slice(&buffer)).unwrap();
+    let expected = (-32..32)
+        .rev()
+        .map(|i| (Value::Int(i), Value::String(format!("value{}", i))))
+        .collect();
+    assert_eq!(decoded, Value::Map(expected));
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, SchemaSerialize)]
+struct Checksum {
+    #[serde(rename = "Sum")]
+    sum: [u8; 4],
+    #[serde(rename = "Size")]
+    size: u64,
+}
+
+#[test]
+fn fixed_size_arrays() {
+    let value = Checksum {
+        sum: [0xde, 0xad, 0, 0xef],
+        size: 7,
+    };
+    let mut stream = StreamSerializer::new_with_write(Vec::new());
+    stream.serialize(&value).unwrap();
+    let buffer = stream.into_writer();
+    let decoded = Checksum::deserialize(gob::Deserializer::from_slice(&buffer)).unwrap();
+    assert_eq!(decoded, value);
+
+    // the length is part of the type
+    let mut stream = StreamSerializer::new_with_write(Vec::new());
+    let type_id = stream.preflight::<[u8; 4]>().unwrap();
+    assert!(stream
+        .serialize_with_type_id(type_id, &vec![1u8, 2, 3])
+        .is_err());
+}
+
+#[test]
+fn chars_as_runes() {
+    let mut stream = StreamSerializer::new_with_write(Vec::new());
+    stream.serialize(&'é').unwrap();
+    let buffer = stream.into_writer();
+    assert_eq!(buffer, &[5, 4, 0, 0xfe, 0x01, 0xd2]);
+    let decoded = char::deserialize(gob::Deserializer::from_slice(&buffer)).unwrap();
+    assert_eq!(decoded, 'é');
+
+    // surrogates and negative ints are not chars
+    for &rune in &[0xd800i64, -1, 0x110000] {
+        let mut stream = StreamSerializer::new_with_write(Vec::new());
+        stream.serialize(&rune).unwrap();
+        let buffer = stream.into_writer();
+        assert!(char::deserialize(gob::Deserializer::from_slice(&buffer)).is_err());
+    }
+}
+
+#[test]
+fn rune_slices() {
+    let runes: Vec<char> = "héllo".chars().collect();
+    let mut stream = StreamSerializer::new_with_write(Vec::new());
+    stream.serialize(&runes).unwrap();
+    let buffer = stream.into_writer();
+
+    let decoded = Vec::<char>::deserialize(gob::Deserializer::from_slice(&buffer)).unwrap();
+    assert_eq!(decoded, runes);
+    let decoded = String::deserialize(gob::Deserializer::from_slice(&buffer)).unwrap();
+    assert_eq!(decoded, "héllo");
+}
+
+#[test]
+fn type_defs_exported_out_of_band() {
+    use gob::de::TypeDefs;
+    use gob::StreamDeserializer;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, SchemaSerialize)]
+    struct Reading {
+        sensor: String,
+        value: f64,
+    }
+
+    let mut stream = StreamSerializer::new_with_write(Vec::new());
+    let mut blob = stream.export_type_defs::<Reading>().unwrap();
+    blob.extend(stream.export_type_defs::<Vec<Reading>>().unwrap());
+    assert!(stream.export_type_defs::<Reading>().unwrap().is_empty());
+
+    let reading = Reading {
+        sensor: "t1".to_owned(),
+        value: 21.5,
+    };
+    stream.serialize(&reading).unwrap();
+    stream.serialize(&vec![reading]).unwrap();
+    let payload = stream.into_writer();
+
+    // the payload alone lacks the definitions
+    let mut defs = TypeDefs::new();
+    assert!(
+        Reading::deserialize(gob::Deserializer::from_slice_with_type_defs(
+            &payload, &mut defs
+        ))
+        .is_err()
+    );
+
+    let defs = TypeDefs::from_bytes(&blob).unwrap();
+    assert!(defs.type_id("Reading").is_some());
+    let mut de = StreamDeserializer::with_type_defs(Cursor::new(payload), defs);
+    let decoded = de.deserialize::<Reading>().unwrap().unwrap();
+    assert_eq!(decoded.sensor, "t1");
+    let decoded = de.deserialize::<Vec<Reading>>().unwrap().unwrap();
+    assert_eq!(decoded.len(), 1);
+    assert!(de.deserialize::<Reading>().unwrap().is_none());
+
+    // values are not definitions
+    assert!(TypeDefs::from_bytes(de.into_inner().get_ref()).is_err());
+}
+
+#[test]
+fn pinned_type_ids() {
+    use gob::StreamDeserializer;
+    use serde_schema::SchemaSerialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, SchemaSerialize)]
+    struct Point {
+        x: i64,
+        y: i64,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, SchemaSerialize)]
+    struct Line {
+        from: Point,
+        to: Point,
+    }
+
+    let line = Line {
+        from: Point { x: 1, y: 2 },
+        to: Point { x: 3, y: 4 },
+    };
+
+    let mut stream = StreamSerializer::new_with_write(Vec::new());
+    let point_id = stream.schema_mut().assign_id("Point", 200).unwrap();
+    let line_id = stream.schema_mut().assign_id("Line", 100).unwrap();
+    stream.serialize(&Point { x: 5, y: 6 }).unwrap();
+    stream.serialize(&line).unwrap();
+    stream.serialize(&vec![1u8, 2]).unwrap();
+    let buffer = stream.into_writer();
+
+    let mut de = StreamDeserializer::new(Cursor::new(buffer));
+    assert_eq!(de.peek_type().unwrap().unwrap().id(), point_id);
+    de.deserialize::<Point>().unwrap().unwrap();
+    assert_eq!(de.peek_type().unwrap().unwrap().id(), line_id);
+    assert_eq!(de.deserialize::<Line>().unwrap(), Some(line));
+    // types not pinned take the ids left free
+    assert_eq!(de.type_defs().type_id("Point"), Some(point_id));
+    assert_eq!(de.deserialize::<Vec<u8>>().unwrap(), Some(vec![1, 2]));
+
+    let mut schema = gob::ser::Schema::new();
+    assert!(schema.assign_id("Point", 64).is_err());
+    schema.assign_id("Point", 70).unwrap();
+    assert!(schema.assign_id("Line", 70).is_err());
+    Point::schema_register(&mut schema).unwrap();
+    assert!(schema.assign_id("Point", 71).is_err());
+    assert!(schema.assign_id("Point", 70).is_ok());
+
+    // a pinned id taken before the pin is refused
+    let mut schema = gob::ser::Schema::new();
+    Point::schema_register(&mut schema).unwrap();
+    assert!(schema.assign_id("Line", 65).is_err());
+}
+
+#[test]
+fn embedded_structs() {
+    use gob::de::DecodeOptions;
+    use gob::StreamDeserializer;
+
+    // type Base struct { ID int; Name string }
+    // type Derived struct { Base; Extra int }
+    #[derive(Debug, PartialEq, Serialize, Deserialize, SchemaSerialize)]
+    #[allow(non_snake_case)]
+    struct Derived {
+        #[serde(default)]
+        ID: i64,
+        Name: String,
+        Extra: i64,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[allow(non_snake_case)]
+    struct Base {
+        #[serde(default)]
+        ID: i64,
+        Name: String,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[allow(non_snake_case)]
+    struct Nested {
+        Base: Base,
+        Extra: i64,
+    }
+
+    let values = [
+        Derived {
+            ID: 7,
+            Name: "seven".into(),
+            Extra: 1,
+        },
+        Derived {
+            ID: 0,
+            Name: "zero".into(),
+            Extra: 2,
+        },
+    ];
+
+    let mut stream = StreamSerializer::new_with_write(Vec::new());
+    stream
+        .schema_mut()
+        .embed("Derived", "Base", &["ID", "Name"])
+        .unwrap();
+    for value in &values {
+        stream.serialize(value).unwrap();
+    }
+    assert!(stream.schema_mut().embed("Derived", "Other", &[]).is_err());
+    let buffer = stream.into_writer();
+
+    let mut de = StreamDeserializer::new(Cursor::new(&buffer));
+    let nested = de.deserialize::<Nested>().unwrap().unwrap();
+    assert_eq!(
+        (nested.Base.ID, nested.Base.Name, nested.Extra),
+        (7, "seven".into(), 1)
+    );
+    let nested = de.deserialize::<Nested>().unwrap().unwrap();
+    assert_eq!(
+        (nested.Base.ID, nested.Base.Name, nested.Extra),
+        (0, "zero".into(), 2)
+    );
+
+    let mut de = StreamDeserializer::new(Cursor::new(&buffer));
+    de.set_options(DecodeOptions::new().flatten_embedded(true));
+    for value in &values {
+        assert_eq!(de.deserialize::<Derived>().unwrap().as_ref(), Some(value));
+    }
+
+    let mut stream = StreamSerializer::new_with_write(Vec::new());
+    stream
+        .schema_mut()
+        .embed("Derived", "Base", &["ID", "Extra"])
+        .unwrap();
+    assert!(stream.serialize(&values[0]).is_err());
+}
+
+#[test]
+fn unknown_fields_round_trip() {
+    use gob::{StreamDeserializer, Value};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, SchemaSerialize)]
+    #[allow(non_snake_case)]
+    struct Inner {
+        X: i64,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, SchemaSerialize)]
+    #[allow(non_snake_case)]
+    struct Full {
+        Name: String,
+        Age: u64,
+        Inner: Inner,
+        Tags: Vec<String>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    #[allow(non_snake_case)]
+    struct Partial {
+        Name: String,
+        #[serde(flatten)]
+        extra: HashMap<String, Value>,
+    }
+
+    let full = Full {
+        Name: "before".into(),
+        Age: 42,
+        Inner: Inner { X: -3 },
+        Tags: vec!["a".into(), "b".into()],
+    };
+    let mut stream = StreamSerializer::new_with_write(Vec::new());
+    stream.serialize(&full).unwrap();
+    let buffer = stream.into_writer();
+
+    let mut de = StreamDeserializer::new(Cursor::new(&buffer));
+    let mut partial = de.deserialize::<Partial>().unwrap().unwrap();
+    assert_eq!(partial.extra.len(), 3);
+    assert_eq!(partial.extra["Age"], Value::Uint(42));
+    partial.Name = "after".into();
+
+    let mut stream = StreamSerializer::new_with_write(Vec::new());
+    let type_id = stream.preflight::<Full>().unwrap();
+    stream.serialize_with_type_id(type_id, &partial).unwrap();
+    partial.extra.insert("Missing".into(), Value::Int(1));
+    assert!(stream.serialize_with_type_id(type_id, &partial).is_err());
+    let buffer = stream.into_writer();
+
+    let mut de = StreamDeserializer::new(Cursor::new(&buffer));
+    let decoded = de.deserialize::<Full>().unwrap().unwrap();
+    assert_eq!(
+        decoded,
+        Full {
+            Name: "after".into(),
+            ..full
+        }
+    );
+}
+
+#[test]
+fn caller_provided_buffers() {
+    #[derive(Serialize, SchemaSerialize)]
+    struct Point {
+        x: i64,
+        y: i64,
+    }
+
+    let point = Point { x: 22, y: 33 };
+    let mut stream = StreamSerializer::new_with_write(Vec::new());
+    stream.serialize(&point).unwrap();
+    let expected = stream.into_writer();
+
+    let mut buf = vec![0xff];
+    {
+        let mut stream = StreamSerializer::new_with_vec(&mut buf);
+        stream.serialize(&point).unwrap();
+        assert_eq!(stream.get_ref().written(), expected.len());
+    }
+    assert_eq!(buf[0], 0xff);
+    assert_eq!(&buf[1..], &expected[..]);
+
+    let mut slice = [0u8; 128];
+    {
+        let mut stream = StreamSerializer::new_with_slice(&mut slice);
+        stream.serialize(&point).unwrap();
+        assert_eq!(stream.get_ref().filled(), &expected[..]);
+    }
+
+    let mut slice = vec![0u8; expected.len() - 1];
+    let mut stream = StreamSerializer::new_with_slice(&mut slice);
+    assert!(stream.serialize(&point).is_err());
+    // the type definition fit, the value didn't
+    let written = stream.get_ref().written();
+    assert!(written < expected.len());
+    // nor does anything after it, as the definition is taken to be sent
+    assert!(stream.serialize(&true).is_err());
+    assert_eq!(stream.get_ref().written(), written);
+}
+
+#[test]
+fn serialized_size() {
+    use gob::ser::{serialized_size, Schema};
+
+    #[derive(Serialize, SchemaSerialize)]
+    struct Point {
+        x: i64,
+        y: i64,
+    }
+
+    let point = Point { x: 22, y: 33 };
+    let schema = Schema::new();
+    let with_types = serialized_size(&point, &schema).unwrap();
+
+    let mut stream = StreamSerializer::new_with_write(Vec::new());
+    assert_eq!(stream.serialized_size(&point).unwrap(), with_types);
+    stream.serialize(&point).unwrap();
+    // the definition of `Point` has been sent by now
+    let without_types = stream.serialized_size(&point).unwrap();
+    assert!(without_types < with_types);
+    stream.serialize(&point).unwrap();
+    assert_eq!(
+        stream.into_writer().len() as u64,
+        with_types + without_types
+    );
+}
+
+#[test]
+fn more_fields_than_the_schema() {
+    #[derive(Serialize, SchemaSerialize)]
+    #[allow(dead_code)]
+    struct Point {
+        x: i64,
+        y: i64,
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename = "Point")]
+    struct Point3 {
+        x: i64,
+        y: i64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        z: Option<i64>,
+    }
+
+    let mut stream = StreamSerializer::new_with_write(Vec::new());
+    let type_id = stream.preflight::<Point>().unwrap();
+    let point = Point3 {
+        x: 1,
+        y: 2,
+        z: None,
+    };
+    assert!(stream.serialize_with_type_id(type_id, &point).is_err());
+    let point = Point3 {
+        x: 1,
+        y: 2,
+        z: Some(3),
+    };
+    assert!(stream.serialize_with_type_id(type_id, &point).is_err());
+}
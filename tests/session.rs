@@ -0,0 +1,90 @@
+extern crate gob;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+
+use gob::{Session, StreamDeserializer, Value};
+
+#[test]
+fn random_access() {
+    let buffer = include_bytes!("reference/output/non_empty_values.gob");
+    let session = Session::capture(buffer.as_ref()).unwrap();
+
+    assert_eq!(session.len(), 7);
+    assert_eq!(session.sections(), 8);
+    assert_eq!(session.deserialize::<String>(4).unwrap(), "foo");
+    assert_eq!(session.deserialize::<u64>(1).unwrap(), 42);
+    assert_eq!(session.deserialize::<Vec<bool>>(6).unwrap(), &[true, false]);
+    assert_eq!(session.deserialize::<String>(4).unwrap(), "foo");
+    assert!(session.deserialize::<bool>(0).unwrap());
+    assert!(session.deserialize::<bool>(7).is_err());
+
+    assert_eq!(session.as_bytes(), buffer.as_ref());
+    let mut out = Vec::new();
+    session.write_to(&mut out).unwrap();
+    assert_eq!(out, buffer.as_ref());
+}
+
+#[test]
+fn single_value_streams() {
+    let buffer = include_bytes!("reference/output/slice_of_bool_non_empty_twice.gob");
+    let session = Session::capture(buffer.as_ref()).unwrap();
+
+    assert_eq!(session.len(), 2);
+    assert_eq!(session.value_type(0), session.value_type(1));
+    assert_eq!(session.type_name(session.value_type(1).unwrap()), Some(""));
+    assert_eq!(session.raw_value(1), Some(&buffer[20..]));
+    assert_eq!(session.raw_value(2), None);
+
+    let mut out = Vec::new();
+    session.write_value_to(1, &mut out).unwrap();
+    assert_eq!(&out[..], [&buffer[..13], &buffer[20..]].concat().as_slice());
+
+    let mut stream = StreamDeserializer::new(out.as_slice());
+    assert_eq!(
+        stream.deserialize::<Value>().unwrap(),
+        Some(Value::Seq(vec![Value::Bool(false), Value::Bool(true)]))
+    );
+    assert!(stream.deserialize::<Value>().unwrap().is_none());
+}
+
+#[test]
+fn truncated() {
+    let buffer = include_bytes!("reference/output/point_struct.gob");
+    assert!(Session::capture(&buffer[..buffer.len() - 1]).is_err());
+}
+
+#[test]
+fn from_stream_deserializer() {
+    let buffer = include_bytes!("reference/output/slice_of_bool_non_empty_twice.gob");
+    let mut stream = StreamDeserializer::new(buffer.as_ref());
+    assert_eq!(
+        stream.deserialize::<Vec<bool>>().unwrap(),
+        Some(vec![true, false])
+    );
+
+    let session = stream.into_session().unwrap();
+    assert_eq!(session.len(), 1);
+    assert_eq!(session.as_bytes(), &buffer[20..]);
+    assert_eq!(session.deserialize::<Vec<bool>>(0).unwrap(), &[false, true]);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn par_iter() {
+    use rayon::iter::ParallelIterator;
+
+    let mut buffer = include_bytes!("reference/output/slice_of_bool_non_empty_twice.gob").to_vec();
+    let value = buffer[20..].to_vec();
+    for _ in 0..1000 {
+        buffer.extend_from_slice(&value);
+    }
+
+    let session = Session::capture(buffer.as_slice()).unwrap();
+    let values = session
+        .par_iter::<Vec<bool>>()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(values.len(), 1002);
+    assert_eq!(values[0], &[true, false]);
+    assert!(values[1..].iter().all(|value| value == &[false, true]));
+}
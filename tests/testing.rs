@@ -0,0 +1,137 @@
+#![cfg(feature = "testing")]
+
+extern crate gob;
+#[macro_use]
+extern crate proptest;
+extern crate rand;
+extern crate serde;
+
+use gob::debug::validate;
+use gob::ser::TypeId;
+use gob::testing::{arbitrary_sample, arbitrary_value, check_sample, round_trip, samples};
+use gob::{Deserializer, StreamDeserializer, Value};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::Deserialize;
+
+fn check_arbitrary_values(preamble: &[u8], names: &[&str]) {
+    let mut stream = StreamDeserializer::new(preamble);
+    assert!(stream.deserialize::<Value>().unwrap().is_none());
+    let mut defs = stream.type_defs();
+
+    let mut type_ids = vec![TypeId::BOOL, TypeId::STRING, TypeId::COMPLEX];
+    type_ids.extend(names.iter().map(|name| defs.type_id(name).unwrap()));
+
+    let mut rng = StdRng::seed_from_u64(1234);
+    for type_id in type_ids {
+        for _ in 0..100 {
+            let value = arbitrary_value(&defs, type_id, &mut rng).unwrap();
+
+            let report = validate([preamble, &value].concat().as_slice()).unwrap();
+            assert!(report.is_valid(), "{:?}", report.error());
+            assert_eq!(report.values(), 1);
+
+            let deserializer = Deserializer::from_slice_with_type_defs(&value, &mut defs);
+            Value::deserialize(deserializer).unwrap();
+        }
+    }
+}
+
+#[test]
+fn enum_with_struct_variants() {
+    let buffer = include_bytes!("reference/output/enum_with_struct_variants.gob");
+    check_arbitrary_values(&buffer[..0x7f], &["Enum", "V1", "V2", "V3"]);
+}
+
+#[test]
+fn composite_fields() {
+    let buffer = include_bytes!("reference/output/ignored_fields.gob");
+    check_arbitrary_values(&buffer[..0xed], &["Everything", "Inner"]);
+}
+
+#[test]
+fn unknown_type() {
+    let defs = gob::de::TypeDefs::new();
+    let mut rng = StdRng::seed_from_u64(1234);
+    assert!(defs.type_id("Everything").is_none());
+    assert!(arbitrary_value(&defs, TypeId::INT, &mut rng).is_ok());
+}
+
+#[test]
+fn arbitrary_types() {
+    let mut rng = StdRng::seed_from_u64(1234);
+    for _ in 0..100 {
+        let sample = arbitrary_sample(&mut rng, 5).unwrap();
+        let report = validate(&sample.stream()[..]).unwrap();
+        assert!(report.is_valid(), "{:?}", report.error());
+        assert_eq!(report.values(), 5);
+        assert_eq!(check_sample(&sample).unwrap().len(), 5);
+    }
+}
+
+proptest! {
+    #[test]
+    fn samples_decode(sample in samples(4)) {
+        prop_assert_eq!(check_sample(&sample).unwrap().len(), sample.values().len());
+    }
+
+    #[test]
+    fn maps_round_trip(value: std::collections::BTreeMap<String, Vec<i64>>) {
+        prop_assert_eq!(round_trip(&value).unwrap(), value);
+    }
+}
+
+#[test]
+fn record_and_replay() {
+    use gob::error::ErrorKind;
+    use gob::testing::{Recorder, Replay};
+    use std::io::{self, Read};
+
+    // delivers the stream in small pieces, failing once on the way
+    struct Connection<'a> {
+        input: &'a [u8],
+        reads: usize,
+    }
+
+    impl<'a> Read for Connection<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.reads += 1;
+            if self.reads == 3 {
+                return Err(io::Error::new(io::ErrorKind::Interrupted, "signal"));
+            }
+            self.input.read(&mut buf[..buf.len().min(5)])
+        }
+    }
+
+    let buffer = include_bytes!("reference/output/enum_with_struct_variants.gob");
+    let connection = Connection {
+        input: buffer,
+        reads: 0,
+    };
+    fn decode_all<R: Read>(stream: &mut StreamDeserializer<R>) -> Vec<Value> {
+        let mut values = Vec::new();
+        loop {
+            match stream.deserialize::<Value>() {
+                Ok(Some(value)) => values.push(value),
+                Ok(None) => return values,
+                Err(err) => assert_eq!(err.kind(), ErrorKind::Io(io::ErrorKind::Interrupted)),
+            }
+        }
+    }
+
+    let mut stream = StreamDeserializer::new(Recorder::new(connection, Vec::new()).unwrap());
+    let live = decode_all(&mut stream);
+    assert!(!live.is_empty());
+    let (_, fixture) = stream.into_inner().into_parts();
+
+    let replay = Replay::new(&fixture).unwrap();
+    assert_eq!(replay.stream(), &buffer[..]);
+    assert_eq!(decode_all(&mut StreamDeserializer::new(replay)), live);
+
+    let mut replay = Replay::new(&fixture).unwrap();
+    let mut chunk = [0; 16];
+    assert_eq!(replay.read(&mut chunk).unwrap(), 5);
+    assert_eq!(replay.read(&mut chunk).unwrap(), 5);
+    let err = replay.read(&mut chunk).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::Interrupted);
+}
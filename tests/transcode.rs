@@ -0,0 +1,68 @@
+extern crate gob;
+extern crate serde_json;
+
+use std::io::Cursor;
+
+use gob::transcode::transcode;
+use gob::{Deserializer, StreamDeserializer};
+
+fn to_json(deserializer: Deserializer) -> String {
+    let mut buffer = Vec::new();
+    {
+        let mut serializer = serde_json::Serializer::new(&mut buffer);
+        transcode(deserializer, &mut serializer).unwrap();
+    }
+    String::from_utf8(buffer).unwrap()
+}
+
+#[test]
+fn point_struct() {
+    let deserializer =
+        Deserializer::from_slice(include_bytes!("reference/output/point_struct.gob"));
+    assert_eq!(to_json(deserializer), r#"{"X":22,"Y":33}"#);
+}
+
+#[test]
+fn map_non_empty() {
+    let deserializer =
+        Deserializer::from_slice(include_bytes!("reference/output/map_non_empty.gob"));
+    assert_eq!(to_json(deserializer), r#"{"bar":false,"foo":true}"#);
+}
+
+#[test]
+fn non_empty_values() {
+    let buffer = include_bytes!("reference/output/non_empty_values.gob");
+    let mut stream = StreamDeserializer::new(Cursor::new(buffer.as_ref()));
+
+    let mut values = Vec::new();
+    while let Some(deserializer) = stream.deserializer().unwrap() {
+        values.push(to_json(deserializer));
+    }
+
+    assert_eq!(
+        values,
+        &[
+            "true",
+            "42",
+            "42",
+            "42.0",
+            r#""foo""#,
+            "[1,2]",
+            "[true,false]"
+        ]
+    );
+}
+
+#[test]
+fn interface_value() {
+    let buffer = include_bytes!("reference/output/interface_value.gob");
+    let mut stream = StreamDeserializer::new(Cursor::new(buffer.as_ref()));
+
+    let point = stream.deserializer().unwrap().unwrap();
+    assert_eq!(to_json(point), r#"{"X":1,"Y":2}"#);
+
+    let wrapper = stream.deserializer().unwrap().unwrap();
+    assert_eq!(to_json(wrapper), r#"{"Value":{"X":22,"Y":33}}"#);
+
+    assert!(stream.deserializer().unwrap().is_none());
+}
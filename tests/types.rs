@@ -0,0 +1,47 @@
+extern crate gob;
+
+use std::io::Cursor;
+
+use gob::types::{CommonType, StructType, TypeId, WireType};
+use gob::{StreamDeserializer, Value};
+
+#[test]
+fn inspect_definitions() {
+    let buffer = include_bytes!("reference/output/point_struct.gob");
+    let mut stream = StreamDeserializer::new(Cursor::new(&buffer[..]));
+    stream.deserialize::<Value>().unwrap().unwrap();
+    let defs = stream.type_defs();
+
+    let id = defs.type_id("Point").unwrap();
+    assert_eq!(id.get(), 65);
+    assert!(!id.is_builtin());
+    let point = match defs.lookup(id) {
+        Some(WireType::Struct(point)) => point,
+        other => panic!("expected a struct, got {:?}", other),
+    };
+    assert_eq!(point.common().name(), "Point");
+    let fields = point
+        .fields()
+        .iter()
+        .map(|field| (field.name(), field.id()))
+        .collect::<Vec<_>>();
+    assert_eq!(fields, &[("X", TypeId::INT), ("Y", TypeId::INT)]);
+
+    // the same definition, built by hand
+    let built = StructType::new(CommonType::new("Point", id))
+        .field("X", TypeId::INT)
+        .field("Y", TypeId::INT);
+    assert_eq!(point, &built);
+
+    assert_eq!(defs.iter().count(), 1);
+    assert!(defs.lookup(TypeId::INT).is_none());
+}
+
+#[test]
+fn type_ids() {
+    assert_eq!(TypeId::new(0), None);
+    assert_eq!(TypeId::new(-65), None);
+    assert_eq!(TypeId::new(6), Some(TypeId::STRING));
+    assert!(TypeId::STRING.is_builtin());
+    assert!(!TypeId::new(65).unwrap().is_builtin());
+}
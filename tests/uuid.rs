@@ -0,0 +1,88 @@
+#![cfg(feature = "uuid")]
+
+extern crate gob;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_schema;
+#[macro_use]
+extern crate serde_schema_derive;
+extern crate uuid;
+
+use gob::compat::uuid::{UuidBytes, UuidString};
+use gob::compat::Gob;
+use gob::{Deserializer, StreamSerializer, Value};
+use serde::Deserialize;
+use serde_schema::SchemaSerialize;
+use uuid::Uuid;
+
+const ID: Uuid = Uuid::from_bytes([
+    0x67, 0xe5, 0x50, 0x44, 0x10, 0xb1, 0x42, 0x6f, 0x92, 0x47, 0xbb, 0x68, 0x0e, 0x5f, 0xe0, 0xc8,
+]);
+
+fn encode<T: SchemaSerialize>(value: &T) -> Vec<u8> {
+    let mut stream = StreamSerializer::new(Vec::new());
+    stream.serialize(value).unwrap();
+    stream.into_writer()
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, SchemaSerialize)]
+struct Record {
+    #[serde(rename = "Array")]
+    array: UuidBytes,
+    #[serde(rename = "Text")]
+    text: UuidString,
+    #[serde(rename = "Google")]
+    google: Gob<Uuid>,
+}
+
+#[test]
+fn uuid_round_trip() {
+    let record = Record {
+        array: UuidBytes(ID),
+        text: UuidString(ID),
+        google: Gob(ID),
+    };
+    let buffer = encode(&record);
+    assert_eq!(
+        Record::deserialize(Deserializer::from_slice(&buffer)).unwrap(),
+        record
+    );
+}
+
+#[test]
+fn uuid_representations() {
+    let value = Value::deserialize(Deserializer::from_slice(&encode(&UuidString(ID)))).unwrap();
+    assert_eq!(
+        value,
+        Value::String("67e55044-10b1-426f-9247-bb680e5fe0c8".into())
+    );
+
+    let value = Value::deserialize(Deserializer::from_slice(&encode(&UuidBytes(ID)))).unwrap();
+    let bytes = ID.as_bytes().iter().map(|&b| Value::Uint(b as u64));
+    assert_eq!(value, Value::Seq(bytes.collect()));
+
+    // `github.com/google/uuid.UUID` is a `BinaryMarshaler` of 16 bytes
+    let buffer = encode(&Gob(ID));
+    assert_eq!(&buffer[1..5], &[0xff, 0x81, 6, 1]);
+    let value = Value::deserialize(Deserializer::from_slice(&buffer)).unwrap();
+    assert_eq!(value, Value::Bytes(ID.as_bytes().to_vec()));
+}
+
+#[test]
+fn uuid_from_any_representation() {
+    let buffers = [
+        encode(&UuidBytes(ID)),
+        encode(&UuidString(ID)),
+        encode(&Gob(ID)),
+    ];
+    for buffer in &buffers {
+        let decoded = UuidString::deserialize(Deserializer::from_slice(buffer)).unwrap();
+        assert_eq!(decoded, UuidString(ID));
+        let decoded = UuidBytes::deserialize(Deserializer::from_slice(buffer)).unwrap();
+        assert_eq!(decoded, UuidBytes(ID));
+    }
+
+    let buffer = encode(&"not a uuid");
+    assert!(UuidString::deserialize(Deserializer::from_slice(&buffer)).is_err());
+}
@@ -0,0 +1,170 @@
+extern crate gob;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+
+use std::io::Cursor;
+
+use gob::debug::validate;
+use gob::value::{synthetic_type_name, PrintOptions};
+use gob::{Deserializer, StreamDeserializer, StreamSerializer, Value};
+use serde::Deserialize;
+
+fn point(x: i64, y: i64) -> Value {
+    Value::Map(vec![
+        (Value::String("X".to_owned()), Value::Int(x)),
+        (Value::String("Y".to_owned()), Value::Int(y)),
+    ])
+}
+
+#[test]
+fn point_struct() {
+    let deserializer =
+        Deserializer::from_slice(include_bytes!("reference/output/point_struct.gob"));
+    let value = Value::deserialize(deserializer).unwrap();
+    assert_eq!(value, point(22, 33));
+    assert_eq!(value.to_string(), "{\n  X: 22\n  Y: 33\n}");
+}
+
+#[test]
+fn non_empty_values() {
+    let buffer = include_bytes!("reference/output/non_empty_values.gob");
+    let mut stream = StreamDeserializer::new(Cursor::new(buffer.as_ref()));
+
+    let mut values = Vec::new();
+    while let Some(value) = stream.deserialize::<Value>().unwrap() {
+        values.push(value);
+    }
+
+    assert_eq!(
+        values,
+        &[
+            Value::Bool(true),
+            Value::Uint(42),
+            Value::Int(42),
+            Value::Float(42.0),
+            Value::String("foo".to_owned()),
+            Value::Bytes(vec![1, 2]),
+            Value::Seq(vec![Value::Bool(true), Value::Bool(false)]),
+        ]
+    );
+}
+
+#[test]
+fn interface_value() {
+    let buffer = include_bytes!("reference/output/interface_value.gob");
+    let mut stream = StreamDeserializer::new(Cursor::new(buffer.as_ref()));
+
+    let _ = stream.deserialize::<Value>().unwrap().unwrap();
+    let wrapper = stream.deserialize::<Value>().unwrap().unwrap();
+    assert_eq!(
+        wrapper,
+        Value::Map(vec![(Value::String("Value".to_owned()), point(22, 33))])
+    );
+    assert_eq!(
+        wrapper.render(&PrintOptions::new().max_depth(1)),
+        "{\n  Value: {... 2 items}\n}"
+    );
+}
+
+#[test]
+fn render_truncated() {
+    let value = Value::Seq(vec![
+        Value::String("a long string".to_owned()),
+        Value::Bytes(vec![0xde, 0xad, 0xbe, 0xef]),
+        Value::Int(-255),
+        Value::Nil,
+    ]);
+
+    let options = PrintOptions::new()
+        .max_items(3)
+        .max_string_len(2)
+        .hex_ints(true)
+        .hex_bytes(true);
+    assert_eq!(
+        value.render(&options),
+        "[\n  \"a \"... (11 more bytes)\n  0xdead... (2 more bytes)\n  -0xff\n  ... 1 more\n]"
+    );
+
+    assert_eq!(
+        Value::Bytes(b"a\n\xff".to_vec()).render(&PrintOptions::new()),
+        "b\"a\\n\\xff\""
+    );
+}
+
+fn dyn_point(x: i64, y: Value) -> Value {
+    Value::Struct(vec![("X".to_owned(), Value::Int(x)), ("Y".to_owned(), y)])
+}
+
+#[test]
+fn synthetic_names() {
+    let name = synthetic_type_name(&dyn_point(22, Value::Int(33))).unwrap();
+    assert_eq!(name, "Tf83b837a783ac770");
+    assert_eq!(
+        synthetic_type_name(&dyn_point(0, Value::Int(42))).unwrap(),
+        name
+    );
+    assert_ne!(
+        synthetic_type_name(&dyn_point(22, Value::Uint(33))).unwrap(),
+        name
+    );
+    assert_ne!(
+        synthetic_type_name(&dyn_point(22, Value::Nil)).unwrap(),
+        name
+    );
+    assert!(synthetic_type_name(&Value::Int(1)).is_err());
+}
+
+#[test]
+fn serialize_value_struct() {
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Point {
+        #[serde(rename = "X")]
+        x: i64,
+        #[serde(rename = "Y")]
+        y: Vec<String>,
+    }
+
+    let value = |x: i64, y: &str| dyn_point(x, Value::Seq(vec![Value::String(y.to_owned())]));
+
+    let mut stream = StreamSerializer::new(Vec::new());
+    stream.serialize_value(&value(22, "a")).unwrap();
+    stream.serialize_value(&value(7, "b")).unwrap();
+    let buffer = stream.into_writer();
+
+    let report = validate(buffer.as_slice()).unwrap();
+    assert!(report.is_valid());
+    assert_eq!(report.definitions(), 2);
+    assert_eq!(report.values(), 2);
+    assert_eq!(
+        report.types()[1].1,
+        synthetic_type_name(&value(22, "a")).unwrap()
+    );
+
+    let mut stream = StreamDeserializer::new(Cursor::new(buffer));
+    assert_eq!(
+        stream.deserialize::<Point>().unwrap().unwrap(),
+        Point {
+            x: 22,
+            y: vec!["a".to_owned()]
+        }
+    );
+    assert_eq!(
+        stream.deserialize::<Point>().unwrap().unwrap(),
+        Point {
+            x: 7,
+            y: vec!["b".to_owned()]
+        }
+    );
+}
+
+#[test]
+fn serialize_value_uninferrable() {
+    let mut stream = StreamSerializer::new(Vec::new());
+    assert!(stream.serialize_value(&Value::Nil).is_err());
+    assert!(stream.serialize_value(&Value::Seq(vec![])).is_err());
+    assert!(stream
+        .serialize_value(&Value::Seq(vec![Value::Int(1), Value::Uint(2)]))
+        .is_err());
+    assert!(stream.into_writer().is_empty());
+}
@@ -0,0 +1,26 @@
+#![cfg(feature = "wasm")]
+
+extern crate gob;
+
+use gob::wasm::{decode_to_json, encode_from_json};
+
+#[test]
+fn json_round_trip() {
+    let schema = r#"{"struct": {"name": "Point", "fields": [
+        {"name": "X", "type": "int"},
+        {"name": "Y", "type": "int"}
+    ]}}"#;
+    let bytes = encode_from_json(schema, r#"{"X": 1, "Y": 2} {"X": -3, "Y": 4}"#).unwrap();
+    assert_eq!(
+        decode_to_json(&bytes).unwrap(),
+        r#"[{"X":1,"Y":2},{"X":-3,"Y":4}]"#
+    );
+    assert_eq!(decode_to_json(&[]).unwrap(), "[]");
+}
+
+#[test]
+fn errors() {
+    assert!(encode_from_json(r#""nope""#, "1").is_err());
+    assert!(encode_from_json(r#""int""#, r#""one""#).is_err());
+    assert!(decode_to_json(&[3, 2, 0]).is_err());
+}
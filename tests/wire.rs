@@ -0,0 +1,65 @@
+extern crate gob;
+extern crate serde;
+
+use serde::{ser, Deserialize};
+
+use gob::ser::TypeId;
+use gob::wire::SectionWriter;
+use gob::{Deserializer, StreamDeserializer, StreamSerializer};
+
+#[test]
+fn value_section() {
+    let mut writer = SectionWriter::new(Vec::new());
+    writer
+        .write_value(TypeId::UINT, |buf| {
+            buf.extend_from_slice(&[0x00, 0x2a]);
+            Ok(())
+        })
+        .unwrap();
+    let bytes = writer.into_inner();
+    assert_eq!(bytes, &[0x03, 0x06, 0x00, 0x2a]);
+
+    let value = u64::deserialize(Deserializer::from_slice(&bytes)).unwrap();
+    assert_eq!(value, 42);
+}
+
+#[test]
+fn failed_payload_writes_nothing() {
+    let mut writer = SectionWriter::new(Vec::new());
+    let result = writer.write_value(TypeId::STRING, |buf| {
+        buf.push(0x00);
+        Err(ser::Error::custom("no payload"))
+    });
+    assert!(result.is_err());
+    assert!(writer.get_ref().is_empty());
+}
+
+#[test]
+fn reframe_stream() {
+    let mut stream = StreamSerializer::new_with_bytes();
+    stream.serialize(&vec![1u64, 2, 3]).unwrap();
+    let original = stream.into_inner().into_inner();
+
+    // one type definition for []uint under id 65, followed by the value
+    let (def, value) = original.split_at(original[0] as usize + 1);
+    assert_eq!(&def[1..3], &[0xff, 0x81]);
+    assert_eq!(&value[1..3], &[0xff, 0x82]);
+
+    let type_id = gob::Session::capture(original.as_ref())
+        .unwrap()
+        .value_type(0)
+        .unwrap();
+    let mut writer = SectionWriter::new(Vec::new());
+    writer
+        .write_type_definition(type_id, |buf| {
+            buf.extend_from_slice(&def[3..]);
+            Ok(())
+        })
+        .unwrap();
+    writer.write_raw(65, &value[3..]).unwrap();
+    let reframed = writer.into_inner();
+    assert_eq!(reframed, original.as_ref());
+
+    let mut de = StreamDeserializer::new(reframed.as_slice());
+    assert_eq!(de.deserialize::<Vec<u64>>().unwrap().unwrap(), &[1, 2, 3]);
+}
@@ -0,0 +1,136 @@
+extern crate gob;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_schema;
+#[macro_use]
+extern crate serde_schema_derive;
+
+use std::fs;
+
+use gob::reader::{Event, EventReader};
+use gob::writer::EventWriter;
+use gob::{StreamDeserializer, StreamSerializer};
+
+fn events(buffer: &[u8]) -> Vec<String> {
+    let mut reader = EventReader::new(buffer);
+    let mut events = Vec::new();
+    while let Some(event) = reader.next_event().unwrap() {
+        events.push(format!("{:?}", event));
+    }
+    events
+}
+
+#[test]
+fn rewrites_go_output() {
+    for entry in fs::read_dir("tests/reference/output").unwrap() {
+        let path = entry.unwrap().path();
+        let buffer = fs::read(&path).unwrap();
+
+        let mut reader = EventReader::new(&buffer[..]);
+        let mut writer = EventWriter::new(Vec::new());
+        while let Some(event) = reader.next_event().unwrap() {
+            writer.write_event(event).unwrap();
+        }
+        let rewritten = writer.into_inner();
+        assert_eq!(events(&rewritten), events(&buffer), "{}", path.display());
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, SchemaSerialize)]
+#[serde(default)]
+struct Record {
+    #[serde(rename = "Name")]
+    name: String,
+    #[serde(rename = "Tags")]
+    tags: Vec<String>,
+    #[serde(rename = "Size")]
+    size: u64,
+}
+
+#[test]
+fn transforms_values() {
+    let mut buffer = Vec::new();
+    {
+        let mut stream = StreamSerializer::new_with_write(&mut buffer);
+        for name in &["a", "secret", "b"] {
+            stream
+                .serialize(&Record {
+                    name: name.to_string(),
+                    tags: vec!["x".to_owned(), "secret".to_owned()],
+                    size: 7,
+                })
+                .unwrap();
+        }
+    }
+
+    // redacts strings and drops a field
+    let mut reader = EventReader::new(&buffer[..]);
+    let mut writer = EventWriter::new(Vec::new());
+    let mut skip = false;
+    while let Some(event) = reader.next_event().unwrap() {
+        match event {
+            Event::Field("Size") => skip = true,
+            Event::String(b"secret") => writer.write_event(Event::String(b"***")).unwrap(),
+            _ if skip => skip = false,
+            event => writer.write_event(event).unwrap(),
+        }
+    }
+    let rewritten = writer.into_inner();
+
+    let mut stream = StreamDeserializer::new(&rewritten[..]);
+    for name in &["a", "***", "b"] {
+        assert_eq!(
+            stream.deserialize::<Record>().unwrap(),
+            Some(Record {
+                name: name.to_string(),
+                tags: vec!["x".to_owned(), "***".to_owned()],
+                size: 0,
+            })
+        );
+    }
+    assert_eq!(stream.deserialize::<Record>().unwrap(), None);
+}
+
+#[test]
+fn rejects_misplaced_events() {
+    let mut buffer = Vec::new();
+    StreamSerializer::new_with_write(&mut buffer)
+        .serialize(&Record {
+            name: "a".to_owned(),
+            tags: vec![],
+            size: 1,
+        })
+        .unwrap();
+    let mut reader = EventReader::new(&buffer[..]);
+    let mut writer = EventWriter::new(Vec::new());
+    let mut record = None;
+    while let Some(event) = reader.next_event().unwrap() {
+        if let Event::Value(type_id) = event {
+            record = Some(type_id);
+        }
+        writer.write_event(event).unwrap();
+    }
+    let record = record.unwrap();
+
+    writer.write_event(Event::Value(record)).unwrap();
+    assert!(writer.write_event(Event::Value(record)).is_err());
+    assert!(writer.write_event(Event::Int(1)).is_err());
+    writer.write_event(Event::StartStruct(record)).unwrap();
+    writer.write_event(Event::Field("Size")).unwrap();
+    // fields out of order
+    assert!(writer.write_event(Event::Field("Name")).is_err());
+    // the wrong type of value
+    assert!(writer.write_event(Event::String(b"1")).is_err());
+    writer.write_event(Event::Uint(2)).unwrap();
+    assert!(writer.write_event(Event::Field("Size")).is_err());
+    assert!(writer.in_value());
+    writer.write_event(Event::End).unwrap();
+    assert!(!writer.in_value());
+    assert!(writer.write_event(Event::End).is_err());
+
+    let output = writer.into_inner();
+    let mut stream = StreamDeserializer::new(&output[..]);
+    assert_eq!(stream.deserialize::<Record>().unwrap().unwrap().size, 1);
+    assert_eq!(stream.deserialize::<Record>().unwrap().unwrap().size, 2);
+}